@@ -0,0 +1 @@
+ALTER TABLE user_settings DROP COLUMN bridge_read_receipts_off;
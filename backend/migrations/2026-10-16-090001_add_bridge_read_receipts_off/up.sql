@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN bridge_read_receipts_off BOOLEAN;
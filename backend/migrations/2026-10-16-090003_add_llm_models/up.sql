@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN llm_models TEXT;
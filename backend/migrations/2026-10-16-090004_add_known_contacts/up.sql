@@ -0,0 +1,9 @@
+CREATE TABLE known_contacts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    service VARCHAR(50) NOT NULL,
+    room_id VARCHAR(255) NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE (user_id, service, room_id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
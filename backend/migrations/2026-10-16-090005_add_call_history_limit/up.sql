@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN call_history_limit INTEGER;
@@ -0,0 +1,9 @@
+CREATE TABLE notified_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    item_key VARCHAR(255) NOT NULL,
+    content_hash VARCHAR(64) NOT NULL,
+    notified_at INTEGER NOT NULL,
+    UNIQUE (user_id, item_key),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
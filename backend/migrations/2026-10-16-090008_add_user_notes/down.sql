@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_user_notes_user_created;
+DROP TABLE user_notes;
@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN call_failure_sms_fallback BOOLEAN;
@@ -0,0 +1,11 @@
+CREATE TABLE room_notification_prefs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    platform VARCHAR(32) NOT NULL,
+    room_id VARCHAR(255) NOT NULL,
+    mode VARCHAR(16) NOT NULL,
+    noti_type VARCHAR(16),
+    updated_at INTEGER NOT NULL,
+    UNIQUE (user_id, platform, room_id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE user_settings DROP COLUMN textbee_status_checked_at;
+ALTER TABLE user_settings DROP COLUMN textbee_device_status;
@@ -0,0 +1,2 @@
+ALTER TABLE user_settings ADD COLUMN textbee_device_status TEXT;
+ALTER TABLE user_settings ADD COLUMN textbee_status_checked_at INTEGER;
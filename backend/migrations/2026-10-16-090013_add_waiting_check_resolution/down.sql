@@ -0,0 +1,2 @@
+ALTER TABLE waiting_checks DROP COLUMN resolved_reason;
+ALTER TABLE waiting_checks DROP COLUMN resolved_at;
@@ -0,0 +1,2 @@
+ALTER TABLE waiting_checks ADD COLUMN resolved_at INTEGER;
+ALTER TABLE waiting_checks ADD COLUMN resolved_reason TEXT;
@@ -0,0 +1,12 @@
+CREATE TABLE failed_notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    notification_type VARCHAR(64) NOT NULL,
+    channel VARCHAR(16) NOT NULL,
+    encrypted_payload TEXT NOT NULL,
+    error TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    retried_at INTEGER,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX idx_failed_notifications_user_id ON failed_notifications(user_id);
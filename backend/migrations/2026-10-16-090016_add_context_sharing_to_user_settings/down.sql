@@ -0,0 +1,3 @@
+ALTER TABLE user_settings DROP COLUMN share_history;
+ALTER TABLE user_settings DROP COLUMN share_contacts;
+ALTER TABLE user_settings DROP COLUMN share_location;
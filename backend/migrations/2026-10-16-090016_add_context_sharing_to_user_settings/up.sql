@@ -0,0 +1,3 @@
+ALTER TABLE user_settings ADD COLUMN share_location BOOLEAN;
+ALTER TABLE user_settings ADD COLUMN share_contacts BOOLEAN;
+ALTER TABLE user_settings ADD COLUMN share_history BOOLEAN;
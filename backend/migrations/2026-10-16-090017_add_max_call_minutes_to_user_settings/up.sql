@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN max_call_minutes INTEGER;
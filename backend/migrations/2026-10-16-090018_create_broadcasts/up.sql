@@ -0,0 +1,19 @@
+CREATE TABLE broadcasts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel VARCHAR(16) NOT NULL,
+    subject TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE TABLE broadcast_recipients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    broadcast_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    error TEXT,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    updated_at INTEGER NOT NULL,
+    FOREIGN KEY (broadcast_id) REFERENCES broadcasts(id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX idx_broadcast_recipients_broadcast_id ON broadcast_recipients(broadcast_id);
@@ -0,0 +1,3 @@
+ALTER TABLE user_settings DROP COLUMN firecrawl_exclude_domains;
+ALTER TABLE user_settings DROP COLUMN firecrawl_include_domains;
+ALTER TABLE user_settings DROP COLUMN firecrawl_result_count;
@@ -0,0 +1,3 @@
+ALTER TABLE user_settings ADD COLUMN firecrawl_result_count INTEGER;
+ALTER TABLE user_settings ADD COLUMN firecrawl_include_domains TEXT;
+ALTER TABLE user_settings ADD COLUMN firecrawl_exclude_domains TEXT;
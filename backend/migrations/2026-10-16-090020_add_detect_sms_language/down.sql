@@ -0,0 +1 @@
+ALTER TABLE user_settings DROP COLUMN detect_sms_language;
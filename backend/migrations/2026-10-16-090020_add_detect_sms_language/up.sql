@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN detect_sms_language BOOLEAN;
@@ -0,0 +1,2 @@
+ALTER TABLE user_settings DROP COLUMN pause_allows_critical;
+ALTER TABLE user_settings DROP COLUMN paused_until;
@@ -0,0 +1,2 @@
+ALTER TABLE user_settings ADD COLUMN paused_until INTEGER;
+ALTER TABLE user_settings ADD COLUMN pause_allows_critical BOOLEAN;
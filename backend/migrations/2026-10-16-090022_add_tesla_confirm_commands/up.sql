@@ -0,0 +1 @@
+ALTER TABLE user_settings ADD COLUMN tesla_confirm_commands TEXT;
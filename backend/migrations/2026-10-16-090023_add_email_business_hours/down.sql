@@ -0,0 +1,2 @@
+ALTER TABLE user_settings DROP COLUMN email_business_hours_end;
+ALTER TABLE user_settings DROP COLUMN email_business_hours_start;
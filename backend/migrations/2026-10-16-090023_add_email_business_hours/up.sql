@@ -0,0 +1,2 @@
+ALTER TABLE user_settings ADD COLUMN email_business_hours_start INTEGER;
+ALTER TABLE user_settings ADD COLUMN email_business_hours_end INTEGER;
@@ -0,0 +1,2 @@
+ALTER TABLE user_settings DROP COLUMN digest_tone;
+ALTER TABLE user_settings DROP COLUMN digest_length;
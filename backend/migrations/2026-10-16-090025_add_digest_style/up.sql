@@ -0,0 +1,2 @@
+ALTER TABLE user_settings ADD COLUMN digest_length TEXT;
+ALTER TABLE user_settings ADD COLUMN digest_tone TEXT;
@@ -0,0 +1,14 @@
+CREATE TABLE scheduled_sends (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    kind VARCHAR(32) NOT NULL,
+    target TEXT NOT NULL,
+    encrypted_payload TEXT NOT NULL,
+    send_at INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'scheduled',
+    error TEXT,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX idx_scheduled_sends_status_send_at ON scheduled_sends(status, send_at);
+CREATE INDEX idx_scheduled_sends_user_id ON scheduled_sends(user_id);
@@ -0,0 +1 @@
+ALTER TABLE keywords ADD COLUMN action TEXT NOT NULL DEFAULT 'notify_sms';
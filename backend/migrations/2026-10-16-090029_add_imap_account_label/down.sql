@@ -0,0 +1 @@
+ALTER TABLE imap_connection DROP COLUMN label;
@@ -0,0 +1 @@
+ALTER TABLE imap_connection ADD COLUMN label TEXT;
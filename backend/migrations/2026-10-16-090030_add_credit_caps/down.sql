@@ -0,0 +1,3 @@
+DROP TABLE credit_spend;
+ALTER TABLE user_settings DROP COLUMN weekly_credit_cap;
+ALTER TABLE user_settings DROP COLUMN daily_credit_cap;
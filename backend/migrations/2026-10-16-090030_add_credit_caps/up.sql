@@ -0,0 +1,10 @@
+ALTER TABLE user_settings ADD COLUMN daily_credit_cap REAL;
+ALTER TABLE user_settings ADD COLUMN weekly_credit_cap REAL;
+CREATE TABLE credit_spend (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    amount REAL NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX idx_credit_spend_user_id_created_at ON credit_spend(user_id, created_at);
@@ -0,0 +1,2 @@
+-- Cleared policies can't be told apart from ones that were never set
+SELECT 1;
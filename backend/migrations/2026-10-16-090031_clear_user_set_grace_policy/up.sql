@@ -0,0 +1,2 @@
+-- Users could pick grace for themselves before it became admin only
+UPDATE user_settings SET insufficient_credits_policy = NULL WHERE insufficient_credits_policy = 'grace';
@@ -0,0 +1,3 @@
+UPDATE processed_emails
+SET email_uid = SUBSTR(email_uid, INSTR(email_uid, ':') + 1)
+WHERE email_uid LIKE '%:%';
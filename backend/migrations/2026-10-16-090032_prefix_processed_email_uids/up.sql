@@ -0,0 +1,11 @@
+-- Emails of the primary account were stored as a plain UID, every account is prefixed now
+UPDATE processed_emails
+SET email_uid = (
+    SELECT MIN(imap_connection.id) FROM imap_connection
+    WHERE imap_connection.user_id = processed_emails.user_id AND imap_connection.status = 'active'
+) || ':' || email_uid
+WHERE email_uid NOT LIKE '%:%'
+  AND EXISTS (
+    SELECT 1 FROM imap_connection
+    WHERE imap_connection.user_id = processed_emails.user_id AND imap_connection.status = 'active'
+  );
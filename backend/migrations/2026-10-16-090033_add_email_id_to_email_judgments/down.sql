@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_email_judgments_user_email_id;
+ALTER TABLE email_judgments DROP COLUMN email_id;
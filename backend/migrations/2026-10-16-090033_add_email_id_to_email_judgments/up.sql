@@ -0,0 +1,3 @@
+-- Judgments were matched to emails by timestamp, which merges emails sent in the same second
+ALTER TABLE email_judgments ADD COLUMN email_id TEXT;
+CREATE INDEX idx_email_judgments_user_email_id ON email_judgments(user_id, email_id);
@@ -13,12 +13,18 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use crate::handlers::imap_handlers::{fetch_emails_imap, fetch_single_email_imap};
+use crate::utils::payload_limits::{
+    check_lengths, Validate, ValidatedJson, MAX_ADDRESS_CHARS, MAX_CONTENT_CHARS, MAX_EMAIL_BODY_CHARS,
+    MAX_MESSAGE_CHARS, MAX_SHORT_TEXT_CHARS, MAX_SUBJECT_CHARS,
+};
 
 
 #[derive(Debug, Deserialize)]
 pub struct LocationCallPayload {
     location: String,
-    units: String,
+    /// Falls back to the user's units setting when the agent leaves it out
+    #[serde(default)]
+    units: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -92,9 +98,11 @@ pub async fn validate_elevenlabs_secret(
                 Ok(value) => {
                     if value == secret_key {
                         tracing::debug!("✅ Secret validation successful");
+                        crate::utils::webhook_status::record_validated("elevenlabs_tools");
                         Ok(next.run(request).await)
                     } else {
                         tracing::error!("❌ Invalid secret provided");
+                        crate::utils::webhook_status::record_rejected("elevenlabs_tools");
                         Err(StatusCode::UNAUTHORIZED)
                     }
                 },
@@ -125,6 +133,67 @@ pub fn get_offset_with_jiff(timezone_str: &str) -> Result<(i32, i32), jiff::Erro
     Ok((hours, minutes))
 }
 
+pub const DEFAULT_CALL_HISTORY_LIMIT: i64 = 1;
+pub const MAX_CALL_HISTORY_LIMIT: i64 = 10;
+/// Past conversations longer than this get summarized before going into the call context
+const MAX_CALL_HISTORY_CHARS: usize = 4000;
+
+/// Number of past user turns to give the voice agent, clamped to 0..=MAX_CALL_HISTORY_LIMIT
+pub fn call_history_limit(setting: Option<i32>) -> i64 {
+    setting
+        .map(|limit| (limit as i64).clamp(0, MAX_CALL_HISTORY_LIMIT))
+        .unwrap_or(DEFAULT_CALL_HISTORY_LIMIT)
+}
+
+/// Removes the dynamic variables the user has turned off sharing for, everything is shared by default
+pub fn withhold_context_variables(
+    settings: &crate::models::user_models::UserSettings,
+    dynamic_variables: &mut HashMap<String, serde_json::Value>,
+) {
+    let mut withheld = Vec::new();
+    if !settings.share_location.unwrap_or(true) {
+        withheld.extend(["location", "nearby_places"]);
+    }
+    if !settings.share_contacts.unwrap_or(true) {
+        withheld.push("recent_contacts");
+    }
+    if !settings.share_history.unwrap_or(true) {
+        withheld.push("recent_conversation");
+    }
+    for variable in withheld {
+        dynamic_variables.remove(variable);
+    }
+}
+
+/// The user's recent conversation for the voice agent, as many user turns as they allow, newest first
+fn call_history(
+    state: &AppState,
+    user_id: i32,
+    user_settings: &crate::models::user_models::UserSettings,
+) -> Vec<crate::models::user_models::MessageHistory> {
+    let history_limit = call_history_limit(user_settings.call_history_limit);
+    if history_limit == 0 || !user_settings.share_history.unwrap_or(true) {
+        return Vec::new();
+    }
+    match state.user_repository.get_conversation_history(user_id, history_limit, /*include_tools=*/false) {
+        Ok(h) => h,
+        Err(e) => {
+            tracing::error!("Failed to fetch history: {:?}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Keeps the newest end of the transcript when it can't be summarized
+fn truncate_history(history: &str, max_chars: usize) -> String {
+    let total = history.chars().count();
+    if total <= max_chars {
+        return history.to_string();
+    }
+    let tail: String = history.chars().skip(total - max_chars).collect();
+    format!("...{}", tail)
+}
+
 pub async fn fetch_assistant(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<AssistantPayload>,
@@ -133,16 +202,14 @@ pub async fn fetch_assistant(
     let call_sid = payload.call_sid;
     let caller_number = payload.caller_id;
     println!("caller_number: {}", caller_number);
-    let us_voice_id = std::env::var("US_VOICE_ID").expect("US_VOICE_ID not set");
-    let fi_voice_id = std::env::var("FI_VOICE_ID").expect("FI_VOICE_ID not set");
-    let de_voice_id = std::env::var("DE_VOICE_ID").expect("DE_VOICE_ID not set");
+    let default_language = crate::utils::voice_languages::voice_language(crate::utils::voice_languages::DEFAULT_LANGUAGE);
     let mut dynamic_variables = HashMap::new();
     let mut conversation_config_override = ConversationConfig {
         agent: AgentConfig {
-            first_message: "Hello {{name}}!".to_string(),
+            first_message: default_language.greeting.clone(),
         },
         tts: VoiceId {
-            voice_id: us_voice_id.clone(),
+            voice_id: default_language.voice_id.clone(),
         },
     };
     match state.user_core.find_by_phone_number(&caller_number) {
@@ -175,26 +242,30 @@ pub async fn fetch_assistant(
                     ));
                 }
             };
+            let (greeting, voice_id) = crate::utils::elevenlabs_prompts::call_opening(&user_settings.agent_language, false);
+            conversation_config_override.agent.first_message = greeting;
+            conversation_config_override.tts.voice_id = voice_id;
             // If user is not verified, verify them
             if !user.verified {
                 if let Err(e) = state.user_core.verify_user(user.id) {
                     tracing::error!("Error verifying user: {}", e);
                     // Continue even if verification fails
                 } else {
-                    if user_settings.agent_language == "fi" {
-                        conversation_config_override.agent.first_message = "Tervetuloa! Numerosi on nyt vahvistettu. Miten voin auttaa?".to_string();
-                        conversation_config_override.tts.voice_id = fi_voice_id.clone();
-                    } else if user_settings.agent_language == "de" {
-                        conversation_config_override.agent.first_message = "Willkommen! Ihre Nummer ist jetzt verifiziert. Wie kann ich Ihnen helfen?".to_string();
-                        conversation_config_override.tts.voice_id = de_voice_id.clone();
-                    } else {
-                        conversation_config_override.agent.first_message = "Welcome! Your number is now verified. Anyways, how can I help?".to_string();
-                        conversation_config_override.tts.voice_id = us_voice_id.clone();
-                    }
+                    let (verified_message, voice_id) = crate::utils::elevenlabs_prompts::call_opening(&user_settings.agent_language, true);
+                    conversation_config_override.agent.first_message = verified_message;
+                    conversation_config_override.tts.voice_id = voice_id;
                 }
             } else if let Err(_) = crate::utils::usage::check_user_credits(&state, &user, "voice", None).await {
                 // Send insufficient credits message
-                let error_message = "Insufficient credits to make a voice call".to_string();
+                let cap_reached = crate::utils::usage::spending_cap_reached(&state, user.id);
+                let error_message = match (cap_reached, crate::utils::usage::credit_policy(&state, user.id)) {
+                    (Some((period, cap)), _) => crate::utils::usage::cap_reached_message(period, cap),
+                    (None, crate::utils::usage::CreditPolicy::Block) => "Insufficient credits to make a voice call".to_string(),
+                    (None, _) => format!(
+                        "Insufficient credits to make a voice call. Top up here: {}/billing",
+                        std::env::var("FRONTEND_URL").unwrap_or_else(|_| "https://lightfriend.ai".to_string())
+                    ),
+                };
                 if let Err(e) = crate::api::twilio_utils::send_conversation_message(
                     &state,
                     &error_message,
@@ -207,17 +278,10 @@ pub async fn fetch_assistant(
                     StatusCode::FORBIDDEN,
                     Json(json!({
                         "error": "Insufficient credits balance",
-                        "message": "Please add more credits to your account to continue on lightfriend website",
+                        "message": crate::utils::branding::render(crate::utils::branding::VOICE_OUT_OF_CREDITS),
                     }))
                 ));
             }
-            if user_settings.agent_language == "fi" {
-                conversation_config_override.agent.first_message = "Moi {{name}}!".to_string();
-                conversation_config_override.tts.voice_id = fi_voice_id.clone();
-            } else if user_settings.agent_language == "de" {
-                conversation_config_override.agent.first_message = "Hallo {{name}}!".to_string();
-                conversation_config_override.tts.voice_id = de_voice_id.clone();
-            }
             let nickname = match user.nickname {
                 Some(nickname) => nickname,
                 None => "".to_string()
@@ -257,38 +321,45 @@ pub async fn fetch_assistant(
             );
             dynamic_variables.insert("timezone".to_string(), json!(timezone_str));
             dynamic_variables.insert("timezone_offset_from_utc".to_string(), json!(offset));
-            let history_limit = 1;
-            let history: Vec<crate::models::user_models::MessageHistory> = match state.user_repository
-                .get_conversation_history(user.id, history_limit, /*include_tools=*/false)
-            {
-                Ok(h) => h,
-                Err(e) => {
-                    tracing::error!("Failed to fetch history: {:?}", e);
-                    Vec::new()
-                }
-            };
-            let history_string = history
+            let history = call_history(&state, user.id, &user_settings);
+            let mut history_string = history
                 .iter()
                 .rev() // oldest → newest
                 .map(|m| format!("{}: {}", m.role, m.encrypted_content))
                 .collect::<Vec<_>>()
                 .join("\n");
+            if history_string.len() > MAX_CALL_HISTORY_CHARS {
+                history_string = match crate::tool_call_utils::utils::summarize_conversation(&state, user.id, &history_string).await {
+                    Some(summary) => format!("Summary of earlier conversation: {}", summary),
+                    None => truncate_history(&history_string, MAX_CALL_HISTORY_CHARS),
+                };
+            }
             dynamic_variables.insert("recent_conversation".to_string(), json!(history_string));
+            let recent_notes = call_context_notes(&state, user.id, user_info.timezone.as_deref());
+            dynamic_variables.insert("recent_notes".to_string(), json!(recent_notes));
             //dynamic_variables.insert("conversation_history".to_string(), json!(history_string));
             let charge_back_threshold= std::env::var("CHARGE_BACK_THRESHOLD")
                 .expect("CHARGE_BACK_THRESHOLD not set")
                 .parse::<f32>()
                 .unwrap_or(2.00);
-            let voice_second_cost = std::env::var("VOICE_SECOND_COST")
-                .expect("VOICE_SECOND_COST not set")
-                .parse::<f32>()
-                .unwrap_or(0.0033);
-            let user_current_credits_to_threshold = user.credits - charge_back_threshold;
-            let seconds_to_threshold = (user_current_credits_to_threshold / voice_second_cost) as i32;
+            // The same per-second cost the call is charged at afterwards
+            let voice_second_cost = crate::utils::usage::credit_costs_for_user(&user, Some(&user_settings)).voice_second;
+            // Under the grace policy the call may run into the overdraft, which keeps it short
+            let (seconds_to_threshold, seconds_to_zero_credits) = crate::utils::usage::voice_seconds_budget(
+                user.credits + crate::utils::usage::allowed_overdraft(&state, user.id),
+                voice_second_cost,
+                charge_back_threshold,
+            );
             // following just so it doesn't go negative although i don't think it matters
-            let recharge_threshold_timestamp: i32 = (chrono::Utc::now().timestamp() as i32) + seconds_to_threshold;
-            let seconds_to_zero_credits= (user.credits / voice_second_cost) as i32;
-            let zero_credits_timestamp: i32 = (chrono::Utc::now().timestamp() as i32) + seconds_to_zero_credits as i32;
+            let recharge_threshold_timestamp: i32 = (chrono::Utc::now().timestamp() as i32).saturating_add(seconds_to_threshold);
+            let zero_credits_timestamp: i32 = (chrono::Utc::now().timestamp() as i32).saturating_add(seconds_to_zero_credits);
+            // The user's own hard cap, enforced by hanging up through Twilio
+            if let (Some(max_call_minutes), Some(cap_seconds)) = (
+                user_settings.max_call_minutes,
+                crate::utils::usage::call_cap_seconds(user_settings.max_call_minutes, seconds_to_zero_credits),
+            ) {
+                schedule_call_cap(&state, user.id, call_sid.clone(), cap_seconds, max_call_minutes);
+            }
             // log usage and start call
             if let Err(e) = state.user_repository.log_usage(
                 user.id,
@@ -306,7 +377,7 @@ pub async fn fetch_assistant(
                 // Continue execution even if logging fails
             }
             // Fetch recent contacts for all platforms and combine into a single string
-            let platforms = vec!["whatsapp", "telegram", "signal"];
+            let platforms = if user_settings.share_contacts.unwrap_or(true) { vec!["whatsapp", "telegram", "signal"] } else { Vec::new() };
             let mut all_contacts_str = String::new();
 
             for platform in platforms {
@@ -323,6 +394,7 @@ pub async fn fetch_assistant(
             }
 
             dynamic_variables.insert("recent_contacts".to_string(), json!(all_contacts_str));
+            withhold_context_variables(&user_settings, &mut dynamic_variables);
         },
         Ok(None) => {
             tracing::debug!("No user found for number: {}", caller_number);
@@ -338,6 +410,11 @@ pub async fn fetch_assistant(
         }
     }
     dynamic_variables.insert("now".to_string(), json!(format!("{}", chrono::Utc::now())));
+    crate::utils::elevenlabs_prompts::sanitize_dynamic_variables(&mut dynamic_variables);
+    conversation_config_override.agent.first_message = crate::utils::elevenlabs_prompts::checked_first_message(
+        &conversation_config_override.agent.first_message,
+        &dynamic_variables,
+    );
     let payload = ConversationInitiationClientData {
         r#type: "conversation_initiation_client_data".to_string(),
         conversation_config_override,
@@ -346,6 +423,168 @@ pub async fn fetch_assistant(
     Ok(Json(payload))
 }
 
+type ToolCallError = (StatusCode, Json<serde_json::Value>);
+
+// Field limits of the tool payloads, checked by `ValidatedJson` before the handler runs
+impl Validate for LocationCallPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[("location", &self.location, MAX_SHORT_TEXT_CHARS)])
+    }
+}
+
+impl Validate for MessageCallPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("message", &self.message, MAX_MESSAGE_CHARS),
+            ("email_id", self.email_id.as_deref().unwrap_or_default(), MAX_ADDRESS_CHARS),
+        ])
+    }
+}
+
+impl Validate for WaitingCheckPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("content", &self.content, MAX_CONTENT_CHARS),
+            ("service_type", &self.service_type, MAX_SHORT_TEXT_CHARS),
+        ])
+    }
+}
+
+impl Validate for NotePayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[("note", &self.note, MAX_CONTENT_CHARS)])
+    }
+}
+
+impl Validate for FireCrawlCallPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[("query", &self.query, MAX_SHORT_TEXT_CHARS)])
+    }
+}
+
+impl Validate for TaskCreatePayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("title", &self.title, MAX_SHORT_TEXT_CHARS),
+            ("description", self.description.as_deref().unwrap_or_default(), MAX_CONTENT_CHARS),
+        ])
+    }
+}
+
+impl Validate for EmailSearchPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[("search_term", &self.search_term, MAX_SHORT_TEXT_CHARS)])
+    }
+}
+
+impl Validate for ChatSearchPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[("search_term", &self.search_term, MAX_SHORT_TEXT_CHARS)])
+    }
+}
+
+impl Validate for ChatConfirmPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("chat_name", &self.chat_name, MAX_SHORT_TEXT_CHARS),
+            ("message", &self.message, MAX_MESSAGE_CHARS),
+            ("image_url", self.image_url.as_deref().unwrap_or_default(), MAX_ADDRESS_CHARS),
+        ])
+    }
+}
+
+impl Validate for CalendarEventConfirmPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("summary", &self.summary, MAX_SHORT_TEXT_CHARS),
+            ("description", self.description.as_deref().unwrap_or_default(), MAX_CONTENT_CHARS),
+        ])
+    }
+}
+
+impl Validate for SendEmailArgs {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("to", &self.to, MAX_ADDRESS_CHARS),
+            ("subject", &self.subject, MAX_SUBJECT_CHARS),
+            ("body", &self.body, MAX_EMAIL_BODY_CHARS),
+        ])
+    }
+}
+
+impl Validate for RespondToEmailArgs {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("email_id", &self.email_id, MAX_ADDRESS_CHARS),
+            ("response_text", &self.response_text, MAX_EMAIL_BODY_CHARS),
+        ])
+    }
+}
+
+impl Validate for ForwardEmailArgs {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("email_id", &self.email_id, MAX_ADDRESS_CHARS),
+            ("to", &self.to, MAX_ADDRESS_CHARS),
+        ])
+    }
+}
+
+impl Validate for DirectionsCallPayload {
+    fn validate(&self) -> Result<(), String> {
+        check_lengths(&[
+            ("start_address", &self.start_address, MAX_SHORT_TEXT_CHARS),
+            ("end_address", &self.end_address, MAX_SHORT_TEXT_CHARS),
+        ])
+    }
+}
+
+/// The `user_id` query parameter ElevenLabs passes to every tool route
+fn user_id_param(params: &HashMap<String, String>) -> Result<i32, ToolCallError> {
+    params
+        .get("user_id")
+        .and_then(|id| id.parse::<i32>().ok())
+        .ok_or_else(|| (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Invalid or missing user_id parameter"
+            }))
+        ))
+}
+
+/// The language the user talks to the agent in, English if their settings can't be read
+fn agent_language(state: &Arc<AppState>, user_id: i32) -> String {
+    state.user_core.get_user_settings(user_id)
+        .map(|settings| settings.agent_language)
+        .unwrap_or_else(|_| "en".to_string())
+}
+
+/// Looks up the user a tool call is for, mapping a missing user to 404 and DB errors to 500
+fn require_user(state: &Arc<AppState>, user_id: i32) -> Result<crate::models::user_models::User, ToolCallError> {
+    match state.user_core.find_by_id(user_id) {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => {
+            tracing::error!("User not found: {}", user_id);
+            Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({
+                    "error": "User not found"
+                }))
+            ))
+        }
+        Err(e) => {
+            error!("Error fetching user: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to fetch user",
+                    "details": e.to_string()
+                }))
+            ))
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct WaitingCheckPayload {
     pub content: String,
@@ -356,68 +595,217 @@ pub struct WaitingCheckPayload {
 pub async fn handle_create_waiting_check_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<WaitingCheckPayload>,
+    ValidatedJson(payload): ValidatedJson<WaitingCheckPayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     tracing::debug!("Received waiting check creation request");
 
-    let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
-        Some(id) => id,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
+    let user_id = user_id_param(&params)?;
+    require_user(&state, user_id)?;
+    let new_check = crate::models::user_models::NewWaitingCheck {
+        user_id: user_id,
+        content: payload.content,
+        service_type: payload.service_type,
+        noti_type: payload.noti_type,
+    };
+    match state.user_repository.create_waiting_check(&new_check) {
+        Ok(_) => {
+            tracing::debug!("Successfully created waiting check for user: {}", user_id);
+            Ok(Json(json!({
+                "response": "I'll keep an eye out for that and notify you when I find it.",
+                "status": "success",
+                "user_id": user_id,
+            })))
+        },
+        Err(e) => {
+            error!("Failed to create waiting check: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "Invalid or missing user_id parameter"
+                    "error": "Failed to create waiting check",
+                    "details": e.to_string()
                 }))
-            ));
+            ))
         }
-    };
-    // Verify user exists
-    match state.user_core.find_by_id(user_id) {
-        Ok(Some(_user)) => {
-            let new_check = crate::models::user_models::NewWaitingCheck {
-                user_id: user_id,
-                content: payload.content,
-                service_type: payload.service_type,
-                noti_type: payload.noti_type,
-            };
+    }
+}
 
-            match state.user_repository.create_waiting_check(&new_check) {
-                Ok(_) => {
-                    tracing::debug!("Successfully created waiting check for user: {}", 
-                        user_id);
-                    Ok(Json(json!({
-                        "response": "I'll keep an eye out for that and notify you when I find it.",
-                        "status": "success",
-                        "user_id": user_id,
-                    })))
-                },
-                Err(e) => {
-                    error!("Failed to create waiting check: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({
-                            "error": "Failed to create waiting check",
-                            "details": e.to_string()
-                        }))
-                    ))
-                }
-            }
-        },
-        Ok(None) => {
-            tracing::error!("User not found: {}", user_id);
+pub const MAX_NOTE_CHARS: usize = 500;
+pub const MAX_NOTES_PER_USER: i64 = 100;
+/// Notes handed to the agent at the start of a call
+const NOTES_IN_CALL_CONTEXT: i64 = 5;
+
+#[derive(Deserialize)]
+pub struct NotePayload {
+    pub note: String,
+}
+
+/// Formats notes as "[Mar 3 14:05] note" lines in the user's timezone, newest first
+fn format_notes(notes: &[crate::models::user_models::UserNote], timezone: Option<&str>) -> Vec<String> {
+    let tz: chrono_tz::Tz = timezone.and_then(|tz| tz.parse().ok()).unwrap_or(chrono_tz::UTC);
+    notes
+        .iter()
+        .map(|note| {
+            let when = chrono::DateTime::from_timestamp(note.created_at as i64, 0)
+                .map(|dt| dt.with_timezone(&tz).format("%b %-d %H:%M").to_string())
+                .unwrap_or_default();
+            format!("[{}] {}", when, note.encrypted_content)
+        })
+        .collect()
+}
+
+/// The newest notes as lines for the `recent_notes` dynamic variable of a call
+fn call_context_notes(state: &AppState, user_id: i32, timezone: Option<&str>) -> String {
+    match state.user_repository.get_recent_user_notes(user_id, NOTES_IN_CALL_CONTEXT) {
+        Ok(notes) => format_notes(&notes, timezone).join("\n"),
+        Err(e) => {
+            tracing::error!("Failed to fetch notes for call context: {}", e);
+            String::new()
+        }
+    }
+}
+
+pub async fn handle_save_note_tool_call(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    ValidatedJson(payload): ValidatedJson<NotePayload>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_param(&params)?;
+    require_user(&state, user_id)?;
+    let note = payload.note.trim();
+    if note.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Note is empty"
+            }))
+        ));
+    }
+    if note.chars().count() > MAX_NOTE_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("Note is too long, keep it under {} characters", MAX_NOTE_CHARS)
+            }))
+        ));
+    }
+    match state.user_repository.add_user_note(user_id, note, MAX_NOTES_PER_USER) {
+        Ok(_) => {
+            tracing::debug!("Saved note for user: {}", user_id);
+            Ok(Json(json!({
+                "response": "Got it, I saved that note.",
+                "status": "success",
+                "user_id": user_id,
+            })))
+        }
+        Err(e) => {
+            error!("Failed to save note: {}", e);
             Err((
-                StatusCode::NOT_FOUND,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "User not found"
+                    "error": "Failed to save note",
+                    "details": e.to_string()
                 }))
             ))
-        },
+        }
+    }
+}
+
+/// Hangs up the notification call the user got, e.g. when they call in to stop it
+pub async fn handle_cancel_call_tool_call(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_param(&params)?;
+    require_user(&state, user_id)?;
+    match cancel_notification_call(&state, user_id).await {
+        Ok(cancelled) => Ok(Json(json!({
+            "response": if cancelled { "The notification call was cancelled." } else { "There's no notification call in progress." },
+            "cancelled": cancelled,
+            "status": "success",
+            "user_id": user_id,
+        }))),
         Err(e) => {
-            error!("Error fetching user: {}", e);
+            error!("Failed to cancel notification call for user {}: {}", user_id, e);
+            Err((
+                StatusCode::BAD_GATEWAY,
+                Json(json!({
+                    "error": "Failed to cancel the call"
+                }))
+            ))
+        }
+    }
+}
+
+/// Reads out the balance and roughly how much calling and messaging it's still good for. Read-only.
+pub async fn handle_credits_tool_call(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_param(&params)?;
+    let user = require_user(&state, user_id)?;
+    let settings = state.user_core.get_user_settings(user_id).ok();
+    let costs = crate::utils::usage::credit_costs_for_user(&user, settings.as_ref());
+    let voice_second_cost = costs.voice_second;
+    let message_cost = if crate::utils::usage::messages_are_included(&user.phone_number) {
+        0.0
+    } else {
+        costs.message
+    };
+    let estimate = crate::utils::usage::balance_estimate(user.credits, user.credits_left, voice_second_cost, message_cost);
+    let messages_part = match estimate.messages {
+        Some(messages) => format!(" and about {} more messages", messages),
+        None => String::from(", and messages aren't charged on your number"),
+    };
+    let response = format!(
+        "You have {:.2} credits left{}. That's about {} minutes of calling{}.",
+        user.credits.max(0.0),
+        if user.credits_left > 0.0 { format!(" plus {} included messages this month", user.credits_left.floor() as i32) } else { String::new() },
+        estimate.call_minutes,
+        messages_part,
+    );
+    Ok(Json(json!({
+        "response": response,
+        "credits": user.credits,
+        "credits_left": user.credits_left,
+        "estimated_call_minutes": estimate.call_minutes,
+        "estimated_messages": estimate.messages,
+        "status": "success",
+        "user_id": user_id,
+    })))
+}
+
+pub async fn handle_recent_notes_tool_call(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_param(&params)?;
+    require_user(&state, user_id)?;
+    let limit = params.get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .unwrap_or(10)
+        .clamp(1, 50);
+    let timezone = state.user_core.get_user_info(user_id).ok().and_then(|info| info.timezone);
+    match state.user_repository.get_recent_user_notes(user_id, limit) {
+        Ok(notes) => {
+            let formatted = format_notes(&notes, timezone.as_deref());
+            let response = if formatted.is_empty() {
+                "You don't have any saved notes.".to_string()
+            } else {
+                format!("Your recent notes, newest first:\n{}", formatted.join("\n"))
+            };
+            Ok(Json(json!({
+                "response": response,
+                "notes": formatted,
+                "status": "success",
+                "user_id": user_id,
+            })))
+        }
+        Err(e) => {
+            error!("Failed to fetch notes: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "Failed to fetch user",
+                    "error": "Failed to fetch notes",
                     "details": e.to_string()
                 }))
             ))
@@ -437,47 +825,23 @@ pub async fn handle_update_monitoring_status_tool_call(
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     tracing::debug!("Received monitoring status update request");
 
-    // Verify user exists
-    match state.user_core.find_by_id(payload.user_id) {
-        Ok(Some(_user)) => {
-            match state.user_core.update_proactive_agent_on(payload.user_id, payload.enabled) {
-                Ok(_) => {
-                    tracing::debug!("Successfully updated monitoring status for user: {}",
-                        payload.user_id);
-                    let status = if payload.enabled { "on" } else { "off" };
-                    Ok(Json(json!({
-                        "response": format!("Monitoring turned {}.", status),
-                        "status": "success",
-                        "user_id": payload.user_id,
-                    })))
-                },
-                Err(e) => {
-                    error!("Failed to update monitoring status: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({
-                            "error": "Failed to update monitoring status",
-                            "details": e.to_string()
-                        }))
-                    ))
-                }
-            }
-        },
-        Ok(None) => {
-            tracing::error!("User not found: {}", payload.user_id);
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": "User not found"
-                }))
-            ))
+    require_user(&state, payload.user_id)?;
+    match state.user_core.update_proactive_agent_on(payload.user_id, payload.enabled) {
+        Ok(_) => {
+            tracing::debug!("Successfully updated monitoring status for user: {}", payload.user_id);
+            let status = if payload.enabled { "on" } else { "off" };
+            Ok(Json(json!({
+                "response": format!("Monitoring turned {}.", status),
+                "status": "success",
+                "user_id": payload.user_id,
+            })))
         },
         Err(e) => {
-            error!("Error fetching user: {}", e);
+            error!("Failed to update monitoring status: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": "Failed to fetch user",
+                    "error": "Failed to update monitoring status",
                     "details": e.to_string()
                 }))
             ))
@@ -485,94 +849,112 @@ pub async fn handle_update_monitoring_status_tool_call(
     }
 }
 
+/// How many emails the voice email tool fetches
+const EMAIL_FETCH_LIMIT: u32 = 10;
+/// How many of them are read out in detail unless the agent asks for more with `max_spoken`
+const DEFAULT_MAX_SPOKEN_EMAILS: usize = 3;
+
+/// What the agent says about the fetched emails: the first `max_spoken` in detail, the rest as a count.
+/// Returns the text and how many emails it describes.
+fn spoken_email_summary(emails: &[crate::handlers::imap_handlers::ImapEmailPreview], max_spoken: usize) -> (String, usize) {
+    // Format emails for voice response in a more natural way
+    let mut response_text = format!(
+        "I found {} recent emails in your inbox. ", 
+        emails.len()
+    );
+
+    // Group emails by read status
+    let unread_count = emails.iter().filter(|e| !e.is_read).count();
+    if unread_count > 0 {
+        response_text.push_str(&format!(
+            "{} of them {} unread. ",
+            unread_count,
+            if unread_count == 1 { "is" } else { "are" }
+        ));
+    }
+
+    // Add details for the first few emails in a conversational way, the rest are only counted
+    let spoken_count = emails.len().min(max_spoken);
+    let remaining = emails.len() - spoken_count;
+    for (i, email) in emails.iter().take(spoken_count).enumerate() {
+        let from = email.from.as_deref().unwrap_or("an unknown sender");
+        let subject = email.subject.as_deref().unwrap_or("no subject");
+        let date = email.date_formatted.as_deref().unwrap_or("recently");
+        
+        // Truncate body if too long and clean it up
+        let body = email.body.as_ref()
+            .map(|b| {
+                let cleaned = b.replace('\n', " ").replace('\r', " ");
+                let chars: Vec<char> = cleaned.chars().collect();
+                if chars.len() > 150 {
+                    let truncated: String = chars.into_iter().take(150).collect();
+                    format!("{}...", truncated)
+                } else {
+                    cleaned
+                }
+            })
+            .unwrap_or_else(|| "no content".to_string());
+        // Format each email in a more natural way
+        let email_intro = if i == 0 {
+            "The most recent email is"
+        } else if i == spoken_count - 1 && remaining == 0 {
+            "And finally"
+        } else {
+            "Next"
+        };
+
+        response_text.push_str(&format!(
+            "{} from {}, sent {}. The subject is '{}'. {}. ",
+            email_intro,
+            from,
+            date,
+            subject,
+            if email.is_read {
+                format!("Here's what it says: {}", body)
+            } else {
+                format!("This unread email says: {}", body)
+            }
+        ));
+    }
+    if remaining > 0 {
+        response_text.push_str(&format!(
+            "There {} {} more {}. Ask if you'd like to hear about {}.",
+            if remaining == 1 { "is" } else { "are" },
+            remaining,
+            if remaining == 1 { "email" } else { "emails" },
+            if remaining == 1 { "it" } else { "them" }
+        ));
+    }
+    (response_text, spoken_count)
+}
+
 pub async fn handle_email_fetch_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract and parse user_id from query params
-    let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
-        Some(id) => id,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Invalid or missing user_id parameter"
-                }))
-            ));
-        }
-    };
+    let user_id = user_id_param(&params)?;
     tracing::debug!("Received email fetch request for user: {}", user_id);
+    let max_spoken = params.get("max_spoken")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_SPOKEN_EMAILS)
+        .clamp(1, EMAIL_FETCH_LIMIT as usize);
     
-    match crate::handlers::imap_handlers::fetch_emails_imap(&state, user_id, true, Some(10), false, false).await {
-        Ok(emails) => {
-            if emails.is_empty() {
-                return Ok(Json(json!({
-                    "response": "I don't see any recent emails in your inbox.",
-                    "emails": [],
-                    "total_count": 0
-                })));
-            }
-
-            // Format emails for voice response in a more natural way
-            let mut response_text = format!(
-                "I found {} recent emails in your inbox. ", 
-                emails.len()
-            );
-
-            // Group emails by read status
-            let unread_count = emails.iter().filter(|e| !e.is_read).count();
-            if unread_count > 0 {
-                response_text.push_str(&format!(
-                    "{} of them {} unread. ",
-                    unread_count,
-                    if unread_count == 1 { "is" } else { "are" }
-                ));
-            }
-
-            // Add details for each email in a conversational way
-            for (i, email) in emails.iter().enumerate() {
-                let from = email.from.as_deref().unwrap_or("an unknown sender");
-                let subject = email.subject.as_deref().unwrap_or("no subject");
-                let date = email.date_formatted.as_deref().unwrap_or("recently");
-                
-                // Truncate body if too long and clean it up
-                let body = email.body.as_ref()
-                    .map(|b| {
-                        let cleaned = b.replace('\n', " ").replace('\r', " ");
-                        let chars: Vec<char> = cleaned.chars().collect();
-                        if chars.len() > 150 {
-                            let truncated: String = chars.into_iter().take(150).collect();
-                            format!("{}...", truncated)
-                        } else {
-                            cleaned
-                        }
-                    })
-                    .unwrap_or_else(|| "no content".to_string());
-                // Format each email in a more natural way
-                let email_intro = if i == 0 {
-                    "The most recent email is"
-                } else if i == emails.len() - 1 {
-                    "And finally"
-                } else {
-                    "Next"
-                };
-
-                response_text.push_str(&format!(
-                    "{} from {}, sent {}. The subject is '{}'. {}. ",
-                    email_intro,
-                    from,
-                    date,
-                    subject,
-                    if email.is_read {
-                        format!("Here's what it says: {}", body)
-                    } else {
-                        format!("This unread email says: {}", body)
-                    }
-                ));
+    match crate::handlers::imap_handlers::fetch_emails_imap(&state, user_id, true, Some(EMAIL_FETCH_LIMIT), false, false, None).await {
+        Ok(emails) => {
+            if emails.is_empty() {
+                return Ok(Json(json!({
+                    "response": "I don't see any recent emails in your inbox.",
+                    "emails": [],
+                    "total_count": 0
+                })));
             }
 
+            let (response_text, spoken_count) = spoken_email_summary(&emails, max_spoken);
+
             Ok(Json(json!({
                 "response": response_text,
+                "spoken_count": spoken_count,
                 "emails": emails.iter().map(|email| {
                     json!({
                         "id": email.id,
@@ -593,12 +975,12 @@ pub async fn handle_email_fetch_tool_call(
             error!("Failed to fetch emails for user {}: {:?}", user_id, e);
 
             // Provide user-friendly error message based on error type
-            let user_message = match e {
+            let user_message = crate::utils::branding::render(match e {
                 crate::handlers::imap_handlers::ImapError::NoConnection => {
-                    "It looks like you haven't connected your email yet. You can set it up in the Lightfriend app settings."
+                    crate::utils::branding::VOICE_EMAIL_NOT_CONNECTED
                 }
                 crate::handlers::imap_handlers::ImapError::CredentialsError(_) => {
-                    "I couldn't access your email because your credentials have expired or are invalid. Please reconnect your email in the Lightfriend app. If you're using Gmail, you may need to generate a new app password."
+                    crate::utils::branding::VOICE_EMAIL_CREDENTIALS_INVALID
                 }
                 crate::handlers::imap_handlers::ImapError::ConnectionError(_) => {
                     "I'm having trouble connecting to your email server right now. This might be a temporary issue. Please try again in a moment."
@@ -606,7 +988,7 @@ pub async fn handle_email_fetch_tool_call(
                 _ => {
                     "I ran into a problem checking your email. Please check your email connection in the app settings and try again."
                 }
-            };
+            });
 
             Ok(Json(json!({
                 "response": user_message,
@@ -623,7 +1005,7 @@ pub async fn handle_email_fetch_tool_call(
 pub async fn handle_send_sms_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<MessageCallPayload>,
+    ValidatedJson(payload): ValidatedJson<MessageCallPayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     tracing::debug!("Received SMS send request");
     
@@ -677,22 +1059,26 @@ pub async fn handle_send_sms_tool_call(
 
     let mut message_sids = Vec::new();
 
-    // Handle email attachments if email_id is provided - spawn as background task
+    // Handle email attachments if email_id is provided - queued as background job
     if let Some(email_id) = payload.email_id.clone() {
-        tracing::debug!("Spawning background task for email attachments processing for email ID: {}", email_id);
-        
+        tracing::debug!("Queueing background job for email attachments processing for email ID: {}", email_id);
+
         let state_clone = Arc::clone(&state);
-        let user_clone = user.clone();
-        tokio::spawn(async move {
-            tracing::debug!("Background task: Fetching email attachments for email ID: {}", email_id);
-            
-            match fetch_single_email_imap(&state_clone, user_clone.id, &email_id).await {
-                Ok(_) => {}
-                Err(e) => {
-                    error!("Background task: Failed to fetch email: {:?}", e);
-                }
+        let user_id = user.id;
+        let job = crate::utils::job_queue::BackgroundJob::new("email_attachments", move || {
+            let state_clone = Arc::clone(&state_clone);
+            let email_id = email_id.clone();
+            async move {
+                tracing::debug!("Background job: Fetching email attachments for email ID: {}", email_id);
+                fetch_single_email_imap(&state_clone, user_id, &email_id, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("Failed to fetch email: {:?}", e))
             }
-        });
+        }).with_retries(3);
+        if let Err(e) = state.job_queue.enqueue(job).await {
+            error!("Failed to queue email attachment processing: {}", e);
+        }
     }
 
     // Send the main message using Twilio
@@ -738,7 +1124,7 @@ pub async fn handle_send_sms_tool_call(
 
 pub async fn handle_perplexity_tool_call(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<MessageCallPayload>,
+    ValidatedJson(payload): ValidatedJson<MessageCallPayload>,
 ) -> Json<serde_json::Value> {
 
     let system_prompt = "You are assisting an AI voice calling service. The questions you receive are from voice conversations where users are seeking information or help. Please note: 1. Provide clear, conversational responses that can be easily read aloud 2. Avoid using any markdown, HTML, or other markup languages 3. Keep responses concise but informative 4. Use natural language sentence structure 5. When listing multiple points, use simple numbering (1, 2, 3) or natural language transitions (First... Second... Finally...) 6. Focus on the most relevant information that addresses the user's immediate needs 7. If specific numbers, dates, or proper names are important, spell them out clearly 8. Format numerical data in a way that's easy to read aloud (e.g., twenty-five percent instead of 25%) Your responses will be incorporated into a voice conversation, so clarity and natural flow are essential.";
@@ -765,10 +1151,16 @@ pub struct FireCrawlCallPayload {
 }
 
 pub async fn handle_firecrawl_tool_call(
-    State(_state): State<Arc<AppState>>,
-    Json(payload): Json<FireCrawlCallPayload>,
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    ValidatedJson(payload): ValidatedJson<FireCrawlCallPayload>,
 ) -> Json<serde_json::Value> {
-    match crate::utils::tool_exec::handle_firecrawl_search(payload.query, 5).await {
+    // Older agent configs don't pass user_id, those searches run with the defaults
+    let options = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
+        Some(user_id) => crate::utils::tool_exec::firecrawl_options(&state, user_id),
+        None => crate::utils::tool_exec::FirecrawlOptions::default(),
+    };
+    match crate::utils::tool_exec::handle_firecrawl_search(payload.query, &options).await {
         Ok(response) => {
             Json(json!({
                 "response": response
@@ -837,6 +1229,329 @@ pub async fn handle_calendar_tool_call(
     }
 }
 
+/// Resolves "today", "tomorrow" or "this week" in the user's timezone to a UTC range. Days run from
+/// local midnight to local midnight and "this week" runs from today through Sunday.
+pub fn resolve_agenda_period(
+    period: &str,
+    tz: chrono_tz::Tz,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+    use chrono::{Datelike, Duration, TimeZone};
+    let today = now.with_timezone(&tz).date_naive();
+    let (first_day, days) = match period.trim().to_lowercase().as_str() {
+        "today" => (today, 1),
+        "tomorrow" => (today + Duration::days(1), 1),
+        "this week" | "week" => (today, 7 - today.weekday().num_days_from_monday() as i64),
+        _ => return None,
+    };
+    // earliest() so a DST jump at midnight still gives a start instead of no range at all
+    let local_midnight = |date: chrono::NaiveDate| tz.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest();
+    let start = local_midnight(first_day)?;
+    let end = local_midnight(first_day + Duration::days(days))?;
+    Some((start.with_timezone(&chrono::Utc), end.with_timezone(&chrono::Utc)))
+}
+
+/// "You have 3 events today. First is Standup at 09:00, then ..." from the calendar fetch response.
+/// Times are spoken in the user's timezone whatever offset the fetch happened to return them in.
+fn spoken_agenda(events: &[Value], period: &str, tz: chrono_tz::Tz) -> String {
+    if events.is_empty() {
+        return format!("You have no events {}.", period);
+    }
+    let described: Vec<String> = events
+        .iter()
+        .map(|event| {
+            let summary = event["summary"].as_str().unwrap_or("No title");
+            let start = event["start"].as_str().unwrap_or_default();
+            let described = match chrono::DateTime::parse_from_rfc3339(start).map(|start| start.with_timezone(&tz)) {
+                Ok(start) if period == "this week" => format!("{} on {} at {}", summary, start.format("%A"), start.format("%H:%M")),
+                Ok(start) => format!("{} at {}", summary, start.format("%H:%M")),
+                // Date-only start means an all-day event
+                Err(_) => format!("{} all day", summary),
+            };
+            match event["attendee_responses"].as_str() {
+                Some(responses) => format!("{} ({})", described, responses),
+                None => described,
+            }
+        })
+        .collect();
+    let count = if events.len() == 1 { "1 event".to_string() } else { format!("{} events", events.len()) };
+    let mut agenda = format!("You have {} {}. First is {}", count, period, described[0]);
+    if described.len() > 1 {
+        agenda.push_str(&format!(", then {}", described[1..].join(", then ")));
+    }
+    agenda.push('.');
+    agenda
+}
+
+pub async fn handle_calendar_agenda_tool_call(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_param(&params)?;
+    require_user(&state, user_id)?;
+    let period = params.get("period").map(|p| p.trim().to_lowercase()).unwrap_or_else(|| "today".to_string());
+    let tz: chrono_tz::Tz = state.user_core.get_user_info(user_id).ok()
+        .and_then(|info| info.timezone)
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(chrono_tz::UTC);
+    let Some((start, end)) = resolve_agenda_period(&period, tz, chrono::Utc::now()) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "period must be \"today\", \"tomorrow\" or \"this week\""
+            }))
+        ));
+    };
+    let period = if period == "week" { "this week".to_string() } else { period };
+
+    let Json(calendar) = crate::handlers::google_calendar::handle_calendar_fetching(
+        &state,
+        user_id,
+        &start.to_rfc3339(),
+        &end.to_rfc3339(),
+    ).await?;
+    let events = calendar["events"].as_array().cloned().unwrap_or_default();
+    Ok(Json(json!({
+        "response": spoken_agenda(&events, &period, tz),
+        "events": events,
+        "period": period,
+        "start": start.to_rfc3339(),
+        "end": end.to_rfc3339(),
+    })))
+}
+
+/// Longest spoken briefing, anything past it is cut at a sentence boundary
+const MAX_BRIEFING_CHARS: usize = 700;
+
+/// What the daily briefing found, None for sources the user hasn't connected or that failed
+#[derive(Debug, Default)]
+pub struct BriefingSources {
+    pub agenda: Option<String>,
+    pub unread_emails: Option<usize>,
+    pub important_emails: Option<Vec<String>>,
+    pub due_tasks: Option<Vec<String>>,
+}
+
+impl BriefingSources {
+    /// The gathered facts as plain sentences, given to the model and used as-is if it fails
+    pub fn facts(&self) -> String {
+        let mut facts = Vec::new();
+        if let Some(agenda) = &self.agenda {
+            facts.push(format!("Calendar: {}", agenda));
+        }
+        if let Some(unread) = self.unread_emails {
+            let mut email = format!("Email: {} unread recent emails.", unread);
+            if let Some(important) = self.important_emails.as_ref().filter(|i| !i.is_empty()) {
+                email.push_str(&format!(" From priority senders: {}.", important.join("; ")));
+            }
+            facts.push(email);
+        }
+        if let Some(tasks) = &self.due_tasks {
+            if tasks.is_empty() {
+                facts.push("Tasks: nothing due today.".to_string());
+            } else {
+                facts.push(format!("Tasks due today or overdue: {}.", tasks.join("; ")));
+            }
+        }
+        facts.join("\n")
+    }
+}
+
+/// Cuts the briefing to MAX_BRIEFING_CHARS, preferring to end on a full sentence
+fn cap_briefing(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= MAX_BRIEFING_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_BRIEFING_CHARS).collect();
+    match cut.rfind(". ") {
+        Some(end) => cut[..=end].to_string(),
+        None => format!("{}…", cut.trim_end()),
+    }
+}
+
+async fn gather_briefing(state: &Arc<AppState>, user_id: i32) -> BriefingSources {
+    let tz: chrono_tz::Tz = state.user_core.get_user_info(user_id).ok()
+        .and_then(|info| info.timezone)
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(chrono_tz::UTC);
+    let now = chrono::Utc::now();
+    let has_calendar = state.user_repository.has_active_google_calendar(user_id).unwrap_or(false);
+    let has_tasks = state.user_repository.has_active_google_tasks(user_id).unwrap_or(false);
+    let has_email = matches!(state.user_repository.get_imap_credentials(user_id), Ok(Some(_)));
+
+    let calendar = async {
+        if !has_calendar {
+            return None;
+        }
+        let (start, end) = resolve_agenda_period("today", tz, now)?;
+        match crate::handlers::google_calendar::handle_calendar_fetching(state, user_id, &start.to_rfc3339(), &end.to_rfc3339()).await {
+            Ok(Json(calendar)) => Some(spoken_agenda(calendar["events"].as_array().map(|e| e.as_slice()).unwrap_or_default(), "today", tz)),
+            Err(e) => {
+                tracing::warn!("Briefing skipped calendar for user {}: {:?}", user_id, e.1);
+                None
+            }
+        }
+    };
+    let email = async {
+        if !has_email {
+            return None;
+        }
+        match fetch_emails_imap(state, user_id, true, Some(EMAIL_FETCH_LIMIT), false, false, None).await {
+            Ok(emails) => Some(emails),
+            Err(e) => {
+                tracing::warn!("Briefing skipped email for user {}: {:?}", user_id, e);
+                None
+            }
+        }
+    };
+    let tasks = async {
+        if !has_tasks {
+            return None;
+        }
+        match crate::handlers::google_tasks::get_tasks(state, user_id).await {
+            Ok(Json(tasks)) => Some(tasks["tasks"].as_array().cloned().unwrap_or_default()),
+            Err(e) => {
+                tracing::warn!("Briefing skipped tasks for user {}: {:?}", user_id, e.1);
+                None
+            }
+        }
+    };
+    let (agenda, emails, tasks) = tokio::join!(calendar, email, tasks);
+
+    let priority_senders: Vec<String> = state.user_repository.get_priority_senders(user_id, "imap")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|sender| sender.sender.to_lowercase())
+        .collect();
+    let unread: Vec<_> = emails.iter().flatten().filter(|email| !email.is_read).collect();
+    let important = unread
+        .iter()
+        .filter(|email| {
+            let from = format!("{} {}", email.from.as_deref().unwrap_or_default(), email.from_email.as_deref().unwrap_or_default()).to_lowercase();
+            priority_senders.iter().any(|sender| from.contains(sender))
+        })
+        .map(|email| format!(
+            "{} about {}",
+            email.from.as_deref().unwrap_or("Unknown"),
+            email.subject.as_deref().unwrap_or("no subject")
+        ))
+        .collect();
+
+    let end_of_today = resolve_agenda_period("today", tz, now).map(|(_, end)| end).unwrap_or(now);
+    let due_tasks = tasks.map(|tasks| {
+        tasks.iter()
+            .filter(|task| task["status"].as_str() != Some("completed"))
+            .filter(|task| {
+                task["due"].as_str()
+                    .and_then(|due| chrono::DateTime::parse_from_rfc3339(due).ok())
+                    .map_or(false, |due| due.with_timezone(&chrono::Utc) <= end_of_today)
+            })
+            .filter_map(|task| task["title"].as_str().map(str::to_string))
+            .collect()
+    });
+
+    BriefingSources {
+        agenda,
+        unread_emails: emails.as_ref().map(|_| unread.len()),
+        important_emails: emails.as_ref().map(|_| important),
+        due_tasks,
+    }
+}
+
+/// Turns the gathered facts into one short spoken summary, falling back to the facts themselves
+async fn compose_briefing(state: &Arc<AppState>, user_id: i32, facts: &str) -> String {
+    use openai_api_rs::v1::chat_completion;
+    use crate::tool_call_utils::utils::{chat_completion_with_fallback, create_openai_client, model_for, ModelPurpose};
+
+    let client = match create_openai_client(state) {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to create client for daily briefing: {}", e);
+            return cap_briefing(facts);
+        }
+    };
+    let message = |role, text: String| chat_completion::ChatCompletionMessage {
+        role,
+        content: chat_completion::Content::Text(text),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    };
+    let messages = vec![
+        message(chat_completion::MessageRole::system, format!(
+            "You write a morning briefing that will be read aloud on a phone call. Use only the facts given, \
+             keep every source that is listed, mention the most important items first and stay under {} characters. \
+             Plain spoken sentences, no lists or formatting.",
+            MAX_BRIEFING_CHARS
+        )),
+        message(chat_completion::MessageRole::user, facts.to_string()),
+    ];
+    let request = chat_completion::ChatCompletionRequest::new(
+        model_for(state, Some(user_id), ModelPurpose::Summarization),
+        messages,
+    )
+    .max_tokens(250);
+    match chat_completion_with_fallback(&client, request, ModelPurpose::Summarization).await {
+        Ok(result) => result.choices.first()
+            .and_then(|choice| choice.message.content.clone())
+            .filter(|content| !content.trim().is_empty())
+            .map(|content| cap_briefing(&content))
+            .unwrap_or_else(|| cap_briefing(facts)),
+        Err(e) => {
+            tracing::error!("Failed to compose daily briefing for user {}: {}", user_id, e);
+            cap_briefing(facts)
+        }
+    }
+}
+
+/// The spoken briefing and the sources that went into it. `compose` turns the facts into the
+/// briefing and is only called when at least one source is connected.
+async fn briefing_response<F, Fut>(sources: &BriefingSources, compose: F) -> (String, Vec<&'static str>)
+where
+    F: FnOnce(String) -> Fut,
+    Fut: std::future::Future<Output = String>,
+{
+    let included: Vec<&'static str> = [
+        ("calendar", sources.agenda.is_some()),
+        ("email", sources.unread_emails.is_some()),
+        ("tasks", sources.due_tasks.is_some()),
+    ]
+    .iter()
+    .filter(|(_, present)| *present)
+    .map(|(source, _)| *source)
+    .collect();
+
+    let response = if included.is_empty() {
+        "I couldn't put a briefing together, none of your calendar, email or tasks are connected right now.".to_string()
+    } else {
+        compose(sources.facts()).await
+    };
+    (response, included)
+}
+
+/// Today's calendar, unread and priority email, and due tasks in one spoken summary.
+/// Sources the user hasn't connected are left out.
+pub async fn handle_daily_briefing_tool_call(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = user_id_param(&params)?;
+    require_user(&state, user_id)?;
+
+    let sources = gather_briefing(&state, user_id).await;
+    let (response, included) = briefing_response(&sources, |facts| async move {
+        compose_briefing(&state, user_id, &facts).await
+    }).await;
+
+    Ok(Json(json!({
+        "response": response,
+        "sources": included,
+        "status": "success",
+        "user_id": user_id,
+    })))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TaskCreatePayload {
     pub title: String,
@@ -847,7 +1562,7 @@ pub struct TaskCreatePayload {
 pub async fn handle_tasks_creation_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(task_payload): axum::extract::Json<TaskCreatePayload>,
+    ValidatedJson(task_payload): ValidatedJson<TaskCreatePayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     
     // Get user_id from query params
@@ -917,17 +1632,7 @@ pub async fn handle_tasks_fetching_tool_call(
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract and parse user_id from query params
-    let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
-        Some(id) => id,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Invalid or missing user_id parameter"
-                }))
-            ));
-        }
-    };
+    let user_id = user_id_param(&params)?;
 
     tracing::debug!("Received tasks fetch request for user: {}", user_id);
 
@@ -959,6 +1664,13 @@ pub struct ChatSearchPayload {
 pub struct ChatConfirmPayload {
     chat_name: String,
     message: String,
+    #[serde(default)]
+    confirm_new_contact: bool,
+    #[serde(default)]
+    image_url: Option<String>,
+    /// Send at this time instead of after the cancel window, "YYYY-MM-DDTHH:MM:SS" in the user's timezone
+    #[serde(default)]
+    send_at: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -968,12 +1680,15 @@ pub struct CalendarEventConfirmPayload {
     duration_minutes: i32,
     description: Option<String>,
     add_notification: Option<bool>,
+    /// Logging an event that already happened, past start times are rejected otherwise
+    #[serde(default)]
+    allow_past: bool,
 }
 
 pub async fn handle_email_search_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<EmailSearchPayload>,
+    ValidatedJson(payload): ValidatedJson<EmailSearchPayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
 
     // Extract user_id from query parameters
@@ -990,7 +1705,7 @@ pub async fn handle_email_search_tool_call(
     };
 
     // First fetch recent emails with increased limit
-    match fetch_emails_imap(&state, user_id, true, Some(50), false, false).await {
+    match fetch_emails_imap(&state, user_id, true, Some(50), false, false, None).await {
         Ok(emails) => {
             let search_term = payload.search_term.to_lowercase();
             let search_type = payload.search_type.as_deref().unwrap_or("all");
@@ -1123,7 +1838,7 @@ pub async fn handle_email_search_tool_call(
             let best_match = &scored_emails[0];
             
             // Fetch the full email content for the best match
-            match fetch_single_email_imap(&state, user_id, &best_match.email.id).await {
+            match fetch_single_email_imap(&state, user_id, &best_match.email.id, None).await {
                 Ok(full_email) => {
                     // Format response text in a more natural, voice-friendly way
                     let match_quality = match best_match.match_type.as_str() {
@@ -1252,12 +1967,12 @@ pub async fn handle_email_search_tool_call(
             error!("Failed to fetch emails for search (user {}): {:?}", user_id, e);
 
             // Provide user-friendly error message based on error type
-            let user_message = match e {
+            let user_message = crate::utils::branding::render(match e {
                 crate::handlers::imap_handlers::ImapError::NoConnection => {
-                    "It looks like you haven't connected your email yet. You can set it up in the Lightfriend app settings."
+                    crate::utils::branding::VOICE_EMAIL_NOT_CONNECTED
                 }
                 crate::handlers::imap_handlers::ImapError::CredentialsError(_) => {
-                    "I couldn't access your email because your credentials have expired or are invalid. Please reconnect your email in the Lightfriend app. If you're using Gmail, you may need to generate a new app password."
+                    crate::utils::branding::VOICE_EMAIL_CREDENTIALS_INVALID
                 }
                 crate::handlers::imap_handlers::ImapError::ConnectionError(_) => {
                     "I'm having trouble connecting to your email server right now. This might be a temporary issue. Please try again in a moment."
@@ -1265,7 +1980,7 @@ pub async fn handle_email_search_tool_call(
                 _ => {
                     "I ran into a problem searching your email. Please check your email connection in the app settings and try again."
                 }
-            };
+            });
 
             Ok(Json(json!({
                 "response": user_message,
@@ -1278,7 +1993,7 @@ pub async fn handle_email_search_tool_call(
 pub async fn handle_send_chat_message(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<ChatConfirmPayload>,
+    ValidatedJson(payload): ValidatedJson<ChatConfirmPayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract user_id from query parameters
     let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
@@ -1326,6 +2041,17 @@ pub async fn handle_send_chat_message(
         }
     };
     let capitalized_platform = platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &platform[1..];
+    let image_url = payload.image_url.as_deref().map(str::trim).filter(|url| !url.is_empty()).map(str::to_string);
+    if let Some(url) = image_url.as_deref() {
+        if let Err(e) = crate::utils::bridge::validate_image_url(url) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": e.to_string()
+                }))
+            ));
+        }
+    }
     // Check bridge connection
     let bridge = match state.user_repository.get_bridge(user_id, &platform) {
         Ok(bridge) => bridge,
@@ -1397,9 +2123,9 @@ pub async fn handle_send_chat_message(
             ));
         }
     };
-    let best_match = crate::utils::bridge::search_best_match(&rooms, &payload.chat_name);
-    let best_match = match best_match {
-        Some(room) => room,
+    let best_match = crate::utils::bridge::search_best_match_scored(&rooms, &payload.chat_name);
+    let (best_match, match_confidence) = match best_match {
+        Some(found) => found,
         None => {
             let error_msg = format!("No {} contacts found matching '{}'.", capitalized_platform, payload.chat_name);
             if let Err(e) = crate::api::twilio_utils::send_conversation_message(
@@ -1420,14 +2146,74 @@ pub async fn handle_send_chat_message(
     };
     // Get the exact name
     let exact_name = crate::utils::bridge::remove_bridge_suffix(&best_match.display_name);
-    // Format the queued message
-    let queued_msg = format!(
-        "Will send {} to '{}' with '{}' in 60s. Use cancel_message tool to discard.",
-        capitalized_platform, exact_name, payload.message
-    );
-    // Create cancellation channel
-    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
-    // Spawn the delayed send task
+    if let Some(reason) = crate::tool_call_utils::utils::self_send_rejection(&state, &user, &exact_name)
+        .or_else(|| crate::tool_call_utils::utils::self_send_rejection(&state, &user, &payload.chat_name))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": reason
+            }))
+        ));
+    }
+    let known_contact = crate::utils::bridge::is_known_contact(&state, user_id, &platform, &best_match.room_id).await;
+    let (delay_secs, low_confidence) = match crate::utils::bridge::send_guard(match_confidence, known_contact, payload.confirm_new_contact) {
+        crate::utils::bridge::SendGuard::Proceed { delay_secs, low_confidence } => (delay_secs, low_confidence),
+        crate::utils::bridge::SendGuard::NeedsConfirmation => {
+            return Ok(Json(json!({
+                "status": "confirmation_required",
+                "requires_confirmation": true,
+                "room_name": exact_name,
+                "message": format!(
+                    "The user has never messaged '{}' on {}. Ask them to confirm, then call again with confirm_new_contact set to true.",
+                    exact_name, capitalized_platform
+                )
+            })));
+        }
+    };
+    // An explicit time skips the cancel window and goes to the scheduled sends table
+    if let Some(send_at) = payload.send_at.as_deref().filter(|s| !s.trim().is_empty()) {
+        let scheduled = crate::utils::scheduled_sends::ScheduledPayload::ChatMessage {
+            platform: platform.clone(),
+            chat_name: exact_name.clone(),
+            room_id: Some(best_match.room_id.clone()),
+            message: payload.message.clone(),
+            image_url: image_url.clone(),
+        };
+        return match crate::utils::scheduled_sends::schedule_from_tool(&state, user_id, send_at, &scheduled, &format!("{} {}", capitalized_platform, exact_name)) {
+            Ok((scheduled_id, send_at, confirmation)) => Ok(Json(json!({
+                "status": "success",
+                "message": confirmation,
+                "scheduled_id": scheduled_id,
+                "send_at": send_at.to_rfc3339()
+            }))),
+            Err(reason) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": reason
+                }))
+            )),
+        };
+    }
+    // Format the queued message, spelling out the resolved contact when the match was a stretch
+    let language = agent_language(&state, user_id);
+    let with_image = if image_url.is_some() { crate::utils::elevenlabs_prompts::image_attachment(&language) } else { "" };
+    let kind = if low_confidence {
+        crate::utils::elevenlabs_prompts::QueuedMessage::ChatLowConfidence
+    } else {
+        crate::utils::elevenlabs_prompts::QueuedMessage::Chat
+    };
+    let queued_msg = crate::utils::elevenlabs_prompts::queued_message(kind, &language, &[
+        ("platform", capitalized_platform.as_str()),
+        ("recipient", exact_name.as_str()),
+        ("requested", payload.chat_name.as_str()),
+        ("content", payload.message.as_str()),
+        ("attachment", with_image),
+        ("delay", delay_secs.to_string().as_str()),
+    ]);
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "chat_message", &format!("{} {}", capitalized_platform, exact_name), &format!("{} message to {}", capitalized_platform, exact_name), std::time::Duration::from_secs(delay_secs)).await;
+    // Queue the delayed send
     let cloned_state = state.clone();
     let cloned_user_id = user_id;
     let cloned_user = user.clone();
@@ -1435,19 +2221,27 @@ pub async fn handle_send_chat_message(
     let cloned_capitalized_platform = capitalized_platform.clone();
     let cloned_exact_name = exact_name.clone();
     let cloned_message = payload.message.clone();
-    tokio::spawn(async move {
-        let reason = tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "timeout",
-            _ = cancel_rx => "cancel",
-        };
-        if reason == "timeout" {
+    let cloned_image_url = image_url.clone();
+    let job = crate::utils::job_queue::BackgroundJob::new("send_bridge_message", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_platform = cloned_platform.clone();
+        let cloned_capitalized_platform = cloned_capitalized_platform.clone();
+        let cloned_exact_name = cloned_exact_name.clone();
+        let cloned_message = cloned_message.clone();
+        let cloned_image_url = cloned_image_url.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
             match crate::utils::bridge::send_bridge_message(
                 &cloned_platform,
                 &cloned_state,
                 cloned_user_id,
                 &cloned_exact_name,
                 &cloned_message,
-                None, // No image URL
+                cloned_image_url,
             ).await {
                 Ok(_) => {
                     // No additional success message
@@ -1464,20 +2258,25 @@ pub async fn handle_send_chat_message(
                     }
                 }
             }
+            Ok(())
         }
-        // Remove from map
-        let mut senders = cloned_state.pending_message_senders.lock().await;
-        senders.remove(&cloned_user_id);
     });
-    // Store the cancel sender in the map
-    {
-        let mut senders = state.pending_message_senders.lock().await;
-        senders.insert(user_id, cancel_tx);
+    if let Err(e) = state.job_queue.enqueue_delayed(job, std::time::Duration::from_secs(delay_secs), cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        error!("Failed to queue send bridge message: {}", e);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "error": "Failed to queue the message, please try again"
+            }))
+        ));
     }
     Ok(Json(json!({
         "status": "success",
         "message": format!("{} message queued", capitalized_platform),
         "room_name": exact_name,
+        "low_confidence_match": low_confidence,
+        "action_id": action_id,
         "notification": queued_msg
     })))
 }
@@ -1487,12 +2286,15 @@ pub struct SendEmailArgs {
     pub to: String,
     pub subject: String,
     pub body: String,
+    /// Send at this time instead of after the cancel window, "YYYY-MM-DDTHH:MM:SS" in the user's timezone
+    #[serde(default)]
+    pub send_at: Option<String>,
 }
 
 pub async fn handle_email_send(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<SendEmailArgs>,
+    ValidatedJson(payload): ValidatedJson<SendEmailArgs>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract user_id from query parameters
     let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
@@ -1528,76 +2330,268 @@ pub async fn handle_email_send(
         }
     };
     // Format the queued message
-    let queued_msg = format!(
-        "Will send email to {} with subject '{}' and body '{}' in 60s. Use cancel_message tool to discard.",
-        payload.to, payload.subject, payload.body
+    if let Some(reason) = crate::tool_call_utils::utils::self_send_rejection(&state, &user, &payload.to) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": reason
+            }))
+        ));
+    }
+    // An explicit time skips the cancel window and goes to the scheduled sends table
+    if let Some(send_at) = payload.send_at.as_deref().filter(|s| !s.trim().is_empty()) {
+        let scheduled = crate::utils::scheduled_sends::ScheduledPayload::Email {
+            to: payload.to.clone(),
+            subject: payload.subject.clone(),
+            body: payload.body.clone(),
+        };
+        return match crate::utils::scheduled_sends::schedule_from_tool(&state, user_id, send_at, &scheduled, &payload.to) {
+            Ok((scheduled_id, send_at, confirmation)) => Ok(Json(json!({
+                "status": "success",
+                "message": confirmation,
+                "scheduled_id": scheduled_id,
+                "send_at": send_at.to_rfc3339()
+            }))),
+            Err(reason) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": reason
+                }))
+            )),
+        };
+    }
+    let language = agent_language(&state, user_id);
+    let (send_delay, deferred_until) = crate::tool_call_utils::utils::email_send_delay(&state, user_id, std::time::Duration::from_secs(60));
+    let mut queued_msg = crate::utils::elevenlabs_prompts::queued_message(
+        crate::utils::elevenlabs_prompts::QueuedMessage::Email,
+        &language,
+        &[("recipient", payload.to.as_str()), ("subject", payload.subject.as_str()), ("content", payload.body.as_str()), ("delay", "60")],
     );
-    // Create cancellation channel
-    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
-    // Spawn the delayed send task
+    if let Some(send_at) = &deferred_until {
+        queued_msg.push_str(&crate::utils::elevenlabs_prompts::business_hours_note(&language, send_at));
+    }
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email", &payload.to, &format!("email to {}", payload.to), send_delay).await;
+    // Queue the delayed send
     let cloned_state = state.clone();
     let cloned_user_id = user_id;
     let cloned_user = user.clone();
     let cloned_to = payload.to.clone();
     let cloned_subject = payload.subject.clone();
     let cloned_body = payload.body.clone();
-    tokio::spawn(async move {
-        let reason = tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "timeout",
-            _ = cancel_rx => "cancel",
-        };
-        if reason == "timeout" {
+    let job = crate::utils::job_queue::BackgroundJob::new("send_email", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_to = cloned_to.clone();
+        let cloned_subject = cloned_subject.clone();
+        let cloned_body = cloned_body.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
             let email_request = crate::handlers::imap_handlers::SendEmailRequest {
                 to: cloned_to,
                 subject: cloned_subject,
                 body: cloned_body,
+                account_id: None,
+            };
+            match crate::handlers::imap_handlers::send_email(
+                State(cloned_state.clone()),
+                crate::handlers::auth_middleware::AuthUser { user_id: cloned_user_id, is_admin: false },
+                Json(email_request)
+            ).await {
+                Ok(_) => {
+                    // No need to send success message
+                }
+                Err((_, error_json)) => {
+                    let error_msg = format!("Failed to send email: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
+                    if let Err(e) = crate::api::twilio_utils::send_conversation_message(
+                        &cloned_state,
+                        &error_msg,
+                        None,
+                        &cloned_user,
+                    ).await {
+                        tracing::error!("Failed to send error message: {}", e);
+                    }
+                }
+            }
+            Ok(())
+        }
+    });
+    if let Err(e) = state.job_queue.enqueue_delayed(job, send_delay, cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        error!("Failed to queue send email: {}", e);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "error": "Failed to queue the message, please try again"
+            }))
+        ));
+    }
+    Ok(Json(json!({
+        "status": "success",
+        "message": "Email queued",
+        "action_id": action_id,
+        "notification": queued_msg,
+        "send_at": deferred_until
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RespondToEmailArgs {
+    pub email_id: String,
+    pub response_text: String,
+    #[serde(default)]
+    pub reply_all: bool,
+}
+pub async fn handle_respond_to_email(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    ValidatedJson(payload): ValidatedJson<RespondToEmailArgs>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    // Extract user_id from query parameters
+    let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
+        Some(id) => id,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Missing or invalid user_id"
+                }))
+            ));
+        }
+    };
+    // Get user from database
+    let user = match state.user_core.find_by_id(user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({
+                    "error": "User not found"
+                }))
+            ));
+        }
+        Err(e) => {
+            error!("Error fetching user: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to fetch user"
+                }))
+            ));
+        }
+    };
+    // Fetch the email details to get the subject
+    let email_details = match crate::imap_handlers::fetch_single_imap_email(
+        State(state.clone()),
+        crate::handlers::auth_middleware::AuthUser { user_id, is_admin: false },
+        axum::extract::Path(payload.email_id.clone()),
+    ).await {
+        Ok(details) => details,
+        Err((_, error_json)) => {
+            let error_msg = format!("Failed to fetch email details: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": error_msg
+                }))
+            ));
+        }
+    };
+    let subject = email_details.0.get("email")
+        .and_then(|e| e.get("subject"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("Unknown subject")
+        .to_string();
+    // Format the queued message using the subject
+    let language = agent_language(&state, user_id);
+    let (send_delay, deferred_until) = crate::tool_call_utils::utils::email_send_delay(&state, user_id, std::time::Duration::from_secs(60));
+    let mut queued_msg = crate::utils::elevenlabs_prompts::queued_message(
+        crate::utils::elevenlabs_prompts::QueuedMessage::EmailReply,
+        &language,
+        &[("subject", subject.as_str()), ("content", payload.response_text.as_str()), ("delay", "60")],
+    );
+    if let Some(send_at) = &deferred_until {
+        queued_msg.push_str(&crate::utils::elevenlabs_prompts::business_hours_note(&language, send_at));
+    }
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email_reply", &subject, &format!("reply to email {}", payload.email_id), send_delay).await;
+    // Queue the delayed send
+    let cloned_state = state.clone();
+    let cloned_user_id = user_id;
+    let cloned_user = user.clone();
+    let cloned_email_id = payload.email_id.clone();
+    let cloned_response_text = payload.response_text.clone();
+    let reply_all = payload.reply_all;
+    let job = crate::utils::job_queue::BackgroundJob::new("respond_to_email", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_email_id = cloned_email_id.clone();
+        let cloned_response_text = cloned_response_text.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
+            let request = crate::imap_handlers::EmailResponseRequest {
+                email_id: cloned_email_id,
+                response_text: cloned_response_text,
+                reply_all,
             };
-            match crate::handlers::imap_handlers::send_email(
+            match crate::imap_handlers::respond_to_email(
                 State(cloned_state.clone()),
                 crate::handlers::auth_middleware::AuthUser { user_id: cloned_user_id, is_admin: false },
-                Json(email_request)
+                Json(request)
             ).await {
                 Ok(_) => {
                     // No need to send success message
                 }
                 Err((_, error_json)) => {
-                    let error_msg = format!("Failed to send email: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
+                    let error_msg = format!("Failed to respond to email: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
                     if let Err(e) = crate::api::twilio_utils::send_conversation_message(
                         &cloned_state,
                         &error_msg,
                         None,
                         &cloned_user,
                     ).await {
-                        eprintln!("Failed to send error message: {}", e);
+                        tracing::error!("Failed to send error message: {}", e);
                     }
                 }
             }
+            Ok(())
         }
-        // Remove from map
-        let mut senders = cloned_state.pending_message_senders.lock().await;
-        senders.remove(&cloned_user_id);
     });
-    // Store the cancel sender in the map
-    {
-        let mut senders = state.pending_message_senders.lock().await;
-        senders.insert(user_id, cancel_tx);
+    if let Err(e) = state.job_queue.enqueue_delayed(job, send_delay, cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        error!("Failed to queue respond to email: {}", e);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "error": "Failed to queue the message, please try again"
+            }))
+        ));
     }
     Ok(Json(json!({
         "status": "success",
-        "message": "Email queued",
-        "notification": queued_msg
+        "message": "Email response queued",
+        "action_id": action_id,
+        "notification": queued_msg,
+        "send_at": deferred_until
     })))
 }
 
+
 #[derive(Debug, Deserialize)]
-pub struct RespondToEmailArgs {
+pub struct ForwardEmailArgs {
     pub email_id: String,
-    pub response_text: String,
+    pub to: String,
 }
-pub async fn handle_respond_to_email(
+pub async fn handle_forward_email(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<RespondToEmailArgs>,
+    ValidatedJson(payload): ValidatedJson<ForwardEmailArgs>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract user_id from query parameters
     let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
@@ -1611,6 +2605,16 @@ pub async fn handle_respond_to_email(
             ));
         }
     };
+    // Validate the destination address before queuing anything
+    let to = payload.to.trim().to_string();
+    if to.parse::<lettre::message::Mailbox>().is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("'{}' is not a valid email address", to)
+            }))
+        ));
+    }
     // Get user from database
     let user = match state.user_core.find_by_id(user_id) {
         Ok(Some(user)) => user,
@@ -1632,52 +2636,62 @@ pub async fn handle_respond_to_email(
             ));
         }
     };
-    // Fetch the email details to get the subject
-    let email_details = match crate::imap_handlers::fetch_single_imap_email(
-        State(state.clone()),
-        crate::handlers::auth_middleware::AuthUser { user_id, is_admin: false },
-        axum::extract::Path(payload.email_id.clone()),
-    ).await {
-        Ok(details) => details,
-        Err((_, error_json)) => {
-            let error_msg = format!("Failed to fetch email details: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
+    if let Some(reason) = crate::tool_call_utils::utils::self_send_rejection(&state, &user, &to) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": reason
+            }))
+        ));
+    }
+    // Fetch the original email to get the subject
+    let original = match fetch_single_email_imap(&state, user_id, &payload.email_id, None).await {
+        Ok(email) => email,
+        Err(e) => {
+            error!("Failed to fetch email {} for forwarding: {:?}", payload.email_id, e);
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
-                    "error": error_msg
+                    "error": "Failed to fetch the email to forward"
                 }))
             ));
         }
     };
-    let subject = email_details.0.get("email")
-        .and_then(|e| e.get("subject"))
-        .and_then(|s| s.as_str())
-        .unwrap_or("Unknown subject")
-        .to_string();
+    let subject = original.subject.clone().unwrap_or_else(|| "Unknown subject".to_string());
     // Format the queued message using the subject
-    let queued_msg = format!(
-        "Will respond to email '{}' with '{}' in 60s. Use cancel_message to discard.",
-        subject, payload.response_text
+    let language = agent_language(&state, user_id);
+    let (send_delay, deferred_until) = crate::tool_call_utils::utils::email_send_delay(&state, user_id, std::time::Duration::from_secs(60));
+    let mut queued_msg = crate::utils::elevenlabs_prompts::queued_message(
+        crate::utils::elevenlabs_prompts::QueuedMessage::EmailForward,
+        &language,
+        &[("subject", subject.as_str()), ("recipient", to.as_str()), ("delay", "60")],
     );
-    // Create cancellation channel
-    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
-    // Spawn the delayed send task
+    if let Some(send_at) = &deferred_until {
+        queued_msg.push_str(&crate::utils::elevenlabs_prompts::business_hours_note(&language, send_at));
+    }
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email_forward", &to, &format!("forward of email {} to {}", payload.email_id, to), send_delay).await;
+    // Queue the delayed send
     let cloned_state = state.clone();
     let cloned_user_id = user_id;
     let cloned_user = user.clone();
     let cloned_email_id = payload.email_id.clone();
-    let cloned_response_text = payload.response_text.clone();
-    tokio::spawn(async move {
-        let reason = tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "timeout",
-            _ = cancel_rx => "cancel",
-        };
-        if reason == "timeout" {
-            let request = crate::imap_handlers::EmailResponseRequest {
+    let cloned_to = to.clone();
+    let job = crate::utils::job_queue::BackgroundJob::new("forward_email", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_email_id = cloned_email_id.clone();
+        let cloned_to = cloned_to.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
+            let request = crate::imap_handlers::ForwardEmailRequest {
                 email_id: cloned_email_id,
-                response_text: cloned_response_text,
+                to: cloned_to,
             };
-            match crate::imap_handlers::respond_to_email(
+            match crate::imap_handlers::forward_email(
                 State(cloned_state.clone()),
                 crate::handlers::auth_middleware::AuthUser { user_id: cloned_user_id, is_admin: false },
                 Json(request)
@@ -1686,31 +2700,36 @@ pub async fn handle_respond_to_email(
                     // No need to send success message
                 }
                 Err((_, error_json)) => {
-                    let error_msg = format!("Failed to respond to email: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
+                    let error_msg = format!("Failed to forward email: {}", error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error"));
                     if let Err(e) = crate::api::twilio_utils::send_conversation_message(
                         &cloned_state,
                         &error_msg,
                         None,
                         &cloned_user,
                     ).await {
-                        eprintln!("Failed to send error message: {}", e);
+                        tracing::error!("Failed to send error message: {}", e);
                     }
                 }
             }
+            Ok(())
         }
-        // Remove from map
-        let mut senders = cloned_state.pending_message_senders.lock().await;
-        senders.remove(&cloned_user_id);
     });
-    // Store the cancel sender in the map
-    {
-        let mut senders = state.pending_message_senders.lock().await;
-        senders.insert(user_id, cancel_tx);
+    if let Err(e) = state.job_queue.enqueue_delayed(job, send_delay, cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        error!("Failed to queue forward email: {}", e);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "error": "Failed to queue the message, please try again"
+            }))
+        ));
     }
     Ok(Json(json!({
         "status": "success",
-        "message": "Email response queued",
-        "notification": queued_msg
+        "message": "Email forward queued",
+        "action_id": action_id,
+        "notification": queued_msg,
+        "send_at": deferred_until
     })))
 }
 
@@ -1718,7 +2737,7 @@ pub async fn handle_respond_to_email(
 pub async fn handle_calendar_event_creation(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<CalendarEventConfirmPayload>,
+    ValidatedJson(payload): ValidatedJson<CalendarEventConfirmPayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract user_id from query parameters
     let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
@@ -1744,6 +2763,18 @@ pub async fn handle_calendar_event_creation(
             ));
         }
     };
+    let timezone = state.user_core.get_user_info(user_id).ok()
+        .and_then(|info| info.timezone)
+        .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok())
+        .unwrap_or(chrono_tz::UTC);
+    if let Some(reason) = crate::tool_call_utils::calendar::past_start_rejection(start_time, chrono::Utc::now(), timezone, payload.allow_past) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": reason
+            }))
+        ));
+    }
     // Create the event request
     let event_request = crate::handlers::google_calendar::CreateEventRequest {
         summary: payload.summary.clone(),
@@ -1771,7 +2802,7 @@ pub async fn handle_calendar_event_creation(
 pub async fn handle_search_chat_contacts_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<ChatSearchPayload>,
+    ValidatedJson(payload): ValidatedJson<ChatSearchPayload>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract user_id from query parameters
     let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
@@ -1828,9 +2859,17 @@ pub async fn handle_search_chat_contacts_tool_call(
                     rooms.len() - 5
                 ));
             }
+            // Numbered like the response so "contact 2" can be tied to its room id in later turns
+            let numbered_rooms: Vec<serde_json::Value> = rooms.iter().enumerate().map(|(i, room)| json!({
+                "contact_number": i + 1,
+                "room_id": room.room_id,
+                "display_name": room.display_name,
+                "last_activity": room.last_activity,
+                "last_activity_formatted": room.last_activity_formatted,
+            })).collect();
             Ok(Json(json!({
                 "response": response_text,
-                "rooms": rooms,
+                "rooms": numbered_rooms,
                 "total_count": rooms.len()
             })))
         },
@@ -1972,20 +3011,24 @@ pub async fn handle_fetch_recent_messages_tool_call(
                 let capitalized_platform = platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &platform[1..];
                 return Ok(Json(json!({
                     "response": format!("No {} messages found for the specified time range.", capitalized_platform),
-                    "messages": []
+                    "messages": [],
+                    "unread_count": 0
                 })));
             }
             // Format messages for voice response
             let capitalized_platform = platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &platform[1..];
+            let unread_count = messages.iter().filter(|m| m.unread).count();
             let mut response_text = format!(
-                "Found {} {} messages. Here are the highlights: ",
+                "{} Found {} {} messages. Here are the highlights: ",
+                crate::utils::bridge::new_messages_line(&messages),
                 messages.len(),
                 capitalized_platform
             );
             // Add up to 5 most recent messages to the voice response
             for (i, msg) in messages.iter().take(20).enumerate() {
                 response_text.push_str(&format!(
-                    "Message {} in chat {}, sent on {}: {}. ",
+                    "{} {} in chat {}, sent on {}: {}. ",
+                    if msg.unread { "New message" } else { "Message" },
                     i + 1,
                     msg.room_name,
                     msg.formatted_timestamp,
@@ -2001,7 +3044,8 @@ pub async fn handle_fetch_recent_messages_tool_call(
             Ok(Json(json!({
                 "response": response_text,
                 "messages": messages,
-                "total_count": messages.len()
+                "total_count": messages.len(),
+                "unread_count": unread_count
             })))
         },
         Err(e) => {
@@ -2023,72 +3067,170 @@ pub async fn handle_cancel_pending_message_tool_call(
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Extract and parse user_id from query params
-    let user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
-        Some(id) => id,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Invalid or missing user_id parameter"
-                }))
-            ));
-        }
-    };
+    let user_id = user_id_param(&params)?;
     tracing::debug!("Received cancel pending message request for user: {}", user_id);
-    // Verify user exists
-    match state.user_core.find_by_id(user_id) {
-        Ok(Some(_user)) => {
-            match crate::tool_call_utils::utils::cancel_pending_message(&state, user_id).await {
+    // Optional action_id targets one queued action, missing or "all" cancels everything pending
+    let action_id = match params.get("action_id").map(|s| s.trim()) {
+        None | Some("") | Some("all") => None,
+        Some(id) => match id.parse::<u64>() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "Invalid action_id parameter, must be a number or 'all'"
+                    }))
+                ));
+            }
+        },
+    };
+    require_user(&state, user_id)?;
+    let outcome = crate::tool_call_utils::utils::cancel_pending_actions(&state, user_id, action_id).await;
+    let cancelled = outcome.cancelled;
+    let remaining = crate::tool_call_utils::utils::list_pending_actions(&state, user_id).await;
+    let remaining_json: Vec<_> = remaining
+        .iter()
+        .map(|(id, description)| json!({"action_id": id, "description": description}))
+        .collect();
+    if cancelled.is_empty() {
+        tracing::debug!("No pending message to cancel for user: {}", user_id);
+        let too_late = format!("Too late to cancel, already sent: {}.", outcome.too_late.join(", "));
+        let response = if !outcome.too_late.is_empty() {
+            too_late.as_str()
+        } else if action_id.is_some() && !remaining.is_empty() {
+            "Couldn't find that pending message, nothing was cancelled."
+        } else {
+            "No pending message to cancel."
+        };
+        Ok(Json(json!({
+            "response": response,
+            "status": "success",
+            "user_id": user_id,
+            "remaining": remaining_json,
+        })))
+    } else {
+        tracing::debug!("Successfully cancelled {} pending messages for user: {}", cancelled.len(), user_id);
+        Ok(Json(json!({
+            "response": format!("Cancelled: {}.", cancelled.join(", ")),
+            "status": "success",
+            "user_id": user_id,
+            "cancelled": cancelled,
+            "remaining": remaining_json,
+        })))
+    }
+}
+
+/// An outbound notification call we placed, kept until it's cancelled or ElevenLabs reports it done
+#[derive(Debug, Clone)]
+pub struct ActiveNotificationCall {
+    pub call_sid: String,
+    pub conversation_id: Option<String>,
+    /// Calls from the user's own ElevenLabs number go through their Twilio account
+    pub uses_user_twilio: bool,
+    pub started_at: i64,
+}
+
+/// Notification calls don't last longer than this, older entries are leftovers from a missed webhook
+const MAX_NOTIFICATION_CALL_SECS: i64 = 60 * 60;
+
+/// Hangs up the user's active notification call through Twilio. Ok(false) when there was nothing
+/// to cancel: no call tracked, or Twilio says it already ended.
+pub async fn cancel_notification_call(state: &Arc<AppState>, user_id: i32) -> Result<bool, String> {
+    let state_clone = Arc::clone(state);
+    cancel_notification_call_with(state, user_id, |call| async move {
+        let (account_sid, auth_token) = if call.uses_user_twilio {
+            state_clone.user_core.get_twilio_credentials(user_id).map_err(|e| e.to_string())?
+        } else {
+            (
+                std::env::var("TWILIO_ACCOUNT_SID").map_err(|e| e.to_string())?,
+                std::env::var("TWILIO_AUTH_TOKEN").map_err(|e| e.to_string())?,
+            )
+        };
+        terminate_twilio_call(&account_sid, &auth_token, &call.call_sid).await
+    }).await
+}
+
+/// `cancel_notification_call` with the Twilio termination passed in
+async fn cancel_notification_call_with<F, Fut>(state: &Arc<AppState>, user_id: i32, terminate: F) -> Result<bool, String>
+where
+    F: FnOnce(ActiveNotificationCall) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, String>>,
+{
+    let Some((_, call)) = state.active_notification_calls.remove(&user_id) else {
+        return Ok(false);
+    };
+    if chrono::Utc::now().timestamp() - call.started_at > MAX_NOTIFICATION_CALL_SECS {
+        return Ok(false);
+    }
+    let call_sid = call.call_sid.clone();
+    let ended = terminate(call).await?;
+    if ended {
+        tracing::info!("Cancelled notification call {} for user {}", call_sid, user_id);
+    } else {
+        tracing::debug!("Notification call {} for user {} had already ended", call_sid, user_id);
+    }
+    Ok(ended)
+}
+
+/// Hangs up a Twilio call. Ok(false) when Twilio doesn't know the call or it's no longer in progress.
+pub async fn terminate_twilio_call(account_sid: &str, auth_token: &str, call_sid: &str) -> Result<bool, String> {
+    let response = reqwest::Client::new()
+        .post(format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Calls/{}.json",
+            account_sid, call_sid
+        ))
+        .basic_auth(account_sid, Some(auth_token))
+        .form(&[("Status", "completed")])
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(true);
+    }
+    let body = response.text().await.unwrap_or_default();
+    // 404 for an unknown call, 21220 when it's no longer in progress
+    if status == reqwest::StatusCode::NOT_FOUND || body.contains("21220") {
+        return Ok(false);
+    }
+    Err(format!("Twilio returned {}: {}", status, body))
+}
+
+/// Hangs up an incoming call once it reaches the user's max call duration and tells them why over SMS.
+/// The call may be on our Twilio account or the user's own, so both are tried.
+fn schedule_call_cap(state: &Arc<AppState>, user_id: i32, call_sid: String, cap_seconds: i32, max_call_minutes: i32) {
+    let state = Arc::clone(state);
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(cap_seconds.max(0) as u64)).await;
+        let mut accounts = Vec::new();
+        if let (Ok(sid), Ok(token)) = (std::env::var("TWILIO_ACCOUNT_SID"), std::env::var("TWILIO_AUTH_TOKEN")) {
+            accounts.push((sid, token));
+        }
+        if let Ok(credentials) = state.user_core.get_twilio_credentials(user_id) {
+            accounts.push(credentials);
+        }
+        let mut ended = false;
+        for (account_sid, auth_token) in accounts {
+            match terminate_twilio_call(&account_sid, &auth_token, &call_sid).await {
                 Ok(true) => {
-                    tracing::debug!("Successfully cancelled pending message for user: {}",
-                        user_id);
-                    Ok(Json(json!({
-                        "response": "Pending message cancelled.",
-                        "status": "success",
-                        "user_id": user_id,
-                    })))
-                },
-                Ok(false) => {
-                    tracing::debug!("No pending message to cancel for user: {}",
-                        user_id);
-                    Ok(Json(json!({
-                        "response": "No pending message to cancel.",
-                        "status": "success",
-                        "user_id": user_id,
-                    })))
-                },
-                Err(e) => {
-                    error!("Failed to cancel pending message: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({
-                            "error": "Failed to cancel pending message",
-                            "details": e.to_string()
-                        }))
-                    ))
+                    ended = true;
+                    break;
                 }
+                Ok(false) => continue,
+                Err(e) => error!("Failed to end call {} at the duration cap: {}", call_sid, e),
             }
-        },
-        Ok(None) => {
-            tracing::error!("User not found: {}", user_id);
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": "User not found"
-                }))
-            ))
-        },
-        Err(e) => {
-            error!("Error fetching user: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Failed to fetch user",
-                    "details": e.to_string()
-                }))
-            ))
         }
-    }
+        if !ended {
+            return;
+        }
+        tracing::info!("Ended call {} for user {} at the {} minute cap", call_sid, user_id, max_call_minutes);
+        if let Ok(Some(user)) = state.user_core.find_by_id(user_id) {
+            let message = format!("Your call was ended after reaching your {} minute call limit.", max_call_minutes);
+            if let Err(e) = crate::api::twilio_utils::send_conversation_message(&state, &message, None, &user).await {
+                error!("Failed to send call cap message: {}", e);
+            }
+        }
+    });
 }
 
 pub async fn make_notification_call(
@@ -2137,6 +3279,17 @@ pub async fn make_notification_call(
             ));
         }
     };
+    let now = chrono::Utc::now().timestamp() as i32;
+    if crate::proactive::utils::is_paused(&user_settings, Some(content_type.as_str()), now) {
+        tracing::info!("Not calling user {}, notifications are paused", user.id);
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": "Notifications are paused",
+                "paused_until": user_settings.paused_until
+            }))
+        ));
+    }
     // Get or set phone_number_country
     let country = match user.phone_number_country {
         Some(c) => c,
@@ -2204,12 +3357,8 @@ pub async fn make_notification_call(
             }
         }
     };
-    // Get voice ID based on country
-    let voice_id = match user_settings.agent_language.to_lowercase().as_str() {
-        "fi" => std::env::var("FI_VOICE_ID").expect("FI_VOICE_ID not set"),
-        "de" => std::env::var("DE_VOICE_ID").expect("DE_VOICE_ID not set"),
-        _ => std::env::var("US_VOICE_ID").expect("US_VOICE_ID not set"), // Default for US/CA/GB/AU/Other
-    };
+    // Get voice ID based on the agent language, English voice for anything not configured
+    let voice_id = crate::utils::voice_languages::voice_language(&user_settings.agent_language).voice_id.clone();
     // Create dynamic variables map with notification message
     let mut dynamic_variables = HashMap::new();
     dynamic_variables.insert("notification_message".to_string(), json!(notification_message));
@@ -2239,6 +3388,11 @@ pub async fn make_notification_call(
     );
     dynamic_variables.insert("timezone".to_string(), json!(timezone_str));
     dynamic_variables.insert("timezone_offset_from_utc".to_string(), json!(offset));
+    crate::utils::elevenlabs_prompts::sanitize_dynamic_variables(&mut dynamic_variables);
+    let notification_first_message = crate::utils::elevenlabs_prompts::checked_first_message(
+        &notification_first_message,
+        &dynamic_variables,
+    );
     // Create the payload for the call
     let payload = NotificationCallPayload {
         agent_id: std::env::var("AGENT_ID").expect("AGENT_ID not set"),
@@ -2258,22 +3412,31 @@ pub async fn make_notification_call(
         },
     };
     let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.elevenlabs.io/v1/convai/twilio/outbound-call".to_string())
-        .header("xi-api-key", std::env::var("ELEVENLABS_API_KEY").expect("ELEVENLABS_API_KEY not set"))
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("Failed to make ElevenLabs API call: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Failed to initiate notification call",
-                    "details": e.to_string()
-                }))
-            )
-        })?;
+    let api_key = std::env::var("ELEVENLABS_API_KEY").expect("ELEVENLABS_API_KEY not set");
+    // Rate limits are retried here, if they outlast the retries the caller's SMS fallback takes over
+    let response = crate::utils::elevenlabs_retry::send_with_retry(
+        crate::utils::elevenlabs_retry::policy(),
+        "place notification call",
+        || {
+            client
+                .post("https://api.elevenlabs.io/v1/convai/twilio/outbound-call")
+                .header("xi-api-key", &api_key)
+                .json(&payload)
+        },
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to make ElevenLabs API call: {}", e);
+        crate::utils::metrics::record_elevenlabs_call(false);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "Failed to initiate notification call",
+                "details": e.to_string()
+            }))
+        )
+    })?;
+    crate::utils::metrics::record_elevenlabs_call(response.status().is_success());
     if !response.status().is_success() {
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
         error!("ElevenLabs API returned error: {}", error_text);
@@ -2285,6 +3448,18 @@ pub async fn make_notification_call(
             }))
         ));
     }
+    // Remember the call so the user can cancel it, the response carries the Twilio call sid
+    let call_started: serde_json::Value = response.json().await.unwrap_or_default();
+    if let Some(call_sid) = call_started["callSid"].as_str() {
+        state.active_notification_calls.insert(user.id, ActiveNotificationCall {
+            call_sid: call_sid.to_string(),
+            conversation_id: call_started["conversation_id"].as_str().map(str::to_string),
+            uses_user_twilio: !is_supported_country,
+            started_at: chrono::Utc::now().timestamp(),
+        });
+    } else {
+        tracing::warn!("Outbound call response for user {} had no callSid, it can't be cancelled", user.id);
+    }
     // Get user information before spawning the thread
     let user = match state.user_core.find_by_id(user_id.parse::<i32>().unwrap_or_default()) {
         Ok(Some(user)) => user,
@@ -2343,7 +3518,7 @@ pub async fn make_notification_call(
 pub async fn handle_weather_tool_call(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<LocationCallPayload>,
+    ValidatedJson(payload): ValidatedJson<LocationCallPayload>,
 ) -> Json<serde_json::Value> {
 
     // Extract user_id from query parameters
@@ -2356,7 +3531,8 @@ pub async fn handle_weather_tool_call(
         }
     };
     
-    match crate::utils::tool_exec::get_weather(&state, &payload.location, &payload.units, user_id).await {
+    let units = crate::utils::tool_exec::resolve_units(&state, user_id, payload.units.as_deref());
+    match crate::utils::tool_exec::get_weather(&state, &payload.location, &units, user_id).await {
         Ok(weather_info) => {
             Json(json!({
                 "response": weather_info
@@ -2383,7 +3559,7 @@ pub struct DirectionsCallPayload {
 pub async fn handle_directions_tool_call(
     State(_state): State<Arc<AppState>>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-    Json(payload): Json<DirectionsCallPayload>,
+    ValidatedJson(payload): ValidatedJson<DirectionsCallPayload>,
 ) -> Json<serde_json::Value> {
     // Extract user_id from query parameters
     let _user_id = match params.get("user_id").and_then(|id| id.parse::<i32>().ok()) {
@@ -2414,3 +3590,422 @@ pub async fn handle_directions_tool_call(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    fn query(pairs: &[(&str, &str)]) -> axum::extract::Query<HashMap<String, String>> {
+        axum::extract::Query(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+    }
+
+    fn waiting_check() -> ValidatedJson<WaitingCheckPayload> {
+        ValidatedJson(WaitingCheckPayload {
+            content: "package from DHL".to_string(),
+            service_type: "email".to_string(),
+            noti_type: Some("sms".to_string()),
+        })
+    }
+
+    #[tokio::test]
+    async fn waiting_check_is_created_for_existing_user() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "waiting@example.com");
+
+        let Json(body) = handle_create_waiting_check_tool_call(
+            State(state.clone()),
+            query(&[("user_id", user_id.to_string().as_str())]),
+            waiting_check(),
+        ).await.expect("handler failed");
+
+        assert_eq!(body["status"], "success");
+        let checks = state.user_repository.get_waiting_checks_all(user_id).unwrap();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].content, "package from DHL");
+    }
+
+    #[tokio::test]
+    async fn waiting_check_rejects_bad_user_id() {
+        let state = test_state();
+        let (status, _) = handle_create_waiting_check_tool_call(State(state.clone()), query(&[("user_id", "abc")]), waiting_check())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = handle_create_waiting_check_tool_call(State(state), query(&[]), waiting_check())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn waiting_check_for_missing_user_is_not_found() {
+        let state = test_state();
+        let (status, _) = handle_create_waiting_check_tool_call(State(state.clone()), query(&[("user_id", "4242")]), waiting_check())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(state.user_repository.get_waiting_checks_all(4242).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn monitoring_status_is_updated() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "monitoring@example.com");
+
+        let Json(body) = handle_update_monitoring_status_tool_call(
+            State(state.clone()),
+            Json(SetProactiveAgentPayload { user_id, enabled: true }),
+        ).await.expect("handler failed");
+        assert_eq!(body["response"], "Monitoring turned on.");
+        assert!(state.user_core.get_proactive_agent_on(user_id).unwrap());
+
+        handle_update_monitoring_status_tool_call(
+            State(state.clone()),
+            Json(SetProactiveAgentPayload { user_id, enabled: false }),
+        ).await.expect("handler failed");
+        assert!(!state.user_core.get_proactive_agent_on(user_id).unwrap());
+    }
+
+    #[tokio::test]
+    async fn monitoring_status_for_missing_user_is_not_found() {
+        let state = test_state();
+        let (status, _) = handle_update_monitoring_status_tool_call(
+            State(state),
+            Json(SetProactiveAgentPayload { user_id: 4242, enabled: true }),
+        ).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn cancel_pending_message_cancels_one_or_all() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "cancel@example.com");
+        let delay = std::time::Duration::from_secs(60);
+        let (first, _first_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email", "a@example.com", "email to a", delay).await;
+        let (_second, _second_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email", "b@example.com", "email to b", delay).await;
+
+        let Json(body) = handle_cancel_pending_message_tool_call(
+            State(state.clone()),
+            query(&[("user_id", user_id.to_string().as_str()), ("action_id", first.to_string().as_str())]),
+        ).await.expect("handler failed");
+        assert_eq!(body["cancelled"], json!(["email to a"]));
+        assert_eq!(body["remaining"].as_array().unwrap().len(), 1);
+
+        let Json(body) = handle_cancel_pending_message_tool_call(
+            State(state.clone()),
+            query(&[("user_id", user_id.to_string().as_str()), ("action_id", "all")]),
+        ).await.expect("handler failed");
+        assert_eq!(body["cancelled"], json!(["email to b"]));
+        assert!(crate::tool_call_utils::utils::list_pending_actions(&state, user_id).await.is_empty());
+
+        let Json(body) = handle_cancel_pending_message_tool_call(
+            State(state),
+            query(&[("user_id", user_id.to_string().as_str())]),
+        ).await.expect("handler failed");
+        assert_eq!(body["response"], "No pending message to cancel.");
+    }
+
+    #[tokio::test]
+    async fn cancel_pending_message_rejects_bad_params() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "cancel-params@example.com");
+        let (status, _) = handle_cancel_pending_message_tool_call(State(state.clone()), query(&[("user_id", "x")]))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = handle_cancel_pending_message_tool_call(
+            State(state.clone()),
+            query(&[("user_id", user_id.to_string().as_str()), ("action_id", "soon")]),
+        ).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = handle_cancel_pending_message_tool_call(State(state), query(&[("user_id", "4242")]))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn credits_tool_reads_out_the_estimate_without_charging() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "balance@example.com");
+        state.user_repository.update_user_credits(user_id, 5.0).unwrap();
+        state.user_repository.update_user_credits_left(user_id, 3.0).unwrap();
+
+        let Json(body) = handle_credits_tool_call(State(state.clone()), query(&[("user_id", user_id.to_string().as_str())]))
+            .await
+            .expect("handler failed");
+
+        let response = body["response"].as_str().unwrap();
+        assert!(response.starts_with("You have 5.00 credits left plus 3 included messages this month."), "{response}");
+        assert!(response.contains(&format!("about {} minutes of calling", body["estimated_call_minutes"])), "{response}");
+        assert!(response.contains(&format!("about {} more messages", body["estimated_messages"])), "{response}");
+        // 3 included messages plus 66 at the US rate of 0.075
+        assert_eq!(body["estimated_messages"], 69);
+
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        assert_eq!((user.credits, user.credits_left), (5.0, 3.0));
+        assert!(state.user_repository.get_recent_usage_logs(user_id, 10).unwrap().is_empty());
+    }
+
+    fn store_turns(state: &Arc<AppState>, user_id: i32, turns: i32) {
+        for turn in 0..turns {
+            for (role, offset) in [("user", 0), ("assistant", 1)] {
+                state.user_repository.create_message_history(&crate::models::user_models::NewMessageHistory {
+                    user_id,
+                    role: role.to_string(),
+                    encrypted_content: crate::utils::encryption::encrypt(&format!("{} turn {}", role, turn)).unwrap(),
+                    tool_name: None,
+                    tool_call_id: None,
+                    created_at: 1_000 + turn * 10 + offset,
+                    conversation_id: String::new(),
+                    tool_calls_json: None,
+                }).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn call_history_follows_the_configured_limit() {
+        crate::test_support::set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "memory@example.com");
+        store_turns(&state, user_id, 4);
+        let history = |state: &Arc<AppState>| {
+            let settings = state.user_core.get_user_settings(user_id).unwrap();
+            call_history(state, user_id, &settings).into_iter().map(|m| m.encrypted_content).collect::<Vec<_>>()
+        };
+
+        // Unset means the last turn only
+        assert_eq!(history(&state), vec!["assistant turn 3", "user turn 3"]);
+
+        state.user_core.update_call_history_limit(user_id, Some(2)).unwrap();
+        assert_eq!(history(&state), vec!["assistant turn 3", "user turn 3", "assistant turn 2", "user turn 2"]);
+
+        state.user_core.update_call_history_limit(user_id, Some(0)).unwrap();
+        assert!(history(&state).is_empty());
+    }
+
+    #[tokio::test]
+    async fn saved_note_is_read_back_and_given_to_the_next_call() {
+        crate::test_support::set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "notes@example.com");
+        let user_query = || query(&[("user_id", user_id.to_string().as_str())]);
+
+        handle_save_note_tool_call(
+            State(state.clone()),
+            user_query(),
+            ValidatedJson(NotePayload { note: "  Parked in section B  ".to_string() }),
+        ).await.expect("handler failed");
+
+        let Json(body) = handle_recent_notes_tool_call(State(state.clone()), user_query()).await.expect("handler failed");
+        let notes = body["notes"].as_array().unwrap();
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].as_str().unwrap().ends_with("] Parked in section B"));
+
+        let context = call_context_notes(&state, user_id, None);
+        assert_eq!(context, notes[0].as_str().unwrap());
+    }
+
+    fn inbox(count: usize) -> Vec<crate::handlers::imap_handlers::ImapEmailPreview> {
+        (0..count).map(|i| crate::handlers::imap_handlers::ImapEmailPreview {
+            id: (500 + i).to_string(),
+            subject: Some(format!("Subject {}", i)),
+            from: Some(format!("Sender {}", i)),
+            from_email: None,
+            date: None,
+            date_formatted: Some("today".to_string()),
+            snippet: None,
+            body: Some("Short body".to_string()),
+            is_read: i % 2 == 0,
+            message_id: None,
+            copies: 1,
+            account: None,
+        }).collect()
+    }
+
+    #[test]
+    fn only_max_spoken_emails_are_described() {
+        let (response, spoken_count) = spoken_email_summary(&inbox(10), 3);
+        assert_eq!(spoken_count, 3);
+        assert_eq!(response.matches("The subject is").count(), 3);
+        assert!(response.contains("from Sender 2,"));
+        assert!(!response.contains("Sender 3"));
+        assert!(response.starts_with("I found 10 recent emails in your inbox. 5 of them are unread."));
+        assert!(response.ends_with("There are 7 more emails. Ask if you'd like to hear about them."));
+    }
+
+    #[test]
+    fn short_inbox_is_read_out_completely() {
+        let (response, spoken_count) = spoken_email_summary(&inbox(2), 3);
+        assert_eq!(spoken_count, 2);
+        assert!(response.contains("And finally from Sender 1,"));
+        assert!(!response.contains("more email"));
+    }
+
+    #[test]
+    fn tomorrow_is_local_midnight_to_midnight_in_utc() {
+        use chrono::TimeZone;
+        // 23:30 UTC on Oct 16 is already 02:30 on Oct 17 in Helsinki, so "tomorrow" is Oct 18 there
+        let now = chrono::Utc.with_ymd_and_hms(2026, 10, 16, 23, 30, 0).unwrap();
+        let (start, end) = resolve_agenda_period("tomorrow", chrono_tz::Europe::Helsinki, now).unwrap();
+        assert_eq!(start, chrono::Utc.with_ymd_and_hms(2026, 10, 17, 21, 0, 0).unwrap());
+        assert_eq!(end, chrono::Utc.with_ymd_and_hms(2026, 10, 18, 21, 0, 0).unwrap());
+
+        let (start, end) = resolve_agenda_period("Tomorrow", chrono_tz::America::New_York, now).unwrap();
+        assert_eq!(start, chrono::Utc.with_ymd_and_hms(2026, 10, 17, 4, 0, 0).unwrap());
+        assert_eq!(end, chrono::Utc.with_ymd_and_hms(2026, 10, 18, 4, 0, 0).unwrap());
+        assert!(resolve_agenda_period("next month", chrono_tz::UTC, now).is_none());
+    }
+
+    #[test]
+    fn agenda_times_are_spoken_in_the_users_timezone() {
+        let events = vec![
+            json!({"summary": "Standup", "start": "2026-10-19T06:00:00+00:00"}),
+            json!({"summary": "Dentist", "start": "2026-10-19T12:30:00Z", "attendee_responses": "Dr. Lee accepted"}),
+            json!({"summary": "Nameday", "start": "2026-10-19"}),
+        ];
+        assert_eq!(
+            spoken_agenda(&events, "tomorrow", chrono_tz::Europe::Helsinki),
+            "You have 3 events tomorrow. First is Standup at 09:00, then Dentist at 15:30 (Dr. Lee accepted), then Nameday all day."
+        );
+        assert_eq!(
+            spoken_agenda(&events[..1], "this week", chrono_tz::America::New_York),
+            "You have 1 event this week. First is Standup on Monday at 02:00."
+        );
+        assert_eq!(spoken_agenda(&[], "today", chrono_tz::UTC), "You have no events today.");
+    }
+
+    #[test]
+    fn finnish_user_gets_the_finnish_email_confirmation() {
+        use crate::utils::elevenlabs_prompts::{queued_message, QueuedMessage};
+        let state = test_state();
+        let user_id = create_test_user(&state, "suomi@example.com");
+        state.user_core.update_agent_language(user_id, "fi").unwrap();
+
+        let language = agent_language(&state, user_id);
+        assert_eq!(language, "fi");
+        assert_eq!(
+            queued_message(QueuedMessage::Email, &language, &[
+                ("recipient", "pomo@example.com"),
+                ("subject", "Sairasloma"),
+                ("content", "Olen kipeänä tänään"),
+                ("delay", "60"),
+            ]),
+            "Lähetän sähköpostin osoitteeseen pomo@example.com, aihe 'Sairasloma' ja sisältö 'Olen kipeänä tänään', 60 sekunnin päästä. Peru cancel_message-työkalulla."
+        );
+
+        let english_user = create_test_user(&state, "english@example.com");
+        assert!(queued_message(QueuedMessage::Email, &agent_language(&state, english_user), &[("delay", "60")])
+            .starts_with("Will send email to "));
+    }
+
+    #[test]
+    fn location_sharing_off_drops_location_and_nearby_places() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "private@example.com");
+        let context = || -> HashMap<String, serde_json::Value> {
+            ["location", "nearby_places", "recent_contacts", "recent_conversation", "timezone"]
+                .iter()
+                .map(|name| (name.to_string(), json!(format!("{} value", name))))
+                .collect()
+        };
+
+        let mut shared = context();
+        withhold_context_variables(&state.user_core.get_user_settings(user_id).unwrap(), &mut shared);
+        assert_eq!(shared, context());
+
+        state.user_core.update_context_sharing(user_id, "share_location", false).unwrap();
+        let mut withheld = context();
+        withhold_context_variables(&state.user_core.get_user_settings(user_id).unwrap(), &mut withheld);
+        let mut remaining: Vec<&str> = withheld.keys().map(String::as_str).collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["recent_contacts", "recent_conversation", "timezone"]);
+    }
+
+    #[tokio::test]
+    async fn cancel_hangs_up_only_an_active_call() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "hangup@example.com");
+        let terminated = std::sync::Mutex::new(Vec::new());
+        let terminate = |call: ActiveNotificationCall| {
+            terminated.lock().unwrap().push(call.call_sid);
+            async { Ok(true) }
+        };
+
+        assert_eq!(cancel_notification_call_with(&state, user_id, terminate).await, Ok(false));
+        assert!(terminated.lock().unwrap().is_empty());
+
+        state.active_notification_calls.insert(user_id, ActiveNotificationCall {
+            call_sid: "CA-ringing".to_string(),
+            conversation_id: Some("conv_1".to_string()),
+            uses_user_twilio: false,
+            started_at: chrono::Utc::now().timestamp() - 20,
+        });
+        assert_eq!(cancel_notification_call_with(&state, user_id, terminate).await, Ok(true));
+        assert_eq!(*terminated.lock().unwrap(), vec!["CA-ringing"]);
+        assert!(state.active_notification_calls.get(&user_id).is_none());
+
+        // A leftover from a missed end-of-call webhook isn't worth a Twilio request
+        state.active_notification_calls.insert(user_id, ActiveNotificationCall {
+            call_sid: "CA-stale".to_string(),
+            conversation_id: None,
+            uses_user_twilio: false,
+            started_at: chrono::Utc::now().timestamp() - 2 * MAX_NOTIFICATION_CALL_SECS,
+        });
+        assert_eq!(cancel_notification_call_with(&state, user_id, terminate).await, Ok(false));
+        assert_eq!(terminated.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn call_that_already_ended_is_not_an_error() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "ended@example.com");
+        state.active_notification_calls.insert(user_id, ActiveNotificationCall {
+            call_sid: "CA-done".to_string(),
+            conversation_id: None,
+            uses_user_twilio: true,
+            started_at: chrono::Utc::now().timestamp(),
+        });
+        let result = cancel_notification_call_with(&state, user_id, |_| async { Ok(false) }).await;
+        assert_eq!(result, Ok(false));
+        assert!(state.active_notification_calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn briefing_mentions_every_connected_source() {
+        let sources = BriefingSources {
+            agenda: Some("You have 1 event today. First is Standup at 09:00.".to_string()),
+            unread_emails: Some(4),
+            important_emails: Some(vec!["Boss: Budget review".to_string()]),
+            due_tasks: Some(vec!["Renew passport".to_string()]),
+        };
+        // Stands in for the model, which is told to keep every listed source
+        let (briefing, included) = briefing_response(&sources, |facts| async move { facts }).await;
+        assert_eq!(included, vec!["calendar", "email", "tasks"]);
+        assert!(briefing.contains("Standup at 09:00"));
+        assert!(briefing.contains("4 unread recent emails") && briefing.contains("Boss: Budget review"));
+        assert!(briefing.contains("Renew passport"));
+    }
+
+    #[tokio::test]
+    async fn briefing_leaves_out_sources_that_are_not_connected() {
+        let email_only = BriefingSources { unread_emails: Some(0), ..Default::default() };
+        let (briefing, included) = briefing_response(&email_only, |facts| async move { facts }).await;
+        assert_eq!(included, vec!["email"]);
+        assert_eq!(briefing, "Email: 0 unread recent emails.");
+
+        let composed = std::sync::atomic::AtomicBool::new(false);
+        let (briefing, included) = briefing_response(&BriefingSources::default(), |facts| {
+            composed.store(true, std::sync::atomic::Ordering::SeqCst);
+            async move { facts }
+        }).await;
+        assert!(included.is_empty());
+        assert!(briefing.starts_with("I couldn't put a briefing together"));
+        assert!(!composed.load(std::sync::atomic::Ordering::SeqCst), "nothing to compose without sources");
+    }
+}
@@ -28,6 +28,51 @@ pub struct WebhookData {
     pub metadata: Metadata,
     pub analysis: Analysis,
     pub conversation_initiation_client_data: ConversationInitiationClientDataWebhook,
+    #[serde(default)]
+    pub transcript: Vec<TranscriptTurn>,
+}
+
+/// One turn of the call transcript. Turns that were only tool calls have no message.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TranscriptTurn {
+    pub role: String, // "user" or "agent"
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub time_in_call_secs: Option<f64>,
+}
+
+/// History rows for the spoken turns of a call, timed from the call start. Empty and unknown turns are
+/// skipped so a partial transcript still gives whatever was said.
+pub fn transcript_history(
+    user_id: i32,
+    conversation_id: &str,
+    call_start: i32,
+    call_end: i32,
+    transcript: &[TranscriptTurn],
+) -> Vec<crate::models::user_models::NewMessageHistory> {
+    transcript
+        .iter()
+        .filter_map(|turn| {
+            let role = match turn.role.as_str() {
+                "user" => "user",
+                "agent" => "assistant",
+                _ => return None,
+            };
+            let message = turn.message.as_deref().map(str::trim).filter(|m| !m.is_empty())?;
+            let offset = turn.time_in_call_secs.unwrap_or(0.0).max(0.0) as i32;
+            Some(crate::models::user_models::NewMessageHistory {
+                user_id,
+                conversation_id: conversation_id.to_string(),
+                role: role.to_string(),
+                encrypted_content: message.to_string(), // encrypted by create_message_history
+                tool_name: None,
+                tool_call_id: None,
+                tool_calls_json: None,
+                created_at: (call_start + offset).min(call_end),
+            })
+        })
+        .collect()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -161,10 +206,12 @@ pub async fn validate_elevenlabs_hmac(
     // Verify signature
     if signature != &expected_hash {
         tracing::info!("❌ HMAC signature validation failed");
+        crate::utils::webhook_status::record_rejected("elevenlabs_webhook");
         return Err(StatusCode::UNAUTHORIZED);
     }
 
     tracing::info!("✅ HMAC validation successful");
+    crate::utils::webhook_status::record_validated("elevenlabs_webhook");
     
     // Reconstruct request and pass to next handler
     let request = Request::from_parts(parts, Body::from(body_bytes));
@@ -207,6 +254,7 @@ pub async fn elevenlabs_webhook(
     println!("Call Successful: {}", call_successful);
     let call_summary = payload.data.analysis.transcript_summary;
     println!("Transcript Summary: {}", call_summary);
+    let transcript = payload.data.transcript;
     let user_id: Option<String> = payload.data.conversation_initiation_client_data.dynamic_variables.user_id;
     println!("User ID: {:?}", user_id);
     // Your webhook processing logic here
@@ -237,6 +285,9 @@ pub async fn elevenlabs_webhook(
         }
     };
 
+    // The call is over, nothing left to cancel
+    state.active_notification_calls.remove_if(&user_id, |_, call| call.conversation_id.as_deref() == Some(conversation_id.as_str()));
+
     // Fetch user from user_repository
     let user = match state.user_core.find_by_id(user_id) {
         Ok(Some(user)) => user,
@@ -341,6 +392,17 @@ pub async fn elevenlabs_webhook(
                 ));
             }
 
+            // What was said, so the next call's recent_conversation has the actual turns
+            let turns = transcript_history(user_id, &conversation_id, start_epoch, end_epoch, &transcript);
+            if turns.is_empty() {
+                tracing::debug!("No spoken turns in the transcript of conversation {}", conversation_id);
+            }
+            for turn in &turns {
+                if let Err(e) = state.user_repository.create_message_history(turn) {
+                    error!("Failed to store transcript turn for conversation {}: {}", conversation_id, e);
+                }
+            }
+
             if let Err(e) = state.user_repository.create_message_history(&call_end) {
                 error!("Failed to create message history: {}", e);
                 return Err((
@@ -376,3 +438,66 @@ pub async fn elevenlabs_webhook(
     })))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, set_test_encryption_key, test_state};
+
+    #[tokio::test]
+    async fn call_transcript_turns_become_history_rows() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "transcript@example.com");
+        state.user_repository.log_usage(
+            user_id, Some("CA-transcript".to_string()), "call".to_string(),
+            None, None, None, None, Some("ongoing".to_string()), None, None,
+        ).unwrap();
+        let payload = json!({
+            "type": "post_call_transcription",
+            "event_timestamp": 1_792_000_000u64,
+            "data": {
+                "conversation_id": "conv_transcript",
+                "status": "done",
+                "metadata": {"call_duration_secs": 30},
+                "analysis": {"call_successful": "success", "transcript_summary": "Asked about the dentist."},
+                "conversation_initiation_client_data": {"dynamic_variables": {"user_id": user_id}},
+                "transcript": [
+                    {"role": "user", "message": "Is my dentist appointment tomorrow?", "time_in_call_secs": 2.4},
+                    {"role": "agent", "message": "Yes, at 15:30.", "time_in_call_secs": 5.0},
+                    // A tool call turn and a silence, neither was said
+                    {"role": "agent", "message": null, "time_in_call_secs": 6.0},
+                    {"role": "user", "message": "   ", "time_in_call_secs": 8.0},
+                    {"role": "moderator", "message": "internal note", "time_in_call_secs": 9.0},
+                ],
+            },
+        });
+
+        elevenlabs_webhook(State(state.clone()), axum::extract::Json(payload)).await.unwrap();
+
+        let mut history = state.user_repository.get_conversation_history(user_id, 10, false).unwrap();
+        history.reverse();
+        let turns: Vec<(&str, &str)> = history.iter()
+            .map(|m| (m.role.as_str(), m.encrypted_content.as_str()))
+            .collect();
+        assert_eq!(turns, vec![
+            ("user", "Is my dentist appointment tomorrow?"),
+            ("assistant", "Yes, at 15:30."),
+            ("system", "[CALL_SUMMARY] Asked about the dentist."),
+        ]);
+        assert!(history.iter().all(|m| m.conversation_id == "conv_transcript"));
+        assert!(history[0].created_at < history[1].created_at);
+    }
+
+    #[test]
+    fn missing_transcript_gives_no_turns() {
+        let data: WebhookData = serde_json::from_value(json!({
+            "conversation_id": "conv_partial",
+            "status": "done",
+            "metadata": {"call_duration_secs": 3},
+            "analysis": {"call_successful": "failure", "transcript_summary": ""},
+            "conversation_initiation_client_data": {"dynamic_variables": {"user_id": "7"}},
+        })).unwrap();
+        assert!(transcript_history(7, &data.conversation_id, 100, 103, &data.transcript).is_empty());
+    }
+}
@@ -103,6 +103,57 @@ pub async fn handle_textbee_sms(
 
 
 
+/// Inbound messages a user's own Twilio number may pass on per minute, INBOUND_SMS_PER_MINUTE overrides.
+/// A person typing fast stays well under it, an auto-reply loop between two numbers doesn't.
+fn inbound_sms_quota() -> governor::Quota {
+    let per_minute = std::env::var("INBOUND_SMS_PER_MINUTE")
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .and_then(std::num::NonZeroU32::new)
+        .unwrap_or(std::num::NonZeroU32::new(20).unwrap());
+    governor::Quota::per_minute(per_minute)
+}
+
+/// Whether another inbound message for the user fits in the per-minute budget
+pub fn allow_inbound_sms(state: &Arc<AppState>, user_id: i32) -> bool {
+    let limiter_key = user_id.to_string();
+    let entry = state.inbound_sms_limiter
+        .entry(limiter_key.clone())
+        .or_insert_with(|| governor::RateLimiter::keyed(inbound_sms_quota()));
+    entry.value().check_key(&limiter_key).is_ok()
+}
+
+/// Whether the user may get another free help menu this hour
+fn allow_help_reply(state: &Arc<AppState>, user_id: i32) -> bool {
+    let limiter_key = user_id.to_string();
+    let per_hour = std::num::NonZeroU32::new(crate::utils::sms_help::HELP_REPLIES_PER_HOUR).unwrap();
+    let entry = state.help_reply_limiter
+        .entry(limiter_key.clone())
+        .or_insert_with(|| governor::RateLimiter::keyed(governor::Quota::per_hour(per_hour)));
+    entry.value().check_key(&limiter_key).is_ok()
+}
+
+/// Wrapper for messages arriving on a user's own Twilio number. Every message costs an LLM call and
+/// credits, so a flood (usually a loop with another auto-responder) is dropped past the per-minute cap.
+pub async fn handle_user_twilio_sms(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(user_id): axum::extract::Path<i32>,
+    Form(payload): Form<TwilioWebhookPayload>,
+) -> (StatusCode, [(axum::http::HeaderName, &'static str); 1], axum::Json<TwilioResponse>) {
+    if !allow_inbound_sms(&state, user_id) {
+        tracing::warn!("Dropping inbound SMS {} for user {}: over the per-minute inbound limit", payload.message_sid, user_id);
+        crate::utils::metrics::record_inbound_sms_throttled();
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(TwilioResponse {
+                message: "Too many messages, slow down".to_string(),
+            })
+        );
+    }
+    handle_incoming_sms(State(state), Form(payload)).await
+}
+
 // New wrapper handler for the regular SMS endpoint
 pub async fn handle_regular_sms(
     State(state): State<Arc<AppState>>,
@@ -150,6 +201,24 @@ pub async fn handle_regular_sms(
     handle_incoming_sms(State(state), Form(payload)).await
 }
 
+/// Whether an inbound SMS is the cancel command, "c" or "cancel" in any case
+pub fn is_cancel_command(body: &str) -> bool {
+    let command = body.trim().trim_end_matches(['.', '!']).to_lowercase();
+    command == "c" || command == "cancel"
+}
+
+/// Cancels everything the user still has queued and returns the SMS reply saying how it went
+async fn cancel_by_sms(state: &Arc<AppState>, user_id: i32) -> Result<String, Box<dyn std::error::Error>> {
+    let outcome = crate::tool_call_utils::utils::cancel_pending_message(state, user_id).await?;
+    Ok(if !outcome.cancelled.is_empty() {
+        "The message got discarded.".to_string()
+    } else if !outcome.too_late.is_empty() {
+        format!("Too late to cancel, already sent: {}.", outcome.too_late.join(", "))
+    } else {
+        "Couldn't find a message to cancel".to_string()
+    })
+}
+
 // Original handler becomes internal and is used by both routes
 pub async fn handle_incoming_sms(
     State(state): State<Arc<AppState>>,
@@ -186,8 +255,11 @@ pub async fn handle_incoming_sms(
         }
     }
 
-    // Process SMS in the background
+    // Process SMS in the background, once any carrier-split segments have been joined
     tokio::spawn(async move {
+        let Some(payload) = state.sms_segments.collect(payload).await else {
+            return;
+        };
         let result = process_sms(&state, payload.clone(), false).await;
         if result.0 != StatusCode::OK {
             tracing::error!("Background SMS processing failed with status: {:?}", result.0);
@@ -257,29 +329,11 @@ pub async fn process_sms(
         }
     }
 
-    // Check if user has sufficient credits before processing the message
-    if let Err(e) = crate::utils::usage::check_user_credits(&state, &user, "message", None).await {
-        tracing::warn!("User {} has insufficient credits: {}", user.id, e);
-        return (
-            StatusCode::PAYMENT_REQUIRED,
-            [(axum::http::header::CONTENT_TYPE, "application/json")],
-            axum::Json(TwilioResponse {
-                message: e,
-            })
-        );
-    }
-    tracing::info!("Found user with ID: {} for phone number: {}", user.id, payload.from);
-
-    // Handle 'cancel' message specially
-    if payload.body.trim().to_lowercase() == "c" {
-        match crate::tool_call_utils::utils::cancel_pending_message(state, user.id).await {
-            Ok(canceled) => {
-                let response_msg = if canceled {
-                    "The message got discarded.".to_string()
-                } else {
-                    "Couldn't find a message to cancel".to_string()
-                };
-
+    // "c" or "cancel" drops whatever is still queued, e.g. an email from a call that has ended.
+    // Checked before credits so a user who ran out can still stop a send.
+    if is_cancel_command(&payload.body) {
+        match cancel_by_sms(state, user.id).await {
+            Ok(response_msg) => {
                 let state_clone = state.clone();
                 let user_clone = user.clone();
                 let response_msg_clone = response_msg.clone();
@@ -356,6 +410,93 @@ pub async fn process_sms(
             }
         }
     }
+
+    // Help menu is free and answered without the assistant, so it works even when out of credits
+    if crate::utils::sms_help::is_help_command(&payload.body) {
+        // Replies cost us an SMS but not the user, so repeats past the hourly cap are dropped
+        if !allow_help_reply(&state, user.id) {
+            tracing::debug!("Help menu rate limited for user {}", user.id);
+            return (
+                StatusCode::OK,
+                [(axum::http::header::CONTENT_TYPE, "application/json")],
+                axum::Json(TwilioResponse {
+                    message: "Help menu recently sent".to_string(),
+                })
+            );
+        }
+        let integrations = state.user_repository.connected_integrations(user.id).unwrap_or_else(|e| {
+            tracing::error!("Failed to check connected integrations for help menu: {}", e);
+            Default::default()
+        });
+        let has_full_access = user.sub_tier.as_deref() == Some("tier 2") || user.discount;
+        let menu = crate::utils::sms_help::help_menu(&integrations, has_full_access);
+
+        let state_clone = state.clone();
+        let user_clone = user.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::api::twilio_utils::send_conversation_message(
+                &state_clone,
+                &menu,
+                None,
+                &user_clone
+            ).await {
+                tracing::error!("Failed to send help menu: {}", e);
+            }
+        });
+
+        return (
+            StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(TwilioResponse {
+                message: "Help menu sent".to_string(),
+            })
+        );
+    }
+
+    // Check if user has sufficient credits before processing the message
+    if let Err(e) = crate::utils::usage::check_user_credits(&state, &user, "message", None).await {
+        tracing::warn!("User {} has insufficient credits: {}", user.id, e);
+        return (
+            StatusCode::PAYMENT_REQUIRED,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(TwilioResponse {
+                message: e,
+            })
+        );
+    }
+    tracing::info!("Found user with ID: {} for phone number: {}", user.id, payload.from);
+
+    // "Reply to the last email: ..." goes straight to the respond-to-email flow
+    if let Some(args) = crate::tool_call_utils::email::last_email_reply_args(state, user.id, &payload.body) {
+        let Some(args) = args else {
+            let msg = "I don't know which email to reply to yet. Ask me about your emails first, then reply.";
+            if let Err(e) = crate::api::twilio_utils::send_conversation_message(state, msg, None, &user).await {
+                tracing::error!("Failed to send last email reply error: {}", e);
+            }
+            return (
+                StatusCode::OK,
+                [(axum::http::header::CONTENT_TYPE, "application/json")],
+                axum::Json(TwilioResponse {
+                    message: msg.to_string(),
+                })
+            );
+        };
+        let result = crate::tool_call_utils::email::handle_respond_to_email(state, user.id, &args, &user).await
+            .map_err(|e| e.to_string());
+        return match result {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::error!("Failed to queue reply to last email: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [(axum::http::header::CONTENT_TYPE, "application/json")],
+                    axum::Json(TwilioResponse {
+                        message: "Failed to queue the email reply".to_string(),
+                    })
+                )
+            }
+        };
+    }
     
     // Log media information for admin user
     if user.id == 1 {
@@ -459,10 +600,17 @@ pub async fn process_sms(
         minutes.abs()
     );
 
+    let reply_language = state.sms_languages.reply_language(
+        user.id,
+        &user_settings.agent_language,
+        &payload.body,
+        user_settings.detect_sms_language.unwrap_or(false),
+    );
+
     // Start with the system message
     let mut chat_messages: Vec<ChatMessage> = vec![ChatMessage {
         role: "system".to_string(),
-        content: chat_completion::Content::Text(format!("You are a direct and efficient AI assistant named lightfriend. The current date is {}. You must provide extremely concise responses (max 400 characters) while being accurate and helpful. Since users pay per message, always provide all available information immediately without asking follow-up questions unless confirming details for actions that involve sending information or making changes. Always use all tools immediately that you think will be needed to complete the user's query and base your response to those responses. IMPORTANT: For calendar events, you must return the exact output from the calendar tool without any modifications, additional text, or formatting. Never add bullet points, markdown formatting (like **, -, #), or any other special characters.
+        content: chat_completion::Content::Text(format!("You are a direct and efficient AI assistant named {}. The current date is {}. You must provide extremely concise responses (max 400 characters) while being accurate and helpful. Since users pay per message, always provide all available information immediately without asking follow-up questions unless confirming details for actions that involve sending information or making changes. Always use all tools immediately that you think will be needed to complete the user's query and base your response to those responses. IMPORTANT: For calendar events, you must return the exact output from the calendar tool without any modifications, additional text, or formatting. Never add bullet points, markdown formatting (like **, -, #), or any other special characters.
 
 ### Tool Usage Guidelines:
 - Provide all relevant details in the response immediately. 
@@ -487,7 +635,7 @@ pub async fn process_sms(
   - 'This week': Use remaining days of current week
   - 'Next week': Use Monday to Sunday of next week
 
-Never use markdown, HTML, or any special formatting characters in responses. Return all information in plain text only. User information: {}. Always use tools to fetch the latest information before answering.", formatted_time, timezone_str, offset, user_given_info)),
+Never use markdown, HTML, or any special formatting characters in responses. Return all information in plain text only. Always reply in {}. User information: {}. Always use tools to fetch the latest information before answering.", crate::utils::branding::assistant_name(), formatted_time, timezone_str, offset, crate::utils::language_detection::language_name(&reply_language), user_given_info)),
         tool_calls: None,
         tool_call_id: None,
     }];
@@ -811,6 +959,7 @@ Never use markdown, HTML, or any special formatting characters in responses. Ret
                     Some(args) => args,
                     None => continue,
                 };
+                let _tool_timer = crate::utils::metrics::ToolTimer::start("sms", name);
                 if name == "ask_perplexity" {
                     tracing::debug!("Executing ask_perplexity tool call");
                     #[derive(Deserialize, Serialize)]
@@ -843,7 +992,8 @@ Never use markdown, HTML, or any special formatting characters in responses. Ret
                     #[derive(Deserialize, Serialize)]
                     struct WeatherQuestion {
                         location: String,
-                        units: String,
+                        #[serde(default)]
+                        units: Option<String>,
                     }
                     let c: WeatherQuestion = match serde_json::from_str(arguments) {
                         Ok(q) => q,
@@ -853,7 +1003,7 @@ Never use markdown, HTML, or any special formatting characters in responses. Ret
                         }
                     };
                     let location= c.location;
-                    let units= c.units;
+                    let units = crate::utils::tool_exec::resolve_units(&state, user.id, c.units.as_deref());
 
                     match crate::utils::tool_exec::get_weather(&state, &location, &units, user.id).await {
                         Ok(answer) => {
@@ -880,7 +1030,8 @@ Never use markdown, HTML, or any special formatting characters in responses. Ret
                         }
                     };
                     let query = c.query;
-                    match crate::utils::tool_exec::handle_firecrawl_search(query, 5).await {
+                    let options = crate::utils::tool_exec::firecrawl_options(state, user.id);
+                    match crate::utils::tool_exec::handle_firecrawl_search(query, &options).await {
                         Ok(answer) => {
                             tracing::debug!("Successfully received fire crawl answer");
                             tool_answers.insert(tool_call_id, answer);
@@ -941,6 +1092,7 @@ Never use markdown, HTML, or any special formatting characters in responses. Ret
 
                     // First get the email ID
                     let email_id = crate::tool_call_utils::email::handle_fetch_specific_email(&state, user.id, &query.query).await;
+                    crate::tool_call_utils::email::remember_surfaced_email(&state, user.id, &email_id);
                     let auth_user = crate::handlers::auth_middleware::AuthUser {
                         user_id: user.id,
                         is_admin: false,
@@ -1663,3 +1815,63 @@ Never use markdown, HTML, or any special formatting characters in responses. Ret
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(sid: &str) -> TwilioWebhookPayload {
+        TwilioWebhookPayload {
+            from: "+15555550123".to_string(),
+            to: "+15555550999".to_string(),
+            body: "hello".to_string(),
+            num_media: None,
+            media_url0: None,
+            media_content_type0: None,
+            message_sid: sid.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_by_sms_drops_the_email_queued_during_a_call() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "hungup@example.com");
+        let (_, mut cancel_rx) = crate::tool_call_utils::utils::register_pending_action(
+            &state,
+            user_id,
+            "email",
+            "boss@example.com",
+            "Email to boss@example.com",
+            std::time::Duration::from_secs(60),
+        ).await;
+
+        for body in ["Cancel!", " c ", "cancel."] {
+            assert!(is_cancel_command(body), "{body:?}");
+        }
+        assert!(!is_cancel_command("cancel my dentist appointment"));
+
+        assert_eq!(cancel_by_sms(&state, user_id).await.unwrap(), "The message got discarded.");
+        assert!(cancel_rx.try_recv().is_ok(), "the queued send was not told to stop");
+        assert!(crate::tool_call_utils::utils::list_pending_actions(&state, user_id).await.is_empty());
+        assert_eq!(cancel_by_sms(&state, user_id).await.unwrap(), "Couldn't find a message to cancel");
+    }
+
+    #[tokio::test]
+    async fn inbound_sms_past_the_per_minute_cap_is_throttled() {
+        let state = crate::test_support::test_state();
+        // A legitimate burst fits in the default budget of 20 per minute
+        for _ in 0..20 {
+            assert!(allow_inbound_sms(&state, 7));
+        }
+        assert!(!allow_inbound_sms(&state, 7));
+        // Other users' numbers have their own budget
+        assert!(allow_inbound_sms(&state, 8));
+
+        let (status, _, _) = handle_user_twilio_sms(
+            State(state.clone()),
+            axum::extract::Path(7),
+            Form(payload("SM_over_cap")),
+        ).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+}
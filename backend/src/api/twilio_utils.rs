@@ -442,10 +442,12 @@ pub async fn validate_twilio_signature(
     // Compare signatures
     if result != signature {
         tracing::error!("❌ Signature validation failed");
+        crate::utils::webhook_status::record_rejected("twilio_sms");
         return Err(StatusCode::UNAUTHORIZED);
     }
 
     tracing::info!("✅ Signature validation successful");
+    crate::utils::webhook_status::record_validated("twilio_sms");
 
     // Rebuild request and pass to next handler
     let request = Request::from_parts(parts, Body::from(params_str));
@@ -495,30 +497,6 @@ pub async fn delete_twilio_message_media(
 use serde_json::json;
 use tokio::spawn;
 
-async fn send_textbee_sms(device_id: String, api_key: String, recipient: String, body: String) -> Result<(), Box<dyn Error>> {
-    let url = format!("https://api.textbee.dev/api/v1/gateway/devices/{}/send-sms", device_id);
-
-    let client = Client::new();
-
-    let data = json!({
-        "recipients": [recipient],
-        "message": body
-    });
-
-    client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("x-api-key", api_key)
-        .json(&data)
-        .send()
-        .await?
-        .error_for_status()?;
-
-    tracing::debug!("Successfully sent conversation message via TextBee to: {}", recipient);
-
-    Ok(())
-}
-
 use std::time::Duration;
 use tokio::time::sleep;
 
@@ -574,6 +552,44 @@ pub async fn delete_twilio_message(
 }
 
 
+/// Legacy per-country sender env vars, still honoured when TWILIO_FROM_NUMBERS doesn't list the country
+const LEGACY_SENDER_ENV_VARS: [(&str, &str); 5] = [
+    ("CA", "CAN_PHONE"),
+    ("FI", "FIN_PHONE"),
+    ("NL", "NL_PHONE"),
+    ("GB", "GB_PHONE"),
+    ("AU", "AUS_PHONE"),
+];
+
+/// Every number Lightfriend sends from, so tools can refuse to message the service itself
+pub fn service_numbers() -> Vec<String> {
+    let mut numbers: Vec<String> = backend::parse_from_numbers(&env::var("TWILIO_FROM_NUMBERS").unwrap_or_default())
+        .into_values()
+        .collect();
+    numbers.extend(LEGACY_SENDER_ENV_VARS.iter().filter_map(|(_, var)| env::var(var).ok()));
+    numbers.extend(env::var("USA_PHONE").ok());
+    numbers
+}
+
+/// Picks the sender number for a recipient: the TWILIO_FROM_NUMBERS entry for their country,
+/// then the legacy per-country env var, then the TWILIO_FROM_NUMBERS "DEFAULT" entry.
+/// `country` is the stored phone country; for "Other" it's worked out from the number itself.
+pub fn sender_for_recipient(country: &str, phone_number: &str) -> Option<String> {
+    let from_numbers = backend::parse_from_numbers(&env::var("TWILIO_FROM_NUMBERS").unwrap_or_default());
+    let country = if country == "Other" {
+        backend::country_for_number(phone_number).unwrap_or(country)
+    } else {
+        country
+    };
+    from_numbers.get(&country.to_uppercase()).cloned()
+        .or_else(|| {
+            LEGACY_SENDER_ENV_VARS.iter()
+                .find(|(code, _)| *code == country)
+                .and_then(|(_, var)| env::var(var).ok())
+        })
+        .or_else(|| from_numbers.get("DEFAULT").cloned())
+}
+
 pub async fn send_conversation_message(
     state: &Arc<AppState>,
     body: &str,
@@ -607,17 +623,11 @@ pub async fn send_conversation_message(
         if media_sid.is_none() {
             // Use TextBee for text-only messages
             let recipient = user.preferred_number.clone().unwrap();
-            let body_clone = body.to_string();
-            let device_id_clone = device_id.clone();
-            let api_key_clone = api_key.clone();
-
-            spawn(async move {
-                if let Err(e) = send_textbee_sms(device_id_clone, api_key_clone, recipient, body_clone).await {
-                    tracing::error!("Failed to send TextBee SMS: {}", e);
-                }
-            });
-
-            return Ok("".to_string());
+            match crate::utils::textbee::send_sms(state, user.id, &device_id, &api_key, &recipient, body).await {
+                Ok(()) => return Ok("".to_string()),
+                // Offline device, fall through to Twilio so the message isn't lost
+                Err(e) => tracing::error!("Failed to send TextBee SMS for user {}: {}", user.id, e),
+            }
         }
         // If media is present, fall back to Twilio
     }
@@ -662,17 +672,14 @@ pub async fn send_conversation_message(
     if preferred.is_empty() {
         update_preferred = true;
         if let Some(c) = country.clone() {
-            match c.as_str() {
-                "US" => {
-                    use_messaging_service = true;
-                    update_preferred = false;
+            if c == "US" {
+                use_messaging_service = true;
+                update_preferred = false;
+            } else {
+                match sender_for_recipient(&c, &user.phone_number) {
+                    Some(number) => from_number = number,
+                    None => tracing::info!("Using empty from_number for unsupported country: {}", c),
                 }
-                "CA" => from_number = env::var("CAN_PHONE").expect("CAN_PHONE not set"),
-                "FI" => from_number = env::var("FIN_PHONE").expect("FIN_PHONE not set"),
-                "NL" => from_number = env::var("NL_PHONE").expect("NL_PHONE not set"),
-                "GB" => from_number = env::var("GB_PHONE").expect("GB_PHONE not set"),
-                "AU" => from_number = env::var("AUS_PHONE").expect("AUS_PHONE not set"),
-                _ => tracing::info!("Using empty from_number for unsupported country: {}", c),
             }
         }
     }
@@ -716,10 +723,12 @@ pub async fn send_conversation_message(
         .basic_auth(&account_sid, Some(&auth_token))
         .form(&form_data)
         .send()
-        .await?;
+        .await
+        .inspect_err(|_| crate::utils::metrics::record_twilio_message(false))?;
 
 
     let status = resp.status();
+    crate::utils::metrics::record_twilio_message(status.is_success());
     if !status.is_success() {
         let text = resp.text().await.unwrap_or_default();
         tracing::error!("Twilio send error: status {}, body: {}", status, text);
@@ -1 +1,176 @@
 // Error types module
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// The error body every converted handler returns:
+/// `{"code": "bad_request", "message": "...", "details": ..., "error": "..."}`.
+/// `error` repeats `message` for clients that still read the old `{"error": "..."}` shape.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorEnvelope {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    pub error: String,
+}
+
+/// Handler error with a status and the standard envelope
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+/// "bad_request" from 400 Bad Request, "error" for statuses without a reason phrase
+fn status_code_name(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .map(|reason| reason.to_lowercase().replace([' ', '-'], "_").replace('\'', ""))
+        .unwrap_or_else(|| "error".to_string())
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: status_code_name(status),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// Replaces the status-derived code with a more specific one, e.g. "email_taken"
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = code.into();
+        self
+    }
+
+    pub fn with_details(mut self, details: impl Into<Value>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn envelope(&self) -> ErrorEnvelope {
+        ErrorEnvelope {
+            code: self.code.clone(),
+            message: self.message.clone(),
+            details: self.details.clone(),
+            error: self.message.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.envelope())).into_response()
+    }
+}
+
+/// For handlers that haven't moved to `ApiError` yet: `{"error": ..}` becomes the message and
+/// anything else in the body ends up in `details`
+impl From<(StatusCode, Json<Value>)> for ApiError {
+    fn from((status, Json(body)): (StatusCode, Json<Value>)) -> Self {
+        let message = body
+            .get("error")
+            .or_else(|| body.get("message"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or("Error").to_string());
+        let mut error = ApiError::new(status, message);
+        if let Value::Object(mut fields) = body {
+            fields.remove("error");
+            if let Some(details) = fields.remove("details") {
+                error.details = Some(details);
+            } else if !fields.is_empty() {
+                error.details = Some(Value::Object(fields));
+            }
+        }
+        error
+    }
+}
+
+/// Lets converted helpers be called from handlers that still return the tuple
+impl From<ApiError> for (StatusCode, Json<Value>) {
+    fn from(error: ApiError) -> Self {
+        (error.status, Json(json!(error.envelope())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(error: ApiError) -> Value {
+        serde_json::to_value(error.envelope()).unwrap()
+    }
+
+    #[test]
+    fn plain_error_has_status_code_and_old_error_field() {
+        assert_eq!(
+            body(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "Bad input")),
+            json!({"code": "unprocessable_entity", "message": "Bad input", "error": "Bad input"})
+        );
+        assert_eq!(ApiError::new(StatusCode::from_u16(599).unwrap(), "odd").code, "error");
+    }
+
+    #[test]
+    fn code_and_details_are_serialized() {
+        let error = ApiError::bad_request("Email is taken")
+            .with_code("email_taken")
+            .with_details(json!({"field": "email"}));
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body(error),
+            json!({"code": "email_taken", "message": "Email is taken", "details": {"field": "email"}, "error": "Email is taken"})
+        );
+    }
+
+    #[test]
+    fn tuple_errors_keep_their_message_and_extra_fields() {
+        let error = ApiError::from((StatusCode::TOO_MANY_REQUESTS, Json(json!({"error": "Slow down", "retry_after_seconds": 30}))));
+        assert_eq!(error.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.code, "too_many_requests");
+        assert_eq!(error.message, "Slow down");
+        assert_eq!(error.details, Some(json!({"retry_after_seconds": 30})));
+
+        // An explicit details field is used as is, a body without a message gets the reason phrase
+        let error = ApiError::from((StatusCode::NOT_FOUND, Json(json!({"details": [1, 2]}))));
+        assert_eq!((error.message.as_str(), error.details), ("Not Found", Some(json!([1, 2]))));
+    }
+
+    #[test]
+    fn converting_back_to_a_tuple_keeps_the_envelope() {
+        let (status, Json(body)) = <(StatusCode, Json<Value>)>::from(ApiError::not_found("Gone"));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["error"], "Gone");
+    }
+}
@@ -34,6 +34,13 @@ pub struct EmailBroadcastRequest {
     pub message: String,
 }
 
+#[derive(Deserialize)]
+pub struct BroadcastPreviewRequest {
+    pub subject: Option<String>,
+    pub message: String,
+    pub user_id: Option<i32>, // sample recipient, the first user when missing
+}
+
 #[derive(Serialize)]
 pub struct UsageLogResponse {
     id: i32,
@@ -50,6 +57,24 @@ pub struct UsageLogResponse {
     zero_credits_timestamp: Option<i32>,
 }
 
+#[derive(Deserialize)]
+pub struct FailedNotificationsQuery {
+    pub user_id: Option<i32>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct FailedNotificationResponse {
+    id: i32,
+    user_id: i32,
+    notification_type: String,
+    channel: String,
+    preview: String, // start of the content only, the full text stays encrypted
+    error: String,
+    created_at: i32,
+    retried_at: Option<i32>,
+}
+
 use crate::AppState;
 
 
@@ -197,6 +222,113 @@ fn wrap_text(text: &str, max_width: usize) -> String {
     result
 }
 
+/// Sends one user their copy of an email broadcast. Ok(false) when the user is skipped
+/// (notifications off or no usable address), those aren't recorded as failures.
+async fn send_broadcast_email(
+    state: &Arc<AppState>,
+    user: &crate::models::user_models::User,
+    subject: &str,
+    message: &str,
+) -> Result<bool, String> {
+    let user_settings = state.user_core.get_user_settings(user.id)
+        .map_err(|e| format!("Failed to get settings for {}: {}", user.email, e))?;
+
+    if !user_settings.notify {
+        tracing::info!("skipping user since they don't have notify on");
+        return Ok(false);
+    }
+
+    // Skip users with invalid or empty email addresses
+    if user.email.is_empty() || !user.email.contains('@') || !user.email.contains('.') {
+        tracing::warn!("Skipping invalid email address: {}", user.email);
+        return Ok(false);
+    }
+
+    // Prepare the unsubscribe link
+    let encoded_email = urlencoding::encode(&user.email);
+    let server_url = std::env::var("SERVER_URL").expect("SERVER_URL not set");
+    let unsubscribe_link = format!("{}/api/unsubscribe?email={}", server_url, encoded_email);
+
+    // Prepare plain text body with unsubscribe (link inline now)
+    let plain_body = format!(
+        "{}\n\nTo unsubscribe from these feature updates/fixes, click here: {}",
+        crate::utils::broadcast_template::render_for_user(message, user), unsubscribe_link
+    );
+    let wrapped_body = wrap_text(&plain_body, 72);
+    // Convert to CRLF line endings for email compliance
+    let crlf_body = wrapped_body.replace("\n", "\r\n");
+
+    // Prepare the email request for the send_email handler
+    let email_request = crate::handlers::imap_handlers::SendEmailRequest {
+        to: user.email.clone(),
+        subject: crate::utils::broadcast_template::render_for_user(subject, user),
+        body: crlf_body,
+        account_id: None,
+    };
+
+    let auth_user = crate::handlers::auth_middleware::AuthUser { user_id: 1, is_admin: false }; // Hardcode user_id to 1
+    // Call the existing send_email handler
+    crate::handlers::imap_handlers::send_email(
+        State(state.clone()),
+        auth_user,
+        Json(email_request)
+    ).await
+        .map(|_| true)
+        .map_err(|(_status, err)| format!("Failed to send to {}: {:?}", user.email, err))
+}
+
+/// Sends the broadcast to each user and records the outcome per recipient
+async fn deliver_broadcast_email(
+    state: &Arc<AppState>,
+    broadcast_id: i32,
+    users: Vec<crate::models::user_models::User>,
+    subject: &str,
+    message: &str,
+) {
+    let mut success_count = 0;
+    let mut failed_count = 0;
+    let mut error_details = Vec::new();
+
+    for user in users {
+        let result = send_broadcast_email(state, &user, subject, message).await;
+        let error = match result {
+            Ok(false) => {
+                // Only matters for a retry, a first send has no row yet
+                if let Err(e) = state.user_repository.skip_broadcast_recipient(broadcast_id, user.id) {
+                    tracing::error!("Failed to mark broadcast {} skipped for user {}: {}", broadcast_id, user.id, e);
+                }
+                continue;
+            }
+            Ok(true) => {
+                success_count += 1;
+                tracing::info!("Successfully sent email to {}", user.email);
+                None
+            }
+            Err(error_msg) => {
+                failed_count += 1;
+                tracing::error!("{}", error_msg);
+                error_details.push(error_msg.clone());
+                Some(error_msg)
+            }
+        };
+        if let Err(e) = state.user_repository.record_broadcast_result(broadcast_id, user.id, error) {
+            tracing::error!("Failed to record broadcast {} result for user {}: {}", broadcast_id, user.id, e);
+        }
+
+        // Add a small delay to avoid hitting rate limits
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+
+    // Log final stats since we can't return them
+    tracing::info!(
+        "Email broadcast {} completed: success={}, failed={}, errors={:?}",
+        broadcast_id,
+        success_count,
+        failed_count,
+        error_details
+    );
+}
+
 pub async fn broadcast_email(
     State(state): State<Arc<AppState>>,
     Json(request): Json<EmailBroadcastRequest>,
@@ -208,6 +340,13 @@ pub async fn broadcast_email(
             Json(json!({"error": "Subject and message cannot be empty"}))
         ));
     }
+    // Placeholders are filled in per recipient, a typo would otherwise reach everyone as is
+    for template in [&request.subject, &request.message] {
+        crate::utils::broadcast_template::validate_template(template).map_err(|e| (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": e}))
+        ))?;
+    }
 
     // Fetch users outside the spawn to avoid DB issues, then move into task
     let users = state.user_core.get_all_users().map_err(|e| {
@@ -218,95 +357,98 @@ pub async fn broadcast_email(
         )
     })?;
 
-    // Clone what we need for the task
-    let state_clone = state.clone();
-    let request_clone = request.clone();
+    let broadcast_id = state.user_repository.create_broadcast("email", &request.subject, &request.message).map_err(|e| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"error": format!("Database error: {}", e)}))
+    ))?;
 
     // Spawn the background task
+    let state_clone = state.clone();
     tokio::spawn(async move {
-        let auth_user = crate::handlers::auth_middleware::AuthUser { user_id: 1, is_admin: false }; // Hardcode user_id to 1
-
-        let mut success_count = 0;
-        let mut failed_count = 0;
-        let mut error_details = Vec::new();
+        deliver_broadcast_email(&state_clone, broadcast_id, users, &request.subject, &request.message).await;
+    });
 
-        for user in users {
-            let user_settings = match state_clone.user_core.get_user_settings(user.id) {
-                Ok(settings) => settings,
-                Err(e) => {
-                    tracing::error!("Failed to get settings for user {}: {}", user.id, e);
-                    failed_count += 1;
-                    error_details.push(format!("Failed to get settings for {}: {}", user.email, e));
-                    continue;
-                }
-            };
+    // Respond immediately
+    Ok(Json(json!({
+        "message": "Email broadcast queued and will process in the background",
+        "broadcast_id": broadcast_id
+    })))
+}
 
-            if !user_settings.notify {
-                tracing::info!("skipping user since they don't have notify on");
-                continue;
-            }
+/// Renders a broadcast for one sample recipient so it can be checked before sending
+pub async fn preview_broadcast(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BroadcastPreviewRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let subject = request.subject.unwrap_or_default();
+    for template in [&subject, &request.message] {
+        crate::utils::broadcast_template::validate_template(template).map_err(|e| (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": e}))
+        ))?;
+    }
+    let db_error = |e: diesel::result::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"error": format!("Database error: {}", e)}))
+    );
+    let user = match request.user_id {
+        Some(user_id) => state.user_core.find_by_id(user_id).map_err(db_error)?,
+        None => state.user_core.get_all_users().map_err(db_error)?.into_iter().next(),
+    }
+    .ok_or_else(|| (
+        StatusCode::NOT_FOUND,
+        Json(json!({"error": "No user to preview the broadcast for"}))
+    ))?;
 
-            // Skip users with invalid or empty email addresses
-            if user.email.is_empty() || !user.email.contains('@') || !user.email.contains('.') {
-                tracing::warn!("Skipping invalid email address: {}", user.email);
-                continue;
-            }
+    Ok(Json(json!({
+        "user_id": user.id,
+        "subject": crate::utils::broadcast_template::render_for_user(&subject, &user),
+        "message": crate::utils::broadcast_template::render_for_user(&request.message, &user),
+        "variables": crate::utils::broadcast_template::TEMPLATE_VARIABLES.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+    })))
+}
 
-            // Prepare the unsubscribe link
-            let encoded_email = urlencoding::encode(&user.email);
-            let server_url = std::env::var("SERVER_URL").expect("SERVER_URL not set");
-            let unsubscribe_link = format!("{}/api/unsubscribe?email={}", server_url, encoded_email);
-
-            // Prepare plain text body with unsubscribe (link inline now)
-            let plain_body = format!(
-                "{}\n\nTo unsubscribe from these feature updates/fixes, click here: {}",
-                request_clone.message, unsubscribe_link
-            );
-            let wrapped_body = wrap_text(&plain_body, 72);
-            // Convert to CRLF line endings for email compliance
-            let crlf_body = wrapped_body.replace("\n", "\r\n");
-
-            // Prepare the email request for the send_email handler
-            let email_request = crate::handlers::imap_handlers::SendEmailRequest {
-                to: user.email.clone(),
-                subject: request_clone.subject.clone(),
-                body: crlf_body,
-            };
-
-            // Call the existing send_email handler
-            match crate::handlers::imap_handlers::send_email(
-                State(state_clone.clone()),
-                auth_user.clone(),
-                Json(email_request)
-            ).await {
-                Ok(_) => {
-                    success_count += 1;
-                    tracing::info!("Successfully sent email to {}", user.email);
-                }
-                Err((_status, err)) => {
-                    failed_count += 1;
-                    let error_msg = format!("Failed to send to {}: {:?}", user.email, err);
-                    tracing::error!("{}", error_msg);
-                    error_details.push(error_msg);
+/// Sends a broadcast again to only the users it failed for, so nobody gets it twice
+pub async fn retry_broadcast(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(broadcast_id): axum::extract::Path<i32>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let db_error = |e: diesel::result::Error| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"error": format!("Database error: {}", e)}))
+    );
+    let broadcast = state.user_repository.get_broadcast(broadcast_id)
+        .map_err(db_error)?
+        .ok_or_else(|| (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Broadcast not found"}))
+        ))?;
+    // Claimed rows are ours alone, a second retry running at the same time gets none of them
+    let failed_user_ids = state.user_repository.claim_failed_broadcast_recipients(broadcast_id).map_err(db_error)?;
+    let mut users = Vec::new();
+    for user_id in &failed_user_ids {
+        match state.user_core.find_by_id(*user_id) {
+            Ok(Some(user)) => users.push(user),
+            Ok(None) => {
+                tracing::warn!("Broadcast {} recipient {} no longer exists", broadcast_id, user_id);
+                if let Err(e) = state.user_repository.skip_broadcast_recipient(broadcast_id, *user_id) {
+                    tracing::error!("Failed to mark broadcast {} skipped for user {}: {}", broadcast_id, user_id, e);
                 }
             }
-
-            // Add a small delay to avoid hitting rate limits
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            Err(e) => return Err(db_error(e)),
         }
+    }
+    let retrying = users.len();
 
-        // Log final stats since we can't return them
-        tracing::info!(
-            "Email broadcast completed: success={}, failed={}, errors={:?}",
-            success_count,
-            failed_count,
-            error_details
-        );
+    let state_clone = state.clone();
+    tokio::spawn(async move {
+        deliver_broadcast_email(&state_clone, broadcast_id, users, &broadcast.subject, &broadcast.message).await;
     });
 
-    // Respond immediately
     Ok(Json(json!({
-        "message": "Email broadcast queued and will process in the background"
+        "message": "Retrying the broadcast for the recipients it failed for",
+        "broadcast_id": broadcast_id,
+        "retrying": retrying
     })))
 }
 
@@ -356,6 +498,34 @@ pub async fn update_discount_tier(
     })))
 }
 
+/// Sets what happens when the user runs out of credits. Admin only: grace lets the balance go
+/// negative, so users can't pick it for themselves. "none" falls back to the deployment default.
+pub async fn update_credit_policy(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path((user_id, policy)): axum::extract::Path<(i32, String)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let policy = match policy.to_lowercase().as_str() {
+        "" | "none" | "null" => None,
+        value => match crate::utils::usage::CreditPolicy::from_str(value) {
+            Some(policy) => Some(format!("{:?}", policy).to_lowercase()),
+            None => return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Invalid policy. Must be 'block', 'grace', 'warn', or 'none'"}))
+            )),
+        },
+    };
+
+    state.user_core.update_insufficient_credits_policy(user_id, policy.clone()).map_err(|e| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"error": format!("Database error: {}", e)}))
+    ))?;
+
+    Ok(Json(json!({
+        "message": "Credit policy updated successfully",
+        "policy": policy
+    })))
+}
+
 pub async fn update_monthly_credits(
     State(state): State<Arc<AppState>>,
     axum::extract::Path((user_id, amount)): axum::extract::Path<(f32, f32)>,
@@ -592,4 +762,169 @@ pub async fn test_sms(
     }
 }
 
+/// Characters of an undelivered notification shown in the admin list
+const FAILED_NOTIFICATION_PREVIEW_CHARS: usize = 20;
+
+pub async fn get_failed_notifications(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FailedNotificationsQuery>,
+) -> Result<Json<Vec<FailedNotificationResponse>>, (StatusCode, Json<serde_json::Value>)> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let failures = state.user_repository.get_failed_notifications(query.user_id, limit)
+        .map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Database error: {}", e)}))
+        ))?;
+
+    let response = failures.into_iter()
+        .map(|failure| {
+            let content = crate::utils::encryption::decrypt(&failure.encrypted_payload).unwrap_or_default();
+            let mut preview: String = content.chars().take(FAILED_NOTIFICATION_PREVIEW_CHARS).collect();
+            if content.chars().count() > FAILED_NOTIFICATION_PREVIEW_CHARS {
+                preview.push('…');
+            }
+            FailedNotificationResponse {
+                id: failure.id.unwrap_or(0),
+                user_id: failure.user_id,
+                notification_type: failure.notification_type,
+                channel: failure.channel,
+                preview,
+                error: failure.error,
+                created_at: failure.created_at,
+                retried_at: failure.retried_at,
+            }
+        })
+        .collect();
+    Ok(Json(response))
+}
+
+/// Whether each incoming webhook has its secrets set and has recently passed validation
+pub async fn get_webhook_status() -> Json<serde_json::Value> {
+    let now = chrono::Utc::now().timestamp();
+    let webhooks = crate::utils::webhook_status::statuses(crate::utils::webhook_status::env_is_set, now);
+    let healthy = webhooks.iter().all(|w| w.status == "ok" || w.status == "unverified");
+    Json(json!({
+        "healthy": healthy,
+        "webhooks": webhooks,
+    }))
+}
 
+/// Resends an undelivered notification as SMS, whatever channel originally failed
+pub async fn retry_failed_notification(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<i32>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let failure = match state.user_repository.get_failed_notification(id) {
+        Ok(Some(failure)) => failure,
+        Ok(None) => return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Failed notification not found"}))
+        )),
+        Err(e) => return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Database error: {}", e)}))
+        )),
+    };
+    let user = match state.user_core.find_by_id(failure.user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "User not found"}))
+        )),
+        Err(e) => return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Database error: {}", e)}))
+        )),
+    };
+    let notification = crate::utils::encryption::decrypt(&failure.encrypted_payload)
+        .map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Failed to decrypt notification: {}", e)}))
+        ))?;
+
+    let sent = crate::api::twilio_utils::send_conversation_message(&state, &notification, None, &user)
+        .await
+        .map_err(|e| e.to_string());
+    let message_sid = sent.map_err(|e| (
+        StatusCode::BAD_GATEWAY,
+        Json(json!({"error": format!("Resend failed: {}", e)}))
+    ))?;
+
+    record_resend(&state, id, &failure, message_sid);
+    Ok(Json(json!({"message": "Notification resent"})))
+}
+
+/// Marks a failed notification as resent and logs the resend like any other notification
+fn record_resend(state: &AppState, id: i32, failure: &crate::models::user_models::FailedNotification, message_sid: String) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i32;
+    if let Err(e) = state.user_repository.mark_failed_notification_retried(id, now) {
+        tracing::error!("Failed to mark failed notification {} retried: {}", id, e);
+    }
+    if let Err(e) = state.user_repository.log_usage(
+        failure.user_id,
+        Some(message_sid),
+        format!("{}_retry", failure.notification_type),
+        None,
+        None,
+        Some(true),
+        Some("Resent by admin after delivery failure".to_string()),
+        Some("delivered".to_string()),
+        None,
+        None,
+    ) {
+        tracing::error!("Failed to log resent notification: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, set_test_encryption_key, test_state};
+
+    async fn failures_of(state: &Arc<AppState>, user_id: i32) -> Vec<FailedNotificationResponse> {
+        let query = FailedNotificationsQuery { user_id: Some(user_id), limit: None };
+        get_failed_notifications(State(state.clone()), Query(query)).await.unwrap().0
+    }
+
+    #[tokio::test]
+    async fn undelivered_notification_is_listed_for_retry() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "undelivered@example.com");
+        state.user_repository
+            .record_failed_notification(user_id, "email_priority_sms", "sms", "Boss: the report is due at noon", "Twilio error 30003")
+            .unwrap();
+
+        let failures = failures_of(&state, user_id).await;
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].preview, "Boss: the report is …");
+        assert_eq!(failures[0].error, "Twilio error 30003");
+        assert_eq!(failures[0].retried_at, None);
+    }
+
+    #[tokio::test]
+    async fn resend_marks_the_failure_retried() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "resent@example.com");
+        state.user_repository.record_failed_notification(user_id, "email_priority_sms", "sms", "hello", "undelivered").unwrap();
+        let failure = state.user_repository.get_failed_notifications(Some(user_id), 1).unwrap().remove(0);
+        let id = failure.id.unwrap();
+
+        record_resend(&state, id, &failure, "SM123".to_string());
+
+        assert!(failures_of(&state, user_id).await[0].retried_at.is_some());
+        let logs = state.user_repository.get_recent_usage_logs(user_id, 10).unwrap();
+        assert_eq!(logs[0].activity_type, "email_priority_sms_retry");
+    }
+
+    #[tokio::test]
+    async fn retrying_a_missing_failure_is_not_found() {
+        let state = test_state();
+        let (status, _) = retry_failed_notification(State(state), axum::extract::Path(12345)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
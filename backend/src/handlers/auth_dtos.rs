@@ -32,6 +32,20 @@ pub struct RegisterRequest {
     pub phone_number: String,
 }
 
+/// One invalid input field, `code` is stable so the frontend can pick its own wording
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, code: &'static str, message: impl Into<String>) -> Self {
+        Self { field, code, message: message.into() }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: i32,
@@ -3,8 +3,8 @@ use crate::handlers::auth_middleware::AuthUser;
 use axum::{
     Json,
     extract::State,
-    response::Response,
-    http::StatusCode,
+    response::{IntoResponse, Response},
+    http::{header, StatusCode},
 };
 use rand::Rng;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -13,11 +13,13 @@ use jsonwebtoken::{encode, decode, Header, EncodingKey, DecodingKey, Validation}
 use chrono::{Duration, Utc};
 use serde::Deserialize;
 use std::num::NonZeroU32;
-use governor::{Quota, RateLimiter};
+use governor::{clock::{Clock, DefaultClock}, state::keyed::DefaultKeyedStateStore, NotUntil, Quota, RateLimiter};
+use dashmap::DashMap;
 use std::env;
 
 use crate::{
-    handlers::auth_dtos::{LoginRequest, RegisterRequest, UserResponse, NewUser},
+    error::ApiError,
+    handlers::auth_dtos::{FieldError, LoginRequest, RegisterRequest, UserResponse, NewUser},
     AppState
 };
 
@@ -39,17 +41,119 @@ pub struct PasswordResetResponse {
     message: String,
 }
 
+/// 429 response with a Retry-After header, the same wait is repeated in the details for the frontend
+fn too_many_requests(body: serde_json::Value, retry_after_secs: u64) -> Response {
+    let retry_after_secs = retry_after_secs.max(1);
+    let mut body = body;
+    body["retry_after_seconds"] = json!(retry_after_secs);
+    (
+        [(header::RETRY_AFTER, retry_after_secs.to_string())],
+        ApiError::from((StatusCode::TOO_MANY_REQUESTS, Json(body))),
+    ).into_response()
+}
+
+/// Seconds until the limiter lets the next request through, rounded up
+fn retry_after_secs(not_until: &NotUntil<<DefaultClock as Clock>::Instant>) -> u64 {
+    let wait = not_until.wait_time_from(DefaultClock::default().now());
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
+
+type Limiters = DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>;
+
+/// Limiters kept before idle ones get swept, each distinct ip/email/phone key adds one
+const LIMITER_SWEEP_THRESHOLD: usize = 1000;
+
+/// Drops limiters whose quota has fully recovered, they'd behave exactly like a new one.
+/// Keeps the maps from growing with every address or email that ever tried once.
+fn prune_idle_limiters(limiters: &Limiters) {
+    if limiters.len() < LIMITER_SWEEP_THRESHOLD {
+        return;
+    }
+    limiters.retain(|_, limiter| {
+        limiter.retain_recent();
+        !limiter.is_empty()
+    });
+}
+
+/// 400 listing every invalid field at once
+fn validation_error(errors: Vec<FieldError>) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "Validation failed").with_details(json!({"fields": errors}))
+}
+
+/// Turns "+1 (555) 123-4567" or "00358 40 123 4567" into E.164, None if it still doesn't look like a number
+fn normalize_phone_number(raw: &str) -> Option<String> {
+    let compact: String = raw
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '(' | ')' | '.'))
+        .collect();
+    let normalized = match compact.strip_prefix("00") {
+        Some(rest) => format!("+{}", rest),
+        None => compact,
+    };
+    let phone_regex = regex::Regex::new(r"^\+[1-9]\d{1,14}$").unwrap();
+    phone_regex.is_match(&normalized).then_some(normalized)
+}
+
+const COMMON_PASSWORDS: [&str; 10] = [
+    "password", "password1", "12345678", "123456789", "1234567890",
+    "qwertyui", "qwerty123", "iloveyou", "11111111", "abcd1234",
+];
+
+/// Reason the password is too weak, if it is
+fn password_weakness(password: &str, email: &str) -> Option<&'static str> {
+    if password.chars().count() < 8 {
+        return Some("Password must be 8+ characters");
+    }
+    // bcrypt ignores everything past 72 bytes
+    if password.len() > 72 {
+        return Some("Password must be at most 72 bytes");
+    }
+    let lower = password.to_lowercase();
+    if COMMON_PASSWORDS.contains(&lower.as_str()) {
+        return Some("Password is too common");
+    }
+    if password.chars().all(|c| c == password.chars().next().unwrap()) {
+        return Some("Password can't be a single repeated character");
+    }
+    let email_local = email.split('@').next().unwrap_or("").to_lowercase();
+    if !email_local.is_empty() && lower == email_local {
+        return Some("Password can't be the same as your email");
+    }
+    None
+}
+
+/// Hash compared against when the email doesn't exist so failed logins take the same time either way
+fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    DUMMY_HASH.get_or_init(|| {
+        bcrypt::hash("lightfriend-dummy-password", bcrypt::DEFAULT_COST).expect("Failed to hash dummy password")
+    })
+}
+
+/// Same answer whether or not the account exists
+const PASSWORD_RESET_SENT: &str = "If an account exists for this email, a reset code was sent to its phone";
+const PHONE_CODE_SENT: &str = "If this number belongs to an account, a verification code was sent to it";
+
+/// Sends a one-time code without making the caller wait for Twilio, so the response time
+/// doesn't tell an existing account from a missing one
+fn send_code_in_background(state: &Arc<AppState>, message: String, user: crate::models::user_models::User) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::api::twilio_utils::send_conversation_message(&state, &message, None, &user).await {
+            tracing::error!("Failed to send one-time code to user {}: {}", user.id, e);
+        }
+    });
+}
+
 pub async fn get_users(
     State(state): State<Arc<AppState>>,
     _auth_user: AuthUser,
-) -> Result<Json<Vec<UserResponse>>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Vec<UserResponse>>, ApiError> {
     println!("Attempting to get all users");
     let users_list = state.user_core.get_all_users().map_err(|e| {
         tracing::error!("Database error while fetching users: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Database error"}))
-        )
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
     })?;
     
     println!("Converting users to response format");
@@ -59,10 +163,7 @@ pub async fn get_users(
         // Get user settings, providing defaults if not found
         let settings = state.user_core.get_user_settings(user.id).map_err(|e| {
             tracing::error!("Database error while fetching user settings: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Database error"}))
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
         })?;
 
         users_response.push(UserResponse {
@@ -89,42 +190,52 @@ pub async fn get_users(
 
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    client_ip: crate::utils::client_ip::ClientIp,
     Json(login_req): Json<LoginRequest>,
-) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Response, Response> {
     println!("Login attempt for email: {}", login_req.email); // Debug log
 
     // Define rate limit: 5 attempts per minute
     let quota = Quota::per_minute(NonZeroU32::new(5).unwrap());
-    let limiter_key = login_req.email.clone(); // Use email as the key
+    // Key by ip and email so someone hammering an address from elsewhere can't lock its owner out
+    let limiter_key = format!("{}|{}", client_ip.key(), login_req.email.to_lowercase());
 
-    // Get or create a keyed rate limiter for this email
+    // Get or create a keyed rate limiter for this ip and email
+    prune_idle_limiters(&state.login_limiter);
     let entry = state.login_limiter
         .entry(limiter_key.clone())
         .or_insert_with(|| RateLimiter::keyed(quota)); // Bind the Entry here
     let limiter = entry.value(); // Now borrow from the bound value
 
     // Check if rate limit is exceeded
-    if limiter.check_key(&limiter_key).is_err() {
+    if let Err(not_until) = limiter.check_key(&limiter_key) {
         println!("Rate limit exceeded for email: [redacted]");
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(json!({"error": "Too many login attempts, try again later"})),
+        return Err(too_many_requests(
+            json!({"error": "Too many login attempts, try again later"}),
+            retry_after_secs(&not_until),
         ));
     }
 
-    let user = match state.user_core.find_by_email(&login_req.email) {
+    let mut field_errors = Vec::new();
+    if login_req.email.trim().is_empty() {
+        field_errors.push(FieldError::new("email", "required", "Email is required"));
+    }
+    if login_req.password.is_empty() {
+        field_errors.push(FieldError::new("password", "required", "Password is required"));
+    }
+    if !field_errors.is_empty() {
+        return Err(validation_error(field_errors).into_response());
+    }
+
+    let user = match state.user_core.find_by_email(login_req.email.trim()) {
         Ok(Some(user)) => user,
         Ok(None) => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "User not found"}))
-            ));
+            // Burn the same bcrypt work as a real check so response times don't reveal which emails exist
+            let _ = bcrypt::verify(&login_req.password, dummy_password_hash());
+            return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Invalid credentials").into_response());
         }
         Err(_) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Database error"}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response());
         }
     };
    
@@ -167,13 +278,10 @@ pub async fn login(
                 return Ok(response);
             }
 
-            generate_tokens_and_response(user.id)
+            generate_tokens_and_response(user.id).map_err(IntoResponse::into_response)
         }
         _ => {
-            Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Invalid credentials"}))
-            ))
+            Err(ApiError::new(StatusCode::UNAUTHORIZED, "Invalid credentials").into_response())
         }
     }
 }
@@ -182,41 +290,38 @@ pub async fn login(
 pub async fn request_password_reset(
     State(state): State<Arc<AppState>>,
     Json(reset_req): Json<PasswordResetRequest>,
-) -> Result<Json<PasswordResetResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<PasswordResetResponse>, Response> {
     // Define rate limit: 3 attempts per hour per email
     let quota = Quota::per_hour(NonZeroU32::new(3).unwrap());
-    let limiter_key = reset_req.email.clone();
+    let limiter_key = reset_req.email.to_lowercase();
 
     // Get or create a rate limiter for this email
+    prune_idle_limiters(&state.password_reset_limiter);
     let entry = state.password_reset_limiter
         .entry(limiter_key.clone())
         .or_insert_with(|| RateLimiter::keyed(quota));
     let limiter = entry.value();
 
     // Check if rate limit is exceeded
-    if limiter.check_key(&limiter_key).is_err() {
+    if let Err(not_until) = limiter.check_key(&limiter_key) {
         println!("Rate limit exceeded for password reset request: [redacted email]");
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(json!({"error": "Too many password reset attempts. Please try again later."}))
+        return Err(too_many_requests(
+            json!({"error": "Too many password reset attempts. Please try again later."}),
+            retry_after_secs(&not_until),
         ));
     }
-    // Find user by email
+    // Unknown emails get the same answer, after the same bcrypt work, so this can't be used to find accounts
     let user = match state.user_core.find_by_email(&reset_req.email) {
         Ok(Some(user)) => user,
         Ok(None) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "User not found"}))
-            ));
+            let _ = bcrypt::verify(&reset_req.email, dummy_password_hash());
+            return Ok(Json(PasswordResetResponse { message: PASSWORD_RESET_SENT.to_string() }));
         }
         Err(_) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Database error"}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response());
         }
     };
+    let _ = bcrypt::verify(&reset_req.email, dummy_password_hash());
 
     // Generate 6-digit OTP
     let otp: String = rand::thread_rng()
@@ -242,46 +347,37 @@ pub async fn request_password_reset(
 
     println!("Stored OTP {} for email {} with expiration {}", otp, reset_req.email, expiration);
 
-    let message = format!("Your Lightfriend password reset code is: {}. Valid for 5 minutes.", otp);
-    if let Err(_) = crate::api::twilio_utils::send_conversation_message(
-        &state,
-        &message,
-        None,
-        &user
-    ).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to send OTP"}))
-        ));
-    }
+    let message = crate::utils::branding::password_reset_sms(&otp);
+    send_code_in_background(&state, message, user);
 
     Ok(Json(PasswordResetResponse {
-        message: "Password reset code sent to your phone".to_string()
+        message: PASSWORD_RESET_SENT.to_string()
     }))
 }
 
 pub async fn verify_password_reset(
     State(state): State<Arc<AppState>>,
     Json(verify_req): Json<VerifyPasswordResetRequest>,
-) -> Result<Json<PasswordResetResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<PasswordResetResponse>, Response> {
     // Define rate limit: 3 attempts per 60 minutes per email
     let quota = Quota::with_period(std::time::Duration::from_secs(60 * 60))
         .unwrap()
         .allow_burst(NonZeroU32::new(3).unwrap());
-    let limiter_key = verify_req.email.clone();
+    let limiter_key = verify_req.email.to_lowercase();
 
     // Get or create a rate limiter for this email
+    prune_idle_limiters(&state.password_reset_verify_limiter);
     let entry = state.password_reset_verify_limiter
         .entry(limiter_key.clone())
         .or_insert_with(|| RateLimiter::keyed(quota));
     let limiter = entry.value();
 
     // Check if rate limit is exceeded
-    if limiter.check_key(&limiter_key).is_err() {
+    if let Err(not_until) = limiter.check_key(&limiter_key) {
         println!("Rate limit exceeded for password reset verification: [redacted email]");
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(json!({"error": "Too many verification attempts. Please try again later."}))
+        return Err(too_many_requests(
+            json!({"error": "Too many verification attempts. Please try again later."}),
+            retry_after_secs(&not_until),
         ));
     }
     println!("Verifying OTP {} for email {}", verify_req.otp, verify_req.email);
@@ -290,10 +386,7 @@ pub async fn verify_password_reset(
     let otp_data = match state.password_reset_otps.remove(&verify_req.email) {
         Some((_, data)) => data,  // The first element is the key (email), second is the value tuple
         None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "No valid OTP found for this email"}))
-            ));
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "No valid OTP found for this email").into_response());
         }
     };
 
@@ -306,42 +399,31 @@ pub async fn verify_password_reset(
 
     if current_time > expiration_time {
         println!("OTP expired: current_time {} > expiration {}", current_time, expiration_time);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "OTP has expired"}))
-        ));
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "OTP has expired").into_response());
     }
 
     if verify_req.otp != stored_otp {
         println!("OTP mismatch: provided {} != stored {}", verify_req.otp, stored_otp);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid OTP"}))
-        ));
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid OTP").into_response());
     }
 
     // Hash new password
     let password_hash = bcrypt::hash(&verify_req.new_password, bcrypt::DEFAULT_COST)
         .map_err(|e| {
             println!("Password hashing failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Password hashing failed"}))
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Password hashing failed").into_response()
         })?;
 
     // Update password in database
     if let Err(e) = state.user_core.update_password(&verify_req.email, &password_hash) {
         println!("Failed to update password: {}", e);
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to update password"}))
-        ));
+        return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update password").into_response());
     }
     println!("New password updated successfully");
 
     // Also remove any rate limiting for this email
-    state.login_limiter.remove(&verify_req.email);
+    let login_key_suffix = format!("|{}", verify_req.email.to_lowercase());
+    state.login_limiter.retain(|key, _| !key.ends_with(&login_key_suffix));
     
     println!("Password reset completed successfully, sending response");
     
@@ -368,39 +450,37 @@ pub struct VerifyOtpRequest {
 pub async fn request_phone_verify(
     State(state): State<Arc<AppState>>,
     Json(reset_req): Json<SendOtpRequest>,
-) -> Result<Json<PasswordResetResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<PasswordResetResponse>, Response> {
     // Define rate limit: 3 attempts per hour per phone_number
     let quota = Quota::per_hour(NonZeroU32::new(3).unwrap());
     let limiter_key = reset_req.phone_number.clone();
     // Get or create a rate limiter for this phone_number
+    prune_idle_limiters(&state.phone_verify_limiter);
     let entry = state.phone_verify_limiter
         .entry(limiter_key.clone())
         .or_insert_with(|| RateLimiter::keyed(quota));
     let limiter = entry.value();
     // Check if rate limit is exceeded
-    if limiter.check_key(&limiter_key).is_err() {
+    if let Err(not_until) = limiter.check_key(&limiter_key) {
         println!("Rate limit exceeded for phone verify request: [redacted phone]");
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(json!({"error": "Too many verification attempts. Please try again later."}))
+        return Err(too_many_requests(
+            json!({"error": "Too many verification attempts. Please try again later."}),
+            retry_after_secs(&not_until),
         ));
     }
-    // Find user by phone_number
+    // Unknown numbers get the same answer after the same work, see request_password_reset
     let user = match state.user_core.find_by_phone_number(&reset_req.phone_number) {
         Ok(Some(user)) => user,
         Ok(None) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "No user found with this phone number"}))
-            ));
+            let _ = bcrypt::verify(&reset_req.phone_number, dummy_password_hash());
+            record_phone_verify_send(&state, &reset_req.phone_number);
+            return Ok(Json(PasswordResetResponse { message: PHONE_CODE_SENT.to_string() }));
         }
         Err(_) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Database error"}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response());
         }
     };
+    let _ = bcrypt::verify(&reset_req.phone_number, dummy_password_hash());
     // Generate 6-digit OTP
     let otp: String = rand::thread_rng()
         .sample_iter(&rand::distributions::Uniform::new(0, 10))
@@ -420,43 +500,140 @@ pub async fn request_phone_verify(
         (otp.clone(), expiration)
     );
     println!("Stored OTP {} for phone {} with expiration {}", otp, reset_req.phone_number, expiration);
-    let message = format!("Your Lightfriend verification code is: {}. Valid for 5 minutes.", otp);
-    if let Err(_) = crate::api::twilio_utils::send_conversation_message(
-        &state,
-        &message,
-        None,
-        &user
-    ).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to send OTP"}))
-        ));
-    }
+    record_phone_verify_send(&state, &reset_req.phone_number);
+    let message = crate::utils::branding::verification_code_sms(&otp);
+    send_code_in_background(&state, message, user);
     Ok(Json(PasswordResetResponse {
-        message: "Verification code sent to your phone".to_string()
+        message: PHONE_CODE_SENT.to_string()
     }))
 }
 
+#[derive(Serialize)]
+pub struct ResendOtpResponse {
+    message: String,
+    cooldown_seconds: u64,
+    sends_remaining: u32,
+}
+
+fn phone_verify_resend_cooldown_secs() -> u64 {
+    env::var("PHONE_VERIFY_RESEND_COOLDOWN_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(60)
+}
+
+fn phone_verify_max_sends_per_hour() -> u32 {
+    env::var("PHONE_VERIFY_MAX_SENDS_PER_HOUR")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(5)
+}
+
+/// Remembers when a verification code was sent so resends can enforce the cooldown and hourly cap
+fn record_phone_verify_send(state: &AppState, phone_number: &str) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let mut sends = state.phone_verify_sends.entry(phone_number.to_string()).or_default();
+    sends.retain(|sent_at| now.saturating_sub(*sent_at) < 3600);
+    sends.push(now);
+}
+
+/// Checks the cooldown and the hourly cap and records the send under the same entry lock, so
+/// concurrent resends can't all pass the check before any of them is recorded. Returns how many
+/// sends are left this hour.
+fn reserve_phone_verify_resend(state: &AppState, phone_number: &str, now: u64, cooldown: u64, max_sends: u32) -> Result<u32, Response> {
+    let mut sends = state.phone_verify_sends.entry(phone_number.to_string()).or_default();
+    sends.retain(|sent_at| now.saturating_sub(*sent_at) < 3600);
+    if sends.len() as u32 >= max_sends {
+        let oldest = sends.iter().min().copied().unwrap_or(now);
+        let retry_after = (oldest + 3600).saturating_sub(now);
+        tracing::debug!("Phone verify resend cap reached: [redacted phone]");
+        return Err(too_many_requests(
+            json!({
+                "error": "Too many verification codes requested. Please try again later.",
+                "locked": true,
+            }),
+            retry_after,
+        ));
+    }
+    if let Some(last_sent) = sends.iter().max() {
+        let elapsed = now.saturating_sub(*last_sent);
+        if elapsed < cooldown {
+            return Err(too_many_requests(
+                json!({
+                    "error": "Please wait before requesting a new code.",
+                    "locked": false,
+                }),
+                cooldown - elapsed,
+            ));
+        }
+    }
+    sends.push(now);
+    Ok(max_sends.saturating_sub(sends.len() as u32))
+}
+
+pub async fn resend_phone_verify(
+    State(state): State<Arc<AppState>>,
+    Json(resend_req): Json<SendOtpRequest>,
+) -> Result<Json<ResendOtpResponse>, Response> {
+    let cooldown = phone_verify_resend_cooldown_secs();
+    let max_sends = phone_verify_max_sends_per_hour();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let sends_remaining = reserve_phone_verify_resend(&state, &resend_req.phone_number, now, cooldown, max_sends)?;
+    let sent = Json(ResendOtpResponse {
+        message: PHONE_CODE_SENT.to_string(),
+        cooldown_seconds: cooldown,
+        sends_remaining,
+    });
+    // Unknown numbers get the same answer after the same work, see request_password_reset
+    let user = match state.user_core.find_by_phone_number(&resend_req.phone_number) {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            let _ = bcrypt::verify(&resend_req.phone_number, dummy_password_hash());
+            return Ok(sent);
+        }
+        Err(_) => {
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response());
+        }
+    };
+    let _ = bcrypt::verify(&resend_req.phone_number, dummy_password_hash());
+    // Generate 6-digit OTP
+    let otp: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Uniform::new(0, 10))
+        .take(6)
+        .map(|d| d.to_string())
+        .collect();
+    let expiration = now + 300; // 5 minutes
+    // Replacing the entry invalidates the previously sent code
+    state.phone_verify_otps.insert(
+        resend_req.phone_number.clone(),
+        (otp.clone(), expiration)
+    );
+    let message = crate::utils::branding::verification_code_sms(&otp);
+    send_code_in_background(&state, message, user);
+    Ok(sent)
+}
+
 pub async fn verify_phone_verify(
     State(state): State<Arc<AppState>>,
     Json(verify_req): Json<VerifyOtpRequest>,
-) -> Result<Json<PasswordResetResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<PasswordResetResponse>, Response> {
     // Define rate limit: 3 attempts per 60 minutes per phone_number
     let quota = Quota::with_period(std::time::Duration::from_secs(60 * 60))
         .unwrap()
         .allow_burst(NonZeroU32::new(3).unwrap());
     let limiter_key = verify_req.phone_number.clone();
     // Get or create a rate limiter for this phone_number
+    prune_idle_limiters(&state.phone_verify_verify_limiter);
     let entry = state.phone_verify_verify_limiter
         .entry(limiter_key.clone())
         .or_insert_with(|| RateLimiter::keyed(quota));
     let limiter = entry.value();
     // Check if rate limit is exceeded
-    if limiter.check_key(&limiter_key).is_err() {
+    if let Err(not_until) = limiter.check_key(&limiter_key) {
         println!("Rate limit exceeded for phone verify verification: [redacted phone]");
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(json!({"error": "Too many verification attempts. Please try again later."}))
+        return Err(too_many_requests(
+            json!({"error": "Too many verification attempts. Please try again later."}),
+            retry_after_secs(&not_until),
         ));
     }
     println!("Verifying OTP {} for phone {}", verify_req.otp, verify_req.phone_number);
@@ -465,10 +642,7 @@ pub async fn verify_phone_verify(
     let otp_data = match state.phone_verify_otps.remove(&verify_req.phone_number) {
         Some((_, data)) => data, // The first element is the key (phone), second is the value tuple
         None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "No valid OTP found for this phone number"}))
-            ));
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "No valid OTP found for this phone number").into_response());
         }
     };
     let (stored_otp, expiration_time) = otp_data;
@@ -478,41 +652,26 @@ pub async fn verify_phone_verify(
         .as_secs();
     if current_time > expiration_time {
         println!("OTP expired: current_time {} > expiration {}", current_time, expiration_time);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "OTP has expired"}))
-        ));
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "OTP has expired").into_response());
     }
     if verify_req.otp != stored_otp {
         println!("OTP mismatch: provided {} != stored {}", verify_req.otp, stored_otp);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid OTP"}))
-        ));
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid OTP").into_response());
     }
     // Find user by phone_number to verify
     let user = match state.user_core.find_by_phone_number(&verify_req.phone_number) {
         Ok(Some(user)) => user,
         Ok(None) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "No user found with this phone number"}))
-            ));
+            return Err(ApiError::new(StatusCode::NOT_FOUND, "No user found with this phone number").into_response());
         }
         Err(_) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Database error"}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response());
         }
     };
     // Verify the user
     if let Err(e) = state.user_core.verify_user(user.id) {
         tracing::error!("Error verifying user: {}", e);
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to verify user"}))
-        ));
+        return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to verify user").into_response());
     }
     println!("User verified successfully");
    
@@ -527,62 +686,50 @@ pub async fn verify_phone_verify(
 pub async fn register(
     State(state): State<Arc<AppState>>,
     Json(reg_req): Json<RegisterRequest>,
-) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Response, ApiError> {
    
     println!("Registration attempt for email: {}", reg_req.email);
     use regex::Regex;
+    let mut reg_req = reg_req;
+    reg_req.email = reg_req.email.trim().to_string();
+    // Validate every field up front so the frontend can highlight all problems at once
+    let mut field_errors = Vec::new();
     let email_regex = Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
     if !email_regex.is_match(&reg_req.email) {
         println!("Invalid email format: {}", reg_req.email);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid email format"}))
-        ));
+        field_errors.push(FieldError::new("email", "invalid_format", "Invalid email format"));
+    }
+    match normalize_phone_number(&reg_req.phone_number) {
+        Some(normalized) => reg_req.phone_number = normalized,
+        None => {
+            tracing::debug!("Invalid phone number format: {}", reg_req.phone_number);
+            field_errors.push(FieldError::new("phone_number", "invalid_format", "Phone number must be in E.164 format (e.g., +1234567890)"));
+        }
+    }
+    if let Some(reason) = password_weakness(&reg_req.password, &reg_req.email) {
+        field_errors.push(FieldError::new("password", "weak", reason));
+    }
+    if !field_errors.is_empty() {
+        return Err(validation_error(field_errors));
     }
     // Check if email exists
     println!("Checking if email exists...");
     if state.user_core.email_exists(&reg_req.email).map_err(|e| {
         println!("Database error while checking email: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("Database error") }))
-        )
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error"))
     })? {
         println!("Email {} already exists", reg_req.email);
-        return Err((
-            StatusCode::CONFLICT,
-            Json(json!({ "error": "Email already exists" })),
-        ));
+        return Err(ApiError::new(StatusCode::CONFLICT, "Email already exists").with_details(json!({"fields": [FieldError::new("email", "taken", "Email already exists")]})));
     }
     println!("Email is available");
-    let phone_regex = Regex::new(r"^\+[1-9]\d{1,14}$").unwrap();
-    if !phone_regex.is_match(&reg_req.phone_number) {
-        println!("Invalid phone number format: {}", reg_req.phone_number);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Phone number must be in E.164 format (e.g., +1234567890)"}))
-        ));
-    }
-    if reg_req.password.len() < 8 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "error": "Password must be 8+ characters" })),
-        ));
-    }
     // Check if phone number exists
     println!("Checking if phone number exists...");
     if state.user_core.phone_number_exists(&reg_req.phone_number).map_err(|e| {
         println!("Database error while checking phone number: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("Database error") }))
-        )
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error"))
     })? {
         println!("Phone number {} already exists", reg_req.phone_number);
-        return Err((
-            StatusCode::CONFLICT,
-            Json(json!({ "error": "Phone number already registered" })),
-        ));
+        return Err(ApiError::new(StatusCode::CONFLICT, "Phone number already registered").with_details(json!({"fields": [FieldError::new("phone_number", "taken", "Phone number already registered")]})));
     }
     println!("Phone number is available");
     // Hash password
@@ -590,10 +737,7 @@ pub async fn register(
     let password_hash = bcrypt::hash(&reg_req.password, bcrypt::DEFAULT_COST)
         .map_err(|e| {
             println!("Password hashing failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": format!("Password hashing failed") })),
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Password hashing failed"))
         })?;
     println!("Password hashed successfully");
     // Create and insert user
@@ -620,23 +764,14 @@ pub async fn register(
     };
     state.user_core.create_user(new_user).map_err(|e| {
         println!("User creation failed: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("User creation failed") })),
-        )
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("User creation failed"))
     })?;
     println!("User registered successfully, setting preferred number");
    
     // Get the newly created user to get their ID
     let user = state.user_core.find_by_email(&reg_req.email)
-        .map_err(|_e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Failed to retrieve user")}))
-        ))?
-        .ok_or_else(|| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "User not found after registration"}))
-        ))?;
+        .map_err(|_e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to retrieve user")))?
+        .ok_or_else(|| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "User not found after registration"))?;
     // Set phone number country
     if let Err(e) = crate::handlers::profile_handlers::set_user_phone_country(&state, user.id, &reg_req.phone_number).await {
         tracing::error!("Failed to set phone country during registration: {}", e);
@@ -647,10 +782,7 @@ pub async fn register(
         state.user_core.set_preferred_number_to_us_default(user.id)
         .map_err(|e| {
             println!("Failed to set preferred number: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": format!("Failed to set preferred number") })),
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to set preferred number"))
         })?;
         println!("Preferred number set successfully, generating tokens");
     }
@@ -660,23 +792,17 @@ pub async fn register(
 pub async fn refresh_token(
     State(_state): State<Arc<AppState>>,
     headers: reqwest::header::HeaderMap,
-) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Response, ApiError> {
     let refresh_token = match headers.get("cookie") {
         Some(cookie_header) => {
             let cookies = cookie_header.to_str().unwrap_or("");
             cookies.split(';').find(|c| c.trim().starts_with("refresh_token="))
                 .and_then(|c| c.split('=').nth(1))
                 .map(|t| t.to_string())
-                .ok_or((
-                    StatusCode::UNAUTHORIZED,
-                    Json(json!({"error": "Missing refresh token"}))
-                ))?
+                .ok_or(ApiError::new(StatusCode::UNAUTHORIZED, "Missing refresh token"))?
         }
         None => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Missing cookies"}))
-            ));
+            return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Missing cookies"));
         }
     };
 
@@ -686,17 +812,11 @@ pub async fn refresh_token(
         &refresh_token,
         &DecodingKey::from_secret(env::var("JWT_REFRESH_KEY").expect("JWT_REFRESH_KEY must be set").as_ref()),
         &validation,
-    ).map_err(|_| (
-        StatusCode::UNAUTHORIZED,
-        Json(json!({"error": "Invalid refresh token"}))
-    ))?;
+    ).map_err(|_| ApiError::new(StatusCode::UNAUTHORIZED, "Invalid refresh token"))?;
 
     let user_id: i32 = token_data.claims["sub"].as_i64().unwrap_or(0) as i32;
     if user_id == 0 {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(json!({"error": "Invalid user in token"}))
-        ));
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Invalid user in token"));
     }
 
     // Optional: Rotate refresh token by generating a new one
@@ -707,7 +827,7 @@ pub async fn testing_handler(
     State(_state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(params): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     println!("Testing route called by user ID: {}", auth_user.user_id);
     println!("Received params: {:?}", params);
 
@@ -721,15 +841,12 @@ pub async fn testing_handler(
         }
         Err(e) => {
             println!("Error in get_nearby_towns: {:?}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get nearby towns: {}", e)}))
-            ))
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get nearby towns: {}", e)))
         }
     }
 }
 
-pub fn generate_tokens_and_response(user_id: i32) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+pub fn generate_tokens_and_response(user_id: i32) -> Result<Response, ApiError> {
     // Generate access token (1 hour)
     let access_token = encode(
         &Header::default(),
@@ -741,10 +858,7 @@ pub fn generate_tokens_and_response(user_id: i32) -> Result<Response, (StatusCod
         &EncodingKey::from_secret(std::env::var("JWT_SECRET_KEY")
             .expect("JWT_SECRET_KEY must be set in environment")
             .as_bytes()),
-    ).map_err(|_| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({"error": "Token generation failed"}))
-    ))?;
+    ).map_err(|_| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Token generation failed"))?;
 
     // Generate refresh token (90 days)
     let refresh_token = encode(
@@ -757,10 +871,7 @@ pub fn generate_tokens_and_response(user_id: i32) -> Result<Response, (StatusCod
         &EncodingKey::from_secret(std::env::var("JWT_REFRESH_KEY")
             .expect("JWT_REFRESH_KEY must be set in environment")
             .as_bytes()),
-    ).map_err(|_| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({"error": "Token generation failed"}))
-    ))?;
+    ).map_err(|_| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Token generation failed"))?;
 
     // Create response with HttpOnly cookies
     let mut response = Response::new(
@@ -798,7 +909,7 @@ pub fn generate_tokens_and_response(user_id: i32) -> Result<Response, (StatusCod
 
 pub async fn auth_status(
     auth_user: AuthUser,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     Ok(Json(json!({
         "authenticated": true,
         "user_id": auth_user.user_id,
@@ -841,3 +952,142 @@ pub async fn logout() -> Result<Response, StatusCode> {
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+    use crate::utils::client_ip::ClientIp;
+
+    fn attempt(email: &str) -> Json<LoginRequest> {
+        // An empty password fails validation right after the limiter, no bcrypt work needed
+        Json(LoginRequest { email: email.to_string(), password: String::new() })
+    }
+
+    fn from(ip: &str) -> ClientIp {
+        ClientIp(Some(ip.parse().unwrap()))
+    }
+
+    #[tokio::test]
+    async fn sixth_rapid_login_gets_429_with_retry_after() {
+        let state = test_state();
+        for _ in 0..5 {
+            let response = login(State(state.clone()), from("203.0.113.7"), attempt("victim@example.com")).await.unwrap_err();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        let response = login(State(state.clone()), from("203.0.113.7"), attempt("victim@example.com")).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after));
+
+        // Someone else trying the same address doesn't lock its owner out
+        let response = login(State(state), from("198.51.100.1"), attempt("victim@example.com")).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn password_reset_answers_the_same_for_unknown_accounts() {
+        let state = test_state();
+        create_test_user(&state, "known@example.com");
+        let reset = |email: &str| Json(PasswordResetRequest { email: email.to_string() });
+
+        let known = request_password_reset(State(state.clone()), reset("known@example.com")).await.unwrap();
+        let unknown = request_password_reset(State(state.clone()), reset("nobody@example.com")).await.unwrap();
+        assert_eq!(known.0.message, unknown.0.message);
+        // Only the real account gets a code to verify
+        assert!(state.password_reset_otps.contains_key("known@example.com"));
+        assert!(!state.password_reset_otps.contains_key("nobody@example.com"));
+    }
+
+    #[tokio::test]
+    async fn phone_verify_answers_the_same_for_unknown_numbers() {
+        let state = test_state();
+        create_test_user(&state, "phone@example.com");
+        let request = |phone: &str| Json(SendOtpRequest { phone_number: phone.to_string() });
+
+        let known = request_phone_verify(State(state.clone()), request("+15555550123")).await.unwrap();
+        let unknown = request_phone_verify(State(state.clone()), request("+15555550999")).await.unwrap();
+        assert_eq!(known.0.message, unknown.0.message);
+    }
+
+    fn resend(phone_number: &str) -> Json<SendOtpRequest> {
+        Json(SendOtpRequest { phone_number: phone_number.to_string() })
+    }
+
+    fn retry_after(response: &Response) -> u64 {
+        response.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn resend_inside_the_cooldown_is_refused() {
+        let state = test_state();
+        let first = resend_phone_verify(State(state.clone()), resend("+15555550777")).await.unwrap();
+        assert_eq!(first.0.sends_remaining, phone_verify_max_sends_per_hour() - 1);
+
+        let response = resend_phone_verify(State(state.clone()), resend("+15555550777")).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(retry_after(&response) <= phone_verify_resend_cooldown_secs());
+        // The refused attempt isn't counted as a send
+        assert_eq!(state.phone_verify_sends.get("+15555550777").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sixth_send_in_an_hour_is_refused() {
+        let state = test_state();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        // Five earlier sends, all past the cooldown but inside the hour
+        let earlier: Vec<u64> = (0..5).map(|i| now - 3000 + i * 300).collect();
+        state.phone_verify_sends.insert("+15555550778".to_string(), earlier);
+
+        let response = resend_phone_verify(State(state.clone()), resend("+15555550778")).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // Locked until the oldest send falls out of the hour, not just the cooldown
+        assert!((590..=600).contains(&retry_after(&response)));
+        assert_eq!(state.phone_verify_sends.get("+15555550778").unwrap().len(), 5);
+    }
+
+    fn registration(email: &str, password: &str, phone_number: &str) -> Json<RegisterRequest> {
+        Json(RegisterRequest {
+            email: email.to_string(),
+            password: password.to_string(),
+            phone_number: phone_number.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn registering_a_taken_email_is_a_conflict() {
+        let state = test_state();
+        create_test_user(&state, "taken@example.com");
+        let error = register(State(state), registration("taken@example.com", "correct horse battery", "+15555550100")).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::CONFLICT);
+        assert_eq!(error.details.unwrap()["fields"][0]["code"], "taken");
+    }
+
+    #[tokio::test]
+    async fn weak_password_is_rejected() {
+        let state = test_state();
+        for password in ["short", "password1", "aaaaaaaaaa", "newuser"] {
+            let error = register(State(state.clone()), registration("newuser@example.com", password, "+15555550100")).await.unwrap_err();
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+            let field = &error.details.unwrap()["fields"][0];
+            assert_eq!((field["field"].as_str(), field["code"].as_str()), (Some("password"), Some("weak")), "{password:?}");
+        }
+        assert!(state.user_core.find_by_email("newuser@example.com").unwrap().is_none());
+    }
+
+    #[test]
+    fn idle_limiters_are_swept_once_over_threshold() {
+        let limiters = Limiters::new();
+        let quota = Quota::per_minute(NonZeroU32::new(5).unwrap());
+        for i in 0..LIMITER_SWEEP_THRESHOLD {
+            limiters.insert(format!("idle-{}", i), RateLimiter::keyed(quota));
+        }
+        let busy = RateLimiter::keyed(quota);
+        busy.check_key(&"busy".to_string()).unwrap();
+        limiters.insert("busy".to_string(), busy);
+
+        prune_idle_limiters(&limiters);
+        assert_eq!(limiters.len(), 1);
+        assert!(limiters.contains_key("busy"));
+    }
+}
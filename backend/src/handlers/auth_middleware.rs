@@ -130,7 +130,7 @@ pub async fn validate_tier3_self_hosted(
     mut request: Request<Body>,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
-    // Extract client IP: Prefer X-Forwarded-For, fallback to ConnectInfo
+    // Extract client IP: the peer, or X-Forwarded-For when the peer is one of our proxies
     if std::env::var("ENVIRONMENT").expect("ENVIRONMENT not set") == "development" {
         // Insert validated user into extensions
         request.extensions_mut().insert(Tier3SelfHostedUser {
@@ -140,13 +140,13 @@ pub async fn validate_tier3_self_hosted(
         tracing::debug!("Tier 3 self-hosted validation passed for user 1");
         return Ok(next.run(request).await);
     }
-    let client_ip = if let Some(xff_header) = request.headers().get("x-forwarded-for") {
-        xff_header
-            .to_str()
-            .map(|s| s.split(',').next().map(str::trim).unwrap_or("").to_string())
-            .unwrap_or_default()
-    } else if let Some(ConnectInfo(addr)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
-        addr.ip().to_string()
+    let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+    let client_ip = if let Some(ip) = crate::utils::client_ip::resolve_client_ip(
+        peer,
+        request.headers(),
+        crate::utils::client_ip::trusted_proxies(),
+    ) {
+        ip.to_string()
     } else {
         tracing::warn!("Unable to determine client IP for self-hosted validation");
         return Err((
@@ -120,3 +120,65 @@ pub async fn update_topup(
     }
 }
 
+
+pub async fn get_credit_estimate(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let user = state.user_core.find_by_id(auth_user.user_id)
+        .map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Database error: {}", e)}))
+        ))?
+        .ok_or_else(|| (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "User not found"}))
+        ))?;
+    let user_settings = state.user_core.get_user_settings(auth_user.user_id).ok();
+    let currency = crate::utils::usage::billing_currency(&user.phone_number);
+
+    // Users outside the supported countries use their own twilio so nothing is charged from credits
+    if crate::utils::usage::messages_are_included(&user.phone_number) {
+        return Ok(Json(json!({
+            "currency": currency,
+            "credits": user.credits,
+            "monthly_credits_left": user.credits_left,
+            "usage_included": true,
+        })));
+    }
+
+    let costs = crate::utils::usage::credit_costs_for_user(&user, user_settings.as_ref());
+    let charge_back_threshold = std::env::var("CHARGE_BACK_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse::<f32>().ok())
+        .unwrap_or(2.00);
+    let (seconds_to_threshold, seconds_to_zero) = crate::utils::usage::voice_seconds_budget(
+        user.credits,
+        costs.voice_second,
+        charge_back_threshold,
+    );
+
+    // Messages use the monthly quota (credits_left) first, one per message, then the credit balance
+    let sms_from_credits = if costs.message > 0.0 {
+        (user.credits.max(0.0) / costs.message).floor() as i32
+    } else {
+        0
+    };
+    let sms_remaining = user.credits_left.max(0.0).floor() as i32 + sms_from_credits;
+
+    Ok(Json(json!({
+        "currency": currency,
+        "credits": user.credits,
+        "monthly_credits_left": user.credits_left,
+        "usage_included": false,
+        "cost_per_voice_minute": costs.voice_second * 60.0,
+        "cost_per_sms": costs.message,
+        "cost_per_notification_sms": costs.noti_msg,
+        "cost_per_notification_call": costs.noti_call,
+        "voice_minutes_remaining": seconds_to_zero.max(0) / 60,
+        // only meaningful when auto top-up is on since that's when we recharge at the threshold
+        "voice_minutes_until_recharge": if user.charge_when_under { Some(seconds_to_threshold.max(0) / 60) } else { None },
+        "sms_remaining": sms_remaining,
+        "charge_back_threshold": charge_back_threshold,
+    })))
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    handlers::auth_middleware::AuthUser,
+    utils::bridge::{normalize_contact_name, unified_contacts, UnifiedContact},
+    AppState,
+};
+
+#[derive(Deserialize)]
+pub struct ContactsQuery {
+    search: Option<String>,
+}
+
+/// Contacts merged across all connected bridges, optionally filtered by name
+pub async fn get_contacts(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<ContactsQuery>,
+) -> Result<Json<Vec<UnifiedContact>>, (StatusCode, Json<serde_json::Value>)> {
+    let mut contacts = unified_contacts(&state, auth_user.user_id).await.map_err(|e| {
+        tracing::error!("Failed to load contacts for user {}: {}", auth_user.user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Failed to load contacts"}))
+        )
+    })?;
+    if let Some(search) = params.search.as_deref().map(normalize_contact_name).filter(|s| !s.is_empty()) {
+        contacts.retain(|contact| normalize_contact_name(&contact.name).contains(&search));
+    }
+    Ok(Json(contacts))
+}
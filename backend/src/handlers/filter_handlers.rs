@@ -12,7 +12,7 @@ use crate::{
     AppState,
     models::user_models::{
         NewWaitingCheck, NewPrioritySender,
-        NewKeyword
+        NewKeyword, NewRoomNotificationPref
     },
     handlers::auth_middleware::AuthUser,
 };
@@ -33,19 +33,48 @@ pub struct PrioritySenderRequest {
     noti_mode: String, // "all", "focus"
 }
 
+#[derive(Deserialize)]
+pub struct RoomNotificationPrefRequest {
+    platform: String, // whatsapp, telegram, ..
+    room_id: String,
+    mode: Option<String>, // "always", "never", or null to remove the override
+    noti_type: Option<String>, // "sms" or "call"
+}
+
 #[derive(Deserialize)]
 pub struct KeywordRequest {
     keyword: String,
     service_type: String, // imap, whatsapp, etc.
+    action: Option<String>, // notify_call, notify_sms (default), ignore or digest_only
 }
 
 // Response DTOs
 #[derive(Serialize)]
 pub struct WaitingCheckResponse {
+    id: Option<i32>,
     user_id: i32,
     content: String,
     service_type: String,
     noti_type: Option<String>, // "sms" or "call"
+    status: String, // "active" or "resolved"
+    resolved_at: Option<i32>,
+    resolved_reason: Option<String>, // "manual" or "auto"
+}
+
+#[derive(Serialize)]
+pub struct RoomNotificationPrefResponse {
+    platform: String,
+    room_id: String,
+    mode: String,
+    noti_type: Option<String>,
+    updated_at: i32,
+}
+
+#[derive(Serialize)]
+pub struct KeywordResponse {
+    keyword: String,
+    service_type: String,
+    action: String,
 }
 
 #[derive(Serialize)]
@@ -117,6 +146,30 @@ pub async fn delete_waiting_check(
     }
 }
 
+pub async fn resolve_waiting_check(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<i32>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    match state.user_repository.resolve_waiting_check(auth_user.user_id, id, "manual") {
+        Ok(_) => {
+            tracing::debug!("Waiting check {} marked resolved by user {}", id, auth_user.user_id);
+            Ok(Json(json!({"message": "Waiting check resolved"})))
+        },
+        Err(DieselError::NotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Waiting check not found or already resolved"}))
+        )),
+        Err(e) => {
+            tracing::error!("Failed to resolve waiting check {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Database error: {}", e)}))
+            ))
+        },
+    }
+}
+
 pub async fn get_waiting_checks(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser
@@ -133,10 +186,14 @@ pub async fn get_waiting_checks(
         })?;
 
     let response: Vec<WaitingCheckResponse> = checks.into_iter().map(|check| WaitingCheckResponse {
+        id: check.id,
         user_id: check.user_id,
         content: check.content,
         service_type: check.service_type,
         noti_type: check.noti_type,
+        status: if check.resolved_at.is_some() { "resolved" } else { "active" }.to_string(),
+        resolved_at: check.resolved_at,
+        resolved_reason: check.resolved_reason,
     }).collect();
 
     Ok(Json(response))
@@ -254,6 +311,13 @@ pub async fn create_keyword(
     Json(request): Json<KeywordRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     println!("Attempting to create keyword for user {}", auth_user.user_id);
+    let action = request.action.clone().unwrap_or_else(|| "notify_sms".to_string());
+    if !crate::proactive::utils::KEYWORD_ACTIONS.contains(&action.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("Invalid action, use one of: {}", crate::proactive::utils::KEYWORD_ACTIONS.join(", "))}))
+        ));
+    }
 
     // First check if the keyword already exists
     let existing_keywords = state.user_repository.get_keywords(auth_user.user_id, &request.service_type)
@@ -277,6 +341,7 @@ pub async fn create_keyword(
         user_id: auth_user.user_id,
         keyword: request.keyword.clone(),
         service_type: request.service_type,
+        action,
     };
 
     match state.user_repository.create_keyword(&new_keyword) {
@@ -295,6 +360,27 @@ pub async fn create_keyword(
     }
 }
 
+pub async fn get_keywords(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(service_type): Path<String>,
+) -> Result<Json<Vec<KeywordResponse>>, (StatusCode, Json<serde_json::Value>)> {
+    let keywords = state.user_repository.get_keywords(auth_user.user_id, &service_type)
+        .map_err(|e| {
+            tracing::error!("Failed to fetch keywords for user {}: {}", auth_user.user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Database error: {}", e)}))
+            )
+        })?;
+    let response = keywords.into_iter().map(|keyword| KeywordResponse {
+        keyword: keyword.keyword,
+        service_type: keyword.service_type,
+        action: keyword.action,
+    }).collect();
+    Ok(Json(response))
+}
+
 pub async fn delete_keyword(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -320,3 +406,138 @@ pub async fn delete_keyword(
         },
     }
 }
+
+// Per-room notification overrides
+pub async fn get_room_notification_prefs(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<RoomNotificationPrefResponse>>, (StatusCode, Json<serde_json::Value>)> {
+    let prefs = state.user_repository.get_room_notification_prefs(auth_user.user_id)
+        .map_err(|e| {
+            tracing::error!("Failed to fetch room notification prefs for user {}: {}", auth_user.user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Database error: {}", e)}))
+            )
+        })?;
+    let response = prefs.into_iter().map(|pref| RoomNotificationPrefResponse {
+        platform: pref.platform,
+        room_id: pref.room_id,
+        mode: pref.mode,
+        noti_type: pref.noti_type,
+        updated_at: pref.updated_at,
+    }).collect();
+    Ok(Json(response))
+}
+
+pub async fn set_room_notification_pref(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(request): Json<RoomNotificationPrefRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if request.room_id.trim().is_empty() || request.platform.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "platform and room_id are required"}))
+        ));
+    }
+    if let Some(noti_type) = request.noti_type.as_deref() {
+        if noti_type != "sms" && noti_type != "call" {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "noti_type must be \"sms\" or \"call\""}))
+            ));
+        }
+    }
+    let db_error = |e: DieselError| {
+        tracing::error!("Failed to update room notification pref for user {}: {}", auth_user.user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Database error: {}", e)}))
+        )
+    };
+    match request.mode.as_deref() {
+        None => {
+            state.user_repository.delete_room_notification_pref(auth_user.user_id, &request.platform.to_lowercase(), &request.room_id)
+                .map_err(db_error)?;
+            Ok(Json(json!({"message": "Room notification override removed"})))
+        }
+        Some(mode @ ("always" | "never")) => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i32;
+            state.user_repository.set_room_notification_pref(&NewRoomNotificationPref {
+                user_id: auth_user.user_id,
+                platform: request.platform.to_lowercase(),
+                room_id: request.room_id.clone(),
+                mode: mode.to_string(),
+                noti_type: request.noti_type.clone(),
+                updated_at: now,
+            }).map_err(db_error)?;
+            Ok(Json(json!({"message": "Room notification override saved"})))
+        }
+        Some(_) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "mode must be \"always\", \"never\" or null"}))
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    #[tokio::test]
+    async fn resolved_check_is_kept_but_no_longer_matched() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "waiting@example.com");
+        let owner = || AuthUser { user_id, is_admin: false };
+        for content in ["Reply from the landlord", "Package tracking number"] {
+            state.user_repository.create_waiting_check(&NewWaitingCheck {
+                user_id,
+                content: content.to_string(),
+                service_type: "messaging".to_string(),
+                noti_type: Some("sms".to_string()),
+            }).unwrap();
+        }
+        let Json(checks) = get_waiting_checks(State(state.clone()), owner()).await.unwrap();
+        let landlord = checks.iter().find(|c| c.content == "Reply from the landlord").unwrap().id.unwrap();
+
+        resolve_waiting_check(State(state.clone()), owner(), Path(landlord)).await.unwrap();
+
+        let matching: Vec<String> = state.user_repository.get_waiting_checks(user_id, "messaging").unwrap()
+            .into_iter().map(|c| c.content).collect();
+        assert_eq!(matching, vec!["Package tracking number"]);
+
+        let Json(checks) = get_waiting_checks(State(state.clone()), owner()).await.unwrap();
+        let resolved = checks.iter().find(|c| c.id == Some(landlord)).unwrap();
+        assert_eq!(resolved.status, "resolved");
+        assert_eq!(resolved.resolved_reason.as_deref(), Some("manual"));
+        assert!(resolved.resolved_at.is_some());
+
+        let (status, _) = resolve_waiting_check(State(state.clone()), owner(), Path(landlord)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn users_cannot_resolve_each_others_checks() {
+        let state = test_state();
+        let owner = create_test_user(&state, "owner@example.com");
+        let other = create_test_user(&state, "other@example.com");
+        state.user_repository.create_waiting_check(&NewWaitingCheck {
+            user_id: owner,
+            content: "Offer letter".to_string(),
+            service_type: "email".to_string(),
+            noti_type: None,
+        }).unwrap();
+        let id = state.user_repository.get_waiting_checks(owner, "email").unwrap()[0].id.unwrap();
+
+        let (status, _) = resolve_waiting_check(State(state.clone()), AuthUser { user_id: other, is_admin: false }, Path(id))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(state.user_repository.get_waiting_checks(owner, "email").unwrap().len(), 1);
+    }
+}
@@ -7,12 +7,12 @@ use axum::{
 };
 use serde_json::json;
 use serde::{Deserialize, Serialize};
-use oauth2::TokenResponse;
 use reqwest::header::{AUTHORIZATION, ACCEPT, CONTENT_TYPE};
 use chrono::{DateTime, Utc, Duration};
 use chrono_tz::Tz;
 
 use crate::AppState;
+use crate::utils::google_tokens::{self, GoogleService, GoogleTokenError};
 
 #[derive(Debug, Deserialize)]
 pub struct TimeframeQuery {
@@ -30,6 +30,132 @@ pub struct CalendarEvent {
     pub status: Option<String>,
     #[serde(default)]
     pub reminders: Option<EventReminders>,
+    #[serde(default)]
+    pub attendees: Vec<EventAttendee>,
+    /// Set when Google left attendees out of the response, e.g. on very large events
+    #[serde(rename = "attendeesOmitted", default)]
+    pub attendees_omitted: Option<bool>,
+    #[serde(rename = "guestsCanSeeOtherGuests", default)]
+    pub guests_can_see_other_guests: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EventAttendee {
+    pub email: Option<String>,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    /// "needsAction", "declined", "tentative" or "accepted"
+    #[serde(rename = "responseStatus")]
+    pub response_status: Option<String>,
+    /// The attendee is the calendar's owner, i.e. our user
+    #[serde(rename = "self", default)]
+    pub is_self: Option<bool>,
+    #[serde(default)]
+    pub organizer: Option<bool>,
+    /// Meeting rooms and other resources are listed as attendees too
+    #[serde(default)]
+    pub resource: Option<bool>,
+}
+
+/// Who has answered an invite, not counting the user themselves or booked rooms
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct AttendeeSummary {
+    pub total: usize,
+    pub accepted: usize,
+    pub declined: usize,
+    pub tentative: usize,
+    pub pending: usize,
+    /// Name of whoever organizes the event, None when it's the user
+    pub organizer: Option<String>,
+    /// Names of the guests who declined
+    pub declined_names: Vec<String>,
+    /// The user's own answer when they're invited
+    pub own_response: Option<String>,
+    /// The organizer hides the guest list or Google left guests out, so the counts are partial
+    pub limited: bool,
+}
+
+/// Most guests named when reading out a list, the rest are counted
+const MAX_SPOKEN_NAMES: usize = 3;
+
+/// "Anna", "Anna and Ben", "Anna, Ben, Cecilia and 2 others"
+fn spoken_names(names: &[String]) -> String {
+    let named = &names[..names.len().min(MAX_SPOKEN_NAMES)];
+    let others = names.len() - named.len();
+    match (named, others) {
+        ([], _) => String::new(),
+        ([only], 0) => only.clone(),
+        (_, 0) => format!("{} and {}", named[..named.len() - 1].join(", "), named[named.len() - 1]),
+        (_, others) => format!("{} and {} {}", named.join(", "), others, if others == 1 { "other" } else { "others" }),
+    }
+}
+
+impl AttendeeSummary {
+    /// "organized by Anna, 3 of 5 have accepted, 1 declined: Ben, 1 hasn't responded"
+    pub fn spoken(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(organizer) = &self.organizer {
+            parts.push(format!("organized by {}", organizer));
+        }
+        if self.total == 0 {
+            parts.push("the guest list is hidden".to_string());
+        } else {
+            parts.push(format!("{} of {} {} accepted", self.accepted, self.total, if self.total == 1 { "has" } else { "have" }));
+        }
+        if self.declined > 0 {
+            parts.push(format!("{} declined: {}", self.declined, spoken_names(&self.declined_names)));
+        }
+        if self.tentative > 0 {
+            parts.push(format!("{} maybe", self.tentative));
+        }
+        if self.pending > 0 {
+            parts.push(format!("{} {} responded", self.pending, if self.pending == 1 { "hasn't" } else { "haven't" }));
+        }
+        if self.limited && self.total > 0 {
+            parts.push("more guests aren't shown".to_string());
+        }
+        if self.own_response.as_deref() == Some("needsAction") {
+            parts.push("you haven't responded".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Counts attendee responses of an event. None for events without other guests. On events the user
+/// doesn't organize, Google may only list the user, which gives a `limited` summary without counts.
+pub fn attendee_summary(event: &CalendarEvent) -> Option<AttendeeSummary> {
+    let mut summary = AttendeeSummary::default();
+    let mut organizes = false;
+    for attendee in &event.attendees {
+        if attendee.is_self.unwrap_or(false) {
+            summary.own_response = attendee.response_status.clone();
+            organizes = attendee.organizer.unwrap_or(false);
+            continue;
+        }
+        if attendee.resource.unwrap_or(false) {
+            continue;
+        }
+        let name = attendee.display_name.clone().or_else(|| attendee.email.clone()).unwrap_or_else(|| "someone".to_string());
+        if attendee.organizer.unwrap_or(false) {
+            summary.organizer = Some(name.clone());
+        }
+        summary.total += 1;
+        match attendee.response_status.as_deref() {
+            Some("accepted") => summary.accepted += 1,
+            Some("declined") => {
+                summary.declined += 1;
+                summary.declined_names.push(name);
+            }
+            Some("tentative") => summary.tentative += 1,
+            _ => summary.pending += 1,
+        }
+    }
+    let guests_hidden = !organizes && event.guests_can_see_other_guests == Some(false);
+    summary.limited = event.attendees_omitted.unwrap_or(false) || guests_hidden;
+    if summary.total == 0 && !(summary.limited && summary.own_response.is_some()) {
+        return None;
+    }
+    Some(summary)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -202,6 +328,15 @@ impl std::fmt::Display for CalendarError {
     }
 }
 
+impl From<GoogleTokenError> for CalendarError {
+    fn from(e: GoogleTokenError) -> Self {
+        match e {
+            GoogleTokenError::NoConnection(_) => CalendarError::NoConnection,
+            other => CalendarError::TokenError(other.to_string()),
+        }
+    }
+}
+
 pub async fn create_calendar_event(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -231,22 +366,10 @@ pub async fn create_calendar_event(
         }
     }
 
-    // Get tokens
-    let (access_token, refresh_token) = match state.user_repository.get_google_calendar_tokens(auth_user.user_id) {
-        Ok(Some((access, refresh))) => (access, refresh),
-        Ok(None) => return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "No active Google Calendar connection found"
-            }))
-        )),
-        Err(e) => return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": format!("Failed to get calendar tokens: {}", e)
-            }))
-        )),
-    };
+    // Get tokens, refreshing the access token up front if it has expired
+    let (access_token, refresh_token) = google_tokens::valid_tokens(&state, GoogleService::Calendar, auth_user.user_id)
+        .await
+        .map_err(GoogleTokenError::into_response)?;
 
     // Calculate end time
     let end_time = event_request.start_time + Duration::minutes(event_request.duration_minutes as i64);
@@ -318,43 +441,15 @@ pub async fn create_calendar_event(
         Err(e) => {
             // Check if error might be due to expired token
             if e.contains("401") {
-                tracing::info!("Access token expired, attempting to refresh");
-                
-                // Create HTTP client for token refresh
-                let http_client = reqwest::ClientBuilder::new()
-                    .redirect(reqwest::redirect::Policy::none())
-                    .build()
-                    .expect("Client should build");
-
-                // Try to refresh the token
-                let token_result = state
-                    .google_calendar_oauth_client
-                    .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token.clone()))
-                    .request_async(&http_client)
-                    .await
-                    .map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({
-                            "error": format!("Failed to refresh token: {}", e)
-                        }))
-                    ))?;
-
-                let new_access_token = token_result.access_token().secret();
-                let expires_in = token_result.expires_in()
-                    .unwrap_or_default()
-                    .as_secs() as i32;
-
-                // Update the access token in the database
-                state.user_repository.update_google_calendar_access_token(
+                tracing::info!("Access token rejected, attempting to refresh");
+                let (new_access_token, _) = google_tokens::refresh_access_token(
+                    &state,
+                    GoogleService::Calendar,
                     auth_user.user_id,
-                    new_access_token.as_str(),
-                    expires_in,
-                ).map_err(|e| (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({
-                        "error": format!("Failed to update access token: {}", e)
-                    }))
-                ))?;
+                    &refresh_token,
+                )
+                .await
+                .map_err(GoogleTokenError::into_response)?;
 
                 // Retry with new token
                 match create_event_with_token(&client, new_access_token.as_str(), &event).await {
@@ -549,13 +644,16 @@ pub async fn handle_calendar_fetching(
                         _ => 0 // Default to 0 for all-day events or invalid times
                     };
 
+                    let attendees = attendee_summary(&event);
                     let summary = event.summary.unwrap_or_else(|| "No title".to_string());
 
                     json!({
                         "summary": summary,
                         "start": start_time,
                         "end": end_time,
-                        "duration_minutes": duration_minutes
+                        "duration_minutes": duration_minutes,
+                        "attendee_responses": attendees.as_ref().map(|attendees| attendees.spoken()),
+                        "attendees": attendees
                     })
                 })
                 .collect();
@@ -719,22 +817,7 @@ pub async fn fetch_calendar_events(
 ) -> Result<Vec<CalendarEvent>, CalendarError> {
     // Get Google Calendar tokens
     tracing::debug!("Getting Google Calendar tokens for user_id: {}", user_id);
-    let (access_token, refresh_token) = match state.user_repository.get_google_calendar_tokens(user_id) {
-        Ok(Some((access, refresh))) => {
-            tracing::debug!("Access token length: {}, Refresh token length: {}", 
-                access.len(), refresh.len());
-            (access, refresh)
-        },
-        Ok(None) => {
-            tracing::debug!("No active Google Calendar connection found");
-            return Err(CalendarError::NoConnection);
-        },
-        Err(e) => {
-            tracing::error!("Error getting tokens: {}", e);
-            tracing::error!("Failed to get calendar tokens: {}", e);
-            return Err(CalendarError::TokenError(format!("Failed to decrypt tokens: {}", e)));
-        }
-    };
+    let (access_token, refresh_token) = google_tokens::valid_tokens(state, GoogleService::Calendar, user_id).await?;
 
 
     // Create HTTP client for Google Calendar API
@@ -745,40 +828,6 @@ pub async fn fetch_calendar_events(
     let end_time = timeframe.end.format("%Y-%m-%dT%H:%M:%SZ").to_string();
     println!("Formatted time range: {} to {}", start_time, end_time);
 
-    async fn refresh_access_token(
-        state: &AppState,
-        user_id: i32,
-        refresh_token: String,
-    ) -> Result<String, CalendarError> {
-        let http_client = reqwest::ClientBuilder::new()
-            .redirect(reqwest::redirect::Policy::none())
-            .build()
-            .expect("Client should build");
-
-        tracing::info!("Attempting to refresh token");
-        
-        let token_result = state
-            .google_calendar_oauth_client
-            .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token))
-            .request_async(&http_client)
-            .await
-            .map_err(|e| CalendarError::TokenError(e.to_string()))?;
-
-        let new_access_token = token_result.access_token().secret().to_string();
-        let expires_in = token_result.expires_in()
-            .unwrap_or_default()
-            .as_secs() as i32;
-
-        // Update the access token in the database
-        state.user_repository.update_google_calendar_access_token(
-            user_id,
-            &new_access_token,
-            expires_in,
-        ).map_err(|e| CalendarError::TokenError(e.to_string()))?;
-
-        Ok(new_access_token)
-    }
-
     async fn fetch_with_token(
         client: &reqwest::Client,
         state: &AppState,
@@ -857,12 +906,12 @@ pub async fn fetch_calendar_events(
                     if err.contains("401") {
                         // Token expired, refresh and retry
                         tracing::info!("Access token expired, refreshing...");
-                        match refresh_access_token(state, user_id, refresh_token.to_string()).await {
-                            Ok(new_token) => {
+                        match google_tokens::refresh_access_token(state, GoogleService::Calendar, user_id, refresh_token).await {
+                            Ok((new_token, _)) => {
                                 tracing::info!("Token refreshed successfully, retrying fetch");
                                 attempt_fetch(client, &new_token, start_time, end_time).await
                             },
-                            Err(e) => Err(e),
+                            Err(e) => Err(e.into()),
                         }
                     } else {
                         Err(e)
@@ -874,7 +923,7 @@ pub async fn fetch_calendar_events(
         }
     }
 
-    let result = fetch_with_token(
+    fetch_with_token(
         &client,
         state,
         user_id,
@@ -882,56 +931,62 @@ pub async fn fetch_calendar_events(
         &refresh_token,
         &start_time,
         &end_time
-    ).await;
+    ).await
+}
 
-    match result {
-        Ok(events) => {
-            Ok(events)
-        },
-        Err(CalendarError::ApiError(e)) if e.contains("401") => {
-            println!("Token expired, starting refresh process...");
-            tracing::info!("Access token expired, attempting to refresh");
-            
-            let http_client = reqwest::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-                .expect("Client should build");
-
-            println!("Exchanging refresh token...");
-            let token_result = state
-                .google_calendar_oauth_client
-                .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token.to_string()))
-                .request_async(&http_client)
-                .await
-                .map_err(|e| CalendarError::TokenError(e.to_string()))?;
-
-            let new_access_token = token_result.access_token().secret();
-            let expires_in = token_result.expires_in()
-                .unwrap_or_default()
-                .as_secs() as i32;
-            println!("New token received, expires in {} seconds", expires_in);
-
-            // Update the access token in the database
-            println!("Updating access token in database...");
-            state.user_repository.update_google_calendar_access_token(
-                user_id,
-                new_access_token.clone().as_str(),
-                expires_in,
-            ).map_err(|e| CalendarError::TokenError(e.to_string()))?;
-
-            // Retry with new token
-            println!("Retrying calendar request with new token...");
-            fetch_with_token(
-                &client,
-                state,
-                user_id,
-                &new_access_token,
-                &refresh_token,
-                &start_time,
-                &end_time
-            ).await
-        },
-        Err(e) => Err(e),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(attendees: serde_json::Value) -> CalendarEvent {
+        serde_json::from_value(json!({
+            "id": "evt1",
+            "summary": "Planning",
+            "start": { "dateTime": "2026-10-20T09:00:00Z" },
+            "end": { "dateTime": "2026-10-20T10:00:00Z" },
+            "attendees": attendees,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn responses_are_counted_without_the_user_or_rooms() {
+        let summary = attendee_summary(&event(json!([
+            { "email": "me@example.com", "self": true, "responseStatus": "needsAction" },
+            { "email": "anna@example.com", "displayName": "Anna", "organizer": true, "responseStatus": "accepted" },
+            { "email": "ben@example.com", "responseStatus": "declined" },
+            { "email": "cecilia@example.com", "responseStatus": "tentative" },
+            { "email": "room@resource.calendar.google.com", "resource": true, "responseStatus": "accepted" },
+        ]))).unwrap();
+        assert_eq!((summary.total, summary.accepted, summary.declined, summary.tentative, summary.pending), (3, 1, 1, 1, 0));
+        assert_eq!(summary.organizer.as_deref(), Some("Anna"));
+        assert_eq!(
+            summary.spoken(),
+            "organized by Anna, 1 of 3 have accepted, 1 declined: ben@example.com, 1 maybe, you haven't responded"
+        );
     }
-}
 
+    #[test]
+    fn users_own_event_has_no_organizer_line() {
+        let summary = attendee_summary(&event(json!([
+            { "email": "me@example.com", "self": true, "organizer": true, "responseStatus": "accepted" },
+            { "email": "anna@example.com", "displayName": "Anna", "responseStatus": "accepted" },
+        ]))).unwrap();
+        assert_eq!(summary.organizer, None);
+        assert_eq!(summary.spoken(), "1 of 1 has accepted");
+    }
+
+    #[test]
+    fn long_decline_lists_are_cut_short() {
+        let declined: Vec<serde_json::Value> = ["Anna", "Ben", "Cecilia", "Daniel", "Eva"]
+            .iter()
+            .map(|name| json!({ "displayName": name, "responseStatus": "declined" }))
+            .collect();
+        let summary = attendee_summary(&event(json!(declined))).unwrap();
+        assert_eq!(summary.spoken(), "0 of 5 have accepted, 5 declined: Anna, Ben, Cecilia and 2 others");
+
+        assert_eq!(spoken_names(&["Anna".to_string(), "Ben".to_string()]), "Anna and Ben");
+        let four: Vec<String> = ["Anna", "Ben", "Cecilia", "Daniel"].iter().map(|name| name.to_string()).collect();
+        assert_eq!(spoken_names(&four), "Anna, Ben, Cecilia and 1 other");
+    }
+}
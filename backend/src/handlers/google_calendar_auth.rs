@@ -312,6 +312,7 @@ pub async fn google_callback(
     }
 
     tracing::info!("Successfully stored Google Calendar connection for user {}", user_id);
+    state.connection_events.publish(user_id, "google_calendar", crate::utils::connection_events::ConnectionStatus::Connected);
 
     let frontend_url = std::env::var("FRONTEND_URL")
         .expect("FRONTEND_URL must be set");
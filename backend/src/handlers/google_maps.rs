@@ -5,6 +5,44 @@ use axum::{
 use serde::Deserialize;
 use serde_json::{json, Value};
 use reqwest;
+use crate::utils::tool_exec::{GeocodeError, MAPS_UNAVAILABLE_MESSAGE};
+
+/// "lat,lon" for the Directions API, or the address itself when Geoapify couldn't resolve it
+async fn waypoint(client: &reqwest::Client, address: &str, api_key: &str, which: &str) -> String {
+    let geocoded = crate::utils::tool_exec::get_coordinates(client, address, api_key).await;
+    waypoint_from(address, geocoded, which)
+}
+
+fn waypoint_from(address: &str, geocoded: Result<(f64, f64, String), GeocodeError>, which: &str) -> String {
+    match geocoded {
+        Ok((lat, lon, _formatted)) => format!("{},{}", lat, lon),
+        Err(e) => {
+            crate::utils::tool_exec::log_geocode_error(&format!("geocoding the {} address", which), &e);
+            address.to_string()
+        }
+    }
+}
+
+/// Status and spoken message for a Directions API status other than OK
+fn directions_error(api_status: &str, error_message: &str, start_address: &str, end_address: &str) -> (StatusCode, String) {
+    match api_status {
+        "OVER_QUERY_LIMIT" | "OVER_DAILY_LIMIT" => {
+            tracing::error!("Google Maps quota exceeded while getting directions: {} {}", api_status, error_message);
+            (StatusCode::SERVICE_UNAVAILABLE, MAPS_UNAVAILABLE_MESSAGE.to_string())
+        }
+        "NOT_FOUND" | "ZERO_RESULTS" => {
+            tracing::info!("No route from {:?} to {:?}: {}", start_address, end_address, api_status);
+            (StatusCode::BAD_REQUEST, format!(
+                "I couldn't find a route from {} to {}. Could you give more specific addresses?",
+                start_address, end_address
+            ))
+        }
+        _ => {
+            tracing::warn!("Directions API error: {} {}", api_status, error_message);
+            (StatusCode::BAD_REQUEST, MAPS_UNAVAILABLE_MESSAGE.to_string())
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct DirectionsRequest {
@@ -19,41 +57,21 @@ pub async fn handle_get_directions(
     let geoapify_api_key = std::env::var("GEOAPIFY_API_KEY")
         .map_err(|_| (
             StatusCode::INTERNAL_SERVER_ERROR,
-            AxumJson(json!({"error": "Missing GEOAPIFY_API_KEY environment variable"})),
+            AxumJson(json!({"error": "Missing GEOAPIFY_API_KEY environment variable", "message": MAPS_UNAVAILABLE_MESSAGE})),
         ))?;
 
     let google_maps_api_key = std::env::var("GOOGLE_API_KEY")
         .map_err(|_| (
             StatusCode::INTERNAL_SERVER_ERROR,
-            AxumJson(json!({"error": "Missing GOOGLE_API_KEY environment variable"})),
+            AxumJson(json!({"error": "Missing GOOGLE_API_KEY environment variable", "message": MAPS_UNAVAILABLE_MESSAGE})),
         ))?;
 
     let client = reqwest::Client::new();
-    println!("haha");
 
-    // Get starting coordinates
-    let (start_lat, start_lon, _start_formatted) = match crate::utils::tool_exec::get_coordinates(&client, &request.start_address, &geoapify_api_key).await {
-        Ok(coords) => coords,
-        Err(e) => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                AxumJson(json!({"error": format!("Failed to geocode start address: {}", e)})),
-            ));
-        }
-    };
-
-    println!("haha");
-    // Get ending coordinates
-    let (end_lat, end_lon, _end_formatted) = match crate::utils::tool_exec::get_coordinates(&client, &request.end_address, &geoapify_api_key).await {
-        Ok(coords) => coords,
-        Err(e) => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                AxumJson(json!({"error": format!("Failed to geocode end address: {}", e)})),
-            ));
-        }
-    };
-    println!("haha");
+    // Get starting and ending coordinates. Google geocodes plain addresses itself, so when Geoapify
+    // can't (unknown place, quota, outage) the address is passed on as is and Google gets a try.
+    let origin = waypoint(&client, &request.start_address, &geoapify_api_key, "start").await;
+    let destination = waypoint(&client, &request.end_address, &geoapify_api_key, "end").await;
 
     // Normalize mode: map "public transport" to "transit", and validate others
     let api_mode = match request.mode.to_lowercase().as_str() {
@@ -65,24 +83,34 @@ pub async fn handle_get_directions(
         _ => {
             return Err((
                 StatusCode::BAD_REQUEST,
-                AxumJson(json!({"error": "Invalid mode. Supported: driving, walking, public transport (or transit), bicycling"})),
+                AxumJson(json!({
+                    "error": "Invalid mode. Supported: driving, walking, public transport (or transit), bicycling",
+                    "message": "I can give directions for driving, walking, public transport or cycling. Which one would you like?"
+                })),
             ));
         }
     };
 
     // Call Google Maps Directions API with coordinates and mode
     let directions_url = format!(
-        "https://maps.googleapis.com/maps/api/directions/json?origin={},{}&destination={},{}&mode={}&key={}",
-        start_lat, start_lon, end_lat, end_lon, api_mode, google_maps_api_key
+        "https://maps.googleapis.com/maps/api/directions/json?origin={}&destination={}&mode={}&key={}",
+        urlencoding::encode(&origin), urlencoding::encode(&destination), api_mode, google_maps_api_key
     );
-    println!("haha");
     let directions_response: Value = match client.get(&directions_url).send().await {
         Ok(res) => {
             let status = res.status();
             if !status.is_success() {
+                if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    tracing::error!("Google Maps quota exceeded while getting directions: {}", status);
+                } else {
+                    tracing::warn!("Google Maps API returned status code: {}", status);
+                }
                 return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    AxumJson(json!({"error": format!("Google Maps API returned status code: {}", status)})),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    AxumJson(json!({
+                        "error": format!("Google Maps API returned status code: {}", status),
+                        "message": MAPS_UNAVAILABLE_MESSAGE
+                    })),
                 ));
             }
             match res.json().await {
@@ -104,13 +132,14 @@ pub async fn handle_get_directions(
             ));
         }
     };
-    println!("haha");
     // Check for API errors
-    if directions_response["status"].as_str() != Some("OK") {
+    let api_status = directions_response["status"].as_str().unwrap_or("UNKNOWN");
+    if api_status != "OK" {
         let error_message = directions_response["error_message"].as_str().unwrap_or("Unknown error");
+        let (status, message) = directions_error(api_status, error_message, &request.start_address, &request.end_address);
         return Err((
-            StatusCode::BAD_REQUEST,
-            AxumJson(json!({"error": format!("Directions API error: {}", error_message)})),
+            status,
+            AxumJson(json!({"error": format!("Directions API error: {}", error_message), "message": message})),
         ));
     }
 
@@ -118,7 +147,7 @@ pub async fn handle_get_directions(
     let duration;
     let distance;
     let mut instructions: Vec<String> = Vec::new();
-    println!("works?: {:#?}", directions_response);
+    tracing::debug!("Directions API response: {:#?}", directions_response);
 
     if let Some(routes) = directions_response["routes"].as_array() {
         if let Some(first_route) = routes.first() {
@@ -187,7 +216,6 @@ pub async fn handle_get_directions(
             AxumJson(json!({"error": "No routes found in response"})),
         ));
     }
-    println!("haha last one");
     if instructions.is_empty() {
         return Err((
             StatusCode::BAD_REQUEST,
@@ -201,3 +229,42 @@ pub async fn handle_get_directions(
         "instructions": instructions
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geocoded_address_becomes_coordinates() {
+        let geocoded = Ok((60.1699, 24.9384, "Helsinki, Finland".to_string()));
+        assert_eq!(waypoint_from("Helsinki", geocoded, "start"), "60.1699,24.9384");
+    }
+
+    #[test]
+    fn unresolved_address_is_passed_on_as_is() {
+        for error in [
+            GeocodeError::NotFound("Kamppi".to_string()),
+            GeocodeError::QuotaExceeded("429".to_string()),
+            GeocodeError::Unavailable("timeout".to_string()),
+        ] {
+            assert_eq!(waypoint_from("Kamppi, Helsinki", Err(error), "end"), "Kamppi, Helsinki");
+        }
+    }
+
+    #[test]
+    fn quota_and_unknown_statuses_say_maps_are_unavailable() {
+        for api_status in ["OVER_QUERY_LIMIT", "OVER_DAILY_LIMIT"] {
+            assert_eq!(directions_error(api_status, "", "A", "B"), (StatusCode::SERVICE_UNAVAILABLE, MAPS_UNAVAILABLE_MESSAGE.to_string()));
+        }
+        for api_status in ["REQUEST_DENIED", "INVALID_REQUEST", "UNKNOWN_ERROR"] {
+            assert_eq!(directions_error(api_status, "", "A", "B"), (StatusCode::BAD_REQUEST, MAPS_UNAVAILABLE_MESSAGE.to_string()));
+        }
+    }
+
+    #[test]
+    fn missing_route_asks_for_better_addresses() {
+        let (status, message) = directions_error("ZERO_RESULTS", "", "home", "the lake");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(message, "I couldn't find a route from home to the lake. Could you give more specific addresses?");
+    }
+}
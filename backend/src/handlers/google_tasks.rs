@@ -5,13 +5,13 @@ use axum::{
     response::Json,
     http::StatusCode,
 };
-use oauth2::TokenResponse;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use chrono::{DateTime, Utc};
 use reqwest::header::{AUTHORIZATION, ACCEPT};
 
 use crate::AppState;
+use crate::utils::google_tokens::{self, GoogleService, GoogleTokenError};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateTaskRequest {
@@ -211,21 +211,9 @@ pub async fn create_task(
     task_request: &CreateTaskRequest,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Get Google Tasks tokens
-    let (access_token, refresh_token) = match state.user_repository.get_google_tasks_tokens(user_id) {
-        Ok(Some((access, refresh))) => (access, refresh),
-        Ok(None) => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "No active Google Tasks connection"}))
-            ));
-        }
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get tasks tokens: {}", e)}))
-            ));
-        }
-    };
+    let (access_token, refresh_token) = google_tokens::valid_tokens(state, GoogleService::Tasks, user_id)
+        .await
+        .map_err(GoogleTokenError::into_response)?;
 
     let client = reqwest::Client::new();
 
@@ -235,38 +223,10 @@ pub async fn create_task(
     let task = match result {
         Ok(task) => task,
         Err(TaskError::ApiError(e)) if e.contains("401") => {
-            tracing::info!("Access token expired, attempting to refresh");
-            
-            let http_client = reqwest::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-                .expect("Client should build");
-
-            // Refresh the token
-            let token_result = state
-                .google_tasks_oauth_client
-                .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token.clone()))
-                .request_async(&http_client)
+            tracing::info!("Access token rejected, attempting to refresh");
+            let (new_access_token, _) = google_tokens::refresh_access_token(state, GoogleService::Tasks, user_id, &refresh_token)
                 .await
-                .map_err(|e| (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": format!("Failed to refresh token: {}", e)}))
-                ))?;
-
-            let new_access_token = token_result.access_token().secret();
-            let expires_in = token_result.expires_in()
-                .unwrap_or_default()
-                .as_secs() as i32;
-
-            // Update the access token in the database
-            state.user_repository.update_google_tasks_access_token(
-                user_id,
-                new_access_token.clone().as_str(),
-                expires_in,
-            ).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update token: {}", e)}))
-            ))?;
+                .map_err(GoogleTokenError::into_response)?;
 
             // Retry with new token
             create_task_with_token(&client, &new_access_token, &task_request)
@@ -354,21 +314,9 @@ pub async fn get_tasks(
     user_id: i32,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Get Google Tasks tokens
-    let (access_token, refresh_token) = match state.user_repository.get_google_tasks_tokens(user_id) {
-        Ok(Some((access, refresh))) => (access, refresh),
-        Ok(None) => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "No active Google Tasks connection"}))
-            ));
-        }
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get tasks tokens: {}", e)}))
-            ));
-        }
-    };
+    let (access_token, refresh_token) = google_tokens::valid_tokens(state, GoogleService::Tasks, user_id)
+        .await
+        .map_err(GoogleTokenError::into_response)?;
 
     let client = reqwest::Client::new();
 
@@ -378,38 +326,10 @@ pub async fn get_tasks(
     let tasks = match result {
         Ok(tasks) => tasks,
         Err(TaskError::ApiError(e)) if e.contains("401") => {
-            tracing::info!("Access token expired, attempting to refresh");
-            
-            let http_client = reqwest::ClientBuilder::new()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-                .expect("Client should build");
-
-            // Refresh the token
-            let token_result = state
-                .google_tasks_oauth_client
-                .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token.clone()))
-                .request_async(&http_client)
+            tracing::info!("Access token rejected, attempting to refresh");
+            let (new_access_token, _) = google_tokens::refresh_access_token(state, GoogleService::Tasks, user_id, &refresh_token)
                 .await
-                .map_err(|e| (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": format!("Failed to refresh token: {}", e)}))
-                ))?;
-
-            let new_access_token = token_result.access_token().secret();
-            let expires_in = token_result.expires_in()
-                .unwrap_or_default()
-                .as_secs() as i32;
-
-            // Update the access token in the database
-            state.user_repository.update_google_tasks_access_token(
-                user_id,
-                new_access_token.clone().as_str(),
-                expires_in,
-            ).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update token: {}", e)}))
-            ))?;
+                .map_err(GoogleTokenError::into_response)?;
 
             // Retry with new token
             fetch_tasks_with_token(&client, &new_access_token)
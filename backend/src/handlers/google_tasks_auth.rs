@@ -218,6 +218,7 @@ pub async fn google_tasks_callback(
     }
 
     tracing::info!("Successfully stored Google Tasks connection for user {}", user_id);
+    state.connection_events.publish(user_id, "google_tasks", crate::utils::connection_events::ConnectionStatus::Connected);
 
     let frontend_url = std::env::var("FRONTEND_URL")
         .expect("FRONTEND_URL must be set");
@@ -326,43 +327,14 @@ pub async fn refresh_google_tasks_token(
 
     let (_, refresh_token) = tokens;
 
-    // Create HTTP client
-    let http_client = reqwest::ClientBuilder::new()
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .expect("Client should build");
-
-    // Refresh the token
-    let token_result = state
-        .google_tasks_oauth_client
-        .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token))
-        .request_async(&http_client)
-        .await
-        .map_err(|e| {
-            tracing::error!("Token refresh failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Token refresh failed: {}", e)}))
-            )
-        })?;
-
-    let new_access_token = token_result.access_token().secret();
-    let expires_in = token_result.expires_in()
-        .unwrap_or_default()
-        .as_secs() as i32;
-
-    // Update the access token in the database
-    if let Err(e) = state.user_repository.update_google_tasks_access_token(
+    let (_, expires_in) = crate::utils::google_tokens::refresh_access_token(
+        &state,
+        crate::utils::google_tokens::GoogleService::Tasks,
         auth_user.user_id,
-        new_access_token,
-        expires_in,
-    ) {
-        tracing::error!("Failed to update access token: {}", e);
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to update access token"}))
-        ));
-    }
+        &refresh_token,
+    )
+    .await
+    .map_err(crate::utils::google_tokens::GoogleTokenError::into_response)?;
 
     tracing::info!("Successfully refreshed Google Tasks token for user {}", auth_user.user_id);
     Ok(Json(json!({
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Query, State},
     http::StatusCode,
     response::{Json as AxumJson},
 };
@@ -23,13 +23,29 @@ pub struct ImapCredentials {
     imap_server: Option<String>, // e.g., "mail.privateemail.com" or "imap.gmail.com"
     #[serde(default)]
     imap_port: Option<u16>,      // e.g., 993
+    #[serde(default)]
+    label: Option<String>,       // e.g., "work", shown instead of the address
 }
 
-// Struct to serialize the IMAP status response
+// Struct to serialize the IMAP status response, connected and email are about the primary account
 #[derive(Serialize)]
 pub struct ImapStatus {
     connected: bool,
     email: Option<String>,
+    accounts: Vec<ImapAccountStatus>,
+}
+
+#[derive(Serialize)]
+pub struct ImapAccountStatus {
+    id: i32,
+    email: String,
+    label: Option<String>,
+}
+
+// Which account to delete, all of them when missing
+#[derive(Deserialize)]
+pub struct DeleteImapQuery {
+    account_id: Option<i32>,
 }
 
 use native_tls::TlsStream;
@@ -66,6 +82,7 @@ pub async fn imap_login(
     let password = payload.password;
     let imap_server = payload.imap_server.as_deref(); // Convert Option<String> to Option<&str>
     let imap_port = payload.imap_port;
+    let label = payload.label.as_deref().map(str::trim).filter(|label| !label.is_empty());
 
     // Attempt to connect to Gmail's IMAP server to verify credentials
     match connect_imap(&email, &password, imap_server, imap_port).await {
@@ -81,6 +98,7 @@ pub async fn imap_login(
                 &password,
                 imap_server,
                 imap_port,
+                label,
             ) {
                 tracing::error!("Failed to store IMAP credentials: {}", e);
                 return Err((
@@ -90,6 +108,11 @@ pub async fn imap_login(
             }
 
             tracing::info!("Successfully stored IMAP credentials for user {}", auth_user.user_id);
+            // Reconnect in case the primary account's credentials changed
+            crate::utils::imap_idle::stop_idle_listener(&state, auth_user.user_id);
+            if crate::utils::imap_idle::wants_idle(&state, auth_user.user_id) {
+                crate::utils::imap_idle::start_idle_listener(&state, auth_user.user_id);
+            }
             Ok(AxumJson(json!({"message": "IMAP connected successfully"})))
         }
         Err(e) => {
@@ -109,9 +132,9 @@ pub async fn imap_status(
 ) -> Result<AxumJson<ImapStatus>, (StatusCode, AxumJson<serde_json::Value>)> {
     tracing::info!("Checking IMAP status for user {}", auth_user.user_id);
 
-    let credentials = state
+    let accounts_list = state
         .user_repository
-        .get_imap_credentials(auth_user.user_id)
+        .get_imap_accounts(auth_user.user_id)
         .map_err(|e| {
             tracing::error!("Failed to fetch IMAP credentials: {}", e);
             (
@@ -119,9 +142,17 @@ pub async fn imap_status(
                 Json(json!({"error": "Failed to fetch IMAP status"})),
             )
         })?;
-
-    match credentials {
-        Some((email, password, imap_server, imap_port)) => {
+    let accounts: Vec<ImapAccountStatus> = accounts_list
+        .iter()
+        .map(|account| ImapAccountStatus {
+            id: account.id,
+            email: account.email.clone(),
+            label: account.label.clone(),
+        })
+        .collect();
+
+    match accounts_list.into_iter().next() {
+        Some(crate::models::user_models::ImapAccount { email, password, imap_server, imap_port, .. }) => {
             // Actually test the connection instead of just checking if credentials exist
             tracing::debug!("Testing IMAP connection for user {}", auth_user.user_id);
 
@@ -136,6 +167,7 @@ pub async fn imap_status(
                     Ok(Json(ImapStatus {
                         connected: true,
                         email: Some(email),
+                        accounts,
                     }))
                 }
                 Err(e) => {
@@ -144,6 +176,7 @@ pub async fn imap_status(
                     Ok(Json(ImapStatus {
                         connected: false,
                         email: Some(email),
+                        accounts,
                     }))
                 }
             }
@@ -151,17 +184,45 @@ pub async fn imap_status(
         None => Ok(Json(ImapStatus {
             connected: false,
             email: None,
+            accounts,
         })),
     }
 }
 
-// Handler to delete the IMAP connection
+// Handler to delete one IMAP account, or the whole connection without ?account_id
 pub async fn delete_imap_connection(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
+    Query(query): Query<DeleteImapQuery>,
 ) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
     tracing::info!("Received request to delete IMAP connection for user {}", auth_user.user_id);
 
+    if let Some(account_id) = query.account_id {
+        match state.user_repository.delete_imap_account(auth_user.user_id, account_id) {
+            Ok(0) => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    AxumJson(json!({"error": "Email account not found"})),
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Failed to delete IMAP account {}: {}", account_id, e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    AxumJson(json!({"error": "Failed to delete IMAP credentials"})),
+                ));
+            }
+        }
+        // The listener follows the primary account, which may have been the one removed
+        crate::utils::imap_idle::stop_idle_listener(&state, auth_user.user_id);
+        if crate::utils::imap_idle::wants_idle(&state, auth_user.user_id) {
+            crate::utils::imap_idle::start_idle_listener(&state, auth_user.user_id);
+        }
+        tracing::info!("Deleted IMAP account {} of user {}", account_id, auth_user.user_id);
+        return Ok(AxumJson(json!({"message": "Email account deleted successfully"})));
+    }
+
     if let Err(e) = state.user_repository.delete_imap_credentials(auth_user.user_id) {
         tracing::error!("Failed to delete IMAP credentials: {}", e);
         return Err((
@@ -170,6 +231,7 @@ pub async fn delete_imap_connection(
         ));
     }
 
+    crate::utils::imap_idle::stop_idle_listener(&state, auth_user.user_id);
     tracing::info!("Successfully deleted IMAP connection for user {}", auth_user.user_id);
     Ok(AxumJson(json!({"message": "IMAP connection deleted successfully"})))
 }
@@ -51,6 +51,9 @@ pub struct ImapEmailPreview {
     pub snippet: Option<String>,
     pub body: Option<String>,
     pub is_read: bool,
+    pub message_id: Option<String>,
+    pub copies: u32, // how many fetched messages were this same email, see `dedupe_previews`
+    pub account: Option<String>, // label or address of the account it came from
 }
 #[derive(Debug, Serialize)]
 pub struct ImapEmail {
@@ -64,6 +67,14 @@ pub struct ImapEmail {
     pub body: Option<String>,
     pub is_read: bool,
     pub attachments: Vec<String>,
+    #[serde(skip)]
+    pub attachment_data: Vec<ImapAttachment>,
+}
+#[derive(Debug, Clone)]
+pub struct ImapAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
 }
 #[derive(Debug)]
 pub enum ImapError {
@@ -73,9 +84,162 @@ pub enum ImapError {
     FetchError(String),
     ParseError(String),
 }
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Clone)]
+pub struct ImapFolder {
+    pub name: String,
+    pub delimiter: Option<String>,
+    pub special_use: Option<String>, // \Sent, \Drafts, \All etc. when the server advertises SPECIAL-USE (gmail does)
+    pub selectable: bool,
+}
+pub const DEFAULT_IMAP_FOLDER: &str = "INBOX";
+const SPECIAL_USE_ATTRIBUTES: [&str; 7] = ["\\All", "\\Archive", "\\Drafts", "\\Flagged", "\\Junk", "\\Sent", "\\Trash"];
+/// Turns the optional folder from a request into the mailbox name to SELECT, defaulting to INBOX.
+/// INBOX is case-insensitive per RFC 3501 so it gets normalized, other names are kept as is
+/// since they can contain hierarchy delimiters like "[Gmail]/Sent Mail" or "Receipts.2024".
+pub fn resolve_imap_folder(folder: Option<&str>) -> String {
+    match folder.map(str::trim) {
+        None | Some("") => DEFAULT_IMAP_FOLDER.to_string(),
+        Some(f) if f.eq_ignore_ascii_case("inbox") => DEFAULT_IMAP_FOLDER.to_string(),
+        Some(f) => f.to_string(),
+    }
+}
+#[derive(Debug, Default, Deserialize)]
 pub struct FetchEmailsQuery {
     pub limit: Option<u32>,
+    pub folder: Option<String>,
+    pub sort: Option<String>, // "date_desc" or "date_asc", server order when missing
+    pub unread_only: Option<bool>,
+    pub include_body: Option<bool>, // previews only fetch a snippet unless this is set
+    pub account_id: Option<i32>, // every connected account when missing
+}
+/// Which messages of the folder `fetch_emails_imap_selected` looks at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImapSelection {
+    /// The newest `limit` messages, read or not
+    Latest,
+    /// The newest `limit` messages without \Seen, found with SEARCH UNSEEN on the server
+    LatestUnseen,
+    /// The newest `limit` messages dated on the days from `start` to `end` (unix seconds), found
+    /// with SEARCH SINCE/BEFORE. IMAP only searches by whole days, callers filter the exact range.
+    Between { start: i64, end: i64 },
+}
+
+/// SEARCH criteria for a selection, None for `Latest` which takes the last sequence numbers instead
+fn imap_search_query(selection: ImapSelection) -> Option<String> {
+    match selection {
+        ImapSelection::Latest => None,
+        ImapSelection::LatestUnseen => Some("UNSEEN".to_string()),
+        ImapSelection::Between { start, end } => Some(imap_date_range_query(start, end)),
+    }
+}
+/// SEARCH criteria for messages dated on the UTC days `start` and `end` fall on, BEFORE is exclusive
+fn imap_date_range_query(start: i64, end: i64) -> String {
+    let day = |timestamp: i64| DateTime::<Utc>::from_timestamp(timestamp, 0).unwrap_or_default().date_naive();
+    let before = day(end).succ_opt().unwrap_or_else(|| day(end));
+    format!("SINCE {} BEFORE {}", day(start).format("%-d-%b-%Y"), before.format("%-d-%b-%Y"))
+}
+/// How much of each message `fetch_emails_imap_selected` downloads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImapBodyFetch {
+    /// The whole message, `body` and `snippet` are both filled
+    Full,
+    /// Headers plus the first `SNIPPET_FETCH_BYTES` of the text, only `snippet` is filled
+    Snippet,
+}
+/// Enough of the text part for a 200 character snippet even with some MIME boundaries and encoding in front
+pub const SNIPPET_FETCH_BYTES: u32 = 2048;
+/// FETCH items for `body`. Both use BODY.PEEK so the server doesn't set \Seen.
+pub fn imap_fetch_query(body: ImapBodyFetch) -> String {
+    match body {
+        ImapBodyFetch::Full => "(UID FLAGS ENVELOPE BODY.PEEK[])".to_string(),
+        ImapBodyFetch::Snippet => format!("(UID FLAGS ENVELOPE BODY.PEEK[HEADER] BODY.PEEK[TEXT]<0.{}>)", SNIPPET_FETCH_BYTES),
+    }
+}
+/// Identifies the same email delivered twice: the Message-ID header, or subject + sender + date
+/// for messages without one
+fn dedupe_key(preview: &ImapEmailPreview) -> String {
+    match preview.message_id.as_deref().map(str::trim).filter(|id| !id.is_empty()) {
+        Some(message_id) => message_id.trim_matches(|c| c == '<' || c == '>').to_lowercase(),
+        None => {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            preview.subject.hash(&mut hasher);
+            preview.from_email.as_deref().map(str::to_lowercase).hash(&mut hasher);
+            preview.date.map(|date| date.timestamp()).hash(&mut hasher);
+            format!("hash:{:x}", hasher.finish())
+        }
+    }
+}
+/// Collapses copies of the same email (forwarding rules, several folders) into the first one seen,
+/// counting them in `copies`. The merged email is unread if any copy is.
+pub fn dedupe_previews(previews: Vec<ImapEmailPreview>) -> Vec<ImapEmailPreview> {
+    let mut merged: Vec<ImapEmailPreview> = Vec::with_capacity(previews.len());
+    let mut positions: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for preview in previews {
+        let key = dedupe_key(&preview);
+        match positions.get(&key) {
+            Some(&index) => {
+                let kept = &mut merged[index];
+                kept.copies += preview.copies;
+                kept.is_read = kept.is_read && preview.is_read;
+            }
+            None => {
+                positions.insert(key, merged.len());
+                merged.push(preview);
+            }
+        }
+    }
+    merged
+}
+/// Sorts previews by date, newest first when `descending`. Undated ones go last either way.
+pub fn sort_previews_by_date(previews: &mut [ImapEmailPreview], descending: bool) {
+    previews.sort_by(|a, b| match (a.date, b.date) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+}
+/// Id of an email in `account_id`, "{account_id}:{uid}". Every account is prefixed so ids stay the
+/// same whichever account counts as primary, and UIDs of different accounts never collide in
+/// processed_emails. Account ids survive logging in again, see `set_imap_credentials`.
+pub fn account_email_id(account_id: i32, uid: &str) -> String {
+    format!("{}:{}", account_id, uid)
+}
+/// Splits an email id from `account_email_id` into (account, UID). Plain UIDs handed out before
+/// accounts were prefixed give None, which `select_imap_account` reads as the primary account.
+pub fn split_email_id(email_id: &str) -> (Option<i32>, &str) {
+    match email_id.split_once(':') {
+        Some((account, uid)) => (account.parse().ok(), uid),
+        None => (None, email_id),
+    }
+}
+/// A UID, optionally prefixed with its account
+pub fn is_valid_email_id(email_id: &str) -> bool {
+    let (account, uid) = split_email_id(email_id);
+    let prefixed = email_id.contains(':');
+    (!prefixed || account.is_some()) && !uid.is_empty() && uid.chars().all(|c| c.is_ascii_digit())
+}
+/// The given account, or the primary one when None
+pub fn select_imap_account(
+    state: &AppState,
+    user_id: i32,
+    account_id: Option<i32>,
+) -> Result<crate::models::user_models::ImapAccount, ImapError> {
+    let accounts = state
+        .user_repository
+        .get_imap_accounts(user_id)
+        .map_err(|e| ImapError::CredentialsError(e.to_string()))?;
+    match account_id {
+        Some(id) => accounts.into_iter().find(|account| account.id == id),
+        None => accounts.into_iter().next(),
+    }
+    .ok_or(ImapError::NoConnection)
+}
+#[derive(Debug, Deserialize)]
+pub struct FolderQuery {
+    pub folder: Option<String>,
 }
 pub async fn fetch_imap_previews(
     State(state): State<Arc<AppState>>,
@@ -83,22 +247,44 @@ pub async fn fetch_imap_previews(
     axum::extract::Query(params): axum::extract::Query<FetchEmailsQuery>,
 ) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
     tracing::info!("Starting IMAP preview fetch for user {} with limit {:?}", auth_user.user_id, params.limit);
-    match fetch_emails_imap(&state, auth_user.user_id, true, params.limit, false, false).await {
-        Ok(previews) => {
+    let descending = match params.sort.as_deref() {
+        None => None,
+        Some("date_desc") => Some(true),
+        Some("date_asc") => Some(false),
+        Some(other) => return Err((
+            StatusCode::BAD_REQUEST,
+            AxumJson(json!({ "error": format!("Unknown sort '{}', use date_desc or date_asc", other) }))
+        )),
+    };
+    let unread_only = params.unread_only.unwrap_or(false);
+    let selection = if unread_only { ImapSelection::LatestUnseen } else { ImapSelection::Latest };
+    let include_body = params.include_body.unwrap_or(false);
+    let body_fetch = if include_body { ImapBodyFetch::Full } else { ImapBodyFetch::Snippet };
+    match fetch_emails_imap_selected(&state, auth_user.user_id, true, params.limit, false, unread_only, params.folder.as_deref(), selection, body_fetch, params.account_id).await {
+        Ok(mut previews) => {
+            if let Some(descending) = descending {
+                sort_previews_by_date(&mut previews, descending);
+            }
             tracing::info!("Fetched {} IMAP previews", previews.len());
           
             let formatted_previews: Vec<_> = previews
                 .into_iter()
                 .map(|p| {
-                    json!({
+                    let mut preview = json!({
                         "id": p.id,
                         "subject": p.subject.unwrap_or_else(|| "No subject".to_string()),
                         "from": p.from.unwrap_or_else(|| "Unknown sender".to_string()),
                         "date": p.date.map(|dt| dt.to_rfc3339()),
                         "date_formatted": p.date_formatted.unwrap_or_else(|| "Unknown date".to_string()),
                         "snippet": p.snippet.unwrap_or_else(|| "No preview".to_string()),
-                        "is_read": p.is_read
-                    })
+                        "is_read": p.is_read,
+                        "copies": p.copies,
+                        "account": p.account
+                    });
+                    if include_body {
+                        preview["body"] = json!(p.body.unwrap_or_else(|| "No content".to_string()));
+                    }
+                    preview
                 })
                 .collect();
             Ok(AxumJson(json!({ "success": true, "previews": formatted_previews })))
@@ -128,7 +314,7 @@ pub async fn fetch_full_imap_emails(
         limit = Some(5);
         testing = true;
     }
-    match fetch_emails_imap(&state, auth_user.user_id, false, limit, false, false).await {
+    match fetch_emails_imap_selected(&state, auth_user.user_id, false, limit, false, false, params.folder.as_deref(), ImapSelection::Latest, ImapBodyFetch::Full, params.account_id).await {
         Ok(previews) => {
             tracing::info!("Fetched {} IMAP full emails", previews.len());
           
@@ -148,7 +334,8 @@ pub async fn fetch_full_imap_emails(
                         "date_formatted": p.date_formatted.unwrap_or_else(|| "Unknown date".to_string()),
                         "snippet": p.snippet.unwrap_or_else(|| "No preview".to_string()),
                         "body": p.body.unwrap_or_else(|| "No content".to_string()),
-                        "is_read": p.is_read
+                        "is_read": p.is_read,
+                        "account": p.account
                     })
                 })
                 .collect();
@@ -167,10 +354,180 @@ pub async fn fetch_full_imap_emails(
         }
     }
 }
+pub async fn fetch_imap_folders(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
+    tracing::info!("Listing IMAP folders for user {}", auth_user.user_id);
+    match list_imap_folders(&state, auth_user.user_id).await {
+        Ok(folders) => Ok(AxumJson(json!({ "success": true, "folders": folders }))),
+        Err(e) => {
+            let (status, message) = match e {
+                ImapError::NoConnection => (StatusCode::BAD_REQUEST, "No IMAP connection found".to_string()),
+                ImapError::CredentialsError(msg) => (StatusCode::UNAUTHORIZED, msg),
+                ImapError::ConnectionError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+                ImapError::FetchError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+                ImapError::ParseError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            };
+            tracing::error!("IMAP folder listing failed: {}", message);
+            Err((status, AxumJson(json!({ "error": message }))))
+        }
+    }
+}
+fn imap_folder_from_name(name: &imap::types::Name) -> ImapFolder {
+    let attributes = name.attributes();
+    let selectable = !attributes.iter().any(|a| matches!(a, imap::types::NameAttribute::NoSelect));
+    // Gmail and other servers supporting SPECIAL-USE mark sent/drafts/etc with custom attributes
+    let special_use = attributes.iter().find_map(|a| match a {
+        imap::types::NameAttribute::Custom(attr) => SPECIAL_USE_ATTRIBUTES
+            .iter()
+            .find(|special| special.eq_ignore_ascii_case(attr))
+            .map(|special| special.to_string()),
+        _ => None,
+    });
+    ImapFolder {
+        name: name.name().to_string(),
+        delimiter: name.delimiter().map(|d| d.to_string()),
+        special_use,
+        selectable,
+    }
+}
+pub async fn list_imap_folders(
+    state: &AppState,
+    user_id: i32,
+) -> Result<Vec<ImapFolder>, ImapError> {
+    // Get IMAP credentials
+    let (email, password, imap_server, imap_port) = state
+        .user_repository
+        .get_imap_credentials(user_id)
+        .map_err(|e| ImapError::CredentialsError(e.to_string()))?
+        .ok_or(ImapError::NoConnection)?;
+    // Set up TLS
+    let tls = TlsConnector::builder()
+        .build()
+        .map_err(|e| ImapError::ConnectionError(format!("Failed to create TLS connector: {}", e)))?;
+    let server = imap_server.as_deref().unwrap_or("imap.gmail.com");
+    let port = imap_port.unwrap_or(993);
+    // Connect to IMAP server
+    let client = imap::connect((server, port as u16), server, &tls)
+    .map_err(|e| ImapError::ConnectionError(format!("Failed to connect to IMAP server: {}", e)))?;
+    // Login
+    let mut imap_session = client
+        .login(&email, &password)
+        .map_err(|(e, _)| ImapError::CredentialsError(format!("Failed to login: {}", e)))?;
+    // List every folder in the hierarchy, "*" also matches across delimiters
+    let names = imap_session
+        .list(Some(""), Some("*"))
+        .map_err(|e| ImapError::FetchError(format!("Failed to list folders: {}", e)))?;
+    let mut folders: Vec<ImapFolder> = names.iter().map(imap_folder_from_name).collect();
+    // INBOX first, the rest alphabetically
+    folders.sort_by(|a, b| {
+        let a_inbox = a.name.eq_ignore_ascii_case(DEFAULT_IMAP_FOLDER);
+        let b_inbox = b.name.eq_ignore_ascii_case(DEFAULT_IMAP_FOLDER);
+        b_inbox.cmp(&a_inbox).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    // Logout
+    imap_session
+        .logout()
+        .map_err(|e| ImapError::ConnectionError(format!("Failed to logout: {}", e)))?;
+    Ok(folders)
+}
+/// Finds the folder the server flagged with the given special-use attribute (e.g. \Sent),
+/// gmail names these "[Gmail]/Sent Mail" etc. and localizes them so the name can't be guessed.
+pub fn find_special_use_folder(folders: &[ImapFolder], special_use: &str) -> Option<String> {
+    folders
+        .iter()
+        .find(|f| f.selectable && f.special_use.as_deref().map_or(false, |s| s.eq_ignore_ascii_case(special_use)))
+        .map(|f| f.name.clone())
+}
+/// The folder to SELECT for a requested name: the exact name, the folder with that special-use
+/// attribute (`\Sent` or just "sent", so gmail's localized names work), or a name differing only in case
+pub fn match_imap_folder(folders: &[ImapFolder], requested: &str) -> Result<String, ImapError> {
+    if requested == DEFAULT_IMAP_FOLDER {
+        return Ok(DEFAULT_IMAP_FOLDER.to_string());
+    }
+    let mut selectable = folders.iter().filter(|f| f.selectable);
+    if let Some(folder) = selectable.clone().find(|f| f.name == requested) {
+        return Ok(folder.name.clone());
+    }
+    if let Some(name) = find_special_use_folder(folders, &format!("\\{}", requested.trim_start_matches('\\'))) {
+        return Ok(name);
+    }
+    selectable
+        .find(|f| f.name.eq_ignore_ascii_case(requested))
+        .map(|f| f.name.clone())
+        .ok_or_else(|| ImapError::FetchError(format!("No folder named {}", requested)))
+}
+/// SELECTs the requested folder (INBOX by default), looking other names up with `match_imap_folder`
+fn select_imap_folder<T: std::io::Read + std::io::Write>(
+    imap_session: &mut imap::Session<T>,
+    folder: Option<&str>,
+) -> Result<imap::types::Mailbox, ImapError> {
+    let mut folder = resolve_imap_folder(folder);
+    if folder != DEFAULT_IMAP_FOLDER {
+        let names = imap_session
+            .list(Some(""), Some("*"))
+            .map_err(|e| ImapError::FetchError(format!("Failed to list folders: {}", e)))?;
+        let folders: Vec<ImapFolder> = names.iter().map(imap_folder_from_name).collect();
+        folder = match_imap_folder(&folders, &folder)?;
+    }
+    imap_session
+        .select(&folder)
+        .map_err(|e| ImapError::FetchError(format!("Failed to select {}: {}", folder, e)))
+}
 #[derive(Debug, Deserialize)]
 pub struct EmailResponseRequest {
     pub email_id: String,
     pub response_text: String,
+    /// Also address everyone the original went to, not just the sender
+    #[serde(default)]
+    pub reply_all: bool,
+}
+/// "mailbox@host" from an envelope address, None for group markers that have no host
+fn plain_address(mailbox: Option<&[u8]>, host: Option<&[u8]>) -> Option<String> {
+    Some(format!(
+        "{}@{}",
+        String::from_utf8_lossy(mailbox?),
+        String::from_utf8_lossy(host?)
+    ))
+}
+/// To and Cc of a reply. The sender goes in To, with reply all the original To and Cc
+/// recipients go in Cc. The user's own address and repeats are left out.
+pub fn reply_recipients(
+    sender: &str,
+    original_to: &[String],
+    original_cc: &[String],
+    own_address: &str,
+    reply_all: bool,
+) -> (Vec<String>, Vec<String>) {
+    let mut seen = vec![sender.to_lowercase(), own_address.to_lowercase()];
+    let mut cc = Vec::new();
+    if reply_all {
+        for address in original_to.iter().chain(original_cc) {
+            let lowered = address.to_lowercase();
+            if !seen.contains(&lowered) {
+                seen.push(lowered);
+                cc.push(address.clone());
+            }
+        }
+    }
+    (vec![sender.to_string()], cc)
+}
+/// In-Reply-To and References for a reply, from the original message's raw header.
+/// References is the original's own chain followed by its Message-ID, as RFC 5322 describes.
+pub fn reply_threading_headers(raw_header: &[u8]) -> Option<(String, String)> {
+    use mail_parser::{HeaderValue, MessageParser};
+    let parsed = MessageParser::default().parse(raw_header)?;
+    let message_id = format!("<{}>", parsed.message_id()?);
+    let mut references: Vec<String> = match parsed.references() {
+        HeaderValue::Text(id) => vec![format!("<{}>", id)],
+        HeaderValue::TextList(ids) => ids.iter().map(|id| format!("<{}>", id)).collect(),
+        _ => Vec::new(),
+    };
+    if !references.contains(&message_id) {
+        references.push(message_id.clone());
+    }
+    Some((message_id, references.join(" ")))
 }
 // this is not used yet since it didn't work and not my priority rn
 pub async fn respond_to_email(
@@ -179,27 +536,25 @@ pub async fn respond_to_email(
     Json(request): Json<EmailResponseRequest>,
 ) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
     tracing::info!("Responding to email {} for user {}", request.email_id, auth_user.user_id);
-    // Validate email_id is a valid number
-    if !request.email_id.chars().all(|c| c.is_ascii_digit()) {
+    // Validate email_id is a UID, optionally prefixed with its account
+    if !is_valid_email_id(&request.email_id) {
         tracing::error!("Invalid email ID format: {}", request.email_id);
         return Err((
             StatusCode::BAD_REQUEST,
             AxumJson(json!({ "error": "Invalid email ID format" }))
         ));
     }
-    // Get IMAP credentials
-    let (email, password, imap_server, imap_port) = match state
-        .user_repository
-        .get_imap_credentials(auth_user.user_id)
-    {
-        Ok(Some(creds)) => creds,
-        Ok(None) => return Err((
+    // Reply from the account the email came to
+    let (account_id, uid) = split_email_id(&request.email_id);
+    let (email, password, imap_server, imap_port) = match select_imap_account(&state, auth_user.user_id, account_id) {
+        Ok(account) => (account.email, account.password, account.imap_server, account.imap_port),
+        Err(ImapError::NoConnection) => return Err((
             StatusCode::BAD_REQUEST,
             AxumJson(json!({ "error": "No IMAP connection found" }))
         )),
         Err(e) => return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            AxumJson(json!({ "error": format!("Failed to get IMAP credentials: {}", e) }))
+            AxumJson(json!({ "error": format!("Failed to get IMAP credentials: {:?}", e) }))
         )),
     };
     tracing::info!("setting up tls");
@@ -238,7 +593,7 @@ pub async fn respond_to_email(
         ));
     }
     // Fetch the original message to get subject and other details
-    let messages = match imap_session.uid_fetch(&request.email_id, "(ENVELOPE)") {
+    let messages = match imap_session.uid_fetch(uid, "(ENVELOPE BODY.PEEK[HEADER])") {
         Ok(messages) => messages,
         Err(e) => return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -279,6 +634,19 @@ pub async fn respond_to_email(
             AxumJson(json!({ "error": "Failed to get recipient address" }))
         ))?;
     tracing::info!("reply addr: {}", reply_to_address);
+    let original_to: Vec<String> = envelope.to.iter().flatten()
+        .filter_map(|addr| plain_address(addr.mailbox.as_deref(), addr.host.as_deref()))
+        .collect();
+    let original_cc: Vec<String> = envelope.cc.iter().flatten()
+        .filter_map(|addr| plain_address(addr.mailbox.as_deref(), addr.host.as_deref()))
+        .collect();
+    let (to_addresses, cc_addresses) = reply_recipients(
+        &reply_to_address,
+        &original_to,
+        &original_cc,
+        &email,
+        request.reply_all,
+    );
     // Get original subject
     let original_subject = envelope
         .subject
@@ -290,6 +658,11 @@ pub async fn respond_to_email(
     } else {
         original_subject
     };
+    // Thread the reply under the original in the recipient's client
+    let threading = original_message.header().and_then(reply_threading_headers);
+    if threading.is_none() {
+        tracing::warn!("Original email {} has no Message-ID, reply won't be threaded", request.email_id);
+    }
     // Create SMTP transport
     let smtp_server = imap_server
         .as_deref()
@@ -311,12 +684,26 @@ pub async fn respond_to_email(
         .credentials(creds)
         .build();
     // Create email message
-    let email_message = match Message::builder()
+    let mut message_builder = Message::builder()
         .from(email.parse().unwrap())
-        .to(reply_to_address.parse().unwrap())
-        .subject(subject.clone())
-        .body(request.response_text.clone())
-    {
+        .subject(subject.clone());
+    for address in to_addresses {
+        message_builder = message_builder.to(address.parse().map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AxumJson(json!({ "error": format!("Invalid recipient address {}: {}", address, e) })),
+        ))?);
+    }
+    for address in cc_addresses {
+        match address.parse() {
+            Ok(mailbox) => message_builder = message_builder.cc(mailbox),
+            // One odd address in a long Cc list shouldn't stop the reply from going out
+            Err(e) => tracing::warn!("Leaving unparseable address {} out of reply all: {}", address, e),
+        }
+    }
+    if let Some((in_reply_to, references)) = threading {
+        message_builder = message_builder.in_reply_to(in_reply_to).references(references);
+    }
+    let email_message = match message_builder.body(request.response_text.clone()) {
         Ok(message) => message,
         Err(e) => return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -369,10 +756,11 @@ pub async fn fetch_single_imap_email(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     axum::extract::Path(email_id): axum::extract::Path<String>,
+    axum::extract::Query(params): axum::extract::Query<FolderQuery>,
 ) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
     tracing::info!("Fetching single IMAP email {} for user {}", email_id, auth_user.user_id);
-    // Validate email_id is a valid number and not empty
-    if email_id.trim().is_empty() || !email_id.chars().all(|c| c.is_ascii_digit()) {
+    // Validate email_id is a UID, optionally prefixed with its account, and not empty
+    if email_id.trim().is_empty() || !is_valid_email_id(&email_id) {
         let error_msg = if email_id.trim().is_empty() {
             "Email ID cannot be empty"
         } else {
@@ -387,7 +775,7 @@ pub async fn fetch_single_imap_email(
             }))
         ));
     }
-    match fetch_single_email_imap(&state, auth_user.user_id, &email_id).await {
+    match fetch_single_email_imap(&state, auth_user.user_id, &email_id, params.folder.as_deref()).await {
         Ok(email) => {
             tracing::debug!("Successfully fetched email {}", email_id);
             // if admin testing their own account
@@ -448,15 +836,86 @@ pub async fn fetch_emails_imap(
     limit: Option<u32>,
     unprocessed: bool,
     unread_only: bool,
+    folder: Option<&str>,
+) -> Result<Vec<ImapEmailPreview>, ImapError> {
+    fetch_emails_imap_selected(state, user_id, preview_only, limit, unprocessed, unread_only, folder, ImapSelection::Latest, ImapBodyFetch::Full, None).await
+}
+/// `fetch_emails_imap` with a choice of which messages to look at and how much of them to download.
+/// With `Latest`, `unread_only` filters within the newest messages; `LatestUnseen` lets the server
+/// pick unread ones instead. Without `account_id` every connected account is fetched and the newest
+/// `limit` emails of all of them are returned, with the same email in several accounts merged.
+pub async fn fetch_emails_imap_selected(
+    state: &AppState,
+    user_id: i32,
+    preview_only: bool,
+    limit: Option<u32>,
+    unprocessed: bool,
+    unread_only: bool,
+    folder: Option<&str>,
+    selection: ImapSelection,
+    body_fetch: ImapBodyFetch,
+    account_id: Option<i32>,
 ) -> Result<Vec<ImapEmailPreview>, ImapError> {
     tracing::debug!("Starting fetch_emails_imap for user {} with preview_only: {}, limit: {:?}, unprocessed: {}",
         user_id, preview_only, limit, unprocessed);
-    // Get IMAP credentials
-    let (email, password, imap_server, imap_port) = state
+    let accounts = state
         .user_repository
-        .get_imap_credentials(user_id)
-        .map_err(|e| ImapError::CredentialsError(e.to_string()))?
-        .ok_or_else(|| ImapError::NoConnection)?;
+        .get_imap_accounts(user_id)
+        .map_err(|e| ImapError::CredentialsError(e.to_string()))?;
+    let accounts: Vec<_> = accounts
+        .into_iter()
+        .filter(|account| account_id.map_or(true, |id| account.id == id))
+        .collect();
+    let limit = limit.unwrap_or(20);
+    let mut previews = Vec::new();
+    let mut first_error = None;
+    for account in &accounts {
+        match fetch_account_emails(state, user_id, account, limit, unprocessed, unread_only, folder, selection, body_fetch).await {
+            Ok(found) => previews.extend(found),
+            // One account being down shouldn't hide the others' mail
+            Err(e) if accounts.len() > 1 => {
+                tracing::warn!("Failed to fetch emails from account {} of user {}: {:?}", account.id, user_id, e);
+                first_error.get_or_insert(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if accounts.is_empty() {
+        return Err(ImapError::NoConnection);
+    }
+    if let (true, Some(e)) = (previews.is_empty(), first_error) {
+        return Err(e);
+    }
+    if accounts.len() == 1 {
+        return Ok(previews);
+    }
+    Ok(merge_account_previews(previews, limit))
+}
+/// Combines the emails of several accounts: oldest first like a single account's server order,
+/// copies of the same email merged, and only the newest `limit` kept
+pub fn merge_account_previews(mut previews: Vec<ImapEmailPreview>, limit: u32) -> Vec<ImapEmailPreview> {
+    sort_previews_by_date(&mut previews, false);
+    let mut previews = dedupe_previews(previews);
+    let excess = previews.len().saturating_sub(limit as usize);
+    previews.drain(..excess);
+    previews
+}
+/// The newest `limit` emails of one account, ids made with `account_email_id`
+async fn fetch_account_emails(
+    state: &AppState,
+    user_id: i32,
+    account: &crate::models::user_models::ImapAccount,
+    limit: u32,
+    unprocessed: bool,
+    unread_only: bool,
+    folder: Option<&str>,
+    selection: ImapSelection,
+    body_fetch: ImapBodyFetch,
+) -> Result<Vec<ImapEmailPreview>, ImapError> {
+    let email = &account.email;
+    let password = &account.password;
+    let imap_server = &account.imap_server;
+    let imap_port = account.imap_port;
     // Add logging for debugging (remove in production)
     tracing::debug!("Fetching IMAP emails for user {} with email {}", user_id, email);
     // Set up TLS
@@ -470,24 +929,35 @@ pub async fn fetch_emails_imap(
     .map_err(|e| ImapError::ConnectionError(format!("Failed to connect to IMAP server: {}", e)))?;
     // Login
     let mut imap_session = client
-        .login(&email, &password)
+        .login(email, password)
         .map_err(|(e, _)| ImapError::CredentialsError(format!("Failed to login: {}", e)))?;
-    // Select INBOX
-    let mailbox = imap_session
-        .select("INBOX")
-        .map_err(|e| ImapError::FetchError(format!("Failed to select INBOX: {}", e)))?;
-    // Calculate how many messages to fetch based on limit parameter
-    let limit = limit.unwrap_or(20);
-    let sequence_set = format!("{}:{}", (mailbox.exists.saturating_sub(limit - 1)), mailbox.exists);
+    // Select the requested folder (INBOX by default)
+    let mailbox = select_imap_folder(&mut imap_session, folder)?;
+    let sequence_set = match imap_search_query(selection) {
+        None => format!("{}:{}", (mailbox.exists.saturating_sub(limit - 1)), mailbox.exists),
+        Some(query) => {
+            let mut found: Vec<u32> = imap_session
+                .search(&query)
+                .map_err(|e| ImapError::FetchError(format!("Failed to search messages ({}): {}", query, e)))?
+                .into_iter()
+                .collect();
+            found.sort_unstable();
+            let newest = &found[found.len().saturating_sub(limit as usize)..];
+            if newest.is_empty() {
+                imap_session
+                    .logout()
+                    .map_err(|e| ImapError::ConnectionError(format!("Failed to logout: {}", e)))?;
+                return Ok(Vec::new());
+            }
+            newest.iter().map(|seq| seq.to_string()).collect::<Vec<_>>().join(",")
+        }
+    };
     let messages = imap_session
-        .fetch(
-            &sequence_set,
-            "(UID FLAGS ENVELOPE BODY.PEEK[])", // PEEK to not mark the email as read
-        )
+        .fetch(&sequence_set, imap_fetch_query(body_fetch))
         .map_err(|e| ImapError::FetchError(format!("Failed to fetch messages: {}", e)))?;
     let mut email_previews = Vec::new();
     for message in messages.iter() {
-        let uid = message.uid.unwrap_or(0).to_string();
+        let uid = account_email_id(account.id, &message.uid.unwrap_or(0).to_string());
       
         // Check if email is already processed using repository method
         let is_processed = state.user_repository.is_email_processed(user_id, &uid)
@@ -526,6 +996,10 @@ pub async fn fetch_emails_imap(
             .subject
             .as_ref()
             .and_then(|s| String::from_utf8(s.to_vec()).ok());
+        let message_id = envelope
+            .message_id
+            .as_ref()
+            .and_then(|id| String::from_utf8(id.to_vec()).ok());
         let raw_date = envelope
             .date
             .as_ref()
@@ -556,7 +1030,11 @@ pub async fn fetch_emails_imap(
         }
             // Try to get both full body and text body
         let full_body = message.body().map(|b| String::from_utf8_lossy(b).into_owned());
-        let text_body = message.text().map(|b| String::from_utf8_lossy(b).into_owned());
+        let text_body = message.text().map(|b| {
+            // The snippet fetch gets the headers separately, the parser needs them to decode the text
+            let header = message.header().map(|h| String::from_utf8_lossy(h).into_owned()).unwrap_or_default();
+            format!("{}{}", header, String::from_utf8_lossy(b))
+        });
       
         use mail_parser::MessageParser;
         let body_content = full_body.or(text_body);
@@ -566,15 +1044,7 @@ pub async fn fetch_emails_imap(
             let parsed = parser.parse(content.as_bytes());
             // Get the best available body content, if parsing succeeded
             let clean_content = parsed.map(|msg| {
-                let body_text = msg.body_text(0).or_else(|| msg.body_html(0));
-                body_text
-                    .map(|text| {
-                        text.lines()
-                            .map(str::trim)
-                            .filter(|line| !line.is_empty())
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    })
+                crate::utils::imap_utils::readable_body(&msg)
                     .unwrap_or_else(|| String::from("[No readable body found]"))
             }).unwrap_or_else(|| String::from("[Failed to parse email body]"));
             // Generate a snippet from the clean body
@@ -602,8 +1072,14 @@ pub async fn fetch_emails_imap(
                 date,
                 date_formatted,
                 snippet: Some(snippet),
-                body: Some(body),
+                body: match body_fetch {
+                    ImapBodyFetch::Full => Some(body),
+                    ImapBodyFetch::Snippet => None,
+                },
                 is_read,
+                message_id,
+                copies: 1,
+                account: Some(account.display_name().to_string()),
             });
         // Mark email as processed if unprocessed is true
         if unprocessed {
@@ -624,19 +1100,20 @@ pub async fn fetch_emails_imap(
         .map_err(|e| ImapError::ConnectionError(format!("Failed to logout: {}", e)))?;
     // Reverse the order so newest emails appear first
     //email_previews.reverse();
-    Ok(email_previews)
+    Ok(dedupe_previews(email_previews))
 }
 pub async fn fetch_single_email_imap(
     state: &AppState,
     user_id: i32,
     email_id: &str,
+    folder: Option<&str>,
 ) -> Result<ImapEmail, ImapError> {
-    // Get IMAP credentials
-    let (email, password, imap_server, imap_port) = state
-        .user_repository
-        .get_imap_credentials(user_id)
-        .map_err(|e| ImapError::CredentialsError(e.to_string()))?
-        .ok_or(ImapError::NoConnection)?;
+    // The account the email id points at, see `account_email_id`
+    let (account_id, email_id) = split_email_id(email_id);
+    let account = select_imap_account(state, user_id, account_id)?;
+    // Plain UIDs from before ids were prefixed come back in the current form
+    let full_id = account_email_id(account.id, email_id);
+    let (email, password, imap_server, imap_port) = (account.email, account.password, account.imap_server, account.imap_port);
     // Set up TLS
     let tls = TlsConnector::builder()
         .build()
@@ -650,10 +1127,8 @@ pub async fn fetch_single_email_imap(
     let mut imap_session = client
         .login(&email, &password)
         .map_err(|(e, _)| ImapError::CredentialsError(format!("Failed to login: {}", e)))?;
-    // Select INBOX
-    imap_session
-        .select("INBOX")
-        .map_err(|e| ImapError::FetchError(format!("Failed to select INBOX: {}", e)))?;
+    // Select the requested folder (INBOX by default)
+    select_imap_folder(&mut imap_session, folder)?;
     // Fetch specific message with body structure for attachments
     // Using BODY.PEEK[] to avoid marking the email as read
     let messages = match imap_session.uid_fetch(
@@ -745,24 +1220,16 @@ pub async fn fetch_single_email_imap(
     // Try to get both full body and text body
     let full_body = message.body().map(|b| String::from_utf8_lossy(b).into_owned());
     let text_body = message.text().map(|b| String::from_utf8_lossy(b).into_owned());
-    use mail_parser::MessageParser;
+    use mail_parser::{MessageParser, MimeHeaders};
     let body_content = full_body.or(text_body);
-    let (body, snippet, attachments) = match body_content.as_ref() {
+    let (body, snippet, attachments, attachment_data) = match body_content.as_ref() {
         Some(content) => {
             // Create a parser and parse the content into an Option<Message>
             let parser = MessageParser::default();
             let parsed = parser.parse(content.as_bytes());
             // Get the best available body content, if parsing succeeded
             let clean_content = parsed.as_ref().map(|msg| {
-                let body_text = msg.body_text(0).or_else(|| msg.body_html(0));
-                body_text
-                    .map(|text| {
-                        text.lines()
-                            .map(str::trim)
-                            .filter(|line| !line.is_empty())
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    })
+                crate::utils::imap_utils::readable_body(msg)
                     .unwrap_or_else(|| String::from("[No readable body found]"))
             }).unwrap_or_else(|| String::from("[Failed to parse email body]"));
             // Generate a snippet from the clean body
@@ -818,9 +1285,30 @@ pub async fn fetch_single_email_imap(
                 Vec::new()
             };
                                 */
-            (clean_content, snippet, Vec::new())
+            // Keep the raw attachment bytes around so the email can be forwarded as is
+            let attachment_data = parsed.as_ref().map(|msg| {
+                msg.attachments()
+                    .map(|attachment| {
+                        let content_type = attachment.content_type()
+                            .map(|ct| match ct.subtype() {
+                                Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                                None => ct.ctype().to_string(),
+                            })
+                            .unwrap_or_else(|| "application/octet-stream".to_string())
+                            .to_lowercase();
+                        ImapAttachment {
+                            filename: attachment.attachment_name()
+                                .map(|name| name.to_string())
+                                .unwrap_or_else(|| "attachment".to_string()),
+                            content_type,
+                            data: attachment.contents().to_vec(),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }).unwrap_or_default();
+            (clean_content, snippet, Vec::new(), attachment_data)
         },
-        None => (String::new(), String::new(), Vec::new())
+        None => (String::new(), String::new(), Vec::new(), Vec::new())
     };
     // Logout
     imap_session
@@ -830,7 +1318,7 @@ pub async fn fetch_single_email_imap(
         .ok()
         .and_then(|info| info.timezone)));
     Ok(ImapEmail {
-        id: email_id.to_string(),
+        id: full_id,
         subject,
         from,
         from_email,
@@ -840,6 +1328,7 @@ pub async fn fetch_single_email_imap(
         body: Some(body),
         is_read,
         attachments,
+        attachment_data,
     })
 }
 
@@ -848,6 +1337,9 @@ pub struct SendEmailRequest {
     pub to: String,
     pub subject: String,
     pub body: String,
+    /// Account to send from, the primary one when missing
+    #[serde(default)]
+    pub account_id: Option<i32>,
 }
 pub async fn send_email(
     State(state): State<Arc<AppState>>,
@@ -856,18 +1348,15 @@ pub async fn send_email(
 ) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
     tracing::info!("Sending new email to {} for user {}", request.to, auth_user.user_id);
     // Get user's email credentials (assuming same as IMAP for SMTP)
-    let (email, password, imap_server, _) = match state
-        .user_repository
-        .get_imap_credentials(auth_user.user_id)
-    {
-        Ok(Some(creds)) => creds,
-        Ok(None) => return Err((
+    let (email, password, imap_server) = match select_imap_account(&state, auth_user.user_id, request.account_id) {
+        Ok(account) => (account.email, account.password, account.imap_server),
+        Err(ImapError::NoConnection) => return Err((
             StatusCode::BAD_REQUEST,
             AxumJson(json!({ "error": "No email credentials found" })),
         )),
         Err(e) => return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            AxumJson(json!({ "error": format!("Failed to get email credentials: {}", e) })),
+            AxumJson(json!({ "error": format!("Failed to get email credentials: {:?}", e) })),
         )),
     };
     // Derive SMTP server from IMAP server (common pattern, e.g., imap.gmail.com -> smtp.gmail.com)
@@ -936,3 +1425,383 @@ pub async fn send_email(
         }
     }
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ForwardEmailRequest {
+    pub email_id: String,
+    pub to: String,
+}
+/// Builds the forwarded message: "Fwd:" subject, the original headers and body quoted
+/// below a forward marker, and the original attachments carried over.
+pub fn build_forward_message(
+    from: &str,
+    to: &str,
+    original: &ImapEmail,
+) -> Result<Message, String> {
+    use lettre::message::{header::ContentType, Attachment, MultiPart, SinglePart};
+    let original_subject = original.subject.clone().unwrap_or_else(|| "No subject".to_string());
+    let subject = if original_subject.to_lowercase().starts_with("fwd:") {
+        original_subject.clone()
+    } else {
+        format!("Fwd: {}", original_subject)
+    };
+    let quoted_body = original.body
+        .as_deref()
+        .unwrap_or("")
+        .lines()
+        .map(|line| format!("> {}", line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = format!(
+        "---------- Forwarded message ---------\nFrom: {}\nDate: {}\nSubject: {}\n\n{}\n",
+        original.from.as_deref().unwrap_or("Unknown sender"),
+        original.date.map(|dt| dt.to_rfc2822()).unwrap_or_else(|| "Unknown date".to_string()),
+        original_subject,
+        quoted_body
+    );
+    let mut multipart = MultiPart::mixed().singlepart(SinglePart::plain(text));
+    for attachment in &original.attachment_data {
+        let content_type = ContentType::parse(&attachment.content_type)
+            .unwrap_or_else(|_| ContentType::parse("application/octet-stream").unwrap());
+        multipart = multipart.singlepart(
+            Attachment::new(attachment.filename.clone()).body(attachment.data.clone(), content_type)
+        );
+    }
+    Message::builder()
+        .from(from.parse().map_err(|e| format!("Invalid sender email format: {}", e))?)
+        .to(to.parse().map_err(|e| format!("Invalid recipient email format: {}", e))?)
+        .subject(subject)
+        .multipart(multipart)
+        .map_err(|e| format!("Failed to build email message: {}", e))
+}
+pub async fn forward_email(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(request): Json<ForwardEmailRequest>,
+) -> Result<AxumJson<serde_json::Value>, (StatusCode, AxumJson<serde_json::Value>)> {
+    tracing::info!("Forwarding email {} to {} for user {}", request.email_id, request.to, auth_user.user_id);
+    if request.email_id.trim().is_empty() || !is_valid_email_id(&request.email_id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            AxumJson(json!({ "error": "Invalid email ID format" }))
+        ));
+    }
+    if request.to.parse::<lettre::message::Mailbox>().is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            AxumJson(json!({ "error": "Invalid recipient email format" }))
+        ));
+    }
+    let original = match fetch_single_email_imap(&state, auth_user.user_id, &request.email_id, None).await {
+        Ok(email) => email,
+        Err(e) => {
+            tracing::error!("Failed to fetch email {} for forwarding: {:?}", request.email_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AxumJson(json!({ "error": "Failed to fetch the original email" }))
+            ));
+        }
+    };
+    // Forward from the account the email came to
+    let (account_id, _) = split_email_id(&request.email_id);
+    let (email, password, imap_server) = match select_imap_account(&state, auth_user.user_id, account_id) {
+        Ok(account) => (account.email, account.password, account.imap_server),
+        Err(ImapError::NoConnection) => return Err((
+            StatusCode::BAD_REQUEST,
+            AxumJson(json!({ "error": "No email credentials found" })),
+        )),
+        Err(e) => return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AxumJson(json!({ "error": format!("Failed to get email credentials: {:?}", e) })),
+        )),
+    };
+    let email_message = build_forward_message(&email, &request.to, &original).map_err(|e| (
+        StatusCode::BAD_REQUEST,
+        AxumJson(json!({ "error": e })),
+    ))?;
+    let smtp_server = imap_server
+        .as_deref()
+        .unwrap_or("smtp.gmail.com")
+        .replace("imap", "smtp");
+    let smtp_port = 587;
+    let creds = Credentials::new(email.clone(), password.clone());
+    let mailer = lettre::SmtpTransport::starttls_relay(&smtp_server)
+        .map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AxumJson(json!({ "error": format!("Failed to create SMTP relay: {}", e) })),
+        ))?
+        .port(smtp_port)
+        .credentials(creds)
+        .build();
+    match mailer.send(&email_message) {
+        Ok(_) => {
+            tracing::info!("Email {} forwarded successfully to {}", request.email_id, request.to);
+            Ok(AxumJson(json!({
+                "success": true,
+                "message": "Email forwarded successfully"
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to forward email to {}: {:?}", request.to, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AxumJson(json!({
+                    "error": format!("Failed to forward email: {}", e),
+                    "details": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, set_test_encryption_key, test_state};
+
+    #[test]
+    fn date_range_search_covers_both_end_days() {
+        // 2026-10-10 12:00 UTC to 2026-10-16 23:30 UTC
+        assert_eq!(imap_date_range_query(1_791_633_600, 1_792_193_400), "SINCE 10-Oct-2026 BEFORE 17-Oct-2026");
+    }
+
+    #[test]
+    fn unread_selection_searches_unseen_on_the_server() {
+        assert_eq!(imap_search_query(ImapSelection::LatestUnseen).as_deref(), Some("UNSEEN"));
+        assert_eq!(imap_search_query(ImapSelection::Latest), None);
+        assert_eq!(
+            imap_search_query(ImapSelection::Between { start: 1_791_633_600, end: 1_792_193_400 }).as_deref(),
+            Some("SINCE 10-Oct-2026 BEFORE 17-Oct-2026")
+        );
+    }
+
+    fn gmail_folders() -> Vec<ImapFolder> {
+        let folder = |name: &str, special_use: Option<&str>, selectable: bool| ImapFolder {
+            name: name.to_string(),
+            delimiter: Some("/".to_string()),
+            special_use: special_use.map(str::to_string),
+            selectable,
+        };
+        vec![
+            folder("INBOX", None, true),
+            folder("[Gmail]", None, false),
+            folder("[Gmail]/Lähetetyt viestit", Some("\\Sent"), true),
+            folder("[Gmail]/Kaikki viestit", Some("\\All"), true),
+            folder("Arkisto", Some("\\Archive"), true),
+            folder("Receipts/2024", None, true),
+        ]
+    }
+
+    #[test]
+    fn special_use_folders_are_found_by_attribute() {
+        let folders = gmail_folders();
+        assert_eq!(find_special_use_folder(&folders, "\\Sent").as_deref(), Some("[Gmail]/Lähetetyt viestit"));
+        assert_eq!(find_special_use_folder(&folders, "\\archive").as_deref(), Some("Arkisto"));
+        assert_eq!(find_special_use_folder(&folders, "\\Drafts"), None);
+        assert_eq!(match_imap_folder(&folders, "Sent").unwrap(), "[Gmail]/Lähetetyt viestit");
+        assert_eq!(match_imap_folder(&folders, "\\Archive").unwrap(), "Arkisto");
+    }
+
+    #[test]
+    fn folder_names_fall_back_to_case_insensitive() {
+        let folders = gmail_folders();
+        assert_eq!(match_imap_folder(&folders, "Receipts/2024").unwrap(), "Receipts/2024");
+        assert_eq!(match_imap_folder(&folders, "receipts/2024").unwrap(), "Receipts/2024");
+        assert_eq!(match_imap_folder(&folders, &resolve_imap_folder(Some(" inbox "))).unwrap(), "INBOX");
+    }
+
+    #[test]
+    fn unknown_folder_is_an_error() {
+        let folders = gmail_folders();
+        assert!(matches!(match_imap_folder(&folders, "Receipts/2025"), Err(ImapError::FetchError(_))));
+        // Listed, but only as a parent in the hierarchy
+        assert!(match_imap_folder(&folders, "[Gmail]").is_err());
+    }
+
+    fn original(subject: &str, body: &str) -> ImapEmail {
+        ImapEmail {
+            id: "1:42".to_string(),
+            subject: Some(subject.to_string()),
+            from: Some("Alice <alice@example.com>".to_string()),
+            from_email: Some("alice@example.com".to_string()),
+            date: None,
+            date_formatted: None,
+            snippet: None,
+            body: Some(body.to_string()),
+            is_read: true,
+            attachments: Vec::new(),
+            attachment_data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn forwarded_message_gets_fwd_subject_and_quoted_body() {
+        let message = build_forward_message(
+            "me@example.com",
+            "bob@example.com",
+            &original("Lunch on Friday", "Are you free?\nSay noon"),
+        ).unwrap();
+        let raw = String::from_utf8(message.formatted()).unwrap();
+        assert!(raw.contains("Subject: Fwd: Lunch on Friday"));
+        assert!(raw.contains("---------- Forwarded message ---------"));
+        assert!(raw.contains("From: Alice <alice@example.com>"));
+        assert!(raw.contains("> Are you free?\r\n> Say noon") || raw.contains("> Are you free?\n> Say noon"));
+    }
+
+    #[test]
+    fn forwarding_a_forward_keeps_one_prefix() {
+        let message = build_forward_message("me@example.com", "bob@example.com", &original("Fwd: Lunch", "hi")).unwrap();
+        let raw = String::from_utf8(message.formatted()).unwrap();
+        assert!(raw.contains("Subject: Fwd: Lunch\r\n"));
+        assert!(!raw.contains("Fwd: Fwd:"));
+    }
+
+    #[test]
+    fn reply_extends_the_existing_references_chain() {
+        let raw = b"From: alice@example.com\r\nMessage-ID: <c@example.com>\r\nIn-Reply-To: <b@example.com>\r\nReferences: <a@example.com> <b@example.com>\r\nSubject: Re: Plans\r\n\r\n";
+        let (in_reply_to, references) = reply_threading_headers(raw).unwrap();
+        assert_eq!(in_reply_to, "<c@example.com>");
+        assert_eq!(references, "<a@example.com> <b@example.com> <c@example.com>");
+    }
+
+    #[test]
+    fn first_reply_references_only_the_original() {
+        let raw = b"From: alice@example.com\r\nMessage-ID: <a@example.com>\r\nSubject: Plans\r\n\r\n";
+        assert_eq!(reply_threading_headers(raw), Some(("<a@example.com>".to_string(), "<a@example.com>".to_string())));
+    }
+
+    #[test]
+    fn original_without_message_id_gets_no_threading_headers() {
+        let raw = b"From: alice@example.com\r\nReferences: <a@example.com>\r\nSubject: Plans\r\n\r\n";
+        assert_eq!(reply_threading_headers(raw), None);
+    }
+
+    #[test]
+    fn every_fetch_leaves_messages_unread() {
+        for body in [ImapBodyFetch::Full, ImapBodyFetch::Snippet] {
+            let query = imap_fetch_query(body);
+            assert!(query.contains("BODY.PEEK["), "{query}");
+            assert!(!query.replace("BODY.PEEK[", "").contains("BODY["), "{query}");
+        }
+        assert!(imap_fetch_query(ImapBodyFetch::Snippet).ends_with(&format!("BODY.PEEK[TEXT]<0.{}>)", SNIPPET_FETCH_BYTES)));
+    }
+
+    fn addresses(list: &[&str]) -> Vec<String> {
+        list.iter().map(|address| address.to_string()).collect()
+    }
+
+    #[test]
+    fn reply_goes_to_the_sender_only() {
+        let (to, cc) = reply_recipients("alice@example.com", &addresses(&["me@example.com", "bob@example.com"]), &addresses(&["carol@example.com"]), "me@example.com", false);
+        assert_eq!(to, vec!["alice@example.com"]);
+        assert!(cc.is_empty());
+    }
+
+    #[test]
+    fn reply_all_leaves_out_the_user_and_repeats() {
+        let to_list = addresses(&["Me@Example.com", "bob@example.com", "alice@example.com"]);
+        let cc_list = addresses(&["BOB@example.com", "carol@example.com", "me@example.com"]);
+        let (to, cc) = reply_recipients("alice@example.com", &to_list, &cc_list, "me@example.com", true);
+        assert_eq!(to, vec!["alice@example.com"]);
+        assert_eq!(cc, vec!["bob@example.com", "carol@example.com"]);
+    }
+
+    fn preview(id: &str, message_id: &str, minutes_ago: i64, account: &str) -> ImapEmailPreview {
+        ImapEmailPreview {
+            id: id.to_string(),
+            subject: Some(format!("subject of {}", message_id)),
+            from: Some("Sender".to_string()),
+            from_email: Some("sender@example.com".to_string()),
+            date: Some(Utc::now() - chrono::Duration::minutes(minutes_ago)),
+            date_formatted: None,
+            snippet: None,
+            body: None,
+            is_read: true,
+            message_id: Some(format!("<{}@example.com>", message_id)),
+            copies: 1,
+            account: Some(account.to_string()),
+        }
+    }
+
+    #[test]
+    fn copies_sharing_a_message_id_are_merged() {
+        let inbox = preview("1:10", "invoice", 15, "work");
+        // The forwarded copy in another folder: new UID, unread, header written differently
+        let forwarded = ImapEmailPreview {
+            is_read: false,
+            message_id: Some(" <INVOICE@example.com> ".to_string()),
+            ..preview("1:99", "invoice", 15, "work")
+        };
+        let other = preview("1:11", "newsletter", 5, "work");
+
+        let merged = dedupe_previews(vec![inbox, other, forwarded]);
+        let ids: Vec<&str> = merged.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["1:10", "1:11"]);
+        assert_eq!((merged[0].copies, merged[0].is_read), (2, false));
+        assert_eq!(merged[1].copies, 1);
+    }
+
+    #[test]
+    fn emails_without_message_id_merge_on_subject_sender_and_date() {
+        let without_id = |id: &str, from_email: &str| ImapEmailPreview {
+            message_id: None,
+            from_email: Some(from_email.to_string()),
+            ..preview(id, "receipt", 0, "work")
+        };
+        let first = without_id("1:1", "shop@example.com");
+        let same = ImapEmailPreview { date: first.date, ..without_id("1:2", "SHOP@example.com") };
+        let other_sender = ImapEmailPreview { date: first.date, ..without_id("1:3", "scam@example.com") };
+
+        let merged = dedupe_previews(vec![first, same, other_sender]);
+        assert_eq!(merged.iter().map(|p| p.copies).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn email_ids_are_always_prefixed_with_the_account() {
+        assert_eq!(account_email_id(3, "812"), "3:812");
+        assert_eq!(split_email_id("3:812"), (Some(3), "812"));
+        // Ids handed out before prefixing still point at the primary account
+        assert_eq!(split_email_id("812"), (None, "812"));
+        assert!(is_valid_email_id("3:812") && is_valid_email_id("812"));
+        assert!(!is_valid_email_id("x:812") && !is_valid_email_id("3:") && !is_valid_email_id("3:abc"));
+    }
+
+    #[test]
+    fn accounts_are_aggregated_and_deduplicated_by_message_id() {
+        let work = vec![preview("1:10", "a", 30, "work"), preview("1:11", "shared", 20, "work")];
+        // The shared email was forwarded to the personal account too, with a different UID
+        let personal = vec![preview("2:50", "shared", 20, "personal"), preview("2:51", "b", 10, "personal")];
+        let merged = merge_account_previews(work.into_iter().chain(personal).collect(), 10);
+
+        let ids: Vec<&str> = merged.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["1:10", "1:11", "2:51"]);
+        assert_eq!(merged[1].copies, 2);
+    }
+
+    #[test]
+    fn aggregation_keeps_the_newest_limit() {
+        let previews = vec![preview("1:1", "old", 50, "work"), preview("2:1", "mid", 40, "personal"), preview("1:2", "new", 30, "work")];
+        let ids: Vec<String> = merge_account_previews(previews, 2).into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec!["2:1", "1:2"]);
+    }
+
+    #[tokio::test]
+    async fn logging_in_again_keeps_the_account_id() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "imap@example.com");
+        let repo = &state.user_repository;
+        repo.set_imap_credentials(user_id, "work@example.com", "pw1", Some("imap.example.com"), Some(993), Some("work")).unwrap();
+        repo.set_imap_credentials(user_id, "home@example.com", "pw2", Some("imap.example.com"), Some(993), None).unwrap();
+        let before = repo.get_imap_accounts(user_id).unwrap();
+
+        repo.set_imap_credentials(user_id, "work@example.com", "new-password", Some("imap.example.com"), Some(993), Some("job")).unwrap();
+        let after = repo.get_imap_accounts(user_id).unwrap();
+
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[0].id, before[0].id);
+        assert_eq!(after[0].password, "new-password");
+        assert_eq!(after[0].label.as_deref(), Some("job"));
+        assert_eq!(after[1].id, before[1].id);
+    }
+}
@@ -364,6 +364,7 @@ async fn monitor_instagram_connection(
                                 state.user_repository.delete_bridge(user_id, "instagram")?;
                                 tracing::debug!("Creating new connected bridge record for Instagram");
                                 state.user_repository.create_bridge(new_bridge)?;
+                                state.connection_events.publish(user_id, "instagram", crate::utils::connection_events::ConnectionStatus::BridgeConnected);
                                 // Add client to app state and start sync
                                 let mut matrix_clients = state.matrix_clients.lock().await;
                                 let mut sync_tasks = state.matrix_sync_tasks.lock().await;
@@ -381,7 +382,10 @@ async fn monitor_instagram_connection(
                                 });
                                 // Store the client
                                 let client_arc = Arc::new(client.clone());
-                                matrix_clients.insert(user_id, client_arc.clone());
+                                // A bridge just connected, its messages come in through this client's sync
+                                matrix_clients.set_pinned(user_id, true);
+                                let evicted = matrix_clients.insert(user_id, client_arc.clone());
+                                crate::utils::matrix_auth::shut_down_evicted(evicted, &mut sync_tasks);
                                 // Create sync task
                                 let sync_settings = MatrixSyncSettings::default()
                                     .timeout(Duration::from_secs(30))
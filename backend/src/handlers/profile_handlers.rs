@@ -25,22 +25,37 @@ pub struct TimezoneUpdateRequest {
 use axum::extract::Path;
 use serde_json::json;
 
+use crate::error::ApiError;
 use crate::AppState;
 
+/// Partial profile update, only the fields present in the payload are changed.
+/// Nullable columns use Option<Option<_>> so an explicit null clears the value while a missing field leaves it alone.
 #[derive(Deserialize)]
 pub struct UpdateProfileRequest {
-    email: String,
-    phone_number: String,
-    nickname: String,
-    info: String,
-    timezone: String,
-    timezone_auto: bool,
-    agent_language: String,
-    notification_type: Option<String>,
-    save_context: Option<i32>,
-    location: String,
-    nearby_places: String,
-    preferred_number: Option<String>,
+    email: Option<String>,
+    phone_number: Option<String>,
+    nickname: Option<String>,
+    info: Option<String>,
+    timezone: Option<String>,
+    timezone_auto: Option<bool>,
+    agent_language: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present_field")]
+    notification_type: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_present_field")]
+    save_context: Option<Option<i32>>,
+    location: Option<String>,
+    nearby_places: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present_field")]
+    preferred_number: Option<Option<String>>,
+}
+
+// Wraps a present field (even if null) in Some so it can be told apart from a missing one
+fn deserialize_present_field<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Serialize)]
@@ -72,11 +87,33 @@ pub struct ProfileResponse {
     openrouter_api_key: Option<String>,
     textbee_device_id: Option<String>,
     textbee_api_key: Option<String>,
+    textbee_status: Option<String>,
     estimated_monitoring_cost: f32,
     location: Option<String>,
     nearby_places: Option<String>,
     phone_number_country: Option<String>,
     server_ip: Option<String>,
+    units: Option<String>,
+    share_location: bool,
+    share_contacts: bool,
+    share_history: bool,
+    max_call_minutes: Option<i32>,
+    firecrawl_result_count: Option<i32>,
+    firecrawl_include_domains: Option<String>,
+    firecrawl_exclude_domains: Option<String>,
+    detect_sms_language: bool,
+    paused_until: Option<i32>,
+    pause_allows_critical: bool,
+    tesla_confirm_commands: Vec<String>,
+    email_business_hours_start: Option<i32>,
+    email_business_hours_end: Option<i32>,
+    email_notification_snippets: bool,
+    digest_length: String,
+    digest_tone: String,
+    email_idle: bool,
+    email_idle_active: bool,
+    daily_credit_cap: Option<f32>,
+    weekly_credit_cap: Option<f32>,
 }
 use crate::handlers::auth_middleware::AuthUser;
 
@@ -84,12 +121,9 @@ use crate::handlers::auth_middleware::AuthUser;
 pub async fn get_profile(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
-) -> Result<Json<ProfileResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<ProfileResponse>, ApiError> {
     // Get user profile and settings from database
-    let user = state.user_core.find_by_id(auth_user.user_id).map_err(|e| (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({"error": format!("Database error: {}", e)}))
-    ))?;
+    let user = state.user_core.find_by_id(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
     match user {
         Some(user) => {
             // TODO can be removed in the future
@@ -102,22 +136,13 @@ pub async fn get_profile(
                     }
                 }
             }
-            let user_settings = state.user_core.get_user_settings(auth_user.user_id).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
-            let user_info = state.user_core.get_user_info(auth_user.user_id).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            let user_settings = state.user_core.get_user_settings(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+            let user_info = state.user_core.get_user_info(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
             // Get current digest settings
             let (morning_digest_time, day_digest_time, evening_digest_time) = state.user_core.get_digests(auth_user.user_id)
                 .map_err(|e| {
                     tracing::error!("Failed to get digest settings: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": format!("Failed to get digest settings: {}", e)}))
-                    )
+                    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get digest settings: {}", e))
                 })?;
             // Count current active digests
             let current_count: i32 = [morning_digest_time.as_ref(), day_digest_time.as_ref(), evening_digest_time.as_ref()]
@@ -179,16 +204,10 @@ pub async fn get_profile(
             // Determine country based on phone number
             let country = phone_country.clone().unwrap();
             // Get critical notification info
-            let critical_info = state.user_core.get_critical_notification_info(auth_user.user_id).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            let critical_info = state.user_core.get_critical_notification_info(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
             let estimated_critical_monthly = critical_info.estimated_monthly_price;
             // Get priority notification info
-            let priority_info = state.user_core.get_priority_notification_info(auth_user.user_id).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            let priority_info = state.user_core.get_priority_notification_info(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
             let estimated_priority_monthly = priority_info.estimated_monthly_price;
             // Calculate digest estimated monthly cost
             let estimated_digest_monthly = if current_count > 0 {
@@ -206,6 +225,7 @@ pub async fn get_profile(
             };
             // Calculate total estimated monitoring cost
             let estimated_monitoring_cost = estimated_critical_monthly + estimated_priority_monthly + estimated_digest_monthly;
+            let digest_style = crate::proactive::utils::DigestStyle::from_settings(user_settings.digest_length.as_deref(), user_settings.digest_tone.as_deref());
             Ok(Json(ProfileResponse {
                 id: user.id,
                 email: user.email,
@@ -234,20 +254,105 @@ pub async fn get_profile(
                 openrouter_api_key: openrouter_api_key,
                 textbee_device_id: textbee_device_id,
                 textbee_api_key: textbee_api_key,
+                textbee_status: user_settings.textbee_device_status.clone(),
                 estimated_monitoring_cost,
                 location: user_info.location,
                 nearby_places: user_info.nearby_places,
                 phone_number_country: phone_country,
                 server_ip: user_settings.server_ip,
+                units: user_settings.units,
+                share_location: user_settings.share_location.unwrap_or(true),
+                share_contacts: user_settings.share_contacts.unwrap_or(true),
+                share_history: user_settings.share_history.unwrap_or(true),
+                max_call_minutes: user_settings.max_call_minutes,
+                firecrawl_result_count: user_settings.firecrawl_result_count,
+                firecrawl_include_domains: user_settings.firecrawl_include_domains,
+                firecrawl_exclude_domains: user_settings.firecrawl_exclude_domains,
+                detect_sms_language: user_settings.detect_sms_language.unwrap_or(false),
+                // An expired pause is over, there's nothing to show
+                paused_until: user_settings.paused_until.filter(|until| *until > chrono::Utc::now().timestamp() as i32),
+                pause_allows_critical: user_settings.pause_allows_critical.unwrap_or(false),
+                tesla_confirm_commands: user_settings.tesla_confirm_commands.as_deref()
+                    .map(|list| list.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect())
+                    .unwrap_or_default(),
+                email_business_hours_start: user_settings.email_business_hours_start,
+                email_business_hours_end: user_settings.email_business_hours_end,
+                email_notification_snippets: user_settings.email_notification_snippets.unwrap_or(true),
+                digest_length: digest_style.length.to_string(),
+                digest_tone: digest_style.tone.to_string(),
+                email_idle: user_settings.email_idle.unwrap_or(false),
+                email_idle_active: crate::utils::imap_idle::is_listening(&state, auth_user.user_id),
+                daily_credit_cap: user_settings.daily_credit_cap,
+                weekly_credit_cap: user_settings.weekly_credit_cap,
             }))
         }
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "User not found"}))
-        )),
+        None => Err(ApiError::new(StatusCode::NOT_FOUND, "User not found")),
     }
 }
 
+#[derive(Serialize)]
+pub struct OnboardingStep {
+    id: &'static str,
+    title: &'static str,
+    completed: bool,
+}
+
+#[derive(Serialize)]
+pub struct OnboardingStatus {
+    steps: Vec<OnboardingStep>,
+    completed_count: usize,
+    total: usize,
+    all_completed: bool,
+    dismissed: bool,
+}
+
+/// Setup checklist for new users. Every step is derived from the current state
+/// so it stays correct even if something gets disconnected later.
+pub async fn get_onboarding_status(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<OnboardingStatus>, ApiError> {
+    let db_error = |e: diesel::result::Error| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e));
+    let user = state.user_core.find_by_id(auth_user.user_id)
+        .map_err(db_error)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "User not found"))?;
+    let user_settings = state.user_core.get_user_settings(auth_user.user_id).map_err(db_error)?;
+    let user_info = state.user_core.get_user_info(auth_user.user_id).map_err(db_error)?;
+    let has_integration = state.user_repository.has_any_integration(auth_user.user_id).map_err(db_error)?;
+
+    let steps = vec![
+        OnboardingStep {
+            id: "verify_phone",
+            title: "Verify your phone number",
+            completed: user.verified,
+        },
+        OnboardingStep {
+            id: "connect_integration",
+            title: "Connect a messaging app, email or calendar",
+            completed: has_integration,
+        },
+        OnboardingStep {
+            id: "set_timezone",
+            title: "Set your timezone",
+            completed: user_info.timezone.is_some() || user_settings.timezone_auto.unwrap_or(false),
+        },
+        OnboardingStep {
+            id: "add_credits",
+            title: "Subscribe or add credits",
+            completed: user.sub_tier.is_some() || user.credits > 0.0 || user.credits_left > 0.0,
+        },
+    ];
+    let completed_count = steps.iter().filter(|step| step.completed).count();
+    let total = steps.len();
+    Ok(Json(OnboardingStatus {
+        steps,
+        completed_count,
+        total,
+        all_completed: completed_count == total,
+        dismissed: user_settings.onboarding_dismissed.unwrap_or(false),
+    }))
+}
+
 
 #[derive(Deserialize)]
 pub struct NotifyCreditsRequest {
@@ -263,25 +368,16 @@ pub async fn update_preferred_number(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(request): Json<PreferredNumberRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     // Get user and settings to check their subscription status
     let user = state.user_core.find_by_id(auth_user.user_id)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)}))
-        ))?
-        .ok_or_else(|| (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "User not found"}))
-        ))?;
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "User not found"))?;
 
     let preferred_number = if user.discount_tier.is_some() {
         // If user has a discount_tier, get their dedicated number from environment
         let env_var_name = format!("TWILIO_USER_PHONE_NUMBER_{}", auth_user.user_id);
-        std::env::var(&env_var_name).map_err(|_| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("No dedicated phone number found for user {}", auth_user.user_id)}))
-        ))?
+        std::env::var(&env_var_name).map_err(|_| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("No dedicated phone number found for user {}", auth_user.user_id)))?
     } else {
         // If no discount_tier, validate the requested number is allowed
         let allowed_numbers = vec![
@@ -292,20 +388,14 @@ pub async fn update_preferred_number(
         ];
         
         if !allowed_numbers.contains(&request.preferred_number) {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "Invalid preferred number. Must be one of the allowed Twilio numbers"}))
-            ));
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid preferred number. Must be one of the allowed Twilio numbers"));
         }
         request.preferred_number.clone()
     };
 
     // Update preferred number
     state.user_core.update_preferred_number(auth_user.user_id, &preferred_number)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)}))
-    ))?;
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
 
     println!("Updated preferred number to: {}", preferred_number);
     Ok(Json(json!({
@@ -320,22 +410,16 @@ pub async fn update_notify(
     auth_user: AuthUser,
     Path(user_id): Path<i32>,
     Json(request): Json<NotifyCreditsRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
 
     // Check if user is modifying their own settings or is an admin
     if auth_user.user_id != user_id && !auth_user.is_admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(json!({"error": "You can only modify your own settings unless you're an admin"}))
-        ));
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "You can only modify your own settings unless you're an admin"));
     }
 
     // Update notify preference
     state.user_core.update_notify(user_id, request.notify)
-        .map_err(|e| (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)}))
-    ))?;
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
 
     Ok(Json(json!({
         "message": "Notification preference updated successfully"
@@ -346,7 +430,7 @@ pub async fn update_timezone(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(request): Json<TimezoneUpdateRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
 
     match state.user_core.update_timezone(
         auth_user.user_id,
@@ -355,10 +439,7 @@ pub async fn update_timezone(
         Ok(_) => Ok(Json(json!({
             "message": "Timezone updated successfully"
         }))),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)}))
-        )),
+        Err(e) => Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))),
     }
 }
 
@@ -374,168 +455,300 @@ pub async fn patch_profile_field(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(request): Json<PatchFieldRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let user_id = auth_user.user_id;
 
     match request.field.as_str() {
         "nickname" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "nickname must be a string"}))
-            ))?;
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "nickname must be a string"))?;
             if value.len() > 30 {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "Nickname must be 30 characters or less"}))
-                ));
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "Nickname must be 30 characters or less"));
             }
-            state.user_core.update_nickname(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            state.user_core.update_nickname(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "info" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "info must be a string"}))
-            ))?;
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "info must be a string"))?;
             if value.len() > 500 {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "Info must be 500 characters or less"}))
-                ));
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "Info must be 500 characters or less"));
             }
-            state.user_core.update_info(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            state.user_core.update_info(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "location" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "location must be a string"}))
-            ))?;
-            state.user_core.update_location(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "location must be a string"))?;
+            state.user_core.update_location(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "nearby_places" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "nearby_places must be a string"}))
-            ))?;
-            state.user_core.update_nearby_places(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "nearby_places must be a string"))?;
+            state.user_core.update_nearby_places(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "timezone" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "timezone must be a string"}))
-            ))?;
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "timezone must be a string"))?;
             // Validate timezone
             if value.parse::<chrono_tz::Tz>().is_err() {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "Invalid timezone"}))
-                ));
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid timezone"));
             }
-            state.user_core.update_timezone(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            state.user_core.update_timezone(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "timezone_auto" => {
-            let value = request.value.as_bool().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "timezone_auto must be a boolean"}))
-            ))?;
-            state.user_core.update_timezone_auto(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "timezone_auto must be a boolean"))?;
+            state.user_core.update_timezone_auto(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "agent_language" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "agent_language must be a string"}))
-            ))?;
-            let allowed_languages = vec!["en", "fi", "de"];
-            if !allowed_languages.contains(&value) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "Invalid agent language. Must be 'en', 'fi', or 'de'"}))
-                ));
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "agent_language must be a string"))?;
+            if !crate::utils::voice_languages::is_supported_language(value) {
+                return Err(unsupported_language_error(value));
             }
-            state.user_core.update_agent_language(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            state.user_core.update_agent_language(user_id, &value.to_lowercase()).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "notification_type" => {
-            let value = request.value.as_str().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "notification_type must be a string"}))
-            ))?;
+            let value = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "notification_type must be a string"))?;
             let allowed_types = vec!["sms", "call"];
             if !allowed_types.contains(&value) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "Invalid notification type. Must be 'sms' or 'call'"}))
-                ));
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid notification type. Must be 'sms' or 'call'"));
             }
-            state.user_core.update_notification_type(user_id, Some(value)).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            state.user_core.update_notification_type(user_id, Some(value)).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "save_context" => {
-            let value = request.value.as_i64().ok_or_else(|| (
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "save_context must be an integer"}))
-            ))? as i32;
+            let value = request.value.as_i64().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "save_context must be an integer"))? as i32;
             if !(0..=10).contains(&value) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "save_context must be between 0 and 10"}))
-                ));
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "save_context must be between 0 and 10"));
             }
-            state.user_core.update_save_context(user_id, value).map_err(|e| (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))?;
+            state.user_core.update_save_context(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
         }
         "preferred_number" => {
             let value = if request.value.is_null() {
                 None
             } else {
-                Some(request.value.as_str().ok_or_else(|| (
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "preferred_number must be a string or null"}))
-                ))?)
+                Some(request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "preferred_number must be a string or null"))?)
             };
             match value {
                 Some(v) if !v.is_empty() => {
-                    state.user_core.update_preferred_number(user_id, v).map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": format!("Database error: {}", e)}))
-                    ))?;
+                    state.user_core.update_preferred_number(user_id, v).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
                 }
                 _ => {
-                    state.user_core.clear_preferred_number(user_id).map_err(|e| (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": format!("Database error: {}", e)}))
-                    ))?;
+                    state.user_core.clear_preferred_number(user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+                }
+            }
+        }
+        "bridge_read_receipts_off" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "bridge_read_receipts_off must be a boolean"))?;
+            state.user_core.update_bridge_read_receipts_off(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "onboarding_dismissed" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "onboarding_dismissed must be a boolean"))?;
+            state.user_core.update_onboarding_dismissed(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "llm_models" => {
+            // Object of purpose -> OpenRouter model id, null clears back to the defaults
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let models = request.value.as_object().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "llm_models must be an object or null"))?;
+                let mut validated = std::collections::HashMap::new();
+                for (purpose, model) in models {
+                    if crate::tool_call_utils::utils::ModelPurpose::from_key(purpose).is_none() {
+                        return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Unknown llm_models purpose: {}", purpose)));
+                    }
+                    let model = model.as_str()
+                        .filter(|m| crate::tool_call_utils::utils::is_valid_model_name(m))
+                        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid model for {}, expected e.g. \"openai/gpt-4o-mini\"", purpose)))?;
+                    validated.insert(purpose.clone(), model.to_string());
                 }
+                if validated.is_empty() { None } else { Some(serde_json::to_string(&validated).unwrap()) }
+            };
+            state.user_core.update_llm_models(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "call_opening_templates" => {
+            // {"email": "...", "calendar": "..."}, null goes back to the built-in openings
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let templates = request.value.as_object().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "call_opening_templates must be an object or null"))?;
+                for (category, template) in templates {
+                    if !crate::utils::call_templates::TEMPLATE_CATEGORIES.contains(&category.as_str()) {
+                        return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Unknown template category '{}', expected one of {:?}", category, crate::utils::call_templates::TEMPLATE_CATEGORIES)));
+                    }
+                    let valid = template.as_str()
+                        .map(|t| !t.trim().is_empty() && t.chars().count() <= crate::utils::call_templates::MAX_TEMPLATE_CHARS)
+                        .unwrap_or(false);
+                    if !valid {
+                        return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Template for '{}' must be a non-empty string of at most {} characters", category, crate::utils::call_templates::MAX_TEMPLATE_CHARS)));
+                    }
+                }
+                Some(request.value.to_string())
+            };
+            state.user_core.update_call_opening_templates(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "max_call_minutes" => {
+            // null removes the cap
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let minutes = request.value.as_i64()
+                    .filter(|m| (1..=240).contains(m))
+                    .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "max_call_minutes must be a number between 1 and 240, or null"))?;
+                Some(minutes as i32)
+            };
+            state.user_core.update_max_call_minutes(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "firecrawl_result_count" => {
+            // null goes back to the default
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let max = crate::utils::tool_exec::MAX_FIRECRAWL_RESULTS as i64;
+                let count = request.value.as_i64()
+                    .filter(|c| (1..=max).contains(c))
+                    .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("firecrawl_result_count must be a number between 1 and {}, or null", max)))?;
+                Some(count as i32)
+            };
+            state.user_core.update_firecrawl_result_count(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "firecrawl_include_domains" | "firecrawl_exclude_domains" => {
+            // A comma-separated list; null or an empty string clears it
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let list = request.value.as_str().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("{} must be a comma-separated string of domains, or null", request.field)))?;
+                crate::utils::tool_exec::parse_domain_list(list).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?
+            };
+            let include = request.field == "firecrawl_include_domains";
+            state.user_core.update_firecrawl_domains(user_id, include, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "units" => {
+            // null goes back to metric
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let units = request.value.as_str()
+                    .filter(|u| crate::utils::tool_exec::UNIT_SYSTEMS.contains(u))
+                    .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "units must be \"metric\", \"imperial\" or null"))?;
+                Some(units.to_string())
+            };
+            state.user_core.update_units(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "share_location" | "share_contacts" | "share_history" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("{} must be a boolean", request.field)))?;
+            state.user_core.update_context_sharing(user_id, &request.field, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "tesla_confirm_commands" => {
+            // A list of command names, empty or null runs every command right away
+            let commands: Vec<String> = if request.value.is_null() {
+                Vec::new()
+            } else {
+                serde_json::from_value(request.value.clone()).map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "tesla_confirm_commands must be a list of command names, or null"))?
+            };
+            let mut normalized: Vec<String> = Vec::new();
+            for command in commands {
+                let command = command.trim().to_lowercase();
+                if !crate::tool_call_utils::tesla::TESLA_COMMANDS.contains(&command.as_str()) {
+                    return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Unknown Tesla command '{}', expected one of: {}", command, crate::tool_call_utils::tesla::TESLA_COMMANDS.join(", "))));
+                }
+                if !normalized.contains(&command) {
+                    normalized.push(command);
+                }
+            }
+            let value = if normalized.is_empty() { None } else { Some(normalized.join(",")) };
+            state.user_core.update_tesla_confirm_commands(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "email_business_hours" => {
+            // {"start": 9, "end": 17} in the user's timezone, weekdays only. null sends at any time.
+            let hours = if request.value.is_null() {
+                None
+            } else {
+                let hour = |key: &str| request.value.get(key).and_then(|v| v.as_i64());
+                match (hour("start"), hour("end")) {
+                    (Some(start), Some(end)) if (0..24).contains(&start) && start < end && end <= 24 => Some((start as i32, end as i32)),
+                    _ => return Err(ApiError::new(StatusCode::BAD_REQUEST, "email_business_hours must be {\"start\": hour, \"end\": hour} with 0 <= start < end <= 24, or null")),
+                }
+            };
+            state.user_core.update_email_business_hours(user_id, hours).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "email_notification_snippets" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "email_notification_snippets must be a boolean"))?;
+            state.user_core.update_email_notification_snippets(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "digest_length" | "digest_tone" => {
+            // Stored together so changing one keeps the other, null goes back to the default
+            let options: &[&str] = if request.field == "digest_length" { &crate::proactive::utils::DIGEST_LENGTHS } else { &crate::proactive::utils::DIGEST_TONES };
+            let value = match request.value.as_str().map(|v| v.trim().to_lowercase()) {
+                Some(v) if options.contains(&v.as_str()) => Some(v),
+                None if request.value.is_null() => None,
+                _ => return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("{} must be one of {} or null", request.field, options.join(", ")))
+                    .with_details(json!({"allowed": options}))),
+            };
+            let settings = state.user_core.get_user_settings(user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+            let (length, tone) = if request.field == "digest_length" {
+                (value, settings.digest_tone)
+            } else {
+                (settings.digest_length, value)
+            };
+            state.user_core.update_digest_style(user_id, length.as_deref(), tone.as_deref()).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "email_idle" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "email_idle must be a boolean"))?;
+            state.user_core.update_email_idle(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+            if value && crate::utils::imap_idle::wants_idle(&state, user_id) {
+                crate::utils::imap_idle::start_idle_listener(&state, user_id);
+            } else if !value {
+                crate::utils::imap_idle::stop_idle_listener(&state, user_id);
             }
         }
+        "daily_credit_cap" | "weekly_credit_cap" => {
+            // null removes the cap
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let cap = request.value.as_f64()
+                    .filter(|cap| cap.is_finite() && *cap > 0.0 && *cap <= 1000.0)
+                    .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("{} must be a number of credits between 0 and 1000, or null", request.field)))?;
+                Some(cap as f32)
+            };
+            let settings = state.user_core.get_user_settings(user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+            let (daily, weekly) = if request.field == "daily_credit_cap" {
+                (value, settings.weekly_credit_cap)
+            } else {
+                (settings.daily_credit_cap, value)
+            };
+            state.user_core.update_credit_caps(user_id, daily, weekly).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "detect_sms_language" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "detect_sms_language must be a boolean"))?;
+            state.user_core.update_detect_sms_language(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "call_failure_sms_fallback" => {
+            let value = request.value.as_bool().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "call_failure_sms_fallback must be a boolean"))?;
+            state.user_core.update_call_failure_sms_fallback(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "call_history_limit" => {
+            // null resets back to the default
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let limit = request.value.as_i64().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "call_history_limit must be an integer or null"))?;
+                if limit < 0 || limit > crate::api::elevenlabs::MAX_CALL_HISTORY_LIMIT {
+                    return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("call_history_limit must be between 0 and {}", crate::api::elevenlabs::MAX_CALL_HISTORY_LIMIT)));
+                }
+                Some(limit as i32)
+            };
+            state.user_core.update_call_history_limit(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
+        "email_poll_interval_minutes" => {
+            // null resets back to the tier default
+            let value = if request.value.is_null() {
+                None
+            } else {
+                let minutes = request.value.as_i64().ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "email_poll_interval_minutes must be an integer or null"))?;
+                let min = crate::jobs::scheduler::min_email_poll_interval_minutes() as i64;
+                if minutes < min || minutes > 120 {
+                    return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("email_poll_interval_minutes must be between {} and 120", min)));
+                }
+                Some(minutes as i32)
+            };
+            state.user_core.update_email_poll_interval(user_id, value).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        }
         _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": format!("Unknown field: {}", request.field)}))
-            ));
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Unknown field: {}", request.field)));
         }
     }
 
@@ -936,78 +1149,126 @@ pub async fn update_profile(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(update_req): Json<UpdateProfileRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     println!("Updating profile with notification type: {:?}", update_req.notification_type);
     use regex::Regex;
-    let email_regex = Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
-    if !email_regex.is_match(&update_req.email) {
-        println!("Invalid email format: {}", update_req.email);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid email format"}))
-        ));
+    if let Some(ref email) = update_req.email {
+        let email_regex = Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
+        if !email_regex.is_match(email) {
+            tracing::debug!("Invalid email format: {}", email);
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid email format"));
+        }
+    }
+    if let Some(ref phone_number) = update_req.phone_number {
+        let phone_regex = Regex::new(r"^\+[1-9]\d{1,14}$").unwrap();
+        if !phone_regex.is_match(phone_number) {
+            tracing::debug!("Invalid phone number format: {}", phone_number);
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Phone number must be in E.164 format (e.g., +1234567890)"));
+        }
     }
-   
-    let phone_regex = Regex::new(r"^\+[1-9]\d{1,14}$").unwrap();
-    if !phone_regex.is_match(&update_req.phone_number) {
-        println!("Invalid phone number format: {}", update_req.phone_number);
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Phone number must be in E.164 format (e.g., +1234567890)"}))
-        ));
+    if let Some(ref nickname) = update_req.nickname {
+        if nickname.len() > 30 {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Nickname must be 30 characters or less"));
+        }
+    }
+    if let Some(ref info) = update_req.info {
+        if info.len() > 500 {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Info must be 500 characters or less"));
+        }
+    }
+    if let Some(ref timezone) = update_req.timezone {
+        if timezone.parse::<chrono_tz::Tz>().is_err() {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid timezone"));
+        }
     }
     // Validate agent language
-    let allowed_languages = vec!["en", "fi", "de"];
-    if !allowed_languages.contains(&update_req.agent_language.as_str()) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid agent language. Must be 'en', 'fi', or 'de'"}))
-        ));
+    if let Some(ref agent_language) = update_req.agent_language {
+        if !crate::utils::voice_languages::is_supported_language(agent_language) {
+            return Err(unsupported_language_error(agent_language));
+        }
     }
-    match state.user_core.update_profile(
-        auth_user.user_id,
-        &update_req.email,
-        &update_req.phone_number,
-        &update_req.nickname,
-        &update_req.info,
-        &update_req.timezone,
-        &update_req.timezone_auto,
-        update_req.notification_type.as_deref(),
-        update_req.save_context,
-        &update_req.location,
-        &update_req.nearby_places,
-        update_req.preferred_number.as_deref(),
-    ) {
+    if let Some(Some(ref notification_type)) = update_req.notification_type {
+        if !["sms", "call"].contains(&notification_type.as_str()) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid notification type. Must be 'sms' or 'call'"));
+        }
+    }
+    if let Some(Some(save_context)) = update_req.save_context {
+        if !(0..=10).contains(&save_context) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "save_context must be between 0 and 10"));
+        }
+    }
+
+    // Only what was sent is written, missing fields keep their stored value (even NULL)
+    let user = state.user_core.find_by_id(auth_user.user_id)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "User not found"))?;
+    let phone_number_changed = update_req.phone_number.as_ref().map_or(false, |p| *p != user.phone_number);
+    let update = crate::models::user_models::ProfileUpdate {
+        email: update_req.email,
+        phone_number: update_req.phone_number,
+        nickname: update_req.nickname,
+        info: update_req.info,
+        timezone: update_req.timezone,
+        timezone_auto: update_req.timezone_auto,
+        notification_type: update_req.notification_type,
+        save_context: update_req.save_context,
+        location: update_req.location,
+        nearby_places: update_req.nearby_places,
+        preferred_number: update_req.preferred_number,
+    };
+
+    match state.user_core.update_profile(auth_user.user_id, &update) {
         Ok(_) => {
-            if let Err(e) = state.user_core.update_agent_language(auth_user.user_id, &update_req.agent_language) {
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": format!("Failed to update agent language: {}", e)}))
-                ));
+            if let Some(ref agent_language) = update_req.agent_language {
+                if let Err(e) = state.user_core.update_agent_language(auth_user.user_id, &agent_language.to_lowercase()) {
+                    return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update agent language: {}", e)));
+                }
             }
             // Set phone country after update
-            if let Err(e) = set_user_phone_country(&state, auth_user.user_id, &update_req.phone_number).await {
-                tracing::error!("Failed to set phone country after profile update: {}", e);
-                // Continue anyway, as it's non-critical
+            if phone_number_changed {
+                let phone_number = update.phone_number.as_deref().unwrap_or_default();
+                if let Err(e) = set_user_phone_country(&state, auth_user.user_id, phone_number).await {
+                    tracing::error!("Failed to set phone country after profile update: {}", e);
+                    // Continue anyway, as it's non-critical
+                }
             }
         }, Err(DieselError::NotFound) => {
-            return Err((
-                StatusCode::CONFLICT,
-                Json(json!({"error": "Email already exists"}))
-            ));
+            return Err(ApiError::new(StatusCode::CONFLICT, "Email already exists"));
         }
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)));
         }
     }
+    let Json(profile) = get_profile(State(state), auth_user).await?;
     Ok(Json(json!({
-        "message": "Profile updated successfully"
+        "message": "Profile updated successfully",
+        "profile": profile,
     })))
 }
 
+/// 400 for an agent language without a configured voice, listing the ones that have one
+fn unsupported_language_error(code: &str) -> ApiError {
+    let supported = crate::utils::voice_languages::supported_languages();
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        format!("Unsupported agent language '{}'. Must be one of: {}", code, supported.join(", ")),
+    )
+    .with_code("unsupported_language")
+    .with_details(json!({ "supported": supported }))
+}
+
+/// Languages the agent can be set to, with display names for the settings page
+pub async fn get_supported_languages() -> Json<serde_json::Value> {
+    let languages: Vec<serde_json::Value> = crate::utils::voice_languages::language_options()
+        .into_iter()
+        .map(|language| json!({ "code": language.code, "name": language.name }))
+        .collect();
+    Json(json!({
+        "languages": languages,
+        "default": crate::utils::voice_languages::DEFAULT_LANGUAGE,
+    }))
+}
+
 use axum::extract::Query;
 use crate::utils::tool_exec::get_nearby_towns;
 
@@ -1020,18 +1281,130 @@ pub async fn get_nearby_places(
     State(_state): State<Arc<AppState>>,
     _auth_user: AuthUser,
     Query(query): Query<GetNearbyPlacesQuery>,
-) -> Result<Json<Vec<String>>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Vec<String>>, ApiError> {
     match get_nearby_towns(&query.location).await {
         Ok(places) => {
             Ok(Json(places))
         },
-        Err(e) => Err((StatusCode::BAD_REQUEST, Json(json!({"error": e.to_string()})))),
+        Err(e) => {
+            crate::utils::tool_exec::log_geocode_error("looking up nearby places", &e);
+            let status = match e {
+                crate::utils::tool_exec::GeocodeError::NotFound(_) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            Err(ApiError::new(status, e.spoken_message(&query.location)))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    page: Option<i64>, // 0-based
+    per_page: Option<i64>,
+    include_content: Option<bool>, // show the text of undelivered notifications
+}
+
+#[derive(Serialize)]
+pub struct ActivityItem {
+    kind: String, // "call", "message", "notification" or "failed_notification"
+    activity_type: String,
+    timestamp: i32,
+    description: String,
+    success: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ActivityFeedResponse {
+    items: Vec<ActivityItem>,
+    page: i64,
+    per_page: i64,
+    has_more: bool,
+}
+
+/// Deepest item the feed can be paged to, older activity is in the usage history
+const MAX_ACTIVITY_ITEMS: i64 = 1000;
+
+/// Kind and one-line description for a usage log activity type, e.g. "whatsapp_critical"
+fn describe_activity(activity_type: &str) -> (&'static str, String) {
+    let via = if activity_type.contains("_call") { "call" } else { "SMS" };
+    let service = activity_type.split('_').next().unwrap_or(activity_type);
+    let service = service.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + service.get(1..).unwrap_or("");
+    match activity_type {
+        "sms" => ("message", "Answered your text message".to_string()),
+        "call" => ("call", "Voice call with the assistant".to_string()),
+        t if t.ends_with("_retry") => ("notification", "Resent a notification that failed earlier".to_string()),
+        t if t.ends_with("_sms_fallback") => ("notification", format!("{} notification sent by SMS after the call failed", service)),
+        t if t.contains("critical") => ("notification", format!("Critical {} notification", service)),
+        t if t.contains("waiting_check") => ("notification", format!("{} message matched one of your waiting checks, notified by {}", service, via)),
+        t if t.contains("priority") => ("notification", format!("{} message from a priority sender, notified by {}", service, via)),
+        t if t.contains("digest") => ("notification", "Sent your digest".to_string()),
+        _ => ("notification", format!("{} notification by {}", service, via)),
+    }
+}
+
+/// What the assistant has done for the user, newest first: usage logs merged with notifications
+/// that couldn't be delivered. Message bodies are left out unless `include_content` is set.
+pub async fn get_activity_feed(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityFeedResponse>, ApiError> {
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+    let page = query.page.unwrap_or(0).max(0);
+    let offset = page * per_page;
+    if offset >= MAX_ACTIVITY_ITEMS {
+        return Ok(Json(ActivityFeedResponse { items: Vec::new(), page, per_page, has_more: false }));
     }
+    // Both sources are newest first, so the first offset + per_page + 1 of each cover this page and tell if there's another
+    let fetch = offset + per_page + 1;
+
+    let logs = state.user_repository.get_recent_usage_logs(auth_user.user_id, fetch)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    let failures = state.user_repository.get_failed_notifications(Some(auth_user.user_id), fetch)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    let mut items: Vec<ActivityItem> = logs.into_iter()
+        .map(|log| {
+            let (kind, description) = describe_activity(&log.activity_type);
+            ActivityItem {
+                kind: kind.to_string(),
+                activity_type: log.activity_type,
+                timestamp: log.created_at,
+                description,
+                success: log.success,
+                content: None,
+            }
+        })
+        .collect();
+    items.extend(failures.into_iter().map(|failure| ActivityItem {
+        kind: "failed_notification".to_string(),
+        description: format!("Couldn't deliver a notification by {}", failure.channel),
+        timestamp: failure.created_at,
+        success: Some(false),
+        content: if query.include_content.unwrap_or(false) {
+            crate::utils::encryption::decrypt(&failure.encrypted_payload).ok()
+        } else {
+            None
+        },
+        activity_type: failure.notification_type,
+    }));
+    // Stable sort keeps each source's own order for equal timestamps
+    items.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    let has_more = items.len() as i64 > offset + per_page;
+    let items: Vec<ActivityItem> = items.into_iter()
+        .skip(offset as usize)
+        .take(per_page as usize)
+        .collect();
+    Ok(Json(ActivityFeedResponse { items, page, per_page, has_more }))
 }
 
 #[derive(Serialize)]
 pub struct EmailJudgmentResponse {
     pub id: i32,
+    pub email_id: Option<String>,
     pub email_timestamp: i32,
     pub processed_at: i32,
     pub should_notify: bool,
@@ -1044,13 +1417,14 @@ pub struct EmailJudgmentResponse {
 pub async fn get_email_judgments(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
-) -> Result<Json<Vec<EmailJudgmentResponse>>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Vec<EmailJudgmentResponse>>, ApiError> {
     match state.user_repository.get_user_email_judgments(auth_user.user_id) {
         Ok(judgments) => {
             let responses: Vec<EmailJudgmentResponse> = judgments
                 .into_iter()
                 .map(|j| EmailJudgmentResponse {
                     id: j.id.unwrap_or(0),
+                    email_id: j.email_id,
                     email_timestamp: j.email_timestamp,
                     processed_at: j.processed_at,
                     should_notify: j.should_notify,
@@ -1062,12 +1436,175 @@ pub async fn get_email_judgments(
         },
         Err(e) => {
             tracing::error!("Failed to get email judgments: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get email judgments: {}", e)}))
-            ))
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get email judgments: {}", e)))
+        }
+    }
+}
+
+
+#[derive(Deserialize)]
+pub struct RerunEmailJudgmentsRequest {
+    start: i64, // unix seconds
+    end: Option<i64>, // unix seconds, defaults to now
+}
+
+#[derive(Serialize)]
+pub struct ChangedJudgment {
+    email_id: String,
+    email_timestamp: i32,
+    subject: Option<String>,
+    previous_should_notify: Option<bool>,
+    should_notify: bool,
+    reason: String,
+}
+
+#[derive(Serialize)]
+pub struct RerunEmailJudgmentsResponse {
+    evaluated: usize,
+    important: usize,
+    changed: Vec<ChangedJudgment>,
+}
+
+const MAX_RERUN_RANGE_SECS: i64 = 7 * 24 * 60 * 60;
+const MAX_RERUN_EMAILS: usize = 20;
+/// The day-granular search can return mail just outside the range, fetch a few extra for the exact filter
+const RERUN_SEARCH_LIMIT: u32 = 2 * MAX_RERUN_EMAILS as u32;
+
+/// Re-classifies the emails in a date range with the user's current priority senders and
+/// settings, replacing the stored judgments. Ranges, email counts and runs per hour are
+/// capped since every email is one LLM call.
+pub async fn rerun_email_judgments(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(request): Json<RerunEmailJudgmentsRequest>,
+) -> Result<Json<RerunEmailJudgmentsResponse>, ApiError> {
+    let now = chrono::Utc::now().timestamp();
+    let end = request.end.unwrap_or(now).min(now);
+    if request.start >= end {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "start must be before end"));
+    }
+    if end - request.start > MAX_RERUN_RANGE_SECS {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Date range can be at most 7 days"));
+    }
+    // Judgments are only kept for 30 days
+    if request.start < now - 30 * 24 * 60 * 60 {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "start can be at most 30 days ago"));
+    }
+
+    // Define rate limit: 3 reruns per hour per user
+    let quota = governor::Quota::per_hour(std::num::NonZeroU32::new(3).unwrap());
+    let limiter_key = auth_user.user_id.to_string();
+    let rate_limited = {
+        let entry = state.email_judgment_rerun_limiter
+            .entry(limiter_key.clone())
+            .or_insert_with(|| governor::RateLimiter::keyed(quota));
+        entry.value().check_key(&limiter_key).is_err()
+    };
+    if rate_limited {
+        return Err(ApiError::new(StatusCode::TOO_MANY_REQUESTS, "Too many re-runs, try again later"));
+    }
+
+    // The server finds the range, however far back it is in the mailbox
+    let selection = crate::handlers::imap_handlers::ImapSelection::Between { start: request.start, end };
+    let emails = crate::handlers::imap_handlers::fetch_emails_imap_selected(
+        &state, auth_user.user_id, true, Some(RERUN_SEARCH_LIMIT), false, false, None,
+        selection, crate::handlers::imap_handlers::ImapBodyFetch::Full, None,
+    )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch emails for judgment rerun: {:?}", e);
+            ApiError::new(StatusCode::BAD_GATEWAY, "Failed to fetch emails")
+        })?;
+    let mut emails: Vec<_> = emails
+        .into_iter()
+        .filter(|email| email.date.map_or(false, |date| (request.start..=end).contains(&date.timestamp())))
+        .collect();
+    emails.sort_by(|a, b| b.date.cmp(&a.date));
+    emails.truncate(MAX_RERUN_EMAILS);
+
+    let priority_senders = state.user_repository.get_priority_senders(auth_user.user_id, "imap").unwrap_or_default();
+    let user_id = auth_user.user_id;
+    let state_ref = &state;
+    let response = rejudge_emails(&state, user_id, emails, &priority_senders, now, move |email_content| async move {
+        match crate::proactive::utils::check_message_importance(state_ref, user_id, &email_content, "", "", "").await {
+            Ok((is_critical, message, _)) => Some((
+                is_critical,
+                message.unwrap_or_else(|| if is_critical { "Critical".to_string() } else { "Not critical".to_string() }),
+            )),
+            Err(e) => {
+                tracing::error!("Failed to re-judge email for user {}: {}", user_id, e);
+                None
+            }
+        }
+    }).await?;
+    Ok(Json(response))
+}
+
+/// Judges each email and replaces its stored judgment. Priority senders are always important,
+/// other emails go through `classify`, which returns None when it couldn't decide.
+async fn rejudge_emails<F, Fut>(
+    state: &Arc<AppState>,
+    user_id: i32,
+    emails: Vec<crate::handlers::imap_handlers::ImapEmailPreview>,
+    priority_senders: &[crate::models::user_models::PrioritySender],
+    now: i64,
+    mut classify: F,
+) -> Result<RerunEmailJudgmentsResponse, ApiError>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Option<(bool, String)>>,
+{
+    let mut response = RerunEmailJudgmentsResponse { evaluated: 0, important: 0, changed: Vec::new() };
+    for email in emails {
+        let from = email.from.as_deref().unwrap_or("Unknown");
+        let from_email = email.from_email.as_deref().unwrap_or("Unknown");
+        let is_priority = priority_senders.iter().filter(|p_send| p_send.noti_mode == "all").any(|priority_sender| {
+            let priority_lower = priority_sender.sender.to_lowercase();
+            from.to_lowercase().contains(&priority_lower) || from_email.to_lowercase().contains(&priority_lower)
+        });
+        let (should_notify, reason) = if is_priority {
+            (true, "Sender is a priority sender".to_string())
+        } else {
+            let email_content = format!(
+                "From: {}\nSubject: {}\nDate: {}\nBody: {}\n---\n",
+                from,
+                email.subject.as_deref().unwrap_or("No subject"),
+                email.date_formatted.as_deref().unwrap_or("Unknown date"),
+                email.body.as_deref().unwrap_or("No content")
+            );
+            match classify(email_content).await {
+                Some(judgment) => judgment,
+                None => continue,
+            }
+        };
+        let email_timestamp = email.date.map(|date| date.timestamp() as i32).unwrap_or_default();
+        let new_judgment = crate::models::user_models::NewEmailJudgment {
+            user_id,
+            email_timestamp,
+            processed_at: now as i32,
+            should_notify,
+            score: if should_notify { 10 } else { 0 },
+            reason: reason.clone(),
+            email_id: Some(email.id.clone()),
+        };
+        let previous = state.user_repository.replace_email_judgment(&new_judgment).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        response.evaluated += 1;
+        if should_notify {
+            response.important += 1;
+        }
+        let previous_should_notify = previous.map(|p| p.should_notify);
+        if previous_should_notify != Some(should_notify) {
+            response.changed.push(ChangedJudgment {
+                email_id: email.id.clone(),
+                email_timestamp,
+                subject: email.subject.clone(),
+                previous_should_notify,
+                should_notify,
+                reason,
+            });
         }
     }
+    Ok(response)
 }
 
 
@@ -1089,15 +1626,12 @@ pub struct UpdateDigestsRequest {
 pub async fn get_digests(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
-) -> Result<Json<DigestsResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<DigestsResponse>, ApiError> {
     // Get current digest settings
     let (morning_digest_time, day_digest_time, evening_digest_time) = state.user_core.get_digests(auth_user.user_id)
         .map_err(|e| {
             tracing::error!("Failed to get digest settings: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get digest settings: {}", e)}))
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get digest settings: {}", e))
         })?;
 
     Ok(Json(DigestsResponse {
@@ -1111,7 +1645,7 @@ pub async fn update_digests(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(request): Json<UpdateDigestsRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     match state.user_core.update_digests(
         auth_user.user_id,
         request.morning_digest_time.as_deref(),
@@ -1125,10 +1659,7 @@ pub async fn update_digests(
             });
             Ok(Json(response))
         },
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Failed to update digest settings: {}", e)}))
-        )),
+        Err(e) => Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update digest settings: {}", e))),
     }
 }
 
@@ -1154,7 +1685,7 @@ pub async fn update_critical_settings(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(request): Json<UpdateCriticalRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     println!("Received update_critical_settings request: enabled={:?}, call_notify={:?}, action={:?}",
         request.enabled, request.call_notify, request.action_on_critical_message);
 
@@ -1162,28 +1693,19 @@ pub async fn update_critical_settings(
         println!("Updating critical_enabled to: {:?}", enabled);
         if let Err(e) = state.user_core.update_critical_enabled(auth_user.user_id, enabled) {
             tracing::error!("Failed to update critical enabled setting: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update critical enabled setting: {}", e)}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update critical enabled setting: {}", e)));
         }
     }
     if let Some(call_notify) = request.call_notify {
         if let Err(e) = state.user_core.update_call_notify(auth_user.user_id, call_notify) {
             tracing::error!("Failed to update call notify setting: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update call notify setting: {}", e)}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update call notify setting: {}", e)));
         }
     }
     if let Some(action) = request.action_on_critical_message {
         if let Err(e) = state.user_core.update_action_on_critical_message(auth_user.user_id, action) {
             tracing::error!("Failed to update action on critical message setting: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update action on critical message setting: {}", e)}))
-            ));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update action on critical message setting: {}", e)));
         }
     }
     Ok(Json(json!({
@@ -1203,15 +1725,12 @@ pub struct CriticalNotificationInfo {
 pub async fn get_critical_settings(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
-) -> Result<Json<CriticalNotificationInfo>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<CriticalNotificationInfo>, ApiError> {
     match state.user_core.get_critical_notification_info(auth_user.user_id) {
         Ok(info) => Ok(Json(info)),
         Err(e) => {
             tracing::error!("Failed to get critical notification info: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get critical notification info: {}", e)})),
-            ))
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get critical notification info: {}", e)))
         }
     }
 }
@@ -1221,7 +1740,7 @@ pub async fn update_proactive_agent_on(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(request): Json<ProactiveAgentEnabledRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
 
     // Update critical enabled setting
     match state.user_core.update_proactive_agent_on(auth_user.user_id, request.enabled) {
@@ -1230,10 +1749,7 @@ pub async fn update_proactive_agent_on(
         }))),
         Err(e) => {
             tracing::error!("Failed to update proactive notifications setting: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update proactive notifications setting: {}", e)}))
-            ))
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update proactive notifications setting: {}", e)))
         }
     }
 }
@@ -1241,7 +1757,7 @@ pub async fn update_proactive_agent_on(
 pub async fn get_proactive_agent_on(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
-) -> Result<Json<ProactiveAgentEnabledResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<ProactiveAgentEnabledResponse>, ApiError> {
     match state.user_core.get_proactive_agent_on(auth_user.user_id) {
         Ok(enabled) => {
             Ok(Json(ProactiveAgentEnabledResponse{
@@ -1250,10 +1766,7 @@ pub async fn get_proactive_agent_on(
         },
         Err(e) => {
             tracing::error!("Failed to get critical enabled setting: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to get critical enabled setting: {}", e)}))
-            ))
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get critical enabled setting: {}", e)))
         }
     }
 }
@@ -1262,14 +1775,11 @@ pub async fn delete_user(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     axum::extract::Path(user_id): axum::extract::Path<i32>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     tracing::info!("Deleting user: {}", auth_user.user_id);
 
     if auth_user.user_id != user_id && !auth_user.is_admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(json!({"error": "You can only delete your own account unless you're an admin"}))
-        ));
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "You can only delete your own account unless you're an admin"));
     }
     
     // First verify the user exists
@@ -1284,28 +1794,651 @@ pub async fn delete_user(
                 },
                 Err(e) => {
                     tracing::error!("Failed to delete user {}: {}", user_id, e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": format!("Failed to delete user: {}", e)}))
-                    ))
+                    Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete user: {}", e)))
                 }
             }
         },
         Ok(None) => {
             tracing::warn!("Attempted to delete non-existent user {}", user_id);
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "User not found"}))
-            ))
+            Err(ApiError::new(StatusCode::NOT_FOUND, "User not found"))
         },
         Err(e) => {
             tracing::error!("Database error while checking user {}: {}", user_id, e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)}))
-            ))
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))
         }
     }
 }
 
 
+
+/// Server-sent events for the user's connection changes (OAuth finished, bridge connected, token
+/// expired), so the frontend can update without polling or reloading the page
+pub async fn connection_events(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> axum::response::sse::Sse<impl futures::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>> {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures::StreamExt;
+
+    let stream = state.connection_events.subscribe(auth_user.user_id).map(|event| {
+        Ok(Event::default()
+            .event("connection")
+            .json_data(&event)
+            .unwrap_or_else(|_| Event::default().event("connection")))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Hangs up the notification call that's ringing or in progress, `cancelled` is false when there was none
+pub async fn cancel_call(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let cancelled = crate::api::elevenlabs::cancel_notification_call(&state, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to cancel notification call for user {}: {}", auth_user.user_id, e);
+            ApiError::new(StatusCode::BAD_GATEWAY, "Failed to cancel the call")
+        })?;
+    Ok(Json(json!({"cancelled": cancelled})))
+}
+
+#[derive(Deserialize)]
+pub struct NotificationPreviewRequest {
+    /// A notification content type, e.g. "email_priority_sms", "whatsapp_critical" or "calendar_notification"
+    content_type: String,
+    sender: Option<String>,
+    subject: Option<String>,
+    content: Option<String>,
+    minutes: Option<i32>,
+    /// Preview with the newest email in the inbox instead of the sample fields, email content types only
+    #[serde(default)]
+    use_latest: bool,
+}
+
+/// Renders the SMS and call opening a notification would have, without sending anything or charging
+pub async fn notification_preview(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(request): Json<NotificationPreviewRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    use crate::utils::call_templates::CallOpeningContext;
+
+    let user_settings = state.user_core.get_user_settings(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    let content_type = request.content_type.trim().to_lowercase();
+    let category = crate::utils::call_templates::template_category(&content_type).ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "content_type must be an email, calendar or messaging notification type"))?;
+
+    let (notification, opening_context) = match category {
+        "email" => {
+            let (from, subject, body) = if request.use_latest {
+                let latest = crate::handlers::imap_handlers::fetch_emails_imap(&state, auth_user.user_id, true, Some(1), false, false, None)
+                    .await
+                    .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, format!("Failed to fetch the latest email: {:?}", e)))?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "No emails to preview with"))?;
+                (latest.from, latest.subject, latest.body.or(latest.snippet))
+            } else {
+                (
+                    Some(request.sender.unwrap_or_else(|| "Alex Smith".to_string())),
+                    Some(request.subject.unwrap_or_else(|| "Project update".to_string())),
+                    Some(request.content.unwrap_or_else(|| "Hi, the deadline moved to Friday. Can you confirm you're still on track?".to_string())),
+                )
+            };
+            let context = CallOpeningContext {
+                sender: Some(from.clone().unwrap_or_else(|| "Unknown".to_string())),
+                subject: Some(subject.clone().unwrap_or_else(|| "No subject".to_string())),
+                ..Default::default()
+            };
+            let include_snippet = user_settings.email_notification_snippets.unwrap_or(true);
+            (crate::proactive::utils::email_notification_text(from.as_deref(), subject.as_deref(), body.as_deref(), include_snippet), context)
+        }
+        "calendar" => {
+            let summary = request.subject.unwrap_or_else(|| "Team meeting".to_string());
+            let minutes = request.minutes.unwrap_or(15);
+            let context = CallOpeningContext {
+                subject: Some(summary.clone()),
+                time: Some((chrono::Utc::now() + chrono::Duration::minutes(minutes as i64)).format("%H:%M UTC").to_string()),
+                ..Default::default()
+            };
+            (crate::proactive::utils::calendar_notification_text(&summary, minutes), context)
+        }
+        _ => {
+            let service = content_type.split('_').next().unwrap_or_default().to_string();
+            let sender = request.sender.unwrap_or_else(|| "Alex Smith".to_string());
+            let content = request.content.unwrap_or_else(|| "Are we still on for tonight? Let me know before 6.".to_string());
+            let context = CallOpeningContext {
+                sender: Some(sender.clone()),
+                service: Some(crate::utils::bridge::capitalize(&service)),
+                ..Default::default()
+            };
+            (crate::utils::bridge::trim_for_sms(&service, &sender, &content), context)
+        }
+    };
+
+    let preview = crate::proactive::utils::preview_notification(&user_settings, &content_type, &notification, &opening_context);
+    Ok(Json(json!({
+        "content_type": content_type,
+        "channel": preview.channel,
+        "sms": preview.sms,
+        "sms_segments": preview.sms_segments,
+        "first_message": preview.first_message,
+    })))
+}
+
+/// Progress of the user's bridge resyncs, for watching a long one or seeing where a failed one stopped
+pub async fn resync_status(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Json<serde_json::Value> {
+    Json(json!({"resyncs": state.bridge_resyncs.status(auth_user.user_id)}))
+}
+
+/// Wipes the user's stored conversation and anything remembered from it
+pub async fn clear_conversation_history(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let removed = state.user_repository.clear_message_history(auth_user.user_id).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    state.sms_languages.forget(auth_user.user_id);
+    tracing::info!("Cleared {} conversation messages for user {}", removed, auth_user.user_id);
+    Ok(Json(json!({"removed": removed})))
+}
+
+/// Queued emails, chat messages and commands that haven't gone out yet, soonest first
+pub async fn list_scheduled_actions(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Json<serde_json::Value> {
+    let scheduled = crate::tool_call_utils::utils::pending_action_summaries(&state, auth_user.user_id).await;
+    // Sends the user asked for at a set time, kept in the database until then
+    let sends: Vec<serde_json::Value> = match state.user_repository.get_upcoming_scheduled_sends(auth_user.user_id) {
+        Ok(sends) => sends.into_iter().map(|send| json!({
+            "id": send.id,
+            "kind": send.kind,
+            "target": send.target,
+            "send_at": send.send_at,
+        })).collect(),
+        Err(e) => {
+            tracing::error!("Failed to get scheduled sends for user {}: {}", auth_user.user_id, e);
+            Vec::new()
+        }
+    };
+    Json(json!({"scheduled": scheduled, "sends": sends}))
+}
+
+/// Cancels an email or message scheduled for a set time
+pub async fn cancel_scheduled_send(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(send_id): Path<i32>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let cancelled = state.user_repository.cancel_scheduled_sends(auth_user.user_id, Some(send_id))
+        .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
+    if cancelled == 0 {
+        return Err(ApiError::not_found("Nothing scheduled with that id, it may have already gone out"));
+    }
+    tracing::info!("User {} cancelled scheduled send {}", auth_user.user_id, send_id);
+    Ok(Json(json!({"cancelled": send_id})))
+}
+
+/// Cancels one queued item so it never goes out
+pub async fn cancel_scheduled_action(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(action_id): Path<u64>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let outcome = crate::tool_call_utils::utils::cancel_pending_actions(&state, auth_user.user_id, Some(action_id)).await;
+    if !outcome.too_late.is_empty() {
+        return Err(ApiError::new(StatusCode::CONFLICT, "Too late to cancel, it has already started going out"));
+    }
+    if outcome.cancelled.is_empty() {
+        return Err(ApiError::not_found("Nothing scheduled with that id, it may have already gone out"));
+    }
+    tracing::info!("User {} cancelled scheduled action {}", auth_user.user_id, action_id);
+    Ok(Json(json!({"cancelled": outcome.cancelled})))
+}
+
+#[derive(Deserialize)]
+pub struct PauseRequest {
+    paused_until: Option<i32>, // unix timestamp, null resumes now
+    #[serde(default)]
+    allow_critical: bool,
+    /// Pause until resumed, `paused_until` is ignored
+    #[serde(default)]
+    indefinite: bool,
+}
+
+/// Vacation mode: no polling, digests or notifications until `paused_until`
+pub async fn update_pause(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(request): Json<PauseRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let paused_until = if request.indefinite {
+        Some(crate::proactive::utils::INDEFINITE_PAUSE)
+    } else {
+        if let Some(until) = request.paused_until {
+            if until <= chrono::Utc::now().timestamp() as i32 {
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "paused_until must be in the future"));
+            }
+        }
+        request.paused_until
+    };
+    state.user_core.update_pause(auth_user.user_id, paused_until, request.allow_critical).map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    Ok(Json(json!({
+        "paused_until": paused_until,
+        "indefinite": request.indefinite,
+        "allow_critical": request.allow_critical,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    fn as_user(user_id: i32) -> AuthUser {
+        AuthUser { user_id, is_admin: false }
+    }
+
+    async fn patch_profile(state: &Arc<AppState>, user_id: i32, body: serde_json::Value) {
+        let request: UpdateProfileRequest = serde_json::from_value(body).unwrap();
+        update_profile(State(state.clone()), as_user(user_id), Json(request)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn nickname_only_update_keeps_null_fields_null() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "nick@example.com");
+
+        patch_profile(&state, user_id, json!({"nickname": "Sam"})).await;
+
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        let info = state.user_core.get_user_info(user_id).unwrap();
+        assert_eq!(user.nickname.as_deref(), Some("Sam"));
+        assert_eq!(info.timezone, None);
+        assert_eq!(info.info, None);
+        assert_eq!(info.location, None);
+        assert_eq!(info.nearby_places, None);
+    }
+
+    #[tokio::test]
+    async fn nickname_only_update_keeps_stored_values() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "kept@example.com");
+        patch_profile(&state, user_id, json!({
+            "timezone": "Europe/Helsinki",
+            "info": "Prefers short answers",
+            "location": "Helsinki",
+            "notification_type": "call",
+        })).await;
+
+        patch_profile(&state, user_id, json!({"nickname": "Sam"})).await;
+
+        let info = state.user_core.get_user_info(user_id).unwrap();
+        assert_eq!(info.timezone.as_deref(), Some("Europe/Helsinki"));
+        assert_eq!(info.info.as_deref(), Some("Prefers short answers"));
+        assert_eq!(info.location.as_deref(), Some("Helsinki"));
+        let settings = state.user_core.get_user_settings(user_id).unwrap();
+        assert_eq!(settings.notification_type.as_deref(), Some("call"));
+        assert!(state.user_core.find_by_id(user_id).unwrap().unwrap().verified);
+    }
+
+    fn completed_steps(status: &OnboardingStatus) -> Vec<&'static str> {
+        status.steps.iter().filter(|step| step.completed).map(|step| step.id).collect()
+    }
+
+    #[tokio::test]
+    async fn onboarding_tracks_phone_and_timezone_steps() {
+        let state = test_state();
+        // Signed up but nothing else: unverified, no plan, no credits
+        state.user_core.create_user(crate::handlers::auth_dtos::NewUser {
+            email: "fresh@example.com".to_string(),
+            password_hash: "not-a-real-hash".to_string(),
+            phone_number: "+15555550124".to_string(),
+            time_to_live: 0,
+            verified: false,
+            credits: 0.0,
+            credits_left: 0.0,
+            charge_when_under: false,
+            waiting_checks_count: 0,
+            discount: false,
+            sub_tier: None,
+        }).unwrap();
+        let user_id = state.user_core.find_by_email("fresh@example.com").unwrap().unwrap().id;
+        state.user_core.ensure_user_settings_exist(user_id).unwrap();
+
+        let status = get_onboarding_status(State(state.clone()), as_user(user_id)).await.unwrap().0;
+        assert!(completed_steps(&status).is_empty());
+        assert_eq!((status.completed_count, status.total, status.all_completed), (0, 4, false));
+
+        state.user_core.verify_user(user_id).unwrap();
+        state.user_core.update_timezone(user_id, "Europe/Helsinki").unwrap();
+
+        let status = get_onboarding_status(State(state.clone()), as_user(user_id)).await.unwrap().0;
+        assert_eq!(completed_steps(&status), vec!["verify_phone", "set_timezone"]);
+        assert_eq!(status.completed_count, 2);
+        assert!(!status.all_completed);
+    }
+
+    fn insert_activity(state: &Arc<AppState>, user_id: i32, calls_at: &[i32], failures_at: &[i32]) {
+        use crate::models::user_models::{NewFailedNotification, NewUsageLog};
+        use crate::schema::{failed_notifications, usage_logs};
+        use diesel::RunQueryDsl;
+        let mut conn = state.db_pool.get().unwrap();
+        for created_at in calls_at {
+            diesel::insert_into(usage_logs::table)
+                .values(&NewUsageLog {
+                    user_id,
+                    sid: None,
+                    activity_type: "call".to_string(),
+                    credits: Some(0.5),
+                    created_at: *created_at,
+                    time_consumed: Some(60),
+                    success: Some(true),
+                    reason: None,
+                    status: None,
+                    recharge_threshold_timestamp: None,
+                    zero_credits_timestamp: None,
+                })
+                .execute(&mut conn)
+                .unwrap();
+        }
+        for created_at in failures_at {
+            diesel::insert_into(failed_notifications::table)
+                .values(&NewFailedNotification {
+                    user_id,
+                    notification_type: "whatsapp_priority_sms".to_string(),
+                    channel: "sms".to_string(),
+                    encrypted_payload: crate::utils::encryption::encrypt("Anna: are you coming?").unwrap(),
+                    error: "undelivered".to_string(),
+                    created_at: *created_at,
+                })
+                .execute(&mut conn)
+                .unwrap();
+        }
+    }
+
+    async fn feed_page(state: &Arc<AppState>, user_id: i32, page: i64, per_page: i64) -> ActivityFeedResponse {
+        let query = ActivityQuery { page: Some(page), per_page: Some(per_page), include_content: None };
+        get_activity_feed(State(state.clone()), as_user(user_id), Query(query)).await.unwrap().0
+    }
+
+    #[tokio::test]
+    async fn activity_feed_interleaves_sources_across_pages() {
+        crate::test_support::set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "feed@example.com");
+        insert_activity(&state, user_id, &[100, 300, 500], &[200, 400]);
+
+        let timestamps = |feed: &ActivityFeedResponse| feed.items.iter().map(|item| item.timestamp).collect::<Vec<_>>();
+        let first = feed_page(&state, user_id, 0, 2).await;
+        assert_eq!((timestamps(&first), first.has_more), (vec![500, 400], true));
+        assert_eq!(first.items[1].kind, "failed_notification");
+        let second = feed_page(&state, user_id, 1, 2).await;
+        assert_eq!((timestamps(&second), second.has_more), (vec![300, 200], true));
+        let last = feed_page(&state, user_id, 2, 2).await;
+        assert_eq!((timestamps(&last), last.has_more), (vec![100], false));
+    }
+
+    #[tokio::test]
+    async fn exactly_full_page_has_no_more() {
+        crate::test_support::set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "full-page@example.com");
+        insert_activity(&state, user_id, &[100, 300], &[200]);
+
+        let feed = feed_page(&state, user_id, 0, 3).await;
+        assert_eq!(feed.items.len(), 3);
+        assert!(!feed.has_more);
+        assert!(feed_page(&state, user_id, 1, 3).await.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unsupported_language_lists_the_supported_ones() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "language@example.com");
+        let request: UpdateProfileRequest = serde_json::from_value(json!({"agent_language": "xx"})).unwrap();
+
+        let error = update_profile(State(state.clone()), as_user(user_id), Json(request)).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "unsupported_language");
+        // The error lists the same codes the settings page offers
+        let mut offered: Vec<String> = get_supported_languages().await.0["languages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|language| language["code"].as_str().unwrap().to_string())
+            .collect();
+        offered.sort();
+        let listed: Vec<String> = serde_json::from_value(error.details.unwrap()["supported"].clone()).unwrap();
+        assert_eq!(listed, offered);
+        assert!(error.message.starts_with("Unsupported agent language 'xx'"));
+        assert_eq!(state.user_core.get_user_settings(user_id).unwrap().agent_language, "en");
+    }
+
+    #[tokio::test]
+    async fn scheduled_send_of_another_user_cannot_be_cancelled() {
+        crate::test_support::set_test_encryption_key();
+        let state = test_state();
+        let owner = create_test_user(&state, "owner@example.com");
+        let other = create_test_user(&state, "other@example.com");
+        let send_at = chrono::Utc::now().timestamp() as i32 + 3600;
+        let send_id = state.user_repository
+            .create_scheduled_send(owner, "email", "friend@example.com", "See you at six", send_at)
+            .unwrap();
+
+        let error = cancel_scheduled_send(State(state.clone()), as_user(other), Path(send_id)).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        let upcoming = state.user_repository.get_upcoming_scheduled_sends(owner).unwrap();
+        assert_eq!(upcoming.iter().map(|send| send.id).collect::<Vec<_>>(), vec![Some(send_id)]);
+
+        cancel_scheduled_send(State(state.clone()), as_user(owner), Path(send_id)).await.unwrap();
+        assert!(state.user_repository.get_upcoming_scheduled_sends(owner).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn queued_action_of_another_user_cannot_be_cancelled() {
+        let state = test_state();
+        let delay = std::time::Duration::from_secs(60);
+        let (action_id, mut cancel_rx) = crate::tool_call_utils::utils::register_pending_action(
+            &state, 1, "email", "friend@example.com", "email to friend", delay,
+        ).await;
+
+        let error = cancel_scheduled_action(State(state.clone()), as_user(2), Path(action_id)).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert!(cancel_rx.try_recv().is_err());
+        assert_eq!(crate::tool_call_utils::utils::list_pending_actions(&state, 1).await.len(), 1);
+    }
+
+    fn stored_messages(state: &Arc<AppState>, user_id: i32) -> i64 {
+        use crate::schema::message_history;
+        use diesel::prelude::*;
+        message_history::table
+            .filter(message_history::user_id.eq(user_id))
+            .count()
+            .get_result(&mut state.db_pool.get().unwrap())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn clearing_history_leaves_other_users_alone() {
+        let state = test_state();
+        let caller = create_test_user(&state, "forgetful@example.com");
+        let bystander = create_test_user(&state, "bystander@example.com");
+        for (user_id, role, created_at) in [(caller, "user", 10), (caller, "assistant", 11), (bystander, "user", 12)] {
+            state.user_repository.create_message_history(&crate::models::user_models::NewMessageHistory {
+                user_id,
+                role: role.to_string(),
+                encrypted_content: "not decrypted here".to_string(),
+                tool_name: None,
+                tool_call_id: None,
+                created_at,
+                conversation_id: String::new(),
+                tool_calls_json: None,
+            }).unwrap();
+        }
+
+        let response = clear_conversation_history(State(state.clone()), as_user(caller)).await.unwrap().0;
+        assert_eq!(response["removed"], 2);
+        assert_eq!(stored_messages(&state, caller), 0);
+        assert_eq!(stored_messages(&state, bystander), 1);
+    }
+
+    async fn pause(state: &Arc<AppState>, user_id: i32, body: serde_json::Value) -> Result<Json<serde_json::Value>, ApiError> {
+        update_pause(State(state.clone()), as_user(user_id), Json(serde_json::from_value(body).unwrap())).await
+    }
+
+    #[tokio::test]
+    async fn expired_pause_no_longer_holds_the_user_back() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "back-from-vacation@example.com");
+        let now = chrono::Utc::now().timestamp() as i32;
+
+        let error = pause(&state, user_id, json!({"paused_until": now - 60})).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+
+        pause(&state, user_id, json!({"paused_until": now + 3600})).await.unwrap();
+        assert!(crate::jobs::scheduler::is_user_paused(&state, user_id));
+        let settings = state.user_core.get_user_settings(user_id).unwrap();
+        assert!(!crate::proactive::utils::is_paused(&settings, None, now + 3600));
+    }
+
+    #[tokio::test]
+    async fn indefinite_pause_lasts_until_resumed() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "sabbatical@example.com");
+
+        let response = pause(&state, user_id, json!({"indefinite": true, "allow_critical": true})).await.unwrap().0;
+        assert_eq!(response["indefinite"], true);
+        let settings = state.user_core.get_user_settings(user_id).unwrap();
+        let years_from_now = chrono::Utc::now().timestamp() as i32 + 10 * 365 * 24 * 3600;
+        assert!(crate::proactive::utils::is_paused(&settings, None, years_from_now));
+        assert!(!crate::proactive::utils::is_paused(&settings, Some("critical_email"), years_from_now));
+
+        pause(&state, user_id, json!({"paused_until": null})).await.unwrap();
+        assert!(!crate::jobs::scheduler::is_user_paused(&state, user_id));
+    }
+
+    fn inbox_email(uid: u32, from_email: &str, urgency: u32) -> crate::handlers::imap_handlers::ImapEmailPreview {
+        crate::handlers::imap_handlers::ImapEmailPreview {
+            id: uid.to_string(),
+            subject: Some(format!("Email {}", uid)),
+            from: None,
+            from_email: Some(from_email.to_string()),
+            date: chrono::DateTime::from_timestamp(1_792_000_000 + uid as i64, 0),
+            date_formatted: None,
+            snippet: None,
+            body: Some(format!("Urgency: {}", urgency)),
+            is_read: false,
+            message_id: None,
+            copies: 1,
+            account: None,
+        }
+    }
+
+    /// Stands in for the LLM: an email is important when its urgency reaches the threshold
+    async fn rejudge_with_threshold(
+        state: &Arc<AppState>,
+        user_id: i32,
+        priority_senders: &[crate::models::user_models::PrioritySender],
+        threshold: u32,
+    ) -> RerunEmailJudgmentsResponse {
+        let emails = vec![
+            inbox_email(1, "boss@example.com", 1),
+            inbox_email(2, "news@example.com", 2),
+            inbox_email(3, "landlord@example.com", 6),
+            inbox_email(4, "hospital@example.com", 9),
+        ];
+        rejudge_emails(state, user_id, emails, priority_senders, 1_792_100_000, move |content| async move {
+            let urgency: u32 = content.split("Urgency: ").nth(1)?.trim_end_matches(|c: char| !c.is_ascii_digit()).parse().ok()?;
+            Some((urgency >= threshold, format!("Urgency {}", urgency)))
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn raising_the_threshold_lowers_the_important_count() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "threshold@example.com");
+        let priority_senders = vec![crate::models::user_models::PrioritySender {
+            id: None,
+            user_id,
+            sender: "boss@example.com".to_string(),
+            service_type: "imap".to_string(),
+            noti_type: None,
+            noti_mode: "all".to_string(),
+        }];
+
+        let lenient = rejudge_with_threshold(&state, user_id, &priority_senders, 2).await;
+        assert_eq!((lenient.evaluated, lenient.important), (4, 4));
+
+        let strict = rejudge_with_threshold(&state, user_id, &priority_senders, 8).await;
+        assert_eq!((strict.evaluated, strict.important), (4, 2));
+        // Only the flipped judgments are reported, the priority sender stays important
+        let flipped: Vec<(&str, Option<bool>, bool)> = strict.changed.iter()
+            .map(|change| (change.email_id.as_str(), change.previous_should_notify, change.should_notify))
+            .collect();
+        assert_eq!(flipped, vec![("2", Some(true), false), ("3", Some(true), false)]);
+
+        let stored = state.user_repository.get_user_email_judgments(user_id).unwrap();
+        assert_eq!(stored.iter().filter(|judgment| judgment.should_notify).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn connection_change_is_pushed_to_the_users_stream_only() {
+        use axum::response::IntoResponse;
+        use crate::utils::connection_events::ConnectionStatus;
+        use futures::StreamExt;
+        let state = test_state();
+        let user_id = create_test_user(&state, "sse@example.com");
+        let other = create_test_user(&state, "sse-other@example.com");
+        let mut body = connection_events(State(state.clone()), as_user(user_id))
+            .await
+            .into_response()
+            .into_body()
+            .into_data_stream();
+
+        state.connection_events.publish(other, "tesla", ConnectionStatus::Connected);
+        state.connection_events.publish(user_id, "google_calendar", ConnectionStatus::TokenExpired);
+
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(1), body.next())
+            .await
+            .expect("no event was pushed")
+            .unwrap()
+            .unwrap();
+        let frame = String::from_utf8(frame.to_vec()).unwrap();
+        assert!(frame.starts_with("event: connection\n"), "{}", frame);
+        assert!(frame.contains(r#""service":"google_calendar""#));
+        assert!(frame.contains(r#""status":"token_expired""#));
+        assert!(!frame.contains("tesla"));
+    }
+
+    #[tokio::test]
+    async fn preview_renders_with_the_users_template_and_sends_nothing() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "preview@example.com");
+        state.user_core.update_call_opening_templates(
+            user_id,
+            Some(r#"{"calendar": "Heads up, {subject} is coming up at {time}"}"#.to_string()),
+        ).unwrap();
+        let before = state.user_core.find_by_id(user_id).unwrap().unwrap();
+
+        let request: NotificationPreviewRequest = serde_json::from_value(json!({
+            "content_type": "Calendar_Notification",
+            "subject": "Dentist",
+            "minutes": 30,
+        })).unwrap();
+        let Json(preview) = notification_preview(State(state.clone()), as_user(user_id), Json(request)).await.unwrap();
+
+        assert_eq!(preview["content_type"], "calendar_notification");
+        assert_eq!(preview["sms"], crate::proactive::utils::calendar_notification_text("Dentist", 30));
+        assert_eq!(preview["sms_segments"], 1);
+        let first_message = preview["first_message"].as_str().unwrap();
+        assert!(first_message.starts_with("Heads up, Dentist is coming up at "), "{}", first_message);
+
+        let after = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        assert_eq!((after.credits, after.credits_left), (before.credits, before.credits_left));
+        assert!(state.user_repository.get_recent_usage_logs(user_id, 10).unwrap().is_empty());
+    }
+}
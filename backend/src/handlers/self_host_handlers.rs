@@ -138,6 +138,14 @@ pub async fn update_textbee_creds(
     match state.user_core.update_textbee_credentials(auth_user.user_id, &req.textbee_device_id, &req.textbee_api_key) {
         Ok(_) => {
             println!("Successfully updated TextBee credentials for user: {}", auth_user.user_id);
+            // Check the new device right away so the profile shows its status
+            let state = state.clone();
+            let user_id = auth_user.user_id;
+            tokio::spawn(async move {
+                if let Err(e) = crate::utils::textbee::refresh_device_status(&state, user_id).await {
+                    tracing::warn!("TextBee check after credential update for user {}: {}", user_id, e);
+                }
+            });
             Ok(StatusCode::OK)
         },
         Err(e) => {
@@ -150,6 +158,38 @@ pub async fn update_textbee_creds(
     }
 }
 
+pub async fn test_textbee_device(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if state.user_core.get_textbee_credentials(auth_user.user_id).is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "TextBee credentials not set"}))
+        ));
+    }
+    device_test_response(crate::utils::textbee::refresh_device_status(&state, auth_user.user_id).await)
+}
+
+/// Online and offline are both answers about the phone, only a gateway failure is an error
+fn device_test_response(
+    result: Result<(), crate::utils::textbee::TextbeeError>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    match result {
+        Ok(()) => Ok(Json(json!({
+            "status": crate::utils::textbee::STATUS_ONLINE
+        }))),
+        Err(crate::utils::textbee::TextbeeError::DeviceOffline(reason)) => Ok(Json(json!({
+            "status": crate::utils::textbee::STATUS_OFFLINE,
+            "reason": reason
+        }))),
+        Err(e) => Err((
+            StatusCode::BAD_GATEWAY,
+            Json(json!({"error": e.to_string()}))
+        )),
+    }
+}
+
 use roxmltree::Document;
 use reqwest;
 
@@ -763,3 +803,19 @@ pub async fn renew_tinfoil_key(
         "message": "Tinfoil API key renewed successfully"
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::textbee::TextbeeError;
+
+    #[test]
+    fn device_test_reports_offline_and_maps_gateway_errors_to_502() {
+        assert_eq!(device_test_response(Ok(())).unwrap().0, json!({"status": "online"}));
+        let offline = device_test_response(Err(TextbeeError::DeviceOffline("disabled".to_string()))).unwrap().0;
+        assert_eq!(offline, json!({"status": "offline", "reason": "disabled"}));
+        let (status, Json(body)) = device_test_response(Err(TextbeeError::Request("gateway returned 500".to_string()))).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body["error"], "TextBee request failed: gateway returned 500");
+    }
+}
@@ -439,6 +439,7 @@ async fn monitor_signal_connection(
                                 };
                                 state.user_repository.delete_bridge(user_id, "signal")?;
                                 state.user_repository.create_bridge(new_bridge)?;
+                                state.connection_events.publish(user_id, "signal", crate::utils::connection_events::ConnectionStatus::BridgeConnected);
                                 // Add client to app state and start sync
                                 let mut matrix_clients = state.matrix_clients.lock().await;
                                 let mut sync_tasks = state.matrix_sync_tasks.lock().await;
@@ -456,7 +457,10 @@ async fn monitor_signal_connection(
                                 });
                                 // Store the client
                                 let client_arc = Arc::new(client.clone());
-                                matrix_clients.insert(user_id, client_arc.clone());
+                                // A bridge just connected, its messages come in through this client's sync
+                                matrix_clients.set_pinned(user_id, true);
+                                let evicted = matrix_clients.insert(user_id, client_arc.clone());
+                                crate::utils::matrix_auth::shut_down_evicted(evicted, &mut sync_tasks);
                                 // Create sync task
                                 let sync_settings = MatrixSyncSettings::default()
                                     .timeout(Duration::from_secs(30))
@@ -560,14 +564,16 @@ pub async fn resync_signal(
         });
 
         // Send the clean-rooms command to the management room
-        let content = RoomMessageEventContent::text_plain("!signal clean-rooms");
-        room.send(content).await.map_err(|e| {
-            tracing::error!("Failed to send clean-rooms command: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                AxumJson(json!({"error": "Failed to send clean-rooms command"})),
-            )
-        })?;
+        crate::utils::bridge_resync::run_resync(
+            &state.bridge_resyncs,
+            auth_user.user_id,
+            "signal",
+            &room,
+            &["!signal clean-rooms"],
+        ).await.map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AxumJson(json!({"error": e, "resumable": true})),
+        ))?;
         tracing::info!("🧹 Sent clean-rooms command to Signal bridge management room");
 
         // Start continuous sync in the background
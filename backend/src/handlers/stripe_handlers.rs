@@ -184,7 +184,7 @@ pub async fn create_unified_subscription_checkout(
             stripe::CreateCheckoutSessionCustomFields {
                 key: "referral_source".to_string(),
                 label: stripe::CreateCheckoutSessionCustomFieldsLabel {
-                    custom: "Where did you hear about Lightfriend?".to_string(),
+                    custom: crate::utils::branding::render(crate::utils::branding::REFERRAL_QUESTION),
                     type_: stripe::CreateCheckoutSessionCustomFieldsLabelType::Custom,
                 },
                 type_: stripe::CreateCheckoutSessionCustomFieldsType::Text,
@@ -592,10 +592,14 @@ pub async fn stripe_webhook(
         &payload_str,
         &sig_header,
         &webhook_secret,
-    ).map_err(|e| (
-        StatusCode::BAD_REQUEST,
-        Json(json!({"error": format!("Invalid Stripe webhook signature: {}", e)})),
-    ))?;
+    ).map_err(|e| {
+        crate::utils::webhook_status::record_rejected("stripe");
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("Invalid Stripe webhook signature: {}", e)})),
+        )
+    })?;
+    crate::utils::webhook_status::record_validated("stripe");
   
     tracing::info!("Stripe event verified successfully: {}", event.type_);
     // Process the event based on its type
@@ -445,6 +445,7 @@ async fn monitor_telegram_connection(
                                 };
                                 state.user_repository.delete_bridge(user_id, "telegram")?;
                                 state.user_repository.create_bridge(new_bridge)?;
+                                state.connection_events.publish(user_id, "telegram", crate::utils::connection_events::ConnectionStatus::BridgeConnected);
 
                                 // Add client to app state and start sync
                                 let mut matrix_clients = state.matrix_clients.lock().await;
@@ -460,7 +461,10 @@ async fn monitor_telegram_connection(
                                 });
 
                                 let client_arc = Arc::new(client.clone());
-                                matrix_clients.insert(user_id, client_arc.clone());
+                                // A bridge just connected, its messages come in through this client's sync
+                                matrix_clients.set_pinned(user_id, true);
+                                let evicted = matrix_clients.insert(user_id, client_arc.clone());
+                                crate::utils::matrix_auth::shut_down_evicted(evicted, &mut sync_tasks);
 
                                 let sync_settings = MatrixSyncSettings::default()
                                     .timeout(Duration::from_secs(30))
@@ -592,29 +596,18 @@ pub async fn resync_telegram(
         sleep(Duration::from_secs(2)).await;
 
         tracing::debug!("📱 Sending Telegram sync commands");
-        
-        // First sync all contacts
-        if let Err(e) = room.send(RoomMessageEventContent::text_plain("sync contacts")).await {
-            tracing::error!("Failed to send contacts sync command: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                AxumJson(json!({"error": "Failed to send contacts sync command"})),
-            ));
-        }
-        tracing::debug!("✅ Sent contacts sync command");
-        
-        // Wait a bit for contacts to sync
-        sleep(Duration::from_secs(2)).await;
-        
-        // Then sync all chats
-        if let Err(e) = room.send(RoomMessageEventContent::text_plain("sync chats")).await {
-            tracing::error!("Failed to send chats sync command: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                AxumJson(json!({"error": "Failed to send chats sync command"})),
-            ));
-        }
-        tracing::debug!("✅ Sent chats sync command");
+
+        // Contacts first, then chats. A retry after a failure picks up where this one stopped.
+        crate::utils::bridge_resync::run_resync(
+            &state.bridge_resyncs,
+            auth_user.user_id,
+            "telegram",
+            &room,
+            &["sync contacts", "sync chats"],
+        ).await.map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AxumJson(json!({"error": e, "resumable": true})),
+        ))?;
 
         tracing::debug!("✅ Telegram resync process completed for user {}", auth_user.user_id);
         Ok(AxumJson(json!({
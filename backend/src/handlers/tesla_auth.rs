@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 use time::OffsetDateTime;
-use tracing::{info, error};
+use tracing::{debug, info, error};
 
 use crate::{
     AppState,
@@ -347,6 +347,7 @@ pub async fn tesla_callback(
         }).ok();
 
     info!("Tesla OAuth connection successfully established for user {}", user_id);
+    state.connection_events.publish(user_id, "tesla", crate::utils::connection_events::ConnectionStatus::Connected);
 
     // Redirect to frontend home page with success query param
     let frontend_url = std::env::var("FRONTEND_URL")
@@ -485,6 +486,7 @@ pub async fn get_valid_tesla_access_token(
     if !token_response.status().is_success() {
         let error_text = token_response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
         error!("Token refresh failed: {}", error_text);
+        state.connection_events.publish(user_id, "tesla", crate::utils::connection_events::ConnectionStatus::TokenExpired);
         return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Token refresh failed: {}", error_text),
@@ -756,9 +758,49 @@ pub async fn tesla_command(
     })))
 }
 
+#[derive(Deserialize)]
+pub struct BatteryStatusQuery {
+    /// Ask the car even when cached state is fresh, waking it if needed
+    #[serde(default)]
+    refresh: bool,
+}
+
+/// Status response from a snapshot, temperatures in the user's units. `source` is "cache" or "vehicle".
+fn battery_status_json(
+    state: &Arc<AppState>,
+    user_id: i32,
+    snapshot: &crate::utils::tesla_telemetry::VehicleSnapshot,
+    source: &str,
+) -> serde_json::Value {
+    // Tesla reports Celsius, show the temps in the user's units
+    let units = crate::utils::tool_exec::resolve_units(state, user_id, None);
+    let inside_temp = snapshot.inside_temp.map(|t| crate::utils::tool_exec::convert_temperature(t, &units));
+    let outside_temp = snapshot.outside_temp.map(|t| crate::utils::tool_exec::convert_temperature(t, &units));
+    let temp_unit = if units == "imperial" { "F" } else { "C" };
+
+    json!({
+        "battery_level": snapshot.battery_level.map(|level| level.round() as i32),
+        "battery_range": snapshot.battery_range,
+        "charging_state": snapshot.charging_state,
+        "inside_temp": inside_temp,
+        "outside_temp": outside_temp,
+        "temp_unit": temp_unit,
+        "is_climate_on": snapshot.is_climate_on,
+        "is_front_defroster_on": snapshot.is_front_defroster_on,
+        "is_rear_defroster_on": snapshot.is_rear_defroster_on,
+        "locked": snapshot.locked,
+        "asleep": snapshot.asleep,
+        "updated_at": snapshot.updated_at,
+        "source": source
+    })
+}
+
+/// Serves the latest pushed or polled state while it's fresh, or while the car sleeps so it isn't
+/// woken just to show the status. `?refresh=true` always asks the car.
 pub async fn tesla_battery_status(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
+    axum::extract::Query(query): axum::extract::Query<BatteryStatusQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     info!("Tesla battery status request from user {}", auth_user.user_id);
 
@@ -774,6 +816,21 @@ pub async fn tesla_battery_status(
         ));
     }
 
+    // Try to use selected vehicle, fall back to first vehicle if none selected
+    let selected_vin = state.user_repository
+        .get_selected_vehicle_vin(auth_user.user_id)
+        .ok()
+        .flatten();
+
+    let now = chrono::Utc::now().timestamp();
+    if !query.refresh {
+        if let Some(snapshot) = selected_vin.as_deref().and_then(|vin| state.tesla_telemetry.get(vin)) {
+            if snapshot.is_fresh(now) {
+                return Ok(Json(battery_status_json(&state, auth_user.user_id, &snapshot, "cache")));
+            }
+        }
+    }
+
     // Get valid access token (with auto-refresh)
     let access_token = match get_valid_tesla_access_token(&state, auth_user.user_id).await {
         Ok(token) => token,
@@ -809,12 +866,6 @@ pub async fn tesla_battery_status(
         ));
     }
 
-    // Try to use selected vehicle, fall back to first vehicle if none selected
-    let selected_vin = state.user_repository
-        .get_selected_vehicle_vin(auth_user.user_id)
-        .ok()
-        .flatten();
-
     let vehicle = if let Some(vin) = selected_vin.as_ref() {
         match vehicles.iter().find(|v| &v.vin == vin) {
             Some(v) => {
@@ -833,6 +884,15 @@ pub async fn tesla_battery_status(
 
     let vehicle_vin = &vehicle.vin;
 
+    // A sleeping car with known state stays asleep unless the user asked for fresh data
+    if vehicle.state != "online" && !query.refresh {
+        if let Some(mut snapshot) = state.tesla_telemetry.get(vehicle_vin) {
+            info!("Vehicle is asleep (state: {}), serving cached state", vehicle.state);
+            snapshot.asleep = true;
+            return Ok(Json(battery_status_json(&state, auth_user.user_id, &snapshot, "cache")));
+        }
+    }
+
     // Wake up vehicle if asleep
     if vehicle.state != "online" {
         info!("Vehicle is asleep (state: {}), waking up...", vehicle.state);
@@ -874,7 +934,7 @@ pub async fn tesla_battery_status(
     // Extract charge state
     let (battery_level, battery_range, charging_state) = if let Some(charge_state) = &vehicle_data.charge_state {
         (
-            Some(charge_state.battery_level),
+            Some(charge_state.battery_level as f64),
             Some(charge_state.battery_range),
             Some(charge_state.charging_state.clone()),
         )
@@ -899,17 +959,90 @@ pub async fn tesla_battery_status(
     let locked = vehicle_data.vehicle_state.as_ref()
         .and_then(|vs| vs.locked);
 
-    Ok(Json(json!({
-        "battery_level": battery_level,
-        "battery_range": battery_range,
-        "charging_state": charging_state,
-        "inside_temp": inside_temp,
-        "outside_temp": outside_temp,
-        "is_climate_on": is_climate_on,
-        "is_front_defroster_on": is_front_defroster_on,
-        "is_rear_defroster_on": is_rear_defroster_on,
-        "locked": locked
-    })))
+    let snapshot = crate::utils::tesla_telemetry::VehicleSnapshot {
+        battery_level,
+        battery_range,
+        charging_state,
+        inside_temp,
+        outside_temp,
+        is_climate_on,
+        is_front_defroster_on,
+        is_rear_defroster_on,
+        locked,
+        asleep: false,
+        updated_at: now,
+    };
+    state.tesla_telemetry.store(vehicle_vin, snapshot.clone());
+
+    Ok(Json(battery_status_json(&state, auth_user.user_id, &snapshot, "vehicle")))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum TelemetryPush {
+    Batch(Vec<crate::utils::tesla_telemetry::TelemetryRecord>),
+    Single(crate::utils::tesla_telemetry::TelemetryRecord),
+}
+
+/// Checks the X-Telemetry-Secret header against the configured secret, 404 while
+/// telemetry isn't configured so the endpoint looks absent
+fn check_telemetry_secret(
+    configured: Option<String>,
+    headers: &axum::http::HeaderMap,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    // An empty secret would let anyone sending an empty header in
+    let Some(secret) = configured.filter(|secret| !secret.trim().is_empty()) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Telemetry is not configured"})),
+        ));
+    };
+    let provided = headers.get("x-telemetry-secret").and_then(|value| value.to_str().ok());
+    if !provided.is_some_and(|provided| crate::utils::encryption::secrets_match(provided, &secret)) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Invalid telemetry secret"})),
+        ));
+    }
+    Ok(())
+}
+
+/// Applies the records of registered vehicles, returns how many were applied
+fn apply_telemetry(
+    state: &AppState,
+    records: &[crate::utils::tesla_telemetry::TelemetryRecord],
+    now: i64,
+) -> usize {
+    let mut applied = 0;
+    for record in records {
+        // Only cars our users have connected get state, so the cache stays bounded by them
+        let registered = state.tesla_telemetry.contains(&record.vin)
+            || state.user_repository.is_selected_vehicle_vin(&record.vin).unwrap_or(false);
+        if !registered {
+            debug!("Ignoring telemetry for unregistered vehicle");
+            continue;
+        }
+        state.tesla_telemetry.apply(record, now);
+        applied += 1;
+    }
+    applied
+}
+
+/// Records forwarded from our fleet telemetry server, authenticated with the shared
+/// TESLA_TELEMETRY_SECRET in the X-Telemetry-Secret header
+pub async fn tesla_telemetry_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(push): Json<TelemetryPush>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    check_telemetry_secret(std::env::var("TESLA_TELEMETRY_SECRET").ok(), &headers)?;
+
+    let records = match push {
+        TelemetryPush::Batch(records) => records,
+        TelemetryPush::Single(record) => vec![record],
+    };
+    let applied = apply_telemetry(&state, &records, chrono::Utc::now().timestamp());
+    Ok(Json(json!({"applied": applied, "ignored": records.len() - applied})))
 }
 
 pub async fn tesla_list_vehicles(
@@ -1155,3 +1288,53 @@ pub async fn update_notify_on_climate_ready(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tesla_telemetry::TelemetryRecord;
+
+    fn with_secret(value: &str) -> axum::http::HeaderMap {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("x-telemetry-secret", value.parse().unwrap());
+        headers
+    }
+
+    fn battery_record(vin: &str) -> TelemetryRecord {
+        serde_json::from_value(json!({
+            "vin": vin,
+            "data": [{"key": "BatteryLevel", "value": {"doubleValue": 80.5}}],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn unconfigured_secret_hides_the_endpoint() {
+        for configured in [None, Some(String::new()), Some("  ".to_string())] {
+            let (status, _) = check_telemetry_secret(configured, &with_secret("")).unwrap_err();
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[test]
+    fn wrong_or_missing_header_is_unauthorized() {
+        let configured = || Some("fleet-secret".to_string());
+        for headers in [with_secret("other-secret"), with_secret(""), axum::http::HeaderMap::new()] {
+            let (status, _) = check_telemetry_secret(configured(), &headers).unwrap_err();
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        assert!(check_telemetry_secret(configured(), &with_secret("fleet-secret")).is_ok());
+    }
+
+    #[test]
+    fn records_for_unregistered_vins_are_ignored() {
+        let state = crate::test_support::test_state();
+        state.tesla_telemetry.store("KNOWNVIN", Default::default());
+
+        let records = [battery_record("KNOWNVIN"), battery_record("STRANGERVIN")];
+        assert_eq!(apply_telemetry(&state, &records, 1_000), 1);
+
+        assert_eq!(state.tesla_telemetry.get("KNOWNVIN").unwrap().battery_level, Some(80.5));
+        assert!(!state.tesla_telemetry.contains("STRANGERVIN"));
+    }
+}
@@ -86,7 +86,7 @@ pub async fn setup_start(
         1,      // skew (allow 1 step before/after)
         30,     // step in seconds
         secret.to_bytes().unwrap(),
-        Some("Lightfriend".to_string()),
+        Some(crate::utils::branding::assistant_name()),
         user.email.clone(),
     ).map_err(|e| {
         tracing::error!("TOTP creation error: {}", e);
@@ -148,7 +148,7 @@ pub async fn setup_verify(
         1,
         30,
         secret.to_bytes().unwrap(),
-        Some("Lightfriend".to_string()),
+        Some(crate::utils::branding::assistant_name()),
         user.email,
     ).map_err(|e| {
         tracing::error!("TOTP creation error: {}", e);
@@ -244,7 +244,7 @@ pub async fn disable(
         1,
         30,
         secret.to_bytes().unwrap(),
-        Some("Lightfriend".to_string()),
+        Some(crate::utils::branding::assistant_name()),
         user.email,
     ).map_err(|e| {
         tracing::error!("TOTP creation error: {}", e);
@@ -321,7 +321,7 @@ pub async fn regenerate_backup_codes(
         1,
         30,
         secret.to_bytes().unwrap(),
-        Some("Lightfriend".to_string()),
+        Some(crate::utils::branding::assistant_name()),
         user.email,
     ).map_err(|e| {
         tracing::error!("TOTP creation error: {}", e);
@@ -412,7 +412,7 @@ pub async fn verify_login(
             1,
             30,
             secret.to_bytes().unwrap(),
-            Some("Lightfriend".to_string()),
+            Some(crate::utils::branding::assistant_name()),
             user.email,
         ).map_err(|e| {
             tracing::error!("TOTP creation error: {}", e);
@@ -430,5 +430,5 @@ pub async fn verify_login(
     state.pending_totp_logins.remove(&req.totp_token);
 
     // Generate tokens and return response
-    generate_tokens_and_response(user_id)
+    generate_tokens_and_response(user_id).map_err(Into::into)
 }
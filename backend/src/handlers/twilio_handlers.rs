@@ -226,6 +226,50 @@ pub struct CountryInfoResponse {
     pub regulations: TwilioRegulations,
 }
 
+/// Twilio's regulations for buying a `number_type` number in the country, empty if they can't be fetched
+async fn fetch_regulations(
+    client: &Client,
+    account_sid: &str,
+    auth_token: &str,
+    country_code: &str,
+    number_type: &str,
+) -> Vec<Regulation> {
+    tracing::debug!("Fetching regulations for {}", number_type);
+    let resp = client
+        .get("https://numbers.twilio.com/v2/RegulatoryCompliance/Regulations")
+        .basic_auth(account_sid, Some(auth_token))
+        .query(&[
+            ("IsoCountry", country_code.to_uppercase().as_str()),
+            ("NumberType", number_type),
+            ("IncludeConstraints", "true"),
+        ])
+        .send()
+        .await;
+    match resp {
+        Ok(resp) if resp.status().is_success() => {
+            match resp.json::<RegulationsResponse>().await {
+                Ok(json) => {
+                    tracing::debug!("Retrieved {} {} regulations", json.results.len(), number_type);
+                    json.results
+                }
+                Err(e) => {
+                    tracing::error!("Failed to parse {} regulations: {}", number_type, e);
+                    vec![]
+                }
+            }
+        }
+        Ok(resp) => {
+            let err_text = resp.text().await.unwrap_or_default();
+            tracing::error!("Twilio API error for {} regulations: {}", number_type, err_text);
+            vec![]
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch {} regulations: {}", number_type, e);
+            vec![]
+        }
+    }
+}
+
 pub async fn get_country_info(
     State(_state): State<Arc<AppState>>,
     Json(req): Json<CountryRequest>,
@@ -477,66 +521,8 @@ pub async fn get_country_info(
     };
     println!("Combined prices data structure created");
 
-    // Fetch regulations for local
-    let mut local_regs = vec![];
-    println!("Fetching regulations for local");
-    let local_regs_resp = client
-        .get("https://numbers.twilio.com/v2/RegulatoryCompliance/Regulations")
-        .basic_auth(&account_sid, Some(&auth_token))
-        .query(&[
-            ("IsoCountry", req.country_code.to_uppercase().as_str()),
-            ("NumberType", "local"),
-            ("IncludeConstraints", "true"),
-        ])
-        .send()
-        .await;
-    match local_regs_resp {
-        Ok(resp) if resp.status().is_success() => {
-            println!("resp: {:#?}", resp);
-            match resp.json::<RegulationsResponse>().await {
-                Ok(json) => {
-                    local_regs = json.results;
-                    println!("Retrieved {} local regulations", local_regs.len());
-                }
-                Err(e) => println!("Failed to parse local regulations: {}", e),
-            }
-        }
-        Ok(resp) => {
-            let err_text = resp.text().await.unwrap_or_default();
-            println!("Twilio API error for local regulations: {}", err_text);
-        }
-        Err(e) => println!("Failed to fetch local regulations: {}", e),
-    }
-
-    // Fetch regulations for mobile
-    let mut mobile_regs = vec![];
-    println!("Fetching regulations for mobile");
-    let mobile_regs_resp = client
-        .get("https://numbers.twilio.com/v2/RegulatoryCompliance/Regulations")
-        .basic_auth(&account_sid, Some(&auth_token))
-        .query(&[
-            ("IsoCountry", req.country_code.to_uppercase().as_str()),
-            ("NumberType", "mobile"),
-            ("IncludeConstraints", "true"),
-        ])
-        .send()
-        .await;
-    match mobile_regs_resp {
-        Ok(resp) if resp.status().is_success() => {
-            match resp.json::<RegulationsResponse>().await {
-                Ok(json) => {
-                    mobile_regs = json.results;
-                    println!("Retrieved {} mobile regulations", mobile_regs.len());
-                }
-                Err(e) => println!("Failed to parse mobile regulations: {}", e),
-            }
-        }
-        Ok(resp) => {
-            let err_text = resp.text().await.unwrap_or_default();
-            println!("Twilio API error for mobile regulations: {}", err_text);
-        }
-        Err(e) => println!("Failed to fetch mobile regulations: {}", e),
-    }
+    let local_regs = fetch_regulations(&client, &account_sid, &auth_token, &req.country_code, "local").await;
+    let mobile_regs = fetch_regulations(&client, &account_sid, &auth_token, &req.country_code, "mobile").await;
 
     let regulations = TwilioRegulations {
         local: local_regs,
@@ -550,3 +536,198 @@ pub async fn get_country_info(
         regulations,
     }))
 }
+
+#[derive(Deserialize)]
+pub struct RegulatoryChecklistRequest {
+    pub country_code: Option<String>, // defaults to the country of the user's phone number
+    pub number_type: String, // "local" or "mobile"
+    pub end_user_type: String, // "individual" or "business"
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChecklistItem {
+    pub kind: String, // "information" or "document"
+    pub title: String,
+    /// Information items: the fields to fill in. Document items: the documents of which any one will do
+    pub options: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RegulatoryChecklistResponse {
+    pub country_code: String,
+    pub number_type: String,
+    pub end_user_type: String,
+    pub information_heading: String,
+    pub documents_heading: String,
+    pub items: Vec<ChecklistItem>,
+}
+
+/// Checklist headings as (language, information heading, documents heading)
+const CHECKLIST_HEADINGS: [(&str, &str, &str); 6] = [
+    ("en", "Information Twilio needs about you", "Documents (any one of the listed ones is enough)"),
+    ("fi", "Tiedot, joita Twilio tarvitsee sinusta", "Asiakirjat (yksi luetelluista riittää)"),
+    ("de", "Angaben, die Twilio über Sie benötigt", "Dokumente (eines der aufgeführten genügt)"),
+    ("sv", "Uppgifter som Twilio behöver om dig", "Dokument (ett av de listade räcker)"),
+    ("fr", "Informations dont Twilio a besoin à votre sujet", "Documents (un seul des documents listés suffit)"),
+    ("es", "Información que Twilio necesita sobre ti", "Documentos (basta con uno de los indicados)"),
+];
+
+fn checklist_headings(language: &str) -> (&'static str, &'static str) {
+    let language = language.to_lowercase();
+    CHECKLIST_HEADINGS
+        .iter()
+        .find(|(lang, _, _)| *lang == language)
+        .or_else(|| CHECKLIST_HEADINGS.first())
+        .map(|(_, info, docs)| (*info, *docs))
+        .unwrap_or_default()
+}
+
+/// Flattens the regulations for one end user type into what the user has to provide.
+/// Duplicate requirements across regulations are listed once.
+pub fn build_regulatory_checklist(regulations: &[Regulation], end_user_type: &str) -> Vec<ChecklistItem> {
+    let mut items: Vec<ChecklistItem> = Vec::new();
+    for regulation in regulations.iter().filter(|r| r.end_user_type.eq_ignore_ascii_case(end_user_type)) {
+        for end_user in &regulation.requirements.end_user {
+            let options = if end_user.detailed_fields.is_empty() {
+                end_user.fields.clone()
+            } else {
+                end_user.detailed_fields.iter().map(|field| field.friendly_name.clone()).collect()
+            };
+            items.push(ChecklistItem {
+                kind: "information".to_string(),
+                title: end_user.name.clone(),
+                options,
+            });
+        }
+        for group in &regulation.requirements.supporting_document {
+            for document in group {
+                items.push(ChecklistItem {
+                    kind: "document".to_string(),
+                    title: if document.description.is_empty() { document.name.clone() } else { document.description.clone() },
+                    options: document.accepted_documents.iter().map(|accepted| accepted.name.clone()).collect(),
+                });
+            }
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    items.retain(|item| seen.insert((item.kind.clone(), item.title.clone())));
+    items
+}
+
+pub async fn get_regulatory_checklist(
+    State(state): State<Arc<AppState>>,
+    auth_user: crate::handlers::auth_middleware::AuthUser,
+    Json(req): Json<RegulatoryChecklistRequest>,
+) -> Result<Json<RegulatoryChecklistResponse>, (StatusCode, Json<Value>)> {
+    if req.number_type != "local" && req.number_type != "mobile" {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "number_type must be \"local\" or \"mobile\""}))));
+    }
+    if req.end_user_type != "individual" && req.end_user_type != "business" {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "end_user_type must be \"individual\" or \"business\""}))));
+    }
+    let user = match state.user_core.find_by_id(auth_user.user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, Json(json!({"error": "User not found"})))),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("Database error: {}", e)})))),
+    };
+    let country_code = match req.country_code.or(user.phone_number_country) {
+        Some(code) => code.to_uppercase(),
+        None => return Err((StatusCode::BAD_REQUEST, Json(json!({"error": "country_code is required, the country of your phone number is not known yet"})))),
+    };
+    let language = state.user_core.get_user_settings(user.id)
+        .map(|settings| settings.agent_language)
+        .unwrap_or_else(|_| "en".to_string());
+
+    let account_sid = env::var("TWILIO_ACCOUNT_SID")
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Missing TWILIO_ACCOUNT_SID"}))))?;
+    let auth_token = env::var("TWILIO_AUTH_TOKEN")
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Missing TWILIO_AUTH_TOKEN"}))))?;
+    let regulations = fetch_regulations(&Client::new(), &account_sid, &auth_token, &country_code, &req.number_type).await;
+
+    let (information_heading, documents_heading) = checklist_headings(&language);
+    Ok(Json(RegulatoryChecklistResponse {
+        items: build_regulatory_checklist(&regulations, &req.end_user_type),
+        country_code,
+        number_type: req.number_type,
+        end_user_type: req.end_user_type,
+        information_heading: information_heading.to_string(),
+        documents_heading: documents_heading.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from a real Twilio /v2/RegulatoryCompliance/Regulations response for FI mobile numbers
+    fn sample_regulations() -> Vec<Regulation> {
+        let field = |machine: &str, friendly: &str| json!({"machine_name": machine, "friendly_name": friendly, "description": ""});
+        let document = |name: &str, description: &str, accepted: &[&str]| json!({
+            "name": name, "type": "document", "requirement_name": name.to_lowercase(), "description": description,
+            "accepted_documents": accepted.iter().map(|doc| json!({
+                "name": doc, "type": "document", "url": "", "fields": [], "detailed_fields": []
+            })).collect::<Vec<_>>(),
+        });
+        let payload = json!({
+            "results": [
+                {
+                    "sid": "RN1", "friendly_name": "Finland: Mobile - Individual", "iso_country": "FI",
+                    "number_type": "mobile", "end_user_type": "individual", "url": "",
+                    "requirements": {
+                        "end_user": [{
+                            "name": "Individual", "type": "individual", "requirement_name": "individual_info", "url": "",
+                            "fields": ["first_name", "last_name"],
+                            "detailed_fields": [field("first_name", "First Name"), field("last_name", "Last Name")],
+                        }],
+                        "supporting_document": [
+                            [document("Proof of Identity", "Government-issued ID", &["Passport", "National ID card"])],
+                            [document("Proof of Address", "", &["Utility bill"])],
+                        ],
+                    },
+                },
+                {
+                    "sid": "RN2", "friendly_name": "Finland: Mobile - Business", "iso_country": "FI",
+                    "number_type": "mobile", "end_user_type": "business", "url": "",
+                    "requirements": {
+                        "end_user": [{
+                            "name": "Business", "type": "business", "requirement_name": "business_info", "url": "",
+                            "fields": ["business_name", "business_registration_number"],
+                            "detailed_fields": [],
+                        }],
+                        "supporting_document": [
+                            [document("Business Registration", "", &["Trade register extract"])],
+                            [document("Proof of Address", "", &["Utility bill"])],
+                        ],
+                    },
+                },
+            ],
+            "meta": {
+                "page": 0, "page_size": 50, "first_page_url": "", "previous_page_url": null,
+                "url": "", "next_page_url": null, "key": "results",
+            },
+        });
+        serde_json::from_value::<RegulationsResponse>(payload).unwrap().results
+    }
+
+    fn titles(items: &[ChecklistItem], kind: &str) -> Vec<String> {
+        items.iter().filter(|item| item.kind == kind).map(|item| item.title.clone()).collect()
+    }
+
+    #[test]
+    fn individual_checklist_only_has_individual_requirements() {
+        let items = build_regulatory_checklist(&sample_regulations(), "individual");
+        assert_eq!(titles(&items, "information"), vec!["Individual"]);
+        assert_eq!(items[0].options, vec!["First Name", "Last Name"]);
+        assert_eq!(titles(&items, "document"), vec!["Government-issued ID", "Proof of Address"]);
+        assert_eq!(items[1].options, vec!["Passport", "National ID card"]);
+    }
+
+    #[test]
+    fn business_checklist_falls_back_to_raw_field_names() {
+        let items = build_regulatory_checklist(&sample_regulations(), "Business");
+        assert_eq!(titles(&items, "information"), vec!["Business"]);
+        assert_eq!(items[0].options, vec!["business_name", "business_registration_number"]);
+        assert_eq!(titles(&items, "document"), vec!["Business Registration", "Proof of Address"]);
+        assert!(build_regulatory_checklist(&sample_regulations(), "government").is_empty());
+    }
+}
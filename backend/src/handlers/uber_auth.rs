@@ -197,6 +197,7 @@ pub async fn uber_callback(
         ));
     }
     tracing::info!("Successfully stored Uber connection for user {}", user_id);
+    state.connection_events.publish(user_id, "uber", crate::utils::connection_events::ConnectionStatus::Connected);
     let frontend_url = std::env::var("FRONTEND_URL")
         .expect("FRONTEND_URL must be set");
     tracing::info!("Redirecting to frontend with success: {}", frontend_url);
@@ -563,6 +563,7 @@ async fn monitor_whatsapp_connection(
 
                                 state.user_repository.delete_bridge(user_id, "whatsapp")?;
                                 state.user_repository.create_bridge(new_bridge)?;
+                                state.connection_events.publish(user_id, "whatsapp", crate::utils::connection_events::ConnectionStatus::BridgeConnected);
 
                                 // Add client to app state and start sync
                                 let mut matrix_clients = state.matrix_clients.lock().await;
@@ -583,7 +584,10 @@ async fn monitor_whatsapp_connection(
 
                                 // Store the client
                                 let client_arc = Arc::new(client.clone());
-                                matrix_clients.insert(user_id, client_arc.clone());
+                                // A bridge just connected, its messages come in through this client's sync
+                                matrix_clients.set_pinned(user_id, true);
+                                let evicted = matrix_clients.insert(user_id, client_arc.clone());
+                                crate::utils::matrix_auth::shut_down_evicted(evicted, &mut sync_tasks);
 
                                 // Create sync task
                                 let sync_settings = MatrixSyncSettings::default()
@@ -734,29 +738,18 @@ pub async fn resync_whatsapp(
         sleep(Duration::from_secs(2)).await;
 
         tracing::debug!("📱 Sending WhatsApp sync commands");
-        
-        // First sync all contacts
-        if let Err(e) = room.send(RoomMessageEventContent::text_plain("!wa sync contacts --create-portals")).await {
-            tracing::error!("Failed to send contacts sync command: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                AxumJson(json!({"error": "Failed to send contacts sync command"})),
-            ));
-        }
-        tracing::debug!("✅ Sent contacts sync command");
-        
-        // Wait a bit for contacts to sync
-        sleep(Duration::from_secs(2)).await;
-        
-        // Then sync all groups
-        if let Err(e) = room.send(RoomMessageEventContent::text_plain("!wa sync groups --create-portals")).await {
-            tracing::error!("Failed to send groups sync command: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                AxumJson(json!({"error": "Failed to send groups sync command"})),
-            ));
-        }
-        tracing::debug!("✅ Sent groups sync command");
+
+        // Contacts first, then groups. A retry after a failure picks up where this one stopped.
+        crate::utils::bridge_resync::run_resync(
+            &state.bridge_resyncs,
+            auth_user.user_id,
+            "whatsapp",
+            &room,
+            &["!wa sync contacts --create-portals", "!wa sync groups --create-portals"],
+        ).await.map_err(|e| (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AxumJson(json!({"error": e, "resumable": true})),
+        ))?;
 
         /*
         // Start accepting invitations for new rooms
@@ -1,8 +1,43 @@
 use tokio_cron_scheduler::{JobScheduler, Job};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::{debug, error};
 use crate::AppState;
 
+/// Keeps a job from starting while its previous pass is still going (e.g. slow IMAP servers),
+/// so overlapping passes can't send the same notification twice
+#[derive(Clone)]
+pub struct PassGuard {
+    name: &'static str,
+    running: Arc<AtomicBool>,
+}
+
+/// Held for the length of a pass, the next tick is allowed again once it's dropped
+pub struct PassToken {
+    running: Arc<AtomicBool>,
+}
+
+impl Drop for PassToken {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+    }
+}
+
+impl PassGuard {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, running: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// None, and a warning, when the previous pass hasn't finished yet
+    pub fn try_start(&self) -> Option<PassToken> {
+        if self.running.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            tracing::warn!("Skipping {} tick, the previous pass is still running", self.name);
+            return None;
+        }
+        Some(PassToken { running: Arc::clone(&self.running) })
+    }
+}
+
 
 
 use crate::handlers::imap_handlers;
@@ -13,68 +48,407 @@ async fn initialize_matrix_clients(state: Arc<AppState>) {
     // Get all users with active WhatsApp connection
     match state.user_repository.get_users_with_matrix_bridge_connections() {
         Ok(users) => {
-            let mut matrix_clients = state.matrix_clients.lock().await;
-            let mut sync_tasks = state.matrix_sync_tasks.lock().await;
-            
-            // Remove any existing clients and sync tasks
-            for (_, task) in sync_tasks.drain() {
-                task.abort();
+            {
+                let mut matrix_clients = state.matrix_clients.lock().await;
+                let mut sync_tasks = state.matrix_sync_tasks.lock().await;
+
+                // Remove any existing clients and sync tasks
+                for (_, task) in sync_tasks.drain() {
+                    task.abort();
+                }
+                matrix_clients.clear();
             }
-            matrix_clients.clear();
 
-            // Setup clients and sync tasks for active users
+            // Setup clients and sync tasks for active users, past the cache cap the least
+            // recently set up are evicted again and come back when they're used
             for user_id in users {
                 tracing::debug!("Setting up new Matrix client for user {}", user_id);
                 
                 // Create and initialize client
                 match crate::utils::matrix_auth::get_client(user_id, &state).await {
                     Ok(client) => {
-                        // Add event handlers before storing/cloning the client
-                        use matrix_sdk::ruma::events::room::message::OriginalSyncRoomMessageEvent;
-                        use matrix_sdk::room::Room;
-                        
-                        let state_for_handler = Arc::clone(&state);
-                        client.add_event_handler(move |ev: OriginalSyncRoomMessageEvent, room: Room, client| {
-                            let state = Arc::clone(&state_for_handler);
-                            async move {
-                                tracing::debug!("📨 Received message in room {}: {:?}", room.room_id(), ev);
-                                crate::utils::bridge::handle_bridge_message(ev, room, client, state).await;
+                        crate::utils::matrix_auth::cache_and_sync(&state, user_id, client).await;
+                    },
+                    Err(e) => {
+                        error!("Failed to create Matrix client for user {}: {}", user_id, e);
+                    }
+                }
+            }
+        },
+        Err(e) => error!("Failed to get active WhatsApp users: {}", e),
+    }
+}
+
+/// Lowest polling interval a user can pick, so one account can't hammer the IMAP server.
+pub fn min_email_poll_interval_minutes() -> u32 {
+    std::env::var("MIN_EMAIL_POLL_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(2)
+}
+
+/// Default email polling interval for a subscription tier, overridable with
+/// EMAIL_POLL_INTERVAL_TIER_2 etc. None for tiers that don't get proactive email checks, which
+/// includes tier 3: self-hosted users run their own instance, we don't poll for them.
+fn tier_email_poll_interval_minutes(tier: &str) -> Option<u32> {
+    let default = match tier {
+        "tier 2" => 10,
+        _ => return None,
+    };
+    let env_key = format!("EMAIL_POLL_INTERVAL_{}", tier.to_uppercase().replace(' ', "_"));
+    Some(std::env::var(env_key)
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default))
+}
+
+/// How often to poll a user's email, None when their tier isn't polled. The user's own setting
+/// can move it either way, but never below the global minimum.
+fn user_email_poll_interval_minutes(tier: Option<&str>, user_override: Option<i32>) -> Option<u32> {
+    let tier_default = tier_email_poll_interval_minutes(tier?)?;
+    let interval = match user_override {
+        Some(minutes) if minutes > 0 => minutes as u32,
+        _ => tier_default,
+    };
+    Some(interval.max(min_email_poll_interval_minutes()))
+}
+
+/// Users are staggered by id so everyone with the same interval isn't polled on the same minute.
+fn is_due_for_email_poll(user_id: i32, interval_minutes: u32, minutes_since_epoch: i64) -> bool {
+    let interval = interval_minutes.max(1) as i64;
+    (minutes_since_epoch + user_id as i64) % interval == 0
+}
+
+/// Vacation mode, see `proactive::utils::is_paused`
+pub fn is_user_paused(state: &Arc<AppState>, user_id: i32) -> bool {
+    let now = chrono::Utc::now().timestamp() as i32;
+    state.user_core.get_user_settings(user_id)
+        .map_or(false, |settings| crate::proactive::utils::is_paused(&settings, None, now))
+}
+
+/// Fetches unprocessed emails and runs them through priority senders, waiting checks and the
+/// critical check. Called by the polling job and whenever an IDLE listener sees new mail.
+pub async fn check_new_emails(state: &Arc<AppState>, user_id: i32) {
+    match imap_handlers::fetch_emails_imap(state, user_id, true, Some(10), true, true, None).await {
+        Ok(emails) => {
+            match state.user_repository.get_processed_emails(user_id) {
+                Ok(mut processed_emails) => {
+                    // Define constants
+                    let fetch_window = 10;  // Number of emails your scheduler fetches
+                    let cleanup_threshold = 100;  // Only cleanup when we have significantly more than fetch window
+
+                    if processed_emails.len() > cleanup_threshold {
+                        // Sort by processed_at timestamp (newest first)
+                        processed_emails.sort_by(|a, b| b.processed_at.cmp(&a.processed_at));
+
+                        // Keep at least fetch_window emails plus some buffer
+                        let keep_count = fetch_window * 2;  // Keep 20 emails (double the fetch window)
+
+                        // Get emails to delete (older than our keep_count)
+                        let emails_to_delete: Vec<_> = processed_emails
+                            .iter()
+                            .skip(keep_count)
+                            .collect();
+
+                        // Delete old processed emails
+                        for email in emails_to_delete {
+                            if let Err(e) = state.user_repository.delete_processed_email(user_id, &email.email_uid) {
+                                error!("Failed to delete old processed email {}: {}", email.email_uid, e);
+                            } else {
+                                debug!("Deleted old processed email {} for user {}", email.email_uid, user_id);
                             }
-                        });
+                        }
 
-                        // Store the client
-                        let client = Arc::new(client);
-                        matrix_clients.insert(user_id, client.clone());
-
-                        // Create sync task
-                        let sync_settings = matrix_sdk::config::SyncSettings::default()
-                            .timeout(std::time::Duration::from_secs(30))
-                            .full_state(true);
-
-                        let handle = tokio::spawn(async move {
-                            loop {
-                                match client.sync(sync_settings.clone()).await {
-                                    Ok(_) => {
-                                        tracing::debug!("Sync completed normally for user {}", user_id);
-                                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                                    },
-                                    Err(e) => {
-                                        error!("Matrix sync error for user {}: {}", user_id, e);
-                                        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+                        // Update the original collection
+                        processed_emails.truncate(keep_count);
+
+                        // Dedup records only matter within the dedup window
+                        let dedup_cutoff = chrono::Utc::now().timestamp() as i32 - crate::proactive::utils::notification_dedup_window_secs().max(24 * 3600);
+                        if let Err(e) = state.user_repository.delete_old_notified_items(dedup_cutoff) {
+                            error!("Failed to delete old notified items: {}", e);
+                        }
+                        // Also clean up old email judgments
+                        if let Err(e) = state.user_repository.delete_old_email_judgments(user_id) {
+                            error!("Failed to delete old email judgments for user {}: {}", user_id, e);
+                        } else {
+                            debug!("Successfully cleaned up old email judgments for user {}", user_id);
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to fetch processed emails for garbage collection: {}", e),
+            }
+
+            if !emails.is_empty() {
+                // Sort emails by date in descending order (most recent first)
+                let mut sorted_emails = emails;
+                sorted_emails.sort_by(|a, b| {
+                    let a_date = a.date.unwrap_or_else(|| chrono::Utc::now());
+                    let b_date = b.date.unwrap_or_else(|| chrono::Utc::now());
+                    b_date.cmp(&a_date)
+                });
+
+                let priority_senders = match state.user_repository.get_priority_senders(user_id, "imap") {
+                    Ok(senders) => senders,
+                    Err(e) => {
+                        tracing::error!("Failed to get priority senders for user {}: {}", user_id, e);
+                        Vec::new()
+                    }
+                };
+                let keywords = match state.user_repository.get_keywords(user_id, "imap") {
+                    Ok(keywords) => keywords,
+                    Err(e) => {
+                        tracing::error!("Failed to get keywords for user {}: {}", user_id, e);
+                        Vec::new()
+                    }
+                };
+                let include_snippets = state.user_core.get_user_settings(user_id)
+                    .ok()
+                    .and_then(|settings| settings.email_notification_snippets)
+                    .unwrap_or(true);
+                // Mark emails as processed and format them for importance checking
+                let mut emails_content = String::from("New emails:\n");
+                let mut critical_candidate_ids: Vec<String> = Vec::new();
+                for email in &sorted_emails {
+                    // Keyword actions come first so an ignore also beats priority senders
+                    let keyword_text = format!(
+                        "{} {} {} {}",
+                        email.from.as_deref().unwrap_or(""),
+                        email.from_email.as_deref().unwrap_or(""),
+                        email.subject.as_deref().unwrap_or(""),
+                        email.body.as_deref().unwrap_or("")
+                    );
+                    match crate::proactive::utils::keyword_action(&keywords, &keyword_text) {
+                        Some((crate::proactive::utils::KeywordAction::Ignore, keyword))
+                        | Some((crate::proactive::utils::KeywordAction::DigestOnly, keyword)) => {
+                            tracing::info!("Email for user {} matched keyword set to {}, not notifying", user_id, keyword.action);
+                            continue;
+                        }
+                        Some((action, keyword)) => {
+                            let suffix = if action == crate::proactive::utils::KeywordAction::NotifyCall { "_call" } else { "_sms" };
+                            let notification_type = format!("email_keyword{}", suffix);
+                            let message = crate::proactive::utils::email_notification_text(
+                                email.from.as_deref(),
+                                email.subject.as_deref(),
+                                email.body.as_deref(),
+                                include_snippets,
+                            );
+                            let first_message = format!("Hello, you have an email about {} from {}",
+                                keyword.keyword,
+                                email.from.as_deref().unwrap_or("Unknown")
+                            );
+                            if !crate::proactive::utils::should_notify_item(state, user_id, &format!("email:{}", email.id), &message) {
+                                continue;
+                            }
+                            crate::tool_call_utils::email::remember_surfaced_email(state, user_id, &email.id);
+                            let opening_context = crate::utils::call_templates::CallOpeningContext {
+                                sender: Some(email.from.clone().unwrap_or_else(|| "Unknown".to_string())),
+                                subject: Some(email.subject.clone().unwrap_or_else(|| "No subject".to_string())),
+                                ..Default::default()
+                            };
+                            let state_clone = state.clone();
+                            tokio::spawn(async move {
+                                crate::proactive::utils::send_notification_with_context(
+                                    &state_clone,
+                                    user_id,
+                                    &message,
+                                    notification_type,
+                                    Some(first_message),
+                                    opening_context,
+                                ).await;
+                            });
+                            continue;
+                        }
+                        None => {}
+                    }
+                    // Check if sender matches priority senders and send the noti anyways about it
+                    if let Some(matched_sender) = priority_senders.iter().filter(|p_send| p_send.noti_mode == "all").find(|priority_sender| {
+                        let priority_lower = priority_sender.sender.to_lowercase();
+                        // Check 'from' (display name)
+                        let from_matches = email.from.as_deref().unwrap_or("Unknown").to_lowercase().contains(&priority_lower);
+                        // Also check 'from_email' (actual email address)
+                        let from_email_matches = email.from_email.as_deref().unwrap_or("Unknown").to_lowercase().contains(&priority_lower);
+                        from_matches || from_email_matches
+                    }) {
+                        tracing::info!("Fast check: Priority sender matched for user {}", user_id);
+
+                        // Determine suffix based on noti_type
+                        let suffix = match matched_sender.noti_type.as_ref().map(|s| s.as_str()) {
+                            Some("call") => "_call",
+                            _ => "_sms",
+                        };
+                        let notification_type = format!("email_priority{}", suffix);
+
+                        // Format the notification message with sender and content
+                        let message = crate::proactive::utils::email_notification_text(
+                            email.from.as_deref(),
+                            email.subject.as_deref(),
+                            email.body.as_deref(),
+                            include_snippets,
+                        );
+                        let first_message = format!("Hello, you have a critical email from {} with subject: {}",
+                            email.from.as_deref().unwrap_or("Unknown"),
+                            email.subject.as_deref().unwrap_or("No subject")
+                        );
+
+                        if !crate::proactive::utils::should_notify_item(state, user_id, &format!("email:{}", email.id), &message) {
+                            continue;
+                        }
+                        crate::tool_call_utils::email::remember_surfaced_email(state, user_id, &email.id);
+                        let opening_context = crate::utils::call_templates::CallOpeningContext {
+                            sender: Some(email.from.clone().unwrap_or_else(|| "Unknown".to_string())),
+                            subject: Some(email.subject.clone().unwrap_or_else(|| "No subject".to_string())),
+                            ..Default::default()
+                        };
+                        // Spawn a new task for sending notification
+                        let state_clone = state.clone();
+                        tokio::spawn(async move {
+                            crate::proactive::utils::send_notification_with_context(
+                                &state_clone,
+                                user_id,
+                                &message,
+                                notification_type,
+                                Some(first_message),
+                                opening_context,
+                            ).await;
+                        });
+                        continue;
+                    }
+                    // Format email content for checking
+                    let email_content = format!(
+                        "From: {}\nSubject: {}\nDate: {}\nBody: {}\n---\n",
+                        email.from.as_deref().unwrap_or("Unknown"),
+                        email.subject.as_deref().unwrap_or("No subject"),
+                        email.date_formatted.as_deref().unwrap_or("Unknown date"),
+                        email.body.as_deref().unwrap_or("No content")
+                    );
+
+                                                            // Check waiting checks first if they exist
+                    let waiting_checks = match state.user_repository.get_waiting_checks(user_id, "email") {
+                        Ok(checks) => checks,
+                        Err(e) => {
+                            tracing::error!("Failed to get waiting checks for user {}: {}", user_id, e);
+                            Vec::new()
+                        }
+                    };
+                    if !waiting_checks.is_empty() {
+                        // Check if any waiting checks match the message
+                        if let Ok((check_id_option, message, first_message)) = crate::proactive::utils::check_waiting_check_match(
+                            state,
+                            &email_content,
+                            &waiting_checks,
+                        ).await {
+                            if let Some(check_id) = check_id_option {
+                                let message = message.unwrap_or("Waiting check matched in Email, but failed to get content".to_string());
+                                let first_message = first_message.unwrap_or("Hey, I found a match for one of your waiting checks in Email.".to_string());
+
+                                // Find the matched waiting check to determine noti_type
+                                let matched_waiting_check = waiting_checks.iter().find(|wc| wc.id == Some(check_id)).cloned();
+                                let suffix = if let Some(wc) = matched_waiting_check {
+                                    match wc.noti_type.as_ref().map(|s| s.as_str()) {
+                                        Some("call") => "_call",
+                                        _ => "_sms",
                                     }
+                                } else {
+                                    "_sms"
+                                };
+                                let notification_type = format!("email_waiting_check{}", suffix);
+
+                                // Resolve the matched waiting check so it isn't matched again
+                                if let Err(e) = state.user_repository.resolve_waiting_check(user_id, check_id, "auto") {
+                                    tracing::error!("Failed to resolve waiting check {}: {}", check_id, e);
+                                }
+
+                                if !crate::proactive::utils::should_notify_item(state, user_id, &format!("email:{}", email.id), &email_content) {
+                                    continue;
                                 }
+                                crate::tool_call_utils::email::remember_surfaced_email(state, user_id, &email.id);
+                                // Send notification
+                                let state_clone = state.clone();
+                                                tokio::spawn(async move {
+                                    crate::proactive::utils::send_notification(
+                                        &state_clone,
+                                        user_id,
+                                        &message,
+                                        notification_type,
+                                        Some(first_message),
+                                    ).await;
+                                });
+                                continue;
                             }
-                        });
+                        }
+                    }
 
-                        sync_tasks.insert(user_id, handle);
-                    },
+                    // Add email to content string for importance checking
+                    emails_content.push_str(&email_content);
+                    critical_candidate_ids.push(email.id.clone());
+                }
+
+
+                // Check message importance based on waiting checks and criticality
+                let user_settings = match state.user_core.get_user_settings(user_id) {
+                    Ok(settings) => settings,
                     Err(e) => {
-                        error!("Failed to create Matrix client for user {}: {}", user_id, e);
+                        tracing::error!("Failed to get user settings: {}", e);
+                        return;
+                    }
+                };
+
+                if user_settings.critical_enabled.is_none() {
+                    tracing::debug!("Critical message checking disabled for user {}", user_id);
+                    return;
+                }
+
+                // Check message importance based on criticality
+                let importance_input = if include_snippets {
+                    emails_content.clone()
+                } else {
+                    format!("{}{}", emails_content, crate::proactive::utils::NO_SNIPPET_INSTRUCTION)
+                };
+                match crate::proactive::utils::check_message_importance(state, user_id, &importance_input, "", "", "").await {
+                    Ok((is_critical, message, first_message)) => {
+                        if is_critical {
+                            let message = message.unwrap_or("Critical email found, check email to see it (failed to fetch actual content, pls report)".to_string());
+                            let first_message = first_message.unwrap_or("Hey, I found some critical email you should know.".to_string());
+                            tracing::info!(
+                                "Email critical check passed for user {}: {}",
+                                user_id, message
+                            );
+                            let item_key = format!("email:{}", critical_candidate_ids.join(","));
+                            if !crate::proactive::utils::should_notify_item(state, user_id, &item_key, &emails_content) {
+                                return;
+                            }
+
+                            // Spawn a new task for sending critical message notification
+                            let state_clone = state.clone();
+                            let message_clone= message.clone();
+                            tokio::spawn(async move {
+                                crate::proactive::utils::send_notification(
+                                    &state_clone,
+                                    user_id,
+                                    &message_clone,
+                                    "email_critical".to_string(),
+                                    Some(first_message),
+                                ).await;
+                            });
+                        } else {
+                            tracing::debug!(
+                                "Email not considered important for user {}: {}",
+                                user_id, message.unwrap_or("failed to get the email content".to_string())
+                            );
+
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to check email importance: {}", e);
                     }
                 }
             }
         },
-        Err(e) => error!("Failed to get active WhatsApp users: {}", e),
+        Err(e) => {
+            error!("Failed to fetch IMAP emails for user {}: Error: {:?}", user_id, e);
+        }
     }
 }
 
@@ -82,252 +456,55 @@ pub async fn start_scheduler(state: Arc<AppState>) {
     // Initialize matrix clients and sync tasks once on startup
     tracing::debug!("Initializing Matrix clients and sync tasks...");
     initialize_matrix_clients(Arc::clone(&state)).await;
+    crate::utils::imap_idle::initialize_idle_listeners(&state).await;
 
     let sched = JobScheduler::new().await.expect("Failed to create scheduler");
 
-    // Create a job that runs every 10 minutes to check for new IMAP messages
+    // Create a job that runs every minute and checks new IMAP messages for users whose polling interval is due
     let state_clone = Arc::clone(&state);
-    let message_monitor_job = Job::new_async("0 */10 * * * *", move |_, _| {
+    let message_monitor_guard = PassGuard::new("message monitor");
+    let message_monitor_job = Job::new_async("0 * * * * *", move |_, _| {
     //let message_monitor_job = Job::new_async("*/30 * * * * *", move |_, _| {
         let state = state_clone.clone();
+        let guard = message_monitor_guard.clone();
         Box::pin(async move {
-            
-            // Process each subscribed user
-            for user in state.user_core.get_users_by_tier("tier 2").unwrap_or(Vec::new()){
-
-                // Check IMAP service
-                if let Ok(imap_users) = state.user_repository.get_active_imap_connection_users() {
-                    if imap_users.contains(&user.id) {
-                        match imap_handlers::fetch_emails_imap(&state, user.id, true, Some(10), true, true).await {
-                            Ok(emails) => {
-                                match state.user_repository.get_processed_emails(user.id) {
-                                    Ok(mut processed_emails) => {
-                                        // Define constants
-                                        let fetch_window = 10;  // Number of emails your scheduler fetches
-                                        let cleanup_threshold = 100;  // Only cleanup when we have significantly more than fetch window
-
-                                        if processed_emails.len() > cleanup_threshold {
-                                            // Sort by processed_at timestamp (newest first)
-                                            processed_emails.sort_by(|a, b| b.processed_at.cmp(&a.processed_at));
-                                            
-                                            // Keep at least fetch_window emails plus some buffer
-                                            let keep_count = fetch_window * 2;  // Keep 20 emails (double the fetch window)
-                                            
-                                            // Get emails to delete (older than our keep_count)
-                                            let emails_to_delete: Vec<_> = processed_emails
-                                                .iter()
-                                                .skip(keep_count)
-                                                .collect();
-
-                                            // Delete old processed emails
-                                            for email in emails_to_delete {
-                                                if let Err(e) = state.user_repository.delete_processed_email(user.id, &email.email_uid) {
-                                                    error!("Failed to delete old processed email {}: {}", email.email_uid, e);
-                                                } else {
-                                                    debug!("Deleted old processed email {} for user {}", email.email_uid, user.id);
-                                                }
-                                            }
-
-                                            // Update the original collection
-                                            processed_emails.truncate(keep_count);
-
-                                            // Also clean up old email judgments
-                                            if let Err(e) = state.user_repository.delete_old_email_judgments(user.id) {
-                                                error!("Failed to delete old email judgments for user {}: {}", user.id, e);
-                                            } else {
-                                                debug!("Successfully cleaned up old email judgments for user {}", user.id);
-                                            }
-                                        }
-                                    }
-                                    Err(e) => error!("Failed to fetch processed emails for garbage collection: {}", e),
-                                }
-                                
-                                if !emails.is_empty() {
-                                    // Sort emails by date in descending order (most recent first)
-                                    let mut sorted_emails = emails;
-                                    sorted_emails.sort_by(|a, b| {
-                                        let a_date = a.date.unwrap_or_else(|| chrono::Utc::now());
-                                        let b_date = b.date.unwrap_or_else(|| chrono::Utc::now());
-                                        b_date.cmp(&a_date)
-                                    });
-
-                                    let priority_senders = match state.user_repository.get_priority_senders(user.id, "imap") {
-                                        Ok(senders) => senders,
-                                        Err(e) => {
-                                            tracing::error!("Failed to get priority senders for user {}: {}", user.id, e);
-                                            Vec::new()
-                                        }
-                                    };
-                                    // Mark emails as processed and format them for importance checking
-                                    let mut emails_content = String::from("New emails:\n");
-                                    for email in &sorted_emails {
-                                        // Check if sender matches priority senders and send the noti anyways about it
-                                        if let Some(matched_sender) = priority_senders.iter().filter(|p_send| p_send.noti_mode == "all").find(|priority_sender| {
-                                            let priority_lower = priority_sender.sender.to_lowercase();
-                                            // Check 'from' (display name)
-                                            let from_matches = email.from.as_deref().unwrap_or("Unknown").to_lowercase().contains(&priority_lower);
-                                            // Also check 'from_email' (actual email address)
-                                            let from_email_matches = email.from_email.as_deref().unwrap_or("Unknown").to_lowercase().contains(&priority_lower);
-                                            from_matches || from_email_matches
-                                        }) {
-                                            tracing::info!("Fast check: Priority sender matched for user {}", user.id);
-                                           
-                                            // Determine suffix based on noti_type
-                                            let suffix = match matched_sender.noti_type.as_ref().map(|s| s.as_str()) {
-                                                Some("call") => "_call",
-                                                _ => "_sms",
-                                            };
-                                            let notification_type = format!("email_priority{}", suffix);
-                                           
-                                            // Format the notification message with sender and content
-                                            let message = format!(
-                                                "Email from: {}\nSubject: {}\nContent: {}",
-                                                email.from.as_deref().unwrap_or("Unknown"),
-                                                email.subject.as_deref().unwrap_or("No subject"),
-                                                email.body.as_deref().unwrap_or("No content").chars().take(200).collect::<String>()
-                                            );
-                                            let first_message = format!("Hello, you have a critical email from {} with subject: {}",
-                                                email.from.as_deref().unwrap_or("Unknown"),
-                                                email.subject.as_deref().unwrap_or("No subject")
-                                            );
-                                           
-                                            // Spawn a new task for sending notification
-                                            let state_clone = state.clone();
-                                            tokio::spawn(async move {
-                                                crate::proactive::utils::send_notification(
-                                                    &state_clone,
-                                                    user.id,
-                                                    &message,
-                                                    notification_type,
-                                                    Some(first_message),
-                                                ).await;
-                                            });
-                                            continue;
-                                        }
-                                        // Format email content for checking
-                                        let email_content = format!(
-                                            "From: {}\nSubject: {}\nDate: {}\nBody: {}\n---\n",
-                                            email.from.as_deref().unwrap_or("Unknown"),
-                                            email.subject.as_deref().unwrap_or("No subject"),
-                                            email.date_formatted.as_deref().unwrap_or("Unknown date"),
-                                            email.body.as_deref().unwrap_or("No content")
-                                        );
-
-                                                                                // Check waiting checks first if they exist
-                                        let waiting_checks = match state.user_repository.get_waiting_checks(user.id, "email") {
-                                            Ok(checks) => checks,
-                                            Err(e) => {
-                                                tracing::error!("Failed to get waiting checks for user {}: {}", user.id, e);
-                                                Vec::new()
-                                            }
-                                        };
-                                        if !waiting_checks.is_empty() {
-                                            // Check if any waiting checks match the message
-                                            if let Ok((check_id_option, message, first_message)) = crate::proactive::utils::check_waiting_check_match(
-                                                &state,
-                                                &email_content,
-                                                &waiting_checks,
-                                            ).await {
-                                                if let Some(check_id) = check_id_option {
-                                                    let message = message.unwrap_or("Waiting check matched in Email, but failed to get content".to_string());
-                                                    let first_message = first_message.unwrap_or("Hey, I found a match for one of your waiting checks in Email.".to_string());
-                                                   
-                                                    // Find the matched waiting check to determine noti_type
-                                                    let matched_waiting_check = waiting_checks.iter().find(|wc| wc.id == Some(check_id)).cloned();
-                                                    let suffix = if let Some(wc) = matched_waiting_check {
-                                                        match wc.noti_type.as_ref().map(|s| s.as_str()) {
-                                                            Some("call") => "_call",
-                                                            _ => "_sms",
-                                                        }
-                                                    } else {
-                                                        "_sms"
-                                                    };
-                                                    let notification_type = format!("email_waiting_check{}", suffix);
-                                                   
-                                                    // Delete the matched waiting check
-                                                    if let Err(e) = state.user_repository.delete_waiting_check_by_id(user.id, check_id) {
-                                                        tracing::error!("Failed to delete waiting check {}: {}", check_id, e);
-                                                    }
-                                                   
-                                                    // Send notification
-                                                    let state_clone = state.clone();
-                                                    let user_id = user.id;
-                                                    tokio::spawn(async move {
-                                                        crate::proactive::utils::send_notification(
-                                                            &state_clone,
-                                                            user_id,
-                                                            &message,
-                                                            notification_type,
-                                                            Some(first_message),
-                                                        ).await;
-                                                    });
-                                                    continue;
-                                                }
-                                            }
-                                        }
-
-                                        // Add email to content string for importance checking
-                                        emails_content.push_str(&email_content);
-                                    }
-
-
-                                    // Check message importance based on waiting checks and criticality
-                                    let user_settings = match state.user_core.get_user_settings(user.id) {
-                                        Ok(settings) => settings,
-                                        Err(e) => {
-                                            tracing::error!("Failed to get user settings: {}", e);
-                                            return;
-                                        }
-                                    };
+            let Some(_pass) = guard.try_start() else { return };
+            let minutes_since_epoch = chrono::Utc::now().timestamp() / 60;
+            let now = chrono::Utc::now().timestamp() as i32;
 
-                                    if user_settings.critical_enabled.is_none() {
-                                        tracing::debug!("Critical message checking disabled for user {}", user.id);
-                                        return;
-                                    }
-
-                                    // Check message importance based on criticality
-                                    match crate::proactive::utils::check_message_importance(&state, user.id, &emails_content, "", "", "").await {
-                                        Ok((is_critical, message, first_message)) => {
-                                            if is_critical {
-                                                let message = message.unwrap_or("Critical email found, check email to see it (failed to fetch actual content, pls report)".to_string());
-                                                let first_message = first_message.unwrap_or("Hey, I found some critical email you should know.".to_string());
-                                                tracing::info!(
-                                                    "Email critical check passed for user {}: {}",
-                                                    user.id, message
-                                                );
-                                                                
-                                                // Spawn a new task for sending critical message notification
-                                                let state_clone = state.clone();
-                                                let message_clone= message.clone();
-                                                tokio::spawn(async move {
-                                                    crate::proactive::utils::send_notification(
-                                                        &state_clone,
-                                                        user.id,
-                                                        &message_clone,
-                                                        "email_critical".to_string(),
-                                                        Some(first_message),
-                                                    ).await;
-                                                });
-                                            } else {
-                                                tracing::debug!(
-                                                    "Email not considered important for user {}: {}",
-                                                    user.id, message.unwrap_or("failed to get the email content".to_string())
-                                                );
-
-                                            }
-                                        }
-                                        Err(e) => {
-                                            tracing::error!("Failed to check email importance: {}", e);
-                                        }
-                                    }
-                                }
-                            },
-                            Err(e) => {
-                                error!("Failed to fetch IMAP emails for user {}: Error: {:?}", user.id, e);
-                            }
-                        }
-                    }
+            // Everyone with email connected, loaded with their settings in one go
+            let imap_users = match state.user_repository.get_active_imap_connection_users() {
+                Ok(imap_users) => imap_users,
+                Err(e) => {
+                    error!("Failed to get IMAP users for polling: {}", e);
+                    return;
+                }
+            };
+            let users = match state.user_core.get_users_with_settings(&imap_users) {
+                Ok(users) => users,
+                Err(e) => {
+                    error!("Failed to load users for email polling: {}", e);
+                    return;
                 }
+            };
+            for (user, settings) in users {
+                // Each user's own tier decides the interval, tiers without one aren't polled.
+                // Users without a settings row get the tier default and can't be paused.
+                let user_override = settings.as_ref().and_then(|settings| settings.email_poll_interval_minutes);
+                let Some(interval) = user_email_poll_interval_minutes(user.sub_tier.as_deref(), user_override) else {
+                    continue;
+                };
+                if !is_due_for_email_poll(user.id, interval, minutes_since_epoch) {
+                    continue;
+                }
+                if settings.as_ref().is_some_and(|settings| crate::proactive::utils::is_paused(settings, None, now)) {
+                    continue;
+                }
+                // New mail is pushed to users with a live IDLE connection watching all their mail
+                if crate::utils::imap_idle::covers_all_mail(&state, user.id) {
+                    continue;
+                }
+                check_new_emails(&state, user.id).await;
             }
 
         })
@@ -468,6 +645,35 @@ pub async fn start_scheduler(state: Arc<AppState>) {
     sched.add(usage_monitor_job).await.expect("Failed to add usage monitor job to scheduler");
     */
 
+    // Create a job that runs every 10 minutes to check TextBee devices are reachable
+    let state_clone = Arc::clone(&state);
+    let textbee_health_guard = PassGuard::new("TextBee health check");
+    let textbee_health_job = Job::new_async("0 */10 * * * *", move |_, _| {
+        let state = state_clone.clone();
+        let guard = textbee_health_guard.clone();
+        Box::pin(async move {
+            let Some(_pass) = guard.try_start() else { return };
+            crate::utils::textbee::check_all_devices(&state).await;
+        })
+    }).expect("Failed to create TextBee health job");
+
+    sched.add(textbee_health_job).await.expect("Failed to add TextBee health job to scheduler");
+
+    // Create a job that runs every minute to send emails and messages scheduled for a set time
+    crate::utils::scheduled_sends::recover_interrupted_sends(&state).await;
+    let state_clone = Arc::clone(&state);
+    let scheduled_sends_guard = PassGuard::new("scheduled sends");
+    let scheduled_sends_job = Job::new_async("30 * * * * *", move |_, _| {
+        let state = state_clone.clone();
+        let guard = scheduled_sends_guard.clone();
+        Box::pin(async move {
+            let Some(_pass) = guard.try_start() else { return };
+            crate::utils::scheduled_sends::run_due_sends(&state).await;
+        })
+    }).expect("Failed to create scheduled sends job");
+
+    sched.add(scheduled_sends_job).await.expect("Failed to add scheduled sends job to scheduler");
+
     // Create a job that runs daily to clean up old task notifications
     let state_clone = Arc::clone(&state);
     let task_cleanup_job = Job::new_async("0 0 0 * * *", move |_, _| {  // Runs at midnight every day
@@ -490,9 +696,12 @@ pub async fn start_scheduler(state: Arc<AppState>) {
 
     // Create a job that runs every hour to check morning digests
     let state_clone = Arc::clone(&state);
+    let digest_check_guard = PassGuard::new("digest check");
     let digest_check_job = Job::new_async("0 0 * * * *", move |_, _| {
         let state = state_clone.clone();
+        let guard = digest_check_guard.clone();
         Box::pin(async move {
+            let Some(_pass) = guard.try_start() else { return };
             debug!("Running hourly morning digest check...");
             
             // Get all users with tier 2 subscription
@@ -506,6 +715,10 @@ pub async fn start_scheduler(state: Arc<AppState>) {
                                 tracing::debug!("User {} does not have monitoring enabled", user.id);
                                 continue;
                             }
+                            if is_user_paused(&state, user.id) {
+                                tracing::debug!("User {} has paused notifications", user.id);
+                                continue;
+                            }
                             if tier == "tier 2" {
                                 debug!("Checking morning digest for user {} with tier 2 subscription", user.id);
                                 if let Err(e) = crate::proactive::utils::check_morning_digest(&state, user.id).await {
@@ -530,16 +743,13 @@ pub async fn start_scheduler(state: Arc<AppState>) {
 
     // Create a job that runs every 5 minutes to check for upcoming calendar events
     let state_clone = Arc::clone(&state);
+    let calendar_notification_guard = PassGuard::new("calendar notification");
     let calendar_notification_job = Job::new_async("0 */5 * * * *", move |_, _| {  // Run every 5 minutes
         let state = state_clone.clone();
+        let guard = calendar_notification_guard.clone();
         Box::pin(async move {
-            // Use a mutex to ensure only one instance runs at a time
-            let calendar_mutex = tokio::sync::Mutex::new(());
-            let _lock = calendar_mutex.try_lock();
-            if _lock.is_err() {
-                debug!("Calendar check already in progress, skipping this run");
-                return;
-            }
+            // Only one instance at a time, the guard outlives a single run unlike a local mutex
+            let Some(_pass) = guard.try_start() else { return };
 
             // Clean up old notifications (older than 24 hours) with retry logic
             let cleanup_threshold = (chrono::Utc::now() - chrono::Duration::hours(24)).timestamp() as i32;
@@ -561,7 +771,8 @@ pub async fn start_scheduler(state: Arc<AppState>) {
                     // Check subscription and calendar status
                     matches!(state.user_repository.has_valid_subscription_tier(user.id, "tier 2"), Ok(true)) &&
                     matches!(state.user_repository.has_active_google_calendar(user.id), Ok(true)) &&
-                    matches!(state.user_core.get_proactive_agent_on(user.id), Ok(true))
+                    matches!(state.user_core.get_proactive_agent_on(user.id), Ok(true)) &&
+                    !is_user_paused(&state, user.id)
                 }).collect::<Vec<_>>(),
                 Err(e) => {
                     error!("Failed to fetch users: {}", e);
@@ -603,7 +814,7 @@ pub async fn start_scheduler(state: Arc<AppState>) {
                     Ok(events) => {
                         debug!("🗓️ Calendar check: Found {} events for user {}", events.len(), user.id);
                         for event in events {
-                            if let (Some(reminders), Some(_start_time)) = (&event.reminders, event.start.date_time) {
+                            if let (Some(reminders), Some(start_time)) = (&event.reminders, event.start.date_time) {
                                 for reminder in &reminders.overrides {
                                     let reminder_key = format!("{}_{}", event.id, reminder.minutes);
                                     
@@ -625,18 +836,30 @@ pub async fn start_scheduler(state: Arc<AppState>) {
                                     }
 
                                     let event_summary = event.summary.clone().unwrap_or_else(|| "Untitled Event".to_string());
-                                    let notification = format!("Calendar: {} in {} mins", event_summary, reminder.minutes);
+                                    let notification = crate::proactive::utils::calendar_notification_text(&event_summary, reminder.minutes);
 
                                     let state_clone = state.clone();
                                     let first_message = format!("Hello, you have a calendar event starting in {}.", reminder.minutes);
+                                    let local_start = match state.user_core.get_user_info(user.id).ok()
+                                        .and_then(|info| info.timezone)
+                                        .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok()) {
+                                        Some(tz) => start_time.with_timezone(&tz).format("%H:%M").to_string(),
+                                        None => start_time.format("%H:%M UTC").to_string(),
+                                    };
+                                    let opening_context = crate::utils::call_templates::CallOpeningContext {
+                                        subject: Some(event_summary.clone()),
+                                        time: Some(local_start),
+                                        ..Default::default()
+                                    };
                                     let user_id = user.id.clone();
                                     tokio::spawn(async move {
-                                        crate::proactive::utils::send_notification(
+                                        crate::proactive::utils::send_notification_with_context(
                                             &state_clone,
                                             user_id,
                                             &notification,
                                             "calendar_notification".to_string(),
                                             Some(first_message),
+                                            opening_context,
                                         ).await;
                                     });
                                 }
@@ -660,3 +883,72 @@ pub async fn start_scheduler(state: Arc<AppState>) {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn users_get_their_tier_interval() {
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 2"), None), Some(10));
+        assert_eq!(user_email_poll_interval_minutes(None, None), None);
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 0"), Some(5)), None);
+    }
+
+    #[test]
+    fn self_hosted_tier_is_not_polled() {
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 3"), None), None);
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 3"), Some(10)), None);
+    }
+
+    #[test]
+    fn overrides_move_tier_2_within_bounds() {
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 2"), Some(5)), Some(5));
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 2"), Some(1)), Some(min_email_poll_interval_minutes()));
+        assert_eq!(user_email_poll_interval_minutes(Some("tier 2"), Some(60)), Some(60));
+    }
+
+    #[test]
+    fn longer_interval_is_polled_less_often() {
+        let polls = |interval: u32, user_id: i32| (0..120).filter(|minute| is_due_for_email_poll(user_id, interval, *minute)).count();
+        assert_eq!(polls(10, 7), 12);
+        assert_eq!(polls(30, 7), 4);
+    }
+
+    #[test]
+    fn users_with_the_same_interval_are_staggered() {
+        let due_at_start: Vec<i32> = (1..=10).filter(|user_id| is_due_for_email_poll(*user_id, 10, 0)).collect();
+        assert_eq!(due_at_start, vec![10]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tick_during_a_long_pass_is_skipped() {
+        use std::sync::atomic::AtomicUsize;
+        use std::time::Duration;
+        let guard = PassGuard::new("slow IMAP");
+        let passes = Arc::new(AtomicUsize::new(0));
+        // A pass that takes 90 seconds on a job that ticks every minute
+        let tick = || {
+            let guard = guard.clone();
+            let passes = Arc::clone(&passes);
+            tokio::spawn(async move {
+                let Some(_pass) = guard.try_start() else { return false };
+                passes.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_secs(90)).await;
+                true
+            })
+        };
+
+        let first = tick();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(!tick().await.unwrap(), "second tick overlapped the first pass");
+        assert_eq!(passes.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(first.await.unwrap());
+        let third = tick();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(passes.load(Ordering::SeqCst), 2);
+        assert!(third.await.unwrap());
+    }
+}
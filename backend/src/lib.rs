@@ -3,11 +3,14 @@ use rand::Rng;
 use reqwest::Client;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use serde_json::json;
+use std::collections::HashMap;
 
 pub struct TwilioConfig {
     pub account_sid: String,
     pub auth_token: String,
     pub from_number: String,
+    /// Country code (e.g. "GB") -> sender number, from TWILIO_FROM_NUMBERS
+    pub from_numbers: HashMap<String, String>,
 }
 
 impl TwilioConfig {
@@ -19,8 +22,57 @@ impl TwilioConfig {
                 .expect("TWILIO_AUTH_TOKEN must be set"),
             from_number: std::env::var("TWILIO_FROM_NUMBER")
                 .expect("TWILIO_FROM_NUMBER must be set"),
+            from_numbers: parse_from_numbers(&std::env::var("TWILIO_FROM_NUMBERS").unwrap_or_default()),
         }
     }
+
+    /// Sender number for the recipient's country, the default `from_number` if none is configured
+    pub fn from_number_for(&self, to_number: &str) -> &str {
+        country_for_number(to_number)
+            .and_then(|country| self.from_numbers.get(country))
+            .map(String::as_str)
+            .unwrap_or(&self.from_number)
+    }
+}
+
+/// Parses "GB=+447700900000,FI=+358401234567" into country -> number.
+/// Malformed entries are skipped.
+pub fn parse_from_numbers(raw: &str) -> HashMap<String, String> {
+    raw.split(',')
+        .filter_map(|entry| {
+            let (country, number) = entry.split_once('=')?;
+            let (country, number) = (country.trim().to_uppercase(), number.trim());
+            if country.is_empty() || !number.starts_with('+') {
+                return None;
+            }
+            Some((country, number.to_string()))
+        })
+        .collect()
+}
+
+/// Dialing prefixes of the countries we have senders in, longest first so "+358" wins over "+35"
+const COUNTRY_PREFIXES: [(&str, &str); 12] = [
+    ("+358", "FI"),
+    ("+353", "IE"),
+    ("+31", "NL"),
+    ("+32", "BE"),
+    ("+33", "FR"),
+    ("+34", "ES"),
+    ("+39", "IT"),
+    ("+44", "GB"),
+    ("+45", "DK"),
+    ("+46", "SE"),
+    ("+49", "DE"),
+    ("+61", "AU"),
+];
+
+/// Country of an E.164 number for sender selection. North American numbers are left
+/// to the caller since US and CA share +1 and need the area code table.
+pub fn country_for_number(number: &str) -> Option<&'static str> {
+    COUNTRY_PREFIXES
+        .iter()
+        .find(|(prefix, _)| number.starts_with(prefix))
+        .map(|(_, country)| *country)
 }
 
 pub fn generate_otp() -> String {
@@ -44,7 +96,7 @@ pub async fn send_otp(config: &TwilioConfig, to_number: &str, otp: &str) -> Resu
 
     // Create form data
     let form = json!({
-        "From": config.from_number,
+        "From": config.from_number_for(to_number),
         "To": to_number,
         "Body": message,
     });
@@ -70,3 +122,33 @@ pub async fn send_otp(config: &TwilioConfig, to_number: &str, otp: &str) -> Resu
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(from_numbers: &str) -> TwilioConfig {
+        TwilioConfig {
+            account_sid: "AC-test".to_string(),
+            auth_token: "token".to_string(),
+            from_number: "+15550000000".to_string(),
+            from_numbers: parse_from_numbers(from_numbers),
+        }
+    }
+
+    #[test]
+    fn gb_recipient_gets_the_gb_sender_when_configured() {
+        let config = config("gb=+447700900000, FI=+358401234567, broken, SE=0701234567");
+        assert_eq!(config.from_number_for("+447911123456"), "+447700900000");
+        assert_eq!(config.from_number_for("+358501234567"), "+358401234567");
+        // Sweden's entry isn't an E.164 number, so it was dropped
+        assert_eq!(config.from_number_for("+46701234567"), "+15550000000");
+    }
+
+    #[test]
+    fn gb_recipient_gets_the_default_without_a_gb_sender() {
+        let config = config("FI=+358401234567");
+        assert_eq!(config.from_number_for("+447911123456"), "+15550000000");
+        assert_eq!(config.from_number_for("+12025550123"), "+15550000000");
+    }
+}
@@ -1,10 +1,10 @@
 use dotenvy::dotenv;
 use axum::{
-    routing::{get, post, delete, patch},
+    routing::{get, post, put, delete, patch},
     Router,
     middleware
 };
-use tokio::sync::{Mutex, oneshot};
+use tokio::sync::Mutex;
 use tower_sessions::{MemoryStore, SessionManagerLayer};
 use std::collections::HashMap;
 use diesel::prelude::*;
@@ -61,6 +61,7 @@ mod handlers {
     pub mod tesla_auth;
     pub mod google_maps;
     pub mod totp_handlers;
+    pub mod contacts_handlers;
 }
 mod utils {
     pub mod encryption;
@@ -74,6 +75,32 @@ mod utils {
     pub mod subaccount_lifecycle;
     pub mod notification_utils;
     pub mod tesla_keys;
+    pub mod tesla_telemetry;
+    pub mod job_queue;
+    pub mod voice_languages;
+    pub mod metrics;
+    pub mod google_tokens;
+    pub mod seed;
+    pub mod textbee;
+    pub mod call_templates;
+    pub mod connection_events;
+    pub mod branding;
+    pub mod sms_segments;
+    pub mod bridge_resync;
+    pub mod client_ip;
+    pub mod payload_limits;
+    pub mod language_detection;
+    pub mod matrix_retry;
+    pub mod matrix_client_cache;
+    pub mod business_hours;
+    pub mod imap_utils;
+    pub mod imap_idle;
+    pub mod scheduled_sends;
+    pub mod elevenlabs_retry;
+    pub mod webhook_status;
+    pub mod self_test;
+    pub mod broadcast_template;
+    pub mod sms_help;
 }
 mod proactive {
     pub mod utils;
@@ -108,6 +135,8 @@ mod repositories {
     pub mod totp_repository;
 }
 mod schema;
+#[cfg(test)]
+mod test_support;
 mod jobs {
     pub mod scheduler;
 }
@@ -143,16 +172,64 @@ pub struct AppState {
     password_reset_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>,
     password_reset_verify_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>,
     matrix_sync_tasks: Arc<Mutex<HashMap<i32, tokio::task::JoinHandle<()>>>>,
-    matrix_clients: Arc<Mutex<HashMap<i32, Arc<matrix_sdk::Client>>>>,
+    matrix_clients: Arc<Mutex<utils::matrix_client_cache::ClientCache<matrix_sdk::Client>>>, // capped at MATRIX_CLIENT_CACHE_MAX, least recently used evicted
     tesla_monitoring_tasks: Arc<DashMap<i32, tokio::task::JoinHandle<()>>>,
+    email_idle_tasks: Arc<DashMap<i32, tokio::task::JoinHandle<()>>>, // IMAP IDLE listener per user
+    email_idle_listening: DashMap<i32, i64>, // user_id -> when their IDLE connection came up, these aren't polled
+    tesla_telemetry: utils::tesla_telemetry::TelemetryCache, // latest vehicle state by VIN, pushed or polled
     password_reset_otps: DashMap<String, (String, u64)>, // (email, (otp, expiration))
     phone_verify_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>,
     phone_verify_verify_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>,
     phone_verify_otps: DashMap<String, (String, u64)>,
-    pending_message_senders: Arc<Mutex<HashMap<i32, oneshot::Sender<()>>>>,
+    phone_verify_sends: DashMap<String, Vec<u64>>, // phone_number -> timestamps of codes sent within the last hour
+    pending_message_senders: Arc<Mutex<HashMap<i32, Vec<tool_call_utils::utils::PendingAction>>>>, // queued cancellable actions per user
     totp_repository: Arc<TotpRepository>,
     pending_totp_logins: DashMap<String, (i32, i64)>, // (totp_token, (user_id, expiry_timestamp))
+    email_judgment_rerun_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>,
+    inbound_sms_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>, // per user id, on the user's own Twilio number route
+    help_reply_limiter: DashMap<String, RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>>, // per user id, free SMS help menu replies
+    last_surfaced_emails: DashMap<i32, String>, // user_id -> uid of the last email shown to them over SMS/call
+    job_queue: Arc<utils::job_queue::JobQueue>, // outbound side effects (delayed sends, attachment processing)
+    connection_events: Arc<utils::connection_events::ConnectionEvents>, // pushed to the frontend over /api/events/connections
+    active_notification_calls: DashMap<i32, api::elevenlabs::ActiveNotificationCall>, // user_id -> outbound call that can still be cancelled
+    sms_segments: utils::sms_segments::SegmentBuffer, // inbound SMS split by the carrier, joined before processing
+    sms_languages: utils::language_detection::LanguageCache, // last language detected in each user's SMS
+    bridge_resyncs: utils::bridge_resync::ResyncTracker, // resync checkpoints per (user, service)
 }
+/// Origins allowed to make credentialed requests: comma-separated FRONTEND_URLS,
+/// falling back to the single FRONTEND_URL. Origins not on the list get no CORS headers.
+fn allowed_origins() -> Vec<HeaderValue> {
+    let raw = std::env::var("FRONTEND_URLS")
+        .or_else(|_| std::env::var("FRONTEND_URL"))
+        .unwrap_or_else(|_| "http://localhost:8080".to_string());
+    parse_origins(&raw)
+}
+
+fn parse_origins(raw: &str) -> Vec<HeaderValue> {
+    raw.split(',')
+        .map(|origin| origin.trim().trim_end_matches('/'))
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| origin.parse::<HeaderValue>().unwrap_or_else(|_| panic!("Invalid frontend origin: {}", origin)))
+        .collect()
+}
+
+fn cors_layer(origins: Vec<HeaderValue>) -> CorsLayer {
+    CorsLayer::new()
+        .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::OPTIONS, axum::http::Method::DELETE, axum::http::Method::PATCH, axum::http::Method::PUT])
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_headers([
+            axum::http::header::CONTENT_TYPE,
+            axum::http::header::AUTHORIZATION,
+            axum::http::header::ACCEPT,
+            axum::http::header::ORIGIN,
+        ])
+        .expose_headers([
+            axum::http::header::CONTENT_TYPE,
+            axum::http::header::CONTENT_LENGTH,
+        ])
+        .allow_credentials(true)
+}
+
 pub fn validate_env() {
     let required_vars = [
         "JWT_SECRET_KEY", "JWT_REFRESH_KEY", "DATABASE_URL", "PERPLEXITY_API_KEY",
@@ -207,6 +284,17 @@ async fn main() {
     let user_core= Arc::new(UserCore::new(pool.clone()));
     let user_repository = Arc::new(UserRepository::new(pool.clone()));
     let totp_repository = Arc::new(TotpRepository::new(pool.clone()));
+    // `backend seed` fills a development/staging database with a test user and exits
+    if std::env::args().nth(1).as_deref() == Some("seed") {
+        match utils::seed::seed_test_data(&user_core, &user_repository) {
+            Ok(user_id) => tracing::info!("Seeded test user {} ({})", user_id, utils::seed::SEED_USER_EMAIL),
+            Err(e) => {
+                tracing::error!("Seeding failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
     let server_url_oauth = std::env::var("SERVER_URL_OAUTH").unwrap_or_else(|_| "http://localhost:3000".to_string());
     let server_url = std::env::var("SERVER_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
     let client_id = std::env::var("GOOGLE_CALENDAR_CLIENT_ID").unwrap_or_else(|_| "default-client-id-for-testing".to_string());
@@ -246,7 +334,7 @@ async fn main() {
         .set_redirect_uri(RedirectUrl::new(format!("{}/api/auth/tesla/callback", tesla_redirect_url)).expect("Invalid redirect URL"));
 
     let matrix_sync_tasks = Arc::new(Mutex::new(HashMap::new()));
-    let matrix_clients = Arc::new(Mutex::new(HashMap::new()));
+    let matrix_clients = Arc::new(Mutex::new(utils::matrix_client_cache::ClientCache::new(utils::matrix_client_cache::cache_capacity())));
     let state = Arc::new(AppState {
         db_pool: pool,
         user_core: user_core.clone(),
@@ -260,21 +348,35 @@ async fn main() {
         password_reset_limiter: DashMap::new(),
         password_reset_verify_limiter: DashMap::new(),
         phone_verify_otps: DashMap::new(),
+        phone_verify_sends: DashMap::new(),
         matrix_sync_tasks,
         matrix_clients,
         tesla_monitoring_tasks: Arc::new(DashMap::new()),
+        email_idle_tasks: Arc::new(DashMap::new()),
+        email_idle_listening: DashMap::new(),
+        tesla_telemetry: utils::tesla_telemetry::TelemetryCache::new(),
         phone_verify_limiter: DashMap::new(),
         phone_verify_verify_limiter: DashMap::new(),
         password_reset_otps: DashMap::new(),
         pending_message_senders: Arc::new(Mutex::new(HashMap::new())),
         totp_repository,
         pending_totp_logins: DashMap::new(),
+        email_judgment_rerun_limiter: DashMap::new(),
+        inbound_sms_limiter: DashMap::new(),
+        help_reply_limiter: DashMap::new(),
+        last_surfaced_emails: DashMap::new(),
+        job_queue: utils::job_queue::JobQueue::from_env(),
+        connection_events: utils::connection_events::ConnectionEvents::new(),
+        active_notification_calls: DashMap::new(),
+        sms_segments: utils::sms_segments::SegmentBuffer::new(),
+        sms_languages: utils::language_detection::LanguageCache::new(),
+        bridge_resyncs: utils::bridge_resync::ResyncTracker::new(),
     });
     let twilio_routes = Router::new()
         .route("/api/sms/server", post(twilio_sms::handle_regular_sms))
         .layer(middleware::from_fn_with_state(state.clone(), api::twilio_utils::validate_twilio_signature));
     let user_twilio_routes = Router::new()
-        .route("/api/sms/server/{user_id}", post(twilio_sms::handle_incoming_sms))
+        .route("/api/sms/server/{user_id}", post(twilio_sms::handle_user_twilio_sms))
         .route_layer(middleware::from_fn(api::twilio_utils::validate_user_twilio_signature));
     let textbee_routes = Router::new()
         .route("/api/sms/textbee-server", post(twilio_sms::handle_textbee_sms));
@@ -288,10 +390,13 @@ async fn main() {
         .route("/api/call/sms", post(elevenlabs::handle_send_sms_tool_call))
         .route("/api/call/calendar", get(elevenlabs::handle_calendar_tool_call))
         .route("/api/call/calendar/create", get(elevenlabs::handle_calendar_event_creation))
+        .route("/api/call/calendar/agenda", get(elevenlabs::handle_calendar_agenda_tool_call))
+        .route("/api/call/daily-briefing", get(elevenlabs::handle_daily_briefing_tool_call))
         .route("/api/call/email", get(elevenlabs::handle_email_fetch_tool_call))
         .route("/api/call/email/specific", post(elevenlabs::handle_email_search_tool_call))
         .route("/api/call/email/respond", post(elevenlabs::handle_respond_to_email))
         .route("/api/call/email/send", post(elevenlabs::handle_email_send))
+        .route("/api/call/email/forward", post(elevenlabs::handle_forward_email))
         .route("/api/call/waiting_check", post(elevenlabs::handle_create_waiting_check_tool_call))
         .route("/api/call/monitoring-status", post(elevenlabs::handle_update_monitoring_status_tool_call))
         .route("/api/call/cancel-message", get(elevenlabs::handle_cancel_pending_message_tool_call))
@@ -303,6 +408,10 @@ async fn main() {
         .route("/api/call/send-chat-message", post(elevenlabs::handle_send_chat_message))
         .route("/api/call/directions", post(elevenlabs::handle_directions_tool_call))
         .route("/api/call/firecrawl", post(elevenlabs::handle_firecrawl_tool_call))
+        .route("/api/call/note", post(elevenlabs::handle_save_note_tool_call))
+        .route("/api/call/notes/recent", get(elevenlabs::handle_recent_notes_tool_call))
+        .route("/api/call/cancel-call", get(elevenlabs::handle_cancel_call_tool_call))
+        .route("/api/call/credits", get(elevenlabs::handle_credits_tool_call))
         .layer(middleware::from_fn_with_state(state.clone(), handlers::auth_middleware::check_subscription_access))
         .route_layer(middleware::from_fn(elevenlabs::validate_elevenlabs_secret));
     let elevenlabs_webhook_routes = Router::new()
@@ -313,7 +422,8 @@ async fn main() {
         .route("/api/auth/google/calendar/callback", get(google_calendar_auth::google_callback))
         .route("/api/auth/google/tasks/callback", get(google_tasks_auth::google_tasks_callback))
         .route("/api/auth/uber/callback", get(uber_auth::uber_callback))
-        .route("/api/auth/tesla/callback", get(tesla_auth::tesla_callback));
+        .route("/api/auth/tesla/callback", get(tesla_auth::tesla_callback))
+        .route("/api/tesla/telemetry", post(tesla_auth::tesla_telemetry_webhook));
     // Public routes that don't need authentication. there's ratelimiting though
     let public_routes = Router::new()
         .route("/api/health", get(health_check))
@@ -325,10 +435,16 @@ async fn main() {
         .route("/api/password-reset/request", post(auth_handlers::request_password_reset))
         .route("/api/password-reset/verify", post(auth_handlers::verify_password_reset))
         .route("/api/phone-verify/request", post(auth_handlers::request_phone_verify))
+        .route("/api/phone-verify/resend", post(auth_handlers::resend_phone_verify))
         .route("/api/phone-verify/verify", post(auth_handlers::verify_phone_verify))
         .route("/api/country-info", post(twilio_handlers::get_country_info))
+        .route("/api/languages", get(profile_handlers::get_supported_languages))
         .route("/api/tier3/check-availability", get(self_host_handlers::check_tier3_availability))
         .route("/api/totp/verify", post(handlers::totp_handlers::verify_login));
+    // Prometheus scrape endpoint, bearer token protected
+    let metrics_routes = Router::new()
+        .route("/metrics", get(utils::metrics::metrics_handler))
+        .route_layer(middleware::from_fn(utils::metrics::require_metrics_token));
     // Admin routes that need admin authentication
     let admin_routes = Router::new()
         .route("/testing", post(auth_handlers::testing_handler))
@@ -337,13 +453,19 @@ async fn main() {
         .route("/api/admin/preferred-number/{user_id}", post(admin_handlers::update_preferred_number_admin))
         .route("/api/admin/broadcast", post(admin_handlers::broadcast_message))
         .route("/api/admin/broadcast-email", post(admin_handlers::broadcast_email))
+        .route("/api/admin/broadcast/retry/{broadcast_id}", post(admin_handlers::retry_broadcast))
+        .route("/api/admin/broadcast/preview", post(admin_handlers::preview_broadcast))
         .route("/api/admin/usage-logs", get(admin_handlers::get_usage_logs))
+        .route("/api/admin/failed-notifications", get(admin_handlers::get_failed_notifications))
+        .route("/api/admin/failed-notifications/{id}/retry", post(admin_handlers::retry_failed_notification))
+        .route("/api/admin/webhooks/status", get(admin_handlers::get_webhook_status))
         .route("/api/admin/subscription/{user_id}/{tier}", post(admin_handlers::update_subscription_tier))
         .route("/api/billing/reset-credits/{user_id}", post(billing_handlers::reset_credits))
         .route("/api/admin/test-sms", post(admin_handlers::test_sms))
         .route("/api/admin/test-sms-with-image", post(admin_handlers::test_sms_with_image))
         .route("/api/admin/monthly-credits/{user_id}/{amount}", post(admin_handlers::update_monthly_credits))
         .route("/api/admin/discount-tier/{user_id}/{tier}", post(admin_handlers::update_discount_tier))
+        .route("/api/admin/credit-policy/{user_id}/{policy}", post(admin_handlers::update_credit_policy))
         .route_layer(middleware::from_fn_with_state(state.clone(), handlers::auth_middleware::require_admin));
     // Protected routes that need user authentication
     let protected_routes = Router::new()
@@ -357,14 +479,27 @@ async fn main() {
         .route("/api/profile/delete/{user_id}", delete(profile_handlers::delete_user))
         .route("/api/profile/update", post(profile_handlers::update_profile))
         .route("/api/profile/field", patch(profile_handlers::patch_profile_field))
+        .route("/api/events/connections", get(profile_handlers::connection_events))
+        .route("/api/profile/cancel-call", post(profile_handlers::cancel_call))
+        .route("/api/profile/notification-preview", post(profile_handlers::notification_preview))
+        .route("/api/profile/resync-status", get(profile_handlers::resync_status))
+        .route("/api/profile/conversation-history", delete(profile_handlers::clear_conversation_history))
+        .route("/api/profile/pause", post(profile_handlers::update_pause))
+        .route("/api/profile/scheduled", get(profile_handlers::list_scheduled_actions))
+        .route("/api/profile/scheduled/{id}", delete(profile_handlers::cancel_scheduled_action))
+        .route("/api/profile/scheduled/sends/{id}", delete(profile_handlers::cancel_scheduled_send))
         .route("/api/profile/server-ip", post(self_host_handlers::update_server_ip))
         .route("/api/profile/magic-link", get(self_host_handlers::get_magic_link))
         .route("/api/profile/twilio-phone", post(self_host_handlers::update_twilio_phone))
         .route("/api/profile/twilio-creds", post(self_host_handlers::update_twilio_creds))
         .route("/api/profile/textbee-creds", post(self_host_handlers::update_textbee_creds))
+        .route("/api/profile/textbee-test", post(self_host_handlers::test_textbee_device))
+        .route("/api/twilio/regulatory-checklist", post(twilio_handlers::get_regulatory_checklist))
         .route("/api/profile/timezone", post(profile_handlers::update_timezone))
         .route("/api/profile/preferred-number", post(profile_handlers::update_preferred_number))
         .route("/api/profile", get(profile_handlers::get_profile))
+        .route("/api/profile/onboarding", get(profile_handlers::get_onboarding_status))
+        .route("/api/contacts", get(handlers::contacts_handlers::get_contacts))
         .route("/api/profile/update-notify/{user_id}", post(profile_handlers::update_notify))
         .route("/api/profile/digests", post(profile_handlers::update_digests))
         .route("/api/profile/digests", get(profile_handlers::get_digests))
@@ -375,6 +510,7 @@ async fn main() {
         .route("/api/profile/get_nearby_places", get(profile_handlers::get_nearby_places))
         .route("/api/billing/increase-credits/{user_id}", post(billing_handlers::increase_credits))
         .route("/api/billing/usage", post(billing_handlers::get_usage_data))
+        .route("/api/billing/estimate", get(billing_handlers::get_credit_estimate))
         .route("/api/billing/update-auto-topup/{user_id}", post(billing_handlers::update_topup))
         .route("/api/stripe/checkout-session/{user_id}", post(stripe_handlers::create_checkout_session))
         .route("/api/stripe/unified-subscription-checkout/{user_id}", post(stripe_handlers::create_unified_subscription_checkout))
@@ -409,6 +545,7 @@ async fn main() {
         .route("/api/auth/imap/login", post(imap_auth::imap_login))
         .route("/api/auth/imap/status", get(imap_auth::imap_status))
         .route("/api/auth/imap/disconnect", delete(imap_auth::delete_imap_connection))
+        .route("/api/imap/folders", get(imap_handlers::fetch_imap_folders))
         .route("/api/imap/previews", get(imap_handlers::fetch_imap_previews))
         .route("/api/imap/message/{email_id}", get(imap_handlers::fetch_single_imap_email))
         .route("/api/imap/full_emails", get(imap_handlers::fetch_full_imap_emails))
@@ -457,6 +594,8 @@ async fn main() {
         // Filter routes
         .route("/api/filters/waiting-checks", get(filter_handlers::get_waiting_checks))
         .route("/api/filters/waiting-check/{service_type}", post(filter_handlers::create_waiting_check))
+        // The segment is the check id, but the router needs the same parameter name as the sibling routes
+        .route("/api/filters/waiting-check/{service_type}/resolve", post(filter_handlers::resolve_waiting_check))
         .route("/api/filters/waiting-check/{service_type}/{content}", delete(filter_handlers::delete_waiting_check))
         .route("/api/filters/monitored-contacts", get(filter_handlers::get_priority_senders))
         .route("/api/filters/monitored-contact/{service_type}", post(filter_handlers::create_priority_sender))
@@ -464,11 +603,16 @@ async fn main() {
         .route("/api/filters/priority-sender/{service_type}", post(filter_handlers::create_priority_sender))
         .route("/api/filters/priority-sender/{service_type}/{sender}", delete(filter_handlers::delete_priority_sender))
         .route("/api/filters/priority-senders/{service_type}", get(filter_handlers::get_priority_senders))
+        .route("/api/filters/keywords/{service_type}", get(filter_handlers::get_keywords))
         .route("/api/filters/keyword/{service_type}", post(filter_handlers::create_keyword))
         .route("/api/filters/keyword/{service_type}/{keyword}", delete(filter_handlers::delete_keyword))
+        .route("/api/filters/room-prefs", get(filter_handlers::get_room_notification_prefs))
+        .route("/api/filters/room-pref", put(filter_handlers::set_room_notification_pref))
         // WhatsApp filter toggle routes
         // Generic filter toggle routes
         .route("/api/profile/email-judgments", get(profile_handlers::get_email_judgments))
+        .route("/api/profile/activity", get(profile_handlers::get_activity_feed))
+        .route("/api/profile/email-judgments/rerun", post(profile_handlers::rerun_email_judgments))
         .route_layer(middleware::from_fn(handlers::auth_middleware::require_auth));
     let self_hosted_public_router = Router::new()
         .route("/verify-token", post(self_host_handlers::verify_token))
@@ -476,6 +620,7 @@ async fn main() {
         .layer(middleware::from_fn_with_state(state.clone(), handlers::auth_middleware::validate_tier3_self_hosted));
     let app = Router::new()
         .merge(public_routes)
+        .merge(metrics_routes)
         .merge(admin_routes)
         .merge(protected_routes)
         .merge(auth_built_in_webhook_routes)
@@ -489,28 +634,14 @@ async fn main() {
         .nest_service("/uploads", ServeDir::new("uploads"))
         .nest("/api/self-hosted", self_hosted_public_router)
         // Serve static files (robots.txt, sitemap.xml) at the root
+        .layer(middleware::from_fn(utils::metrics::track_requests))
         .layer(session_layer)
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
                 .on_response(DefaultOnResponse::new().level(Level::INFO))
         )
-        .layer(
-            CorsLayer::new()
-                .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::OPTIONS, axum::http::Method::DELETE, axum::http::Method::PATCH, axum::http::Method::PUT])
-                .allow_origin(AllowOrigin::exact(std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:8080".to_string()).parse().expect("Invalid FRONTEND_URL"))) // Restrict in production
-                .allow_headers([
-                    axum::http::header::CONTENT_TYPE,
-                    axum::http::header::AUTHORIZATION,
-                    axum::http::header::ACCEPT,
-                    axum::http::header::ORIGIN,
-                ])
-                .expose_headers([
-                    axum::http::header::CONTENT_TYPE,
-                    axum::http::header::CONTENT_LENGTH,
-                ])
-                .allow_credentials(true)
-        )
+        .layer(cors_layer(allowed_origins()))
         // Security headers to prevent clickjacking, XSS, and other attacks
         .layer(SetResponseHeaderLayer::overriding(
             axum::http::header::X_FRAME_OPTIONS,
@@ -569,7 +700,62 @@ async fn main() {
         }
     }
 
+    utils::self_test::run_on_startup(&state).await;
+
     tracing::info!("Starting server on port {}", port);
     let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await.unwrap();
-    axum::serve(listener, app.into_make_service()).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+    // Let queued outbound actions finish before exiting
+    state.job_queue.shutdown(std::time::Duration::from_secs(90)).await;
+}
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to install Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutdown signal received");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    async fn allow_origin_for(origin: &str) -> Option<HeaderValue> {
+        let origins = parse_origins("https://lightfriend.ai, https://www.lightfriend.ai/,https://staging.lightfriend.ai");
+        let app: Router = Router::new().route("/api/ping", get(|| async { "pong" })).layer(cors_layer(origins));
+        let request = axum::http::Request::builder()
+            .uri("/api/ping")
+            .header(axum::http::header::ORIGIN, origin)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        response.headers().get(axum::http::header::ACCESS_CONTROL_ALLOW_ORIGIN).cloned()
+    }
+
+    #[tokio::test]
+    async fn listed_origin_is_echoed() {
+        assert_eq!(allow_origin_for("https://www.lightfriend.ai").await, Some(HeaderValue::from_static("https://www.lightfriend.ai")));
+        assert_eq!(allow_origin_for("https://staging.lightfriend.ai").await, Some(HeaderValue::from_static("https://staging.lightfriend.ai")));
+    }
+
+    #[tokio::test]
+    async fn unlisted_origin_gets_no_cors_headers() {
+        assert_eq!(allow_origin_for("https://evil.example.com").await, None);
+    }
 }
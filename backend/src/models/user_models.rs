@@ -21,6 +21,15 @@ use crate::schema::subaccounts;
 use crate::schema::country_availability;
 use crate::schema::totp_secrets;
 use crate::schema::totp_backup_codes;
+use crate::schema::known_contacts;
+use crate::schema::notified_items;
+use crate::schema::user_notes;
+use crate::schema::room_notification_prefs;
+use crate::schema::failed_notifications;
+use crate::schema::scheduled_sends;
+use crate::schema::broadcasts;
+use crate::schema::broadcast_recipients;
+use crate::schema::credit_spend;
 
 
 
@@ -120,6 +129,7 @@ pub struct ImapConnection {
     pub expires_in: i32,
     pub imap_server: Option<String>,
     pub imap_port: Option<i32>,
+    pub label: Option<String>, // e.g. "work", shown when a user has several accounts
 }
 
 #[derive(Insertable)]
@@ -135,6 +145,25 @@ pub struct NewImapConnection {
     pub expires_in: i32,
     pub imap_server: Option<String>,
     pub imap_port: Option<i32>,
+    pub label: Option<String>,
+}
+
+/// A connected email account with its password decrypted, see `UserRepository::get_imap_accounts`
+#[derive(Clone)]
+pub struct ImapAccount {
+    pub id: i32,
+    pub email: String,
+    pub password: String,
+    pub imap_server: Option<String>,
+    pub imap_port: Option<i32>,
+    pub label: Option<String>,
+}
+
+impl ImapAccount {
+    /// The label if the user gave one, otherwise the address
+    pub fn display_name(&self) -> &str {
+        self.label.as_deref().filter(|label| !label.trim().is_empty()).unwrap_or(&self.email)
+    }
 }
 
 #[derive(Queryable, Selectable, Insertable)]
@@ -166,6 +195,7 @@ pub struct EmailJudgment {
     pub should_notify: bool,
     pub score: i32,
     pub reason: String,
+    pub email_id: Option<String>, // the id emails are fetched by, None on judgments from before it was stored
 }
 
 #[derive(Insertable)]
@@ -177,8 +207,179 @@ pub struct NewEmailJudgment {
     pub should_notify: bool,
     pub score: i32,
     pub reason: String,
+    pub email_id: Option<String>, // the id emails are fetched by, None on judgments from before it was stored
+}
+
+
+/// A bridge room the user has messaged before, used to guard sends to new contacts
+#[derive(Insertable)]
+#[diesel(table_name = known_contacts)]
+pub struct NewKnownContact {
+    pub user_id: i32,
+    pub service: String,
+    pub room_id: String,
+    pub created_at: i32,
+}
+
+/// Profile fields to change, None leaves the stored value alone. Nullable columns are
+/// Option<Option<_>> so Some(None) clears them.
+#[derive(Debug, Default)]
+pub struct ProfileUpdate {
+    pub email: Option<String>,
+    pub phone_number: Option<String>,
+    pub nickname: Option<String>,
+    pub info: Option<String>,
+    pub timezone: Option<String>,
+    pub timezone_auto: Option<bool>,
+    pub notification_type: Option<Option<String>>,
+    pub save_context: Option<Option<i32>>,
+    pub location: Option<String>,
+    pub nearby_places: Option<String>,
+    pub preferred_number: Option<Option<String>>,
+}
+
+/// Last time the user was notified about an item (email uid, bridge event), for deduplication
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = notified_items)]
+pub struct NewNotifiedItem {
+    pub user_id: i32,
+    pub item_key: String,
+    pub content_hash: String,
+    pub notified_at: i32,
+}
+
+/// Per-chat override of the bridge notification filters
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = room_notification_prefs)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RoomNotificationPref {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub platform: String, // whatsapp, telegram, ..
+    pub room_id: String, // matrix room id of the bridged chat
+    pub mode: String, // "always" to notify about every message, "never" to ignore the chat
+    pub noti_type: Option<String>, // "sms" or "call" for "always", sms when None
+    pub updated_at: i32,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = room_notification_prefs, treat_none_as_null = true)]
+pub struct NewRoomNotificationPref {
+    pub user_id: i32,
+    pub platform: String,
+    pub room_id: String,
+    pub mode: String,
+    pub noti_type: Option<String>,
+    pub updated_at: i32,
+}
+
+/// A notification that couldn't be delivered by any channel, kept so support can see and resend it
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = failed_notifications)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FailedNotification {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub notification_type: String, // the usage log activity type, e.g. email_priority_sms
+    pub channel: String, // "sms" or "call"
+    pub encrypted_payload: String,
+    pub error: String,
+    pub created_at: i32,
+    pub retried_at: Option<i32>,
 }
 
+#[derive(Insertable)]
+#[diesel(table_name = failed_notifications)]
+pub struct NewFailedNotification {
+    pub user_id: i32,
+    pub notification_type: String,
+    pub channel: String,
+    pub encrypted_payload: String,
+    pub error: String,
+    pub created_at: i32,
+}
+
+/// An email or chat message the user asked to be sent at a set time
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = scheduled_sends)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ScheduledSend {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub kind: String, // "email" or "chat_message"
+    pub target: String, // who it goes to, for listing
+    pub encrypted_payload: String, // JSON of the send, see utils::scheduled_sends
+    pub send_at: i32,
+    pub status: String, // "scheduled", "sending", "sent", "failed" or "cancelled"
+    pub error: Option<String>,
+    pub created_at: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = scheduled_sends)]
+pub struct NewScheduledSend {
+    pub user_id: i32,
+    pub kind: String,
+    pub target: String,
+    pub encrypted_payload: String,
+    pub send_at: i32,
+    pub status: String,
+    pub created_at: i32,
+}
+
+/// Credits one paid action cost, summed per day and week for the spending caps
+#[derive(Insertable)]
+#[diesel(table_name = credit_spend)]
+pub struct NewCreditSpend {
+    pub user_id: i32,
+    pub amount: f32,
+    pub created_at: i32,
+}
+
+/// An admin broadcast, kept with its per-recipient results so failed sends can be retried
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = broadcasts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Broadcast {
+    pub id: Option<i32>,
+    pub channel: String, // "email"
+    pub subject: String,
+    pub message: String,
+    pub created_at: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = broadcasts)]
+pub struct NewBroadcast {
+    pub channel: String,
+    pub subject: String,
+    pub message: String,
+    pub created_at: i32,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = broadcast_recipients)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BroadcastRecipient {
+    pub id: Option<i32>,
+    pub broadcast_id: i32,
+    pub user_id: i32,
+    pub status: String, // "sent" or "failed"
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub updated_at: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = broadcast_recipients)]
+pub struct NewBroadcastRecipient {
+    pub broadcast_id: i32,
+    pub user_id: i32,
+    pub status: String,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub updated_at: i32,
+}
 
 #[derive(Queryable, Selectable, Insertable, Debug)]
 #[diesel(table_name = bridges)]
@@ -194,6 +395,22 @@ pub struct Bridge {
     pub last_seen_online: Option<i32>,
 }
 
+/// What a user has connected, see `UserRepository::connected_integrations`
+#[derive(Debug, Clone, Default)]
+pub struct ConnectedIntegrations {
+    pub bridges: Vec<String>, // bridge types, e.g. "whatsapp"
+    pub email: bool,
+    pub calendar: bool,
+    pub tasks: bool,
+    pub uber: bool,
+    pub tesla: bool,
+}
+
+impl ConnectedIntegrations {
+    pub fn any(&self) -> bool {
+        !self.bridges.is_empty() || self.email || self.calendar || self.tasks || self.uber || self.tesla
+    }
+}
 
 #[derive(Insertable)]
 #[diesel(table_name = bridges)]
@@ -380,6 +597,8 @@ pub struct WaitingCheck {
     pub content: String,
     pub service_type: String,// like email, whatsapp, .. 
     pub noti_type: Option<String>, // "sms", "call"
+    pub resolved_at: Option<i32>, // set once the check is satisfied, resolved checks are never matched again
+    pub resolved_reason: Option<String>, // "manual" or "auto"
 }
 
 #[derive(Insertable)]
@@ -421,6 +640,7 @@ pub struct Keyword {
     pub user_id: i32,
     pub keyword: String,
     pub service_type: String, // like email, whatsapp, .. 
+    pub action: String, // notify_call, notify_sms, ignore or digest_only
 }
 
 #[derive(Insertable)]
@@ -429,6 +649,7 @@ pub struct NewKeyword {
     pub user_id: i32,
     pub keyword: String,
     pub service_type: String, // like email, whatsapp, .. 
+    pub action: String,
 }
 
 #[derive(Queryable, Selectable, Insertable, Clone)]
@@ -466,6 +687,36 @@ pub struct UserSettings {
     pub monthly_message_count: i32, // for US/CA tier 3 monitoring (threshold at 1000 messages/month)
     pub outbound_message_pricing: Option<f32>, // cached Twilio outbound SMS price for user's country
     pub notify_on_climate_ready: bool, // whether to send notification when Tesla climate reaches target temp
+    pub email_poll_interval_minutes: Option<i32>, // how often (in minutes) to check email for this user, None uses the tier default
+    pub bridge_read_receipts_off: Option<bool>, // fetch bridge messages without marking them read
+    pub onboarding_dismissed: Option<bool>, // user hid the onboarding checklist
+    pub llm_models: Option<String>, // JSON map of purpose -> OpenRouter model id
+    pub call_history_limit: Option<i32>, // past conversations given to voice calls, None = default
+    pub insufficient_credits_policy: Option<String>, // "block", "grace" or "warn", set by admins only, None = deployment default
+    pub call_failure_sms_fallback: Option<bool>, // send the full notification as sms when a notification call fails to start, None = on
+    pub textbee_device_status: Option<String>, // "online" or "offline" from the last textbee reachability check, None = never checked
+    pub textbee_status_checked_at: Option<i32>, // when textbee_device_status was last updated
+    pub call_opening_templates: Option<String>, // json object of notification call openings by category ("email", "message", "calendar"), None = built-in defaults
+    pub units: Option<String>, // "metric" or "imperial", None = metric
+    pub share_location: Option<bool>, // location and nearby places in call context, None = shared
+    pub share_contacts: Option<bool>, // recent chat contacts in call context, None = shared
+    pub share_history: Option<bool>, // recent conversation in call context, None = shared
+    pub max_call_minutes: Option<i32>, // hard cap on a single voice call, None = only the credit balance limits it
+    pub firecrawl_result_count: Option<i32>, // results crawled per web search, None = 5
+    pub firecrawl_include_domains: Option<String>, // comma-separated domains searches are restricted to
+    pub firecrawl_exclude_domains: Option<String>, // comma-separated domains left out of searches
+    pub detect_sms_language: Option<bool>, // reply in the language of the inbound SMS, None = off
+    pub paused_until: Option<i32>, // proactive work and notifications are paused until this timestamp
+    pub pause_allows_critical: Option<bool>, // critical notifications still go through while paused, None = no
+    pub tesla_confirm_commands: Option<String>, // comma-separated Tesla commands queued with a cancel window before running, None = none
+    pub email_business_hours_start: Option<i32>, // hour (0-23, user timezone) queued emails may start going out on weekdays, None = any time
+    pub email_business_hours_end: Option<i32>, // hour (1-24) after which queued emails wait for the next weekday
+    pub email_notification_snippets: Option<bool>, // include the body snippet in email notifications, None = yes
+    pub digest_length: Option<String>, // "brief", "standard" or "detailed", None = standard
+    pub digest_tone: Option<String>, // "neutral", "friendly", "formal" or "playful", None = neutral
+    pub email_idle: Option<bool>, // keep an IMAP IDLE connection open for instant email notifications, None = off
+    pub daily_credit_cap: Option<f32>, // most credits spent per day in the user's timezone, None = no cap
+    pub weekly_credit_cap: Option<f32>, // same per week starting Monday, None = no cap
 }
 
 #[derive(Insertable)]
@@ -537,6 +788,24 @@ pub struct MessageHistory {
     pub tool_calls_json: Option<String>, 
 }
 
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = user_notes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct UserNote {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub encrypted_content: String, // decrypted when read through the repository
+    pub created_at: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = user_notes)]
+pub struct NewUserNote {
+    pub user_id: i32,
+    pub encrypted_content: String,
+    pub created_at: i32,
+}
+
 #[derive(Insertable, Debug)]
 #[diesel(table_name = message_history)]
 pub struct NewMessageHistory {
@@ -5,10 +5,9 @@ use std::sync::Arc;
 use openai_api_rs::v1::{
     chat_completion,
     types,
-    common::GPT4_O,
 };
 use chrono::Timelike;
-use crate::tool_call_utils::utils::create_openai_client;
+use crate::tool_call_utils::utils::{create_openai_client, model_for, chat_completion_with_fallback, ModelPurpose};
 use serde::{Deserialize, Serialize};
 use chrono::{Utc, Duration};
 
@@ -190,14 +189,14 @@ pub async fn check_waiting_check_match(
     }];
 
     let request = chat_completion::ChatCompletionRequest::new(
-        GPT4_O.to_string(),
+        model_for(state, None, ModelPurpose::EmailJudgment),
         messages)
         .tools(tools)
         .tool_choice(chat_completion::ToolChoiceType::Required)
         .temperature(0.0)
         .max_tokens(200);
 
-    let result = client.chat_completion(request).await?;
+    let result = chat_completion_with_fallback(&client, request, ModelPurpose::EmailJudgment).await?;
     let tool_call = result.choices[0]
         .message
         .tool_calls
@@ -220,6 +219,52 @@ pub async fn check_waiting_check_match(
     Ok((response.waiting_check_id, response.sms_message, response.first_message))
 }
 
+/// What a keyword filter does with a message containing the keyword
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeywordAction {
+    // Declared strictest first, see `keyword_action`
+    Ignore,
+    DigestOnly,
+    NotifyCall,
+    NotifySms,
+}
+
+pub const KEYWORD_ACTIONS: [&str; 4] = ["notify_call", "notify_sms", "ignore", "digest_only"];
+
+impl KeywordAction {
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "ignore" => Some(KeywordAction::Ignore),
+            "digest_only" => Some(KeywordAction::DigestOnly),
+            "notify_call" => Some(KeywordAction::NotifyCall),
+            "notify_sms" => Some(KeywordAction::NotifySms),
+            _ => None,
+        }
+    }
+}
+
+/// The action for a message from the user's keywords (case-insensitive substring match). When
+/// several match the strictest wins: ignore, then digest_only, then notify_call, then notify_sms.
+pub fn keyword_action<'a>(
+    keywords: &'a [crate::models::user_models::Keyword],
+    text: &str,
+) -> Option<(KeywordAction, &'a crate::models::user_models::Keyword)> {
+    let text = text.to_lowercase();
+    keywords
+        .iter()
+        .filter(|k| {
+            let keyword = k.keyword.trim().to_lowercase();
+            !keyword.is_empty() && text.contains(&keyword)
+        })
+        .map(|k| (KeywordAction::from_str(&k.action).unwrap_or(KeywordAction::NotifySms), k))
+        .min_by_key(|(action, _)| *action)
+}
+
+/// Keyword filters are stored per service, email ones under "imap" like priority senders
+fn keyword_service_type(platform: &str) -> &str {
+    if platform == "email" { "imap" } else { platform }
+}
+
 #[derive(Debug, Serialize)]
 pub struct DigestData {
     pub messages: Vec<MessageInfo>,
@@ -334,7 +379,7 @@ pub async fn check_message_importance(
         },
     }];
     let request = chat_completion::ChatCompletionRequest::new(
-        GPT4_O.to_string(),
+        model_for(state, Some(user_id), ModelPurpose::EmailJudgment),
         messages)
         .tools(tools)
         .tool_choice(chat_completion::ToolChoiceType::Required)
@@ -342,7 +387,7 @@ pub async fn check_message_importance(
         .temperature(0.2)
         .max_tokens(200);
     // ---------------------------------------------------------------------
-    match client.chat_completion(request).await {
+    match chat_completion_with_fallback(&client, request, ModelPurpose::EmailJudgment).await {
         Ok(result) => {
             if let Some(tool_calls) = result.choices[0].message.tool_calls.as_ref() {
                 if let Some(first_call) = tool_calls.first() {
@@ -500,7 +545,7 @@ pub async fn check_morning_digest(state: &Arc<AppState>, user_id: i32) -> Result
             let mut messages = match state.user_repository.get_imap_credentials(user_id) {
                 Ok(Some(_)) => {
                     // Fetch and filter emails
-                    match crate::handlers::imap_handlers::fetch_emails_imap(state, user_id, false, Some(50), false, true).await {
+                    match crate::handlers::imap_handlers::fetch_emails_imap(state, user_id, false, Some(50), false, true, None).await {
                         Ok(emails) => {
                             emails.into_iter()
                                 .filter(|email| {
@@ -762,7 +807,7 @@ pub async fn check_morning_digest(state: &Arc<AppState>, user_id: i32) -> Result
             };
 
             // Generate the digest
-            let digest_message = match generate_digest(&state, digest_data, priority_map).await {
+            let digest_message = match generate_digest(&state, user_id, digest_data, priority_map).await {
                 Ok(digest) => format!("Good morning! {}",digest),
                 Err(_) => format!(
                     "Good morning! Here's your morning digest covering the last {} hours. Next digest in {} hours.",
@@ -878,7 +923,7 @@ pub async fn check_day_digest(state: &Arc<AppState>, user_id: i32) -> Result<(),
             let mut messages = match state.user_repository.get_imap_credentials(user_id) {
                 Ok(Some(_)) => {
                     // Fetch and filter emails
-                    match crate::handlers::imap_handlers::fetch_emails_imap(state, user_id, false, Some(50), false, true).await {
+                    match crate::handlers::imap_handlers::fetch_emails_imap(state, user_id, false, Some(50), false, true, None).await {
                         Ok(emails) => {
                             emails.into_iter()
                                 .filter(|email| {
@@ -1115,7 +1160,7 @@ pub async fn check_day_digest(state: &Arc<AppState>, user_id: i32) -> Result<(),
             };
 
             // Generate the digest
-            let digest_message = match generate_digest(&state, digest_data, priority_map).await {
+            let digest_message = match generate_digest(&state, user_id, digest_data, priority_map).await {
                 Ok(digest) => format!("Hello! {}",digest),
                 Err(_) => format!(
                     "Hello! Here's your daily digest covering the last {} hours. Next digest in {} hours.",
@@ -1254,7 +1299,7 @@ pub async fn check_evening_digest(state: &Arc<AppState>, user_id: i32) -> Result
             let mut messages = match state.user_repository.get_imap_credentials(user_id) {
                 Ok(Some(_)) => {
                     // Fetch and filter emails
-                    match crate::handlers::imap_handlers::fetch_emails_imap(state, user_id, false, Some(50), false, true).await {
+                    match crate::handlers::imap_handlers::fetch_emails_imap(state, user_id, false, Some(50), false, true, None).await {
                         Ok(emails) => {
                             emails.into_iter()
                                 .filter(|email| {
@@ -1488,7 +1533,7 @@ pub async fn check_evening_digest(state: &Arc<AppState>, user_id: i32) -> Result
             };
 
             // Generate the digest
-            let digest_message = match generate_digest(&state, digest_data, priority_map).await {
+            let digest_message = match generate_digest(&state, user_id, digest_data, priority_map).await {
                 Ok(digest) => format!("Good evening! {}",digest),
                 Err(_) => format!(
                     "Hello! Here's your evening digest covering the last {} hours. Next digest in {} hours.",
@@ -1512,9 +1557,9 @@ pub async fn check_evening_digest(state: &Arc<AppState>, user_id: i32) -> Result
     Ok(())
 }
 
-const DIGEST_PROMPT: &str = r#"You are an AI called lightfriend that creates concise SMS digests of messages and calendar events. Your goal is to help users stay on top of unread messages and upcoming calendar events without needing to open their apps. Group items by platform (e.g., WHATSAPP:, EMAIL:, CALENDAR:), starting each group on a new line. Within each group, provide clear teasers for critical or prioritized items (e.g., sender, topic hint, timestamp in parentheses), separating them with commas or '+' for brevity. Summarize less urgent or grouped items at the end of the group with '+' (e.g., '+ other routine items from xai, claude, ..'). Adjust detail based on overall content: if low volume or mostly low-criticality, expand critical items with fuller, detailed teasers (e.g., key excerpts or actions) to avoid follow-ups. For high volume or non-critical items, use minimal teasers. Highlight critical/actionable items with more specific hints to reduce follow-ups, but avoid full content. Cover all items concisely without omissions.
+const DIGEST_PROMPT: &str = r#"You are an AI called {assistant} that creates concise SMS digests of messages and calendar events. Your goal is to help users stay on top of unread messages and upcoming calendar events without needing to open their apps. Group items by platform (e.g., WHATSAPP:, EMAIL:, CALENDAR:), starting each group on a new line. Within each group, provide clear teasers for critical or prioritized items (e.g., sender, topic hint, timestamp in parentheses), separating them with commas or '+' for brevity. Summarize less urgent or grouped items at the end of the group with '+' (e.g., '+ other routine items from xai, claude, ..'). Adjust detail based on overall content: if low volume or mostly low-criticality, expand critical items with fuller, detailed teasers (e.g., key excerpts or actions) to avoid follow-ups. For high volume or non-critical items, use minimal teasers. Highlight critical/actionable items with more specific hints to reduce follow-ups, but avoid full content. Cover all items concisely without omissions.
 Rules
-• Absolute length limit: 480 characters.
+{style_rules}
 • Do NOT use markdown (no *, **, _, links, or backticks).
 • Do NOT use emojis or emoticons.
 • Plain text only.
@@ -1527,12 +1572,114 @@ Rules
 Return JSON with a single field:
 • `digest` – the plain-text SMS message, with newlines separating groups.
 "#;
+/// Digest lengths users can pick, shortest first
+pub const DIGEST_LENGTHS: [&str; 3] = ["brief", "standard", "detailed"];
+/// Digest tones users can pick
+pub const DIGEST_TONES: [&str; 4] = ["neutral", "friendly", "formal", "playful"];
+
+/// How long and in what voice a user's digest is written, from the digest_length and digest_tone settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigestStyle {
+    pub length: &'static str,
+    pub tone: &'static str,
+}
+
+impl DigestStyle {
+    /// Unset or unknown values fall back to "standard" and "neutral"
+    pub fn from_settings(length: Option<&str>, tone: Option<&str>) -> Self {
+        let pick = |options: &[&'static str], value: Option<&str>, default: &'static str| {
+            value
+                .and_then(|value| options.iter().find(|option| option.eq_ignore_ascii_case(value.trim())))
+                .copied()
+                .unwrap_or(default)
+        };
+        Self {
+            length: pick(&DIGEST_LENGTHS, length, "standard"),
+            tone: pick(&DIGEST_TONES, tone, "neutral"),
+        }
+    }
+
+    /// Characters the digest itself may use. Brief leaves room for the "Good morning! " greeting
+    /// so the whole SMS stays a single segment.
+    pub fn char_limit(&self) -> usize {
+        match self.length {
+            "brief" => 140,
+            "detailed" => 900,
+            _ => 480,
+        }
+    }
+
+    fn max_tokens(&self) -> i64 {
+        match self.length {
+            "brief" => 80,
+            "detailed" => 400,
+            _ => 200,
+        }
+    }
+
+    /// The length and tone rules for the digest prompt
+    fn prompt_rules(&self) -> String {
+        let length_rule = match self.length {
+            "brief" => format!(
+                "• Absolute length limit: {} characters, it must fit in one SMS. Name only priority and critical items and count the rest per platform (e.g., '+4 more').",
+                self.char_limit()
+            ),
+            "detailed" => format!(
+                "• Absolute length limit: {} characters. Give critical and priority items fuller teasers with key details.",
+                self.char_limit()
+            ),
+            _ => format!("• Absolute length limit: {} characters.", self.char_limit()),
+        };
+        let tone_rule = match self.tone {
+            "friendly" => "• Tone: warm and casual, like a friend catching them up.",
+            "formal" => "• Tone: formal and businesslike, no slang.",
+            "playful" => "• Tone: light and playful, but keep the facts clear.",
+            _ => "• Tone: neutral and matter-of-fact.",
+        };
+        format!("{}\n{}", length_rule, tone_rule)
+    }
+}
+
+/// Cuts a digest that ran over its limit at the last word that fits
+fn fit_digest(digest: String, limit: usize) -> String {
+    if digest.chars().count() <= limit {
+        return digest;
+    }
+    let cut: String = digest.chars().take(limit.saturating_sub(3)).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(end) if end > 0 => &cut[..end],
+        _ => cut.as_str(),
+    };
+    format!("{}...", cut.trim_end_matches([',', '+', ' ', '\n']))
+}
+
 pub async fn generate_digest(
     state: &Arc<AppState>,
+    user_id: i32,
     data: DigestData,
     priority_map: HashMap<String, HashSet<String>>,
 ) -> Result<String, Box<dyn std::error::Error>> {
     let client = create_openai_client(&state)?;
+    // Messages the user set a keyword to ignore stay out of the digest too, digest_only ones belong here
+    let mut data = data;
+    let mut keywords_by_platform: HashMap<String, Vec<crate::models::user_models::Keyword>> = HashMap::new();
+    data.messages.retain(|msg| {
+        let keywords = keywords_by_platform.entry(msg.platform.clone()).or_insert_with(|| {
+            state.user_repository.get_keywords(user_id, keyword_service_type(&msg.platform)).unwrap_or_else(|e| {
+                tracing::error!("Failed to get keywords for user {}: {}", user_id, e);
+                Vec::new()
+            })
+        });
+        let text = format!("{} {}", msg.sender, msg.content);
+        !matches!(keyword_action(keywords, &text), Some((KeywordAction::Ignore, _)))
+    });
+    let style = match state.user_core.get_user_settings(user_id) {
+        Ok(settings) => DigestStyle::from_settings(settings.digest_length.as_deref(), settings.digest_tone.as_deref()),
+        Err(e) => {
+            tracing::warn!("Failed to get digest settings for user {}, using defaults: {}", user_id, e);
+            DigestStyle::from_settings(None, None)
+        }
+    };
     // Format messages for the prompt
     let messages_str = data.messages
         .iter()
@@ -1581,7 +1728,9 @@ pub async fn generate_digest(
     let messages = vec![
         chat_completion::ChatCompletionMessage {
             role: chat_completion::MessageRole::system,
-            content: chat_completion::Content::Text(DIGEST_PROMPT.to_string()),
+            content: chat_completion::Content::Text(
+                crate::utils::branding::render(DIGEST_PROMPT).replace("{style_rules}", &style.prompt_rules()),
+            ),
             name: None,
             tool_calls: None,
             tool_call_id: None,
@@ -1620,13 +1769,13 @@ pub async fn generate_digest(
         },
     }];
     let request = chat_completion::ChatCompletionRequest::new(
-        GPT4_O.to_string(),
+        model_for(state, Some(user_id), ModelPurpose::Summarization),
         messages,
     )
     .tools(tools)
     .tool_choice(chat_completion::ToolChoiceType::Required)
-    .max_tokens(200);
-    match client.chat_completion(request).await {
+    .max_tokens(style.max_tokens());
+    match chat_completion_with_fallback(&client, request, ModelPurpose::Summarization).await {
         Ok(result) => {
             if let Some(tool_calls) = result.choices[0].message.tool_calls.as_ref() {
                 if let Some(first_call) = tool_calls.first() {
@@ -1641,7 +1790,7 @@ pub async fn generate_digest(
                                     "Generated digest: {}",
                                     response.digest
                                 );
-                                Ok(response.digest)
+                                Ok(fit_digest(response.digest, style.char_limit()))
                             }
                             Err(e) => {
                                 tracing::error!("Failed to parse digest response: {}", e);
@@ -1668,12 +1817,266 @@ pub async fn generate_digest(
     }
 }
 
+/// Minutes an already notified item stays muted, from NOTIFICATION_DEDUP_WINDOW_MINUTES (default 360)
+pub fn notification_dedup_window_secs() -> i32 {
+    std::env::var("NOTIFICATION_DEDUP_WINDOW_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i32>().ok())
+        .filter(|minutes| *minutes >= 0)
+        .unwrap_or(360)
+        .saturating_mul(60)
+}
+
+/// Whether a notification about `item_key` (e.g. "email:1234") should go out now.
+/// Items notified within the dedup window are suppressed unless their content changed.
+/// Records the notification when it returns true.
+pub fn should_notify_item(state: &Arc<AppState>, user_id: i32, item_key: &str, content: &str) -> bool {
+    let now = Utc::now().timestamp() as i32;
+    should_notify_item_at(state, user_id, item_key, content, now, notification_dedup_window_secs())
+}
+
+fn should_notify_item_at(state: &Arc<AppState>, user_id: i32, item_key: &str, content: &str, now: i32, window_secs: i32) -> bool {
+    use sha2::{Digest, Sha256};
+    let content_hash = format!("{:x}", Sha256::digest(content.trim().as_bytes()));
+    match state.user_repository.get_notified_item(user_id, item_key) {
+        Ok(Some((previous_hash, notified_at))) => {
+            if previous_hash == content_hash && now - notified_at < window_secs {
+                tracing::info!("Suppressing duplicate notification for {} (user {})", item_key, user_id);
+                return false;
+            }
+        }
+        Ok(None) => {}
+        // Better a duplicate than a missed notification
+        Err(e) => tracing::error!("Failed to check notification dedup for user {}: {}", user_id, e),
+    }
+    if let Err(e) = state.user_repository.record_notified_item(user_id, item_key, &content_hash, now) {
+        tracing::error!("Failed to record notified item for user {}: {}", user_id, e);
+    }
+    true
+}
+
+/// Keeps a notification that no channel could deliver, so support can look it up and resend it
+fn record_undelivered_notification(
+    state: &Arc<AppState>,
+    user_id: i32,
+    content_type: &str,
+    channel: &str,
+    notification: &str,
+    error: &str,
+) {
+    if let Err(e) = state.user_repository.record_failed_notification(user_id, content_type, channel, notification, error) {
+        tracing::error!("Failed to record undelivered {} notification for user {}: {}", content_type, user_id, e);
+    }
+}
+
+/// When a notification call can't even be started, send the whole notification as SMS instead so
+/// the content isn't lost. Charged and logged like a regular SMS notification.
+async fn send_call_failure_fallback(
+    state: &Arc<AppState>,
+    user: &crate::models::user_models::User,
+    notification: &str,
+    content_type: &str,
+    current_time: i32,
+) {
+    send_call_failure_fallback_with(state, user, notification, content_type, current_time, |text| async move {
+        crate::api::twilio_utils::send_conversation_message(state, &text, None, user).await
+    }).await
+}
+
+async fn send_call_failure_fallback_with<F, Fut>(
+    state: &Arc<AppState>,
+    user: &crate::models::user_models::User,
+    notification: &str,
+    content_type: &str,
+    current_time: i32,
+    send_sms: F,
+) where
+    F: FnOnce(String) -> Fut,
+    Fut: std::future::Future<Output = Result<String, Box<dyn std::error::Error>>>,
+{
+    if let Err(e) = crate::utils::usage::check_user_credits(state, user, "noti_msg", None).await {
+        tracing::warn!("Skipping SMS fallback for user {}, insufficient credits: {}", user.id, e);
+        return;
+    }
+    let fallback_type = format!("{}_sms_fallback", content_type);
+    match send_sms(notification.to_string()).await {
+        Ok(response_sid) => {
+            tracing::info!("Sent failed call notification as SMS for user {}", user.id);
+            let assistant_notification = crate::models::user_models::NewMessageHistory {
+                user_id: user.id,
+                role: "assistant".to_string(),
+                encrypted_content: notification.to_string(),
+                tool_name: None,
+                tool_call_id: None,
+                tool_calls_json: None,
+                created_at: current_time,
+                conversation_id: "".to_string(),
+            };
+            if let Err(e) = state.user_repository.create_message_history(&assistant_notification) {
+                tracing::error!("Failed to store fallback notification in history: {}", e);
+            }
+            if let Err(e) = state.user_repository.log_usage(
+                user.id,
+                Some(response_sid),
+                fallback_type,
+                None,
+                None,
+                Some(true),
+                Some("Notification call failed to start, sent as SMS".to_string()),
+                Some("delivered".to_string()),
+                None,
+                None,
+            ) {
+                tracing::error!("Failed to log SMS fallback usage: {}", e);
+            }
+            if let Err(e) = crate::utils::usage::deduct_user_credits(state, user.id, "noti_msg", None) {
+                tracing::error!("Failed to deduct credits for user {} after SMS fallback: {}", user.id, e);
+            }
+        }
+        Err(e) => {
+            tracing::error!("SMS fallback for failed call failed too for user {}: {}", user.id, e);
+            record_undelivered_notification(state, user.id, content_type, "sms", notification, &e.to_string());
+            if let Err(log_err) = state.user_repository.log_usage(
+                user.id,
+                None,
+                fallback_type,
+                None,
+                None,
+                Some(false),
+                Some(format!("Failed to send SMS fallback: {}", e)),
+                Some("failed".to_string()),
+                None,
+                None,
+            ) {
+                tracing::error!("Failed to log failed SMS fallback: {}", log_err);
+            }
+        }
+    }
+}
+
+/// `paused_until` of a pause that lasts until the user resumes
+pub const INDEFINITE_PAUSE: i32 = i32::MAX;
+
+/// Whether vacation mode holds back proactive work for the user. With a content type, critical
+/// notifications go through when the user let them. Pausing ends by itself once `paused_until` passes.
+pub fn is_paused(user_settings: &crate::models::user_models::UserSettings, content_type: Option<&str>, now: i32) -> bool {
+    let paused = user_settings.paused_until.map_or(false, |until| until > now);
+    let critical_allowed = content_type.map_or(false, |content_type| content_type.contains("critical"))
+        && user_settings.pause_allows_critical.unwrap_or(false);
+    paused && !critical_allowed
+}
+
+/// SMS text of a priority email notification. Without `include_snippet` it's just the sender and
+/// subject, for users who'd rather read the email itself.
+pub fn email_notification_text(from: Option<&str>, subject: Option<&str>, body: Option<&str>, include_snippet: bool) -> String {
+    let mut text = format!(
+        "Email from: {}\nSubject: {}",
+        from.unwrap_or("Unknown"),
+        subject.unwrap_or("No subject"),
+    );
+    if include_snippet {
+        text.push_str(&format!("\nContent: {}", body.unwrap_or("No content").chars().take(200).collect::<String>()));
+    }
+    text
+}
+
+/// Added to what the critical check reads when the user has snippets off, so the message and
+/// call opening it writes stay at sender and subject
+pub const NO_SNIPPET_INSTRUCTION: &str = "\n(In what_to_inform and first_message mention only the sender and subject of the critical email, never what it says.)";
+
+/// SMS text of a calendar reminder
+pub fn calendar_notification_text(summary: &str, minutes: i32) -> String {
+    format!("Calendar: {} in {} mins", summary, minutes)
+}
+
+const DEFAULT_CALL_OPENING: &str = "Hello, I have a critical notification to tell you about";
+
+/// What a notification would look like, rendered the same way `send_notification_with_context` does
+#[derive(Debug, serde::Serialize)]
+pub struct NotificationPreview {
+    pub channel: String,
+    pub sms: String,
+    pub sms_segments: usize,
+    pub first_message: String,
+}
+
+/// Renders a notification without sending it or touching credits
+pub fn preview_notification(
+    user_settings: &crate::models::user_models::UserSettings,
+    content_type: &str,
+    notification: &str,
+    opening_context: &crate::utils::call_templates::CallOpeningContext,
+) -> NotificationPreview {
+    let first_message = crate::utils::call_templates::first_message(
+        user_settings.call_opening_templates.as_deref(),
+        &user_settings.agent_language,
+        content_type,
+        opening_context,
+    ).unwrap_or_else(|| DEFAULT_CALL_OPENING.to_string());
+    NotificationPreview {
+        channel: notification_channel(user_settings, content_type).to_string(),
+        sms: notification.to_string(),
+        sms_segments: crate::utils::sms_segments::segment_count(notification),
+        first_message,
+    }
+}
+
+/// Whether a notification of `content_type` goes out as a call or an SMS for this user
+pub fn notification_channel<'a>(user_settings: &'a crate::models::user_models::UserSettings, content_type: &str) -> &'a str {
+    if content_type.contains("critical") {
+        user_settings.critical_enabled.as_deref().unwrap_or("sms")
+    } else if content_type.contains("_call") {
+        "call"
+    } else if content_type.contains("_sms") {
+        "sms"
+    } else {
+        user_settings.notification_type.as_deref().unwrap_or("sms")
+    }
+}
+
+/// The user's channel for the notification, except that a call they can't pay a minimal call for goes out as SMS
+fn delivery_channel<'a>(
+    state: &Arc<AppState>,
+    user: &crate::models::user_models::User,
+    user_settings: &'a crate::models::user_models::UserSettings,
+    content_type: &str,
+) -> &'a str {
+    let channel = notification_channel(user_settings, content_type);
+    if channel == "call" && !crate::utils::usage::can_afford_notification_call(state, user, user_settings) {
+        tracing::info!(
+            "User {} can't afford {}s of a notification call (credits {:.2}), sending {} as SMS",
+            user.id, crate::utils::usage::notification_call_min_seconds(), user.credits, content_type
+        );
+        return "sms";
+    }
+    channel
+}
+
 pub async fn send_notification(
     state: &Arc<AppState>,
     user_id: i32,
     notification: &str,
     content_type: String,
     first_message: Option<String>,
+) {
+    send_notification_with_context(
+        state,
+        user_id,
+        notification,
+        content_type,
+        first_message,
+        crate::utils::call_templates::CallOpeningContext::default(),
+    ).await
+}
+
+/// Like `send_notification`, with the details a call opening template can refer to
+pub async fn send_notification_with_context(
+    state: &Arc<AppState>,
+    user_id: i32,
+    notification: &str,
+    content_type: String,
+    first_message: Option<String>,
+    opening_context: crate::utils::call_templates::CallOpeningContext,
 ) {
     // Get current timestamp for message history
     let current_time = std::time::SystemTime::now()
@@ -1710,16 +2113,13 @@ pub async fn send_notification(
         }
     };
 
+    if is_paused(&user_settings, Some(content_type.as_str()), current_time) {
+        tracing::debug!("Skipping {} notification for user {}, notifications are paused", content_type, user_id);
+        return;
+    }
+
     // Check user's notification preference from settings
-    let notification_type = if content_type.contains("critical") {
-        user_settings.critical_enabled.as_deref().unwrap_or("sms")
-    } else if content_type.contains("_call") {
-        "call"
-    } else if content_type.contains("_sms") {
-        "sms"
-    } else {
-        user_settings.notification_type.as_deref().unwrap_or("sms")
-    };
+    let notification_type = delivery_channel(state, &user, &user_settings, &content_type);
 
 
     match notification_type {
@@ -1731,11 +2131,17 @@ pub async fn send_notification(
 
             // Create dynamic variables (optional, can be customized based on needs)
             let dynamic_vars = std::collections::HashMap::new();
+            let first_message = crate::utils::call_templates::first_message(
+                user_settings.call_opening_templates.as_deref(),
+                &user_settings.agent_language,
+                &content_type,
+                &opening_context,
+            ).or(first_message);
 
             match crate::api::elevenlabs::make_notification_call(
                 &state.clone(),
                 content_type.clone(), // Notification type
-                first_message.clone().unwrap_or("Hello, I have a critical notification to tell you about".to_string()),
+                first_message.clone().unwrap_or(DEFAULT_CALL_OPENING.to_string()),
                 notification.to_string(),
                 user.id.to_string(),
                 user_info.timezone,
@@ -1794,7 +2200,7 @@ pub async fn send_notification(
                     if let Err(e) = state.user_repository.log_usage(
                         user_id,
                         None,
-                        content_type,
+                        content_type.clone(),
                         None,
                         None,
                         Some(false),
@@ -1805,6 +2211,12 @@ pub async fn send_notification(
                     ) {
                         tracing::error!("Failed to log failed call notification: {}", e);
                     }
+
+                    if user_settings.call_failure_sms_fallback.unwrap_or(true) {
+                        send_call_failure_fallback(state, &user, notification, &content_type, current_time).await;
+                    } else {
+                        record_undelivered_notification(state, user_id, &content_type, "call", notification, &format!("{:?}", json_err));
+                    }
                 }
             }
         }
@@ -1868,6 +2280,7 @@ pub async fn send_notification(
                 Err(e) => {
                     tracing::error!("Failed to send notification: {}", e);
                     println!("Failed to send SMS notification for user {}", user_id);
+                    record_undelivered_notification(state, user_id, &content_type, "sms", notification, &e.to_string());
                     
                     // Log failed SMS notification
                     if let Err(log_err) = state.user_repository.log_usage(
@@ -1889,3 +2302,129 @@ pub async fn send_notification(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user_models::Keyword;
+
+    fn keyword(word: &str, action: &str) -> Keyword {
+        Keyword {
+            id: None,
+            user_id: 1,
+            keyword: word.to_string(),
+            service_type: "imap".to_string(),
+            action: action.to_string(),
+        }
+    }
+
+    #[test]
+    fn brief_digest_is_shorter_than_detailed_and_fits_one_sms() {
+        // What the model wrote for a busy day, longer than either limit allows
+        let busy_day = (1..=40)
+            .map(|i| format!("WhatsApp from contact {} about plans for the weekend,", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let brief = DigestStyle::from_settings(Some("Brief"), None);
+        let detailed = DigestStyle::from_settings(Some("detailed"), Some("formal"));
+
+        let brief_digest = fit_digest(busy_day.clone(), brief.char_limit());
+        let detailed_digest = fit_digest(busy_day, detailed.char_limit());
+        assert!(brief_digest.chars().count() < detailed_digest.chars().count());
+        assert!(detailed_digest.chars().count() <= 900);
+        assert!(brief_digest.ends_with("...") && !brief_digest.contains("weekend,..."), "{brief_digest}");
+        assert_eq!(crate::utils::sms_segments::segment_count(&format!("Good morning! {}", brief_digest)), 1);
+
+        assert!(brief.prompt_rules().contains("it must fit in one SMS"));
+        assert!(detailed.prompt_rules().contains("Tone: formal"));
+        assert_eq!(DigestStyle::from_settings(Some("epic"), Some("grumpy")), DigestStyle { length: "standard", tone: "neutral" });
+    }
+
+    #[tokio::test]
+    async fn balance_below_the_call_floor_gets_an_sms_instead() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "floor@example.com");
+        state.user_core.update_insufficient_credits_policy(user_id, Some("block".to_string())).unwrap();
+        let settings = state.user_core.get_user_settings(user_id).unwrap();
+
+        // 30 seconds at the US rate of 0.0033 is 0.099 credits
+        for (credits, expected) in [(0.05, "sms"), (0.10, "call"), (10.0, "call")] {
+            state.user_repository.update_user_credits(user_id, credits).unwrap();
+            let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+            assert_eq!(delivery_channel(&state, &user, &settings, "email_priority_call"), expected, "credits {credits}");
+        }
+        // Notifications that were going to be texted anyway stay SMS
+        state.user_repository.update_user_credits(user_id, 10.0).unwrap();
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        assert_eq!(delivery_channel(&state, &user, &settings, "email_priority_sms"), "sms");
+    }
+
+    #[test]
+    fn snippets_off_leaves_out_the_body_only() {
+        let body = "Hi, the contract is attached. Please sign by Friday.";
+        let with_snippet = email_notification_text(Some("Jane <jane@acme.com>"), Some("Contract"), Some(body), true);
+        assert_eq!(with_snippet, format!("Email from: Jane <jane@acme.com>\nSubject: Contract\nContent: {}", body));
+
+        let without = email_notification_text(Some("Jane <jane@acme.com>"), Some("Contract"), Some(body), false);
+        assert_eq!(without, "Email from: Jane <jane@acme.com>\nSubject: Contract");
+        assert!(!without.contains("sign by Friday"));
+    }
+
+    #[test]
+    fn ignore_beats_notify_sms() {
+        let keywords = vec![keyword("invoice", "notify_sms"), keyword("newsletter", "ignore")];
+        let matched = keyword_action(&keywords, "Your monthly NEWSLETTER with this month's invoice");
+        assert_eq!(matched.map(|(action, k)| (action, k.keyword.as_str())), Some((KeywordAction::Ignore, "newsletter")));
+    }
+
+    #[test]
+    fn single_match_keeps_its_action() {
+        let keywords = vec![keyword("invoice", "notify_call"), keyword("newsletter", "ignore")];
+        assert_eq!(keyword_action(&keywords, "Invoice #42 is due").map(|(action, _)| action), Some(KeywordAction::NotifyCall));
+        assert!(keyword_action(&keywords, "Lunch tomorrow?").is_none());
+    }
+
+    #[tokio::test]
+    async fn same_item_is_notified_once_per_window() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "dedup@example.com");
+        let window = 6 * 3600;
+        let notify = |key: &str, content: &str, now: i32| should_notify_item_at(&state, user_id, key, content, now, window);
+
+        assert!(notify("email:4107", "Rent is due", 1_000));
+        assert!(!notify("email:4107", "Rent is due ", 1_000 + 60));
+        assert!(!notify("email:4107", "Rent is due", 1_000 + window - 1));
+        // A different item, or the same one with new content, isn't held back
+        assert!(notify("email:4108", "Rent is due", 1_000 + 60));
+        assert!(notify("email:4107", "Rent is due today", 1_000 + 120));
+
+        // The window restarted with the changed content
+        assert!(!notify("email:4107", "Rent is due today", 1_000 + window));
+        assert!(notify("email:4107", "Rent is due today", 1_000 + 120 + window));
+    }
+
+    #[tokio::test]
+    async fn failed_call_sends_the_whole_notification_as_sms() {
+        use crate::schema::usage_logs;
+        use diesel::prelude::*;
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "fallback@example.com");
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        // Far longer than what a call notification's SMS preview would carry
+        let notification = format!("Critical email from your landlord: {}", "The water will be shut off on Friday. ".repeat(8));
+        let sent = std::sync::Mutex::new(Vec::new());
+
+        send_call_failure_fallback_with(&state, &user, &notification, "critical", 1_000, |text| {
+            sent.lock().unwrap().push(text);
+            async { Ok::<_, Box<dyn std::error::Error>>("SM-fallback".to_string()) }
+        }).await;
+
+        assert_eq!(*sent.lock().unwrap(), vec![notification]);
+        let logged: Vec<(String, Option<String>, Option<bool>)> = usage_logs::table
+            .filter(usage_logs::user_id.eq(user_id))
+            .select((usage_logs::activity_type, usage_logs::sid, usage_logs::success))
+            .load(&mut state.db_pool.get().unwrap())
+            .unwrap();
+        assert_eq!(logged, vec![("critical_sms_fallback".to_string(), Some("SM-fallback".to_string()), Some(true))]);
+    }
+}
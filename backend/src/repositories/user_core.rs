@@ -246,6 +246,28 @@ impl UserCore {
         Ok(users_list)
     }
 
+    /// The users with their settings in two queries, for jobs that go through many users every
+    /// minute. The settings are None for users who have no settings row yet.
+    pub fn get_users_with_settings(&self, user_ids: &[i32]) -> Result<Vec<(User, Option<UserSettings>)>, DieselError> {
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let users_list = users::table
+            .filter(users::id.eq_any(user_ids))
+            .load::<User>(&mut conn)?;
+        let mut settings: std::collections::HashMap<i32, UserSettings> = user_settings::table
+            .filter(user_settings::user_id.eq_any(user_ids))
+            .load::<UserSettings>(&mut conn)?
+            .into_iter()
+            .map(|settings| (settings.user_id, settings))
+            .collect();
+        Ok(users_list
+            .into_iter()
+            .map(|user| {
+                let user_settings = settings.remove(&user.id);
+                (user, user_settings)
+            })
+            .collect())
+    }
+
     pub fn get_users_by_tier(&self, tier: &str) -> Result<Vec<User>, DieselError> {
         let mut conn = self.pool.get().expect("Failed to get DB connection");
         let users_list = users::table
@@ -559,6 +581,239 @@ impl UserCore {
         Ok(())
     }
 
+    pub fn update_email_poll_interval(&self, user_id: i32, interval_minutes: Option<i32>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::email_poll_interval_minutes.eq(interval_minutes))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_bridge_read_receipts_off(&self, user_id: i32, off: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::bridge_read_receipts_off.eq(Some(off)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_onboarding_dismissed(&self, user_id: i32, dismissed: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::onboarding_dismissed.eq(Some(dismissed)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_llm_models(&self, user_id: i32, llm_models: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::llm_models.eq(llm_models))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_call_history_limit(&self, user_id: i32, limit: Option<i32>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::call_history_limit.eq(limit))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_insufficient_credits_policy(&self, user_id: i32, policy: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::insufficient_credits_policy.eq(policy))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_max_call_minutes(&self, user_id: i32, minutes: Option<i32>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::max_call_minutes.eq(minutes))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_units(&self, user_id: i32, units: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::units.eq(units))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_firecrawl_result_count(&self, user_id: i32, count: Option<i32>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::firecrawl_result_count.eq(count))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// `include` picks the allow list, otherwise the block list. Domains are comma-separated.
+    pub fn update_firecrawl_domains(&self, user_id: i32, include: bool, domains: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        let target = user_settings::table.filter(user_settings::user_id.eq(user_id));
+        if include {
+            diesel::update(target).set(user_settings::firecrawl_include_domains.eq(domains)).execute(&mut conn)?;
+        } else {
+            diesel::update(target).set(user_settings::firecrawl_exclude_domains.eq(domains)).execute(&mut conn)?;
+        }
+        Ok(())
+    }
+
+    /// Vacation mode, `paused_until` None resumes right away
+    pub fn update_pause(&self, user_id: i32, paused_until: Option<i32>, allow_critical: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set((
+                user_settings::paused_until.eq(paused_until),
+                user_settings::pause_allows_critical.eq(Some(allow_critical)),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// `hours` is (start, end), None lets queued emails go out at any time
+    pub fn update_email_business_hours(&self, user_id: i32, hours: Option<(i32, i32)>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set((
+                user_settings::email_business_hours_start.eq(hours.map(|(start, _)| start)),
+                user_settings::email_business_hours_end.eq(hours.map(|(_, end)| end)),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_tesla_confirm_commands(&self, user_id: i32, commands: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::tesla_confirm_commands.eq(commands))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_email_notification_snippets(&self, user_id: i32, enabled: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::email_notification_snippets.eq(Some(enabled)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_digest_style(&self, user_id: i32, length: Option<&str>, tone: Option<&str>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set((
+                user_settings::digest_length.eq(length),
+                user_settings::digest_tone.eq(tone),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_email_idle(&self, user_id: i32, enabled: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::email_idle.eq(Some(enabled)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_credit_caps(&self, user_id: i32, daily: Option<f32>, weekly: Option<f32>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set((
+                user_settings::daily_credit_cap.eq(daily),
+                user_settings::weekly_credit_cap.eq(weekly),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_detect_sms_language(&self, user_id: i32, enabled: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::detect_sms_language.eq(Some(enabled)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_call_opening_templates(&self, user_id: i32, templates: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::call_opening_templates.eq(templates))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn update_call_failure_sms_fallback(&self, user_id: i32, enabled: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set(user_settings::call_failure_sms_fallback.eq(Some(enabled)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// `setting` is one of "share_location", "share_contacts" or "share_history"
+    pub fn update_context_sharing(&self, user_id: i32, setting: &str, enabled: bool) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        self.ensure_user_settings_exist(user_id)?;
+        let target = user_settings::table.filter(user_settings::user_id.eq(user_id));
+        match setting {
+            "share_location" => diesel::update(target).set(user_settings::share_location.eq(Some(enabled))).execute(&mut conn)?,
+            "share_contacts" => diesel::update(target).set(user_settings::share_contacts.eq(Some(enabled))).execute(&mut conn)?,
+            "share_history" => diesel::update(target).set(user_settings::share_history.eq(Some(enabled))).execute(&mut conn)?,
+            _ => return Err(DieselError::NotFound),
+        };
+        Ok(())
+    }
+
     pub fn clear_preferred_number(&self, user_id: i32) -> Result<(), DieselError> {
         let mut conn = self.pool.get().expect("Failed to get DB connection");
         diesel::update(users::table.find(user_id))
@@ -576,71 +831,79 @@ impl UserCore {
         Ok(preferred_number)
     }
 
-    // Update user's profile
-    pub fn update_profile(&self, user_id: i32, email: &str, phone_number: &str, nickname: &str, info: &str, timezone: &str, timezone_auto: &bool, notification_type: Option<&str>, save_context: Option<i32>, location: &str, nearby_places: &str, preferred_number: Option<&str>) -> Result<(), DieselError> {
+    /// Applies a partial profile update, columns whose field is None are left as they are.
+    /// Err(NotFound) when the email belongs to someone else, RollbackTransaction for the phone number.
+    pub fn update_profile(&self, user_id: i32, update: &crate::models::user_models::ProfileUpdate) -> Result<(), DieselError> {
         use crate::schema::users;
+        // These take their own connection, so they run before the transaction holds the write lock
+        self.ensure_user_settings_exist(user_id)?;
+        self.ensure_user_info_exists(user_id)?;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
-        println!("Repository: Updating user {} with notification type: {:?}", user_id, notification_type);
-       
-        // Start a transaction
+
         conn.transaction(|conn| {
-            // Check if phone number exists for a different user
-            let existing_phone = users::table
-                .filter(users::phone_number.eq(phone_number))
-                .filter(users::id.ne(user_id))
-                .first::<User>(conn)
-                .optional()?;
-               
-            if existing_phone.is_some() {
-                return Err(DieselError::RollbackTransaction);
-            }
-            // Check if email exists for a different user
-            let existing_email = users::table
-                .filter(users::email.eq(email.to_lowercase()))
-                .filter(users::id.ne(user_id))
-                .first::<User>(conn)
-                .optional()?;
-               
-            if existing_email.is_some() {
-                return Err(DieselError::NotFound);
-            }
-            // Get current user to check if phone number is changing
             let current_user = users::table
                 .find(user_id)
                 .first::<User>(conn)?;
-            // If phone number is changing, set verified to false
-            let should_unverify = current_user.phone_number != phone_number;
-            // Update user table
-            diesel::update(users::table.find(user_id))
-                .set((
-                    users::email.eq(email),
-                    users::phone_number.eq(phone_number),
-                    users::nickname.eq(nickname),
-                    users::verified.eq(!should_unverify && current_user.verified), // Only keep verified true if phone number hasn't changed
-                    users::preferred_number.eq(preferred_number),
-                ))
-                .execute(conn)?;
-            // Ensure user settings exist
-            self.ensure_user_settings_exist(user_id)?;
-            // Ensure user info exists
-            self.ensure_user_info_exists(user_id)?;
-            // Update the settings
-            diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
-                .set((
-                    user_settings::timezone_auto.eq(timezone_auto),
-                    user_settings::notification_type.eq(notification_type.map(|s| s.to_string())),
-                    user_settings::save_context.eq(save_context),
-                ))
-                .execute(conn)?;
-            // Update user info
-            diesel::update(user_info::table.filter(user_info::user_id.eq(user_id)))
-                .set((
-                    user_info::timezone.eq(timezone),
-                    user_info::info.eq(info),
-                    user_info::location.eq(location),
-                    user_info::nearby_places.eq(nearby_places),
-                ))
-                .execute(conn)?;
+            if let Some(phone_number) = &update.phone_number {
+                // Check if phone number exists for a different user
+                let existing_phone = users::table
+                    .filter(users::phone_number.eq(phone_number))
+                    .filter(users::id.ne(user_id))
+                    .first::<User>(conn)
+                    .optional()?;
+                if existing_phone.is_some() {
+                    return Err(DieselError::RollbackTransaction);
+                }
+                // A new number has to be verified again
+                if *phone_number != current_user.phone_number {
+                    diesel::update(users::table.find(user_id))
+                        .set((users::phone_number.eq(phone_number), users::verified.eq(false)))
+                        .execute(conn)?;
+                }
+            }
+            if let Some(email) = &update.email {
+                // Check if email exists for a different user
+                let existing_email = users::table
+                    .filter(users::email.eq(email.to_lowercase()))
+                    .filter(users::id.ne(user_id))
+                    .first::<User>(conn)
+                    .optional()?;
+                if existing_email.is_some() {
+                    return Err(DieselError::NotFound);
+                }
+                diesel::update(users::table.find(user_id)).set(users::email.eq(email)).execute(conn)?;
+            }
+            if let Some(nickname) = &update.nickname {
+                diesel::update(users::table.find(user_id)).set(users::nickname.eq(nickname)).execute(conn)?;
+            }
+            if let Some(preferred_number) = &update.preferred_number {
+                diesel::update(users::table.find(user_id)).set(users::preferred_number.eq(preferred_number)).execute(conn)?;
+            }
+
+            let settings = user_settings::table.filter(user_settings::user_id.eq(user_id));
+            if let Some(timezone_auto) = update.timezone_auto {
+                diesel::update(settings).set(user_settings::timezone_auto.eq(timezone_auto)).execute(conn)?;
+            }
+            if let Some(notification_type) = &update.notification_type {
+                diesel::update(settings).set(user_settings::notification_type.eq(notification_type)).execute(conn)?;
+            }
+            if let Some(save_context) = update.save_context {
+                diesel::update(settings).set(user_settings::save_context.eq(save_context)).execute(conn)?;
+            }
+
+            let info = user_info::table.filter(user_info::user_id.eq(user_id));
+            if let Some(timezone) = &update.timezone {
+                diesel::update(info).set(user_info::timezone.eq(timezone)).execute(conn)?;
+            }
+            if let Some(text) = &update.info {
+                diesel::update(info).set(user_info::info.eq(text)).execute(conn)?;
+            }
+            if let Some(location) = &update.location {
+                diesel::update(info).set(user_info::location.eq(location)).execute(conn)?;
+            }
+            if let Some(nearby_places) = &update.nearby_places {
+                diesel::update(info).set(user_info::nearby_places.eq(nearby_places)).execute(conn)?;
+            }
             Ok(())
         })
     }
@@ -1246,11 +1509,36 @@ impl UserCore {
             .set((
                 user_settings::encrypted_textbee_device_id.eq(encrypted_device_id.clone()),
                 user_settings::encrypted_textbee_api_key.eq(encrypted_api_key.clone()),
+                // New device, the old reachability status doesn't apply
+                user_settings::textbee_device_status.eq(None::<String>),
+                user_settings::textbee_status_checked_at.eq(None::<i32>),
             ))
             .execute(&mut conn)?;
 
         Ok(())
     }
+
+    pub fn update_textbee_status(&self, user_id: i32, status: &str, checked_at: i32) -> Result<(), DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::update(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .set((
+                user_settings::textbee_device_status.eq(Some(status)),
+                user_settings::textbee_status_checked_at.eq(Some(checked_at)),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn get_textbee_user_ids(&self) -> Result<Vec<i32>, DieselError> {
+        use crate::schema::user_settings;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        user_settings::table
+            .filter(user_settings::encrypted_textbee_device_id.is_not_null())
+            .filter(user_settings::encrypted_textbee_api_key.is_not_null())
+            .select(user_settings::user_id)
+            .load::<i32>(&mut conn)
+    }
     pub fn get_elevenlabs_phone_number_id(&self, user_id: i32) -> Result<Option<String>, DieselError> {
         use crate::schema::user_settings;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
@@ -1425,3 +1713,27 @@ impl UserCore {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use crate::schema::user_settings;
+    use crate::test_support::{create_test_user, test_state};
+
+    #[tokio::test]
+    async fn users_without_settings_are_still_returned() {
+        let state = test_state();
+        let with_settings = create_test_user(&state, "with-settings@example.com");
+        let without_settings = create_test_user(&state, "without-settings@example.com");
+        let mut conn = state.db_pool.get().unwrap();
+        diesel::delete(user_settings::table.filter(user_settings::user_id.eq(without_settings)))
+            .execute(&mut conn)
+            .unwrap();
+
+        let users = state.user_core.get_users_with_settings(&[with_settings, without_settings]).unwrap();
+        assert_eq!(users.len(), 2);
+        for (user, settings) in users {
+            assert_eq!(settings.is_some(), user.id == with_settings);
+        }
+    }
+}
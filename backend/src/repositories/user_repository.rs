@@ -24,6 +24,9 @@ use crate::{
     DbPool,
 };
 
+/// Dead letters kept per user, older ones are dropped on insert
+const MAX_FAILED_NOTIFICATIONS_PER_USER: i64 = 50;
+
 pub struct UserRepository {
     pub pool: DbPool
 }
@@ -166,6 +169,14 @@ impl UserRepository {
             .execute(&mut self.pool.get().unwrap())
     }
 
+    /// Deletes every stored conversation turn of the user, returns how many were removed
+    pub fn clear_message_history(&self, user_id: i32) -> Result<usize, DieselError> {
+        use crate::schema::message_history;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::delete(message_history::table.filter(message_history::user_id.eq(user_id)))
+            .execute(&mut conn)
+    }
+
     pub fn delete_old_message_history(&self, user_id: i32, save_context_limit: i64) -> Result<usize, diesel::result::Error> {
         use crate::schema::message_history;
         use diesel::prelude::*;
@@ -210,6 +221,7 @@ impl UserRepository {
         password: &str,
         imap_server: Option<&str>,
         imap_port: Option<u16>,
+        label: Option<&str>,
     ) -> Result<(), diesel::result::Error> {
         use crate::schema::imap_connection;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
@@ -223,57 +235,118 @@ impl UserRepository {
             .unwrap()
             .as_secs() as i32;
 
-        // First, delete any existing connections for this user
-        diesel::delete(imap_connection::table)
-            .filter(imap_connection::user_id.eq(user_id))
-            .execute(&mut conn)?;
-
-        // Create new connection
-        let new_connection = NewImapConnection {
-            user_id,
-            method: imap_server.map(|s| s.to_string()).unwrap_or("gmail".to_string()),
-            encrypted_password,
-            status: "active".to_string(),
-            last_update: current_time,
-            created_on: current_time,
-            description: email.to_string(),
-            expires_in: 0,
-            imap_server: imap_server.map(|s| s.to_string()),
-            imap_port: imap_port.map(|p| p as i32),
-        };
+        let label = label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Logging in again to the same address updates that account in place, so its id and
+            // with it the ids of its emails (see `account_email_id`) stay the same
+            let existing: Option<i32> = imap_connection::table
+                .filter(imap_connection::user_id.eq(user_id))
+                .filter(imap_connection::description.eq(email))
+                .select(imap_connection::id)
+                .order(imap_connection::id.asc())
+                .first::<Option<i32>>(conn)
+                .optional()?
+                .flatten();
+            if let Some(id) = existing {
+                diesel::update(imap_connection::table.filter(imap_connection::id.eq(id)))
+                    .set((
+                        imap_connection::method.eq(imap_server.map(|s| s.to_string()).unwrap_or("gmail".to_string())),
+                        imap_connection::encrypted_password.eq(&encrypted_password),
+                        imap_connection::status.eq("active"),
+                        imap_connection::last_update.eq(current_time),
+                        imap_connection::imap_server.eq(imap_server.map(|s| s.to_string())),
+                        imap_connection::imap_port.eq(imap_port.map(|p| p as i32)),
+                        imap_connection::label.eq(label.clone()),
+                    ))
+                    .execute(conn)?;
+                // Leftover duplicates of the address from before it was updated in place
+                diesel::delete(imap_connection::table)
+                    .filter(imap_connection::user_id.eq(user_id))
+                    .filter(imap_connection::description.eq(email))
+                    .filter(imap_connection::id.ne(id))
+                    .execute(conn)?;
+                return Ok(());
+            }
 
-        // Insert the new connection
-        diesel::insert_into(imap_connection::table)
-            .values(&new_connection)
-            .execute(&mut conn)?;
+            let new_connection = NewImapConnection {
+                user_id,
+                method: imap_server.map(|s| s.to_string()).unwrap_or("gmail".to_string()),
+                encrypted_password: encrypted_password.clone(),
+                status: "active".to_string(),
+                last_update: current_time,
+                created_on: current_time,
+                description: email.to_string(),
+                expires_in: 0,
+                imap_server: imap_server.map(|s| s.to_string()),
+                imap_port: imap_port.map(|p| p as i32),
+                label: label.clone(),
+            };
+            diesel::insert_into(imap_connection::table)
+                .values(&new_connection)
+                .execute(conn)?;
+            Ok(())
+        })?;
 
         Ok(())
     }
     
 
+    /// Credentials of the user's primary (first connected) account
     pub fn get_imap_credentials(
         &self,
         user_id: i32,
     ) -> Result<Option<(String, String, Option<String>, Option<i32>)>, diesel::result::Error> {
+        Ok(self.get_imap_accounts(user_id)?
+            .into_iter()
+            .next()
+            .map(|account| (account.email, account.password, account.imap_server, account.imap_port)))
+    }
+
+    /// The user's active accounts, oldest first so the first one is the primary
+    pub fn get_imap_accounts(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<crate::models::user_models::ImapAccount>, diesel::result::Error> {
         use crate::schema::imap_connection;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
 
-        // Get the active IMAP connection for the user
-        let imap_conn = imap_connection::table
+        let connections = imap_connection::table
             .filter(imap_connection::user_id.eq(user_id))
             .filter(imap_connection::status.eq("active"))
-            .first::<crate::models::user_models::ImapConnection>(&mut conn)
-            .optional()?;
+            .order(imap_connection::id.asc())
+            .load::<crate::models::user_models::ImapConnection>(&mut conn)?;
 
-        if let Some(conn) = imap_conn {
-            // Decrypt the password
-            match decrypt(&conn.encrypted_password) {
-                Ok(decrypted_password) => Ok(Some((conn.description, decrypted_password, conn.imap_server, conn.imap_port))),
-                Err(_) => Err(diesel::result::Error::RollbackTransaction)
-            }
-        } else {
-            Ok(None)
-        }
+        connections
+            .into_iter()
+            .map(|conn| {
+                // Decrypt the password
+                let password = decrypt(&conn.encrypted_password)
+                    .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+                Ok(crate::models::user_models::ImapAccount {
+                    id: conn.id.unwrap_or(0),
+                    email: conn.description,
+                    password,
+                    imap_server: conn.imap_server,
+                    imap_port: conn.imap_port,
+                    label: conn.label,
+                })
+            })
+            .collect()
+    }
+
+    /// Removes one account, returns how many rows went (0 when it isn't the user's)
+    pub fn delete_imap_account(
+        &self,
+        user_id: i32,
+        account_id: i32,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::imap_connection;
+        let connection = &mut self.pool.get().unwrap();
+
+        diesel::delete(imap_connection::table
+            .filter(imap_connection::user_id.eq(user_id))
+            .filter(imap_connection::id.eq(account_id)))
+            .execute(connection)
     }
 
     pub fn delete_imap_credentials(
@@ -447,6 +520,16 @@ impl UserRepository {
         Ok(logs)
     }
 
+    /// The user's newest usage logs first
+    pub fn get_recent_usage_logs(&self, user_id: i32, limit: i64) -> Result<Vec<crate::models::user_models::UsageLog>, DieselError> {
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        usage_logs::table
+            .filter(usage_logs::user_id.eq(user_id))
+            .order_by((usage_logs::created_at.desc(), usage_logs::id.desc()))
+            .limit(limit)
+            .load::<crate::models::user_models::UsageLog>(&mut conn)
+    }
+
     pub fn has_recent_notification(&self, user_id: i32, activity_type: &str, seconds_ago: i32) -> Result<bool, DieselError> {
         use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -514,12 +597,26 @@ impl UserRepository {
         Ok(())
     }
 
-    pub fn delete_waiting_check_by_id(&self, user_id: i32, id: i32) -> Result<(), DieselError> {
+    /// Marks the check satisfied so it's no longer matched, keeping the row for history.
+    /// `reason` is "manual" when the user resolved it and "auto" when an incoming message matched it.
+    pub fn resolve_waiting_check(&self, user_id: i32, id: i32, reason: &str) -> Result<(), DieselError> {
         let mut conn = self.pool.get().expect("Failed to get DB connection");
-        diesel::delete(waiting_checks::table)
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        let updated = diesel::update(waiting_checks::table)
             .filter(waiting_checks::user_id.eq(user_id))
             .filter(waiting_checks::id.eq(id))
+            .filter(waiting_checks::resolved_at.is_null())
+            .set((
+                waiting_checks::resolved_at.eq(Some(now)),
+                waiting_checks::resolved_reason.eq(Some(reason)),
+            ))
             .execute(&mut conn)?;
+        if updated == 0 {
+            return Err(DieselError::NotFound);
+        }
         Ok(())
     }
 
@@ -547,16 +644,19 @@ impl UserRepository {
         if service_type == "email" {
             waiting_checks::table
                 .filter(waiting_checks::user_id.eq(user_id))
+                .filter(waiting_checks::resolved_at.is_null())
                 .filter(waiting_checks::service_type.eq("imap").or(waiting_checks::service_type.eq("email")))
                 .load::<WaitingCheck>(&mut conn)
         } else if service_type == "messaging" {
             waiting_checks::table
                 .filter(waiting_checks::user_id.eq(user_id))
+                .filter(waiting_checks::resolved_at.is_null())
                 .filter(waiting_checks::service_type.eq("messaging").or(waiting_checks::service_type.eq("whatsapp")))
                 .load::<WaitingCheck>(&mut conn)
         } else {
             waiting_checks::table
                 .filter(waiting_checks::user_id.eq(user_id))
+                .filter(waiting_checks::resolved_at.is_null())
                 .filter(waiting_checks::service_type.eq(service_type))
                 .load::<WaitingCheck>(&mut conn)
         }
@@ -722,6 +822,37 @@ impl UserRepository {
         Ok(())
     }
 
+    /// Stores the refresh token Google returned in place of the old one when it rotates them
+    pub fn update_google_calendar_refresh_token(
+        &self,
+        user_id: i32,
+        new_refresh_token: &str,
+    ) -> Result<(), DieselError> {
+        use crate::schema::google_calendar;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let encrypted_refresh_token = encrypt(new_refresh_token)
+            .map_err(|_| DieselError::RollbackTransaction)?;
+        diesel::update(google_calendar::table)
+            .filter(google_calendar::user_id.eq(user_id))
+            .filter(google_calendar::status.eq("active"))
+            .set(google_calendar::encrypted_refresh_token.eq(encrypted_refresh_token))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// (last_update, expires_in) of the active calendar access token
+    pub fn get_google_calendar_token_expiry(&self, user_id: i32) -> Result<Option<(i32, i32)>, DieselError> {
+        use crate::schema::google_calendar;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+
+        google_calendar::table
+            .filter(google_calendar::user_id.eq(user_id))
+            .filter(google_calendar::status.eq("active"))
+            .select((google_calendar::last_update, google_calendar::expires_in))
+            .first::<(i32, i32)>(&mut conn)
+            .optional()
+    }
+
     pub fn delete_google_calendar_connection(&self, user_id: i32) -> Result<(), DieselError> {
         use crate::schema::google_calendar;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
@@ -740,6 +871,7 @@ impl UserRepository {
         let user_ids = imap_connection::table
             .filter(imap_connection::status.eq("active"))
             .select(imap_connection::user_id)
+            .distinct()
             .load::<i32>(&mut conn)?;
 
         Ok(user_ids)
@@ -1076,6 +1208,20 @@ impl UserRepository {
         Ok(vehicle_vin)
     }
 
+    /// Whether some active Tesla connection has this vehicle selected
+    pub fn is_selected_vehicle_vin(&self, vin: &str) -> Result<bool, DieselError> {
+        use crate::schema::tesla;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+
+        let count = tesla::table
+            .filter(tesla::selected_vehicle_vin.eq(vin))
+            .filter(tesla::status.eq("active"))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+
+        Ok(count > 0)
+    }
+
     pub fn set_selected_vehicle(
         &self,
         user_id: i32,
@@ -1177,6 +1323,24 @@ impl UserRepository {
         }
     }
 
+    /// Stores the refresh token Google returned in place of the old one when it rotates them
+    pub fn update_google_tasks_refresh_token(
+        &self,
+        user_id: i32,
+        new_refresh_token: &str,
+    ) -> Result<(), DieselError> {
+        use crate::schema::google_tasks;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let encrypted_refresh_token = encrypt(new_refresh_token)
+            .map_err(|_| DieselError::RollbackTransaction)?;
+        diesel::update(google_tasks::table)
+            .filter(google_tasks::user_id.eq(user_id))
+            .filter(google_tasks::status.eq("active"))
+            .set(google_tasks::encrypted_refresh_token.eq(encrypted_refresh_token))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
     pub fn update_google_tasks_access_token(
         &self,
         user_id: i32,
@@ -1207,6 +1371,19 @@ impl UserRepository {
         Ok(())
     }
 
+    /// (last_update, expires_in) of the active tasks access token
+    pub fn get_google_tasks_token_expiry(&self, user_id: i32) -> Result<Option<(i32, i32)>, DieselError> {
+        use crate::schema::google_tasks;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+
+        google_tasks::table
+            .filter(google_tasks::user_id.eq(user_id))
+            .filter(google_tasks::status.eq("active"))
+            .select((google_tasks::last_update, google_tasks::expires_in))
+            .first::<(i32, i32)>(&mut conn)
+            .optional()
+    }
+
     pub fn delete_google_tasks_connection(&self, user_id: i32) -> Result<(), DieselError> {
         use crate::schema::google_tasks;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
@@ -1368,6 +1545,484 @@ impl UserRepository {
         Ok(bridge)
     }
 
+    pub fn is_known_contact(&self, user_id: i32, service: &str, room_id: &str) -> Result<bool, DieselError> {
+        use crate::schema::known_contacts;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let count = known_contacts::table
+            .filter(known_contacts::user_id.eq(user_id))
+            .filter(known_contacts::service.eq(service))
+            .filter(known_contacts::room_id.eq(room_id))
+            .count()
+            .get_result::<i64>(&mut conn)?;
+        Ok(count > 0)
+    }
+
+    pub fn add_known_contact(&self, user_id: i32, service: &str, room_id: &str) -> Result<(), DieselError> {
+        use crate::schema::known_contacts;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let new_contact = crate::models::user_models::NewKnownContact {
+            user_id,
+            service: service.to_string(),
+            room_id: room_id.to_string(),
+            created_at: chrono::Utc::now().timestamp() as i32,
+        };
+        diesel::insert_or_ignore_into(known_contacts::table)
+            .values(&new_contact)
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// (content hash, notified_at) of the last notification about this item, if any
+    pub fn get_notified_item(&self, user_id: i32, item_key: &str) -> Result<Option<(String, i32)>, DieselError> {
+        use crate::schema::notified_items;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        notified_items::table
+            .filter(notified_items::user_id.eq(user_id))
+            .filter(notified_items::item_key.eq(item_key))
+            .select((notified_items::content_hash, notified_items::notified_at))
+            .first::<(String, i32)>(&mut conn)
+            .optional()
+    }
+
+    pub fn record_notified_item(&self, user_id: i32, item_key: &str, content_hash: &str, notified_at: i32) -> Result<(), DieselError> {
+        use crate::schema::notified_items;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let item = crate::models::user_models::NewNotifiedItem {
+            user_id,
+            item_key: item_key.to_string(),
+            content_hash: content_hash.to_string(),
+            notified_at,
+        };
+        diesel::insert_into(notified_items::table)
+            .values(&item)
+            .on_conflict((notified_items::user_id, notified_items::item_key))
+            .do_update()
+            .set(&item)
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Matrix room ids are unique across platforms, so the room id alone identifies the chat
+    pub fn get_room_notification_pref(&self, user_id: i32, room_id: &str) -> Result<Option<crate::models::user_models::RoomNotificationPref>, DieselError> {
+        use crate::schema::room_notification_prefs;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        room_notification_prefs::table
+            .filter(room_notification_prefs::user_id.eq(user_id))
+            .filter(room_notification_prefs::room_id.eq(room_id))
+            .first::<crate::models::user_models::RoomNotificationPref>(&mut conn)
+            .optional()
+    }
+
+    /// Records a notification that couldn't be delivered. Only the newest MAX_FAILED_NOTIFICATIONS_PER_USER
+    /// are kept per user so a persistently broken number can't grow the table forever.
+    pub fn record_failed_notification(&self, user_id: i32, notification_type: &str, channel: &str, payload: &str, error: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        use crate::schema::failed_notifications;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        let new_failure = crate::models::user_models::NewFailedNotification {
+            user_id,
+            notification_type: notification_type.to_string(),
+            channel: channel.to_string(),
+            encrypted_payload: encrypt(payload)?,
+            error: error.to_string(),
+            created_at: now,
+        };
+        diesel::insert_into(failed_notifications::table)
+            .values(&new_failure)
+            .execute(&mut conn)?;
+
+        let keep: Vec<Option<i32>> = failed_notifications::table
+            .filter(failed_notifications::user_id.eq(user_id))
+            .order(failed_notifications::id.desc())
+            .limit(MAX_FAILED_NOTIFICATIONS_PER_USER)
+            .select(failed_notifications::id)
+            .load(&mut conn)?;
+        diesel::delete(failed_notifications::table)
+            .filter(failed_notifications::user_id.eq(user_id))
+            .filter(failed_notifications::id.ne_all(keep))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Newest first, optionally only for one user
+    pub fn get_failed_notifications(&self, user_id: Option<i32>, limit: i64) -> Result<Vec<crate::models::user_models::FailedNotification>, DieselError> {
+        use crate::schema::failed_notifications;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let mut query = failed_notifications::table
+            .order(failed_notifications::id.desc())
+            .limit(limit)
+            .into_boxed();
+        if let Some(user_id) = user_id {
+            query = query.filter(failed_notifications::user_id.eq(user_id));
+        }
+        query.load::<crate::models::user_models::FailedNotification>(&mut conn)
+    }
+
+    pub fn get_failed_notification(&self, id: i32) -> Result<Option<crate::models::user_models::FailedNotification>, DieselError> {
+        use crate::schema::failed_notifications;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        failed_notifications::table
+            .find(id)
+            .first::<crate::models::user_models::FailedNotification>(&mut conn)
+            .optional()
+    }
+
+    pub fn mark_failed_notification_retried(&self, id: i32, retried_at: i32) -> Result<(), DieselError> {
+        use crate::schema::failed_notifications;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::update(failed_notifications::table.find(id))
+            .set(failed_notifications::retried_at.eq(Some(retried_at)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Stores a send for later with its payload encrypted, returns the id
+    pub fn create_scheduled_send(&self, user_id: i32, kind: &str, target: &str, payload: &str, send_at: i32) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
+        use crate::schema::scheduled_sends;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        let new_send = crate::models::user_models::NewScheduledSend {
+            user_id,
+            kind: kind.to_string(),
+            target: target.to_string(),
+            encrypted_payload: encrypt(payload)?,
+            send_at,
+            status: "scheduled".to_string(),
+            created_at: now,
+        };
+        // last_insert_rowid is per connection, so it's our row even with other inserts going on
+        let id = conn.transaction(|conn| {
+            diesel::insert_into(scheduled_sends::table)
+                .values(&new_send)
+                .execute(conn)?;
+            diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("last_insert_rowid()"))
+                .get_result::<i32>(conn)
+        })?;
+        Ok(id)
+    }
+
+    /// Sends a previous run left "sending", e.g. because it was shut down mid-pass. They may or may
+    /// not have gone out, so they're marked failed rather than sent again. Returns them for telling the users.
+    pub fn fail_interrupted_scheduled_sends(&self, error: &str) -> Result<Vec<crate::models::user_models::ScheduledSend>, DieselError> {
+        use crate::schema::scheduled_sends;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        conn.transaction(|conn| {
+            let stuck = scheduled_sends::table
+                .filter(scheduled_sends::status.eq("sending"))
+                .load::<crate::models::user_models::ScheduledSend>(conn)?;
+            diesel::update(scheduled_sends::table.filter(scheduled_sends::status.eq("sending")))
+                .set((
+                    scheduled_sends::status.eq("failed"),
+                    scheduled_sends::error.eq(error),
+                ))
+                .execute(conn)?;
+            Ok(stuck)
+        })
+    }
+
+    /// Sends that are due, marked "sending" so a slow pass and the next one can't both pick them up
+    pub fn claim_due_scheduled_sends(&self, now: i32) -> Result<Vec<crate::models::user_models::ScheduledSend>, DieselError> {
+        use crate::schema::scheduled_sends;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        conn.transaction(|conn| {
+            let due = scheduled_sends::table
+                .filter(scheduled_sends::status.eq("scheduled"))
+                .filter(scheduled_sends::send_at.le(now))
+                .order(scheduled_sends::send_at.asc())
+                .load::<crate::models::user_models::ScheduledSend>(conn)?;
+            let ids: Vec<Option<i32>> = due.iter().map(|send| send.id).collect();
+            diesel::update(scheduled_sends::table.filter(scheduled_sends::id.eq_any(ids)))
+                .set(scheduled_sends::status.eq("sending"))
+                .execute(conn)?;
+            Ok(due)
+        })
+    }
+
+    pub fn finish_scheduled_send(&self, id: i32, status: &str, error: Option<&str>) -> Result<(), DieselError> {
+        use crate::schema::scheduled_sends;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::update(scheduled_sends::table.find(id))
+            .set((
+                scheduled_sends::status.eq(status),
+                scheduled_sends::error.eq(error),
+            ))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// A user's sends still waiting for their time, soonest first
+    pub fn get_upcoming_scheduled_sends(&self, user_id: i32) -> Result<Vec<crate::models::user_models::ScheduledSend>, DieselError> {
+        use crate::schema::scheduled_sends;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        scheduled_sends::table
+            .filter(scheduled_sends::user_id.eq(user_id))
+            .filter(scheduled_sends::status.eq("scheduled"))
+            .order(scheduled_sends::send_at.asc())
+            .load::<crate::models::user_models::ScheduledSend>(&mut conn)
+    }
+
+    /// Cancels one of the user's sends, or all of them with None. Returns how many were cancelled,
+    /// sends already going out aren't touched.
+    pub fn cancel_scheduled_sends(&self, user_id: i32, id: Option<i32>) -> Result<usize, DieselError> {
+        use crate::schema::scheduled_sends;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let waiting = scheduled_sends::table
+            .filter(scheduled_sends::user_id.eq(user_id))
+            .filter(scheduled_sends::status.eq("scheduled"));
+        match id {
+            Some(id) => diesel::update(waiting.filter(scheduled_sends::id.eq(id)))
+                .set(scheduled_sends::status.eq("cancelled"))
+                .execute(&mut conn),
+            None => diesel::update(waiting)
+                .set(scheduled_sends::status.eq("cancelled"))
+                .execute(&mut conn),
+        }
+    }
+
+    /// Records what a paid action cost. Only the last week is ever summed, so older rows are dropped.
+    pub fn record_credit_spend(&self, user_id: i32, amount: f32, now: i32) -> Result<(), DieselError> {
+        use crate::schema::credit_spend;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::insert_into(credit_spend::table)
+            .values(&crate::models::user_models::NewCreditSpend { user_id, amount, created_at: now })
+            .execute(&mut conn)?;
+        diesel::delete(credit_spend::table
+            .filter(credit_spend::user_id.eq(user_id))
+            .filter(credit_spend::created_at.lt(now - 8 * 24 * 3600)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    /// Credits the user has spent since the timestamp
+    pub fn get_credit_spend_since(&self, user_id: i32, since: i32) -> Result<f32, DieselError> {
+        use crate::schema::credit_spend;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let total: Option<f32> = credit_spend::table
+            .filter(credit_spend::user_id.eq(user_id))
+            .filter(credit_spend::created_at.ge(since))
+            .select(diesel::dsl::sum(credit_spend::amount))
+            .first(&mut conn)?;
+        Ok(total.unwrap_or(0.0))
+    }
+
+    /// Stores a new broadcast and returns its id
+    pub fn create_broadcast(&self, channel: &str, subject: &str, message: &str) -> Result<i32, DieselError> {
+        use crate::schema::broadcasts;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        conn.transaction(|conn| {
+            diesel::insert_into(broadcasts::table)
+                .values(&crate::models::user_models::NewBroadcast {
+                    channel: channel.to_string(),
+                    subject: subject.to_string(),
+                    message: message.to_string(),
+                    created_at: now,
+                })
+                .execute(conn)?;
+            broadcasts::table
+                .order(broadcasts::id.desc())
+                .select(broadcasts::id)
+                .first::<Option<i32>>(conn)?
+                .ok_or(DieselError::NotFound)
+        })
+    }
+
+    pub fn get_broadcast(&self, id: i32) -> Result<Option<crate::models::user_models::Broadcast>, DieselError> {
+        use crate::schema::broadcasts;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        broadcasts::table
+            .find(id)
+            .first::<crate::models::user_models::Broadcast>(&mut conn)
+            .optional()
+    }
+
+    /// Records how sending a broadcast to one user went, a retry updates the existing row
+    pub fn record_broadcast_result(&self, broadcast_id: i32, user_id: i32, error: Option<String>) -> Result<(), DieselError> {
+        use crate::schema::broadcast_recipients;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        let status = if error.is_none() { "sent" } else { "failed" };
+        let existing = broadcast_recipients::table
+            .filter(broadcast_recipients::broadcast_id.eq(broadcast_id))
+            .filter(broadcast_recipients::user_id.eq(user_id))
+            .first::<crate::models::user_models::BroadcastRecipient>(&mut conn)
+            .optional()?;
+        match existing {
+            Some(recipient) => {
+                diesel::update(broadcast_recipients::table.filter(broadcast_recipients::id.eq(recipient.id)))
+                    .set((
+                        broadcast_recipients::status.eq(status),
+                        broadcast_recipients::error.eq(error),
+                        broadcast_recipients::attempts.eq(recipient.attempts + 1),
+                        broadcast_recipients::updated_at.eq(now),
+                    ))
+                    .execute(&mut conn)?;
+            }
+            None => {
+                diesel::insert_into(broadcast_recipients::table)
+                    .values(&crate::models::user_models::NewBroadcastRecipient {
+                        broadcast_id,
+                        user_id,
+                        status: status.to_string(),
+                        error,
+                        attempts: 1,
+                        updated_at: now,
+                    })
+                    .execute(&mut conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Claims the users the broadcast failed for by moving them to "retrying", so a concurrent
+    /// retry can't pick the same rows. Rows left "retrying" by a retry that died are claimable
+    /// again after an hour.
+    pub fn claim_failed_broadcast_recipients(&self, broadcast_id: i32) -> Result<Vec<i32>, DieselError> {
+        use crate::schema::broadcast_recipients;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        let claimable = broadcast_recipients::status.eq("failed")
+            .or(broadcast_recipients::status.eq("retrying").and(broadcast_recipients::updated_at.lt(now - 3600)));
+        // IMMEDIATE takes the write lock up front, the select and update can't interleave with another claim
+        conn.immediate_transaction(|conn| {
+            let user_ids = broadcast_recipients::table
+                .filter(broadcast_recipients::broadcast_id.eq(broadcast_id))
+                .filter(claimable)
+                .select(broadcast_recipients::user_id)
+                .load::<i32>(conn)?;
+            diesel::update(broadcast_recipients::table
+                .filter(broadcast_recipients::broadcast_id.eq(broadcast_id))
+                .filter(broadcast_recipients::user_id.eq_any(&user_ids)))
+                .set((
+                    broadcast_recipients::status.eq("retrying"),
+                    broadcast_recipients::updated_at.eq(now),
+                ))
+                .execute(conn)?;
+            Ok(user_ids)
+        })
+    }
+
+    /// Marks a claimed recipient that won't be sent to (notifications off, bad address or deleted user)
+    pub fn skip_broadcast_recipient(&self, broadcast_id: i32, user_id: i32) -> Result<(), DieselError> {
+        use crate::schema::broadcast_recipients;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::update(broadcast_recipients::table
+            .filter(broadcast_recipients::broadcast_id.eq(broadcast_id))
+            .filter(broadcast_recipients::user_id.eq(user_id))
+            .filter(broadcast_recipients::status.eq("retrying")))
+            .set(broadcast_recipients::status.eq("skipped"))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn get_room_notification_prefs(&self, user_id: i32) -> Result<Vec<crate::models::user_models::RoomNotificationPref>, DieselError> {
+        use crate::schema::room_notification_prefs;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        room_notification_prefs::table
+            .filter(room_notification_prefs::user_id.eq(user_id))
+            .order(room_notification_prefs::updated_at.desc())
+            .load::<crate::models::user_models::RoomNotificationPref>(&mut conn)
+    }
+
+    /// Creates or replaces the override for the chat
+    pub fn set_room_notification_pref(&self, pref: &crate::models::user_models::NewRoomNotificationPref) -> Result<(), DieselError> {
+        use crate::schema::room_notification_prefs;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::insert_into(room_notification_prefs::table)
+            .values(pref)
+            .on_conflict((room_notification_prefs::user_id, room_notification_prefs::platform, room_notification_prefs::room_id))
+            .do_update()
+            .set(pref)
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    pub fn delete_room_notification_pref(&self, user_id: i32, platform: &str, room_id: &str) -> Result<usize, DieselError> {
+        use crate::schema::room_notification_prefs;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::delete(room_notification_prefs::table
+            .filter(room_notification_prefs::user_id.eq(user_id))
+            .filter(room_notification_prefs::platform.eq(platform))
+            .filter(room_notification_prefs::room_id.eq(room_id)))
+            .execute(&mut conn)
+    }
+
+    /// Drops dedup records older than `older_than` so the table doesn't grow forever
+    pub fn delete_old_notified_items(&self, older_than: i32) -> Result<usize, DieselError> {
+        use crate::schema::notified_items;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        diesel::delete(notified_items::table.filter(notified_items::notified_at.lt(older_than)))
+            .execute(&mut conn)
+    }
+
+    /// Saves an encrypted note and drops the oldest ones beyond `max_notes`
+    pub fn add_user_note(&self, user_id: i32, content: &str, max_notes: i64) -> Result<(), DieselError> {
+        use crate::schema::user_notes;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let encrypted_content = encrypt(content).map_err(|_| DieselError::RollbackTransaction)?;
+        let new_note = crate::models::user_models::NewUserNote {
+            user_id,
+            encrypted_content,
+            created_at: chrono::Utc::now().timestamp() as i32,
+        };
+        conn.transaction(|conn| {
+            diesel::insert_into(user_notes::table)
+                .values(&new_note)
+                .execute(conn)?;
+            let stale_ids: Vec<Option<i32>> = user_notes::table
+                .filter(user_notes::user_id.eq(user_id))
+                .order_by((user_notes::created_at.desc(), user_notes::id.desc()))
+                .offset(max_notes)
+                .select(user_notes::id)
+                .load(conn)?;
+            let stale_ids: Vec<i32> = stale_ids.into_iter().flatten().collect();
+            if !stale_ids.is_empty() {
+                diesel::delete(user_notes::table.filter(user_notes::id.eq_any(stale_ids)))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Most recent notes first, with the content decrypted
+    pub fn get_recent_user_notes(&self, user_id: i32, limit: i64) -> Result<Vec<crate::models::user_models::UserNote>, DieselError> {
+        use crate::schema::user_notes;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+        let notes = user_notes::table
+            .filter(user_notes::user_id.eq(user_id))
+            .order_by((user_notes::created_at.desc(), user_notes::id.desc()))
+            .limit(limit)
+            .load::<crate::models::user_models::UserNote>(&mut conn)?;
+        Ok(notes
+            .into_iter()
+            .filter_map(|mut note| match decrypt(&note.encrypted_content) {
+                Ok(content) => {
+                    note.encrypted_content = content;
+                    Some(note)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to decrypt note {:?}: {:?}", note.id, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
     pub fn has_active_bridges(&self, user_id: i32) -> Result<bool, DieselError> {
         use crate::schema::bridges;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
@@ -1379,6 +2034,33 @@ impl UserRepository {
         Ok(count > 0)
     }
 
+    /// True if the user has any bridge, email, calendar or other service connected
+    pub fn has_any_integration(&self, user_id: i32) -> Result<bool, DieselError> {
+        Ok(self.connected_integrations(user_id)?.any())
+    }
+
+    /// Every service the user has connected
+    pub fn connected_integrations(&self, user_id: i32) -> Result<crate::models::user_models::ConnectedIntegrations, DieselError> {
+        use crate::schema::bridges;
+        // The connection goes back to the pool before the other checks take theirs
+        let connected_bridges = {
+            let mut conn = self.pool.get().expect("Failed to get DB connection");
+            bridges::table
+                .filter(bridges::user_id.eq(user_id))
+                .filter(bridges::status.eq("connected"))
+                .select(bridges::bridge_type)
+                .load::<String>(&mut conn)?
+        };
+        Ok(crate::models::user_models::ConnectedIntegrations {
+            bridges: connected_bridges,
+            email: self.get_imap_credentials(user_id)?.is_some(),
+            calendar: self.has_active_google_calendar(user_id)?,
+            tasks: self.has_active_google_tasks(user_id)?,
+            uber: self.has_active_uber(user_id)?,
+            tesla: self.has_active_tesla(user_id)?,
+        })
+    }
+
     pub fn get_active_signal_connection(&self, user_id: i32) -> Result<Option<Bridge>, DieselError> {
         use crate::schema::bridges;
         let mut conn = self.pool.get().expect("Failed to get DB connection");
@@ -1535,6 +2217,49 @@ impl UserRepository {
         Ok(())
     }
 
+    // Replace the judgment for an email, returning the previous one if any. Emails are identified
+    // by their id; judgments stored before ids were kept only have the timestamp, one of those
+    // is taken over the first time its email is judged again.
+    pub fn replace_email_judgment(&self, new_judgment: &crate::models::user_models::NewEmailJudgment) -> Result<Option<crate::models::user_models::EmailJudgment>, DieselError> {
+        use crate::schema::email_judgments;
+        let mut conn = self.pool.get().expect("Failed to get DB connection");
+
+        conn.transaction(|conn| {
+            let mut previous = match new_judgment.email_id.as_deref() {
+                Some(email_id) => email_judgments::table
+                    .filter(email_judgments::user_id.eq(new_judgment.user_id))
+                    .filter(email_judgments::email_id.eq(email_id))
+                    .order_by(email_judgments::processed_at.desc())
+                    .first::<crate::models::user_models::EmailJudgment>(conn)
+                    .optional()?,
+                None => None,
+            };
+            if previous.is_none() {
+                previous = email_judgments::table
+                    .filter(email_judgments::user_id.eq(new_judgment.user_id))
+                    .filter(email_judgments::email_id.is_null())
+                    .filter(email_judgments::email_timestamp.eq(new_judgment.email_timestamp))
+                    .order_by(email_judgments::processed_at.desc())
+                    .first::<crate::models::user_models::EmailJudgment>(conn)
+                    .optional()?;
+            }
+            if let Some(legacy) = previous.as_ref().filter(|judgment| judgment.email_id.is_none()) {
+                diesel::delete(email_judgments::table.filter(email_judgments::id.eq(legacy.id)))
+                    .execute(conn)?;
+            }
+            if let Some(email_id) = new_judgment.email_id.as_deref() {
+                diesel::delete(email_judgments::table)
+                    .filter(email_judgments::user_id.eq(new_judgment.user_id))
+                    .filter(email_judgments::email_id.eq(email_id))
+                    .execute(conn)?;
+            }
+            diesel::insert_into(email_judgments::table)
+                .values(new_judgment)
+                .execute(conn)?;
+            Ok(previous)
+        })
+    }
+
     // Delete email judgments older than 30 days
     pub fn delete_old_email_judgments(&self, user_id: i32) -> Result<(), DieselError> {
         use crate::schema::email_judgments;
@@ -1654,3 +2379,67 @@ impl UserRepository {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::models::user_models::NewEmailJudgment;
+    use crate::test_support::{create_test_user, test_state};
+
+    fn judgment(user_id: i32, email_id: Option<&str>, should_notify: bool) -> NewEmailJudgment {
+        NewEmailJudgment {
+            user_id,
+            email_timestamp: 1_792_000_000,
+            processed_at: 1_792_000_100,
+            should_notify,
+            score: if should_notify { 10 } else { 0 },
+            reason: "test".to_string(),
+            email_id: email_id.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn emails_in_the_same_second_keep_separate_judgments() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "judgments@example.com");
+        let repo = &state.user_repository;
+        assert!(repo.replace_email_judgment(&judgment(user_id, Some("1:100"), true)).unwrap().is_none());
+        assert!(repo.replace_email_judgment(&judgment(user_id, Some("1:101"), false)).unwrap().is_none());
+        assert_eq!(repo.get_user_email_judgments(user_id).unwrap().len(), 2);
+
+        let previous = repo.replace_email_judgment(&judgment(user_id, Some("1:100"), false)).unwrap().unwrap();
+        assert!(previous.should_notify);
+        assert_eq!(repo.get_user_email_judgments(user_id).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn legacy_judgment_is_taken_over_once() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "legacy-judgments@example.com");
+        let repo = &state.user_repository;
+        repo.replace_email_judgment(&judgment(user_id, None, true)).unwrap();
+
+        let previous = repo.replace_email_judgment(&judgment(user_id, Some("1:100"), false)).unwrap().unwrap();
+        assert!(previous.email_id.is_none());
+        let stored = repo.get_user_email_judgments(user_id).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].email_id.as_deref(), Some("1:100"));
+    }
+
+    #[tokio::test]
+    async fn failed_broadcast_recipients_are_claimed_once() {
+        let state = test_state();
+        let failed = create_test_user(&state, "failed@example.com");
+        let sent = create_test_user(&state, "sent@example.com");
+        let broadcast_id = state.user_repository.create_broadcast("email", "Subject", "Body").unwrap();
+        state.user_repository.record_broadcast_result(broadcast_id, failed, Some("bounced".to_string())).unwrap();
+        state.user_repository.record_broadcast_result(broadcast_id, sent, None).unwrap();
+
+        assert_eq!(state.user_repository.claim_failed_broadcast_recipients(broadcast_id).unwrap(), vec![failed]);
+        // A second retry racing the first finds nothing left to send
+        assert!(state.user_repository.claim_failed_broadcast_recipients(broadcast_id).unwrap().is_empty());
+
+        // Failing again makes the row claimable for the next retry
+        state.user_repository.record_broadcast_result(broadcast_id, failed, Some("bounced".to_string())).unwrap();
+        assert_eq!(state.user_repository.claim_failed_broadcast_recipients(broadcast_id).unwrap(), vec![failed]);
+    }
+}
@@ -13,6 +13,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    broadcast_recipients (id) {
+        id -> Nullable<Integer>,
+        broadcast_id -> Integer,
+        user_id -> Integer,
+        status -> Text,
+        error -> Nullable<Text>,
+        attempts -> Integer,
+        updated_at -> Integer,
+    }
+}
+
+diesel::table! {
+    broadcasts (id) {
+        id -> Nullable<Integer>,
+        channel -> Text,
+        subject -> Text,
+        message -> Text,
+        created_at -> Integer,
+    }
+}
+
 diesel::table! {
     calendar_notifications (id) {
         id -> Nullable<Integer>,
@@ -49,6 +71,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    credit_spend (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        amount -> Float,
+        created_at -> Integer,
+    }
+}
+
 diesel::table! {
     critical_categories (id) {
         id -> Nullable<Integer>,
@@ -68,6 +99,20 @@ diesel::table! {
         should_notify -> Bool,
         score -> Integer,
         reason -> Text,
+        email_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    failed_notifications (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        notification_type -> Text,
+        channel -> Text,
+        encrypted_payload -> Text,
+        error -> Text,
+        created_at -> Integer,
+        retried_at -> Nullable<Integer>,
     }
 }
 
@@ -112,6 +157,7 @@ diesel::table! {
         expires_in -> Integer,
         imap_server -> Nullable<Text>,
         imap_port -> Nullable<Integer>,
+        label -> Nullable<Text>,
     }
 }
 
@@ -121,6 +167,17 @@ diesel::table! {
         user_id -> Integer,
         keyword -> Text,
         service_type -> Text,
+        action -> Text,
+    }
+}
+
+diesel::table! {
+    known_contacts (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        service -> Text,
+        room_id -> Text,
+        created_at -> Integer,
     }
 }
 
@@ -138,6 +195,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    notified_items (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        item_key -> Text,
+        content_hash -> Text,
+        notified_at -> Integer,
+    }
+}
+
 diesel::table! {
     priority_senders (id) {
         id -> Nullable<Integer>,
@@ -158,6 +225,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    room_notification_prefs (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        platform -> Text,
+        room_id -> Text,
+        mode -> Text,
+        noti_type -> Nullable<Text>,
+        updated_at -> Integer,
+    }
+}
+
+diesel::table! {
+    scheduled_sends (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        kind -> Text,
+        target -> Text,
+        encrypted_payload -> Text,
+        send_at -> Integer,
+        status -> Text,
+        error -> Nullable<Text>,
+        created_at -> Integer,
+    }
+}
+
 diesel::table! {
     subaccounts (id) {
         id -> Integer,
@@ -269,6 +362,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_notes (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        encrypted_content -> Text,
+        created_at -> Integer,
+    }
+}
+
 diesel::table! {
     user_settings (id) {
         id -> Nullable<Integer>,
@@ -302,6 +404,36 @@ diesel::table! {
         monthly_message_count -> Integer,
         outbound_message_pricing -> Nullable<Float>,
         notify_on_climate_ready -> Bool,
+        email_poll_interval_minutes -> Nullable<Integer>,
+        bridge_read_receipts_off -> Nullable<Bool>,
+        onboarding_dismissed -> Nullable<Bool>,
+        llm_models -> Nullable<Text>,
+        call_history_limit -> Nullable<Integer>,
+        insufficient_credits_policy -> Nullable<Text>,
+        call_failure_sms_fallback -> Nullable<Bool>,
+        textbee_device_status -> Nullable<Text>,
+        textbee_status_checked_at -> Nullable<Integer>,
+        call_opening_templates -> Nullable<Text>,
+        units -> Nullable<Text>,
+        share_location -> Nullable<Bool>,
+        share_contacts -> Nullable<Bool>,
+        share_history -> Nullable<Bool>,
+        max_call_minutes -> Nullable<Integer>,
+        firecrawl_result_count -> Nullable<Integer>,
+        firecrawl_include_domains -> Nullable<Text>,
+        firecrawl_exclude_domains -> Nullable<Text>,
+        detect_sms_language -> Nullable<Bool>,
+        paused_until -> Nullable<Integer>,
+        pause_allows_critical -> Nullable<Bool>,
+        tesla_confirm_commands -> Nullable<Text>,
+        email_business_hours_start -> Nullable<Integer>,
+        email_business_hours_end -> Nullable<Integer>,
+        email_notification_snippets -> Nullable<Bool>,
+        digest_length -> Nullable<Text>,
+        digest_tone -> Nullable<Text>,
+        email_idle -> Nullable<Bool>,
+        daily_credit_cap -> Nullable<Float>,
+        weekly_credit_cap -> Nullable<Float>,
     }
 }
 
@@ -346,38 +478,57 @@ diesel::table! {
         content -> Text,
         service_type -> Text,
         noti_type -> Nullable<Text>,
+        resolved_at -> Nullable<Integer>,
+        resolved_reason -> Nullable<Text>,
     }
 }
 
 diesel::joinable!(bridges -> users (user_id));
+diesel::joinable!(broadcast_recipients -> broadcasts (broadcast_id));
+diesel::joinable!(broadcast_recipients -> users (user_id));
 diesel::joinable!(calendar_notifications -> users (user_id));
 diesel::joinable!(conversations -> users (user_id));
+diesel::joinable!(failed_notifications -> users (user_id));
+diesel::joinable!(credit_spend -> users (user_id));
 diesel::joinable!(imap_connection -> users (user_id));
 diesel::joinable!(keywords -> users (user_id));
+diesel::joinable!(known_contacts -> users (user_id));
 diesel::joinable!(message_history -> users (user_id));
+diesel::joinable!(notified_items -> users (user_id));
 diesel::joinable!(priority_senders -> users (user_id));
 diesel::joinable!(processed_emails -> users (user_id));
+diesel::joinable!(room_notification_prefs -> users (user_id));
+diesel::joinable!(scheduled_sends -> users (user_id));
 diesel::joinable!(tesla -> users (user_id));
 diesel::joinable!(totp_backup_codes -> users (user_id));
 diesel::joinable!(totp_secrets -> users (user_id));
 diesel::joinable!(user_info -> users (user_id));
+diesel::joinable!(user_notes -> users (user_id));
 diesel::joinable!(user_settings -> users (user_id));
 diesel::joinable!(waiting_checks -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     bridges,
+    broadcast_recipients,
+    broadcasts,
     calendar_notifications,
     conversations,
     country_availability,
+    credit_spend,
     critical_categories,
     email_judgments,
+    failed_notifications,
     google_calendar,
     google_tasks,
     imap_connection,
     keywords,
+    known_contacts,
     message_history,
+    notified_items,
     priority_senders,
     processed_emails,
+    room_notification_prefs,
+    scheduled_sends,
     subaccounts,
     task_notifications,
     tesla,
@@ -386,6 +537,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     uber,
     usage_logs,
     user_info,
+    user_notes,
     user_settings,
     users,
     waiting_checks,
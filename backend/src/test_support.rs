@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::SqliteConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+use tokio::sync::Mutex;
+use tower_sessions::MemoryStore;
+
+use crate::handlers::auth_dtos::NewUser;
+use crate::repositories::totp_repository::TotpRepository;
+use crate::repositories::user_core::UserCore;
+use crate::repositories::user_repository::UserRepository;
+use crate::{utils, AppState, DbPool, GoogleOAuthClient};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// A fresh migrated in-memory database. Named and shared-cache so every pooled connection sees
+/// the same data, repository methods often take a second connection while holding one.
+pub fn test_pool() -> DbPool {
+    let url = format!("file:test-{}?mode=memory&cache=shared", uuid::Uuid::new_v4());
+    let pool = r2d2::Pool::builder()
+        .max_size(4)
+        .build(ConnectionManager::<SqliteConnection>::new(url))
+        .expect("Failed to create test pool");
+    pool.get()
+        .expect("Failed to get test connection")
+        .run_pending_migrations(MIGRATIONS)
+        .expect("Failed to run migrations");
+    pool
+}
+
+fn oauth_client(name: &str) -> GoogleOAuthClient {
+    BasicClient::new(ClientId::new(format!("test-{}-client-id", name)))
+        .set_client_secret(ClientSecret::new("test-secret".to_string()))
+        .set_auth_uri(AuthUrl::new("http://localhost/auth".to_string()).unwrap())
+        .set_token_uri(TokenUrl::new("http://localhost/token".to_string()).unwrap())
+        .set_redirect_uri(RedirectUrl::new(format!("http://localhost/api/auth/{}/callback", name)).unwrap())
+}
+
+/// AppState over a fresh database, with OAuth clients pointing nowhere. Needs a tokio runtime for the job queue.
+pub fn test_state() -> Arc<AppState> {
+    set_test_voice_ids();
+    let pool = test_pool();
+    Arc::new(AppState {
+        db_pool: pool.clone(),
+        user_core: Arc::new(UserCore::new(pool.clone())),
+        user_repository: Arc::new(UserRepository::new(pool.clone())),
+        google_calendar_oauth_client: oauth_client("google-calendar"),
+        google_tasks_oauth_client: oauth_client("google-tasks"),
+        uber_oauth_client: oauth_client("uber"),
+        tesla_oauth_client: oauth_client("tesla"),
+        session_store: MemoryStore::default(),
+        login_limiter: DashMap::new(),
+        password_reset_limiter: DashMap::new(),
+        password_reset_verify_limiter: DashMap::new(),
+        phone_verify_otps: DashMap::new(),
+        phone_verify_sends: DashMap::new(),
+        matrix_sync_tasks: Arc::new(Mutex::new(HashMap::new())),
+        matrix_clients: Arc::new(Mutex::new(utils::matrix_client_cache::ClientCache::new(utils::matrix_client_cache::cache_capacity()))),
+        tesla_monitoring_tasks: Arc::new(DashMap::new()),
+        email_idle_tasks: Arc::new(DashMap::new()),
+        email_idle_listening: DashMap::new(),
+        tesla_telemetry: utils::tesla_telemetry::TelemetryCache::new(),
+        phone_verify_limiter: DashMap::new(),
+        phone_verify_verify_limiter: DashMap::new(),
+        password_reset_otps: DashMap::new(),
+        pending_message_senders: Arc::new(Mutex::new(HashMap::new())),
+        totp_repository: Arc::new(TotpRepository::new(pool)),
+        pending_totp_logins: DashMap::new(),
+        email_judgment_rerun_limiter: DashMap::new(),
+        inbound_sms_limiter: DashMap::new(),
+        help_reply_limiter: DashMap::new(),
+        last_surfaced_emails: DashMap::new(),
+        job_queue: utils::job_queue::JobQueue::start(1, 100),
+        connection_events: utils::connection_events::ConnectionEvents::new(),
+        active_notification_calls: DashMap::new(),
+        sms_segments: utils::sms_segments::SegmentBuffer::new(),
+        sms_languages: utils::language_detection::LanguageCache::new(),
+        bridge_resyncs: utils::bridge_resync::ResyncTracker::new(),
+    })
+}
+
+/// Inserts a verified tier 2 user and returns their id
+pub fn create_test_user(state: &Arc<AppState>, email: &str) -> i32 {
+    state.user_core.create_user(NewUser {
+        email: email.to_string(),
+        password_hash: "not-a-real-hash".to_string(),
+        phone_number: "+15555550123".to_string(),
+        time_to_live: 0,
+        verified: true,
+        credits: 10.0,
+        credits_left: 10.0,
+        charge_when_under: false,
+        waiting_checks_count: 0,
+        discount: false,
+        sub_tier: Some("tier 2".to_string()),
+    }).expect("Failed to create test user");
+    let user_id = state.user_core.find_by_email(email).unwrap().expect("Test user missing").id;
+    state.user_core.ensure_user_settings_exist(user_id).expect("Failed to create test user settings");
+    user_id
+}
+
+/// ENCRYPTION_KEY for tests that store credentials, the same fixed key everywhere
+pub fn set_test_encryption_key() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+        std::env::set_var("ENCRYPTION_KEY", BASE64.encode([7u8; 32]));
+    });
+}
+
+/// The voice language table is loaded once per process and needs the English voice, set it
+/// before any test state can reach it
+fn set_test_voice_ids() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| std::env::set_var("US_VOICE_ID", "test-voice-en"));
+}
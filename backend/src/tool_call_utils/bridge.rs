@@ -11,8 +11,8 @@ pub fn get_search_chat_contacts_tool() -> openai_api_rs::v1::chat_completion::To
         "platform".to_string(),
         Box::new(types::JSONSchemaDefine {
             schema_type: Some(types::JSONSchemaType::String),
-            description: Some("The platform to fetch messages from. Must be either 'telegram', 'whatsapp' or 'signal'.".to_string()),
-            enum_values: Some(vec!["telegram".to_string(), "whatsapp".to_string(), "signal".to_string()]),
+            description: Some("The platform to search. Must be either 'telegram', 'whatsapp', 'signal' or 'all'. Use 'all' (the default) when the user didn't name a platform.".to_string()),
+            enum_values: Some(vec!["telegram".to_string(), "whatsapp".to_string(), "signal".to_string(), "all".to_string()]),
             ..Default::default()
         }),
     );
@@ -31,12 +31,13 @@ pub fn get_search_chat_contacts_tool() -> openai_api_rs::v1::chat_completion::To
             description: Some(String::from(
                 "Searches to check if specific contacts, rooms, groups, or channels exist on the specified platform by name or keyword. \
                 Use this only when the user asks to search, find, or check for the existence of contacts/people/groups/channels on Telegram, WhatsApp or Signal. \
+                With platform 'all' the results list every platform the contact is reachable on. \
                 Do not use this for searching messages within a chat; use the separate message search tool for that."
             )),
             parameters: types::FunctionParameters {
                 schema_type: types::JSONSchemaType::Object,
                 properties: Some(properties),
-                required: Some(vec![String::from("platform"), String::from("search_term")]),
+                required: Some(vec![String::from("search_term")]),
             },
         },
     }
@@ -158,6 +159,22 @@ pub fn get_send_chat_message_tool() -> openai_api_rs::v1::chat_completion::Tool
             ..Default::default()
         }),
     );
+    properties.insert(
+        "confirm_new_contact".to_string(),
+        Box::new(types::JSONSchemaDefine {
+            schema_type: Some(types::JSONSchemaType::Boolean),
+            description: Some("Set to true only when the user has explicitly confirmed sending to a contact they have never messaged before, after being asked.".to_string()),
+            ..Default::default()
+        }),
+    );
+    properties.insert(
+        "send_at".to_string(),
+        Box::new(types::JSONSchemaDefine {
+            schema_type: Some(types::JSONSchemaType::String),
+            description: Some("Only when the user asks for a specific send time (e.g. 'at 9am tomorrow'): that time as 'YYYY-MM-DDTHH:MM:SS' in the user's local time. Leave out to send after the usual short cancel window.".to_string()),
+            ..Default::default()
+        }),
+    );
     chat_completion::Tool {
         r#type: chat_completion::ToolType::Function,
         function: types::Function {
@@ -166,7 +183,8 @@ pub fn get_send_chat_message_tool() -> openai_api_rs::v1::chat_completion::Tool
                 Some(String::from(
                     "Sends a message to a specific chat on the specified platform. \
                     Use this when the user asks to send a message to a contact or group on Telegram, WhatsApp or Signal. \
-                    This tool will fuzzy search for the chat_name, add the message to the sending queue and unless user replies cancel the message will be sent after 60 seconds.
+                    This tool will fuzzy search for the chat_name, add the message to the sending queue and unless user replies cancel the message will be sent after 60 seconds (180 seconds if the contact match is uncertain). \
+                    Contacts the user has never messaged are not sent to until the user confirms; if they confirm, call again with confirm_new_contact set to true.
                     Only use this tool if the user has explicitly mentioned the message content or it is obviously clear what content they want to send; otherwise, ask the user to specify the message content, recipient and platform before calling the tool."
                 )),
             parameters: types::FunctionParameters {
@@ -187,6 +205,10 @@ struct SendChatMessageArgs {
     platform: String,
     chat_name: String,
     message: String,
+    #[serde(default)]
+    confirm_new_contact: bool,
+    #[serde(default)]
+    send_at: Option<String>,
 }
 pub async fn handle_send_chat_message(
     state: &Arc<AppState>,
@@ -206,7 +228,7 @@ pub async fn handle_send_chat_message(
             None,
             user,
         ).await {
-            eprintln!("Failed to send error message: {}", e);
+            tracing::error!("Failed to send error message: {}", e);
         }
         return Ok((
             StatusCode::OK,
@@ -227,7 +249,7 @@ pub async fn handle_send_chat_message(
                 None,
                 user,
             ).await {
-                eprintln!("Failed to send error message: {}", e);
+                tracing::error!("Failed to send error message: {}", e);
             }
             return Ok((
                 StatusCode::OK,
@@ -238,9 +260,9 @@ pub async fn handle_send_chat_message(
             ));
         }
     };
-    let best_match = crate::utils::bridge::search_best_match(&rooms, &args.chat_name);
-    let best_match = match best_match {
-        Some(room) => room,
+    let best_match = crate::utils::bridge::search_best_match_scored(&rooms, &args.chat_name);
+    let (best_match, match_confidence) = match best_match {
+        Some(found) => found,
         None => {
             let error_msg = format!("No {} contacts found matching '{}'.", capitalized_platform, args.chat_name.as_str());
             if let Err(e) = crate::api::twilio_utils::send_conversation_message(
@@ -249,7 +271,7 @@ pub async fn handle_send_chat_message(
                 None,
                 user,
             ).await {
-                eprintln!("Failed to send error message: {}", e);
+                tracing::error!("Failed to send error message: {}", e);
             }
             return Ok((
                 StatusCode::OK,
@@ -262,17 +284,102 @@ pub async fn handle_send_chat_message(
     };
     // Get the best match
     let exact_name = crate::utils::bridge::remove_bridge_suffix(&best_match.display_name);
-    tracing::info!("Message will be sent to {}", exact_name);
+    if let Some(reason) = crate::tool_call_utils::utils::self_send_rejection(state, user, &exact_name)
+        .or_else(|| crate::tool_call_utils::utils::self_send_rejection(state, user, &args.chat_name))
+    {
+        if let Err(e) = crate::api::twilio_utils::send_conversation_message(
+            state,
+            &reason,
+            None,
+            user,
+        ).await {
+            tracing::error!("Failed to send error message: {}", e);
+        }
+        return Ok((
+            StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            Json(TwilioResponse {
+                message: reason,
+            })
+        ));
+    }
+    tracing::info!("Message will be sent to {} (match confidence {:.2})", exact_name, match_confidence);
+    let known_contact = crate::utils::bridge::is_known_contact(state, user_id, &args.platform, &best_match.room_id).await;
+    let (delay_secs, low_confidence) = match crate::utils::bridge::send_guard(match_confidence, known_contact, args.confirm_new_contact) {
+        crate::utils::bridge::SendGuard::Proceed { delay_secs, low_confidence } => (delay_secs, low_confidence),
+        crate::utils::bridge::SendGuard::NeedsConfirmation => {
+            let confirm_msg = format!(
+                "You haven't messaged '{}' on {} before. Reply 'yes' to confirm sending '{}' to them.",
+                exact_name, capitalized_platform, args.message
+            );
+            if let Err(e) = crate::api::twilio_utils::send_conversation_message(
+                state,
+                &confirm_msg,
+                None,
+                user,
+            ).await {
+                tracing::error!("Failed to send confirmation request: {}", e);
+            }
+            return Ok((
+                StatusCode::OK,
+                [(axum::http::header::CONTENT_TYPE, "application/json")],
+                Json(TwilioResponse {
+                    message: confirm_msg,
+                })
+            ));
+        }
+    };
+    // Spell out the resolved contact when the fuzzy match was a stretch
+    let recipient = if low_confidence {
+        format!("'{}' (closest match for '{}')", exact_name.to_uppercase(), args.chat_name)
+    } else {
+        format!("'{}'", exact_name)
+    };
+    // An explicit time skips the cancel window and goes to the scheduled sends table
+    if let Some(send_at) = args.send_at.as_deref().filter(|s| !s.trim().is_empty()) {
+        let payload = crate::utils::scheduled_sends::ScheduledPayload::ChatMessage {
+            platform: args.platform.clone(),
+            chat_name: exact_name.clone(),
+            room_id: Some(best_match.room_id.clone()),
+            message: args.message.clone(),
+            image_url: image_url.map(|s| s.to_string()),
+        };
+        let (reply, scheduled) = match crate::utils::scheduled_sends::schedule_from_tool(state, user_id, send_at, &payload, &format!("{} {}", capitalized_platform, exact_name)) {
+            Ok((_, _, confirmation)) => (confirmation, true),
+            Err(reason) => (reason, false),
+        };
+        match crate::api::twilio_utils::send_conversation_message(
+            state,
+            &reply,
+            None,
+            user,
+        ).await {
+            Ok(_) if scheduled => {
+                if let Err(e) = crate::utils::usage::deduct_user_credits(state, user_id, "message", None) {
+                    tracing::error!("Failed to deduct user credits: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to send scheduled send message: {}", e),
+        }
+        return Ok((
+            StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            Json(TwilioResponse {
+                message: reply,
+            })
+        ));
+    }
     // Format the queued message with the found contact name and image if present
     let queued_msg = if image_url.is_some() {
         format!(
-            "Will send {} to '{}' with image and caption '{}' in 60s. Reply 'C' to discard.",
-            capitalized_platform, exact_name, args.message
+            "Will send {} to {} with image and caption '{}' in {}s. Reply 'C' to discard.",
+            capitalized_platform, recipient, args.message, delay_secs
         )
     } else {
         format!(
-            "Will send {} to '{}' with content '{}' in 60s. Reply 'C' to discard.",
-            capitalized_platform, exact_name, args.message
+            "Will send {} to {} with content '{}' in {}s. Reply 'C' to discard.",
+            capitalized_platform, recipient, args.message, delay_secs
         )
     };
     // Send the queued message
@@ -289,7 +396,7 @@ pub async fn handle_send_chat_message(
             }
         }
         Err(e) => {
-            eprintln!("Failed to send queued message: {}", e);
+            tracing::error!("Failed to send queued message: {}", e);
             return Ok((
                 StatusCode::OK,
                 [(axum::http::header::CONTENT_TYPE, "application/json")],
@@ -299,9 +406,9 @@ pub async fn handle_send_chat_message(
             ));
         }
     }
-    // Create cancellation channel
-    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
-    // Spawn the delayed send task after sending the message
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "chat_message", &format!("{} {}", capitalized_platform, exact_name), &format!("{} message to {}", capitalized_platform, exact_name), std::time::Duration::from_secs(delay_secs)).await;
+    // Queue the delayed send after sending the message
     let cloned_state = state.clone();
     let cloned_user_id = user_id;
     let cloned_user = user.clone();
@@ -310,12 +417,19 @@ pub async fn handle_send_chat_message(
     let cloned_exact_name = exact_name.clone();
     let cloned_message = args.message.clone();
     let cloned_image_url = image_url.map(|s| s.to_string());
-    tokio::spawn(async move {
-        let reason = tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "timeout",
-            _ = cancel_rx => "cancel",
-        };
-        if reason == "timeout" {
+    let job = crate::utils::job_queue::BackgroundJob::new("send_bridge_message", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_capitalized_platform = cloned_capitalized_platform.clone();
+        let cloned_platform = cloned_platform.clone();
+        let cloned_exact_name = cloned_exact_name.clone();
+        let cloned_message = cloned_message.clone();
+        let cloned_image_url = cloned_image_url.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
             // Proceed with send using captured variables
             println!("sending message now");
             if let Err(e) = crate::utils::bridge::send_bridge_message(
@@ -333,18 +447,22 @@ pub async fn handle_send_chat_message(
                     None,
                     &cloned_user,
                 ).await {
-                    eprintln!("Failed to send error message: {}", e);
+                    tracing::error!("Failed to send error message: {}", e);
                 }
             }
+            Ok(())
         }
-        // Remove from map
-        let mut senders = cloned_state.pending_message_senders.lock().await;
-        senders.remove(&cloned_user_id);
     });
-    // Store the cancel sender in the map
-    {
-        let mut senders = state.pending_message_senders.lock().await;
-        senders.insert(user_id, cancel_tx);
+    if let Err(e) = state.job_queue.enqueue_delayed(job, std::time::Duration::from_secs(delay_secs), cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        tracing::error!("Failed to queue send bridge message: {}", e);
+        return Ok((
+            StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            Json(TwilioResponse {
+                message: "Failed to queue the message, please try again".to_string(),
+            })
+        ));
     }
     Ok((
         StatusCode::OK,
@@ -357,10 +475,43 @@ pub async fn handle_send_chat_message(
 
 #[derive(Deserialize)]
 struct SearchChatContactsArgs {
-    platform: String,
+    platform: Option<String>,
     search_term: String,
 }
 
+async fn search_unified_contacts(state: &Arc<AppState>, user_id: i32, search_term: &str) -> String {
+    let search = crate::utils::bridge::normalize_contact_name(search_term);
+    match crate::utils::bridge::unified_contacts(state, user_id).await {
+        Ok(contacts) => {
+            let matches: Vec<_> = contacts
+                .into_iter()
+                .filter(|contact| crate::utils::bridge::normalize_contact_name(&contact.name).contains(&search))
+                .collect();
+            if matches.is_empty() {
+                return format!("No contacts found matching '{}'.", search_term);
+            }
+            let mut response = matches
+                .iter()
+                .take(5)
+                .enumerate()
+                .map(|(i, contact)| {
+                    let platforms: Vec<&str> = contact.platforms.iter().map(|p| p.service.as_str()).collect();
+                    format!("{}. {} on {} (last active: {})", i + 1, contact.name, platforms.join(", "), contact.last_activity_formatted)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            if matches.len() > 5 {
+                response.push_str(&format!("\n\n(+ {} more contacts)", matches.len() - 5));
+            }
+            response
+        }
+        Err(e) => {
+            tracing::error!("Failed to load unified contacts: {}", e);
+            "Failed to search contacts. Please make sure you have a messaging app connected.".to_string()
+        }
+    }
+}
+
 pub async fn handle_search_chat_contacts(
     state: &Arc<AppState>,
     user_id: i32,
@@ -373,15 +524,19 @@ pub async fn handle_search_chat_contacts(
             return "Failed to parse search request.".to_string();
         }
     };
+    let platform = match args.platform.as_deref() {
+        None | Some("all") => return search_unified_contacts(state, user_id, &args.search_term).await,
+        Some(platform) => platform.to_string(),
+    };
     match crate::utils::bridge::search_bridge_rooms(
-        &args.platform,
+        &platform,
         state,
         user_id,
         &args.search_term,
     ).await {
         Ok(rooms) => {
             if rooms.is_empty() {
-                let capitalized_platform = args.platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &args.platform[1..];
+                let capitalized_platform = platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &platform[1..];
                 format!("No {} contacts found matching '{}'.", capitalized_platform, args.search_term)
             } else {
                 let mut response = String::new();
@@ -410,7 +565,7 @@ pub async fn handle_search_chat_contacts(
         }
         Err(e) => {
             eprintln!("Failed to search rooms: {}", e);
-            let capitalized_platform = args.platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &args.platform[1..];
+            let capitalized_platform = platform.chars().next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + &platform[1..];
             format!("Failed to search contacts. Please make sure you're connected to {} bridge.", capitalized_platform)
         }
     }
@@ -528,7 +683,7 @@ pub async fn handle_fetch_recent_messages(
             if messages.is_empty() {
                 format!("No {} messages found for this time period.", capitalized_platform)
             } else {
-                let mut response = String::new();
+                let mut response = crate::utils::bridge::new_messages_line(&messages);
                 for (i, msg) in messages.iter().take(15).enumerate() {
                     let content = if msg.content.len() > 100 {
                         format!("{}...", &msg.content[..97])
@@ -536,21 +691,13 @@ pub async fn handle_fetch_recent_messages(
                         msg.content.clone()
                     };
                    
-                    if i == 0 {
-                        response.push_str(&format!("{}. {} at {}:\n{}",
-                            i + 1,
-                            msg.room_name,
-                            msg.formatted_timestamp,
-                            content
-                        ));
-                    } else {
-                        response.push_str(&format!("\n\n{}. {} at {}:\n{}",
-                            i + 1,
-                            msg.room_name,
-                            msg.formatted_timestamp,
-                            content
-                        ));
-                    }
+                    response.push_str(&format!("\n\n{}. {}{} at {}:\n{}",
+                        i + 1,
+                        if msg.unread { "(new) " } else { "" },
+                        msg.room_name,
+                        msg.formatted_timestamp,
+                        content
+                    ));
                 }
                
                 if messages.len() > 15 {
@@ -2,7 +2,7 @@ use crate::AppState;
 use std::sync::Arc;
 use serde::Deserialize;
 use axum::Json;
-use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
 use chrono_tz;
 use serde_json::Value;
 
@@ -89,6 +89,14 @@ pub fn get_create_calendar_event_tool() -> openai_api_rs::v1::chat_completion::T
             ..Default::default()
         }),
     );
+    calendar_event_properties.insert(
+        "allow_past".to_string(),
+        Box::new(types::JSONSchemaDefine {
+            schema_type: Some(types::JSONSchemaType::Boolean),
+            description: Some("Set to true only when the user explicitly wants to log an event that already happened. Events starting in the past are rejected otherwise.".to_string()),
+            ..Default::default()
+        }),
+    );
 
 
     chat_completion::Tool {
@@ -223,6 +231,29 @@ struct CalendarEventArgs {
     duration_minutes: String,  // Changed to String to handle quoted numbers
     description: Option<String>,
     add_notification: Option<bool>,
+    #[serde(default)]
+    allow_past: bool,
+}
+
+/// How far before now a new event may start before it's taken for a misheard date
+pub const PAST_START_TOLERANCE_MINUTES: i64 = 15;
+
+/// Why an event starting at `start` shouldn't be created: it's more than the tolerance in the past
+/// and the user didn't ask to log a past event. Worded to be read back, with the time in their timezone.
+pub fn past_start_rejection(start: DateTime<Utc>, now: DateTime<Utc>, timezone: chrono_tz::Tz, allow_past: bool) -> Option<String> {
+    if allow_past || start >= now - chrono::Duration::minutes(PAST_START_TOLERANCE_MINUTES) {
+        return None;
+    }
+    let local_start = start.with_timezone(&timezone);
+    let when = if local_start.date_naive() == now.with_timezone(&timezone).date_naive() {
+        local_start.format("%-I:%M %p today").to_string()
+    } else {
+        local_start.format("%B %-d, %Y at %-I:%M %p").to_string()
+    };
+    Some(format!(
+        "That start time, {}, is already in the past, so I didn't create the event. Please check the date and time, or tell me if you meant to log an event that already happened.",
+        when
+    ))
 }
 
 pub async fn handle_create_calendar_event(
@@ -248,6 +279,23 @@ pub async fn handle_create_calendar_event(
     let user_tz: chrono_tz::Tz = timezone.parse()
         .unwrap_or(chrono_tz::UTC);
     let local_time = start_time.with_timezone(&user_tz);
+    if let Some(reason) = past_start_rejection(start_time.with_timezone(&Utc), Utc::now(), user_tz, args.allow_past) {
+        if let Err(e) = crate::api::twilio_utils::send_conversation_message(
+            &state,
+            &reason,
+            None,
+            user,
+        ).await {
+            tracing::error!("Failed to send past event message: {}", e);
+        }
+        return Ok((
+            axum::http::StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(crate::api::twilio_sms::TwilioResponse {
+                message: reason,
+            })
+        ));
+    }
    
     // Format the date and time
     let formatted_time = local_time.format("%B %d at %I:%M %p %Z").to_string();
@@ -290,7 +338,7 @@ pub async fn handle_create_calendar_event(
                 None,
                 user,
             ).await {
-                eprintln!("Failed to send error message: {}", e);
+                tracing::error!("Failed to send error message: {}", e);
             }
             return Ok((
                 axum::http::StatusCode::OK,
@@ -303,3 +351,30 @@ pub async fn handle_create_calendar_event(
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn past_start_is_rejected_unless_logging_a_past_event() {
+        // 15:00 in Helsinki
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let tz = chrono_tz::Europe::Helsinki;
+        let two_days_ago = Utc.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap();
+
+        let reason = past_start_rejection(two_days_ago, now, tz, false).expect("past start was accepted");
+        assert!(reason.starts_with("That start time, October 14, 2026 at 12:00 PM, is already in the past"), "{reason}");
+        assert_eq!(past_start_rejection(two_days_ago, now, tz, true), None);
+    }
+
+    #[test]
+    fn a_few_minutes_late_is_still_accepted() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let tz = chrono_tz::Europe::Helsinki;
+        assert_eq!(past_start_rejection(now - chrono::Duration::minutes(10), now, tz, false), None);
+        let reason = past_start_rejection(now - chrono::Duration::minutes(20), now, tz, false).unwrap();
+        assert!(reason.starts_with("That start time, 2:40 PM today,"), "{reason}");
+    }
+}
@@ -86,6 +86,14 @@ pub fn get_send_email_tool() -> openai_api_rs::v1::chat_completion::Tool {
             ..Default::default()
         }),
     );
+    properties.insert(
+        "send_at".to_string(),
+        Box::new(types::JSONSchemaDefine {
+            schema_type: Some(types::JSONSchemaType::String),
+            description: Some("Only when the user asks for a specific send time (e.g. 'at 9am tomorrow'): that time as 'YYYY-MM-DDTHH:MM:SS' in the user's local time. Leave out to send after the usual short cancel window.".to_string()),
+            ..Default::default()
+        }),
+    );
     chat_completion::Tool {
         r#type: chat_completion::ToolType::Function,
         function: types::Function {
@@ -120,6 +128,14 @@ pub fn get_respond_to_email_tool() -> openai_api_rs::v1::chat_completion::Tool {
             ..Default::default()
         }),
     );
+    properties.insert(
+        "reply_all".to_string(),
+        Box::new(types::JSONSchemaDefine {
+            schema_type: Some(types::JSONSchemaType::Boolean),
+            description: Some("Set to true to reply to the sender and everyone else the original email went to. Defaults to replying to the sender only.".to_string()),
+            ..Default::default()
+        }),
+    );
     chat_completion::Tool {
         r#type: chat_completion::ToolType::Function,
         function: types::Function {
@@ -141,6 +157,8 @@ pub struct SendEmailArgs {
     pub to: String,
     pub subject: String,
     pub body: String,
+    #[serde(default)]
+    pub send_at: Option<String>,
 }
 pub async fn handle_send_email(
     state: &Arc<AppState>,
@@ -149,10 +167,71 @@ pub async fn handle_send_email(
     user: &crate::models::user_models::User,
 ) -> Result<(axum::http::StatusCode, [(axum::http::HeaderName, &'static str); 1], axum::Json<crate::api::twilio_sms::TwilioResponse>), Box<dyn std::error::Error>> {
     let args: SendEmailArgs = serde_json::from_str(args)?;
+    let rejection = crate::utils::payload_limits::check_lengths(&[
+        ("The subject", &args.subject, crate::utils::payload_limits::MAX_SUBJECT_CHARS),
+        ("The email body", &args.body, crate::utils::payload_limits::MAX_EMAIL_BODY_CHARS),
+    ])
+    .err()
+    .or_else(|| crate::tool_call_utils::utils::self_send_rejection(state, user, &args.to));
+    if let Some(reason) = rejection {
+        if let Err(e) = crate::api::twilio_utils::send_conversation_message(
+            state,
+            &reason,
+            None,
+            user,
+        ).await {
+            tracing::error!("Failed to send error message: {}", e);
+        }
+        return Ok((
+            axum::http::StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(crate::api::twilio_sms::TwilioResponse {
+                message: reason,
+            })
+        ));
+    }
+    // An explicit time skips the cancel window and goes to the scheduled sends table
+    if let Some(send_at) = args.send_at.as_deref().filter(|s| !s.trim().is_empty()) {
+        let payload = crate::utils::scheduled_sends::ScheduledPayload::Email {
+            to: args.to.clone(),
+            subject: args.subject.clone(),
+            body: args.body.clone(),
+        };
+        let (reply, scheduled) = match crate::utils::scheduled_sends::schedule_from_tool(state, user_id, send_at, &payload, &args.to) {
+            Ok((_, _, confirmation)) => (confirmation, true),
+            Err(reason) => (reason, false),
+        };
+        match crate::api::twilio_utils::send_conversation_message(
+            state,
+            &reply,
+            None,
+            user,
+        ).await {
+            Ok(_) if scheduled => {
+                if let Err(e) = crate::utils::usage::deduct_user_credits(state, user_id, "message", None) {
+                    tracing::error!("Failed to deduct user credits: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to send scheduled send message: {}", e),
+        }
+        return Ok((
+            axum::http::StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(crate::api::twilio_sms::TwilioResponse {
+                message: reply,
+            })
+        ));
+    }
+    let (send_delay, deferred_until) = crate::tool_call_utils::utils::email_send_delay(state, user_id, std::time::Duration::from_secs(60));
+    let when = match &deferred_until {
+        Some(send_at) => format!("on {}, when your business hours start", send_at),
+        None => String::from("in 60s"),
+    };
     // Format the queued message
     let queued_msg = format!(
-        "Will send email to {} with subject '{}' and body '{}' in 60s. Reply 'C' to discard.",
-        args.to, args.subject, args.body
+        "Will send email to {} with subject '{}' and body '{}' {}. Reply 'C' to discard.",
+        args.to, args.subject, args.body, when
     );
     // Send the queued message
     match crate::api::twilio_utils::send_conversation_message(
@@ -168,7 +247,7 @@ pub async fn handle_send_email(
             }
         }
         Err(e) => {
-            eprintln!("Failed to send queued message: {}", e);
+            tracing::error!("Failed to send queued message: {}", e);
             return Ok((
                 axum::http::StatusCode::OK,
                 [(axum::http::header::CONTENT_TYPE, "application/json")],
@@ -178,25 +257,31 @@ pub async fn handle_send_email(
             ));
         }
     }
-    // Create cancellation channel
-    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
-    // Spawn the delayed send task
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email", &args.to, &format!("email to {}", args.to), send_delay).await;
+    // Queue the delayed send
     let cloned_state = state.clone();
     let cloned_user_id = user_id;
     let cloned_user = user.clone();
     let cloned_to = args.to.clone();
     let cloned_subject = args.subject.clone();
     let cloned_body = args.body.clone();
-    tokio::spawn(async move {
-        let reason = tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "timeout",
-            _ = cancel_rx => "cancel",
-        };
-        if reason == "timeout" {
+    let job = crate::utils::job_queue::BackgroundJob::new("send_email", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_to = cloned_to.clone();
+        let cloned_subject = cloned_subject.clone();
+        let cloned_body = cloned_body.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
             let email_request = crate::handlers::imap_handlers::SendEmailRequest {
                 to: cloned_to,
                 subject: cloned_subject,
                 body: cloned_body,
+                account_id: None,
             };
             match crate::handlers::imap_handlers::send_email(
                 axum::extract::State(cloned_state.clone()),
@@ -214,19 +299,23 @@ pub async fn handle_send_email(
                         None,
                         &cloned_user,
                     ).await {
-                        eprintln!("Failed to send error message: {}", e);
+                        tracing::error!("Failed to send error message: {}", e);
                     }
                 }
             }
+            Ok(())
         }
-        // Remove from map
-        let mut senders = cloned_state.pending_message_senders.lock().await;
-        senders.remove(&cloned_user_id);
     });
-    // Store the cancel sender in the map
-    {
-        let mut senders = state.pending_message_senders.lock().await;
-        senders.insert(user_id, cancel_tx);
+    if let Err(e) = state.job_queue.enqueue_delayed(job, send_delay, cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        tracing::error!("Failed to queue send email: {}", e);
+        return Ok((
+            axum::http::StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(crate::api::twilio_sms::TwilioResponse {
+                message: "Failed to queue the message, please try again".to_string(),
+            })
+        ));
     }
     Ok((
         axum::http::StatusCode::OK,
@@ -237,13 +326,65 @@ pub async fn handle_send_email(
     ))
 }
 
+/// Remembers the email the user was last shown so "reply to the last email" knows what to answer
+pub fn remember_surfaced_email(state: &Arc<AppState>, user_id: i32, email_id: &str) {
+    if !email_id.is_empty() && email_id.chars().all(|c| c.is_ascii_digit()) {
+        state.last_surfaced_emails.insert(user_id, email_id.to_string());
+    }
+}
+
+pub fn last_surfaced_email(state: &Arc<AppState>, user_id: i32) -> Option<String> {
+    state.last_surfaced_emails.get(&user_id).map(|id| id.clone())
+}
+
+/// respond_to_email arguments for an SMS like "Reply to the last email: ...", aimed at the email
+/// the user was last shown. None for any other SMS, Some(None) when no email has been shown yet.
+pub fn last_email_reply_args(state: &Arc<AppState>, user_id: i32, sms_body: &str) -> Option<Option<String>> {
+    let response_text = parse_last_email_reply(sms_body)?;
+    let Some(email_id) = last_surfaced_email(state, user_id) else {
+        return Some(None);
+    };
+    tracing::info!("Routing SMS reply from user {} to last surfaced email {}", user_id, email_id);
+    Some(Some(serde_json::json!({
+        "email_id": email_id,
+        "response_text": response_text,
+    }).to_string()))
+}
+
+const LAST_EMAIL_REPLY_PREFIXES: [&str; 6] = [
+    "reply to the last email",
+    "reply to last email",
+    "reply to the latest email",
+    "reply to latest email",
+    "reply to that email",
+    "reply last email",
+];
+
+/// Picks the response text out of an SMS like "Reply to the last email: sounds good, see you then".
+/// Returns None if the SMS isn't a last-email reply or has nothing to send.
+pub fn parse_last_email_reply(body: &str) -> Option<String> {
+    let body = body.trim();
+    LAST_EMAIL_REPLY_PREFIXES.iter().find_map(|prefix| {
+        let head = body.get(..prefix.len())?;
+        if !head.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        let rest = body[prefix.len()..].trim_start_matches(|c: char| c == ':' || c == '-' || c == ',' || c.is_whitespace());
+        // "reply to the last emails..." etc. isn't this command
+        if body[prefix.len()..].starts_with(|c: char| c.is_alphanumeric()) || rest.is_empty() {
+            return None;
+        }
+        Some(rest.to_string())
+    })
+}
+
 pub async fn handle_fetch_emails(state: &Arc<AppState>, user_id: i32) -> String {
     let auth_user = crate::handlers::auth_middleware::AuthUser {
         user_id,
         is_admin: false,
     };
 
-    let query_obj = crate::handlers::imap_handlers::FetchEmailsQuery { limit: None };
+    let query_obj = crate::handlers::imap_handlers::FetchEmailsQuery { limit: None, ..Default::default() };
 
     match crate::handlers::imap_handlers::fetch_full_imap_emails(
         axum::extract::State(state.clone()),
@@ -256,6 +397,9 @@ pub async fn handle_fetch_emails(state: &Arc<AppState>, user_id: i32) -> String
                     let mut parts: Vec<String> = Vec::new();
                     for email in emails_array.iter().rev().take(5) {
                         let id = email.get("id").and_then(|i| i.as_str()).unwrap_or("Unknown ID");
+                        if parts.is_empty() {
+                            remember_surfaced_email(state, user_id, id);
+                        }
                         let subject = email.get("subject").and_then(|s| s.as_str()).unwrap_or("No subject");
                         let from = email.get("from").and_then(|f| f.as_str()).unwrap_or("Unknown sender");
                         let date_formatted = email.get("date_formatted")
@@ -289,10 +433,10 @@ pub async fn handle_fetch_emails(state: &Arc<AppState>, user_id: i32) -> String
 
             let error_message = match status {
                 axum::http::StatusCode::BAD_REQUEST => {
-                    "I couldn't find your email connection. Please set up your email in the Lightfriend app settings.".to_string()
+                    crate::utils::branding::render(crate::utils::branding::EMAIL_NOT_CONNECTED)
                 }
                 axum::http::StatusCode::UNAUTHORIZED => {
-                    "Your email credentials have expired or are invalid. Please reconnect your email in the Lightfriend app settings. If you're using Gmail, you may need to generate a new app password.".to_string()
+                    crate::utils::branding::render(crate::utils::branding::EMAIL_CREDENTIALS_INVALID)
                 }
                 _ => {
                     format!("I ran into a problem checking your email: {}. Please try again in a moment, or check your email connection in the app settings.", error_detail)
@@ -315,6 +459,8 @@ use crate::{
 pub struct RespondToEmailArgs {
     pub email_id: String,
     pub response_text: String,
+    #[serde(default)]
+    pub reply_all: bool,
 }
 pub async fn handle_respond_to_email(
     state: &Arc<AppState>,
@@ -338,7 +484,7 @@ pub async fn handle_respond_to_email(
                 None,
                 user,
             ).await {
-                eprintln!("Failed to send error message: {}", e);
+                tracing::error!("Failed to send error message: {}", e);
             }
             return Ok((
                 axum::http::StatusCode::OK,
@@ -354,10 +500,16 @@ pub async fn handle_respond_to_email(
         .and_then(|s| s.as_str())
         .unwrap_or("Unknown subject")
         .to_string();
+    let (send_delay, deferred_until) = crate::tool_call_utils::utils::email_send_delay(state, user_id, std::time::Duration::from_secs(60));
+    let when = match &deferred_until {
+        Some(send_at) => format!("on {}, when your business hours start", send_at),
+        None => String::from("in 60s"),
+    };
     // Format the queued message using the subject
     let queued_msg = format!(
-        "Will respond to email '{}' with '{}' in 60s. Reply 'C' to discard.",
-        subject, args.response_text
+        "Will {} email '{}' with '{}' {}. Reply 'C' to discard.",
+        if args.reply_all { "reply all to" } else { "respond to" },
+        subject, args.response_text, when
     );
     // Send the queued message
     match crate::api::twilio_utils::send_conversation_message(
@@ -383,23 +535,29 @@ pub async fn handle_respond_to_email(
             ));
         }
     }
-    // Create cancellation channel
-    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
-    // Spawn the delayed send task
+    // Register the cancellable action
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(&state, user_id, "email_reply", &subject, &format!("reply to email {}", args.email_id), send_delay).await;
+    // Queue the delayed send
     let cloned_state = state.clone();
     let cloned_user_id = user_id;
     let cloned_user = user.clone();
     let cloned_email_id = args.email_id.clone();
     let cloned_response_text = args.response_text.clone();
-    tokio::spawn(async move {
-        let reason = tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "timeout",
-            _ = cancel_rx => "cancel",
-        };
-        if reason == "timeout" {
+    let reply_all = args.reply_all;
+    let job = crate::utils::job_queue::BackgroundJob::new("respond_to_email", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_user = cloned_user.clone();
+        let cloned_email_id = cloned_email_id.clone();
+        let cloned_response_text = cloned_response_text.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, cloned_user_id, action_id).await {
+                return Ok(());
+            }
             let request = crate::imap_handlers::EmailResponseRequest {
                 email_id: cloned_email_id,
                 response_text: cloned_response_text,
+                reply_all,
             };
             match crate::imap_handlers::respond_to_email(
                 State(cloned_state.clone()),
@@ -417,19 +575,23 @@ pub async fn handle_respond_to_email(
                         None,
                         &cloned_user,
                     ).await {
-                        eprintln!("Failed to send error message: {}", e);
+                        tracing::error!("Failed to send error message: {}", e);
                     }
                 }
             }
+            Ok(())
         }
-        // Remove from map
-        let mut senders = cloned_state.pending_message_senders.lock().await;
-        senders.remove(&cloned_user_id);
     });
-    // Store the cancel sender in the map
-    {
-        let mut senders = state.pending_message_senders.lock().await;
-        senders.insert(user_id, cancel_tx);
+    if let Err(e) = state.job_queue.enqueue_delayed(job, send_delay, cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(&state, user_id, action_id).await;
+        tracing::error!("Failed to queue respond to email: {}", e);
+        return Ok((
+            axum::http::StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            axum::Json(crate::api::twilio_sms::TwilioResponse {
+                message: "Failed to queue the message, please try again".to_string(),
+            })
+        ));
     }
     Ok((
         axum::http::StatusCode::OK,
@@ -454,7 +616,7 @@ pub async fn handle_fetch_specific_email(state: &Arc<AppState>, user_id: i32, qu
     let user_id_clone = user_id.clone();
 
     // Fetch the latest 20 emails with full content
-    match crate::handlers::imap_handlers::fetch_emails_imap(&state_clone, user_id_clone, true, Some(20), false, false).await {
+    match crate::handlers::imap_handlers::fetch_emails_imap(&state_clone, user_id_clone, true, Some(20), false, false, None).await {
         Ok(emails) => {
             if emails.is_empty() {
                 return "No emails found".to_string();
@@ -476,7 +638,7 @@ pub async fn handle_fetch_specific_email(state: &Arc<AppState>, user_id: i32, qu
 
             // Use LLM to select the most relevant email
             match crate::tool_call_utils::utils::select_most_relevant_email(&client, 
-                openai_api_rs::v1::common::GPT4_O.to_string(),
+                crate::tool_call_utils::utils::model_for(state, Some(user_id), crate::tool_call_utils::utils::ModelPurpose::EmailSelection),
                 query, &formatted_emails).await {
                 Ok((selected_email_id, _)) => selected_email_id,
                 Err(e) => {
@@ -499,3 +661,29 @@ pub async fn handle_fetch_specific_email(state: &Arc<AppState>, user_id: i32, qu
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply_target(state: &Arc<AppState>, user_id: i32, sms_body: &str) -> Option<Option<serde_json::Value>> {
+        last_email_reply_args(state, user_id, sms_body)
+            .map(|args| args.map(|args| serde_json::from_str(&args).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn sms_reply_goes_to_the_last_surfaced_email() {
+        let state = crate::test_support::test_state();
+        assert_eq!(reply_target(&state, 1, "Reply to the last email: sounds good"), Some(None));
+
+        remember_surfaced_email(&state, 1, "4101");
+        remember_surfaced_email(&state, 1, "4107");
+        // Another user's emails and non-uid ids don't move the target
+        remember_surfaced_email(&state, 2, "9999");
+        remember_surfaced_email(&state, 1, "Unknown ID");
+
+        let args = reply_target(&state, 1, "reply to last email - sounds good, see you then").unwrap().unwrap();
+        assert_eq!(args["email_id"], "4107");
+        assert_eq!(args["response_text"], "sounds good, see you then");
+        assert_eq!(reply_target(&state, 1, "What's on my calendar?"), None);
+    }
+}
@@ -44,7 +44,7 @@ pub fn get_weather_tool() -> openai_api_rs::v1::chat_completion::Tool {
         "units".to_string(),
         Box::new(types::JSONSchemaDefine {
             schema_type: Some(types::JSONSchemaType::String),
-            description: Some("Units that the weather should be returned as. Should be either 'metric' or 'imperial'".to_string()),
+            description: Some("Units that the weather should be returned as. Should be either 'metric' or 'imperial'. Leave out to use the user's preferred units".to_string()),
             ..Default::default()
         }),
     );
@@ -57,7 +57,7 @@ pub fn get_weather_tool() -> openai_api_rs::v1::chat_completion::Tool {
             parameters: types::FunctionParameters {
                 schema_type: types::JSONSchemaType::Object,
                 properties: Some(weather_properties),
-                required: Some(vec![String::from("location"), String::from("units")]),
+                required: Some(vec![String::from("location")]),
             },
         },
     }
@@ -354,11 +354,16 @@ pub async fn handle_directions_tool(
             Ok(format!("With {}: Duration: {}\nDistance: {}\nDirections:\n{}", effective_mode, duration, distance, formatted_instructions))
         }
         Err((status, AxumJson(err_value))) => {
-            Ok(format!(
+            // The details go to the logs, the user gets something that can be read back to them
+            tracing::warn!(
                 "Error fetching directions (status {}): {}",
                 status,
                 err_value["error"].as_str().unwrap_or("Unknown error")
-            ))
+            );
+            Ok(err_value["message"]
+                .as_str()
+                .unwrap_or(crate::utils::tool_exec::MAPS_UNAVAILABLE_MESSAGE)
+                .to_string())
         }
     }
 }
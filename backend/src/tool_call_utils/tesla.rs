@@ -244,6 +244,85 @@ pub async fn handle_tesla_command(
         return "You haven't connected your Tesla account yet. Please connect it first in the app settings.".to_string();
     }
 
+    route_tesla_command(state, user_id, command, |state, user_id, command| async move {
+        dispatch_tesla_command(&state, user_id, &command).await
+    }).await
+}
+
+/// Queues the command behind a cancel window if the user asked to confirm it, otherwise
+/// hands it to `dispatch` right away
+async fn route_tesla_command<D, Fut>(state: &Arc<AppState>, user_id: i32, command: &str, dispatch: D) -> String
+where
+    D: Fn(Arc<AppState>, i32, String) -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = String> + Send + 'static,
+{
+    let confirm_commands = state.user_core.get_user_settings(user_id)
+        .ok()
+        .and_then(|settings| settings.tesla_confirm_commands);
+    if requires_confirmation(confirm_commands.as_deref(), command) {
+        return queue_tesla_command(state, user_id, command, dispatch).await;
+    }
+
+    dispatch(state.clone(), user_id, command.to_string()).await
+}
+
+/// Commands the user can send, and so also the ones they can ask to confirm first
+pub const TESLA_COMMANDS: [&str; 7] = ["lock", "unlock", "climate_on", "climate_off", "defrost", "remote_start", "charge_status"];
+
+/// How long a command that needs confirmation waits for a cancel
+const TESLA_CONFIRM_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Whether the command is in the user's comma-separated `tesla_confirm_commands`
+pub fn requires_confirmation(confirm_commands: Option<&str>, command: &str) -> bool {
+    confirm_commands.map_or(false, |list| list.split(',').any(|c| c.trim().eq_ignore_ascii_case(command)))
+}
+
+/// Runs the command after the cancel window unless the user cancels it, and tells them how it went
+async fn queue_tesla_command<D, Fut>(state: &Arc<AppState>, user_id: i32, command: &str, dispatch: D) -> String
+where
+    D: Fn(Arc<AppState>, i32, String) -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = String> + Send + 'static,
+{
+    let description = format!("Tesla {}", command.replace('_', " "));
+    let (action_id, cancel_rx) = crate::tool_call_utils::utils::register_pending_action(state, user_id, "tesla_command", command, &description, TESLA_CONFIRM_WINDOW).await;
+    let cloned_state = state.clone();
+    let cloned_command = command.to_string();
+    let dispatch = Arc::new(dispatch);
+    let job = crate::utils::job_queue::BackgroundJob::new("tesla_command", move || {
+        let cloned_state = cloned_state.clone();
+        let cloned_command = cloned_command.clone();
+        let dispatch = dispatch.clone();
+        async move {
+            // The user may have cancelled it at the last moment
+            if !crate::tool_call_utils::utils::claim_pending_action(&cloned_state, user_id, action_id).await {
+                return Ok(());
+            }
+            let result = dispatch(cloned_state.clone(), user_id, cloned_command).await;
+            crate::proactive::utils::send_notification(
+                &cloned_state,
+                user_id,
+                &format!("Tesla command completed: {}", result),
+                "tesla_command_success".to_string(),
+                Some(result),
+            ).await;
+            Ok(())
+        }
+    });
+    if let Err(e) = state.job_queue.enqueue_delayed(job, TESLA_CONFIRM_WINDOW, cancel_rx) {
+        crate::tool_call_utils::utils::complete_pending_action(state, user_id, action_id).await;
+        error!("Failed to queue Tesla command: {}", e);
+        return "Failed to queue the Tesla command, please try again".to_string();
+    }
+    info!("Queued Tesla {} for user {} with a {}s cancel window", command, user_id, TESLA_CONFIRM_WINDOW.as_secs());
+    format!(
+        "Your Tesla will {} in {} seconds. Reply 'cancel' to stop it.",
+        command.replace('_', " "),
+        TESLA_CONFIRM_WINDOW.as_secs()
+    )
+}
+
+/// Wakes the car if needed and runs the command
+async fn dispatch_tesla_command(state: &Arc<AppState>, user_id: i32, command: &str) -> String {
     // Get valid access token
     let access_token = match get_valid_tesla_access_token(state, user_id).await {
         Ok(token) => token,
@@ -649,4 +728,44 @@ fn spawn_climate_monitoring_internal(
     });
 
     state.tesla_monitoring_tasks.insert(user_id, handle);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    /// Records the commands that reached the car instead of calling Tesla
+    fn recording_car() -> (Arc<std::sync::Mutex<Vec<String>>>, impl Fn(Arc<AppState>, i32, String) -> std::future::Ready<String> + Send + Sync + 'static) {
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let car = sent.clone();
+        (sent, move |_, _, command: String| {
+            car.lock().unwrap().push(command.clone());
+            std::future::ready(format!("{} done", command))
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn confirmed_unlock_waits_and_can_be_cancelled_while_lock_runs_at_once() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "tesla@example.com");
+        state.user_core.update_tesla_confirm_commands(user_id, Some("unlock".to_string())).unwrap();
+
+        let (sent, car) = recording_car();
+        assert_eq!(route_tesla_command(&state, user_id, "lock", car).await, "lock done");
+        assert_eq!(*sent.lock().unwrap(), vec!["lock"]);
+
+        let (sent, car) = recording_car();
+        let reply = route_tesla_command(&state, user_id, "unlock", car).await;
+        assert_eq!(reply, "Your Tesla will unlock in 60 seconds. Reply 'cancel' to stop it.");
+        assert!(sent.lock().unwrap().is_empty());
+        let pending = crate::tool_call_utils::utils::list_pending_actions(&state, user_id).await;
+        assert_eq!(pending.iter().map(|(_, d)| d.as_str()).collect::<Vec<_>>(), vec!["Tesla unlock"]);
+
+        let outcome = crate::tool_call_utils::utils::cancel_pending_actions(&state, user_id, None).await;
+        assert_eq!(outcome.cancelled, vec!["Tesla unlock"]);
+        tokio::time::sleep(TESLA_CONFIRM_WINDOW * 2).await;
+        assert!(sent.lock().unwrap().is_empty(), "a cancelled unlock must never reach the car");
+        assert_eq!(state.job_queue.pending_count(), 0);
+    }
+}
@@ -52,6 +52,178 @@ pub fn create_openai_client(
             .map_err(|e| e.into())
 }
 
+/// What an LLM call is used for, each can run on its own model
+#[derive(Clone, Copy, Debug)]
+pub enum ModelPurpose {
+    Summarization,
+    EmailJudgment,
+    EmailSelection,
+    WebSearch,
+}
+
+impl ModelPurpose {
+    pub const ALL: [ModelPurpose; 4] = [
+        ModelPurpose::Summarization,
+        ModelPurpose::EmailJudgment,
+        ModelPurpose::EmailSelection,
+        ModelPurpose::WebSearch,
+    ];
+
+    /// Key used in the user's llm_models setting
+    pub fn key(&self) -> &'static str {
+        match self {
+            ModelPurpose::Summarization => "summarization",
+            ModelPurpose::EmailJudgment => "email_judgment",
+            ModelPurpose::EmailSelection => "email_selection",
+            ModelPurpose::WebSearch => "web_search",
+        }
+    }
+
+    fn env_var(&self) -> &'static str {
+        match self {
+            ModelPurpose::Summarization => "LLM_MODEL_SUMMARIZATION",
+            ModelPurpose::EmailJudgment => "LLM_MODEL_EMAIL_JUDGMENT",
+            ModelPurpose::EmailSelection => "LLM_MODEL_EMAIL_SELECTION",
+            ModelPurpose::WebSearch => "LLM_MODEL_WEB_SEARCH",
+        }
+    }
+
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            ModelPurpose::Summarization => "openai/gpt-4o",
+            ModelPurpose::EmailJudgment => "openai/gpt-4o",
+            ModelPurpose::EmailSelection => "openai/gpt-4o",
+            ModelPurpose::WebSearch => "perplexity/sonar-reasoning-pro",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|purpose| purpose.key() == key)
+    }
+}
+
+/// OpenRouter model ids look like "provider/model-name", optionally with a ":variant" suffix
+pub fn is_valid_model_name(model: &str) -> bool {
+    let Some((provider, name)) = model.split_once('/') else { return false };
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':');
+    !provider.is_empty()
+        && !name.is_empty()
+        && model.len() <= 100
+        && provider.chars().all(allowed)
+        && name.chars().all(allowed)
+}
+
+/// Model to use for `purpose`: the user's own choice first, then the env var, then the built-in default.
+/// Anything that doesn't look like a model id is skipped with a warning.
+pub fn model_for(state: &Arc<AppState>, user_id: Option<i32>, purpose: ModelPurpose) -> String {
+    let user_model = user_id
+        .and_then(|id| state.user_core.get_user_settings(id).ok())
+        .and_then(|settings| settings.llm_models)
+        .and_then(|raw| serde_json::from_str::<HashMap<String, String>>(&raw).ok())
+        .and_then(|models| models.get(purpose.key()).cloned());
+    if let Some(model) = user_model {
+        if is_valid_model_name(&model) {
+            return model;
+        }
+        tracing::warn!("Ignoring invalid {} model '{}' in user settings", purpose.key(), model);
+    }
+    if let Ok(model) = env::var(purpose.env_var()) {
+        if is_valid_model_name(&model) {
+            return model;
+        }
+        tracing::warn!("Ignoring invalid model '{}' in {}", model, purpose.env_var());
+    }
+    purpose.default_model().to_string()
+}
+
+/// Whether a completion error says the model itself is the problem, as opposed to network trouble
+/// or a provider outage that the default model would hit just the same
+fn is_model_unavailable(error: &str) -> bool {
+    let error = error.to_lowercase();
+    [
+        "model_not_found",
+        "model not found",
+        "does not exist",
+        "not a valid model",
+        "invalid model",
+        "unsupported model",
+        "model is not supported",
+        "no endpoints found",
+    ]
+    .iter()
+    .any(|phrase| error.contains(phrase))
+}
+
+/// Runs the completion and, if the provider rejects a configured model, retries once with the default
+pub async fn chat_completion_with_fallback(
+    client: &OpenAIClient,
+    request: chat_completion::ChatCompletionRequest,
+    purpose: ModelPurpose,
+) -> Result<chat_completion::ChatCompletionResponse, openai_api_rs::v1::error::APIError> {
+    let default_model = purpose.default_model();
+    if request.model == default_model {
+        return client.chat_completion(request).await;
+    }
+    let mut fallback_request = request.clone();
+    match client.chat_completion(request).await {
+        Ok(response) => Ok(response),
+        Err(e) if !is_model_unavailable(&e.to_string()) => Err(e),
+        Err(e) => {
+            tracing::warn!(
+                "{} model '{}' failed ({}), falling back to {}",
+                purpose.key(), fallback_request.model, e, default_model
+            );
+            fallback_request.model = default_model.to_string();
+            client.chat_completion(fallback_request).await
+        }
+    }
+}
+
+/// Condenses a conversation transcript into a few sentences so long histories fit in a call's context.
+/// Returns None if the model couldn't be reached, callers should fall back to truncating.
+pub async fn summarize_conversation(state: &Arc<AppState>, user_id: i32, transcript: &str) -> Option<String> {
+    let client = match create_openai_client(state) {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to create client for history summary: {}", e);
+            return None;
+        }
+    };
+    let messages = vec![
+        chat_completion::ChatCompletionMessage {
+            role: chat_completion::MessageRole::system,
+            content: chat_completion::Content::Text(
+                "Summarize this conversation between a user and their assistant in at most 5 short sentences. \
+                Keep names, dates, open requests and anything the assistant promised to do. Reply with the summary only.".to_string()
+            ),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+        chat_completion::ChatCompletionMessage {
+            role: chat_completion::MessageRole::user,
+            content: chat_completion::Content::Text(transcript.to_string()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    ];
+    let request = chat_completion::ChatCompletionRequest::new(
+        model_for(state, Some(user_id), ModelPurpose::Summarization),
+        messages,
+    )
+    .max_tokens(300);
+    match chat_completion_with_fallback(&client, request, ModelPurpose::Summarization).await {
+        Ok(result) => result.choices.first()
+            .and_then(|choice| choice.message.content.clone())
+            .filter(|summary| !summary.trim().is_empty()),
+        Err(e) => {
+            tracing::error!("Failed to summarize conversation history: {}", e);
+            None
+        }
+    }
+}
+
 // Function to create evaluation tool properties
 pub fn create_eval_properties() -> HashMap<String, Box<types::JSONSchemaDefine>> {
     let mut eval_properties = HashMap::new();
@@ -74,17 +246,244 @@ pub fn create_eval_properties() -> HashMap<String, Box<types::JSONSchemaDefine>>
     eval_properties
 }
 
-pub async fn cancel_pending_message(
+/// A delayed outbound action (email, chat message...) the user can still cancel.
+pub struct PendingAction {
+    pub id: u64,
+    /// e.g. "email", "email_reply", "chat_message" or "tesla_command"
+    pub kind: String,
+    /// Who or what it goes to, e.g. the recipient address or contact name
+    pub target: String,
+    pub description: String,
+    pub created_at: i64,
+    /// When it runs unless cancelled first
+    pub scheduled_for: i64,
+    /// Set by `claim_pending_action` when the job starts, after that it can't be cancelled
+    started: bool,
+    cancel_tx: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+/// Started actions are kept this long past their send time so a late cancel can say it was too late
+const STARTED_ACTION_TTL_SECS: i64 = 10 * 60;
+
+/// What `cancel_pending_actions` did, by description
+#[derive(Debug, Default)]
+pub struct CancelOutcome {
+    pub cancelled: Vec<String>,
+    /// Actions that had already started running and went out anyway
+    pub too_late: Vec<String>,
+}
+
+/// A pending action as the scheduled items list shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingActionSummary {
+    pub id: u64,
+    pub kind: String,
+    pub target: String,
+    pub description: String,
+    pub created_at: i64,
+    pub scheduled_for: i64,
+}
+
+/// Digits of something that looks like a phone number, so "+358 40-123 4567" and "00358401234567"
+/// compare equal. None for names and anything too short to be a number.
+fn phone_digits(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit() || " +-().".contains(c)) {
+        return None;
+    }
+    let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+    let digits = digits.strip_prefix("00").map(str::to_string).unwrap_or(digits);
+    if digits.len() < 7 { None } else { Some(digits) }
+}
+
+/// Explains why sending to `destination` would loop back to the user or to Lightfriend itself,
+/// None when the destination is fine. Works for email addresses and phone-number chat names.
+pub fn self_send_rejection(
+    state: &Arc<AppState>,
+    user: &crate::models::user_models::User,
+    destination: &str,
+) -> Option<String> {
+    if destination.contains('@') {
+        let address = destination.parse::<lettre::message::Mailbox>()
+            .map(|mailbox| mailbox.email.to_string())
+            .unwrap_or_else(|_| destination.trim().to_string())
+            .to_lowercase();
+        let mut own_addresses = vec![user.email.trim().to_lowercase()];
+        if let Ok(Some((imap_email, _, _, _))) = state.user_repository.get_imap_credentials(user.id) {
+            own_addresses.push(imap_email.trim().to_lowercase());
+        }
+        if own_addresses.contains(&address) {
+            return Some(format!("{} is your own email address, so I won't send it there. Who should it go to instead?", address));
+        }
+        return None;
+    }
+
+    let digits = phone_digits(destination)?;
+    if phone_digits(&user.phone_number).as_deref() == Some(digits.as_str()) {
+        return Some("That's your own phone number, so I won't send it there. Who should it go to instead?".to_string());
+    }
+    let mut service_numbers = crate::api::twilio_utils::service_numbers();
+    service_numbers.extend(user.preferred_number.clone());
+    if service_numbers.iter().any(|number| phone_digits(number).as_deref() == Some(digits.as_str())) {
+        return Some(crate::utils::branding::render(crate::utils::branding::OWN_SERVICE_NUMBER));
+    }
+    None
+}
+
+/// How long to hold a queued email: the cancel window, stretched to the start of the user's
+/// business hours when they've set some and the window would end outside them. The second
+/// value is the local send time to tell the user when the email was deferred.
+pub fn email_send_delay(
     state: &Arc<AppState>,
     user_id: i32,
-) -> Result<bool, Box<dyn std::error::Error>> {
+    cancel_window: std::time::Duration,
+) -> (std::time::Duration, Option<String>) {
+    let Some(hours) = state.user_core.get_user_settings(user_id).ok()
+        .and_then(|settings| crate::utils::business_hours::BusinessHours::from_settings(&settings))
+    else {
+        return (cancel_window, None);
+    };
+    let timezone: chrono_tz::Tz = state.user_core.get_user_info(user_id).ok()
+        .and_then(|info| info.timezone)
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(chrono_tz::UTC);
+    let now = chrono::Utc::now().with_timezone(&timezone);
+    let due = now + chrono::Duration::from_std(cancel_window).unwrap_or_default();
+    match hours.next_opening(due).and_then(|opening| Some((opening, (opening - now).to_std().ok()?))) {
+        Some((opening, delay)) => (delay, Some(opening.format("%A %H:%M").to_string())),
+        None => (cancel_window, None),
+    }
+}
+
+static NEXT_PENDING_ACTION_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+/// Registers a new pending action for the user and returns its id with the receiver the delayed task waits on.
+/// Each action has its own channel so queueing a second one doesn't orphan the first.
+/// `delay` is how long the job will be held, for showing when it goes out.
+pub async fn register_pending_action(
+    state: &Arc<AppState>,
+    user_id: i32,
+    kind: &str,
+    target: &str,
+    description: &str,
+    delay: std::time::Duration,
+) -> (u64, tokio::sync::oneshot::Receiver<()>) {
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
+    let id = NEXT_PENDING_ACTION_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    let created_at = chrono::Utc::now().timestamp();
     let mut senders = state.pending_message_senders.lock().await;
-    if let Some(sender) = senders.remove(&user_id) {
-        let _ = sender.send(());
-        Ok(true)  // Cancellation occurred
-    } else {
-        Ok(false)  // No pending message to cancel
+    let actions = senders.entry(user_id).or_default();
+    actions.retain(|a| !a.started || a.scheduled_for + STARTED_ACTION_TTL_SECS > created_at);
+    actions.push(PendingAction {
+        id,
+        kind: kind.to_string(),
+        target: target.to_string(),
+        description: description.to_string(),
+        created_at,
+        scheduled_for: created_at + delay.as_secs() as i64,
+        started: false,
+        cancel_tx: Some(cancel_tx),
+    });
+    (id, cancel_rx)
+}
+
+/// Called by the delayed job as it starts. False when the user cancelled it first, then the job
+/// must not run. The pending list lock makes this and `cancel_pending_actions` mutually exclusive,
+/// so an action is either cancelled or runs, never both.
+pub async fn claim_pending_action(state: &Arc<AppState>, user_id: i32, action_id: u64) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    let mut senders = state.pending_message_senders.lock().await;
+    let Some(actions) = senders.get_mut(&user_id) else {
+        return false;
+    };
+    actions.retain(|a| !a.started || a.scheduled_for + STARTED_ACTION_TTL_SECS > now);
+    match actions.iter_mut().find(|a| a.id == action_id) {
+        Some(action) if !action.started => {
+            action.started = true;
+            action.cancel_tx = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Removes the action from the pending list, called when it couldn't be queued.
+pub async fn complete_pending_action(state: &Arc<AppState>, user_id: i32, action_id: u64) {
+    let mut senders = state.pending_message_senders.lock().await;
+    if let Some(actions) = senders.get_mut(&user_id) {
+        actions.retain(|a| a.id != action_id);
+        if actions.is_empty() {
+            senders.remove(&user_id);
+        }
+    }
+}
+
+/// (id, description) of the user's pending actions, oldest first
+pub async fn list_pending_actions(state: &Arc<AppState>, user_id: i32) -> Vec<(u64, String)> {
+    let senders = state.pending_message_senders.lock().await;
+    senders
+        .get(&user_id)
+        .map(|actions| actions.iter().filter(|a| !a.started).map(|a| (a.id, a.description.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// The user's pending actions with when each goes out, soonest first
+pub async fn pending_action_summaries(state: &Arc<AppState>, user_id: i32) -> Vec<PendingActionSummary> {
+    let senders = state.pending_message_senders.lock().await;
+    let mut summaries: Vec<PendingActionSummary> = senders
+        .get(&user_id)
+        .map(|actions| actions.iter().filter(|a| !a.started).map(|a| PendingActionSummary {
+            id: a.id,
+            kind: a.kind.clone(),
+            target: a.target.clone(),
+            description: a.description.clone(),
+            created_at: a.created_at,
+            scheduled_for: a.scheduled_for,
+        }).collect())
+        .unwrap_or_default();
+    summaries.sort_by_key(|a| (a.scheduled_for, a.id));
+    summaries
+}
+
+/// Cancels the given action, or all of the user's pending actions when `action_id` is None.
+/// Actions whose job already claimed them are reported as too late instead.
+pub async fn cancel_pending_actions(
+    state: &Arc<AppState>,
+    user_id: i32,
+    action_id: Option<u64>,
+) -> CancelOutcome {
+    let mut outcome = CancelOutcome::default();
+    let mut senders = state.pending_message_senders.lock().await;
+    let Some(actions) = senders.get_mut(&user_id) else {
+        return outcome;
+    };
+    let (targeted, remaining): (Vec<PendingAction>, Vec<PendingAction>) = actions
+        .drain(..)
+        .partition(|a| action_id.map_or(true, |id| a.id == id));
+    *actions = remaining;
+    for mut action in targeted {
+        if action.started {
+            outcome.too_late.push(action.description.clone());
+            actions.push(action);
+            continue;
+        }
+        if let Some(cancel_tx) = action.cancel_tx.take() {
+            let _ = cancel_tx.send(());
+        }
+        outcome.cancelled.push(action.description);
     }
+    if actions.is_empty() {
+        senders.remove(&user_id);
+    }
+    outcome
+}
+
+/// Cancels everything the user has queued, for the SMS cancel command
+pub async fn cancel_pending_message(
+    state: &Arc<AppState>,
+    user_id: i32,
+) -> Result<CancelOutcome, Box<dyn std::error::Error>> {
+    Ok(cancel_pending_actions(state, user_id, None).await)
 }
 
 // Helper function for boolean deserialization
@@ -329,7 +728,7 @@ pub async fn select_most_relevant_email(
     .tool_choice(chat_completion::ToolChoiceType::Required)
     .max_tokens(200);
 
-    match client.chat_completion(select_req).await {
+    match chat_completion_with_fallback(client, select_req, ModelPurpose::EmailSelection).await {
         Ok(result) => {
             if let Some(tool_calls) = result.choices[0].message.tool_calls.as_ref() {
                 if let Some(first_call) = tool_calls.first() {
@@ -370,3 +769,111 @@ pub fn create_eval_tools() -> Vec<chat_completion::Tool> {
         },
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+    use std::time::Duration;
+
+    #[test]
+    fn only_model_errors_fall_back() {
+        assert!(is_model_unavailable("404 Not Found: {\"error\":{\"code\":\"model_not_found\"}}"));
+        assert!(is_model_unavailable("400 Bad Request: openai/gpt-9 is not a valid model ID"));
+        assert!(is_model_unavailable("404: No endpoints found for some/model."));
+        assert!(!is_model_unavailable("502 Bad Gateway: upstream connect error"));
+        assert!(!is_model_unavailable("error sending request for url (https://openrouter.ai/api/v1/chat/completions)"));
+        assert!(!is_model_unavailable("429 Too Many Requests: rate limited"));
+    }
+
+    #[tokio::test]
+    async fn email_queued_outside_business_hours_is_deferred() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "hours@example.com");
+        let cancel_window = Duration::from_secs(60);
+        // A one hour window two hours from now (UTC, the user has no timezone), so the send is always outside it
+        let start = (chrono::Utc::now().hour() as i32 + 2) % 24;
+        state.user_core.update_email_business_hours(user_id, Some((start, start + 1))).unwrap();
+
+        let (delay, send_time) = email_send_delay(&state, user_id, cancel_window);
+        assert!(delay > cancel_window, "the send must wait for the next window");
+        assert!(send_time.is_some_and(|time| time.ends_with(&format!("{:02}:00", start))));
+    }
+
+    #[tokio::test]
+    async fn email_without_business_hours_goes_after_the_cancel_window() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "anytime@example.com");
+        let cancel_window = Duration::from_secs(60);
+        assert_eq!(email_send_delay(&state, user_id, cancel_window), (cancel_window, None));
+    }
+
+    #[tokio::test]
+    async fn queued_actions_are_cancelled_independently() {
+        let state = crate::test_support::test_state();
+        let delay = Duration::from_secs(60);
+        let (first, mut first_rx) = register_pending_action(&state, 1, "email", "a@example.com", "email to a", delay).await;
+        let (second, mut second_rx) = register_pending_action(&state, 1, "email", "b@example.com", "email to b", delay).await;
+        assert_ne!(first, second);
+
+        let outcome = cancel_pending_actions(&state, 1, Some(first)).await;
+        assert_eq!(outcome.cancelled, vec!["email to a".to_string()]);
+        assert!(first_rx.try_recv().is_ok());
+        assert!(second_rx.try_recv().is_err());
+        assert_eq!(list_pending_actions(&state, 1).await, vec![(second, "email to b".to_string())]);
+
+        let outcome = cancel_pending_actions(&state, 1, Some(second)).await;
+        assert_eq!(outcome.cancelled, vec!["email to b".to_string()]);
+        assert!(list_pending_actions(&state, 1).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_after_the_job_started_is_too_late() {
+        let state = crate::test_support::test_state();
+        let (action_id, _rx) = register_pending_action(&state, 1, "email", "a@example.com", "email to a", Duration::from_secs(60)).await;
+        assert!(claim_pending_action(&state, 1, action_id).await);
+
+        let outcome = cancel_pending_actions(&state, 1, Some(action_id)).await;
+        assert!(outcome.cancelled.is_empty());
+        assert_eq!(outcome.too_late, vec!["email to a".to_string()]);
+        assert!(list_pending_actions(&state, 1).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_action_cannot_be_claimed() {
+        let state = crate::test_support::test_state();
+        let (action_id, _rx) = register_pending_action(&state, 1, "email", "a@example.com", "email to a", Duration::from_secs(60)).await;
+        assert_eq!(cancel_pending_actions(&state, 1, None).await.cancelled.len(), 1);
+        assert!(!claim_pending_action(&state, 1, action_id).await);
+    }
+
+    #[test]
+    fn sends_to_the_users_own_phone_or_email_are_refused() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "Self@Example.com");
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+
+        let own_email = self_send_rejection(&state, &user, "Me <self@example.COM>").unwrap();
+        assert!(own_email.contains("self@example.com is your own email address"));
+        assert!(self_send_rejection(&state, &user, "+1 (555) 555-0123").unwrap().contains("your own phone number"));
+        assert!(self_send_rejection(&state, &user, "0015555550123").is_some());
+
+        assert_eq!(self_send_rejection(&state, &user, "friend@example.com"), None);
+        assert_eq!(self_send_rejection(&state, &user, "+1 555 555 0199"), None);
+        // Chat names are never mistaken for numbers
+        assert_eq!(self_send_rejection(&state, &user, "Mom 5555550123"), None);
+    }
+
+    #[test]
+    fn sends_to_the_users_lightfriend_number_are_refused() {
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "service@example.com");
+        state.user_core.update_preferred_number(user_id, "+358 45 1234567").unwrap();
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+
+        assert_eq!(
+            self_send_rejection(&state, &user, "+358451234567"),
+            Some(crate::utils::branding::render(crate::utils::branding::OWN_SERVICE_NUMBER))
+        );
+    }
+}
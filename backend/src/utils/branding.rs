@@ -0,0 +1,77 @@
+/// Name used when neither ASSISTANT_NAME nor BRAND_NAME is set
+pub const DEFAULT_ASSISTANT_NAME: &str = "Lightfriend";
+
+/// The name users see for the assistant and the service. Self-hosters set ASSISTANT_NAME
+/// (or BRAND_NAME), everything user-facing goes through here.
+pub fn assistant_name() -> String {
+    assistant_name_from(|var| std::env::var(var).ok())
+}
+
+/// The assistant name from the given environment lookup
+fn assistant_name_from(env: impl Fn(&str) -> Option<String>) -> String {
+    ["ASSISTANT_NAME", "BRAND_NAME"]
+        .iter()
+        .filter_map(|var| env(var))
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_ASSISTANT_NAME.to_string())
+}
+
+/// Fills `{assistant}` in a user-facing template with the configured name
+pub fn render(template: &str) -> String {
+    render_as(template, &assistant_name())
+}
+
+fn render_as(template: &str, name: &str) -> String {
+    template.replace("{assistant}", name)
+}
+
+pub const VERIFICATION_CODE_SMS: &str = "Your {assistant} verification code is: {code}. Valid for 5 minutes.";
+pub const PASSWORD_RESET_SMS: &str = "Your {assistant} password reset code is: {code}. Valid for 5 minutes.";
+pub const EMAIL_NOT_CONNECTED: &str = "I couldn't find your email connection. Please set up your email in the {assistant} app settings.";
+pub const EMAIL_CREDENTIALS_INVALID: &str = "Your email credentials have expired or are invalid. Please reconnect your email in the {assistant} app settings. If you're using Gmail, you may need to generate a new app password.";
+pub const VOICE_EMAIL_NOT_CONNECTED: &str = "It looks like you haven't connected your email yet. You can set it up in the {assistant} app settings.";
+pub const VOICE_EMAIL_CREDENTIALS_INVALID: &str = "I couldn't access your email because your credentials have expired or are invalid. Please reconnect your email in the {assistant} app. If you're using Gmail, you may need to generate a new app password.";
+pub const OWN_SERVICE_NUMBER: &str = "That's the {assistant} number, a message there would just come back to me. Who should it go to instead?";
+pub const VOICE_OUT_OF_CREDITS: &str = "Please add more credits to your account to continue on the {assistant} website";
+pub const GOOGLE_RECONNECT: &str = "Your {service} access has expired. Please reconnect {service} in the {assistant} settings.";
+pub const BRIDGE_NOTIFY_PROMPT: &str = "Hi, I'm {assistant}, your friend's AI assistant. This message looks time-sensitive—since they're not currently on their computer, would you like me to send them a notification about it? Reply \"yes\" or \"no.\"";
+pub const REFERRAL_QUESTION: &str = "Where did you hear about {assistant}?";
+
+pub fn verification_code_sms(code: &str) -> String {
+    render(VERIFICATION_CODE_SMS).replace("{code}", code)
+}
+
+pub fn password_reset_sms(code: &str) -> String {
+    render(PASSWORD_RESET_SMS).replace("{code}", code)
+}
+
+pub fn google_reconnect(service: &str) -> String {
+    render(GOOGLE_RECONNECT).replace("{service}", service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_with(vars: &[(&str, &str)]) -> String {
+        assistant_name_from(|key| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string()))
+    }
+
+    #[test]
+    fn configured_name_appears_in_user_facing_messages() {
+        let name = name_with(&[("ASSISTANT_NAME", " Aava ")]);
+        assert_eq!(
+            render_as(VERIFICATION_CODE_SMS, &name).replace("{code}", "123456"),
+            "Your Aava verification code is: 123456. Valid for 5 minutes."
+        );
+        assert_eq!(render_as(REFERRAL_QUESTION, &name), "Where did you hear about Aava?");
+    }
+
+    #[test]
+    fn brand_name_and_default_fill_in_for_a_missing_assistant_name() {
+        assert_eq!(name_with(&[("ASSISTANT_NAME", "  "), ("BRAND_NAME", "Kotiapu")]), "Kotiapu");
+        assert_eq!(name_with(&[("ASSISTANT_NAME", "Aava"), ("BRAND_NAME", "Kotiapu")]), "Aava");
+        assert_eq!(name_with(&[]), DEFAULT_ASSISTANT_NAME);
+    }
+}
@@ -35,6 +35,10 @@ pub struct BridgeMessage {
     pub message_type: String,
     pub room_name: String,
     pub media_url: Option<String>,
+    /// Newer than the user's read receipt and last reply in the room. Only set by
+    /// `fetch_bridge_messages`, messages from other lookups are false.
+    #[serde(default)]
+    pub unread: bool,
 }
 
 
@@ -100,7 +104,17 @@ fn infer_service(room_name: &str, sender_localpart: &str) -> Option<String> {
     None
 }
 
+/// Rooms of a bridge, with the timeout and retries of `matrix_retry`
 pub async fn get_service_rooms(client: &MatrixClient, service: &str) -> Result<Vec<BridgeRoom>> {
+    let operation = format!("load your {} chats", capitalize(service));
+    crate::utils::matrix_retry::with_retry(
+        crate::utils::matrix_retry::policy(),
+        &operation,
+        || load_service_rooms(client, service),
+    ).await
+}
+
+async fn load_service_rooms(client: &MatrixClient, service: &str) -> Result<Vec<BridgeRoom>> {
     let joined_rooms = client.joined_rooms();
     let sender_prefix = get_sender_prefix(service);
     let service_cap = capitalize(service);
@@ -151,7 +165,7 @@ pub async fn get_service_rooms(client: &MatrixClient, service: &str) -> Result<V
     }
     let results = join_all(futures).await;
     let mut rooms: Vec<BridgeRoom> = results.into_iter().flatten().collect();
-    rooms.sort_by_key(|r| std::cmp::Reverse(r.last_activity));
+    rooms.sort_by(recency_then_room_id);
     Ok(rooms)
 }
 
@@ -160,7 +174,9 @@ pub fn find_exact_room(
     search_term: &str,
 ) -> Option<BridgeRoom> {
     let search_term_lower = search_term.trim().to_lowercase();
-    if let Some(room) = bridge_rooms.iter().find(|r| remove_bridge_suffix(r.display_name.as_str()).to_lowercase() == search_term_lower) {
+    if let Some(room) = bridge_rooms.iter()
+        .filter(|r| remove_bridge_suffix(r.display_name.as_str()).to_lowercase() == search_term_lower)
+        .min_by(|a, b| recency_then_room_id(a, b)) {
         tracing::info!("Found exact match for room");
         return Some(room.clone());
     }
@@ -171,32 +187,100 @@ pub fn search_best_match(
     bridge_rooms: &[BridgeRoom],
     search_term: &str,
 ) -> Option<BridgeRoom> {
+    search_best_match_scored(bridge_rooms, search_term).map(|(room, _)| room)
+}
+
+/// Ordering for rooms that match equally well: most recently active first, then by room id
+/// so the same rooms always come out in the same order
+fn recency_then_room_id(a: &BridgeRoom, b: &BridgeRoom) -> std::cmp::Ordering {
+    b.last_activity.cmp(&a.last_activity).then_with(|| a.room_id.cmp(&b.room_id))
+}
+
+/// Same as `search_best_match` but also returns how confident the match is:
+/// 1.0 for an exact name, the name similarity for substring and fuzzy matches
+pub fn search_best_match_scored(
+    bridge_rooms: &[BridgeRoom],
+    search_term: &str,
+) -> Option<(BridgeRoom, f64)> {
     let search_term_lower = search_term.trim().to_lowercase();
     // Try exact match first (fastest)
-    if let Some(room) = bridge_rooms.iter().find(|r| remove_bridge_suffix(r.display_name.as_str()).to_lowercase() == search_term_lower) {
+    if let Some(room) = bridge_rooms.iter()
+        .filter(|r| remove_bridge_suffix(r.display_name.as_str()).to_lowercase() == search_term_lower)
+        .min_by(|a, b| recency_then_room_id(a, b)) {
         tracing::info!("Found exact match for room");
-        return Some(room.clone());
+        return Some((room.clone(), 1.0));
     }
     // Then try substring match
     if let Some(room) = bridge_rooms.iter()
         .filter(|r| remove_bridge_suffix(r.display_name.as_str()).to_lowercase().contains(&search_term_lower))
-        .max_by_key(|r| r.last_activity) {
+        .min_by(|a, b| recency_then_room_id(a, b)) {
         tracing::info!("Found substring match for room");
-        return Some(room.clone());
+        let score = strsim::jaro_winkler(&search_term_lower, &remove_bridge_suffix(room.display_name.as_str()).to_lowercase());
+        return Some((room.clone(), score));
     }
     // Finally try similarity match
     let best_match = bridge_rooms.iter()
         .map(|r| (strsim::jaro_winkler(&search_term_lower, &remove_bridge_suffix(r.display_name.as_str()).to_lowercase()), r))
         .filter(|(score, _)| *score >= 0.7)
-        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        .min_by(|a, b| {
+            b.0.partial_cmp(&a.0)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| recency_then_room_id(a.1, b.1))
+        });
     if let Some((score, room)) = best_match {
         tracing::info!("Found similar match with score {}", score);
-        Some(room.clone())
+        Some((room.clone(), score))
     } else {
         None
     }
 }
 
+/// Matches below this confidence get the contact name spelled out and a longer cancel window
+pub const LOW_CONFIDENCE_MATCH: f64 = 0.9;
+pub const DEFAULT_SEND_DELAY_SECS: u64 = 60;
+pub const LOW_CONFIDENCE_SEND_DELAY_SECS: u64 = 180;
+
+#[derive(Debug, PartialEq)]
+pub enum SendGuard {
+    /// Queue the message with this cancel window
+    Proceed { delay_secs: u64, low_confidence: bool },
+    /// The user has never messaged this contact, ask before queueing anything
+    NeedsConfirmation,
+}
+
+/// Decides how careful to be before messaging a resolved contact
+pub fn send_guard(match_confidence: f64, known_contact: bool, confirmed: bool) -> SendGuard {
+    if !known_contact && !confirmed {
+        return SendGuard::NeedsConfirmation;
+    }
+    let low_confidence = match_confidence < LOW_CONFIDENCE_MATCH;
+    SendGuard::Proceed {
+        delay_secs: if low_confidence { LOW_CONFIDENCE_SEND_DELAY_SECS } else { DEFAULT_SEND_DELAY_SECS },
+        low_confidence,
+    }
+}
+
+/// True if the user has messaged this room before, either through us (tracked in known_contacts)
+/// or directly on the platform, in which case it gets remembered for next time
+pub async fn is_known_contact(state: &Arc<AppState>, user_id: i32, service: &str, room_id: &str) -> bool {
+    if state.user_repository.is_known_contact(user_id, service, room_id).unwrap_or(false) {
+        return true;
+    }
+    match get_latest_sent_message_in_room(service, state, user_id, room_id).await {
+        Ok(Some(_)) => {
+            if let Err(e) = state.user_repository.add_known_contact(user_id, service, room_id) {
+                tracing::error!("Failed to remember known contact: {}", e);
+            }
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            tracing::warn!("Failed to check message history for known contact: {}", e);
+            false
+        }
+    }
+}
+
 pub fn get_best_matches(
     bridge_rooms: &[BridgeRoom],
     search_term: &str,
@@ -215,7 +299,6 @@ pub fn get_best_matches(
 
 }
 
-const AI_PROMPT_TEXT: &str = "Hi, I'm Lightfriend, your friend's AI assistant. This message looks time-sensitive—since they're not currently on their computer, would you like me to send them a notification about it? Reply \"yes\" or \"no.\"";
 
 pub async fn get_triggering_message_in_room(
     service: &str,
@@ -281,7 +364,7 @@ pub async fn get_triggering_message_in_room(
                             MessageType::Text(ref t) => t.body.clone(),
                             _ => continue,
                         };
-                        if body.contains(AI_PROMPT_TEXT) {
+                        if body.contains(&crate::utils::branding::render(crate::utils::branding::BRIDGE_NOTIFY_PROMPT)) {
                             found_prompt = true;
                             continue; // Skip to the next message (older)
                         }
@@ -321,6 +404,7 @@ pub async fn get_triggering_message_in_room(
                             message_type: msgtype.to_string(),
                             room_name: cleaned_room_name,
                             media_url: None,
+                            unread: false,
                         }));
                     }
                 }
@@ -422,6 +506,7 @@ pub async fn get_latest_sent_message_in_room(
                         message_type: msgtype.to_string(),
                         room_name: cleaned_room_name,
                         media_url: None,
+                        unread: false,
                     }));
                 }
             }
@@ -458,6 +543,7 @@ pub async fn fetch_bridge_room_messages(
     let rooms = get_service_rooms(&client, service).await?;
     let matching_room = search_best_match(&rooms, chat_name);
     let user_info = state.user_core.get_user_info(user_id)?;
+    let read_receipts_off = read_receipts_off(state, user_id);
     match matching_room {
         Some(room_info) => {
             let room_id = match matrix_sdk::ruma::OwnedRoomId::try_from(room_info.room_id.as_str()) {
@@ -468,7 +554,7 @@ pub async fn fetch_bridge_room_messages(
                 Some(r) => r,
                 None => return Err(anyhow!("Room not found")),
             };
-            fetch_messages_from_room(service, room, limit, user_info.timezone).await
+            fetch_messages_from_room(service, room, limit, user_info.timezone, read_receipts_off).await
         }
         None => Err(anyhow!("No matching {} room found for '{}'", capitalize(&service), chat_name))
     }
@@ -476,6 +562,54 @@ pub async fn fetch_bridge_room_messages(
 
 use matrix_sdk::notification_settings::RoomNotificationMode;
 
+/// Whether the user wants to check bridge messages without them showing up as read on the platform
+fn read_receipts_off(state: &Arc<AppState>, user_id: i32) -> bool {
+    state.user_core.get_user_settings(user_id)
+        .ok()
+        .and_then(|settings| settings.bridge_read_receipts_off)
+        .unwrap_or(false)
+}
+
+/// Backward /messages options for fetching. With receipts off we only peek at the timeline
+/// and lazy-load members so nothing about the fetch looks like the user opened the chat.
+fn fetch_options(limit: u64, read_receipts_off: bool) -> MessagesOptions {
+    let mut options = MessagesOptions::backward();
+    options.limit = matrix_sdk::ruma::UInt::new(limit).unwrap();
+    if read_receipts_off {
+        options.filter.lazy_load_options = matrix_sdk::ruma::api::client::filter::LazyLoadOptions::Enabled {
+            include_redundant_members: false,
+        };
+    }
+    options
+}
+
+/// How far the user has read a room: their read receipt or last reply there (`room_seen`), and
+/// when they were last online on the platform
+#[derive(Debug, Clone, Copy)]
+pub struct ReadState {
+    pub room_seen: Option<i64>,
+    pub bridge_last_seen: i64,
+}
+
+impl ReadState {
+    fn read_until(&self) -> i64 {
+        self.room_seen.unwrap_or(0).max(self.bridge_last_seen)
+    }
+
+    pub fn is_unread(&self, timestamp: i64) -> bool {
+        timestamp > self.read_until()
+    }
+}
+
+/// Opening line of a spoken or texted message summary, e.g. "You have 4 new messages."
+pub fn new_messages_line(messages: &[BridgeMessage]) -> String {
+    match messages.iter().filter(|m| m.unread).count() {
+        0 => "You have no new messages.".to_string(),
+        1 => "You have 1 new message.".to_string(),
+        n => format!("You have {} new messages.", n),
+    }
+}
+
 pub async fn fetch_bridge_messages(
     service: &str,
     state: &Arc<AppState>,
@@ -496,9 +630,10 @@ pub async fn fetch_bridge_messages(
     let bridge_last_seen = state.user_repository.get_bridge(user_id, service)?
         .and_then(|b| b.last_seen_online)
         .unwrap_or(0) as i64;
+    let read_receipts_off = read_receipts_off(state, user_id);
 
     let service_rooms = get_service_rooms(&client, service).await?;
-    let mut room_infos: Vec<(Room, BridgeRoom, i64)> = Vec::new(); // (room, bridge_room, seen_until)
+    let mut active_rooms: Vec<(Room, BridgeRoom)> = Vec::new();
     for bridge_room in service_rooms {
         let room_id = match matrix_sdk::ruma::OwnedRoomId::try_from(bridge_room.room_id.as_str()) {
             Ok(id) => id,
@@ -508,27 +643,33 @@ pub async fn fetch_bridge_messages(
         if room.user_defined_notification_mode().await == Some(RoomNotificationMode::Mute) {
             continue;
         }
-
+        active_rooms.push((room, bridge_room));
+    }
+    // Already sorted by last_activity desc from get_service_rooms
+    active_rooms.truncate(5);
+    let mut room_infos: Vec<(Room, BridgeRoom, i64, ReadState)> = Vec::new(); // (room, bridge_room, seen_until, read_state)
+    for (room, bridge_room) in active_rooms {
+        // Everything up to the user's read receipt, last reply or last time online on the platform
+        // counts as read, this has to be looked up before the fetch below marks the room read
+        let read_state = ReadState {
+            room_seen: get_room_seen_timestamp(&room, &client).await,
+            bridge_last_seen,
+        };
         // Calculate seen_until: the timestamp up to which messages should be filtered out
         let seen_until = if unread_only {
-            // Get the room's seen timestamp from read receipts and user replies
-            let room_seen = get_room_seen_timestamp(&room, &client).await.unwrap_or(0);
-            // Use the max of: start_time, room_seen, bridge_last_seen
-            start_time.max(room_seen).max(bridge_last_seen)
+            start_time.max(read_state.read_until())
         } else {
             // For live fetching, just use start_time (no filtering by seen status)
             start_time
         };
 
-        room_infos.push((room, bridge_room, seen_until));
+        room_infos.push((room, bridge_room, seen_until, read_state));
     }
-    // Already sorted by last_activity desc from get_service_rooms
-    room_infos.truncate(5);
     // Fetch messages in parallel
     let user_timezone = user_info.timezone.clone();
     let sender_prefix = get_sender_prefix(service);
     let mut futures = Vec::new();
-    for (room, bridge_room, seen_until) in room_infos {
+    for (room, bridge_room, seen_until, read_state) in room_infos {
         let sender_prefix = sender_prefix.clone();
         let user_timezone = user_timezone.clone();
         let room_name = remove_bridge_suffix(&bridge_room.display_name);
@@ -537,8 +678,7 @@ pub async fn fetch_bridge_messages(
             continue;
         }
         futures.push(async move {
-            let mut options = matrix_sdk::room::MessagesOptions::backward();
-            options.limit = matrix_sdk::ruma::UInt::new(50).unwrap(); // Fetch enough to cover filters
+            let options = fetch_options(50, read_receipts_off); // Fetch enough to cover filters
             let mut messages: Vec<BridgeMessage> = Vec::new();
             match room.messages(options).await {
                 Ok(response) => {
@@ -588,6 +728,7 @@ pub async fn fetch_bridge_messages(
                                     message_type: msgtype.to_string(),
                                     room_name: room_name.clone(),
                                     media_url: None,
+                                    unread: read_state.is_unread(timestamp),
                                 });
                                 if messages.len() == 5 {
                                     break;
@@ -613,6 +754,50 @@ pub async fn fetch_bridge_messages(
 use futures::future::join_all;
 use matrix_sdk::room::MessagesOptions;
 
+/// Bridges that can deliver m.image events to the other side
+const MEDIA_CAPABLE_SERVICES: [&str; 5] = ["whatsapp", "telegram", "signal", "messenger", "instagram"];
+pub const MAX_BRIDGE_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Checks an image URL before anything is queued: only http(s) URLs are accepted
+pub fn validate_image_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("Invalid image URL: {}", e))?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return Err(anyhow!("Image URL must be http or https"));
+    }
+    if parsed.host_str().is_none() {
+        return Err(anyhow!("Image URL has no host"));
+    }
+    Ok(())
+}
+
+/// Downloads an image for sending over a bridge, refusing non-images and anything over MAX_BRIDGE_IMAGE_BYTES
+async fn download_bridge_image(url: &str) -> Result<(mime_guess::mime::Mime, Vec<u8>)> {
+    validate_image_url(url)?;
+    let mut resp = reqwest::get(url).await?.error_for_status()?;
+    // Get MIME type from headers before consuming the response
+    let mime: mime_guess::mime::Mime = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| mime_guess::MimeGuess::from_path(url).first_or_octet_stream());
+    if mime.type_() != mime_guess::mime::IMAGE {
+        return Err(anyhow!("Attachment is not an image ({})", mime));
+    }
+    if resp.content_length().map_or(false, |len| len as usize > MAX_BRIDGE_IMAGE_BYTES) {
+        return Err(anyhow!("Image is larger than {} MB", MAX_BRIDGE_IMAGE_BYTES / (1024 * 1024)));
+    }
+    // Read in chunks so a missing or lying Content-Length can't blow past the cap
+    let mut bytes = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if bytes.len() + chunk.len() > MAX_BRIDGE_IMAGE_BYTES {
+            return Err(anyhow!("Image is larger than {} MB", MAX_BRIDGE_IMAGE_BYTES / (1024 * 1024)));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok((mime, bytes))
+}
+
 pub async fn send_bridge_message(
     service: &str,
     state: &Arc<AppState>,
@@ -620,6 +805,52 @@ pub async fn send_bridge_message(
     chat_name: &str,
     message: &str,
     media_url: Option<String>,
+) -> Result<BridgeMessage> {
+    let operation = format!("send your {} message to {}", capitalize(service), chat_name);
+    crate::utils::matrix_retry::with_timeout(
+        crate::utils::matrix_retry::policy().send_timeout,
+        &operation,
+        send_bridge_message_once(service, state, user_id, RoomTarget::Name(chat_name), message, media_url),
+    ).await
+}
+
+/// Like `send_bridge_message` but to a room resolved earlier, for sends that go out later.
+/// `chat_name` is only for messages.
+pub async fn send_bridge_message_to_room(
+    service: &str,
+    state: &Arc<AppState>,
+    user_id: i32,
+    room_id: &str,
+    chat_name: &str,
+    message: &str,
+    media_url: Option<String>,
+) -> Result<BridgeMessage> {
+    let operation = format!("send your {} message to {}", capitalize(service), chat_name);
+    crate::utils::matrix_retry::with_timeout(
+        crate::utils::matrix_retry::policy().send_timeout,
+        &operation,
+        send_bridge_message_once(service, state, user_id, RoomTarget::Id(room_id), message, media_url),
+    ).await
+}
+
+/// Where a message goes: a chat name matched against the user's rooms, or a known room id
+enum RoomTarget<'a> {
+    Name(&'a str),
+    Id(&'a str),
+}
+
+fn joined_room(client: &MatrixClient, room_id: &str) -> Result<Room> {
+    let room_id = matrix_sdk::ruma::OwnedRoomId::try_from(room_id).map_err(|e| anyhow!("Invalid room ID: {}", e))?;
+    client.get_room(&room_id).ok_or_else(|| anyhow!("Room not found"))
+}
+
+async fn send_bridge_message_once(
+    service: &str,
+    state: &Arc<AppState>,
+    user_id: i32,
+    target: RoomTarget<'_>,
+    message: &str,
+    media_url: Option<String>,
 ) -> Result<BridgeMessage> {
     // Get user for timezone info
     tracing::info!("Sending {} message", service);
@@ -629,19 +860,18 @@ pub async fn send_bridge_message(
     if bridge.map(|b| b.status != "connected").unwrap_or(true) {
         return Err(anyhow!("{} bridge is not connected. Please log in first.", capitalize(&service)));
     }
+    let chat_name = match target {
+        RoomTarget::Id(room_id) => {
+            // The chat may have been left since the send was scheduled
+            let room = joined_room(&client, room_id)?;
+            return send_to_room(service, state, user_id, &client, room, message, media_url).await;
+        }
+        RoomTarget::Name(chat_name) => chat_name,
+    };
     let service_rooms = get_service_rooms(&client, service).await?;
     let exact_room = find_exact_room(&service_rooms, chat_name);
     let room = match exact_room {
-        Some(room_info) => {
-            let room_id = match matrix_sdk::ruma::OwnedRoomId::try_from(room_info.room_id.as_str()) {
-                Ok(id) => id,
-                Err(e) => return Err(anyhow!("Invalid room ID: {}", e)),
-            };
-            match client.get_room(&room_id) {
-                Some(r) => r,
-                None => return Err(anyhow!("Room not found")),
-            }
-        }
+        Some(room_info) => joined_room(&client, &room_info.room_id)?,
         None => {
             let suggestions = get_best_matches(&service_rooms, chat_name);
             let error_msg = if suggestions.is_empty() {
@@ -657,48 +887,68 @@ pub async fn send_bridge_message(
             return Err(anyhow!(error_msg));
         }
     };
+    send_to_room(service, state, user_id, &client, room, message, media_url).await
+}
+
+/// An m.image event for an uploaded image, with the message as its caption in the same event
+fn image_message(
+    caption: &str,
+    mxc: matrix_sdk::ruma::OwnedMxcUri,
+    mime: &mime_guess::mime::Mime,
+    size: usize,
+) -> matrix_sdk::ruma::events::room::message::RoomMessageEventContent {
+    use matrix_sdk::ruma::events::room::message::{ImageMessageEventContent, RoomMessageEventContent};
+    let mut img = ImageMessageEventContent::plain(caption.to_owned(), mxc);
+    // Basic metadata so bridges & clients know the size
+    let mut imageinfo = matrix_sdk::ruma::events::room::ImageInfo::new();
+    imageinfo.size = Some(matrix_sdk::ruma::UInt::new(size as u64).unwrap_or_default());
+    imageinfo.mimetype = Some(mime.to_string());
+    img.info = Some(Box::new(imageinfo));
+    RoomMessageEventContent::new(MessageType::Image(img))
+}
+
+async fn send_to_room(
+    service: &str,
+    state: &Arc<AppState>,
+    user_id: i32,
+    client: &MatrixClient,
+    room: Room,
+    message: &str,
+    media_url: Option<String>,
+) -> Result<BridgeMessage> {
     use matrix_sdk::{
-        ruma::events::room::message::{
-            RoomMessageEventContent, MessageType, ImageMessageEventContent,
-        },
+        ruma::events::room::message::RoomMessageEventContent,
     };
+    let mut media_url = media_url;
+    let mut sent_text_only_note = false;
+    if media_url.is_some() && !MEDIA_CAPABLE_SERVICES.contains(&service) {
+        // Still deliver the text, just tell the recipient what's missing
+        tracing::info!("{} bridge doesn't support images, sending text only", service);
+        let note = format!("{}\n(An image was attached but can't be sent over {}.)", message, capitalize(service));
+        room.send(RoomMessageEventContent::text_plain(note)).await?;
+        media_url = None;
+        sent_text_only_note = true;
+    }
     if let Some(url) = media_url {
         // ── 1. Download the image and get MIME type ────────────────────────────────
-        let resp = reqwest::get(&url).await?;
-        // Get MIME type from headers before consuming the response
-        let mime: mime_guess::mime::Mime = resp
-            .headers()
-            .get(reqwest::header::CONTENT_TYPE)
-            .and_then(|h| h.to_str().ok())
-            .and_then(|s| s.parse().ok())
-            .unwrap_or_else(|| mime_guess::MimeGuess::from_path(&url).first_or_octet_stream());
-        // Now consume the response to get the bytes
-        let bytes = resp.bytes().await?;
+        let (mime, bytes) = download_bridge_image(&url).await?;
         let size = bytes.len();
         // ── 2. Upload to the homeserver ──────────────────────────────────────────
         let upload_resp = client
             .media()
-            .upload(&mime, bytes.to_vec(), None)
+            .upload(&mime, bytes, None)
             .await?;
         let mxc: matrix_sdk::ruma::OwnedMxcUri = upload_resp.content_uri;
-        // ── 4. Build the image-message content with caption in *one* event ──────
-        let mut img = ImageMessageEventContent::plain(
-            message.to_owned(), // ← this is the caption / body
-            mxc,
-        );
-        // Optional but nice: add basic metadata so bridges & clients know the size
-        let mut imageinfo = matrix_sdk::ruma::events::room::ImageInfo::new();
-        imageinfo.size = Some(matrix_sdk::ruma::UInt::new(size as u64).unwrap_or_default());
-        img.info = Some(Box::new(imageinfo));
-        // Wrap it as a generic “m.room.message”
-        let content = RoomMessageEventContent::new(MessageType::Image(img));
-        // ── 5. Send it ───────────────────────────────────────────────────────────
-        room.send(content).await?;
-    } else {
+        // ── 4. Send it ───────────────────────────────────────────────────────────
+        room.send(image_message(message, mxc, &mime, size)).await?;
+    } else if !sent_text_only_note {
         // plain text
         room.send(RoomMessageEventContent::text_plain(message)).await?;
     }
     tracing::debug!("Message sent!");
+    if let Err(e) = state.user_repository.add_known_contact(user_id, service, room.room_id().as_str()) {
+        tracing::error!("Failed to remember known contact: {}", e);
+    }
     let user_info= state.user_core.get_user_info(user_id)?;
     let current_timestamp = chrono::Utc::now().timestamp();
     // Return the sent message details
@@ -711,6 +961,7 @@ pub async fn send_bridge_message(
         message_type: "text".to_string(),
         room_name: room.display_name().await?.to_string(),
         media_url: None,
+        unread: false,
     })
 }
 
@@ -724,11 +975,11 @@ async fn fetch_messages_from_room(
     room: matrix_sdk::room::Room,
     limit: Option<u64>,
     timezone: Option<String>,
+    read_receipts_off: bool,
 ) -> Result<(Vec<BridgeMessage>, String)> {
     let room_name = room.display_name().await?.to_string();
     let sender_prefix = get_sender_prefix(service);
-    let mut options = MessagesOptions::backward();
-    options.limit = matrix_sdk::ruma::UInt::new(limit.unwrap_or(20)).unwrap();
+    let options = fetch_options(limit.unwrap_or(20), read_receipts_off);
 
     let response = room.messages(options).await?;
     
@@ -773,6 +1024,7 @@ async fn fetch_messages_from_room(
                     message_type: msgtype.to_string(),
                     room_name: room_name.clone(),
                     media_url: None,
+                    unread: false,
                 })
             } else {
                 None
@@ -836,6 +1088,32 @@ pub async fn get_room_seen_timestamp(
     seen_until
 }
 
+/// What to do with an incoming bridge message before the content filters run
+#[derive(Debug, PartialEq)]
+pub enum RoomNotificationRule {
+    /// The user asked to hear about every message in this chat
+    Always { noti_type: Option<String> },
+    /// The user turned this chat off in Lightfriend
+    Never,
+    /// The chat is muted in the messaging app
+    Muted,
+    /// No override, keywords then priority senders then waiting checks then criticality decide
+    Filters,
+}
+
+/// Precedence: room override > mute > keyword > priority sender > waiting check.
+/// Only the first two are decided here, the rest are content-based and run later in the handler.
+pub fn resolve_room_rule(pref: Option<&crate::models::user_models::RoomNotificationPref>, muted: bool) -> RoomNotificationRule {
+    match pref.map(|p| p.mode.as_str()) {
+        Some("always") => RoomNotificationRule::Always {
+            noti_type: pref.and_then(|p| p.noti_type.clone()),
+        },
+        Some("never") => RoomNotificationRule::Never,
+        _ if muted => RoomNotificationRule::Muted,
+        _ => RoomNotificationRule::Filters,
+    }
+}
+
 pub async fn handle_bridge_message(
     event: OriginalSyncRoomMessageEvent,
     room: Room,
@@ -843,10 +1121,7 @@ pub async fn handle_bridge_message(
     state: Arc<AppState>,
 ) {
     tracing::debug!("Entering bridge message handler");
-    if room.user_defined_notification_mode().await == Some(RoomNotificationMode::Mute) {
-        tracing::info!("Skipping message from a muted room");
-        return;
-    }
+    let muted = room.user_defined_notification_mode().await == Some(RoomNotificationMode::Mute);
     // Check message age
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -880,6 +1155,24 @@ pub async fn handle_bridge_message(
     let user_id = user.id;
     // New: Check if this is a bridge management room
     let room_id_str = room.room_id().to_string();
+    let room_pref = state.user_repository
+        .get_room_notification_pref(user_id, &room_id_str)
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to get room notification preference: {}", e);
+            None
+        });
+    let room_rule = resolve_room_rule(room_pref.as_ref(), muted);
+    match room_rule {
+        RoomNotificationRule::Never => {
+            tracing::info!("Skipping message from a room the user turned off");
+            return;
+        }
+        RoomNotificationRule::Muted => {
+            tracing::info!("Skipping message from a muted room");
+            return;
+        }
+        _ => {}
+    }
     let bridge_types = vec!["signal", "telegram", "whatsapp"];
     let mut bridges = Vec::new();
     for bridge_type in &bridge_types {
@@ -1158,7 +1451,8 @@ pub async fn handle_bridge_message(
     if user_id == 1 {
         println!("members: {}", member_count);
     }
-    if member_count > 3 {
+    let always_notify = matches!(room_rule, RoomNotificationRule::Always { .. });
+    if member_count > 3 && !always_notify {
         let is_mentioned = event.content.mentions.as_ref()
             .map(|m| m.user_ids.contains(&matrix_user_id))
             .unwrap_or(false);
@@ -1184,7 +1478,7 @@ pub async fn handle_bridge_message(
             let room_id_str = room.room_id().as_str();
             match get_latest_sent_message_in_room(&service, &state, user_id, room_id_str).await {
                 Ok(Some(prev_msg)) => {
-                    if prev_msg.content.contains("Hi, I'm Lightfriend, your friend's AI assistant. This message looks time-sensitive—since they're not currently on their computer, would you like me to send them a notification about it? Reply \"yes\" or \"no.\"") {
+                    if prev_msg.content.contains(&crate::utils::branding::render(crate::utils::branding::BRIDGE_NOTIFY_PROMPT)) {
                         // Fetch the triggering message
                         match get_triggering_message_in_room(&service, &state, user_id, room_id_str).await {
                             Ok(Some(triggering_msg)) => {
@@ -1242,25 +1536,82 @@ pub async fn handle_bridge_message(
         .to_string();
     let waiting_checks = state.user_repository.get_waiting_checks(user_id, "messaging").unwrap_or(Vec::new());
     let priority_senders = state.user_repository.get_priority_senders(user_id, &service).unwrap_or(Vec::new());
-    fn trim_for_sms(service: &str, sender: &str, content: &str) -> String {
-        let prefix = format!("{} from ", capitalize(&service));
-        let separator = ": ";
-        let max_len = 157;
-        let static_len = prefix.len() + separator.len();
-        let mut remaining = max_len - static_len;
-        // Reserve up to 30 chars for sender
-        let mut sender_trimmed = sender.chars().take(30).collect::<String>();
-        if sender.len() > sender_trimmed.len() {
-            sender_trimmed.push('…');
+    let service_cap = capitalize(&service);
+    let item_key = format!("{}:{}", service, event.event_id);
+    // Room override beats every content filter
+    if let RoomNotificationRule::Always { noti_type } = &room_rule {
+        let suffix = match noti_type.as_deref() {
+            Some("call") => "_call",
+            _ => "_sms",
+        };
+        let notification_type = format!("{}_room{}", service, suffix);
+        if !crate::proactive::utils::should_notify_item(&state, user_id, &item_key, &content) {
+            return;
         }
-        remaining = remaining.saturating_sub(sender_trimmed.len());
-        let mut content_trimmed = content.chars().take(remaining).collect::<String>();
-        if content.len() > content_trimmed.len() {
-            content_trimmed.push('…');
+        if let Err(e) = crate::utils::usage::check_user_credits(&state, &user, "noti_msg", None).await {
+            tracing::warn!("User {} does not have enough credits for room notification: {}", user_id, e);
+            return;
         }
-        format!("{}{}{}{}", prefix, sender_trimmed, separator, content_trimmed)
+        let message = trim_for_sms(&service, &chat_name, &content);
+        let first_message = format!("Hello, you have a new {} message in {}.", service_cap, chat_name);
+        let opening_context = crate::utils::call_templates::CallOpeningContext {
+            sender: Some(chat_name.clone()),
+            service: Some(service_cap.clone()),
+            ..Default::default()
+        };
+        let state_clone = state.clone();
+        tokio::spawn(async move {
+            crate::proactive::utils::send_notification_with_context(
+                &state_clone,
+                user_id,
+                &message,
+                notification_type,
+                Some(first_message),
+                opening_context,
+            ).await;
+        });
+        return;
+    }
+    // Keyword actions next, an ignore or digest_only also beats priority senders
+    let keywords = state.user_repository.get_keywords(user_id, &service).unwrap_or(Vec::new());
+    match crate::proactive::utils::keyword_action(&keywords, &format!("{} {} {}", chat_name, sender_name, content)) {
+        Some((crate::proactive::utils::KeywordAction::Ignore, keyword))
+        | Some((crate::proactive::utils::KeywordAction::DigestOnly, keyword)) => {
+            tracing::info!("{} message for user {} matched keyword set to {}, not notifying", service_cap, user_id, keyword.action);
+            return;
+        }
+        Some((action, keyword)) => {
+            let suffix = if action == crate::proactive::utils::KeywordAction::NotifyCall { "_call" } else { "_sms" };
+            let notification_type = format!("{}_keyword{}", service, suffix);
+            if !crate::proactive::utils::should_notify_item(&state, user_id, &item_key, &content) {
+                return;
+            }
+            if let Err(e) = crate::utils::usage::check_user_credits(&state, &user, "noti_msg", None).await {
+                tracing::warn!("User {} does not have enough credits for keyword notification: {}", user_id, e);
+                return;
+            }
+            let message = trim_for_sms(&service, &chat_name, &content);
+            let first_message = format!("Hello, you have a {} message about {} in {}.", service_cap, keyword.keyword, chat_name);
+            let opening_context = crate::utils::call_templates::CallOpeningContext {
+                sender: Some(chat_name.clone()),
+                service: Some(service_cap.clone()),
+                ..Default::default()
+            };
+            let state_clone = state.clone();
+            tokio::spawn(async move {
+                crate::proactive::utils::send_notification_with_context(
+                    &state_clone,
+                    user_id,
+                    &message,
+                    notification_type,
+                    Some(first_message),
+                    opening_context,
+                ).await;
+            });
+            return;
+        }
+        None => {}
     }
-    let service_cap = capitalize(&service);
     // FAST CHECKS SECOND - Check priority senders if active
     for priority_sender in &priority_senders {
         if priority_sender.noti_mode == "all" {
@@ -1275,6 +1626,9 @@ pub async fn handle_bridge_message(
                 };
                 let notification_type = format!("{}_priority{}", service, suffix);
             
+                if !crate::proactive::utils::should_notify_item(&state, user_id, &item_key, &content) {
+                    return;
+                }
                 // Check if user has enough credits for notification
                 match crate::utils::usage::check_user_credits(&state, &user, "noti_msg", None).await {
                     Ok(()) => {
@@ -1283,16 +1637,22 @@ pub async fn handle_bridge_message(
                         let content_clone = content.clone();
                         let message = trim_for_sms(&service, &priority_sender.sender, &content_clone);
                         let first_message = format!("Hello, you have an important {} message from {}.", service_cap, priority_sender.sender);
+                        let opening_context = crate::utils::call_templates::CallOpeningContext {
+                            sender: Some(priority_sender.sender.clone()),
+                            service: Some(service_cap.clone()),
+                            ..Default::default()
+                        };
                     
                         // Spawn a new task for sending notification
                         tokio::spawn(async move {
                             // Send the notification
-                            crate::proactive::utils::send_notification(
+                            crate::proactive::utils::send_notification_with_context(
                                 &state_clone,
                                 user_id,
                                 &message,
                                 notification_type,
                                 Some(first_message),
+                                opening_context,
                             ).await;
                         
                         });
@@ -1328,9 +1688,12 @@ pub async fn handle_bridge_message(
                 };
                 let notification_type = format!("{}_waiting_check{}", service, suffix);
             
-                // Delete the matched waiting check
-                if let Err(e) = state.user_repository.delete_waiting_check_by_id(user_id, check_id) {
-                    tracing::error!("Failed to delete waiting check {}: {}", check_id, e);
+                // Resolve the matched waiting check so it isn't matched again
+                if let Err(e) = state.user_repository.resolve_waiting_check(user_id, check_id, "auto") {
+                    tracing::error!("Failed to resolve waiting check {}: {}", check_id, e);
+                }
+                if !crate::proactive::utils::should_notify_item(&state, user_id, &item_key, &content) {
+                    return;
                 }
             
                 // Send notification
@@ -1400,6 +1763,9 @@ pub async fn handle_bridge_message(
                     }
                 }
 
+                if !crate::proactive::utils::should_notify_item(&state, user_id, &item_key, &content) {
+                    return;
+                }
                 let message = message_opt.unwrap_or(format!("Critical {} message found, failed to get content, but you can check your {} to see it.", service_cap, service));
                 let first_message = first_message_opt.unwrap_or(format!("Hey, I found some critical {} message.", service_cap));
 
@@ -1431,8 +1797,20 @@ pub async fn search_bridge_rooms(
     if bridge.map(|b| b.status != "connected").unwrap_or(true) {
         return Err(anyhow!("{} bridge is not connected. Please log in first.", capitalize(&service)));
     }
-    let client = crate::utils::matrix_auth::get_cached_client(user_id, &state).await?;
+    let client = crate::utils::matrix_retry::with_timeout(
+        crate::utils::matrix_retry::policy().timeout,
+        "connect to the messaging bridge",
+        crate::utils::matrix_auth::get_cached_client(user_id, &state),
+    ).await?;
     let all_rooms = get_service_rooms(&client, service).await?;
+    let matching_rooms = rank_matching_rooms(all_rooms, search_term);
+    tracing::info!("Found {} matching {} rooms", matching_rooms.len(), capitalize(&service));
+    Ok(matching_rooms)
+}
+
+/// Rooms whose name matches the search term, best match first. Equally good matches are ordered
+/// by last activity and room id, so "contact 2" is the same room when the search is repeated.
+fn rank_matching_rooms(all_rooms: Vec<BridgeRoom>, search_term: &str) -> Vec<BridgeRoom> {
     let search_term_lower = search_term.trim().to_lowercase();
     // Single-pass matching with prioritized results
     let mut matching_rooms: Vec<(f64, BridgeRoom)> = all_rooms
@@ -1457,15 +1835,114 @@ pub async fn search_bridge_rooms(
             }
         })
         .collect();
-    // Sort by match quality (higher score = better match) and then by last activity
+    // Sort by match quality (higher score = better match), then by last activity and room id
     matching_rooms.sort_by(|a, b| {
         b.0.partial_cmp(&a.0)
             .unwrap_or(std::cmp::Ordering::Equal)
-            .then(b.1.last_activity.cmp(&a.1.last_activity))
+            .then_with(|| recency_then_room_id(&a.1, &b.1))
     });
-    tracing::info!("Found {} matching {} rooms", matching_rooms.len(), capitalize(&service));
-   
-    Ok(matching_rooms.into_iter().map(|(_, room)| room).collect())
+    matching_rooms.into_iter().map(|(_, room)| room).collect()
+}
+
+/// Bridges that can be grouped into unified contacts
+pub const CONTACT_SERVICES: [&str; 5] = ["whatsapp", "telegram", "signal", "messenger", "instagram"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContactPlatform {
+    pub service: String,
+    pub room_id: String,
+    pub display_name: String,
+    pub last_activity: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnifiedContact {
+    pub name: String,
+    pub platforms: Vec<ContactPlatform>,
+    pub last_activity: i64,
+    pub last_activity_formatted: String,
+}
+
+/// Name used to decide if rooms on different platforms are the same person:
+/// bridge suffix removed, lowercased, emoji/punctuation dropped and whitespace collapsed
+pub fn normalize_contact_name(display_name: &str) -> String {
+    let name = remove_bridge_suffix(display_name);
+    let name = name
+        .trim_end_matches("(Signal)")
+        .trim_end_matches("(TG)")
+        .trim();
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Groups (service, room) pairs by normalized name. The contact keeps the name of its most
+/// recently active room and contacts are sorted by last activity, newest first.
+pub fn merge_contacts(rooms: Vec<(String, BridgeRoom)>, timezone: Option<String>) -> Vec<UnifiedContact> {
+    let mut grouped: std::collections::HashMap<String, Vec<ContactPlatform>> = std::collections::HashMap::new();
+    for (service, room) in rooms {
+        let key = normalize_contact_name(&room.display_name);
+        if key.is_empty() {
+            continue;
+        }
+        grouped.entry(key).or_default().push(ContactPlatform {
+            service,
+            room_id: room.room_id,
+            display_name: remove_bridge_suffix(&room.display_name),
+            last_activity: room.last_activity,
+        });
+    }
+    let mut contacts: Vec<UnifiedContact> = grouped
+        .into_values()
+        .map(|mut platforms| {
+            platforms.sort_by(|a, b| {
+                b.last_activity.cmp(&a.last_activity)
+                    .then(a.service.cmp(&b.service))
+                    .then_with(|| a.room_id.cmp(&b.room_id))
+            });
+            let last_activity = platforms[0].last_activity;
+            UnifiedContact {
+                name: platforms[0].display_name.clone(),
+                last_activity,
+                last_activity_formatted: format_timestamp(last_activity, timezone.clone()),
+                platforms,
+            }
+        })
+        .collect();
+    contacts.sort_by(|a, b| b.last_activity.cmp(&a.last_activity).then(a.name.cmp(&b.name)));
+    contacts
+}
+
+/// Contacts across every connected bridge, with the platforms each one is reachable on.
+/// A bridge that fails to load is logged and skipped so one broken bridge doesn't hide the rest.
+pub async fn unified_contacts(
+    state: &Arc<AppState>,
+    user_id: i32,
+) -> Result<Vec<UnifiedContact>> {
+    let connected: Vec<&str> = CONTACT_SERVICES
+        .iter()
+        .copied()
+        .filter(|service| {
+            matches!(state.user_repository.get_bridge(user_id, service), Ok(Some(b)) if b.status == "connected")
+        })
+        .collect();
+    if connected.is_empty() {
+        return Ok(Vec::new());
+    }
+    let client = crate::utils::matrix_auth::get_cached_client(user_id, &state).await?;
+    let mut rooms = Vec::new();
+    for service in connected {
+        match get_service_rooms(&client, service).await {
+            Ok(service_rooms) => rooms.extend(service_rooms.into_iter().map(|room| (service.to_string(), room))),
+            Err(e) => tracing::warn!("Failed to get {} rooms for contacts: {}", service, e),
+        }
+    }
+    let timezone = state.user_core.get_user_info(user_id)?.timezone;
+    Ok(merge_contacts(rooms, timezone))
 }
 
 pub async fn fetch_recent_bridge_contacts(
@@ -1514,6 +1991,26 @@ pub async fn fetch_recent_bridge_contacts(
     Ok(room_names)
 }
 
+/// SMS text of a chat message notification, cut to fit a single segment
+pub fn trim_for_sms(service: &str, sender: &str, content: &str) -> String {
+    let prefix = format!("{} from ", capitalize(&service));
+    let separator = ": ";
+    let max_len = 157;
+    let static_len = prefix.len() + separator.len();
+    let mut remaining = max_len - static_len;
+    // Reserve up to 30 chars for sender
+    let mut sender_trimmed = sender.chars().take(30).collect::<String>();
+    if sender.len() > sender_trimmed.len() {
+        sender_trimmed.push('…');
+    }
+    remaining = remaining.saturating_sub(sender_trimmed.len());
+    let mut content_trimmed = content.chars().take(remaining).collect::<String>();
+    if content.len() > content_trimmed.len() {
+        content_trimmed.push('…');
+    }
+    format!("{}{}{}{}", prefix, sender_trimmed, separator, content_trimmed)
+}
+
 pub fn capitalize(s: &str) -> String {
     let mut c = s.chars();
     match c.next() {
@@ -1521,3 +2018,233 @@ pub fn capitalize(s: &str) -> String {
         Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    #[test]
+    fn unread_count_follows_the_read_receipt_and_last_seen() {
+        let message = |timestamp: i64, read_state: ReadState| BridgeMessage {
+            sender: "@whatsapp_358401234567:example.com".to_string(),
+            sender_display_name: "whatsapp_358401234567".to_string(),
+            content: format!("message at {}", timestamp),
+            timestamp,
+            formatted_timestamp: String::new(),
+            message_type: "text".to_string(),
+            room_name: "Anna".to_string(),
+            media_url: None,
+            unread: read_state.is_unread(timestamp),
+        };
+        let timestamps = [1_000, 2_000, 3_000, 4_000, 5_000, 6_000];
+
+        // Read receipt on the message at 2000, last online on WhatsApp before that
+        let receipt = ReadState { room_seen: Some(2_000), bridge_last_seen: 1_500 };
+        let messages: Vec<BridgeMessage> = timestamps.iter().map(|t| message(*t, receipt)).collect();
+        assert_eq!(messages.iter().filter(|m| m.unread).count(), 4);
+        assert_eq!(new_messages_line(&messages), "You have 4 new messages.");
+
+        // Seen on the phone after the receipt, only the newest one is left
+        let seen_on_phone = ReadState { room_seen: Some(2_000), bridge_last_seen: 5_500 };
+        let messages: Vec<BridgeMessage> = timestamps.iter().map(|t| message(*t, seen_on_phone)).collect();
+        assert_eq!(new_messages_line(&messages), "You have 1 new message.");
+
+        // No receipt or reply in the room at all
+        let never_read = ReadState { room_seen: None, bridge_last_seen: 0 };
+        assert!(timestamps.iter().all(|t| never_read.is_unread(*t)));
+        let read = ReadState { room_seen: Some(6_000), bridge_last_seen: 0 };
+        assert_eq!(new_messages_line(&[message(6_000, read)]), "You have no new messages.");
+    }
+
+    #[test]
+    fn repeated_room_search_returns_the_same_order() {
+        let room = |room_id: &str, display_name: &str, last_activity: i64| BridgeRoom {
+            room_id: room_id.to_string(),
+            display_name: display_name.to_string(),
+            last_activity,
+            last_activity_formatted: String::new(),
+        };
+        // Two exact "Anna" matches, an older and a newer "Anna K" with the same activity as each other
+        let rooms = vec![
+            room("!c:example.com", "Anna K (WA)", 100),
+            room("!b:example.com", "Anna (WA)", 50),
+            room("!d:example.com", "Anna K (WA)", 100),
+            room("!a:example.com", "Anna (WA)", 50),
+            room("!e:example.com", "Bob (WA)", 300),
+        ];
+        let ids = |rooms: Vec<BridgeRoom>| rooms.into_iter().map(|r| r.room_id).collect::<Vec<_>>();
+
+        let first = ids(rank_matching_rooms(rooms.clone(), "anna"));
+        assert_eq!(first, vec!["!a:example.com", "!b:example.com", "!c:example.com", "!d:example.com"]);
+        let mut reversed = rooms;
+        reversed.reverse();
+        assert_eq!(ids(rank_matching_rooms(reversed, " Anna ")), first);
+    }
+
+    #[test]
+    fn receipts_off_only_peeks_at_the_timeline() {
+        let options = fetch_options(20, true);
+        assert_eq!(options.limit, matrix_sdk::ruma::UInt::new(20).unwrap());
+        assert!(matches!(
+            options.filter.lazy_load_options,
+            matrix_sdk::ruma::api::client::filter::LazyLoadOptions::Enabled { include_redundant_members: false }
+        ));
+
+        let options = fetch_options(50, false);
+        assert_eq!(options.limit, matrix_sdk::ruma::UInt::new(50).unwrap());
+        assert!(matches!(
+            options.filter.lazy_load_options,
+            matrix_sdk::ruma::api::client::filter::LazyLoadOptions::Disabled
+        ));
+    }
+
+    fn room(service: &str, room_id: &str, display_name: &str, last_activity: i64) -> (String, BridgeRoom) {
+        (service.to_string(), BridgeRoom {
+            room_id: room_id.to_string(),
+            display_name: display_name.to_string(),
+            last_activity,
+            last_activity_formatted: String::new(),
+        })
+    }
+
+    #[test]
+    fn contact_names_match_across_bridges() {
+        assert_eq!(normalize_contact_name("Anna Virtanen (WA)"), "anna virtanen");
+        assert_eq!(normalize_contact_name("anna  virtanen 🌸 (Telegram)"), "anna virtanen");
+        assert_eq!(normalize_contact_name("Anna Virtanen (Signal)"), "anna virtanen");
+        assert_eq!(normalize_contact_name("🌸"), "");
+    }
+
+    #[test]
+    fn same_person_on_several_bridges_is_one_contact() {
+        let rooms = vec![
+            room("whatsapp", "!wa-anna", "Anna Virtanen (WA)", 100),
+            room("telegram", "!tg-anna", "anna virtanen 🌸 (Telegram)", 300),
+            room("signal", "!sig-bob", "Bob (Signal)", 200),
+            room("signal", "!sig-emoji", "🌸", 400),
+        ];
+        let contacts = merge_contacts(rooms, None);
+
+        let names: Vec<&str> = contacts.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["anna virtanen 🌸", "Bob (Signal)"]);
+        let anna = &contacts[0];
+        assert_eq!(anna.last_activity, 300);
+        let services: Vec<&str> = anna.platforms.iter().map(|p| p.service.as_str()).collect();
+        assert_eq!(services, vec!["telegram", "whatsapp"]);
+        assert_eq!(contacts[1].platforms.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn receipts_off_setting_reaches_the_fetch() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "receipts@example.com");
+        assert!(!read_receipts_off(&state, user_id));
+
+        state.user_core.update_bridge_read_receipts_off(user_id, true).unwrap();
+        assert!(read_receipts_off(&state, user_id));
+    }
+
+    #[tokio::test]
+    async fn loose_match_to_a_new_contact_needs_confirmation() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "guard@example.com");
+        let rooms: Vec<BridgeRoom> = vec![
+            room("whatsapp", "!wa-anna", "Anna Virtanen (WA)", 100).1,
+            room("whatsapp", "!wa-bob", "Bob (WA)", 200).1,
+        ];
+
+        let (matched, confidence) = search_best_match_scored(&rooms, "anna").unwrap();
+        assert_eq!(matched.room_id, "!wa-anna");
+        assert!(confidence < LOW_CONFIDENCE_MATCH);
+        let known = state.user_repository.is_known_contact(user_id, "whatsapp", &matched.room_id).unwrap();
+        assert_eq!(send_guard(confidence, known, false), SendGuard::NeedsConfirmation);
+        assert_eq!(
+            send_guard(confidence, known, true),
+            SendGuard::Proceed { delay_secs: LOW_CONFIDENCE_SEND_DELAY_SECS, low_confidence: true },
+        );
+
+        // Once messaged, the same loose match only gets the longer cancel window
+        state.user_repository.add_known_contact(user_id, "whatsapp", &matched.room_id).unwrap();
+        let known = state.user_repository.is_known_contact(user_id, "whatsapp", &matched.room_id).unwrap();
+        assert!(matches!(send_guard(confidence, known, false), SendGuard::Proceed { low_confidence: true, .. }));
+        let (_, exact) = search_best_match_scored(&rooms, "Bob").unwrap();
+        assert_eq!(
+            send_guard(exact, true, false),
+            SendGuard::Proceed { delay_secs: DEFAULT_SEND_DELAY_SECS, low_confidence: false },
+        );
+    }
+
+    #[test]
+    fn uploaded_image_is_sent_as_m_image_with_its_mxc_uri() {
+        use matrix_sdk::ruma::events::room::{message::MessageType, MediaSource};
+        let mxc: matrix_sdk::ruma::OwnedMxcUri = "mxc://matrix.example.com/AbCdEf".into();
+        let content = image_message("Here's the receipt", mxc.clone(), &mime_guess::mime::IMAGE_PNG, 2048);
+
+        let MessageType::Image(image) = &content.msgtype else {
+            panic!("expected an m.image event, got {}", content.msgtype());
+        };
+        match &image.source {
+            MediaSource::Plain(uri) => assert_eq!(uri, &mxc),
+            MediaSource::Encrypted(_) => panic!("bridged images go out unencrypted"),
+        }
+        assert_eq!(image.body, "Here's the receipt");
+        let info = image.info.as_ref().unwrap();
+        assert_eq!(info.mimetype.as_deref(), Some("image/png"));
+        assert_eq!(info.size, Some(matrix_sdk::ruma::UInt::new(2048).unwrap()));
+        assert_eq!(serde_json::to_value(&content).unwrap()["msgtype"], "m.image");
+    }
+
+    #[test]
+    fn only_web_image_urls_are_accepted() {
+        assert!(validate_image_url("https://example.com/receipt.png").is_ok());
+        assert!(validate_image_url("file:///etc/passwd").is_err());
+        assert!(validate_image_url("not a url").is_err());
+    }
+
+    #[test]
+    fn room_override_beats_mute_and_mute_beats_keywords() {
+        use crate::models::user_models::{NewKeyword, NewRoomNotificationPref};
+        use crate::proactive::utils::{keyword_action, KeywordAction};
+        let state = test_state();
+        let user_id = create_test_user(&state, "rooms@example.com");
+        let room_id = "!family:matrix.example.com";
+        state.user_repository.create_keyword(&NewKeyword {
+            user_id,
+            keyword: "invoice".to_string(),
+            service_type: "whatsapp".to_string(),
+            action: "notify_call".to_string(),
+        }).unwrap();
+        let keywords = state.user_repository.get_keywords(user_id, "whatsapp").unwrap();
+        assert!(matches!(
+            keyword_action(&keywords, "Family Mom the invoice is due"),
+            Some((KeywordAction::NotifyCall, _))
+        ));
+
+        let rule = |muted: bool| {
+            let pref = state.user_repository.get_room_notification_pref(user_id, room_id).unwrap();
+            resolve_room_rule(pref.as_ref(), muted)
+        };
+        let set = |mode: &str, noti_type: Option<&str>| {
+            state.user_repository.set_room_notification_pref(&NewRoomNotificationPref {
+                user_id,
+                platform: "whatsapp".to_string(),
+                room_id: room_id.to_string(),
+                mode: mode.to_string(),
+                noti_type: noti_type.map(str::to_string),
+                updated_at: 0,
+            }).unwrap();
+        };
+
+        // Muted in the app, the keyword never gets a say
+        assert_eq!(rule(true), RoomNotificationRule::Muted);
+        assert_eq!(rule(false), RoomNotificationRule::Filters);
+
+        set("always", Some("call"));
+        assert_eq!(rule(true), RoomNotificationRule::Always { noti_type: Some("call".to_string()) });
+
+        set("never", None);
+        assert_eq!(rule(true), RoomNotificationRule::Never);
+        assert_eq!(rule(false), RoomNotificationRule::Never);
+    }
+}
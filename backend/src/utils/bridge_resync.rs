@@ -0,0 +1,221 @@
+use dashmap::DashMap;
+use matrix_sdk::room::Room;
+use matrix_sdk::ruma::events::room::message::RoomMessageEventContent;
+use serde::Serialize;
+use tokio::time::{sleep, Duration};
+
+/// Attempts per bridge command before the resync is marked failed
+const SEND_ATTEMPTS: u32 = 4;
+/// First retry delay, doubled on each attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Pause between commands so the bridge finishes one before the next arrives
+const STEP_PAUSE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResyncState {
+    Running,
+    Failed,
+    Completed,
+}
+
+/// Checkpoint of a resync: which of its bridge commands have gone through
+#[derive(Debug, Clone, Serialize)]
+pub struct ResyncProgress {
+    pub service: String,
+    pub steps: Vec<String>,
+    pub completed_steps: usize,
+    pub state: ResyncState,
+    pub runs: u32,
+    pub last_error: Option<String>,
+    pub updated_at: i32,
+}
+
+/// Resync progress per (user, service). A failed resync keeps its checkpoint so the next
+/// attempt continues from the first command that didn't go through.
+pub struct ResyncTracker {
+    progress: DashMap<(i32, String), ResyncProgress>,
+}
+
+fn now() -> i32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i32
+}
+
+impl ResyncTracker {
+    pub fn new() -> Self {
+        Self { progress: DashMap::new() }
+    }
+
+    /// Marks a resync as running and returns the step to start from. Err when one is already running.
+    pub fn begin(&self, user_id: i32, service: &str, steps: &[&str]) -> Result<usize, String> {
+        let steps: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+        let mut entry = self.progress.entry((user_id, service.to_string())).or_insert_with(|| ResyncProgress {
+            service: service.to_string(),
+            steps: steps.clone(),
+            completed_steps: 0,
+            state: ResyncState::Completed,
+            runs: 0,
+            last_error: None,
+            updated_at: now(),
+        });
+        if entry.state == ResyncState::Running {
+            return Err(format!("A {} resync is already running", service));
+        }
+        // Only a failed run of the same commands is resumed, anything else starts over
+        if entry.state != ResyncState::Failed || entry.steps != steps {
+            entry.completed_steps = 0;
+            entry.steps = steps;
+        }
+        entry.state = ResyncState::Running;
+        entry.runs += 1;
+        entry.last_error = None;
+        entry.updated_at = now();
+        Ok(entry.completed_steps)
+    }
+
+    fn update(&self, user_id: i32, service: &str, apply: impl FnOnce(&mut ResyncProgress)) {
+        if let Some(mut entry) = self.progress.get_mut(&(user_id, service.to_string())) {
+            apply(&mut entry);
+            entry.updated_at = now();
+        }
+    }
+
+    pub fn step_done(&self, user_id: i32, service: &str) {
+        self.update(user_id, service, |progress| progress.completed_steps += 1);
+    }
+
+    pub fn fail(&self, user_id: i32, service: &str, error: String) {
+        self.update(user_id, service, |progress| {
+            progress.state = ResyncState::Failed;
+            progress.last_error = Some(error);
+        });
+    }
+
+    pub fn finish(&self, user_id: i32, service: &str) {
+        self.update(user_id, service, |progress| progress.state = ResyncState::Completed);
+    }
+
+    /// All resyncs the user has run since the server started
+    pub fn status(&self, user_id: i32) -> Vec<ResyncProgress> {
+        let mut statuses: Vec<ResyncProgress> = self.progress
+            .iter()
+            .filter(|entry| entry.key().0 == user_id)
+            .map(|entry| entry.value().clone())
+            .collect();
+        statuses.sort_by(|a, b| a.service.cmp(&b.service));
+        statuses
+    }
+}
+
+/// Sends a bridge command, retrying with exponential backoff on Matrix errors
+async fn send_with_backoff(room: &Room, command: &str) -> Result<(), String> {
+    let mut delay = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match room.send(RoomMessageEventContent::text_plain(command)).await {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt < SEND_ATTEMPTS => {
+                tracing::warn!("Sending bridge command {:?} failed (attempt {}/{}): {}", command, attempt, SEND_ATTEMPTS, e);
+                sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(format!("Failed to send {:?} after {} attempts: {}", command, SEND_ATTEMPTS, e)),
+        }
+    }
+}
+
+/// Runs the resync commands in order, starting after the last one that went through on a previous try
+pub async fn run_resync(
+    tracker: &ResyncTracker,
+    user_id: i32,
+    service: &str,
+    room: &Room,
+    steps: &[&str],
+) -> Result<(), String> {
+    run_steps(tracker, user_id, service, steps, |command| async move {
+        send_with_backoff(room, &command).await
+    })
+    .await
+}
+
+async fn run_steps<F, Fut>(
+    tracker: &ResyncTracker,
+    user_id: i32,
+    service: &str,
+    steps: &[&str],
+    mut send: F,
+) -> Result<(), String>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    let start = tracker.begin(user_id, service, steps)?;
+    if start > 0 {
+        tracing::info!("Resuming {} resync for user {} at step {}/{}", service, user_id, start + 1, steps.len());
+    }
+    for (index, command) in steps.iter().enumerate().skip(start) {
+        if index > start {
+            sleep(STEP_PAUSE).await;
+        }
+        if let Err(e) = send(command.to_string()).await {
+            tracing::error!("{} resync for user {} stopped at step {}: {}", service, user_id, index + 1, e);
+            tracker.fail(user_id, service, e.clone());
+            return Err(e);
+        }
+        tracker.step_done(user_id, service);
+        tracing::debug!("✅ Sent {} resync command {:?}", service, command);
+    }
+    tracker.finish(user_id, service);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const STEPS: [&str; 3] = ["sync contacts", "sync chats", "sync avatars"];
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_resumes_from_the_failed_step() {
+        let tracker = ResyncTracker::new();
+        let sent = &Mutex::new(Vec::new());
+
+        let first = run_steps(&tracker, 1, "whatsapp", &STEPS, move |command| async move {
+            if command == "sync chats" {
+                return Err("bridge timed out".to_string());
+            }
+            sent.lock().unwrap().push(command);
+            Ok(())
+        })
+        .await;
+        assert_eq!(first, Err("bridge timed out".to_string()));
+        let progress = &tracker.status(1)[0];
+        assert_eq!((progress.state, progress.completed_steps), (ResyncState::Failed, 1));
+
+        run_steps(&tracker, 1, "whatsapp", &STEPS, move |command| async move {
+            sent.lock().unwrap().push(command);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), ["sync contacts", "sync chats", "sync avatars"]);
+        let progress = &tracker.status(1)[0];
+        assert_eq!((progress.state, progress.completed_steps, progress.runs), (ResyncState::Completed, 3, 2));
+    }
+
+    #[test]
+    fn second_resync_is_refused_while_one_runs() {
+        let tracker = ResyncTracker::new();
+        assert_eq!(tracker.begin(1, "telegram", &STEPS), Ok(0));
+        assert!(tracker.begin(1, "telegram", &STEPS).is_err());
+        // Other services and other users aren't blocked
+        assert_eq!(tracker.begin(1, "whatsapp", &STEPS), Ok(0));
+        assert_eq!(tracker.begin(2, "telegram", &STEPS), Ok(0));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::models::user_models::User;
+
+/// Variables a broadcast can use as {{name}}, with what's put in when the user has no value.
+/// A template can give its own fallback instead, e.g. {{nickname|friend}}.
+pub const TEMPLATE_VARIABLES: [(&str, &str); 5] = [
+    ("nickname", "there"),
+    ("email", ""),
+    ("credits", "0.00"),
+    ("credits_left", "0.00"),
+    ("plan", "free"),
+];
+
+/// A {{...}} placeholder: byte range in the template, variable name and its own fallback
+struct Placeholder<'a> {
+    start: usize,
+    end: usize,
+    name: &'a str,
+    fallback: Option<&'a str>,
+}
+
+fn placeholders(template: &str) -> Result<Vec<Placeholder<'_>>, String> {
+    let mut found = Vec::new();
+    let mut rest = 0;
+    while let Some(open) = template[rest..].find("{{").map(|i| rest + i) {
+        let close = template[open + 2..]
+            .find("}}")
+            .map(|i| open + 2 + i)
+            .ok_or_else(|| format!("Unclosed '{{{{' at character {}", template[..open].chars().count() + 1))?;
+        let inner = &template[open + 2..close];
+        let (name, fallback) = match inner.split_once('|') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (inner.trim(), None),
+        };
+        found.push(Placeholder { start: open, end: close + 2, name, fallback });
+        rest = close + 2;
+    }
+    Ok(found)
+}
+
+/// Checks every placeholder is closed and names a known variable
+pub fn validate_template(template: &str) -> Result<(), String> {
+    for placeholder in placeholders(template)? {
+        if !TEMPLATE_VARIABLES.iter().any(|(name, _)| *name == placeholder.name) {
+            let known: Vec<&str> = TEMPLATE_VARIABLES.iter().map(|(name, _)| *name).collect();
+            return Err(format!("Unknown variable '{{{{{}}}}}', expected one of {:?}", placeholder.name, known));
+        }
+    }
+    Ok(())
+}
+
+/// The user's value for each variable, None when they don't have one
+pub fn user_values(user: &User) -> Vec<(&'static str, Option<String>)> {
+    vec![
+        ("nickname", user.nickname.clone()),
+        ("email", Some(user.email.clone())),
+        ("credits", Some(format!("{:.2}", user.credits))),
+        ("credits_left", Some(format!("{:.2}", user.credits_left))),
+        ("plan", user.sub_tier.clone()),
+    ]
+}
+
+/// Fills in the placeholders. Blank and missing values get the template's fallback or the
+/// variable's default, unknown variables are left as they are (`validate_template` catches those).
+pub fn render(template: &str, values: &[(&str, Option<String>)]) -> String {
+    let Ok(found) = placeholders(template) else {
+        return template.to_string();
+    };
+    let mut rendered = String::with_capacity(template.len());
+    let mut copied = 0;
+    for placeholder in found {
+        let Some((_, default)) = TEMPLATE_VARIABLES.iter().find(|(name, _)| *name == placeholder.name) else {
+            continue;
+        };
+        let value = values
+            .iter()
+            .find(|(name, _)| *name == placeholder.name)
+            .and_then(|(_, value)| value.as_deref())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .or(placeholder.fallback)
+            .unwrap_or(default);
+        rendered.push_str(&template[copied..placeholder.start]);
+        rendered.push_str(value);
+        copied = placeholder.end;
+    }
+    rendered.push_str(&template[copied..]);
+    rendered
+}
+
+pub fn render_for_user(template: &str, user: &User) -> String {
+    render(template, &user_values(user))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    #[tokio::test]
+    async fn placeholders_are_filled_from_the_user() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "broadcast@example.com");
+        let mut user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        user.nickname = Some("Sam".to_string());
+
+        let rendered = render_for_user("Hi {{nickname}}, {{ credits_left }} left on {{plan}} ({{email}})", &user);
+        assert_eq!(rendered, "Hi Sam, 10.00 left on tier 2 (broadcast@example.com)");
+    }
+
+    #[tokio::test]
+    async fn missing_nickname_uses_the_fallback() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "nameless@example.com");
+        let mut user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        user.nickname = None;
+        assert_eq!(render_for_user("Hi {{nickname}}!", &user), "Hi there!");
+        assert_eq!(render_for_user("Hi {{nickname|friend}}!", &user), "Hi friend!");
+
+        // A blank nickname counts as missing
+        user.nickname = Some("  ".to_string());
+        assert_eq!(render_for_user("Hi {{nickname|friend}}!", &user), "Hi friend!");
+    }
+
+    #[test]
+    fn unknown_and_unclosed_placeholders_are_left_alone() {
+        assert_eq!(render("Hi {{name}}", &[]), "Hi {{name}}");
+        assert_eq!(render("Hi {{nickname", &[]), "Hi {{nickname");
+        assert!(validate_template("Hi {{name}}").is_err());
+        assert!(validate_template("Hi {{nickname|friend}}").is_ok());
+    }
+}
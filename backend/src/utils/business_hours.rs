@@ -0,0 +1,73 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Weekday};
+use chrono_tz::Tz;
+
+use crate::models::user_models::UserSettings;
+
+/// Weekday hours the user is fine with emails going out in, in their own timezone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BusinessHours {
+    pub start_hour: u32,
+    /// Exclusive, 24 runs to midnight
+    pub end_hour: u32,
+}
+
+impl BusinessHours {
+    /// None unless both hours are set and make a non-empty window
+    pub fn from_settings(settings: &UserSettings) -> Option<Self> {
+        let start_hour = u32::try_from(settings.email_business_hours_start?).ok()?;
+        let end_hour = u32::try_from(settings.email_business_hours_end?).ok()?;
+        (start_hour < end_hour && end_hour <= 24).then_some(Self { start_hour, end_hour })
+    }
+
+    fn is_business_day(day: Weekday) -> bool {
+        !matches!(day, Weekday::Sat | Weekday::Sun)
+    }
+
+    /// When something due at `at` may go out. None when `at` is within business hours,
+    /// otherwise the start of the next window.
+    pub fn next_opening(&self, at: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        if Self::is_business_day(at.weekday()) && (self.start_hour..self.end_hour).contains(&at.hour()) {
+            return None;
+        }
+        let timezone = at.timezone();
+        let start = NaiveTime::from_hms_opt(self.start_hour, 0, 0)?;
+        (0..=7).find_map(|days| {
+            let date = at.date_naive() + Duration::days(days);
+            if !Self::is_business_day(date.weekday()) {
+                return None;
+            }
+            // A start hour skipped by a DST change just moves on to the next day
+            let opening = timezone.from_local_datetime(&date.and_time(start)).earliest()?;
+            (opening > at).then_some(opening)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn helsinki(day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        // October 2026: the 14th is a Wednesday, the 16th a Friday
+        chrono_tz::Europe::Helsinki.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    const NINE_TO_FIVE: BusinessHours = BusinessHours { start_hour: 9, end_hour: 17 };
+
+    #[test]
+    fn inside_business_hours_sends_right_away() {
+        assert_eq!(NINE_TO_FIVE.next_opening(helsinki(14, 9, 0)), None);
+        assert_eq!(NINE_TO_FIVE.next_opening(helsinki(14, 16, 59)), None);
+    }
+
+    #[test]
+    fn evening_is_deferred_to_next_morning() {
+        assert_eq!(NINE_TO_FIVE.next_opening(helsinki(14, 22, 30)), Some(helsinki(15, 9, 0)));
+        assert_eq!(NINE_TO_FIVE.next_opening(helsinki(14, 6, 0)), Some(helsinki(14, 9, 0)));
+    }
+
+    #[test]
+    fn friday_evening_is_deferred_to_monday() {
+        assert_eq!(NINE_TO_FIVE.next_opening(helsinki(16, 17, 0)), Some(helsinki(19, 9, 0)));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+/// Content type groups that can have their own notification call opening
+pub const TEMPLATE_CATEGORIES: [&str; 3] = ["email", "message", "calendar"];
+pub const MAX_TEMPLATE_CHARS: usize = 200;
+const PLACEHOLDERS: [&str; 4] = ["sender", "subject", "time", "service"];
+
+/// Built-in openings as (language, category, template)
+const DEFAULT_TEMPLATES: [(&str, &str, &str); 18] = [
+    ("en", "email", "Hello, you have an important email from {sender} about {subject}."),
+    ("en", "message", "Hello, you have an important {service} message from {sender}."),
+    ("en", "calendar", "Hello, {subject} starts at {time}."),
+    ("fi", "email", "Moi, sinulle tuli tärkeä sähköposti lähettäjältä {sender} aiheesta {subject}."),
+    ("fi", "message", "Moi, sinulle tuli tärkeä {service}-viesti lähettäjältä {sender}."),
+    ("fi", "calendar", "Moi, {subject} alkaa kello {time}."),
+    ("de", "email", "Hallo, du hast eine wichtige E-Mail von {sender} zum Thema {subject}."),
+    ("de", "message", "Hallo, du hast eine wichtige {service}-Nachricht von {sender}."),
+    ("de", "calendar", "Hallo, {subject} beginnt um {time}."),
+    ("sv", "email", "Hej, du har ett viktigt mejl från {sender} om {subject}."),
+    ("sv", "message", "Hej, du har ett viktigt {service}-meddelande från {sender}."),
+    ("sv", "calendar", "Hej, {subject} börjar klockan {time}."),
+    ("fr", "email", "Bonjour, vous avez un e-mail important de {sender} au sujet de {subject}."),
+    ("fr", "message", "Bonjour, vous avez un message {service} important de {sender}."),
+    ("fr", "calendar", "Bonjour, {subject} commence à {time}."),
+    ("es", "email", "Hola, tienes un correo importante de {sender} sobre {subject}."),
+    ("es", "message", "Hola, tienes un mensaje importante de {service} de {sender}."),
+    ("es", "calendar", "Hola, {subject} empieza a las {time}."),
+];
+
+/// Values available to the opening template of a notification call
+#[derive(Debug, Default, Clone)]
+pub struct CallOpeningContext {
+    pub sender: Option<String>,
+    pub subject: Option<String>,
+    pub time: Option<String>,
+    pub service: Option<String>,
+}
+
+impl CallOpeningContext {
+    fn value(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "sender" => self.sender.as_deref(),
+            "subject" => self.subject.as_deref(),
+            "time" => self.time.as_deref(),
+            "service" => self.service.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// "email_priority_call" -> "email", "whatsapp_critical" -> "message", "calendar_notification" -> "calendar"
+pub fn template_category(content_type: &str) -> Option<&'static str> {
+    if content_type.starts_with("calendar") {
+        Some("calendar")
+    } else if content_type.starts_with("email") {
+        Some("email")
+    } else if ["whatsapp", "telegram", "signal", "messenger", "instagram"].iter().any(|s| content_type.starts_with(s)) {
+        Some("message")
+    } else {
+        None
+    }
+}
+
+/// Fills the placeholders, None if the template uses one the context doesn't have
+pub fn render_template(template: &str, context: &CallOpeningContext) -> Option<String> {
+    let mut rendered = template.to_string();
+    for placeholder in PLACEHOLDERS {
+        let token = format!("{{{}}}", placeholder);
+        if rendered.contains(&token) {
+            let value = crate::utils::elevenlabs_prompts::sanitize_dynamic_value(context.value(placeholder)?);
+            rendered = rendered.replace(&token, &value);
+        }
+    }
+    Some(rendered)
+}
+
+/// Parses the user's stored templates, dropping anything that isn't a known category
+pub fn parse_user_templates(raw: Option<&str>) -> HashMap<String, String> {
+    raw.and_then(|raw| serde_json::from_str::<HashMap<String, String>>(raw).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(category, _)| TEMPLATE_CATEGORIES.contains(&category.as_str()))
+        .collect()
+}
+
+/// Opening line for a notification call: the user's template for the content type, then the
+/// built-in one for their language, then whatever the caller passed in
+pub fn first_message(
+    user_templates: Option<&str>,
+    language: &str,
+    content_type: &str,
+    context: &CallOpeningContext,
+) -> Option<String> {
+    let category = template_category(content_type)?;
+    let user_template = parse_user_templates(user_templates).remove(category);
+    if let Some(rendered) = user_template.and_then(|template| render_template(&template, context)) {
+        return Some(rendered);
+    }
+    let language = language.to_lowercase();
+    DEFAULT_TEMPLATES
+        .iter()
+        .find(|(lang, cat, _)| *lang == language && *cat == category)
+        .or_else(|| DEFAULT_TEMPLATES.iter().find(|(lang, cat, _)| *lang == "en" && *cat == category))
+        .and_then(|(_, _, template)| render_template(template, context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standup() -> CallOpeningContext {
+        CallOpeningContext {
+            sender: Some("Google Calendar".to_string()),
+            subject: Some("Team standup".to_string()),
+            time: Some("09:30".to_string()),
+            service: None,
+        }
+    }
+
+    #[test]
+    fn calendar_calls_open_with_the_calendar_template() {
+        assert_eq!(
+            first_message(None, "en", "calendar_notification", &standup()).as_deref(),
+            Some("Hello, Team standup starts at 09:30.")
+        );
+        assert_eq!(
+            first_message(None, "fi", "calendar_notification", &standup()).as_deref(),
+            Some("Moi, Team standup alkaa kello 09:30.")
+        );
+        // Unknown languages fall back to English rather than dropping the opening
+        assert_eq!(
+            first_message(None, "pt", "calendar_notification", &standup()).as_deref(),
+            Some("Hello, Team standup starts at 09:30.")
+        );
+    }
+
+    #[test]
+    fn users_own_template_wins_for_its_category_only() {
+        let templates = r#"{"calendar": "Heads up, {subject} at {time}", "email": "Mail from {sender}"}"#;
+        assert_eq!(
+            first_message(Some(templates), "en", "calendar_notification", &standup()).as_deref(),
+            Some("Heads up, Team standup at 09:30")
+        );
+        let whatsapp = CallOpeningContext {
+            sender: Some("Mom".to_string()),
+            service: Some("WhatsApp".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            first_message(Some(templates), "en", "whatsapp_critical", &whatsapp).as_deref(),
+            Some("Hello, you have an important WhatsApp message from Mom.")
+        );
+    }
+
+    #[test]
+    fn template_with_a_missing_value_falls_back_to_the_default() {
+        let templates = r#"{"calendar": "{sender} says {subject} is at {time}"}"#;
+        let context = CallOpeningContext { sender: None, ..standup() };
+        assert_eq!(
+            first_message(Some(templates), "en", "calendar_notification", &context).as_deref(),
+            Some("Hello, Team standup starts at 09:30.")
+        );
+        assert_eq!(first_message(Some(templates), "en", "digest", &context), None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+
+use axum::extract::{connect_info::ConnectInfo, FromRequestParts};
+use axum::http::{request::Parts, HeaderMap};
+
+use crate::AppState;
+
+/// Proxies in front of the server when TRUSTED_PROXIES isn't set: nginx on the same host
+const DEFAULT_TRUSTED_PROXIES: &str = "127.0.0.0/8,::1/128";
+
+/// An address range like "10.0.0.0/8", a bare address is a /32 or /128
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn parse(value: &str) -> Option<Cidr> {
+        let value = value.trim();
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+            None => (value, None),
+        };
+        let network: IpAddr = address.parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        if prefix > max {
+            return None;
+        }
+        Some(Cidr { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// IPv4 peers on a dual-stack socket show up as ::ffff:a.b.c.d
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+/// Parses a comma-separated CIDR list, skipping (and logging) entries that don't parse
+pub fn parse_trusted_proxies(list: &str) -> Vec<Cidr> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let cidr = Cidr::parse(entry);
+            if cidr.is_none() {
+                tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry {:?}", entry);
+            }
+            cidr
+        })
+        .collect()
+}
+
+/// TRUSTED_PROXIES, read once
+pub fn trusted_proxies() -> &'static [Cidr] {
+    static TRUSTED: OnceLock<Vec<Cidr>> = OnceLock::new();
+    TRUSTED.get_or_init(|| {
+        let list = std::env::var("TRUSTED_PROXIES").unwrap_or_else(|_| DEFAULT_TRUSTED_PROXIES.to_string());
+        parse_trusted_proxies(&list)
+    })
+}
+
+fn is_trusted(ip: IpAddr, trusted: &[Cidr]) -> bool {
+    trusted.iter().any(|cidr| cidr.contains(ip))
+}
+
+/// The address of whoever sent the request. X-Forwarded-For is only read when the direct peer is a
+/// trusted proxy, and then from the right, skipping our own proxies, so entries a client prepended
+/// to the header can't pick the address.
+pub fn resolve_client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[Cidr]) -> Option<IpAddr> {
+    // Without the peer address there's no telling whether the header can be believed
+    let peer = canonical(peer?);
+    if !is_trusted(peer, trusted) {
+        return Some(peer);
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    let mut client = Some(peer);
+    for entry in forwarded.iter().rev() {
+        let Ok(ip) = entry.parse::<IpAddr>().map(canonical) else {
+            // Garbage in the chain, the last hop we could vouch for is the best answer
+            break;
+        };
+        client = Some(ip);
+        if !is_trusted(ip, trusted) {
+            break;
+        }
+    }
+    client
+}
+
+/// Client address for rate limiting and logging, see `resolve_client_ip`
+#[derive(Debug, Clone)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl ClientIp {
+    /// Key for rate limiters, "unknown" when the address couldn't be determined
+    pub fn key(&self) -> String {
+        self.0.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        Ok(ClientIp(resolve_client_ip(peer, &parts.headers, trusted_proxies())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn ranges_match_by_prefix() {
+        let private = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(private.contains(ip("10.200.3.4")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("203.0.113.9")));
+        assert!(Cidr::parse("fd00::/8").unwrap().contains(ip("fd12::1")));
+        assert_eq!(Cidr::parse("10.0.0.0/33"), None);
+        assert_eq!(parse_trusted_proxies("127.0.0.1, nonsense, ::1"), vec![Cidr::parse("127.0.0.1").unwrap(), Cidr::parse("::1").unwrap()]);
+    }
+
+    #[test]
+    fn ipv4_mapped_peer_matches_an_ipv4_range() {
+        let loopback = Cidr::parse("127.0.0.0/8").unwrap();
+        assert!(loopback.contains(ip("::ffff:127.0.0.1")));
+        assert!(!Cidr::parse("::1/128").unwrap().contains(ip("::ffff:127.0.0.1")));
+    }
+
+    #[test]
+    fn forwarded_header_from_an_untrusted_peer_is_ignored() {
+        let trusted = parse_trusted_proxies(DEFAULT_TRUSTED_PROXIES);
+        let headers = forwarded_for("198.51.100.7");
+        assert_eq!(resolve_client_ip(Some(ip("203.0.113.9")), &headers, &trusted), Some(ip("203.0.113.9")));
+        assert_eq!(resolve_client_ip(None, &headers, &trusted), None);
+    }
+
+    #[test]
+    fn forwarded_header_from_a_trusted_proxy_is_used() {
+        let trusted = parse_trusted_proxies("127.0.0.0/8,10.0.0.0/8");
+        // The client prepended a fake entry, the last untrusted hop from the right wins
+        let headers = forwarded_for("1.2.3.4, 198.51.100.7, 10.0.0.5");
+        assert_eq!(resolve_client_ip(Some(ip("::ffff:127.0.0.1")), &headers, &trusted), Some(ip("198.51.100.7")));
+        // No header: the proxy itself is all we know
+        assert_eq!(resolve_client_ip(Some(ip("127.0.0.1")), &HeaderMap::new(), &trusted), Some(ip("127.0.0.1")));
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Events are dropped for subscribers that fall this far behind, they resync from the status endpoints
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    /// OAuth flow finished and the tokens are stored
+    Connected,
+    /// A bridge login went through
+    BridgeConnected,
+    /// A refresh was rejected, the user has to reconnect
+    TokenExpired,
+}
+
+/// A change in one of the user's connections, e.g. ("tesla", Connected)
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    #[serde(skip)]
+    pub user_id: i32,
+    pub service: String,
+    pub status: ConnectionStatus,
+    pub at: i32,
+}
+
+/// In-process fan-out of connection changes to the open `/api/events/connections` streams
+pub struct ConnectionEvents {
+    sender: broadcast::Sender<ConnectionEvent>,
+}
+
+impl ConnectionEvents {
+    pub fn new() -> Arc<Self> {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Arc::new(Self { sender })
+    }
+
+    /// Publishes a change. Nobody listening is the normal case, so send errors are ignored.
+    pub fn publish(&self, user_id: i32, service: &str, status: ConnectionStatus) {
+        let at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32;
+        tracing::debug!("Connection event for user {}: {} {:?}", user_id, service, status);
+        let _ = self.sender.send(ConnectionEvent {
+            user_id,
+            service: service.to_string(),
+            status,
+            at,
+        });
+    }
+
+    /// Stream of the given user's events only
+    pub fn subscribe(&self, user_id: i32) -> impl Stream<Item = ConnectionEvent> {
+        let receiver = self.sender.subscribe();
+        futures::stream::unfold(receiver, move |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.user_id == user_id => return Some((event, receiver)),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Connection event stream for user {} skipped {} events", user_id, skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,159 @@
+/// Confirmations the voice agent gets back when it queues an outgoing message or email.
+/// The agent reads them to the user, so they follow the user's `agent_language`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueuedMessage {
+    /// {platform}, {recipient}, {content}, {attachment}, {delay}
+    Chat,
+    /// Like `Chat`, plus {requested} for the name the user actually said
+    ChatLowConfidence,
+    /// {recipient}, {subject}, {content}, {delay}
+    Email,
+    /// {subject}, {content}, {delay}
+    EmailReply,
+    /// {subject}, {recipient}, {delay}
+    EmailForward,
+}
+
+/// Finnish and German have their own templates, everything else gets English
+fn template(kind: QueuedMessage, language: &str) -> &'static str {
+    match (language, kind) {
+        ("fi", QueuedMessage::Chat) => "Lähetän {platform}-viestin '{recipient}': '{content}'{attachment} {delay} sekunnin päästä. Peru cancel_message-työkalulla.",
+        ("fi", QueuedMessage::ChatLowConfidence) => "Lähetän {platform}-viestin '{recipient}' (lähin osuma nimelle '{requested}'): '{content}'{attachment} {delay} sekunnin päästä. Lue yhteystiedon nimi käyttäjälle. Peru cancel_message-työkalulla.",
+        ("fi", QueuedMessage::Email) => "Lähetän sähköpostin osoitteeseen {recipient}, aihe '{subject}' ja sisältö '{content}', {delay} sekunnin päästä. Peru cancel_message-työkalulla.",
+        ("fi", QueuedMessage::EmailReply) => "Vastaan sähköpostiin '{subject}' viestillä '{content}' {delay} sekunnin päästä. Peru cancel_message-työkalulla.",
+        ("fi", QueuedMessage::EmailForward) => "Välitän sähköpostin '{subject}' osoitteeseen {recipient} {delay} sekunnin päästä. Peru cancel_message-työkalulla.",
+        ("de", QueuedMessage::Chat) => "Sende {platform}-Nachricht an '{recipient}' mit '{content}'{attachment} in {delay} Sekunden. Mit dem Tool cancel_message verwerfen.",
+        ("de", QueuedMessage::ChatLowConfidence) => "Sende {platform}-Nachricht an '{recipient}' (beste Übereinstimmung für '{requested}') mit '{content}'{attachment} in {delay} Sekunden. Lies dem Nutzer den Kontaktnamen vor. Mit dem Tool cancel_message verwerfen.",
+        ("de", QueuedMessage::Email) => "Sende E-Mail an {recipient} mit Betreff '{subject}' und Text '{content}' in {delay} Sekunden. Mit dem Tool cancel_message verwerfen.",
+        ("de", QueuedMessage::EmailReply) => "Antworte auf die E-Mail '{subject}' mit '{content}' in {delay} Sekunden. Mit cancel_message verwerfen.",
+        ("de", QueuedMessage::EmailForward) => "Leite die E-Mail '{subject}' in {delay} Sekunden an {recipient} weiter. Mit cancel_message verwerfen.",
+        (_, QueuedMessage::Chat) => "Will send {platform} to '{recipient}' with '{content}'{attachment} in {delay}s. Use cancel_message tool to discard.",
+        (_, QueuedMessage::ChatLowConfidence) => "Will send {platform} to '{recipient}' (closest match for '{requested}') with '{content}'{attachment} in {delay}s. Read the contact name back to the user. Use cancel_message tool to discard.",
+        (_, QueuedMessage::Email) => "Will send email to {recipient} with subject '{subject}' and body '{content}' in {delay}s. Use cancel_message tool to discard.",
+        (_, QueuedMessage::EmailReply) => "Will respond to email '{subject}' with '{content}' in {delay}s. Use cancel_message to discard.",
+        (_, QueuedMessage::EmailForward) => "Will forward email '{subject}' to {recipient} in {delay}s. Use cancel_message to discard.",
+    }
+}
+
+/// " and an image" in the user's language, for the {attachment} placeholder
+pub fn image_attachment(language: &str) -> &'static str {
+    match language {
+        "fi" => " ja kuva",
+        "de" => " und einem Bild",
+        _ => " and an image",
+    }
+}
+
+/// Appended to an email confirmation when the send waits for the user's business hours
+pub fn business_hours_note(language: &str, send_at: &str) -> String {
+    match language.to_lowercase().as_str() {
+        "fi" => format!(" Sen jälkeen se odottaa työaikaasi ja lähtee {}.", send_at),
+        "de" => format!(" Danach wartet sie auf Ihre Geschäftszeiten und geht {} raus.", send_at),
+        _ => format!(" After that it waits for your business hours and goes out {}.", send_at),
+    }
+}
+
+/// Renders the confirmation for `kind` in `language`. Placeholders without a value are left empty.
+pub fn queued_message(kind: QueuedMessage, language: &str, values: &[(&str, &str)]) -> String {
+    let language = language.to_lowercase();
+    let mut rendered = template(kind, &language).to_string();
+    for placeholder in ["platform", "recipient", "requested", "content", "attachment", "subject", "delay"] {
+        let value = values
+            .iter()
+            .find(|(name, _)| *name == placeholder)
+            .map(|(_, value)| *value)
+            .unwrap_or("");
+        rendered = rendered.replace(&format!("{{{}}}", placeholder), value);
+    }
+    rendered
+}
+
+/// First message of an incoming call and the voice to say it with, from the same language so
+/// they always match. `newly_verified` picks the verification welcome over the normal greeting.
+pub fn call_opening(language: &str, newly_verified: bool) -> (String, String) {
+    let language = crate::utils::voice_languages::voice_language(language);
+    let message = if newly_verified { &language.verified_message } else { &language.greeting };
+    (message.clone(), language.voice_id.clone())
+}
+
+/// Removes `{{` and `}}` from a value that ends up in a dynamic variable or first message, so text
+/// from users, contacts or emails can never reference another variable.
+pub fn sanitize_dynamic_value(value: &str) -> String {
+    let mut sanitized = value.to_string();
+    while sanitized.contains("{{") || sanitized.contains("}}") {
+        sanitized = sanitized.replace("{{", "{").replace("}}", "}");
+    }
+    sanitized
+}
+
+/// Sanitizes every string value, nested ones included
+pub fn sanitize_dynamic_variables(variables: &mut std::collections::HashMap<String, serde_json::Value>) {
+    fn sanitize(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = sanitize_dynamic_value(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(sanitize),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(sanitize),
+            _ => {}
+        }
+    }
+    variables.values_mut().for_each(sanitize);
+}
+
+/// Names of the `{{name}}` placeholders in a template
+pub fn template_placeholders(template: &str) -> Vec<String> {
+    let mut placeholders = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        placeholders.push(after[..end].trim().to_string());
+        rest = &after[end + 2..];
+    }
+    placeholders
+}
+
+/// Drops placeholders the first message uses but no dynamic variable provides, ElevenLabs would
+/// otherwise refuse to start the conversation or read the braces out loud
+pub fn checked_first_message(first_message: &str, variables: &std::collections::HashMap<String, serde_json::Value>) -> String {
+    let mut checked = first_message.to_string();
+    for placeholder in template_placeholders(first_message) {
+        if !variables.contains_key(&placeholder) {
+            tracing::warn!("First message uses unknown dynamic variable '{}', removing it", placeholder);
+            checked = checked.replace(&format!("{{{{{}}}}}", placeholder), "");
+        }
+    }
+    checked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn user_content_with_braces_cannot_reference_a_variable() {
+        let mut variables: HashMap<String, serde_json::Value> = HashMap::from([
+            ("user_id".to_string(), json!("42")),
+            ("user_info".to_string(), json!("I'm {{user_id}}, call me {{{{secret}}}}")),
+            ("recent_contacts".to_string(), json!([{"name": "Eve }}{{now"}])),
+        ]);
+        sanitize_dynamic_variables(&mut variables);
+
+        let mut keys: Vec<&String> = variables.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["recent_contacts", "user_id", "user_info"]);
+        assert_eq!(variables["user_info"], "I'm {user_id}, call me {secret}");
+        assert_eq!(variables["recent_contacts"][0]["name"], "Eve }{now");
+        for value in variables.values() {
+            assert!(template_placeholders(&value.to_string()).is_empty(), "{} still has a placeholder", value);
+        }
+    }
+
+    #[test]
+    fn first_message_keeps_only_provided_placeholders() {
+        let variables = HashMap::from([("name".to_string(), json!("Anna"))]);
+        assert_eq!(template_placeholders("Hi {{ name }}, it's {{now}}"), vec!["name", "now"]);
+        assert_eq!(checked_first_message("Hi {{name}}, it's {{now}}.", &variables), "Hi {{name}}, it's .");
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::{header::HeaderMap, StatusCode};
+
+/// Retries for requests to the ElevenLabs API
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Tries before giving up, including the first
+    pub attempts: u32,
+    /// Wait before the first retry when the response has no Retry-After, doubled after each
+    pub backoff: Duration,
+    /// Longest Retry-After we wait for, anything longer fails right away
+    pub max_wait: Duration,
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    match std::env::var(key) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("Ignoring invalid {}={:?}, using {}", key, value, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// ELEVENLABS_RETRY_ATTEMPTS, ELEVENLABS_RETRY_BACKOFF_MS and ELEVENLABS_RETRY_MAX_WAIT_SECS, read once
+pub fn policy() -> &'static RetryPolicy {
+    static POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+    POLICY.get_or_init(|| RetryPolicy {
+        attempts: env_u64("ELEVENLABS_RETRY_ATTEMPTS", 3).clamp(1, 10) as u32,
+        backoff: Duration::from_millis(env_u64("ELEVENLABS_RETRY_BACKOFF_MS", 1000)),
+        max_wait: Duration::from_secs(env_u64("ELEVENLABS_RETRY_MAX_WAIT_SECS", 30)),
+    })
+}
+
+/// Rate limits and overload mean the request wasn't handled and can be sent again. Auth and
+/// validation errors won't get better, and other 5xx may already have placed the call.
+pub fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+/// Retry-After as delay-seconds or an HTTP date, a date in the past means right away
+pub fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Sends the request built by `build`, retrying rate limits after their Retry-After (or the backoff
+/// when there is none) and connection failures that never reached ElevenLabs. The last response is
+/// returned as is once retries run out, so callers handle a final 429 like any other error status.
+pub async fn send_with_retry<F>(policy: &RetryPolicy, operation: &str, build: F) -> reqwest::Result<reqwest::Response>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut backoff = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        let result = build().send().await;
+        let (wait, reason) = match &result {
+            Ok(response) if is_retryable(response.status()) => (
+                retry_after(response.headers(), Utc::now()).unwrap_or(backoff),
+                response.status().to_string(),
+            ),
+            Err(e) if e.is_connect() => (backoff, e.to_string()),
+            _ => return result,
+        };
+        if attempt >= policy.attempts {
+            tracing::warn!("Giving up on {} after {} attempts: {}", operation, attempt, reason);
+            return result;
+        }
+        if wait > policy.max_wait {
+            tracing::warn!("Not retrying {}, asked to wait {}s: {}", operation, wait.as_secs(), reason);
+            return result;
+        }
+        tracing::warn!(
+            "Failed to {} (attempt {}/{}), retrying in {}ms: {}",
+            operation, attempt, policy.attempts, wait.as_millis(), reason
+        );
+        tokio::time::sleep(wait).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::time::Instant;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        backoff: Duration::from_millis(10),
+        max_wait: Duration::from_secs(30),
+    };
+
+    /// A stand-in for the outbound call endpoint that answers with `replies` in order, repeating
+    /// the last one, and records when each request came in
+    async fn elevenlabs(replies: Vec<(u16, Option<&'static str>)>) -> (String, Arc<Mutex<Vec<Instant>>>) {
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let recorded = hits.clone();
+        let app = axum::Router::new().route(
+            "/v1/convai/twilio/outbound-call",
+            axum::routing::post(move || {
+                let hits = recorded.clone();
+                let replies = replies.clone();
+                async move {
+                    let mut hits = hits.lock().unwrap();
+                    hits.push(Instant::now());
+                    let (status, retry_after) = replies[(hits.len() - 1).min(replies.len() - 1)];
+                    let mut response = axum::response::IntoResponse::into_response(
+                        axum::http::StatusCode::from_u16(status).unwrap(),
+                    );
+                    if let Some(retry_after) = retry_after {
+                        response.headers_mut().insert("retry-after", retry_after.parse().unwrap());
+                    }
+                    response
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/convai/twilio/outbound-call", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, hits)
+    }
+
+    #[tokio::test]
+    async fn rate_limit_is_retried_after_the_retry_after() {
+        let (url, hits) = elevenlabs(vec![(429, Some("1")), (200, None)]).await;
+        let client = reqwest::Client::new();
+
+        let response = send_with_retry(&POLICY, "place notification call", || client.post(&url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let hits = hits.lock().unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits[1] - hits[0] >= Duration::from_secs(1), "retried after {:?}", hits[1] - hits[0]);
+    }
+
+    #[tokio::test]
+    async fn auth_error_is_not_retried() {
+        let (url, hits) = elevenlabs(vec![(401, None), (200, None)]).await;
+        let client = reqwest::Client::new();
+
+        let response = send_with_retry(&POLICY, "place notification call", || client.post(&url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(hits.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_that_outlasts_the_retries_comes_back_to_the_caller() {
+        let (url, hits) = elevenlabs(vec![(429, None)]).await;
+        let client = reqwest::Client::new();
+
+        let response = send_with_retry(&POLICY, "place notification call", || client.post(&url)).await.unwrap();
+
+        // make_notification_call turns this into its error and the SMS fallback
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(hits.lock().unwrap().len(), 3);
+        let far_future = {
+            let mut headers = HeaderMap::new();
+            headers.insert(reqwest::header::RETRY_AFTER, "120".parse().unwrap());
+            retry_after(&headers, Utc::now())
+        };
+        assert!(far_future.unwrap() > POLICY.max_wait);
+    }
+}
@@ -4,7 +4,9 @@ use aes_gcm::{
     Nonce,
 };
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use hmac::{Hmac, Mac};
 use rand::Rng;
+use sha2::Sha256;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -90,3 +92,28 @@ pub fn decrypt(encrypted: &str) -> Result<String, EncryptionError> {
         .map_err(|e| EncryptionError::Utf8Error(e.to_string()))
 }
 
+/// Checks a presented shared secret (webhook or scrape token) against the configured one in
+/// constant time. Both are MACed first so neither the matching prefix nor the length leaks.
+pub fn secrets_match(provided: &str, expected: &str) -> bool {
+    let mac_of = |value: &str| {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(b"lightfriend-secret-compare")
+            .expect("HMAC accepts any key length");
+        mac.update(value.as_bytes());
+        mac
+    };
+    let provided_tag = mac_of(provided).finalize().into_bytes();
+    mac_of(expected).verify_slice(&provided_tag).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_match_only_the_exact_secret() {
+        assert!(secrets_match("s3cret", "s3cret"));
+        assert!(!secrets_match("s3cre", "s3cret"));
+        assert!(!secrets_match("s3cret!", "s3cret"));
+        assert!(!secrets_match("", "s3cret"));
+    }
+}
@@ -0,0 +1,274 @@
+use axum::{http::StatusCode, Json};
+use oauth2::TokenResponse;
+use serde_json::json;
+
+use crate::AppState;
+
+/// Refresh this long before Google's reported expiry so a request never starts with a token about to die
+const EXPIRY_MARGIN_SECS: i32 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GoogleService {
+    Calendar,
+    Tasks,
+}
+
+impl GoogleService {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            GoogleService::Calendar => "Google Calendar",
+            GoogleService::Tasks => "Google Tasks",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GoogleTokenError {
+    NoConnection(GoogleService),
+    /// The refresh token was rejected, only reconnecting the account helps
+    ReconnectRequired(GoogleService, String),
+    /// Network trouble or a server error at Google, the next try may work
+    Unavailable(GoogleService, String),
+    Storage(String),
+}
+
+impl std::fmt::Display for GoogleTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoogleTokenError::NoConnection(service) => write!(f, "No active {} connection", service.display_name()),
+            GoogleTokenError::ReconnectRequired(service, _) => {
+                write!(f, "{}", crate::utils::branding::google_reconnect(service.display_name()))
+            }
+            GoogleTokenError::Unavailable(service, _) => {
+                write!(f, "Couldn't reach {} right now, please try again in a moment", service.display_name())
+            }
+            GoogleTokenError::Storage(msg) => write!(f, "Failed to access stored tokens: {}", msg),
+        }
+    }
+}
+
+impl GoogleTokenError {
+    /// Error response for the HTTP handlers; `reconnect` tells the frontend to offer the connect flow
+    pub fn into_response(self) -> (StatusCode, Json<serde_json::Value>) {
+        let (status, reconnect) = match &self {
+            GoogleTokenError::NoConnection(_) => (StatusCode::BAD_REQUEST, true),
+            GoogleTokenError::ReconnectRequired(_, _) => (StatusCode::UNAUTHORIZED, true),
+            GoogleTokenError::Unavailable(_, _) => (StatusCode::SERVICE_UNAVAILABLE, false),
+            GoogleTokenError::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR, false),
+        };
+        (status, Json(json!({
+            "error": self.to_string(),
+            "reconnect": reconnect
+        })))
+    }
+}
+
+/// Whether a token stored at `last_update` with lifetime `expires_in` should be refreshed at `now`.
+/// A zero lifetime means Google didn't tell us, so we rely on the 401 retry instead.
+pub fn token_expired(last_update: i32, expires_in: i32, now: i32) -> bool {
+    expires_in > 0 && now >= last_update + expires_in - EXPIRY_MARGIN_SECS
+}
+
+/// Whether a failed refresh means the grant itself is gone. Only a standard OAuth error from the
+/// token endpoint says that (invalid_grant and friends, sent with 400/401). Network failures,
+/// unparseable bodies and nonstandard errors from a struggling server are worth retrying.
+fn needs_reconnect<RE: std::error::Error + 'static>(error: &oauth2::RequestTokenError<RE, oauth2::basic::BasicErrorResponse>) -> bool {
+    match error {
+        oauth2::RequestTokenError::ServerResponse(response) => {
+            !matches!(response.error(), oauth2::basic::BasicErrorResponseType::Extension(_))
+        }
+        _ => false,
+    }
+}
+
+fn stored_tokens(state: &AppState, service: GoogleService, user_id: i32) -> Result<(String, String), GoogleTokenError> {
+    let tokens = match service {
+        GoogleService::Calendar => state.user_repository.get_google_calendar_tokens(user_id),
+        GoogleService::Tasks => state.user_repository.get_google_tasks_tokens(user_id),
+    };
+    match tokens {
+        Ok(Some(tokens)) => Ok(tokens),
+        Ok(None) => Err(GoogleTokenError::NoConnection(service)),
+        Err(e) => Err(GoogleTokenError::Storage(e.to_string())),
+    }
+}
+
+/// Exchanges the refresh token for a new access token and persists it, along with the new refresh token if Google rotated it
+pub async fn refresh_access_token(
+    state: &AppState,
+    service: GoogleService,
+    user_id: i32,
+    refresh_token: &str,
+) -> Result<(String, i32), GoogleTokenError> {
+    let http_client = reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Client should build");
+
+    let oauth_client = match service {
+        GoogleService::Calendar => &state.google_calendar_oauth_client,
+        GoogleService::Tasks => &state.google_tasks_oauth_client,
+    };
+
+    tracing::info!("Refreshing {} access token for user {}", service.display_name(), user_id);
+    let token_result = oauth_client
+        .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token.to_string()))
+        .request_async(&http_client)
+        .await
+        .map_err(|e| {
+            if !needs_reconnect(&e) {
+                tracing::warn!("{} token refresh for user {} failed, will retry: {}", service.display_name(), user_id, e);
+                return GoogleTokenError::Unavailable(service, e.to_string());
+            }
+            tracing::error!("{} token refresh rejected for user {}: {}", service.display_name(), user_id, e);
+            let service_name = match service {
+                GoogleService::Calendar => "google_calendar",
+                GoogleService::Tasks => "google_tasks",
+            };
+            state.connection_events.publish(user_id, service_name, crate::utils::connection_events::ConnectionStatus::TokenExpired);
+            GoogleTokenError::ReconnectRequired(service, e.to_string())
+        })?;
+
+    store_refreshed_tokens(state, service, user_id, refresh_token, &token_result)
+}
+
+/// Persists the token endpoint's answer: the new access token, and the refresh token if it changed
+fn store_refreshed_tokens(
+    state: &AppState,
+    service: GoogleService,
+    user_id: i32,
+    refresh_token: &str,
+    token_result: &oauth2::basic::BasicTokenResponse,
+) -> Result<(String, i32), GoogleTokenError> {
+    let new_access_token = token_result.access_token().secret().to_string();
+    let expires_in = token_result.expires_in()
+        .unwrap_or_default()
+        .as_secs() as i32;
+
+    let updated = match service {
+        GoogleService::Calendar => state.user_repository.update_google_calendar_access_token(user_id, &new_access_token, expires_in),
+        GoogleService::Tasks => state.user_repository.update_google_tasks_access_token(user_id, &new_access_token, expires_in),
+    };
+    updated.map_err(|e| GoogleTokenError::Storage(e.to_string()))?;
+
+    // Google may hand out a new refresh token with the access token, the old one stops working then
+    if let Some(new_refresh_token) = token_result.refresh_token().map(|token| token.secret().as_str()) {
+        if !new_refresh_token.is_empty() && new_refresh_token != refresh_token {
+            tracing::info!("{} refresh token rotated for user {}", service.display_name(), user_id);
+            let stored = match service {
+                GoogleService::Calendar => state.user_repository.update_google_calendar_refresh_token(user_id, new_refresh_token),
+                GoogleService::Tasks => state.user_repository.update_google_tasks_refresh_token(user_id, new_refresh_token),
+            };
+            stored.map_err(|e| GoogleTokenError::Storage(e.to_string()))?;
+        }
+    }
+
+    Ok((new_access_token, expires_in))
+}
+
+/// Returns (access token, refresh token), refreshing the access token first if it has expired
+pub async fn valid_tokens(
+    state: &AppState,
+    service: GoogleService,
+    user_id: i32,
+) -> Result<(String, String), GoogleTokenError> {
+    let (access_token, refresh_token) = stored_tokens(state, service, user_id)?;
+
+    let expiry = match service {
+        GoogleService::Calendar => state.user_repository.get_google_calendar_token_expiry(user_id),
+        GoogleService::Tasks => state.user_repository.get_google_tasks_token_expiry(user_id),
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i32;
+
+    match expiry {
+        Ok(Some((last_update, expires_in))) if token_expired(last_update, expires_in, now) => {
+            let (new_access_token, _) = refresh_access_token(state, service, user_id, &refresh_token).await?;
+            // Read back in case the refresh rotated the refresh token
+            let (_, refresh_token) = stored_tokens(state, service, user_id)?;
+            Ok((new_access_token, refresh_token))
+        }
+        Ok(_) => Ok((access_token, refresh_token)),
+        Err(e) => Err(GoogleTokenError::Storage(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oauth2::basic::{BasicErrorResponse, BasicErrorResponseType};
+    use oauth2::{HttpClientError, RequestTokenError, StandardErrorResponse};
+
+    type RefreshError = RequestTokenError<HttpClientError<reqwest::Error>, BasicErrorResponse>;
+
+    fn server_error(kind: BasicErrorResponseType) -> RefreshError {
+        RequestTokenError::ServerResponse(StandardErrorResponse::new(kind, None, None))
+    }
+
+    #[test]
+    fn rejected_grant_needs_reconnect() {
+        assert!(needs_reconnect(&server_error(BasicErrorResponseType::InvalidGrant)));
+        assert!(needs_reconnect(&server_error(BasicErrorResponseType::UnauthorizedClient)));
+    }
+
+    fn token_response(body: serde_json::Value) -> oauth2::basic::BasicTokenResponse {
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn stored_refresh_token_ciphertext(state: &AppState, user_id: i32) -> String {
+        use crate::schema::google_calendar;
+        use diesel::prelude::*;
+        google_calendar::table
+            .filter(google_calendar::user_id.eq(user_id))
+            .select(google_calendar::encrypted_refresh_token)
+            .first(&mut state.db_pool.get().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn rotated_refresh_token_replaces_the_stored_one_encrypted() {
+        crate::test_support::set_test_encryption_key();
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "rotate@example.com");
+        state.user_repository.create_google_calendar_connection(user_id, "access-old", Some("refresh-old"), 3600).unwrap();
+
+        let response = token_response(json!({
+            "access_token": "access-new",
+            "token_type": "bearer",
+            "expires_in": 3599,
+            "refresh_token": "refresh-new",
+        }));
+        let refreshed = store_refreshed_tokens(&state, GoogleService::Calendar, user_id, "refresh-old", &response).unwrap();
+
+        assert_eq!(refreshed, ("access-new".to_string(), 3599));
+        assert_eq!(
+            state.user_repository.get_google_calendar_tokens(user_id).unwrap(),
+            Some(("access-new".to_string(), "refresh-new".to_string()))
+        );
+        let ciphertext = stored_refresh_token_ciphertext(&state, user_id);
+        assert!(!ciphertext.contains("refresh-new"));
+        assert_eq!(crate::utils::encryption::decrypt(&ciphertext).unwrap(), "refresh-new");
+    }
+
+    #[test]
+    fn refresh_without_a_new_refresh_token_keeps_the_old_one() {
+        crate::test_support::set_test_encryption_key();
+        let state = crate::test_support::test_state();
+        let user_id = crate::test_support::create_test_user(&state, "keep@example.com");
+        state.user_repository.create_google_calendar_connection(user_id, "access-old", Some("refresh-old"), 3600).unwrap();
+
+        let response = token_response(json!({"access_token": "access-new", "token_type": "bearer", "expires_in": 3599}));
+        store_refreshed_tokens(&state, GoogleService::Calendar, user_id, "refresh-old", &response).unwrap();
+
+        let (_, refresh_token) = state.user_repository.get_google_calendar_tokens(user_id).unwrap().unwrap();
+        assert_eq!(refresh_token, "refresh-old");
+    }
+
+    #[test]
+    fn transient_failures_are_retryable() {
+        assert!(!needs_reconnect(&server_error(BasicErrorResponseType::Extension("internal_failure".to_string()))));
+        assert!(!needs_reconnect(&RefreshError::Other("server returned empty error response".to_string())));
+    }
+}
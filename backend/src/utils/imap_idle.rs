@@ -0,0 +1,354 @@
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use native_tls::TlsConnector;
+
+use crate::AppState;
+
+/// How long one IDLE command is left open. RFC 2177 lets servers drop it after 30 minutes.
+const IDLE_RENEW: Duration = Duration::from_secs(25 * 60);
+/// First wait before reconnecting a dropped listener, doubled up to RECONNECT_MAX
+const RECONNECT_MIN: Duration = Duration::from_secs(10);
+const RECONNECT_MAX: Duration = Duration::from_secs(15 * 60);
+/// Mail arriving this soon after the last check is picked up together with it
+const BATCH_WINDOW: Duration = Duration::from_secs(5);
+
+/// IMAP_IDLE_MAX_LISTENERS, how many IDLE connections are held open at once (default 100). Each
+/// one occupies a thread of tokio's blocking pool, so this stays well below its 512 threads.
+/// Users over the cap stay on polling.
+pub fn max_listeners() -> usize {
+    static MAX: OnceLock<usize> = OnceLock::new();
+    *MAX.get_or_init(|| match std::env::var("IMAP_IDLE_MAX_LISTENERS") {
+        Ok(value) => value.trim().parse::<usize>().map(|max| max.min(256)).unwrap_or_else(|_| {
+            tracing::warn!("Ignoring invalid IMAP_IDLE_MAX_LISTENERS={:?}, using 100", value);
+            100
+        }),
+        Err(_) => 100,
+    })
+}
+
+/// What the blocking IDLE session reports back to its supervisor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleEvent {
+    /// Logged in and waiting on INBOX, polling can stop
+    Listening,
+    /// INBOX changed, e.g. a new message arrived
+    MailboxChanged,
+    /// IDLE was renewed after IDLE_RENEW without changes
+    Renewed,
+}
+
+/// Ends a blocking session from the outside. Shutting its socket down wakes the IDLE wait right
+/// away, so a stopped listener doesn't keep the old credentials logged in until its next renewal.
+#[derive(Default)]
+pub struct IdleCancel {
+    cancelled: AtomicBool,
+    socket: Mutex<Option<TcpStream>>,
+}
+
+impl IdleCancel {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(socket) = self.socket.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Keeps a handle to the session's socket, false (with the socket shut) when already cancelled
+    fn attach(&self, socket: TcpStream) -> bool {
+        let mut slot = self.socket.lock().unwrap_or_else(|e| e.into_inner());
+        if self.is_cancelled() {
+            let _ = socket.shutdown(Shutdown::Both);
+            return false;
+        }
+        *slot = Some(socket);
+        true
+    }
+}
+
+/// Cancel handle of each user's current session
+fn cancels() -> &'static DashMap<i32, Arc<IdleCancel>> {
+    static CANCELS: OnceLock<DashMap<i32, Arc<IdleCancel>>> = OnceLock::new();
+    CANCELS.get_or_init(DashMap::new)
+}
+
+#[derive(Debug)]
+pub enum IdleError {
+    /// The server doesn't advertise IDLE, the user stays on polling
+    Unsupported,
+    Connection(String),
+}
+
+impl std::fmt::Display for IdleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdleError::Unsupported => write!(f, "server doesn't support IDLE"),
+            IdleError::Connection(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Connection details from `get_imap_credentials`
+pub struct IdleAccount {
+    pub email: String,
+    pub password: String,
+    pub server: String,
+    pub port: u16,
+}
+
+/// Blocking: logs in, checks for IDLE and waits on INBOX, sending an event whenever it changes.
+/// The IDLE is renewed every IDLE_RENEW. Returns Ok once the receiver is gone or `cancel` fires,
+/// errors when the connection fails so the supervisor can reconnect.
+pub fn idle_session(account: &IdleAccount, events: &tokio::sync::mpsc::UnboundedSender<IdleEvent>, cancel: &IdleCancel) -> Result<(), IdleError> {
+    let result = run_idle_session(account, events, cancel);
+    // A cancelled session fails on its shut socket, that's the expected way out
+    if cancel.is_cancelled() { Ok(()) } else { result }
+}
+
+fn run_idle_session(account: &IdleAccount, events: &tokio::sync::mpsc::UnboundedSender<IdleEvent>, cancel: &IdleCancel) -> Result<(), IdleError> {
+    let connection_error = |what: &str, e: &dyn std::fmt::Display| IdleError::Connection(format!("{}: {}", what, e));
+    let tls = TlsConnector::builder().build().map_err(|e| connection_error("Failed to create TLS connector", &e))?;
+    let socket = TcpStream::connect((account.server.as_str(), account.port))
+        .map_err(|e| connection_error("Failed to connect to IMAP server", &e))?;
+    let handle = socket.try_clone().map_err(|e| connection_error("Failed to clone socket", &e))?;
+    if !cancel.attach(handle) {
+        return Ok(());
+    }
+    let tls_stream = tls
+        .connect(&account.server, socket)
+        .map_err(|e| connection_error("TLS handshake failed", &e))?;
+    let mut client = imap::Client::new(tls_stream);
+    client.read_greeting().map_err(|e| connection_error("Failed to read greeting", &e))?;
+    let mut session = client
+        .login(&account.email, &account.password)
+        .map_err(|(e, _)| connection_error("Failed to login", &e))?;
+    let supports_idle = session
+        .capabilities()
+        .map_err(|e| connection_error("Failed to read capabilities", &e))?
+        .has_str("IDLE");
+    if !supports_idle {
+        let _ = session.logout();
+        return Err(IdleError::Unsupported);
+    }
+    session
+        .select(crate::handlers::imap_handlers::DEFAULT_IMAP_FOLDER)
+        .map_err(|e| connection_error("Failed to select INBOX", &e))?;
+    if events.send(IdleEvent::Listening).is_err() {
+        let _ = session.logout();
+        return Ok(());
+    }
+    loop {
+        let outcome = session
+            .idle()
+            .map_err(|e| connection_error("Failed to start IDLE", &e))?
+            .wait_with_timeout(IDLE_RENEW)
+            .map_err(|e| connection_error("IDLE failed", &e))?;
+        let event = if outcome == imap::extensions::idle::WaitOutcome::MailboxChanged {
+            IdleEvent::MailboxChanged
+        } else {
+            IdleEvent::Renewed
+        };
+        // The supervisor re-checks whether the user still wants IDLE on every event
+        if cancel.is_cancelled() || events.send(event).is_err() {
+            let _ = session.logout();
+            return Ok(());
+        }
+        // A NOOP tells a dead connection from a quiet mailbox before idling again
+        session.noop().map_err(|e| connection_error("Connection lost", &e))?;
+    }
+}
+
+/// Whether a user's IDLE connection is up, in which case the scheduler doesn't poll them
+pub fn is_listening(state: &AppState, user_id: i32) -> bool {
+    state.email_idle_listening.contains_key(&user_id)
+}
+
+/// Whether the scheduler can skip polling the user. IDLE only watches the primary account, so
+/// users with more than one account keep being polled for mail that lands in the others.
+pub fn covers_all_mail(state: &AppState, user_id: i32) -> bool {
+    is_listening(state, user_id)
+        && state.user_repository.get_imap_accounts(user_id).map_or(false, |accounts| accounts.len() <= 1)
+}
+
+/// Starts the listener for a user unless one is already running or the cap is reached. It
+/// reconnects with backoff until stopped, and exits for good when the server doesn't support IDLE
+/// or the user no longer qualifies (see `wants_idle`).
+pub fn start_idle_listener(state: &Arc<AppState>, user_id: i32) {
+    if state.email_idle_tasks.get(&user_id).map_or(false, |task| !task.is_finished()) {
+        tracing::debug!("IMAP IDLE listener already running for user {}", user_id);
+        return;
+    }
+    let running = state.email_idle_tasks.iter().filter(|task| !task.is_finished()).count();
+    if running >= max_listeners() {
+        tracing::warn!("IMAP IDLE listener cap of {} reached, user {} stays on polling", max_listeners(), user_id);
+        return;
+    }
+    let state_clone = state.clone();
+    let handle = tokio::spawn(async move {
+        run_idle_listener(&state_clone, user_id).await;
+        state_clone.email_idle_listening.remove(&user_id);
+        state_clone.email_idle_tasks.remove(&user_id);
+    });
+    state.email_idle_tasks.insert(user_id, handle);
+}
+
+/// Stops the listener and closes its IMAP connection
+pub fn stop_idle_listener(state: &AppState, user_id: i32) {
+    if let Some((_, task)) = state.email_idle_tasks.remove(&user_id) {
+        task.abort();
+        tracing::info!("Stopped IMAP IDLE listener for user {}", user_id);
+    }
+    if let Some((_, cancel)) = cancels().remove(&user_id) {
+        cancel.cancel();
+    }
+    state.email_idle_listening.remove(&user_id);
+}
+
+async fn run_idle_listener(state: &Arc<AppState>, user_id: i32) {
+    let mut backoff = RECONNECT_MIN;
+    loop {
+        if !wants_idle(state, user_id) {
+            tracing::info!("User {} no longer qualifies for IMAP IDLE, stopping listener", user_id);
+            return;
+        }
+        let account = match state.user_repository.get_imap_credentials(user_id) {
+            Ok(Some((email, password, server, port))) => IdleAccount {
+                email,
+                password,
+                server: server.unwrap_or_else(|| "imap.gmail.com".to_string()),
+                port: port.unwrap_or(993) as u16,
+            },
+            Ok(None) => {
+                tracing::info!("IMAP connection removed for user {}, stopping IDLE listener", user_id);
+                return;
+            }
+            Err(e) => {
+                tracing::error!("Failed to get IMAP credentials for IDLE listener of user {}: {}", user_id, e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+                continue;
+            }
+        };
+        let cancel = Arc::new(IdleCancel::default());
+        cancels().insert(user_id, cancel.clone());
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let session_cancel = cancel.clone();
+        let session = tokio::task::spawn_blocking(move || idle_session(&account, &events_tx, &session_cancel));
+        let mut stopped = false;
+        while let Some(event) = events.recv().await {
+            // Tier, settings or credentials may have changed since the listener started
+            if !wants_idle(state, user_id) {
+                tracing::info!("User {} no longer qualifies for IMAP IDLE, stopping listener", user_id);
+                cancel.cancel();
+                stopped = true;
+                break;
+            }
+            match event {
+                IdleEvent::Listening => {
+                    tracing::info!("IMAP IDLE listening for user {}", user_id);
+                    state.email_idle_listening.insert(user_id, chrono::Utc::now().timestamp());
+                    backoff = RECONNECT_MIN;
+                    // Anything that arrived while reconnecting
+                    if !crate::jobs::scheduler::is_user_paused(state, user_id) {
+                        crate::jobs::scheduler::check_new_emails(state, user_id).await;
+                    }
+                }
+                IdleEvent::MailboxChanged => {
+                    // Flag changes and bursts of mail come as several events, one check covers them
+                    tokio::time::sleep(BATCH_WINDOW).await;
+                    while events.try_recv().is_ok() {}
+                    if crate::jobs::scheduler::is_user_paused(state, user_id) {
+                        continue;
+                    }
+                    tracing::debug!("IMAP IDLE saw new mail for user {}", user_id);
+                    crate::jobs::scheduler::check_new_emails(state, user_id).await;
+                }
+                IdleEvent::Renewed => {}
+            }
+        }
+        // Polling takes over until the listener is back
+        state.email_idle_listening.remove(&user_id);
+        drop(events);
+        let outcome = session.await;
+        cancels().remove_if(&user_id, |_, current| Arc::ptr_eq(current, &cancel));
+        if stopped {
+            return;
+        }
+        match outcome {
+            Ok(Ok(())) => return,
+            Ok(Err(IdleError::Unsupported)) => {
+                tracing::info!("IMAP server of user {} doesn't support IDLE, staying on polling", user_id);
+                return;
+            }
+            Ok(Err(e)) => tracing::warn!("IMAP IDLE connection for user {} dropped, reconnecting in {}s: {}", user_id, backoff.as_secs(), e),
+            Err(e) => tracing::error!("IMAP IDLE session for user {} panicked: {}", user_id, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX);
+    }
+}
+
+/// IDLE is only kept open for users who turned it on, still have IMAP connected and get proactive email checks
+pub fn wants_idle(state: &AppState, user_id: i32) -> bool {
+    let enabled = state.user_core.get_user_settings(user_id).ok().and_then(|settings| settings.email_idle).unwrap_or(false);
+    enabled
+        && state.user_repository.get_imap_credentials(user_id).ok().flatten().is_some()
+        && state.user_core.find_by_id(user_id).ok().flatten().map_or(false, |user| user.sub_tier.as_deref() == Some("tier 2"))
+}
+
+/// Starts listeners for everyone who wants one, on startup
+pub async fn initialize_idle_listeners(state: &Arc<AppState>) {
+    let users = match state.user_repository.get_active_imap_connection_users() {
+        Ok(users) => users,
+        Err(e) => {
+            tracing::error!("Failed to get IMAP users for IDLE listeners: {}", e);
+            return;
+        }
+    };
+    for user_id in users {
+        if wants_idle(state, user_id) {
+            start_idle_listener(state, user_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn cancel_shuts_the_attached_socket() {
+        let (client, mut server) = socket_pair();
+        let cancel = IdleCancel::default();
+        assert!(cancel.attach(client.try_clone().unwrap()));
+        cancel.cancel();
+        assert!(cancel.is_cancelled());
+        // The peer sees EOF instead of waiting on an open connection
+        let mut buf = [0u8; 1];
+        assert_eq!(server.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn attach_after_cancel_refuses_the_socket() {
+        let (client, _server) = socket_pair();
+        let cancel = IdleCancel::default();
+        cancel.cancel();
+        assert!(!cancel.attach(client));
+    }
+}
@@ -0,0 +1,191 @@
+use mail_parser::Message;
+
+/// Elements whose contents are never shown to a reader
+const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "head", "title"];
+/// Tags that start a new line in rendered HTML
+const BLOCK_TAGS: [&str; 18] = [
+    "br", "p", "div", "tr", "li", "ul", "ol", "table", "h1", "h2", "h3", "h4", "h5", "h6",
+    "blockquote", "section", "article", "hr",
+];
+
+/// The body as readable text: the text/plain part when the email has one, otherwise the HTML
+/// part converted with `html_to_text`. Blank lines and surrounding whitespace are dropped.
+pub fn readable_body(message: &Message) -> Option<String> {
+    let text = match message.text_bodies().find(|part| !part.is_text_html()).and_then(|part| part.text_contents()) {
+        Some(plain) if !plain.trim().is_empty() => plain.to_string(),
+        _ => html_to_text(message.html_bodies().find_map(|part| part.text_contents())?),
+    };
+    let cleaned = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+/// Plain text from HTML, good enough to read aloud or send over SMS: scripts, styles and comments
+/// are dropped, block elements become line breaks, entities are decoded and whitespace collapsed.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        let opens_tag = rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        let Some(end) = rest.find('>').filter(|_| opens_tag) else {
+            // A '<' in the text, like "a < b"
+            text.push('<');
+            rest = &rest[1..];
+            continue;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if !tag.starts_with('/') && HIDDEN_ELEMENTS.contains(&name.as_str()) {
+            // Skip to the closing tag, case-insensitively
+            let closing = format!("</{}", name);
+            rest = rest.to_ascii_lowercase().find(&closing)
+                .and_then(|pos| rest[pos..].find('>').map(|close| &rest[pos + close + 1..]))
+                .unwrap_or("");
+            continue;
+        }
+        if BLOCK_TAGS.contains(&name.as_str()) {
+            text.push('\n');
+            if name == "li" && !tag.starts_with('/') {
+                text.push_str("- ");
+            }
+        } else if name == "td" || name == "th" {
+            text.push(' ');
+        }
+    }
+    text.push_str(rest);
+
+    decode_entities(&text)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty() && line != "-")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Decodes numeric entities and the named ones that show up in email
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((end, entity_text(&rest[1..end + 1])?)));
+        match entity {
+            Some((end, replacement)) => {
+                decoded.push_str(replacement.as_ref());
+                rest = &rest[end + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity_text(name: &str) -> Option<std::borrow::Cow<'static, str>> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code).map(|c| c.to_string().into());
+    }
+    Some(std::borrow::Cow::Borrowed(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        // Non-breaking spaces are just spacing to a reader, zero-width joiners (used as
+        // preheader padding by newsletters) aren't anything
+        "nbsp" | "ensp" | "emsp" | "thinsp" => " ",
+        "zwnj" | "zwj" => "",
+        "ndash" => "–",
+        "mdash" => "—",
+        "hellip" => "…",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "laquo" => "«",
+        "raquo" => "»",
+        "bull" | "middot" => "·",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "euro" => "€",
+        "pound" => "£",
+        "yen" => "¥",
+        "cent" => "¢",
+        "deg" => "°",
+        "times" => "×",
+        _ => return None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mail_parser::MessageParser;
+
+    const NEWSLETTER: &str = "<html><head><title>Order update</title><style>p { color: red }</style></head>\
+        <body><!-- preheader --><div>Hi Anna,</div>\
+        <p>Your order&nbsp;#1234 is on its way &amp; arrives <b>Friday</b>.</p>\
+        <ul><li>Coffee  beans</li><li>Filter papers</li></ul>\
+        <table><tr><td>Total</td><td>&euro;24.90</td></tr></table>\
+        <p>Orders &lt; 30&#8364; ship for 2 < 3 euros.</p><script>track('open')</script></body></html>";
+
+    #[test]
+    fn html_email_becomes_readable_text() {
+        assert_eq!(
+            html_to_text(NEWSLETTER),
+            "Hi Anna,\n\
+             Your order #1234 is on its way & arrives Friday.\n\
+             - Coffee beans\n\
+             - Filter papers\n\
+             Total €24.90\n\
+             Orders < 30€ ship for 2 < 3 euros."
+        );
+    }
+
+    #[test]
+    fn plain_part_is_preferred_and_html_only_mail_is_converted() {
+        let alternative = "From: shop@example.com\r\nSubject: Order\r\nMIME-Version: 1.0\r\n\
+            Content-Type: multipart/alternative; boundary=\"b\"\r\n\r\n\
+            --b\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n  Your order ships Friday.  \r\n\r\n\r\n--b\r\n\
+            Content-Type: text/html; charset=utf-8\r\n\r\n<p>Your <b>order</b> ships Friday.</p>\r\n--b--\r\n";
+        let message = MessageParser::default().parse(alternative.as_bytes()).unwrap();
+        assert_eq!(readable_body(&message).as_deref(), Some("Your order ships Friday."));
+
+        let html_only = format!(
+            "From: shop@example.com\r\nSubject: Order\r\nMIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\n\r\n{}\r\n",
+            NEWSLETTER
+        );
+        let message = MessageParser::default().parse(html_only.as_bytes()).unwrap();
+        let body = readable_body(&message).unwrap();
+        assert!(body.starts_with("Hi Anna,\nYour order #1234"), "{body}");
+        assert!(!body.contains("<p>") && !body.contains("track("), "{body}");
+    }
+}
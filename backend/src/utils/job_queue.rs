@@ -0,0 +1,284 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
+use tokio::task::JoinHandle;
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// A unit of outbound work (sending a message, processing attachments...).
+/// `run` is called again for every retry so it has to build a fresh future each time.
+pub struct BackgroundJob {
+    pub name: String,
+    pub max_attempts: u32,
+    run: Box<dyn Fn() -> JobFuture + Send + Sync>,
+}
+
+impl BackgroundJob {
+    pub fn new<F, Fut>(name: impl Into<String>, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            max_attempts: 1, // no retries by default so sends never go out twice
+            run: Box::new(move || Box::pin(run())),
+        }
+    }
+
+    pub fn with_retries(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+}
+
+/// Small in-process job queue: a bounded channel drained by a fixed pool of workers.
+/// Enqueueing waits when the channel is full which gives us backpressure instead of
+/// an unbounded amount of detached tasks.
+pub struct JobQueue {
+    sender: mpsc::Sender<BackgroundJob>,
+    pending: Arc<AtomicUsize>, // queued + running + waiting for their delay
+    shutdown_tx: watch::Sender<bool>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl JobQueue {
+    pub fn start(worker_count: usize, capacity: usize) -> Arc<Self> {
+        let (sender, receiver) = mpsc::channel::<BackgroundJob>(capacity.max(1));
+        let receiver = Arc::new(Mutex::new(receiver));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let (shutdown_tx, _) = watch::channel(false);
+
+        let mut workers = Vec::new();
+        for worker_id in 0..worker_count.max(1) {
+            let receiver = Arc::clone(&receiver);
+            let pending = Arc::clone(&pending);
+            workers.push(tokio::spawn(async move {
+                loop {
+                    let job = {
+                        let mut receiver = receiver.lock().await;
+                        receiver.recv().await
+                    };
+                    let Some(job) = job else { break };
+                    run_job(worker_id, job).await;
+                    pending.fetch_sub(1, Ordering::SeqCst);
+                }
+            }));
+        }
+
+        Arc::new(Self {
+            sender,
+            pending,
+            shutdown_tx,
+            workers: Mutex::new(workers),
+        })
+    }
+
+    /// Worker and capacity counts from JOB_QUEUE_WORKERS / JOB_QUEUE_CAPACITY, defaulting to 4 and 1000
+    pub fn from_env() -> Arc<Self> {
+        let workers = std::env::var("JOB_QUEUE_WORKERS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(4);
+        let capacity = std::env::var("JOB_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(1000);
+        Self::start(workers, capacity)
+    }
+
+    /// Number of jobs that are queued, running or waiting for their delay to pass
+    pub fn pending_count(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        *self.shutdown_tx.borrow()
+    }
+
+    pub async fn enqueue(&self, job: BackgroundJob) -> Result<(), String> {
+        if self.is_shutting_down() {
+            return Err(format!("Job queue is shutting down, rejected job '{}'", job.name));
+        }
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.sender.send(job).await {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            return Err(format!("Failed to enqueue job '{}': workers are gone", e.0.name));
+        }
+        Ok(())
+    }
+
+    /// Enqueues the job after `delay` unless `cancel_rx` fires (or its sender is dropped) first.
+    /// Shutdown drops jobs still waiting out their delay: the delay is the user's window to cancel,
+    /// so sending early could send something they were about to take back.
+    pub fn enqueue_delayed(
+        self: &Arc<Self>,
+        job: BackgroundJob,
+        delay: Duration,
+        cancel_rx: oneshot::Receiver<()>,
+    ) -> Result<(), String> {
+        if self.is_shutting_down() {
+            return Err(format!("Job queue is shutting down, rejected job '{}'", job.name));
+        }
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        let queue = Arc::clone(self);
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let fire = tokio::select! {
+                _ = tokio::time::sleep(delay) => true,
+                _ = cancel_rx => false,
+                _ = shutdown_rx.wait_for(|shutting_down| *shutting_down) => {
+                    tracing::warn!("Shutting down, dropping delayed job '{}' before its cancel window ended", job.name);
+                    false
+                }
+            };
+            if !fire {
+                tracing::debug!("Delayed job '{}' not run", job.name);
+                queue.pending.fetch_sub(1, Ordering::SeqCst);
+                return;
+            }
+            // Already counted as pending above so push straight to the channel
+            if let Err(e) = queue.sender.send(job).await {
+                tracing::error!("Failed to enqueue delayed job '{}': workers are gone", e.0.name);
+                queue.pending.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+        Ok(())
+    }
+
+    /// Stops accepting new jobs, drops delayed ones and waits for everything else pending to finish.
+    /// Whatever is still running after `timeout` is aborted.
+    pub async fn shutdown(&self, timeout: Duration) {
+        let _ = self.shutdown_tx.send(true);
+        tracing::info!("Draining job queue, {} jobs pending", self.pending_count());
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.pending_count() > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let remaining = self.pending_count();
+        if remaining > 0 {
+            tracing::warn!("Job queue drain timed out with {} jobs still pending", remaining);
+        } else {
+            tracing::info!("Job queue drained");
+        }
+        for worker in self.workers.lock().await.drain(..) {
+            worker.abort();
+        }
+    }
+}
+
+async fn run_job(worker_id: usize, job: BackgroundJob) {
+    let mut attempt = 1;
+    loop {
+        tracing::debug!("Worker {} running job '{}' (attempt {}/{})", worker_id, job.name, attempt, job.max_attempts);
+        match (job.run)().await {
+            Ok(()) => return,
+            Err(e) if attempt < job.max_attempts => {
+                let backoff = Duration::from_secs(2u64.pow(attempt.min(6)));
+                tracing::warn!("Job '{}' failed on attempt {}: {}. Retrying in {:?}", job.name, attempt, e, backoff);
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                tracing::error!("Job '{}' failed after {} attempts: {}", job.name, attempt, e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    fn counting_job(name: &str, runs: &Arc<AtomicU32>) -> BackgroundJob {
+        let runs = Arc::clone(runs);
+        BackgroundJob::new(name, move || {
+            let runs = Arc::clone(&runs);
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+    }
+
+    async fn wait_until_idle(queue: &JobQueue) {
+        for _ in 0..100 {
+            if queue.pending_count() == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("jobs still pending");
+    }
+
+    #[tokio::test]
+    async fn enqueued_jobs_are_processed() {
+        let queue = JobQueue::start(2, 10);
+        let runs = Arc::new(AtomicU32::new(0));
+        for i in 0..5 {
+            queue.enqueue(counting_job(&format!("job {}", i), &runs)).await.unwrap();
+        }
+        wait_until_idle(&queue).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn failed_jobs_are_retried() {
+        let queue = JobQueue::start(1, 10);
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&attempts);
+        let job = BackgroundJob::new("flaky", move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 { Err("first try fails".to_string()) } else { Ok(()) }
+            }
+        }).with_retries(2);
+        // Paused time skips ahead through the retry backoff
+        tokio::time::pause();
+        queue.enqueue(job).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_queued_jobs() {
+        let queue = JobQueue::start(1, 10);
+        let runs = Arc::new(AtomicU32::new(0));
+        for i in 0..3 {
+            queue.enqueue(counting_job(&format!("job {}", i), &runs)).await.unwrap();
+        }
+        queue.shutdown(Duration::from_secs(5)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(queue.pending_count(), 0);
+        assert!(queue.enqueue(counting_job("late", &runs)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_drops_delayed_jobs_instead_of_sending_early() {
+        let queue = JobQueue::start(1, 10);
+        let runs = Arc::new(AtomicU32::new(0));
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+        queue.enqueue_delayed(counting_job("delayed send", &runs), Duration::from_secs(60), cancel_rx).unwrap();
+        assert_eq!(queue.pending_count(), 1);
+
+        queue.shutdown(Duration::from_secs(5)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert_eq!(queue.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_delayed_job_never_runs() {
+        let queue = JobQueue::start(1, 10);
+        let runs = Arc::new(AtomicU32::new(0));
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        queue.enqueue_delayed(counting_job("delayed send", &runs), Duration::from_secs(60), cancel_rx).unwrap();
+        cancel_tx.send(()).unwrap();
+        wait_until_idle(&queue).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+/// How long a detected language carries over to short messages that don't give one away ("ok", "7pm")
+const STICKY_FOR: Duration = Duration::from_secs(30 * 60);
+/// Common-word hits a message needs before it's taken to be in a language
+const MIN_HITS: usize = 2;
+
+/// Frequent short words per language. Good enough to tell the supported languages apart
+/// without a model call, anything else falls back to the configured language.
+const COMMON_WORDS: [(&str, &[&str]); 6] = [
+    ("en", &["the", "and", "is", "are", "you", "what", "my", "me", "to", "of", "have", "can", "please", "when", "with", "for", "this", "today", "tomorrow", "send", "how", "do", "any"]),
+    ("fi", &["ja", "on", "ei", "mitä", "mikä", "minä", "mä", "sä", "mulle", "minun", "mun", "onko", "että", "tänään", "huomenna", "kiitos", "moi", "hei", "voitko", "kello", "lähetä", "mulla", "onks", "mitkä", "kanssa"]),
+    ("de", &["und", "ist", "nicht", "ich", "das", "die", "der", "was", "wie", "mir", "bitte", "heute", "morgen", "ein", "eine", "habe", "hast", "mit", "für", "kannst", "sind"]),
+    ("sv", &["och", "är", "inte", "jag", "det", "vad", "hur", "mig", "idag", "imorgon", "tack", "hej", "kan", "har", "ett", "med", "för", "på", "någon", "skicka"]),
+    ("fr", &["et", "est", "pas", "je", "tu", "le", "les", "quoi", "comment", "moi", "aujourd'hui", "demain", "merci", "bonjour", "une", "avec", "pour", "sur", "peux", "mes"]),
+    ("es", &["y", "es", "yo", "tú", "el", "los", "qué", "cómo", "hoy", "mañana", "gracias", "hola", "una", "con", "para", "por", "mi", "puedes", "tengo", "mis"]),
+];
+
+/// Letters only one of the supported languages uses, each counts as a hit
+const DISTINCT_LETTERS: [(char, &str); 6] = [('å', "sv"), ('ß', "de"), ('ñ', "es"), ('¿', "es"), ('ç', "fr"), ('ê', "fr")];
+
+/// English name of a language code, for the reply instruction in the prompt
+pub fn language_name(code: &str) -> &str {
+    match code {
+        "en" => "English",
+        "fi" => "Finnish",
+        "de" => "German",
+        "sv" => "Swedish",
+        "fr" => "French",
+        "es" => "Spanish",
+        other => other,
+    }
+}
+
+/// Guesses the language of a message from its common words. None when the message is too short
+/// or too mixed to tell.
+pub fn detect(text: &str) -> Option<&'static str> {
+    let lowered = text.to_lowercase();
+    let words: Vec<&str> = lowered
+        .split(|c: char| !(c.is_alphabetic() || c == '\''))
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let mut scores: Vec<(&'static str, usize)> = COMMON_WORDS
+        .iter()
+        .map(|(code, common)| {
+            let mut hits = words.iter().filter(|word| common.contains(word)).count();
+            hits += DISTINCT_LETTERS
+                .iter()
+                .filter(|(letter, language)| language == code && lowered.contains(*letter))
+                .count();
+            (*code, hits)
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let (best, hits) = scores[0];
+    let runner_up = scores[1].1;
+    (hits >= MIN_HITS && hits > runner_up).then_some(best)
+}
+
+/// Last language detected per user, so a conversation keeps its language through short replies
+pub struct LanguageCache {
+    detected: DashMap<i32, (&'static str, Instant)>,
+}
+
+impl LanguageCache {
+    pub fn new() -> Self {
+        Self { detected: DashMap::new() }
+    }
+
+    /// Drops what was detected from the user's messages, e.g. when they clear their history
+    pub fn forget(&self, user_id: i32) {
+        self.detected.remove(&user_id);
+    }
+
+    /// Language to reply to this message in. With detection off, or when neither the message nor
+    /// the user's recent messages give a language away, it's the configured one.
+    pub fn reply_language(&self, user_id: i32, configured: &str, body: &str, enabled: bool) -> String {
+        if !enabled {
+            return configured.to_string();
+        }
+        if let Some(language) = detect(body) {
+            self.detected.insert(user_id, (language, Instant::now()));
+            if language != configured {
+                tracing::debug!("Replying to user {} in {} instead of {}", user_id, language, configured);
+            }
+            return language.to_string();
+        }
+        match self.detected.get(&user_id) {
+            Some(entry) if entry.1.elapsed() < STICKY_FOR => entry.0.to_string(),
+            _ => configured.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    #[test]
+    fn finnish_text_from_an_english_user_gets_a_finnish_reply() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "kaksikielinen@example.com");
+        state.user_core.update_detect_sms_language(user_id, true).unwrap();
+        let settings = state.user_core.get_user_settings(user_id).unwrap();
+        assert_eq!(settings.agent_language, "en");
+        let reply_language = |body: &str| {
+            state.sms_languages.reply_language(
+                user_id,
+                &settings.agent_language,
+                body,
+                settings.detect_sms_language.unwrap_or(false),
+            )
+        };
+
+        let language = reply_language("Moi, onko mulla tänään mitään kalenterissa?");
+        assert_eq!((language.as_str(), language_name(&language)), ("fi", "Finnish"));
+        // A short follow-up gives nothing away, the conversation stays in Finnish
+        assert_eq!(reply_language("ok 7pm"), "fi");
+        assert_eq!(reply_language("What do I have tomorrow?"), "en");
+    }
+
+    #[test]
+    fn detection_off_keeps_the_configured_language() {
+        let cache = LanguageCache::new();
+        assert_eq!(cache.reply_language(1, "en", "Moi, onko mulla tänään mitään kalenterissa?", false), "en");
+        assert_eq!(detect("ok"), None);
+    }
+}
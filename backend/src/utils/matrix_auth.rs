@@ -252,27 +252,92 @@ pub async fn get_client(user_id: i32, state: &Arc<AppState>) -> Result<MatrixCli
 
 /// Get a cached Matrix client from AppState, with fallback to creating a new client
 /// Note: The fallback client is not stored in the cache - that's managed by the scheduler
+/// Registers the bridge message handler, caches the client and starts its sync loop. When another
+/// caller cached a client for the user in the meantime, that one is kept and returned instead.
+pub async fn cache_and_sync(state: &Arc<AppState>, user_id: i32, client: MatrixClient) -> Arc<MatrixClient> {
+    use matrix_sdk::ruma::events::room::message::OriginalSyncRoomMessageEvent;
+    use matrix_sdk::room::Room;
+
+    // Bridge messages arrive through the sync loop, so those users keep their client through evictions
+    let has_bridges = state.user_repository.has_active_bridges(user_id).unwrap_or(true);
+    let mut matrix_clients = state.matrix_clients.lock().await;
+    let mut sync_tasks = state.matrix_sync_tasks.lock().await;
+    matrix_clients.set_pinned(user_id, has_bridges);
+    if let Some(existing) = matrix_clients.get(&user_id) {
+        return existing;
+    }
+    let state_for_handler = Arc::clone(state);
+    client.add_event_handler(move |ev: OriginalSyncRoomMessageEvent, room: Room, client| {
+        let state = Arc::clone(&state_for_handler);
+        async move {
+            tracing::debug!("📨 Received message in room {}: {:?}", room.room_id(), ev);
+            crate::utils::bridge::handle_bridge_message(ev, room, client, state).await;
+        }
+    });
+    let client = Arc::new(client);
+    let evicted = matrix_clients.insert(user_id, client.clone());
+    shut_down_evicted(evicted, &mut sync_tasks);
+
+    let sync_settings = matrix_sdk::config::SyncSettings::default()
+        .timeout(std::time::Duration::from_secs(30))
+        .full_state(true);
+    let sync_client = client.clone();
+    let handle = tokio::spawn(async move {
+        loop {
+            match sync_client.sync(sync_settings.clone()).await {
+                Ok(_) => {
+                    tracing::debug!("Sync completed normally for user {}", user_id);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                },
+                Err(e) => {
+                    tracing::error!("Matrix sync error for user {}: {}", user_id, e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+                }
+            }
+        }
+    });
+    if let Some(previous) = sync_tasks.insert(user_id, handle) {
+        previous.abort();
+    }
+    client
+}
+
+/// Stops the sync tasks of clients evicted from the cache. The clients are dropped once the
+/// aborted tasks let go of them, `get_cached_client` starts them again when they're needed.
+/// Users with a connected bridge are pinned in the cache so their sync never stops here.
+pub fn shut_down_evicted(
+    evicted: Vec<(i32, Arc<MatrixClient>)>,
+    sync_tasks: &mut std::collections::HashMap<i32, tokio::task::JoinHandle<()>>,
+) {
+    for (user_id, _client) in evicted {
+        if let Some(task) = sync_tasks.remove(&user_id) {
+            task.abort();
+        }
+        tracing::info!("Evicted least recently used Matrix client of user {}", user_id);
+    }
+}
+
 pub async fn get_cached_client(
     user_id: i32,
     state: &Arc<AppState>,
 ) -> Result<Arc<MatrixClient>> {
     // Get the matrix clients map from AppState
-    let matrix_clients = state.matrix_clients.lock().await;
+    let mut matrix_clients = state.matrix_clients.lock().await;
     
     // Try to get the client for this user
     if let Some(client) = matrix_clients.get(&user_id) {
         tracing::debug!("Found cached Matrix client for user {}", user_id);
-        Ok(client.clone())
+        Ok(client)
     } else {
-        tracing::debug!("No cached Matrix client found for user {}, creating temporary client", user_id);
+        tracing::debug!("No cached Matrix client found for user {}, creating one", user_id);
         // Drop the lock before the potentially long-running get_client operation
         drop(matrix_clients);
         
-        // Create a new client as fallback
+        // Evicted or never started, bring it back with its sync task
         match get_client(user_id, state).await {
             Ok(client) => {
-                tracing::debug!("Successfully created temporary Matrix client for user {}", user_id);
-                Ok(Arc::new(client))
+                tracing::debug!("Successfully created Matrix client for user {}", user_id);
+                Ok(cache_and_sync(state, user_id, client).await)
             },
             Err(e) => {
                 tracing::error!("Failed to create temporary Matrix client for user {}: {}", user_id, e);
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// References a cached client has when nobody is using it: the cache's own and its sync task's
+const IDLE_REFS: usize = 2;
+
+/// MATRIX_CLIENT_CACHE_MAX, how many users keep a client and a sync task at once (default 500)
+pub fn cache_capacity() -> usize {
+    match std::env::var("MATRIX_CLIENT_CACHE_MAX") {
+        Ok(value) => value.trim().parse::<usize>().ok().filter(|max| *max > 0).unwrap_or_else(|| {
+            tracing::warn!("Ignoring invalid MATRIX_CLIENT_CACHE_MAX={:?}, using 500", value);
+            500
+        }),
+        Err(_) => 500,
+    }
+}
+
+struct CacheEntry<T> {
+    client: Arc<T>,
+    last_used: u64,
+}
+
+/// Matrix clients by user id with a size cap. Going over it evicts the least recently used
+/// client nobody else holds a reference to, in-use clients are never evicted even if that
+/// leaves the cache over the cap for a while. Pinned users are never evicted either: their
+/// sync task is what delivers bridge messages to `handle_bridge_message`, without it their
+/// notifications would stop until something happened to need the client again.
+pub struct ClientCache<T> {
+    entries: HashMap<i32, CacheEntry<T>>,
+    pinned: HashSet<i32>,
+    capacity: usize,
+    clock: u64,
+}
+
+impl<T> ClientCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), pinned: HashSet::new(), capacity: capacity.max(1), clock: 0 }
+    }
+
+    /// Pins or unpins the user's client, users with a connected bridge are pinned
+    pub fn set_pinned(&mut self, user_id: i32, pinned: bool) {
+        if pinned {
+            self.pinned.insert(user_id);
+        } else {
+            self.pinned.remove(&user_id);
+        }
+    }
+
+    fn touch(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// The user's client, marking it as the most recently used
+    pub fn get(&mut self, user_id: &i32) -> Option<Arc<T>> {
+        let now = self.touch();
+        let entry = self.entries.get_mut(user_id)?;
+        entry.last_used = now;
+        Some(entry.client.clone())
+    }
+
+    /// Adds or replaces a user's client and returns whatever had to be evicted to stay under the
+    /// cap. The caller shuts the evicted clients down, see `matrix_auth::shut_down_evicted`.
+    pub fn insert(&mut self, user_id: i32, client: Arc<T>) -> Vec<(i32, Arc<T>)> {
+        let now = self.touch();
+        self.entries.insert(user_id, CacheEntry { client, last_used: now });
+        let mut evicted = Vec::new();
+        while self.entries.len() > self.capacity {
+            let least_recent = self
+                .entries
+                .iter()
+                .filter(|(id, entry)| **id != user_id && !self.pinned.contains(id) && Arc::strong_count(&entry.client) <= IDLE_REFS)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| *id);
+            let Some(id) = least_recent else {
+                tracing::warn!(
+                    "Matrix client cache is over its cap of {} but every client is in use or pinned",
+                    self.capacity
+                );
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&id) {
+                evicted.push((id, entry.client));
+            }
+        }
+        evicted
+    }
+
+    pub fn remove(&mut self, user_id: &i32) -> Option<Arc<T>> {
+        self.pinned.remove(user_id);
+        self.entries.remove(user_id).map(|entry| entry.client)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.pinned.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Caches `count` clients for users 1..=count, oldest first, held only by the cache
+    fn filled(capacity: usize, count: i32) -> ClientCache<String> {
+        let mut cache = ClientCache::new(capacity);
+        for user_id in 1..=count {
+            assert!(cache.insert(user_id, Arc::new(format!("client {}", user_id))).is_empty());
+        }
+        cache
+    }
+
+    #[test]
+    fn going_over_the_cap_evicts_least_recently_used() {
+        let mut cache = filled(3, 3);
+        // Using user 1 makes user 2 the least recently used
+        cache.get(&1);
+
+        let evicted = cache.insert(4, Arc::new("client 4".to_string()));
+        assert_eq!(evicted.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(cache.entries.len(), 3);
+        assert!(cache.get(&2).is_none());
+    }
+
+    #[test]
+    fn clients_in_use_are_not_evicted() {
+        let mut cache = filled(2, 2);
+        // Held by a caller on top of the cache and its sync task
+        let in_use = cache.get(&1).unwrap();
+        let _sync_task = in_use.clone();
+
+        let evicted = cache.insert(3, Arc::new("client 3".to_string()));
+        assert_eq!(evicted.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2]);
+        assert!(cache.get(&1).is_some());
+    }
+
+    #[test]
+    fn pinned_users_are_not_evicted() {
+        let mut cache = filled(2, 2);
+        cache.set_pinned(1, true);
+
+        let evicted = cache.insert(3, Arc::new("client 3".to_string()));
+        assert_eq!(evicted.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2]);
+        assert!(cache.get(&1).is_some());
+
+        // Everything else pinned too leaves the cache over its cap rather than stopping a sync
+        cache.set_pinned(3, true);
+        assert!(cache.insert(4, Arc::new("client 4".to_string())).is_empty());
+        assert_eq!(cache.entries.len(), 3);
+    }
+
+    #[test]
+    fn removing_a_client_unpins_it() {
+        let mut cache = filled(1, 1);
+        cache.set_pinned(1, true);
+        cache.remove(&1);
+        cache.insert(1, Arc::new("client 1 again".to_string()));
+
+        let evicted = cache.insert(2, Arc::new("client 2".to_string()));
+        assert_eq!(evicted.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Timeout and retries for bridge operations against the homeserver
+#[derive(Debug, Clone, Copy)]
+pub struct MatrixPolicy {
+    /// Per attempt of reads like listing rooms
+    pub timeout: Duration,
+    /// Per send, longer since it may download and upload an image
+    pub send_timeout: Duration,
+    /// Tries of a read before giving up, sends are only tried once
+    pub attempts: u32,
+    /// Delay before the first retry, doubled after each
+    pub backoff: Duration,
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    match std::env::var(key) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("Ignoring invalid {}={:?}, using {}", key, value, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// MATRIX_OP_TIMEOUT_SECS, MATRIX_SEND_TIMEOUT_SECS, MATRIX_OP_ATTEMPTS and MATRIX_OP_BACKOFF_MS, read once
+pub fn policy() -> &'static MatrixPolicy {
+    static POLICY: OnceLock<MatrixPolicy> = OnceLock::new();
+    POLICY.get_or_init(|| MatrixPolicy {
+        timeout: Duration::from_secs(env_u64("MATRIX_OP_TIMEOUT_SECS", 20).max(1)),
+        send_timeout: Duration::from_secs(env_u64("MATRIX_SEND_TIMEOUT_SECS", 60).max(1)),
+        attempts: env_u64("MATRIX_OP_ATTEMPTS", 3).clamp(1, 10) as u32,
+        backoff: Duration::from_millis(env_u64("MATRIX_OP_BACKOFF_MS", 500)),
+    })
+}
+
+/// A bridge operation that ran out of time or retries. The messages are meant to be read to the user as-is.
+#[derive(Debug)]
+pub enum MatrixOpError {
+    TimedOut { operation: String, after: Duration },
+    Exhausted { operation: String, attempts: u32, last_error: String },
+}
+
+impl std::fmt::Display for MatrixOpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatrixOpError::TimedOut { operation, after } => write!(
+                f,
+                "The messaging bridge didn't respond in time while trying to {} (waited {} seconds). Please try again in a moment.",
+                operation,
+                after.as_secs()
+            ),
+            MatrixOpError::Exhausted { operation, attempts, last_error } => write!(
+                f,
+                "Couldn't {} after {} tries, the messaging bridge seems to be having trouble. Please try again later. ({})",
+                operation, attempts, last_error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MatrixOpError {}
+
+/// Homeserver and network errors are worth another try, our own errors (not connected, no such room) aren't
+fn is_transient(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<matrix_sdk::Error>().is_some()
+        || error.downcast_ref::<matrix_sdk::HttpError>().is_some()
+        || error.downcast_ref::<reqwest::Error>().is_some()
+}
+
+/// Runs a read, giving each attempt `policy.timeout` and retrying timeouts and transient errors
+/// with exponential backoff. Permanent errors come back right away.
+pub async fn with_retry<T, F, Fut>(policy: &MatrixPolicy, operation: &str, mut attempt_op: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut delay = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        let last_error = match tokio::time::timeout(policy.timeout, attempt_op()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) if !is_transient(&e) => return Err(e),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) if attempt >= policy.attempts => {
+                return Err(MatrixOpError::TimedOut { operation: operation.to_string(), after: policy.timeout }.into());
+            }
+            Err(_) => format!("timed out after {} seconds", policy.timeout.as_secs()),
+        };
+        if attempt >= policy.attempts {
+            return Err(MatrixOpError::Exhausted { operation: operation.to_string(), attempts: attempt, last_error }.into());
+        }
+        tracing::warn!("Failed to {} (attempt {}/{}): {}", operation, attempt, policy.attempts, last_error);
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Runs an operation that mustn't be repeated, like a send, with a timeout but no retries.
+/// A send that timed out may still have gone through, a duplicate message would be worse than the error.
+pub async fn with_timeout<T, Fut>(limit: Duration, operation: &str, op: Fut) -> anyhow::Result<T>
+where
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    match tokio::time::timeout(limit, op).await {
+        Ok(result) => result,
+        Err(_) => Err(MatrixOpError::TimedOut { operation: operation.to_string(), after: limit }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const POLICY: MatrixPolicy = MatrixPolicy {
+        timeout: Duration::from_secs(20),
+        send_timeout: Duration::from_secs(60),
+        attempts: 3,
+        backoff: Duration::from_millis(500),
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn hanging_read_times_out_after_every_attempt() {
+        let attempts = AtomicU32::new(0);
+        let started = tokio::time::Instant::now();
+        let result: anyhow::Result<()> = with_retry(&POLICY, "list your WhatsApp chats", || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            std::future::pending()
+        }).await;
+
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<MatrixOpError>(),
+            Some(MatrixOpError::TimedOut { after, .. }) if *after == POLICY.timeout
+        ));
+        assert!(error.to_string().starts_with("The messaging bridge didn't respond in time while trying to list your WhatsApp chats"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        // Three 20 second attempts with 0.5 and 1 second of backoff between them
+        assert_eq!(started.elapsed(), Duration::from_millis(61_500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn read_that_recovers_is_retried_and_permanent_errors_are_not() {
+        let attempts = AtomicU32::new(0);
+        let rooms = with_retry(&POLICY, "list rooms", || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt == 0 {
+                    std::future::pending::<()>().await;
+                }
+                Ok(vec!["!family:example.com"])
+            }
+        }).await.unwrap();
+        assert_eq!((rooms, attempts.load(Ordering::SeqCst)), (vec!["!family:example.com"], 2));
+
+        let attempts = AtomicU32::new(0);
+        let result: anyhow::Result<()> = with_retry(&POLICY, "find the chat", || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(anyhow::anyhow!("WhatsApp is not connected")) }
+        }).await;
+        assert_eq!(result.unwrap_err().to_string(), "WhatsApp is not connected");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hanging_send_is_not_retried() {
+        let result: anyhow::Result<()> = with_timeout(POLICY.send_timeout, "send your message", std::future::pending()).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<MatrixOpError>(),
+            Some(MatrixOpError::TimedOut { after, .. }) if *after == Duration::from_secs(60)
+        ));
+    }
+}
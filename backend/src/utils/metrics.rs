@@ -0,0 +1,243 @@
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Upper bounds (seconds) of the latency histogram buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// (name, type, help) of every exported metric
+const METRIC_DESCRIPTIONS: [(&str, &str, &str); 7] = [
+    ("lightfriend_http_requests_total", "counter", "HTTP requests by method, route and status"),
+    ("lightfriend_tool_call_duration_seconds", "histogram", "Tool call latency by channel and tool"),
+    ("lightfriend_twilio_messages_total", "counter", "Outbound Twilio messages by result"),
+    ("lightfriend_elevenlabs_calls_total", "counter", "Outbound ElevenLabs calls by result"),
+    ("lightfriend_credits_spent_total", "counter", "Credit balance deducted from users by event type, in the billing currency"),
+    ("lightfriend_monthly_quota_spent_total", "counter", "Monthly quota (credits_left) used by event type, in quota units"),
+    ("lightfriend_inbound_sms_throttled_total", "counter", "Inbound SMS dropped by the per-user rate limit"),
+];
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// In-process metric store, rendered in the Prometheus text format on /metrics.
+/// Series are keyed by (metric name, rendered label set).
+#[derive(Default)]
+struct Metrics {
+    counters: DashMap<(&'static str, String), f64>,
+    histograms: DashMap<(&'static str, String), Histogram>,
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", key, escaped)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn inc_counter(name: &'static str, labels: &[(&str, &str)]) {
+    add_counter(name, labels, 1.0);
+}
+
+pub fn add_counter(name: &'static str, labels: &[(&str, &str)], value: f64) {
+    if !value.is_finite() || value < 0.0 {
+        return;
+    }
+    *metrics().counters.entry((name, render_labels(labels))).or_insert(0.0) += value;
+}
+
+pub fn observe(name: &'static str, labels: &[(&str, &str)], seconds: f64) {
+    let mut histogram = metrics().histograms.entry((name, render_labels(labels))).or_default();
+    for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+        if seconds <= *bound {
+            histogram.buckets[i] += 1;
+        }
+    }
+    histogram.count += 1;
+    histogram.sum += seconds;
+}
+
+/// Records a tool call's duration when dropped, so early returns and `continue`s are counted too
+pub struct ToolTimer {
+    channel: &'static str,
+    tool: String,
+    started: Instant,
+}
+
+impl ToolTimer {
+    pub fn start(channel: &'static str, tool: &str) -> Self {
+        Self { channel, tool: tool.to_string(), started: Instant::now() }
+    }
+}
+
+impl Drop for ToolTimer {
+    fn drop(&mut self) {
+        observe(
+            "lightfriend_tool_call_duration_seconds",
+            &[("channel", self.channel), ("tool", &self.tool)],
+            self.started.elapsed().as_secs_f64(),
+        );
+    }
+}
+
+pub fn record_twilio_message(success: bool) {
+    inc_counter("lightfriend_twilio_messages_total", &[("result", if success { "success" } else { "failure" })]);
+}
+
+pub fn record_elevenlabs_call(success: bool) {
+    inc_counter("lightfriend_elevenlabs_calls_total", &[("result", if success { "success" } else { "failure" })]);
+}
+
+pub fn record_credit_spend(event_type: &str, amount: f32) {
+    add_counter("lightfriend_credits_spent_total", &[("event_type", event_type)], amount as f64);
+}
+
+pub fn record_quota_spend(event_type: &str, amount: f32) {
+    add_counter("lightfriend_monthly_quota_spent_total", &[("event_type", event_type)], amount as f64);
+}
+
+pub fn record_inbound_sms_throttled() {
+    inc_counter("lightfriend_inbound_sms_throttled_total", &[("route", "user_twilio")]);
+}
+
+/// Everything collected so far in the Prometheus text exposition format
+pub fn render() -> String {
+    let metrics = metrics();
+    let mut out = String::new();
+    for (name, kind, help) in METRIC_DESCRIPTIONS {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        if kind == "histogram" {
+            let mut series: Vec<_> = metrics.histograms.iter()
+                .filter(|entry| entry.key().0 == name)
+                .map(|entry| (entry.key().1.clone(), entry.buckets, entry.count, entry.sum))
+                .collect();
+            series.sort_by(|a, b| a.0.cmp(&b.0));
+            for (labels, buckets, count, sum) in series {
+                let prefix = if labels.is_empty() { String::new() } else { format!("{},", labels) };
+                for (bound, bucket_count) in LATENCY_BUCKETS.iter().zip(buckets.iter()) {
+                    let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, prefix, bound, bucket_count);
+                }
+                let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, prefix, count);
+                let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, sum);
+                let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, count);
+            }
+        } else {
+            let mut series: Vec<_> = metrics.counters.iter()
+                .filter(|entry| entry.key().0 == name)
+                .map(|entry| (entry.key().1.clone(), *entry.value()))
+                .collect();
+            series.sort_by(|a, b| a.0.cmp(&b.0));
+            for (labels, value) in series {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+            }
+        }
+    }
+    out
+}
+
+/// Counts every request by its route template (not the raw path, to keep label cardinality bounded)
+pub async fn track_requests(request: Request<Body>, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    // Voice agent tools are plain HTTP routes, so their latency is measured here
+    if let Some(tool) = route.strip_prefix("/api/call/") {
+        observe(
+            "lightfriend_tool_call_duration_seconds",
+            &[("channel", "voice"), ("tool", tool)],
+            started.elapsed().as_secs_f64(),
+        );
+    }
+    inc_counter(
+        "lightfriend_http_requests_total",
+        &[("method", &method), ("route", &route), ("status", response.status().as_str())],
+    );
+    response
+}
+
+/// /metrics is only served when METRICS_TOKEN is set, and only to scrapers presenting it as a bearer token
+pub async fn require_metrics_token(request: Request<Body>, next: Next) -> Response {
+    let Ok(expected) = std::env::var("METRICS_TOKEN") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(token) if !expected.is_empty() && crate::utils::encryption::secrets_match(token, &expected) => next.run(request).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+pub async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    /// The value of one series in the exposition, None when it hasn't been recorded
+    fn series_value(line_prefix: &str) -> Option<f64> {
+        render()
+            .lines()
+            .find_map(|line| line.strip_prefix(line_prefix)?.trim().parse().ok())
+    }
+
+    #[tokio::test]
+    async fn requests_are_counted_by_route_template() {
+        let app: Router = Router::new()
+            .route("/api/metrics-test/{id}", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(track_requests));
+        let series = r#"lightfriend_http_requests_total{method="GET",route="/api/metrics-test/{id}",status="200"}"#;
+        assert_eq!(series_value(series), None);
+
+        for id in ["1", "2"] {
+            let request = Request::builder().uri(format!("/api/metrics-test/{}", id)).body(Body::empty()).unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        }
+        assert_eq!(series_value(series), Some(2.0));
+    }
+
+    #[test]
+    fn tool_timer_records_one_observation_per_call() {
+        drop(ToolTimer::start("sms", "metrics_test_tool"));
+        let series = r#"lightfriend_tool_call_duration_seconds_count{channel="sms",tool="metrics_test_tool"}"#;
+        assert_eq!(series_value(series), Some(1.0));
+        let bucket = r#"lightfriend_tool_call_duration_seconds_bucket{channel="sms",tool="metrics_test_tool",le="+Inf"}"#;
+        assert_eq!(series_value(bucket), Some(1.0));
+    }
+}
@@ -66,6 +66,7 @@ pub async fn send_tinfoil_renewal_notification(
         to: "rasmus@ahtava.com".to_string(),
         subject: format!("Tinfoil Key Renewal - User {}", user_id),
         body: body.replace("\n", "\r\n"),  // CRLF for email
+        account_id: None,
     };
 
     // Create a fake auth user for sending (admin context)
@@ -159,7 +160,7 @@ pub async fn send_admin_alert(
     // Search for emails from admin containing the subject line
     if let Ok(Some(_)) = state.user_repository.get_imap_credentials(1) {
         // Admin (user_id 1) has IMAP configured, check for replies
-        match crate::handlers::imap_handlers::fetch_emails_imap(state, 1, false, Some(10), false, true).await {
+        match crate::handlers::imap_handlers::fetch_emails_imap(state, 1, false, Some(10), false, true, None).await {
             Ok(emails) => {
                 // Check if any email from admin's sent folder or replies contains the subject
                 // and has content indicating they want to disable alerts
@@ -201,6 +202,7 @@ pub async fn send_admin_alert(
         to: admin_email.clone(),
         subject: subject.to_string(),
         body: enhanced_message.replace("\n", "\r\n"),
+        account_id: None,
     };
 
     // Create admin auth context
@@ -0,0 +1,108 @@
+use axum::{
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    Json,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+/// Email subject lines
+pub const MAX_SUBJECT_CHARS: usize = 300;
+/// Email bodies and replies
+pub const MAX_EMAIL_BODY_CHARS: usize = 20_000;
+/// SMS and chat messages, ten SMS segments
+pub const MAX_MESSAGE_CHARS: usize = 1_600;
+/// Notes, waiting checks and similar free text that ends up in the database
+pub const MAX_CONTENT_CHARS: usize = 2_000;
+/// Titles, names, search terms and queries
+pub const MAX_SHORT_TEXT_CHARS: usize = 500;
+/// Email addresses, URLs and ids
+pub const MAX_ADDRESS_CHARS: usize = 2_048;
+
+/// Checks (field name, value, limit) triples, the error names the first field that's too long
+pub fn check_lengths(fields: &[(&str, &str, usize)]) -> Result<(), String> {
+    for (field, value, max) in fields {
+        let length = value.chars().count();
+        if length > *max {
+            return Err(format!(
+                "{} is {} characters long, the limit is {}. Please shorten it.",
+                field, length, max
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Payloads that have size limits on their fields
+pub trait Validate {
+    fn validate(&self) -> Result<(), String>;
+}
+
+/// `Json` that also runs `Validate`, so handlers only ever see payloads within the limits
+pub struct ValidatedJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(payload) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| (rejection.status(), Json(json!({"error": rejection.body_text()}))))?;
+        payload
+            .validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({"error": e}))))?;
+        Ok(ValidatedJson(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[derive(serde::Deserialize)]
+    struct Email {
+        subject: String,
+        body: String,
+    }
+
+    impl Validate for Email {
+        fn validate(&self) -> Result<(), String> {
+            check_lengths(&[
+                ("subject", &self.subject, MAX_SUBJECT_CHARS),
+                ("body", &self.body, MAX_EMAIL_BODY_CHARS),
+            ])
+        }
+    }
+
+    fn request(body: &str) -> Request {
+        Request::builder()
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({"subject": "Hi", "body": body}).to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn over_limit_body_is_rejected_with_400() {
+        let body = "a".repeat(MAX_EMAIL_BODY_CHARS + 1);
+        let Err((status, Json(error))) = ValidatedJson::<Email>::from_request(request(&body), &()).await else {
+            panic!("an over-limit body must be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error["error"],
+            format!("body is {} characters long, the limit is {}. Please shorten it.", MAX_EMAIL_BODY_CHARS + 1, MAX_EMAIL_BODY_CHARS)
+        );
+    }
+
+    #[tokio::test]
+    async fn body_at_the_limit_passes() {
+        let body = "a".repeat(MAX_EMAIL_BODY_CHARS);
+        assert!(ValidatedJson::<Email>::from_request(request(&body), &()).await.is_ok());
+    }
+}
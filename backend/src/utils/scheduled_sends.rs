@@ -0,0 +1,274 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+/// How far ahead send_at has to be, anything sooner goes through the normal cancel window
+pub const MIN_LEAD_SECS: i64 = 60;
+
+/// What a scheduled send does when its time comes, stored encrypted as JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduledPayload {
+    Email {
+        to: String,
+        subject: String,
+        body: String,
+    },
+    ChatMessage {
+        platform: String,
+        chat_name: String, // the resolved room name, not what the user typed
+        /// The resolved room, so a renamed or newly similar chat can't redirect the send.
+        /// Missing on sends stored before it was added, those resolve chat_name again.
+        #[serde(default)]
+        room_id: Option<String>,
+        message: String,
+        image_url: Option<String>,
+    },
+}
+
+impl ScheduledPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScheduledPayload::Email { .. } => "email",
+            ScheduledPayload::ChatMessage { .. } => "chat_message",
+        }
+    }
+}
+
+pub fn user_timezone(state: &AppState, user_id: i32) -> Tz {
+    state.user_core.get_user_info(user_id).ok()
+        .and_then(|info| info.timezone)
+        .and_then(|tz| tz.parse::<Tz>().ok())
+        .unwrap_or(chrono_tz::UTC)
+}
+
+/// Reads send_at from a tool call. RFC3339 with an offset is taken as is, a date and time without
+/// one ("2026-10-17T09:00:00") is in the user's timezone. Errors are worded to be read back.
+pub fn parse_send_at(value: &str, timezone: Tz, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    let send_at = match DateTime::parse_from_rfc3339(value) {
+        Ok(at) => at.with_timezone(&Utc),
+        Err(_) => {
+            let naive = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .ok_or_else(|| format!("I couldn't read the send time '{}'. Please give a date and time.", value))?;
+            timezone
+                .from_local_datetime(&naive)
+                .earliest()
+                .ok_or_else(|| format!("{} doesn't exist in your timezone because of a clock change. Please pick another time.", naive.format("%H:%M on %B %-d")))?
+                .with_timezone(&Utc)
+        }
+    };
+    if send_at <= now {
+        return Err(format!(
+            "The send time {} has already passed. Please pick a time in the future.",
+            describe_send_time(send_at, timezone)
+        ));
+    }
+    if send_at < now + chrono::Duration::seconds(MIN_LEAD_SECS) {
+        return Err(format!(
+            "The send time {} is less than a minute away. Please pick a later time, or send it without a time.",
+            describe_send_time(send_at, timezone)
+        ));
+    }
+    Ok(send_at)
+}
+
+/// e.g. "Friday, October 17 at 09:00" in the user's timezone
+pub fn describe_send_time(send_at: DateTime<Utc>, timezone: Tz) -> String {
+    send_at.with_timezone(&timezone).format("%A, %B %-d at %H:%M").to_string()
+}
+
+/// Stores the send in the scheduled_sends table, which survives restarts unlike the job queue.
+/// `target` is who it goes to, shown when the user lists their scheduled sends.
+pub fn schedule_send(
+    state: &AppState,
+    user_id: i32,
+    payload: &ScheduledPayload,
+    target: &str,
+    send_at: DateTime<Utc>,
+) -> Result<i32, String> {
+    let json = serde_json::to_string(payload).map_err(|e| format!("Failed to encode scheduled send: {}", e))?;
+    state.user_repository
+        .create_scheduled_send(user_id, payload.kind(), target, &json, send_at.timestamp() as i32)
+        .map_err(|e| format!("Failed to store scheduled send: {}", e))
+}
+
+/// Called once on startup, before the first pass. Sends the last run was in the middle of are
+/// marked failed and their users told, they can check and send again.
+pub async fn recover_interrupted_sends(state: &Arc<AppState>) {
+    let stuck = match state.user_repository.fail_interrupted_scheduled_sends("interrupted by a restart") {
+        Ok(stuck) => stuck,
+        Err(e) => {
+            tracing::error!("Failed to recover interrupted scheduled sends: {}", e);
+            return;
+        }
+    };
+    for send in stuck {
+        tracing::warn!("Scheduled send {:?} for user {} was interrupted mid-send", send.id, send.user_id);
+        if let Ok(Some(user)) = state.user_core.find_by_id(send.user_id) {
+            let what = if send.kind == "email" { "email" } else { "message" };
+            let message = format!("Your scheduled {} to {} was interrupted while sending and may not have gone out. Please check and send it again if needed.", what, send.target);
+            if let Err(e) = crate::api::twilio_utils::send_conversation_message(state, &message, None, &user).await {
+                tracing::error!("Failed to tell user {} about the interrupted scheduled send: {}", send.user_id, e);
+            }
+        }
+    }
+}
+
+/// Sends everything that's due. The user hears about failures, successful sends stay quiet
+/// like the cancel-window sends do.
+pub async fn run_due_sends(state: &Arc<AppState>) {
+    let now = Utc::now().timestamp() as i32;
+    let due = match state.user_repository.claim_due_scheduled_sends(now) {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("Failed to load due scheduled sends: {}", e);
+            return;
+        }
+    };
+    for send in due {
+        let Some(id) = send.id else { continue };
+        let result = match crate::utils::encryption::decrypt(&send.encrypted_payload)
+            .map_err(|e| format!("couldn't read the stored message ({})", e))
+            .and_then(|json| serde_json::from_str::<ScheduledPayload>(&json).map_err(|e| format!("couldn't read the stored message ({})", e)))
+        {
+            Ok(payload) => deliver(state, send.user_id, payload).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                tracing::info!("Sent scheduled {} {} for user {}", send.kind, id, send.user_id);
+                if let Err(e) = state.user_repository.finish_scheduled_send(id, "sent", None) {
+                    tracing::error!("Failed to mark scheduled send {} sent: {}", id, e);
+                }
+            }
+            Err(error) => {
+                tracing::error!("Scheduled send {} for user {} failed: {}", id, send.user_id, error);
+                if let Err(e) = state.user_repository.finish_scheduled_send(id, "failed", Some(&error)) {
+                    tracing::error!("Failed to mark scheduled send {} failed: {}", id, e);
+                }
+                if let Ok(Some(user)) = state.user_core.find_by_id(send.user_id) {
+                    let what = if send.kind == "email" { "email" } else { "message" };
+                    let message = format!("Your scheduled {} to {} couldn't be sent: {}", what, send.target, error);
+                    if let Err(e) = crate::api::twilio_utils::send_conversation_message(state, &message, None, &user).await {
+                        tracing::error!("Failed to tell user {} about the failed scheduled send: {}", send.user_id, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn deliver(state: &Arc<AppState>, user_id: i32, payload: ScheduledPayload) -> Result<(), String> {
+    match payload {
+        ScheduledPayload::Email { to, subject, body } => {
+            let request = crate::handlers::imap_handlers::SendEmailRequest { to, subject, body, account_id: None };
+            crate::handlers::imap_handlers::send_email(
+                axum::extract::State(state.clone()),
+                crate::handlers::auth_middleware::AuthUser { user_id, is_admin: false },
+                axum::Json(request),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|(_, error_json)| error_json.0.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error").to_string())
+        }
+        ScheduledPayload::ChatMessage { platform, chat_name, room_id, message, image_url } => {
+            let sent = match room_id {
+                Some(room_id) => crate::utils::bridge::send_bridge_message_to_room(&platform, state, user_id, &room_id, &chat_name, &message, image_url).await,
+                None => crate::utils::bridge::send_bridge_message(&platform, state, user_id, &chat_name, &message, image_url).await,
+            };
+            sent.map(|_| ()).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Validates send_at from a tool call and stores the send. Returns the id, the time and a
+/// confirmation, or the reason it was refused. Both texts are meant for the user.
+pub fn schedule_from_tool(
+    state: &AppState,
+    user_id: i32,
+    send_at: &str,
+    payload: &ScheduledPayload,
+    target: &str,
+) -> Result<(i32, DateTime<Utc>, String), String> {
+    let timezone = user_timezone(state, user_id);
+    let send_at = parse_send_at(send_at, timezone, Utc::now())?;
+    let id = schedule_send(state, user_id, payload, target, send_at).map_err(|e| {
+        tracing::error!("Failed to schedule {} for user {}: {}", payload.kind(), user_id, e);
+        "I couldn't schedule that, please try again.".to_string()
+    })?;
+    let what = match payload {
+        ScheduledPayload::Email { .. } => "email",
+        ScheduledPayload::ChatMessage { .. } => "message",
+    };
+    let confirmation = format!("Scheduled {} to {} for {}.", what, target, describe_send_time(send_at, timezone));
+    Ok((id, send_at, confirmation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, set_test_encryption_key, test_state};
+
+    fn email() -> ScheduledPayload {
+        ScheduledPayload::Email { to: "a@example.com".to_string(), subject: "Hi".to_string(), body: "Hello".to_string() }
+    }
+
+    #[test]
+    fn too_soon_and_past_times_are_told_apart() {
+        let now = Utc::now();
+        let soon = (now + chrono::Duration::seconds(30)).to_rfc3339();
+        assert!(parse_send_at(&soon, chrono_tz::UTC, now).unwrap_err().contains("less than a minute away"));
+        let past = (now - chrono::Duration::minutes(5)).to_rfc3339();
+        assert!(parse_send_at(&past, chrono_tz::UTC, now).unwrap_err().contains("already passed"));
+        let later = now + chrono::Duration::hours(1);
+        assert_eq!(parse_send_at(&later.to_rfc3339(), chrono_tz::UTC, now).unwrap().timestamp(), later.timestamp());
+    }
+
+    #[test]
+    fn chat_sends_stored_before_room_ids_still_load() {
+        let stored = r#"{"kind":"chat_message","platform":"whatsapp","chat_name":"Mom","message":"hi","image_url":null}"#;
+        match serde_json::from_str::<ScheduledPayload>(stored).unwrap() {
+            ScheduledPayload::ChatMessage { room_id, chat_name, .. } => {
+                assert_eq!(chat_name, "Mom");
+                assert!(room_id.is_none());
+            }
+            other => panic!("unexpected payload {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn each_send_gets_its_own_id() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "sends@example.com");
+        let send_at = Utc::now() + chrono::Duration::hours(1);
+        let first = schedule_send(&state, user_id, &email(), "a@example.com", send_at).unwrap();
+        let second = schedule_send(&state, user_id, &email(), "a@example.com", send_at).unwrap();
+        assert_ne!(first, second);
+        let upcoming: Vec<Option<i32>> = state.user_repository.get_upcoming_scheduled_sends(user_id).unwrap().iter().map(|send| send.id).collect();
+        assert_eq!(upcoming, vec![Some(first), Some(second)]);
+    }
+
+    #[tokio::test]
+    async fn sends_stuck_in_sending_are_failed_on_startup() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = create_test_user(&state, "stuck@example.com");
+        let id = schedule_send(&state, user_id, &email(), "a@example.com", Utc::now() + chrono::Duration::hours(1)).unwrap();
+        // Claimed by a pass that never finished
+        let claimed = state.user_repository.claim_due_scheduled_sends(i32::MAX).unwrap();
+        assert_eq!(claimed.len(), 1);
+
+        let stuck = state.user_repository.fail_interrupted_scheduled_sends("interrupted by a restart").unwrap();
+        assert_eq!(stuck.iter().map(|send| send.id).collect::<Vec<_>>(), vec![Some(id)]);
+        assert!(state.user_repository.fail_interrupted_scheduled_sends("interrupted by a restart").unwrap().is_empty());
+        assert!(state.user_repository.claim_due_scheduled_sends(i32::MAX).unwrap().is_empty());
+    }
+}
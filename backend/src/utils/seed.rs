@@ -0,0 +1,152 @@
+use std::error::Error;
+
+use crate::handlers::auth_dtos::NewUser;
+use crate::models::user_models::{NewBridge, NewKeyword, NewMessageHistory, NewPrioritySender, NewWaitingCheck};
+use crate::repositories::user_core::UserCore;
+use crate::repositories::user_repository::UserRepository;
+
+pub const SEED_USER_EMAIL: &str = "seed@lightfriend.test";
+const SEED_USER_PHONE: &str = "+15555550100";
+const SEED_USER_PASSWORD: &str = "seed-password";
+const SEED_CONVERSATION_ID: &str = "seed-conversation";
+
+/// Seeding writes fake users and connections, so only development and staging databases are allowed.
+/// An unset ENVIRONMENT is treated as production.
+fn ensure_seeding_allowed(environment: Option<&str>) -> Result<(), String> {
+    match environment {
+        Some(env) if env == "development" || env == "staging" => Ok(()),
+        Some(env) => Err(format!("Refusing to seed data with ENVIRONMENT={}", env)),
+        None => Err("ENVIRONMENT not set, refusing to seed data".to_string()),
+    }
+}
+
+/// Creates a verified test user with mock bridge, email and calendar connections, sample filters and
+/// a short conversation history. Returns the user id. Run with `backend seed`.
+pub fn seed_test_data(user_core: &UserCore, user_repository: &UserRepository) -> Result<i32, Box<dyn Error>> {
+    seed_test_data_in(std::env::var("ENVIRONMENT").ok().as_deref(), user_core, user_repository)
+}
+
+fn seed_test_data_in(environment: Option<&str>, user_core: &UserCore, user_repository: &UserRepository) -> Result<i32, Box<dyn Error>> {
+    ensure_seeding_allowed(environment)?;
+
+    if let Some(existing) = user_core.find_by_email(SEED_USER_EMAIL)? {
+        return Err(format!("Seed user already exists with id {}, delete it to reseed", existing.id).into());
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i32;
+
+    user_core.create_user(NewUser {
+        email: SEED_USER_EMAIL.to_string(),
+        password_hash: bcrypt::hash(SEED_USER_PASSWORD, bcrypt::DEFAULT_COST)?,
+        phone_number: SEED_USER_PHONE.to_string(),
+        time_to_live: 0,
+        verified: true,
+        credits: 20.00,
+        credits_left: 20.00,
+        charge_when_under: false,
+        waiting_checks_count: 0,
+        discount: false,
+        sub_tier: Some("tier 2".to_string()),
+    })?;
+    let user_id = user_core
+        .find_by_email(SEED_USER_EMAIL)?
+        .ok_or("Seed user missing right after insert")?
+        .id;
+
+    user_core.ensure_user_settings_exist(user_id)?;
+    user_core.update_timezone(user_id, "Europe/Helsinki")?;
+    user_core.update_digests(user_id, Some("08:00"), Some("12:00"), Some("18:00"))?;
+    user_core.update_proactive_agent_on(user_id, true)?;
+
+    // Mock integrations: the rows look connected, but point at nothing real
+    for service in ["whatsapp", "telegram", "signal"] {
+        user_repository.create_bridge(NewBridge {
+            user_id,
+            bridge_type: service.to_string(),
+            status: "connected".to_string(),
+            room_id: Some(format!("!seed-{}:localhost", service)),
+            data: None,
+            created_at: Some(now),
+        })?;
+    }
+    user_repository.set_imap_credentials(user_id, SEED_USER_EMAIL, "seed-imap-password", Some("imap.invalid"), Some(993), Some("personal"))?;
+    user_repository.create_google_calendar_connection(user_id, "seed-access-token", Some("seed-refresh-token"), 0)?;
+
+    // Filters
+    for (keyword, service_type, action) in [("invoice", "imap", "notify_call"), ("newsletter", "imap", "ignore"), ("urgent", "whatsapp", "notify_sms")] {
+        user_repository.create_keyword(&NewKeyword {
+            user_id,
+            keyword: keyword.to_string(),
+            service_type: service_type.to_string(),
+            action: action.to_string(),
+        })?;
+    }
+    for (sender, service_type) in [("boss@example.com", "imap"), ("Mom", "whatsapp")] {
+        user_repository.create_priority_sender(&NewPrioritySender {
+            user_id,
+            sender: sender.to_string(),
+            service_type: service_type.to_string(),
+            noti_type: Some("sms".to_string()),
+            noti_mode: "all".to_string(),
+        })?;
+    }
+    user_repository.create_waiting_check(&NewWaitingCheck {
+        user_id,
+        content: "Package delivery confirmation".to_string(),
+        service_type: "imap".to_string(),
+        noti_type: Some("sms".to_string()),
+    })?;
+
+    // Conversation history, oldest first
+    let history = [
+        ("user", "What's on my calendar tomorrow?"),
+        ("assistant", "You have a dentist appointment at 10:00 and lunch with Anna at 12:30."),
+        ("user", "Remind me to buy milk"),
+        ("assistant", "Okay, I'll remind you to buy milk."),
+    ];
+    for (i, (role, content)) in history.iter().enumerate() {
+        user_repository.create_message_history(&NewMessageHistory {
+            user_id,
+            role: role.to_string(),
+            encrypted_content: content.to_string(), // encrypted by create_message_history
+            tool_name: None,
+            tool_call_id: None,
+            created_at: now - (history.len() - i) as i32 * 60,
+            conversation_id: SEED_CONVERSATION_ID.to_string(),
+            tool_calls_json: None,
+        })?;
+    }
+
+    Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{set_test_encryption_key, test_state};
+
+    #[tokio::test]
+    async fn production_is_never_seeded() {
+        let state = test_state();
+        for environment in [Some("production"), Some("self_hosted"), None] {
+            assert!(seed_test_data_in(environment, &state.user_core, &state.user_repository).is_err());
+        }
+        assert!(state.user_core.find_by_email(SEED_USER_EMAIL).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn seeding_twice_keeps_the_first_user() {
+        set_test_encryption_key();
+        let state = test_state();
+        let user_id = seed_test_data_in(Some("development"), &state.user_core, &state.user_repository).unwrap();
+        let history_len = state.user_repository.get_conversation_history(user_id, 100, false).unwrap().len();
+
+        assert!(seed_test_data_in(Some("staging"), &state.user_core, &state.user_repository).is_err());
+        assert_eq!(state.user_core.find_by_email(SEED_USER_EMAIL).unwrap().unwrap().id, user_id);
+        assert_eq!(state.user_repository.get_keywords(user_id, "imap").unwrap().len(), 2);
+        assert_eq!(state.user_repository.get_conversation_history(user_id, 100, false).unwrap().len(), history_len);
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::AppState;
+
+/// Each check gets this long, a hanging provider shouldn't hold up startup
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of one check, `detail` says what was verified or what went wrong
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Runs in development unless STARTUP_SELF_TEST=false, elsewhere only with STARTUP_SELF_TEST=true
+pub fn should_run() -> bool {
+    match std::env::var("STARTUP_SELF_TEST").map(|v| v.trim().to_lowercase()).as_deref() {
+        Ok("true") | Ok("1") => true,
+        Ok("false") | Ok("0") => false,
+        _ => std::env::var("ENVIRONMENT").as_deref() == Ok("development"),
+    }
+}
+
+fn env(key: &str) -> Result<String, String> {
+    std::env::var(key)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| format!("{} is not set", key))
+}
+
+/// A rejected key is told apart from a provider that's just having trouble
+pub fn check_status(status: StatusCode) -> Result<(), String> {
+    match status {
+        s if s.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(format!("credentials rejected ({})", status)),
+        _ => Err(format!("unexpected response ({})", status)),
+    }
+}
+
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+    request.timeout(CHECK_TIMEOUT).send().await.map_err(|e| format!("unreachable: {}", e))
+}
+
+/// Fetches the account, which needs a valid SID and auth token but sends nothing
+pub async fn check_twilio(client: &reqwest::Client, base_url: &str, account_sid: &str, auth_token: &str) -> Result<String, String> {
+    let url = format!("{}/2010-04-01/Accounts/{}.json", base_url, account_sid);
+    let response = send(client.get(&url).basic_auth(account_sid, Some(auth_token))).await?;
+    check_status(response.status())?;
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    match body["status"].as_str() {
+        Some("active") | None => Ok("account reachable".to_string()),
+        Some(status) => Err(format!("account is {}", status)),
+    }
+}
+
+/// Reads the key's own user, which works with any valid key
+pub async fn check_elevenlabs(client: &reqwest::Client, base_url: &str, api_key: &str) -> Result<String, String> {
+    let response = send(client.get(format!("{}/v1/user", base_url)).header("xi-api-key", api_key)).await?;
+    check_status(response.status())?;
+    Ok("API key accepted".to_string())
+}
+
+/// Looks up the key itself, which costs nothing and fails for unknown keys
+pub async fn check_openrouter(client: &reqwest::Client, base_url: &str, api_key: &str) -> Result<String, String> {
+    let response = send(client.get(format!("{}/api/v1/auth/key", base_url)).bearer_auth(api_key)).await?;
+    check_status(response.status())?;
+    Ok("API key accepted".to_string())
+}
+
+/// Web search goes to Perplexity through OpenRouter, so this checks the model is still listed there
+pub async fn check_perplexity(client: &reqwest::Client, base_url: &str, model: &str) -> Result<String, String> {
+    let response = send(client.get(format!("{}/api/v1/models", base_url))).await?;
+    check_status(response.status())?;
+    let body: serde_json::Value = response.json().await.map_err(|e| format!("unreadable model list: {}", e))?;
+    let listed = body["data"]
+        .as_array()
+        .map_or(false, |models| models.iter().any(|m| m["id"].as_str() == Some(model)));
+    if listed {
+        Ok(format!("{} available", model))
+    } else {
+        Err(format!("{} is not offered by OpenRouter", model))
+    }
+}
+
+/// Runs every check at once against the real providers
+pub async fn run_self_test(state: &Arc<AppState>) -> Vec<CheckResult> {
+    let client = reqwest::Client::new();
+    let web_search_model = crate::tool_call_utils::utils::model_for(state, None, crate::tool_call_utils::utils::ModelPurpose::WebSearch);
+    let twilio = async {
+        let (sid, token) = (env("TWILIO_ACCOUNT_SID")?, env("TWILIO_AUTH_TOKEN")?);
+        check_twilio(&client, "https://api.twilio.com", &sid, &token).await
+    };
+    let elevenlabs = async { check_elevenlabs(&client, "https://api.elevenlabs.io", &env("ELEVENLABS_API_KEY")?).await };
+    let openrouter = async { check_openrouter(&client, "https://openrouter.ai", &env("OPENROUTER_API_KEY")?).await };
+    let perplexity = check_perplexity(&client, "https://openrouter.ai", &web_search_model);
+    let (twilio, elevenlabs, openrouter, perplexity) = futures::join!(twilio, elevenlabs, openrouter, perplexity);
+    [("twilio", twilio), ("elevenlabs", elevenlabs), ("openrouter", openrouter), ("perplexity", perplexity)]
+        .into_iter()
+        .map(|(name, result)| CheckResult {
+            name,
+            passed: result.is_ok(),
+            detail: result.unwrap_or_else(|e| e),
+        })
+        .collect()
+}
+
+/// One line per check, failures as errors so they stand out from the rest of the startup log
+pub fn log_summary(results: &[CheckResult]) {
+    let failed = results.iter().filter(|r| !r.passed).count();
+    for result in results {
+        if result.passed {
+            tracing::info!("Self-test {}: PASS ({})", result.name, result.detail);
+        } else {
+            tracing::error!("Self-test {}: FAIL ({})", result.name, result.detail);
+        }
+    }
+    if failed == 0 {
+        tracing::info!("Startup self-test passed, all {} outbound channels work", results.len());
+    } else {
+        tracing::error!(
+            "STARTUP SELF-TEST FAILED: {} of {} outbound channels are misconfigured or unreachable, see above",
+            failed, results.len()
+        );
+    }
+}
+
+/// Called before the server starts listening. Never fails startup, problems are only logged.
+pub async fn run_on_startup(state: &Arc<AppState>) {
+    if !should_run() {
+        return;
+    }
+    tracing::info!("Running startup self-test of outbound channels...");
+    let results = run_self_test(state).await;
+    log_summary(&results);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+    const GOOD_KEY: &str = "sk_valid";
+
+    /// Serves the Twilio and ElevenLabs endpoints the checks call, accepting only GOOD_KEY
+    async fn provider() -> String {
+        fn authorized(headers: &HeaderMap, header: &str, expected: &str) -> StatusCode {
+            if headers.get(header).and_then(|v| v.to_str().ok()) == Some(expected) {
+                StatusCode::OK
+            } else {
+                StatusCode::UNAUTHORIZED
+            }
+        }
+        // basic auth of AC123:sk_valid
+        let twilio_auth = format!("Basic {}", STANDARD.encode(format!("AC123:{}", GOOD_KEY)));
+        let app = axum::Router::new()
+            .route("/v1/user", axum::routing::get(|headers: HeaderMap| async move { authorized(&headers, "xi-api-key", GOOD_KEY) }))
+            .route("/2010-04-01/Accounts/{account}", axum::routing::get(move |headers: HeaderMap| {
+                let status = authorized(&headers, "authorization", &twilio_auth);
+                async move {
+                    match status {
+                        StatusCode::OK => (status, axum::Json(serde_json::json!({"status": "active"}))),
+                        _ => (status, axum::Json(serde_json::json!({"message": "Authenticate"}))),
+                    }
+                }
+            }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn invalid_credentials_are_reported_as_failures() {
+        let base_url = provider().await;
+        let client = reqwest::Client::new();
+
+        assert_eq!(check_elevenlabs(&client, &base_url, GOOD_KEY).await, Ok("API key accepted".to_string()));
+        assert_eq!(
+            check_elevenlabs(&client, &base_url, "sk_revoked").await,
+            Err("credentials rejected (401 Unauthorized)".to_string())
+        );
+        assert_eq!(check_twilio(&client, &base_url, "AC123", GOOD_KEY).await, Ok("account reachable".to_string()));
+        assert_eq!(
+            check_twilio(&client, &base_url, "AC123", "wrong-token").await,
+            Err("credentials rejected (401 Unauthorized)".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn provider_that_cannot_be_reached_fails_the_check() {
+        // Nothing listens on a port we just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let result = check_openrouter(&reqwest::Client::new(), &base_url, GOOD_KEY).await;
+        assert!(result.unwrap_err().starts_with("unreachable: "));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::models::user_models::ConnectedIntegrations;
+
+/// Longest help reply, two SMS segments
+pub const MAX_HELP_CHARS: usize = 300;
+
+/// Words that ask for the menu, in the languages the assistant speaks. Most are also ordinary
+/// one-word messages for the assistant, so they only count behind a `/` or `#` (e.g. "/ayuda").
+const HELP_KEYWORDS: [&str; 11] = [
+    "help", "menu", "apua", "valikko", "hilfe", "menü", "hjälp", "meny", "aide", "ayuda", "menú",
+];
+
+/// Bare messages that can only mean the menu
+const EXPLICIT_HELP: [&str; 2] = ["help", "?"];
+
+/// Help replies a user can get per hour, they're free so they're capped separately from credits
+pub const HELP_REPLIES_PER_HOUR: u32 = 3;
+
+/// SMS_HELP_KEYWORDS, a comma-separated list, replaces the built-in keywords
+fn help_keywords() -> Vec<String> {
+    match std::env::var("SMS_HELP_KEYWORDS") {
+        Ok(list) if !list.trim().is_empty() => list
+            .split(',')
+            .map(|keyword| keyword.trim().to_lowercase())
+            .filter(|keyword| !keyword.is_empty())
+            .collect(),
+        _ => HELP_KEYWORDS.iter().map(|keyword| keyword.to_string()).collect(),
+    }
+}
+
+/// Whether the whole SMS asks for the menu: "help" or "?" on their own, or a keyword behind
+/// `/` or `#` ("/menu", "#apua"). In any case and with trailing punctuation.
+pub fn is_help_command(body: &str) -> bool {
+    let body = body.trim().to_lowercase();
+    if body == "?" {
+        return true;
+    }
+    let command = body.trim_end_matches(['.', '!', '?']);
+    if EXPLICIT_HELP.contains(&command) {
+        return true;
+    }
+    match command.strip_prefix('/').or_else(|| command.strip_prefix('#')) {
+        Some(keyword) => help_keywords().iter().any(|known| known == keyword.trim()),
+        None => false,
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// What the user can ask for over SMS given what they've connected. Integrations need a full
+/// subscription (see `requires_subscription`), without one the menu points at upgrading instead.
+pub fn help_menu(integrations: &ConnectedIntegrations, has_full_access: bool) -> String {
+    let mut things = Vec::new();
+    if has_full_access {
+        if integrations.email {
+            things.push("read, send and reply to email".to_string());
+        }
+        if !integrations.bridges.is_empty() {
+            let platforms: Vec<String> = integrations.bridges.iter().map(|b| capitalize(b)).collect();
+            things.push(format!("read and send {} messages", platforms.join("/")));
+        }
+        if integrations.calendar {
+            things.push("check and add calendar events".to_string());
+        }
+        if integrations.tasks {
+            things.push("tasks".to_string());
+        }
+        if integrations.tesla {
+            things.push("control your Tesla".to_string());
+        }
+    }
+    things.push("search the web, weather and directions".to_string());
+
+    let mut menu = format!("Text me what you need, I can: {}.", things.join("; "));
+    if !has_full_access {
+        menu.push_str(" Upgrade to use email, chats and calendar.");
+    } else if !integrations.any() {
+        menu.push_str(" Connect email, chats or calendar on the website for more.");
+    }
+    menu.push_str(" Reply C to cancel a queued send.");
+    if menu.chars().count() > MAX_HELP_CHARS {
+        let cut: String = menu.chars().take(MAX_HELP_CHARS - 1).collect();
+        menu = format!("{}…", cut.trim_end());
+    }
+    menu
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_forms_open_the_menu() {
+        for body in ["help", "HELP!", " ? ", "/menu", "#apua", "/Ayuda."] {
+            assert!(is_help_command(body), "{body:?} should open the menu");
+        }
+    }
+
+    #[test]
+    fn bare_words_go_to_the_assistant() {
+        for body in ["menu", "meny", "aide", "ayuda", "apua", "help me find my keys"] {
+            assert!(!is_help_command(body), "{body:?} should reach the assistant");
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::api::twilio_sms::TwilioWebhookPayload;
+
+/// How long to wait for the next segment before handling what has arrived
+const SEGMENT_WINDOW: Duration = Duration::from_millis(2500);
+/// Upper bound on waiting for a message whose part markers say segments are still missing
+const MAX_ASSEMBLY_WAIT: Duration = Duration::from_secs(15);
+/// Characters per segment of a concatenated SMS, GSM-7 and UCS-2
+const GSM_SEGMENT_CHARS: usize = 153;
+const UCS2_SEGMENT_CHARS: usize = 67;
+
+/// A segment that isn't the last one is cut at most this many characters short of a full segment,
+/// carriers that split on word boundaries leave a little room
+const SEGMENT_SLACK: usize = 10;
+
+/// Part marker carriers put at the start of each segment, "(1/2)". Bare "1/2" is left alone,
+/// people write that themselves ("1/2 cup flour").
+static PART_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\((\d{1,2})/(\d{1,2})\)\s*").unwrap());
+
+struct Segment {
+    part: (u32, u32), // (index, total) from the carrier marker
+    arrival: u64,
+    text: String,
+}
+
+struct PendingMessage {
+    generation: u64,
+    started: Instant,
+    first: TwilioWebhookPayload,
+    segments: Vec<Segment>,
+}
+
+/// Reassembles inbound SMS that carriers delivered as separate segments, keyed by sender.
+pub struct SegmentBuffer {
+    pending: DashMap<String, PendingMessage>,
+    counter: AtomicU64,
+}
+
+/// Splits the carrier part marker off a body that is a segment of a longer message: marked "(i/n)",
+/// and every part but the last about a full segment long. None keeps the body intact.
+fn split_part_marker(body: &str) -> Option<((u32, u32), &str)> {
+    let captures = PART_MARKER.captures(body)?;
+    let index: u32 = captures[1].parse().ok()?;
+    let total: u32 = captures[2].parse().ok()?;
+    if index < 1 || total < 2 || index > total {
+        return None;
+    }
+    let (single, per_segment) = if body.is_ascii() { (160, GSM_SEGMENT_CHARS) } else { (70, UCS2_SEGMENT_CHARS) };
+    let chars = body.chars().count();
+    if index < total && (chars + SEGMENT_SLACK < per_segment || chars > single) {
+        return None;
+    }
+    Some(((index, total), &body[captures[0].len()..]))
+}
+
+/// How many segments an outbound SMS of this text is billed as. Non-ASCII text is counted as UCS-2,
+/// which overestimates a little for the few accented characters GSM-7 has.
+pub fn segment_count(text: &str) -> usize {
+    let (single, per_segment) = if text.is_ascii() { (160, GSM_SEGMENT_CHARS) } else { (70, UCS2_SEGMENT_CHARS) };
+    let chars = text.chars().count();
+    if chars <= single {
+        1
+    } else {
+        chars.div_ceil(per_segment)
+    }
+}
+
+/// Joins segments by their index. Carriers that mark parts split on word boundaries and drop the
+/// space, so one goes back in between unless a part already ends or starts with whitespace.
+fn assemble(segments: &mut [Segment]) -> String {
+    segments.sort_by_key(|segment| (segment.part.0, segment.arrival));
+    let mut body = String::new();
+    for segment in segments.iter() {
+        let joined = body.is_empty() || body.ends_with(char::is_whitespace) || segment.text.starts_with(char::is_whitespace);
+        if !joined {
+            body.push(' ');
+        }
+        body.push_str(&segment.text);
+    }
+    body
+}
+
+impl SegmentBuffer {
+    pub fn new() -> Self {
+        Self {
+            pending: DashMap::new(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the complete message once no more segments are coming, or None when this segment was
+    /// merged into a message another call will hand back. Messages that don't look split pass straight through.
+    pub async fn collect(&self, payload: TwilioWebhookPayload) -> Option<TwilioWebhookPayload> {
+        let has_media = payload.num_media.as_deref().map_or(false, |n| n != "0");
+        let (part, text) = match split_part_marker(&payload.body) {
+            Some((part, text)) if !has_media => (part, text.to_string()),
+            _ => return Some(payload),
+        };
+
+        let sender = payload.from.clone();
+        let generation = self.counter.fetch_add(1, Ordering::SeqCst) + 1;
+        let segment = Segment { part, arrival: generation, text };
+        {
+            let mut entry = self.pending.entry(sender.clone()).or_insert_with(|| PendingMessage {
+                generation,
+                started: Instant::now(),
+                first: payload.clone(),
+                segments: Vec::new(),
+            });
+            entry.generation = generation;
+            entry.segments.push(segment);
+        }
+
+        loop {
+            tokio::time::sleep(SEGMENT_WINDOW).await;
+
+            let ready = {
+                let Some(entry) = self.pending.get(&sender) else { return None };
+                if entry.generation != generation {
+                    // A later segment arrived, its call takes over
+                    return None;
+                }
+                let expected = entry.segments.iter().map(|segment| segment.part.1).max().unwrap_or(0);
+                let missing = (entry.segments.len() as u32) < expected;
+                !missing || entry.started.elapsed() >= MAX_ASSEMBLY_WAIT
+            };
+            if !ready {
+                continue;
+            }
+
+            let (_, mut message) = self.pending.remove(&sender)?;
+            let total = message.segments.len();
+            let expected = message.segments.iter().map(|segment| segment.part.1).max().unwrap_or(0);
+            if (total as u32) < expected {
+                tracing::warn!("Handling SMS from {} with {} of {} segments after waiting", sender, total, expected);
+            }
+            let mut assembled = message.first;
+            assembled.body = assemble(&mut message.segments);
+            if total > 1 {
+                tracing::debug!("Reassembled SMS from {} out of {} segments", sender, total);
+            }
+            return Some(assembled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(body: &str) -> TwilioWebhookPayload {
+        TwilioWebhookPayload {
+            from: "+15555550123".to_string(),
+            to: "+15555550100".to_string(),
+            body: body.to_string(),
+            num_media: Some("0".to_string()),
+            media_url0: None,
+            media_content_type0: None,
+            message_sid: "SM-test".to_string(),
+        }
+    }
+
+    /// A full first segment: marker plus words up to the GSM segment size
+    fn first_part() -> String {
+        let mut body = "(1/2) ".to_string();
+        while body.len() + 5 <= GSM_SEGMENT_CHARS {
+            body.push_str("word ");
+        }
+        body.trim_end().to_string()
+    }
+
+    #[test]
+    fn fractions_and_short_marked_messages_stay_intact() {
+        assert!(split_part_marker("1/2 cup flour").is_none());
+        assert!(split_part_marker("(1/2) cup flour").is_none());
+        assert!(split_part_marker(&"x".repeat(160)).is_none());
+    }
+
+    #[test]
+    fn carrier_segments_are_recognised() {
+        let first = first_part();
+        let (part, text) = split_part_marker(&first).unwrap();
+        assert_eq!(part, (1, 2));
+        assert!(text.starts_with("word"));
+        // The last part can be any length
+        assert_eq!(split_part_marker("(2/2) the end").unwrap(), ((2, 2), "the end"));
+    }
+
+    #[test]
+    fn segments_are_joined_in_order_with_a_space() {
+        let mut segments = vec![
+            Segment { part: (2, 2), arrival: 1, text: "world".to_string() },
+            Segment { part: (1, 2), arrival: 2, text: "hello".to_string() },
+        ];
+        assert_eq!(assemble(&mut segments), "hello world");
+        let mut spaced = vec![
+            Segment { part: (1, 2), arrival: 1, text: "hello ".to_string() },
+            Segment { part: (2, 2), arrival: 2, text: "world".to_string() },
+        ];
+        assert_eq!(assemble(&mut spaced), "hello world");
+    }
+
+    #[tokio::test]
+    async fn unmarked_message_passes_straight_through() {
+        let buffer = SegmentBuffer::new();
+        let message = buffer.collect(payload("1/2 cup flour")).await.unwrap();
+        assert_eq!(message.body, "1/2 cup flour");
+    }
+
+    #[tokio::test]
+    async fn marked_segments_are_merged() {
+        tokio::time::pause();
+        let buffer = std::sync::Arc::new(SegmentBuffer::new());
+        let first = first_part();
+        let expected = format!("{} the end", &first["(1/2) ".len()..]);
+        let waiting = {
+            let buffer = buffer.clone();
+            tokio::spawn(async move { buffer.collect(payload(&first)).await })
+        };
+        tokio::task::yield_now().await;
+        let merged = buffer.collect(payload("(2/2) the end")).await.unwrap();
+        assert_eq!(merged.body, expected);
+        assert!(waiting.await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How long pushed or polled state is served without asking the car again
+pub const TELEMETRY_FRESH_SECS: i64 = 5 * 60;
+
+/// Latest known state of a vehicle, temperatures in Celsius as Tesla reports them
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VehicleSnapshot {
+    pub battery_level: Option<f64>,
+    pub battery_range: Option<f64>,
+    pub charging_state: Option<String>,
+    pub inside_temp: Option<f64>,
+    pub outside_temp: Option<f64>,
+    pub is_climate_on: Option<bool>,
+    pub is_front_defroster_on: Option<bool>,
+    pub is_rear_defroster_on: Option<bool>,
+    pub locked: Option<bool>,
+    /// The car dropped its connection, the values are from before it went to sleep
+    pub asleep: bool,
+    /// When a value was last updated, going to sleep doesn't count
+    pub updated_at: i64,
+}
+
+impl VehicleSnapshot {
+    pub fn is_fresh(&self, now: i64) -> bool {
+        now - self.updated_at < TELEMETRY_FRESH_SECS
+    }
+}
+
+/// One record forwarded from the fleet telemetry server. Data records carry `data`,
+/// connectivity records carry `status` ("CONNECTED" or "DISCONNECTED").
+#[derive(Debug, Deserialize)]
+pub struct TelemetryRecord {
+    pub vin: String,
+    #[serde(default)]
+    pub data: Vec<TelemetryDatum>,
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelemetryDatum {
+    pub key: String,
+    /// e.g. {"doubleValue": 80.5}, {"stringValue": "Charging"}, {"booleanValue": true} or {"invalid": true}
+    pub value: Value,
+}
+
+fn number(value: &Value) -> Option<f64> {
+    ["doubleValue", "floatValue", "intValue", "longValue"]
+        .iter()
+        .find_map(|key| value.get(key))
+        .and_then(|v| v.as_f64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+        // Some fields only come as strings, e.g. {"stringValue": "72.5"}
+        .or_else(|| value.get("stringValue").and_then(Value::as_str).and_then(|s| s.parse().ok()))
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.get("stringValue")
+        .or_else(|| value.get("chargeStateValue"))
+        .or_else(|| value.get("detailedChargeStateValue"))
+        .and_then(Value::as_str)
+        // "DetailedChargeStateCharging" -> "Charging", the way the vehicle data endpoint words it
+        .map(|s| s.trim_start_matches("DetailedChargeState").trim_start_matches("ChargeState").to_string())
+}
+
+fn flag(value: &Value) -> Option<bool> {
+    value.get("booleanValue").and_then(Value::as_bool)
+        .or_else(|| {
+            let s = value.get("stringValue")
+                .or_else(|| value.get("hvacPowerValue"))
+                .or_else(|| value.get("defrostModeValue"))
+                .and_then(Value::as_str)?;
+            // Enum fields come as e.g. "HvacPowerStateOn" or "DefrostModeStateMax"
+            let state = s.trim_start_matches("HvacPowerState").trim_start_matches("DefrostModeState");
+            match state.to_lowercase().as_str() {
+                "true" | "on" | "precondition" | "normal" | "max" | "autodefog" => Some(true),
+                "false" | "off" | "overheatprotect" => Some(false),
+                _ => None,
+            }
+        })
+}
+
+/// Applies a record to a snapshot. Fields we don't show and invalid values are ignored.
+pub fn apply_record(snapshot: &mut VehicleSnapshot, record: &TelemetryRecord, now: i64) {
+    match record.status.as_deref() {
+        Some("DISCONNECTED") => snapshot.asleep = true,
+        Some("CONNECTED") => snapshot.asleep = false,
+        _ => {}
+    }
+    for datum in &record.data {
+        if datum.value.get("invalid").and_then(Value::as_bool).unwrap_or(false) {
+            continue;
+        }
+        let value = &datum.value;
+        match datum.key.as_str() {
+            "BatteryLevel" | "Soc" => snapshot.battery_level = number(value).or(snapshot.battery_level),
+            "EstBatteryRange" | "RatedRange" => snapshot.battery_range = number(value).or(snapshot.battery_range),
+            "DetailedChargeState" | "ChargeState" => snapshot.charging_state = text(value).or(snapshot.charging_state.take()),
+            "InsideTemp" => snapshot.inside_temp = number(value).or(snapshot.inside_temp),
+            "OutsideTemp" => snapshot.outside_temp = number(value).or(snapshot.outside_temp),
+            "HvacPower" => snapshot.is_climate_on = flag(value).or(snapshot.is_climate_on),
+            "DefrostForPreconditioning" | "DefrostMode" => snapshot.is_front_defroster_on = flag(value).or(snapshot.is_front_defroster_on),
+            "RearDefrostEnabled" => snapshot.is_rear_defroster_on = flag(value).or(snapshot.is_rear_defroster_on),
+            "Locked" => snapshot.locked = flag(value).or(snapshot.locked),
+            _ => continue,
+        }
+        // Data only arrives from an awake car
+        snapshot.asleep = false;
+        snapshot.updated_at = now;
+    }
+}
+
+/// Latest state per VIN, from telemetry pushes and on-demand polls
+pub struct TelemetryCache {
+    vehicles: DashMap<String, VehicleSnapshot>,
+}
+
+impl TelemetryCache {
+    pub fn new() -> Self {
+        Self { vehicles: DashMap::new() }
+    }
+
+    pub fn apply(&self, record: &TelemetryRecord, now: i64) {
+        let mut snapshot = self.vehicles.entry(record.vin.clone()).or_default();
+        apply_record(&mut snapshot, record, now);
+    }
+
+    /// Whether the VIN already has state, i.e. it was accepted before or polled for its owner
+    pub fn contains(&self, vin: &str) -> bool {
+        self.vehicles.contains_key(vin)
+    }
+
+    /// Replaces the state after polling the car directly
+    pub fn store(&self, vin: &str, snapshot: VehicleSnapshot) {
+        self.vehicles.insert(vin.to_string(), snapshot);
+    }
+
+    pub fn get(&self, vin: &str) -> Option<VehicleSnapshot> {
+        self.vehicles.get(vin).map(|snapshot| snapshot.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(key: &str, value: Value) -> TelemetryRecord {
+        TelemetryRecord {
+            vin: "5YJ3E1EA7KF000001".to_string(),
+            data: vec![TelemetryDatum { key: key.to_string(), value }],
+            status: None,
+        }
+    }
+
+    #[test]
+    fn hvac_power_enum_sets_climate() {
+        let mut snapshot = VehicleSnapshot::default();
+        apply_record(&mut snapshot, &record("HvacPower", json!({"hvacPowerValue": "HvacPowerStateOn"})), 10);
+        assert_eq!(snapshot.is_climate_on, Some(true));
+        apply_record(&mut snapshot, &record("HvacPower", json!({"stringValue": "HvacPowerStateOff"})), 20);
+        assert_eq!(snapshot.is_climate_on, Some(false));
+        assert_eq!(snapshot.updated_at, 20);
+    }
+
+    #[test]
+    fn cache_only_knows_vehicles_it_was_given() {
+        let cache = TelemetryCache::new();
+        assert!(!cache.contains("5YJ3E1EA7KF000001"));
+        cache.apply(&record("BatteryLevel", json!({"doubleValue": 80.5})), 10);
+        assert!(cache.contains("5YJ3E1EA7KF000001"));
+        assert_eq!(cache.get("5YJ3E1EA7KF000001").and_then(|s| s.battery_level), Some(80.5));
+    }
+}
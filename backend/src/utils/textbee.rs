@@ -0,0 +1,211 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::AppState;
+
+const TEXTBEE_API_URL: &str = "https://api.textbee.dev/api/v1/gateway";
+/// A phone that can't answer within this is as good as offline for sending
+const TEXTBEE_TIMEOUT_SECS: u64 = 10;
+
+pub const STATUS_ONLINE: &str = "online";
+pub const STATUS_OFFLINE: &str = "offline";
+
+#[derive(Debug)]
+pub enum TextbeeError {
+    /// The device isn't registered, is disabled, or the gateway couldn't reach it
+    DeviceOffline(String),
+    Request(String),
+}
+
+impl std::fmt::Display for TextbeeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextbeeError::DeviceOffline(reason) => write!(f, "TextBee device is offline or unreachable: {}", reason),
+            TextbeeError::Request(msg) => write!(f, "TextBee request failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TextbeeError {}
+
+#[derive(Deserialize)]
+struct DeviceList {
+    #[serde(default)]
+    data: Vec<Device>,
+}
+
+#[derive(Deserialize)]
+struct Device {
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(default)]
+    enabled: bool,
+}
+
+fn client() -> Client {
+    Client::builder()
+        .timeout(Duration::from_secs(TEXTBEE_TIMEOUT_SECS))
+        .build()
+        .expect("Client should build")
+}
+
+fn now() -> i32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i32
+}
+
+/// Asks the TextBee gateway whether the device is registered and enabled
+pub async fn check_device(device_id: &str, api_key: &str) -> Result<(), TextbeeError> {
+    let response = client()
+        .get(format!("{}/devices", TEXTBEE_API_URL))
+        .header("x-api-key", api_key)
+        .send()
+        .await
+        .map_err(|e| TextbeeError::DeviceOffline(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(TextbeeError::Request(format!("gateway returned {}", response.status())));
+    }
+    let devices: DeviceList = response
+        .json()
+        .await
+        .map_err(|e| TextbeeError::Request(format!("invalid device list: {}", e)))?;
+    device_in_list(&devices, device_id)
+}
+
+fn device_in_list(devices: &DeviceList, device_id: &str) -> Result<(), TextbeeError> {
+    match devices.data.iter().find(|device| device.id == device_id) {
+        Some(device) if device.enabled => Ok(()),
+        Some(_) => Err(TextbeeError::DeviceOffline("device is disabled in TextBee".to_string())),
+        None => Err(TextbeeError::DeviceOffline("device is not registered with this API key".to_string())),
+    }
+}
+
+/// Checks the user's device now and stores the result so it shows up in their profile
+pub async fn refresh_device_status(state: &Arc<AppState>, user_id: i32) -> Result<(), TextbeeError> {
+    let (device_id, api_key) = state.user_core.get_textbee_credentials(user_id)
+        .map_err(|e| TextbeeError::Request(e.to_string()))?;
+    let result = check_device(&device_id, &api_key).await;
+    store_device_status(state, user_id, result)
+}
+
+fn store_device_status(state: &Arc<AppState>, user_id: i32, result: Result<(), TextbeeError>) -> Result<(), TextbeeError> {
+    let status = match &result {
+        Ok(()) => STATUS_ONLINE,
+        Err(TextbeeError::DeviceOffline(_)) => STATUS_OFFLINE,
+        // A gateway problem says nothing about the phone, keep the last known status
+        Err(TextbeeError::Request(_)) => return result,
+    };
+    let previous = state.user_core.get_user_settings(user_id).ok().and_then(|s| s.textbee_device_status);
+    if previous.as_deref() != Some(status) {
+        tracing::info!("TextBee device for user {} is now {}", user_id, status);
+    }
+    if let Err(e) = state.user_core.update_textbee_status(user_id, status, now()) {
+        tracing::error!("Failed to store TextBee status for user {}: {}", user_id, e);
+    }
+    result
+}
+
+/// Sends through the user's phone. Fails fast with `DeviceOffline` when the last check found the
+/// device offline, so callers can fall back to Twilio instead of the message vanishing.
+pub async fn send_sms(
+    state: &Arc<AppState>,
+    user_id: i32,
+    device_id: &str,
+    api_key: &str,
+    recipient: &str,
+    body: &str,
+) -> Result<(), TextbeeError> {
+    let status = state.user_core.get_user_settings(user_id).ok().and_then(|s| s.textbee_device_status);
+    if status.as_deref() == Some(STATUS_OFFLINE) {
+        return Err(TextbeeError::DeviceOffline("last reachability check failed".to_string()));
+    }
+
+    let response = client()
+        .post(format!("{}/devices/{}/send-sms", TEXTBEE_API_URL, device_id))
+        .header("Content-Type", "application/json")
+        .header("x-api-key", api_key)
+        .json(&json!({
+            "recipients": [recipient],
+            "message": body
+        }))
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => {
+            tracing::debug!("Successfully sent conversation message via TextBee to: {}", recipient);
+            Ok(())
+        }
+        Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+            let _ = state.user_core.update_textbee_status(user_id, STATUS_OFFLINE, now());
+            Err(TextbeeError::DeviceOffline("device not found".to_string()))
+        }
+        Ok(response) => Err(TextbeeError::Request(format!("gateway returned {}", response.status()))),
+        Err(e) => {
+            let _ = state.user_core.update_textbee_status(user_id, STATUS_OFFLINE, now());
+            Err(TextbeeError::DeviceOffline(e.to_string()))
+        }
+    }
+}
+
+/// Periodic reachability check for every user with a TextBee device. Offline devices are picked up
+/// again automatically once the phone comes back, since every device is checked on each run.
+pub async fn check_all_devices(state: &Arc<AppState>) {
+    let user_ids = match state.user_core.get_textbee_user_ids() {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::error!("Failed to list TextBee users: {}", e);
+            return;
+        }
+    };
+    for user_id in user_ids {
+        if let Err(e) = refresh_device_status(state, user_id).await {
+            tracing::warn!("TextBee check for user {}: {}", user_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    fn stored_status(state: &Arc<AppState>, user_id: i32) -> Option<String> {
+        state.user_core.get_user_settings(user_id).unwrap().textbee_device_status
+    }
+
+    #[test]
+    fn only_an_enabled_registered_device_is_online() {
+        let devices: DeviceList = serde_json::from_value(json!({"data": [
+            {"_id": "phone-1", "enabled": true},
+            {"_id": "phone-2", "enabled": false},
+        ]})).unwrap();
+        assert!(device_in_list(&devices, "phone-1").is_ok());
+        assert!(matches!(device_in_list(&devices, "phone-2"), Err(TextbeeError::DeviceOffline(_))));
+        assert!(matches!(device_in_list(&devices, "phone-3"), Err(TextbeeError::DeviceOffline(_))));
+    }
+
+    #[tokio::test]
+    async fn device_checks_are_stored_but_gateway_errors_are_not() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "textbee@example.com");
+
+        assert!(store_device_status(&state, user_id, Ok(())).is_ok());
+        assert_eq!(stored_status(&state, user_id).as_deref(), Some(STATUS_ONLINE));
+
+        let offline = store_device_status(&state, user_id, Err(TextbeeError::DeviceOffline("disabled".to_string())));
+        assert!(matches!(offline, Err(TextbeeError::DeviceOffline(_))));
+        assert_eq!(stored_status(&state, user_id).as_deref(), Some(STATUS_OFFLINE));
+
+        // The gateway failing says nothing about the phone
+        store_device_status(&state, user_id, Ok(())).unwrap();
+        let gateway = store_device_status(&state, user_id, Err(TextbeeError::Request("gateway returned 503".to_string())));
+        assert!(matches!(gateway, Err(TextbeeError::Request(_))));
+        assert_eq!(stored_status(&state, user_id).as_deref(), Some(STATUS_ONLINE));
+    }
+}
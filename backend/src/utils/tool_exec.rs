@@ -2,27 +2,123 @@ use crate::AppState;
 use std::sync::Arc;
 use std::error::Error;
 
-use crate::tool_call_utils::utils::create_openai_client;
+use crate::tool_call_utils::utils::{create_openai_client, chat_completion_with_fallback, ModelPurpose};
 use openai_api_rs::v1::chat_completion::{self, ChatCompletionMessage, MessageRole, Content};
 
 use serde_json::json;
 
-pub async fn handle_firecrawl_search(
-    query: String,
-    limit: u32,
-) -> Result<String, Box<dyn Error>> {
-    let api_key = std::env::var("FIRECRAWL_API_KEY")
-        .map_err(|_| "FIRECRAWL_API_KEY environment variable not set")?;
+pub const DEFAULT_FIRECRAWL_RESULTS: u32 = 5;
+/// Each result is crawled and billed, so users can't go past this
+pub const MAX_FIRECRAWL_RESULTS: u32 = 10;
+
+/// How a user's web searches are run: how many results to crawl and which sites to use
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirecrawlOptions {
+    pub limit: u32,
+    pub include_domains: Vec<String>,
+    pub exclude_domains: Vec<String>,
+}
+
+impl Default for FirecrawlOptions {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_FIRECRAWL_RESULTS,
+            include_domains: Vec::new(),
+            exclude_domains: Vec::new(),
+        }
+    }
+}
+
+impl FirecrawlOptions {
+    /// The query with the domain filters appended as search operators
+    pub fn filtered_query(&self, query: &str) -> String {
+        let mut filtered = query.trim().to_string();
+        if !self.include_domains.is_empty() {
+            let sites: Vec<String> = self.include_domains.iter().map(|d| format!("site:{}", d)).collect();
+            filtered.push_str(&format!(" ({})", sites.join(" OR ")));
+        }
+        for domain in &self.exclude_domains {
+            filtered.push_str(&format!(" -site:{}", domain));
+        }
+        filtered
+    }
+}
+
+/// Normalizes a domain like "https://www.Example.com/" to "example.com", rejecting anything that isn't a hostname
+pub fn normalize_domain(domain: &str) -> Result<String, String> {
+    let trimmed = domain.trim().to_lowercase();
+    let host = trimmed
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.")
+        .trim_end_matches('/');
+    let valid = host.len() <= 253
+        && host.contains('.')
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(host.to_string())
+    } else {
+        Err(format!("\"{}\" is not a valid domain", domain.trim()))
+    }
+}
 
-    let data = json!({
-      "query": query,
-      "limit": limit,
+/// Parses a comma-separated domain list as stored in the settings, None for an empty list
+pub fn parse_domain_list(list: &str) -> Result<Option<String>, String> {
+    let mut domains: Vec<String> = Vec::new();
+    for domain in list.split(',').filter(|d| !d.trim().is_empty()) {
+        let domain = normalize_domain(domain)?;
+        if !domains.contains(&domain) {
+            domains.push(domain);
+        }
+    }
+    Ok(if domains.is_empty() { None } else { Some(domains.join(",")) })
+}
+
+/// The user's search preferences, defaults for anything unset or unreadable
+pub fn firecrawl_options(state: &Arc<AppState>, user_id: i32) -> FirecrawlOptions {
+    let Ok(settings) = state.user_core.get_user_settings(user_id) else {
+        return FirecrawlOptions::default();
+    };
+    let split = |list: Option<String>| -> Vec<String> {
+        list.map(|l| l.split(',').filter_map(|d| normalize_domain(d).ok()).collect())
+            .unwrap_or_default()
+    };
+    FirecrawlOptions {
+        limit: settings.firecrawl_result_count
+            .map(|count| (count.max(1) as u32).min(MAX_FIRECRAWL_RESULTS))
+            .unwrap_or(DEFAULT_FIRECRAWL_RESULTS),
+        include_domains: split(settings.firecrawl_include_domains),
+        exclude_domains: split(settings.firecrawl_exclude_domains),
+    }
+}
+
+/// Body of the Firecrawl search request with the user's result count and domain filters applied
+fn firecrawl_search_body(query: &str, options: &FirecrawlOptions) -> serde_json::Value {
+    json!({
+      "query": options.filtered_query(query),
+      "limit": options.limit,
       "location": "",
       "tbs": "",
       "scrapeOptions": {
         "formats": [ "markdown" ]
       }
-    });
+    })
+}
+
+pub async fn handle_firecrawl_search(
+    query: String,
+    options: &FirecrawlOptions,
+) -> Result<String, Box<dyn Error>> {
+    let api_key = std::env::var("FIRECRAWL_API_KEY")
+        .map_err(|_| "FIRECRAWL_API_KEY environment variable not set")?;
+
+    let data = firecrawl_search_body(&query, options);
 
     let client = reqwest::Client::new();
     let response = client
@@ -41,6 +137,28 @@ pub async fn handle_firecrawl_search(
     Ok(text)
 }
 
+pub const UNIT_SYSTEMS: [&str; 2] = ["metric", "imperial"];
+
+/// Units asked for in the tool call, or the user's stored preference when the call left them out
+pub fn resolve_units(state: &Arc<AppState>, user_id: i32, requested: Option<&str>) -> String {
+    if let Some(units) = requested.map(|u| u.trim().to_lowercase()).filter(|u| UNIT_SYSTEMS.contains(&u.as_str())) {
+        return units;
+    }
+    state.user_core.get_user_settings(user_id)
+        .ok()
+        .and_then(|settings| settings.units)
+        .filter(|units| UNIT_SYSTEMS.contains(&units.as_str()))
+        .unwrap_or_else(|| "metric".to_string())
+}
+
+/// Tesla reports temperatures in Celsius, this converts them for imperial users
+pub fn convert_temperature(celsius: f64, units: &str) -> f64 {
+    match units {
+        "imperial" => ((celsius * 9.0 / 5.0 + 32.0) * 10.0).round() / 10.0,
+        _ => celsius,
+    }
+}
+
 pub async fn get_weather(
     state: &Arc<AppState>,
     location: &str, 
@@ -222,11 +340,11 @@ pub async fn ask_perplexity(
     ];
 
     let request = chat_completion::ChatCompletionRequest::new(
-        "perplexity/sonar-reasoning-pro".to_string(),
+        crate::tool_call_utils::utils::model_for(state, None, ModelPurpose::WebSearch),
         messages,
     );
 
-    let response = client.chat_completion(request).await?;
+    let response = chat_completion_with_fallback(&client, request, ModelPurpose::WebSearch).await?;
     
     let content = response.choices[0].message.content.clone().unwrap_or_default();
 
@@ -238,37 +356,74 @@ use reqwest;
 use serde_json;
 use urlencoding;
 
+/// Read back when the maps APIs are down or out of quota
+pub const MAPS_UNAVAILABLE_MESSAGE: &str = "The map service isn't available right now, please try again in a little while.";
+
+/// Why a maps lookup failed. Running out of quota is kept apart from an outage and from an address
+/// that just doesn't exist, both for the logs and for what the user is told.
+#[derive(Debug)]
+pub enum GeocodeError {
+    NotFound(String),
+    QuotaExceeded(String),
+    Unavailable(String),
+}
+
+impl std::fmt::Display for GeocodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeocodeError::NotFound(e) => write!(f, "location not found: {}", e),
+            GeocodeError::QuotaExceeded(e) => write!(f, "maps API quota exceeded: {}", e),
+            GeocodeError::Unavailable(e) => write!(f, "maps API unavailable: {}", e),
+        }
+    }
+}
+
+impl Error for GeocodeError {}
+
+impl GeocodeError {
+    /// What to tell the user instead of the raw error, worded to be read out in a call
+    pub fn spoken_message(&self, location: &str) -> String {
+        match self {
+            GeocodeError::NotFound(_) => format!(
+                "I couldn't find {} on the map. Could you give a more specific address or a nearby landmark?",
+                location
+            ),
+            GeocodeError::QuotaExceeded(_) | GeocodeError::Unavailable(_) => MAPS_UNAVAILABLE_MESSAGE.to_string(),
+        }
+    }
+}
+
+/// Quota errors are logged as errors since every user is affected until the quota resets
+pub fn log_geocode_error(context: &str, error: &GeocodeError) {
+    match error {
+        GeocodeError::QuotaExceeded(e) => tracing::error!("Maps API quota exceeded while {}: {}", context, e),
+        GeocodeError::Unavailable(e) => tracing::warn!("Maps API unavailable while {}: {}", context, e),
+        GeocodeError::NotFound(e) => tracing::info!("Location not found while {}: {}", context, e),
+    }
+}
+
+/// Geoapify answers 429 when rate limited and 401/402 when the key is out of credits
+fn geoapify_status_error(status: reqwest::StatusCode) -> Option<GeocodeError> {
+    match status.as_u16() {
+        200..=299 => None,
+        401 | 402 | 429 => Some(GeocodeError::QuotaExceeded(format!("Geoapify returned {}", status))),
+        _ => Some(GeocodeError::Unavailable(format!("Geoapify returned {}", status))),
+    }
+}
+
+fn geoapify_key() -> Result<String, GeocodeError> {
+    std::env::var("GEOAPIFY_API_KEY").map_err(|_| GeocodeError::Unavailable("GEOAPIFY_API_KEY is not set".to_string()))
+}
+
 pub async fn get_nearby_towns(
     location: &str,
-) -> Result<Vec<String>, Box<dyn Error>> {
+) -> Result<Vec<String>, GeocodeError> {
    
     let client = reqwest::Client::new();
-    let geoapify_key = std::env::var("GEOAPIFY_API_KEY").expect("GEOAPIFY_API_KEY must be set");
+    let geoapify_key = geoapify_key()?;
    
     // Get coordinates using Geoapify Geocoding
-    let geocoding_url = format!(
-        "https://api.geoapify.com/v1/geocode/search?text={}&format=json&apiKey={}",
-        urlencoding::encode(location),
-        geoapify_key
-    );
-    let geocoding_response: serde_json::Value = client
-        .get(&geocoding_url)
-        .send()
-        .await?
-        .json()
-        .await?;
-    let results = geocoding_response["results"].as_array()
-        .ok_or("No results found")?;
-    if results.is_empty() {
-        return Err("Location not found".into());
-    }
-    let result = &results[0];
-    let lat = result["lat"].as_f64()
-        .ok_or("Latitude not found")?;
-    let lon = result["lon"].as_f64()
-        .ok_or("Longitude not found")?;
-    let location_name = result["formatted"].as_str()
-        .unwrap_or(location);
+    let (lat, lon, location_name) = get_coordinates(&client, location, &geoapify_key).await?;
     println!("Found coordinates for {}: lat={}, lon={}", location_name, lat, lon);
    
     // Get nearby populated places (focus on suburb and neighbourhood for close places)
@@ -283,12 +438,17 @@ pub async fn get_nearby_towns(
         geoapify_key
     );
     println!("Places API URL: {}", places_url);
-    let response = client.get(&places_url).send().await?;
+    let response = client.get(&places_url).send().await
+        .map_err(|e| GeocodeError::Unavailable(e.to_string()))?;
     println!("Places API status: {}", response.status());
-    let places_response: serde_json::Value = response.json().await?;
+    if let Some(e) = geoapify_status_error(response.status()) {
+        return Err(e);
+    }
+    let places_response: serde_json::Value = response.json().await
+        .map_err(|e| GeocodeError::Unavailable(format!("Failed to parse places response: {}", e)))?;
    
     let features = places_response["features"].as_array()
-        .ok_or("No features found")?;
+        .ok_or_else(|| GeocodeError::Unavailable("No features in places response".to_string()))?;
    
     let mut nearby_places: Vec<(String, f64)> = Vec::new(); // (name, distance)
     let mut seen = HashSet::new();
@@ -335,23 +495,78 @@ pub async fn get_coordinates(
     client: &reqwest::Client,
     address: &str,
     api_key: &str,
-) -> Result<(f64, f64, String), Box<dyn Error>> {
+) -> Result<(f64, f64, String), GeocodeError> {
     let url = format!(
         "https://api.geoapify.com/v1/geocode/search?text={}&format=json&apiKey={}",
         urlencoding::encode(address),
         api_key
     );
-    let response: serde_json::Value = client.get(&url).send().await?.json().await?;
-    let results = response["results"].as_array().ok_or("No results found")?;
-    if results.is_empty() {
-        return Err("Location not found".into());
+    let response = client.get(&url).send().await
+        .map_err(|e| GeocodeError::Unavailable(e.to_string()))?;
+    if let Some(e) = geoapify_status_error(response.status()) {
+        return Err(e);
     }
-    let result = &results[0];
-    let lat = result["lat"].as_f64().ok_or("Latitude not found")?;
-    let lon = result["lon"].as_f64().ok_or("Longitude not found")?;
+    let response: serde_json::Value = response.json().await
+        .map_err(|e| GeocodeError::Unavailable(format!("Failed to parse geocoding response: {}", e)))?;
+    let result = response["results"]
+        .as_array()
+        .and_then(|results| results.first())
+        .ok_or_else(|| GeocodeError::NotFound(address.to_string()))?;
+    let (lat, lon) = result["lat"].as_f64().zip(result["lon"].as_f64())
+        .ok_or_else(|| GeocodeError::NotFound(format!("{} has no coordinates", address)))?;
     let formatted = result["formatted"]
         .as_str()
         .unwrap_or(address)
         .to_string();
     Ok((lat, lon, formatted))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+
+    #[test]
+    fn weather_without_units_uses_the_users_default() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "imperial@example.com");
+        assert_eq!(resolve_units(&state, user_id, None), "metric");
+
+        state.user_core.update_units(user_id, Some("imperial".to_string())).unwrap();
+        assert_eq!(resolve_units(&state, user_id, None), "imperial");
+        assert_eq!(resolve_units(&state, user_id, Some("kelvin")), "imperial");
+        assert_eq!(resolve_units(&state, user_id, Some(" Metric ")), "metric");
+    }
+
+    #[test]
+    fn tesla_temperatures_follow_the_units() {
+        assert_eq!(convert_temperature(21.5, "imperial"), 70.7);
+        assert_eq!(convert_temperature(-40.0, "imperial"), -40.0);
+        assert_eq!(convert_temperature(21.5, "metric"), 21.5);
+    }
+
+    #[test]
+    fn configured_count_and_domains_reach_the_firecrawl_request() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "crawler@example.com");
+        let body = firecrawl_search_body("sauna heater", &firecrawl_options(&state, user_id));
+        assert_eq!((body["query"].as_str(), body["limit"].as_u64()), (Some("sauna heater"), Some(DEFAULT_FIRECRAWL_RESULTS as u64)));
+
+        state.user_core.update_firecrawl_result_count(user_id, Some(3)).unwrap();
+        state.user_core.update_firecrawl_domains(user_id, true, parse_domain_list("https://www.Harvia.com/, tori.fi").unwrap()).unwrap();
+        state.user_core.update_firecrawl_domains(user_id, false, parse_domain_list("pinterest.com").unwrap()).unwrap();
+
+        let body = firecrawl_search_body(" sauna heater ", &firecrawl_options(&state, user_id));
+        assert_eq!(body["limit"], 3);
+        assert_eq!(body["query"], "sauna heater (site:harvia.com OR site:tori.fi) -site:pinterest.com");
+    }
+
+    #[test]
+    fn result_count_is_kept_within_bounds() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "greedy@example.com");
+        state.user_core.update_firecrawl_result_count(user_id, Some(50)).unwrap();
+        assert_eq!(firecrawl_options(&state, user_id).limit, MAX_FIRECRAWL_RESULTS);
+        assert!(parse_domain_list("example.com, not a domain").is_err());
+    }
+}
@@ -4,6 +4,115 @@ use std::sync::Arc;
 /// Checks if a user has sufficient credits to perform an action.
 /// Returns Ok(()) if the user has enough credits, or Err with an appropriate error message if not.
 /// Also handles automatic recharging if enabled.
+/// What to do when a user runs out of credits
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CreditPolicy {
+    /// Refuse the request (default)
+    Block,
+    /// Allow it while the balance stays above -overdraft, warning the user to top up
+    Grace,
+    /// Refuse it, but send a top-up link instead of the generic notice
+    Warn,
+}
+
+impl CreditPolicy {
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "block" => Some(CreditPolicy::Block),
+            "grace" => Some(CreditPolicy::Grace),
+            "warn" => Some(CreditPolicy::Warn),
+            _ => None,
+        }
+    }
+}
+
+/// Largest overdraft (in credits) the grace policy may allow
+const MAX_CREDIT_OVERDRAFT: f32 = 5.0;
+
+/// How far below zero the grace policy lets the balance go, from CREDIT_OVERDRAFT (default 0.50)
+pub fn credit_overdraft() -> f32 {
+    std::env::var("CREDIT_OVERDRAFT")
+        .ok()
+        .and_then(|v| v.parse::<f32>().ok())
+        .filter(|v| v.is_finite())
+        .unwrap_or(0.50)
+        .clamp(0.0, MAX_CREDIT_OVERDRAFT)
+}
+
+/// The policy an admin set for the user first, then INSUFFICIENT_CREDITS_POLICY, then block
+pub fn credit_policy(state: &Arc<AppState>, user_id: i32) -> CreditPolicy {
+    state.user_core.get_user_settings(user_id).ok()
+        .and_then(|settings| settings.insufficient_credits_policy)
+        .and_then(|policy| CreditPolicy::from_str(&policy))
+        .or_else(|| std::env::var("INSUFFICIENT_CREDITS_POLICY").ok().and_then(|policy| CreditPolicy::from_str(&policy)))
+        .unwrap_or(CreditPolicy::Block)
+}
+
+/// Overdraft a user is allowed under their policy, zero unless the policy is grace
+pub fn allowed_overdraft(state: &Arc<AppState>, user_id: i32) -> f32 {
+    match credit_policy(state, user_id) {
+        CreditPolicy::Grace => credit_overdraft(),
+        _ => 0.0,
+    }
+}
+
+fn top_up_link() -> String {
+    format!("{}/billing", std::env::var("FRONTEND_URL").unwrap_or_else(|_| "https://lightfriend.ai".to_string()))
+}
+
+/// Replies to an inbound SMS from a user without credits as (language, template), `{link}` is the top-up link
+const OUT_OF_CREDITS_REPLIES: [(&str, &str); 6] = [
+    ("en", "I can't answer this one, you're out of credits. Top up here and I'll be right back: {link}"),
+    ("fi", "En voi vastata tähän, krediittisi ovat loppuneet. Lataa lisää täältä niin jatketaan: {link}"),
+    ("de", "Ich kann darauf nicht antworten, dein Guthaben ist aufgebraucht. Lade hier auf, dann geht es weiter: {link}"),
+    ("sv", "Jag kan inte svara på det här, dina krediter är slut. Fyll på här så är jag tillbaka: {link}"),
+    ("fr", "Je ne peux pas répondre, vous n'avez plus de crédits. Rechargez ici pour continuer : {link}"),
+    ("es", "No puedo responder, te has quedado sin créditos. Recarga aquí y seguimos: {link}"),
+];
+
+/// Auto-reply for an inbound SMS that can't be processed for lack of credits. OUT_OF_CREDITS_REPLY
+/// overrides the built-in text for every language and may use `{link}` too.
+pub fn out_of_credits_reply(language: &str) -> String {
+    out_of_credits_reply_with(language, std::env::var("OUT_OF_CREDITS_REPLY").ok())
+}
+
+fn out_of_credits_reply_with(language: &str, override_template: Option<String>) -> String {
+    let language = language.to_lowercase();
+    let template = override_template
+        .filter(|reply| !reply.trim().is_empty())
+        .unwrap_or_else(|| {
+            OUT_OF_CREDITS_REPLIES
+                .iter()
+                .find(|(lang, _)| *lang == language)
+                .unwrap_or(&OUT_OF_CREDITS_REPLIES[0])
+                .1
+                .to_string()
+        });
+    template.replace("{link}", &top_up_link())
+}
+
+/// What the user is texted when an event finds them out of credits
+fn out_of_credits_notice(state: &Arc<AppState>, user_id: i32, policy: CreditPolicy, event_type: &str, within_grace: bool) -> String {
+    match policy {
+        // The user texted in and is waiting for an answer, tell them why none is coming
+        _ if event_type == "message" && !within_grace => {
+            let language = state.user_core.get_user_settings(user_id)
+                .map(|settings| settings.agent_language)
+                .unwrap_or_else(|_| "en".to_string());
+            out_of_credits_reply(&language)
+        }
+        CreditPolicy::Block => "Your credits and monthly quota have been depleted. Please recharge your credits to continue using the service.".to_string(),
+        CreditPolicy::Grace if within_grace => format!(
+            "You're out of credits, so this one is on a small overdraft. Top up to keep using the service: {}",
+            top_up_link()
+        ),
+        CreditPolicy::Grace | CreditPolicy::Warn => format!(
+            "Your credits and monthly quota have been depleted. Top up here to continue: {}",
+            top_up_link()
+        ),
+    }
+}
+
 pub async fn check_user_credits(
     state: &Arc<AppState>,
     user: &crate::models::user_models::User,
@@ -28,6 +137,12 @@ pub async fn check_user_credits(
         return Ok(());
     }
 
+    // The caps hold whatever the balance, the user was told when the cap was hit
+    if let Some((period, cap)) = spending_cap_reached(state, user.id) {
+        tracing::info!("User {} reached their {} credit cap of {:.2}, blocking {}", user.id, period, cap, event_type);
+        return Err(cap_reached_message(period, cap));
+    }
+
     // Define costs based on phone number
     let (message_cost, voice_second_cost, noti_msg_cost, noti_call_cost) = if user.phone_number.starts_with("+1") {
         (0.075, 0.0033, 0.075, 0.15) // US, CA
@@ -64,6 +179,14 @@ pub async fn check_user_credits(
 
     
     if (user.credits_left < 0.00 || user.credits_left < required_credits_left) && (user.credits < 0.0 || user.credits < required_credits) {
+        let policy = credit_policy(state, user.id);
+        let overdraft = credit_overdraft();
+        let within_grace = policy == CreditPolicy::Grace && user.credits - required_credits >= -overdraft;
+        tracing::info!(
+            "User {} is out of credits for {} (credits {:.2}, needed {:.2}), policy {:?}: {}",
+            user.id, event_type, user.credits, required_credits, policy,
+            if within_grace { "allowing within overdraft" } else { "blocking" }
+        );
         // Check if enough time has passed since the last notification (24 hours)
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -83,18 +206,22 @@ pub async fn check_user_credits(
                 eprintln!("Failed to update last_credits_notification: {}", e);
             }
 
+            let notice = out_of_credits_notice(state, user.id, policy, event_type, within_grace);
             let user_clone = user.clone();
             let state_clone = state.clone();
             
             tokio::spawn(async move {
                 let _ = crate::api::twilio_utils::send_conversation_message(
                     &state_clone,
-                    "Your credits and monthly quota have been depleted. Please recharge your credits to continue using the service.",
+                    &notice,
                     None,
                     &user_clone,
                 ).await;
             });
         }
+        if within_grace {
+            return Ok(());
+        }
         return Err("Insufficient credits. You have used all your monthly quota and don't have enough extra credits.".to_string());
     }
 
@@ -121,6 +248,228 @@ pub async fn check_user_credits(
     Ok(())
 }
 
+/// Start of the user's current day and week (from Monday) as timestamps, in their timezone
+pub fn cap_period_starts(now: chrono::DateTime<chrono::Utc>, timezone: chrono_tz::Tz) -> (i32, i32) {
+    use chrono::{Datelike, TimeZone};
+    let today = now.with_timezone(&timezone).date_naive();
+    let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+    let midnight = |date: chrono::NaiveDate| {
+        let naive = date.and_hms_opt(0, 0, 0).expect("midnight exists");
+        // A clock change at midnight skips it, the day then starts at the first valid time
+        timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .or_else(|| timezone.from_local_datetime(&(naive + chrono::Duration::hours(1))).earliest())
+            .map_or(naive.and_utc().timestamp(), |start| start.timestamp()) as i32
+    };
+    (midnight(today), midnight(monday))
+}
+
+/// (period, cap, credits spent this period) for each cap the user has set
+fn spending_against_caps(state: &Arc<AppState>, user_id: i32) -> Vec<(&'static str, f32, f32)> {
+    let Ok(settings) = state.user_core.get_user_settings(user_id) else {
+        return Vec::new();
+    };
+    let caps = [("daily", settings.daily_credit_cap), ("weekly", settings.weekly_credit_cap)];
+    if caps.iter().all(|(_, cap)| cap.is_none()) {
+        return Vec::new();
+    }
+    let timezone = crate::utils::scheduled_sends::user_timezone(state, user_id);
+    let (day_start, week_start) = cap_period_starts(chrono::Utc::now(), timezone);
+    caps.into_iter()
+        .filter_map(|(period, cap)| {
+            let since = if period == "daily" { day_start } else { week_start };
+            match state.user_repository.get_credit_spend_since(user_id, since) {
+                Ok(spent) => Some((period, cap?, spent)),
+                Err(e) => {
+                    tracing::error!("Failed to sum {} credit spend of user {}: {}", period, user_id, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The first cap the user's spending has reached this period, as (period, cap)
+pub fn spending_cap_reached(state: &Arc<AppState>, user_id: i32) -> Option<(&'static str, f32)> {
+    spending_against_caps(state, user_id)
+        .into_iter()
+        .find(|(_, cap, spent)| spent >= cap)
+        .map(|(period, cap, _)| (period, cap))
+}
+
+/// Sent once when a cap is hit, and the reason given for anything blocked afterwards
+pub fn cap_reached_message(period: &str, cap: f32) -> String {
+    let until = if period == "daily" { "midnight" } else { "Monday" };
+    format!(
+        "You've reached your {} spending cap of {:.2} credits, so calls, messages and notifications are paused until {}. You can change the cap in your settings.",
+        period, cap, until
+    )
+}
+
+/// Adds the cost to the user's spending and texts them if it took them over a cap. Only the
+/// action that crosses the cap sends the note, so it goes out once per period.
+fn record_spend_against_caps(state: &Arc<AppState>, user: &crate::models::user_models::User, cost: f32) {
+    if cost <= 0.0 {
+        return;
+    }
+    let now = chrono::Utc::now().timestamp() as i32;
+    if let Err(e) = state.user_repository.record_credit_spend(user.id, cost, now) {
+        tracing::error!("Failed to record credit spend of user {}: {}", user.id, e);
+        return;
+    }
+    let crossed = spending_against_caps(state, user.id)
+        .into_iter()
+        .find(|(_, cap, spent)| *spent >= *cap && *spent - cost < *cap);
+    if let Some((period, cap, _)) = crossed {
+        tracing::info!("User {} reached their {} credit cap of {:.2}", user.id, period, cap);
+        let notice = cap_reached_message(period, cap);
+        let user_clone = user.clone();
+        let state_clone = state.clone();
+        tokio::spawn(async move {
+            let _ = crate::api::twilio_utils::send_conversation_message(
+                &state_clone,
+                &notice,
+                None,
+                &user_clone,
+            ).await;
+        });
+    }
+}
+
+/// Per-unit credit costs for a user, in their `billing_currency`. Shared by the deduction logic,
+/// call budgeting and the billing estimate so what we show is what gets charged.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct CreditCosts {
+    pub message: f32,
+    pub voice_second: f32,
+    pub noti_msg: f32,
+    pub noti_call: f32,
+}
+
+/// Currency a user's credits are priced in: US, Canada and Australia see dollars, everyone else euros
+pub fn billing_currency(phone_number: &str) -> &'static str {
+    if phone_number.starts_with("+1") || phone_number.starts_with("+61") {
+        "USD"
+    } else {
+        "EUR"
+    }
+}
+
+/// Users outside the supported countries pay twilio themselves so we don't charge them for messages
+pub fn messages_are_included(phone_number: &str) -> bool {
+    // true since they pay to twilio. won't cause bug since sending messages outside the range will require their own creds
+    !(phone_number.starts_with("+1") ||
+        phone_number.starts_with("+358") ||
+        phone_number.starts_with("+31") ||
+        phone_number.starts_with("+44") ||
+        phone_number.starts_with("+61"))
+}
+
+/// Costs based on phone number or tier 3 dynamic pricing. `settings` is only needed for tier 3 users.
+pub fn credit_costs_for_user(
+    user: &crate::models::user_models::User,
+    settings: Option<&crate::models::user_models::UserSettings>,
+) -> CreditCosts {
+    let is_tier3 = user.sub_tier.as_deref() == Some("tier 3");
+    let (message, voice_second, noti_msg, noti_call) = if is_tier3 {
+        // For tier 3, use outbound_message_pricing from user_settings
+        match settings.and_then(|s| s.outbound_message_pricing) {
+            // Tier 3 dynamic pricing based on country
+            Some(pricing) => (pricing, 0.005, pricing, pricing * 2.0),
+            // Fallback to US pricing if not set
+            None => (0.075, 0.0033, 0.075, 0.15),
+        }
+    } else if user.phone_number.starts_with("+1") {
+        (0.075, 0.0033, 0.075, 0.15) // US
+    } else if user.phone_number.starts_with("+358") {
+        (0.30, 0.005, 0.15, 0.70) // Finland
+    } else if user.phone_number.starts_with("+31") {
+        (0.30, 0.005, 0.15, 0.45) // NL
+    } else if user.phone_number.starts_with("+44") {
+        (0.30, 0.005, 0.15, 0.20) // UK
+    } else if user.phone_number.starts_with("+61") {
+        (0.30, 0.005, 0.15, 0.20) // Australia
+    } else {
+        (0.0, 0.0, 0.0, 0.0)
+    };
+    CreditCosts { message, voice_second, noti_msg, noti_call }
+}
+
+/// Budget for calls whose cost isn't taken from credits, long enough to never be the limit
+const UNMETERED_CALL_SECONDS: i32 = 24 * 60 * 60;
+
+/// Returns (seconds until the balance hits the recharge threshold, seconds until it hits zero)
+/// when talking at `voice_second_cost`. fetch_assistant uses this for the call's top-up and hang up timestamps.
+pub fn voice_seconds_budget(credits: f32, voice_second_cost: f32, charge_back_threshold: f32) -> (i32, i32) {
+    // Calls that aren't charged from credits (users on their own twilio) aren't limited by the balance either
+    if voice_second_cost <= 0.0 {
+        return (UNMETERED_CALL_SECONDS, UNMETERED_CALL_SECONDS);
+    }
+    let user_current_credits_to_threshold = credits - charge_back_threshold;
+    let seconds_to_threshold = (user_current_credits_to_threshold / voice_second_cost) as i32;
+    let seconds_to_zero_credits = (credits / voice_second_cost) as i32;
+    (seconds_to_threshold, seconds_to_zero_credits)
+}
+
+/// Talk time a notification call must be able to pay for before we dial, from
+/// NOTIFICATION_CALL_MIN_SECONDS (default 30). Below it the notification goes out as SMS instead
+/// of a call that hangs up as soon as it's answered.
+pub fn notification_call_min_seconds() -> i32 {
+    std::env::var("NOTIFICATION_CALL_MIN_SECONDS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i32>().ok())
+        .filter(|v| *v >= 0)
+        .unwrap_or(30)
+}
+
+/// Whether `credits` plus the allowed overdraft pays for `min_seconds` of talking
+pub fn covers_call_floor(credits: f32, overdraft: f32, voice_second_cost: f32, min_seconds: i32) -> bool {
+    credits + overdraft >= voice_second_cost * min_seconds as f32
+}
+
+/// Checked before a notification call is placed, users we don't charge for voice always pass
+pub fn can_afford_notification_call(
+    state: &Arc<AppState>,
+    user: &crate::models::user_models::User,
+    settings: &crate::models::user_models::UserSettings,
+) -> bool {
+    let costs = credit_costs_for_user(user, Some(settings));
+    covers_call_floor(
+        user.credits,
+        allowed_overdraft(state, user.id),
+        costs.voice_second,
+        notification_call_min_seconds(),
+    )
+}
+
+/// What a balance still buys: whole minutes of calling and messages, counting the monthly
+/// included messages (`credits_left`) first. `messages` is None when messages aren't charged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BalanceEstimate {
+    pub call_minutes: i32,
+    pub messages: Option<i32>,
+}
+
+pub fn balance_estimate(credits: f32, credits_left: f32, voice_second_cost: f32, message_cost: f32) -> BalanceEstimate {
+    let credits = credits.max(0.0);
+    let call_minutes = if voice_second_cost > 0.0 {
+        (credits / voice_second_cost / 60.0).floor() as i32
+    } else {
+        0
+    };
+    let messages = (message_cost > 0.0)
+        .then(|| credits_left.max(0.0).floor() as i32 + (credits / message_cost).floor() as i32);
+    BalanceEstimate { call_minutes, messages }
+}
+
+/// Longest a call may run before we hang up: the user's max_call_minutes, or the credit budget if that's
+/// shorter. None when no cap is set, the zero-credits timestamp alone limits the call then.
+pub fn call_cap_seconds(max_call_minutes: Option<i32>, seconds_to_zero_credits: i32) -> Option<i32> {
+    let cap = max_call_minutes.filter(|minutes| *minutes > 0)?.saturating_mul(60);
+    Some(cap.min(seconds_to_zero_credits.max(0)))
+}
+
 /// Deducts credits from a user's account, using monthly credits (credits_left) first before using regular credits.
 /// Returns Ok(()) if credits were successfully deducted, or Err with an appropriate error message if not.
 pub fn deduct_user_credits(
@@ -152,57 +501,20 @@ pub fn deduct_user_credits(
         None
     };
 
-    let messages_are_included;
-    if user.phone_number.starts_with("+1") ||
-            user.phone_number.starts_with("+358") ||
-            user.phone_number.starts_with("+31") ||
-            user.phone_number.starts_with("+44") ||
-            user.phone_number.starts_with("+61") {
-        messages_are_included = false;
-    } else {
-        // true since they pay to twilio. won't cause bug since sending messages outside the range will require their own creds
-        messages_are_included = true;
-    }
-
-    if messages_are_included {
+    if messages_are_included(&user.phone_number) {
         return Ok(());
     }
 
     // Define costs based on phone number or tier 3 dynamic pricing
-    let (message_cost, voice_second_cost, noti_msg_cost, noti_call_cost) = if is_tier3 {
-        // For tier 3, use outbound_message_pricing from user_settings
-        if let Some(ref settings) = user_settings {
-            if let Some(pricing) = settings.outbound_message_pricing {
-                // Tier 3 dynamic pricing based on country
-                (pricing, 0.005, pricing, pricing * 2.0)
-            } else {
-                // Fallback to US pricing if not set
-                (0.075, 0.0033, 0.075, 0.15)
-            }
-        } else {
-            (0.075, 0.0033, 0.075, 0.15)
-        }
-    } else if user.phone_number.starts_with("+1") {
-        (0.075, 0.0033, 0.075, 0.15) // US
-    } else if user.phone_number.starts_with("+358") {
-        (0.30, 0.005, 0.15, 0.70) // Finland
-    } else if user.phone_number.starts_with("+31") {
-        (0.30, 0.005, 0.15, 0.45) // NL
-    } else if user.phone_number.starts_with("+44") {
-        (0.30, 0.005, 0.15, 0.20) // UK
-    } else if user.phone_number.starts_with("+61") {
-        (0.30, 0.005, 0.15, 0.20) // Australia
-    } else {
-        (0.0, 0.0, 0.0, 0.0)
-    };
+    let costs = credit_costs_for_user(&user, user_settings.as_ref());
 
     // Calculate cost based on event type
     let cost = match event_type {
-        "message" => message_cost,
-        "voice" => amount.unwrap_or(0) as f32 * voice_second_cost,
-        "noti_msg" => noti_msg_cost,
-        "noti_call" => noti_call_cost,
-        "digest" => message_cost,
+        "message" => costs.message,
+        "voice" => amount.unwrap_or(0) as f32 * costs.voice_second,
+        "noti_msg" => costs.noti_msg,
+        "noti_call" => costs.noti_call,
+        "digest" => costs.message,
         _ => return Err("Invalid event type".to_string()),
     };
 
@@ -223,14 +535,22 @@ pub fn deduct_user_credits(
             return Err("Failed to process credits".to_string());
         }
     } else {
-        // Deduct from regular credits only
-        let new_credits = (user.credits - cost).max(0.0);
+        // Deduct from regular credits only, the grace policy may take the balance into its overdraft
+        let new_credits = (user.credits - cost).max(-allowed_overdraft(state, user_id));
         if let Err(e) = state.user_repository.update_user_credits(user_id, new_credits) {
             eprintln!("Failed to update user credits: {}", e);
             return Err("Failed to process credits".to_string());
         }
     }
 
+    if user.credits_left >= cost_credits_left {
+        crate::utils::metrics::record_quota_spend(event_type, cost_credits_left);
+    } else {
+        crate::utils::metrics::record_credit_spend(event_type, cost);
+    }
+    // Monthly quota counts toward the caps too, at what the action would have cost in credits
+    record_spend_against_caps(state, &user, cost);
+
     // For tier 3 US/CA users: Increment monthly message count and monitor for 1000 limit
     if is_tier3 && event_type == "message" {
         if let Some(ref settings) = user_settings {
@@ -291,6 +611,7 @@ async fn send_tier3_usage_alert(
         to: "rasmus@ahtava.com".to_string(),
         subject: format!("Tier 3 Usage Alert - User {} - 1000 Messages", user_id),
         body: body.replace("\n", "\r\n"),
+        account_id: None,
     };
 
     let auth_user = crate::handlers::auth_middleware::AuthUser {
@@ -314,3 +635,172 @@ async fn send_tier3_usage_alert(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_test_user, test_state};
+    use chrono::TimeZone;
+
+    #[test]
+    fn cap_periods_start_at_local_midnight_and_monday() {
+        // Friday 2026-10-16 10:00 UTC is 13:00 in Helsinki (UTC+3)
+        let now = chrono::Utc.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap();
+        let (day_start, week_start) = cap_period_starts(now, chrono_tz::Europe::Helsinki);
+        assert_eq!(day_start as i64, chrono::Utc.with_ymd_and_hms(2026, 10, 15, 21, 0, 0).unwrap().timestamp());
+        assert_eq!(week_start as i64, chrono::Utc.with_ymd_and_hms(2026, 10, 11, 21, 0, 0).unwrap().timestamp());
+    }
+
+    #[test]
+    fn balance_estimate_counts_minutes_and_included_messages_first() {
+        // 5 credits at 0.0033/s is 1515 seconds, 25 whole minutes. 3 included messages plus 66 paid at 0.075.
+        assert_eq!(balance_estimate(5.0, 3.0, 0.0033, 0.075), BalanceEstimate { call_minutes: 25, messages: Some(69) });
+        // A negative balance buys nothing, and numbers where messages are free leave them out
+        assert_eq!(balance_estimate(-1.0, 0.0, 0.005, 0.0), BalanceEstimate { call_minutes: 0, messages: None });
+    }
+
+    #[tokio::test]
+    async fn unanswered_message_is_explained_in_the_users_language() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "kieli@example.com");
+        state.user_core.update_agent_language(user_id, "fi").unwrap();
+
+        let notice = out_of_credits_notice(&state, user_id, CreditPolicy::Block, "message", false);
+        assert!(notice.starts_with("En voi vastata tähän"), "{notice}");
+        // Inside the grace overdraft the message is answered, so it gets the overdraft note instead
+        let notice = out_of_credits_notice(&state, user_id, CreditPolicy::Grace, "message", true);
+        assert!(notice.starts_with("You're out of credits, so this one is on a small overdraft"), "{notice}");
+        // Other events keep the policy's notice
+        let notice = out_of_credits_notice(&state, user_id, CreditPolicy::Block, "noti_msg", false);
+        assert!(notice.starts_with("Your credits and monthly quota have been depleted"), "{notice}");
+    }
+
+    #[test]
+    fn unknown_language_gets_the_english_reply() {
+        assert_eq!(out_of_credits_reply_with("xx", None), out_of_credits_reply_with("en", None));
+        assert_eq!(out_of_credits_reply_with("DE", None), out_of_credits_reply_with("de", None));
+    }
+
+    #[test]
+    fn configured_reply_replaces_every_language() {
+        let reply = out_of_credits_reply_with("fi", Some("Out of credits, top up: {link}".to_string()));
+        assert_eq!(reply, format!("Out of credits, top up: {}", top_up_link()));
+        // A blank override falls back to the built-in text
+        assert_eq!(out_of_credits_reply_with("fi", Some(" ".to_string())), out_of_credits_reply_with("fi", None));
+    }
+
+    #[tokio::test]
+    async fn tier_2_on_their_own_twilio_is_never_out_of_credits() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "sweden@example.com");
+        let mut user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        assert_eq!(user.sub_tier.as_deref(), Some("tier 2"));
+        user.phone_number = "+46701234567".to_string();
+        user.credits = 0.0;
+        user.credits_left = 0.0;
+
+        assert!(messages_are_included(&user.phone_number));
+        assert!(!messages_are_included("+358401234567"));
+        assert!(check_user_credits(&state, &user, "message", None).await.is_ok());
+    }
+
+    #[test]
+    fn partial_seconds_of_credit_are_not_granted() {
+        // 0.5625 credits over the threshold buy 4.5 seconds, 2.5625 credits buy 20.5
+        assert_eq!(voice_seconds_budget(2.5625, 0.125, 2.0), (4, 20));
+        // Already under the threshold: top up right away, hang up once the rest is spent
+        assert_eq!(voice_seconds_budget(0.5, 0.125, 2.0), (-12, 4));
+        assert_eq!(voice_seconds_budget(0.5, 0.0, 2.0), (UNMETERED_CALL_SECONDS, UNMETERED_CALL_SECONDS));
+    }
+
+    #[tokio::test]
+    async fn reaching_the_daily_cap_blocks_voice_and_sms() {
+        std::env::set_var("CHARGE_BACK_THRESHOLD", "2.00");
+        let state = test_state();
+        let user_id = create_test_user(&state, "capped@example.com");
+        state.user_core.update_credit_caps(user_id, Some(0.5), None).unwrap();
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        assert!(check_user_credits(&state, &user, "message", None).await.is_ok());
+
+        let now = chrono::Utc::now().timestamp() as i32;
+        state.user_repository.record_credit_spend(user_id, 0.5, now).unwrap();
+
+        let blocked = check_user_credits(&state, &user, "message", None).await.unwrap_err();
+        assert_eq!(blocked, cap_reached_message("daily", 0.5));
+        assert!(check_user_credits(&state, &user, "voice", Some(60)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn spending_before_today_does_not_count_toward_the_daily_cap() {
+        std::env::set_var("CHARGE_BACK_THRESHOLD", "2.00");
+        let state = test_state();
+        let user_id = create_test_user(&state, "reset@example.com");
+        state.user_core.update_credit_caps(user_id, Some(0.5), None).unwrap();
+        let two_days_ago = (chrono::Utc::now() - chrono::Duration::days(2)).timestamp() as i32;
+        state.user_repository.record_credit_spend(user_id, 5.0, two_days_ago).unwrap();
+
+        let user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        assert!(check_user_credits(&state, &user, "message", None).await.is_ok());
+        assert!(check_user_credits(&state, &user, "voice", Some(60)).await.is_ok());
+    }
+
+    /// A Finnish user (messages cost 0.30) with no quota left and the given balance
+    fn nearly_broke_user(state: &Arc<AppState>, email: &str, credits: f32, policy: &str) -> crate::models::user_models::User {
+        let user_id = create_test_user(state, email);
+        state.user_core.update_insufficient_credits_policy(user_id, Some(policy.to_string())).unwrap();
+        let mut user = state.user_core.find_by_id(user_id).unwrap().unwrap();
+        user.phone_number = "+358401234567".to_string();
+        user.credits = credits;
+        user.credits_left = 0.0;
+        user
+    }
+
+    #[tokio::test]
+    async fn near_zero_balance_follows_the_users_policy() {
+        let state = test_state();
+        for (policy, allowed) in [("block", false), ("warn", false), ("grace", true)] {
+            let user = nearly_broke_user(&state, &format!("{}@example.com", policy), 0.05, policy);
+            assert_eq!(credit_policy(&state, user.id), CreditPolicy::from_str(policy).unwrap());
+            let result = check_user_credits(&state, &user, "message", None).await;
+            assert_eq!(result.is_ok(), allowed, "{policy}: {result:?}");
+            // The user heard about it once, whichever way it went
+            assert!(state.user_core.find_by_id(user.id).unwrap().unwrap().last_credits_notification.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn grace_stops_at_the_overdraft() {
+        let state = test_state();
+        // 0.30 more would take the balance to -0.55, past the default 0.50 overdraft
+        let user = nearly_broke_user(&state, "overdrawn@example.com", -0.25, "grace");
+        assert!(check_user_credits(&state, &user, "message", None).await.is_err());
+        assert_eq!(allowed_overdraft(&state, user.id), credit_overdraft());
+
+        let blocked = nearly_broke_user(&state, "blocked@example.com", 0.05, "block");
+        assert_eq!(allowed_overdraft(&state, blocked.id), 0.0);
+        let warned = out_of_credits_notice(&state, blocked.id, CreditPolicy::Warn, "noti_msg", false);
+        assert!(warned.ends_with(&top_up_link()), "{warned}");
+    }
+
+    #[test]
+    fn call_cap_is_the_shorter_of_the_setting_and_the_balance() {
+        let state = test_state();
+        let user_id = create_test_user(&state, "capped@example.com");
+        let cap_for = |credits: f32| {
+            let settings = state.user_core.get_user_settings(user_id).unwrap();
+            let (_, seconds_to_zero_credits) = voice_seconds_budget(credits, 0.125, 2.0);
+            call_cap_seconds(settings.max_call_minutes, seconds_to_zero_credits)
+        };
+        assert_eq!(cap_for(100.0), None);
+
+        state.user_core.update_max_call_minutes(user_id, Some(10)).unwrap();
+        // 100 credits last 800 seconds, the 10 minute setting hangs up first
+        assert_eq!(cap_for(100.0), Some(600));
+        // 50 credits only last 400 seconds
+        assert_eq!(cap_for(50.0), Some(400));
+        assert_eq!(cap_for(-1.0), Some(0));
+
+        state.user_core.update_max_call_minutes(user_id, Some(0)).unwrap();
+        assert_eq!(cap_for(100.0), None);
+    }
+}
@@ -0,0 +1,188 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Everything a voice call needs to speak a language: the ElevenLabs voice and the
+/// opening lines. `greeting` and `verified_message` may use the `{{name}}` dynamic variable.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VoiceLanguage {
+    pub code: String,
+    /// Shown in the language picker, defaults to the English name of the code
+    #[serde(default)]
+    pub name: String,
+    pub voice_id: String,
+    pub greeting: String,
+    pub verified_message: String,
+}
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Built-in languages as (code, voice id env var, greeting, verified message).
+/// A language is only offered when its voice id env var is set, except English which is required.
+const BUILTIN_LANGUAGES: [(&str, &str, &str, &str); 6] = [
+    ("en", "US_VOICE_ID", "Hello {{name}}!", "Welcome! Your number is now verified. Anyways, how can I help?"),
+    ("fi", "FI_VOICE_ID", "Moi {{name}}!", "Tervetuloa! Numerosi on nyt vahvistettu. Miten voin auttaa?"),
+    ("de", "DE_VOICE_ID", "Hallo {{name}}!", "Willkommen! Ihre Nummer ist jetzt verifiziert. Wie kann ich Ihnen helfen?"),
+    ("sv", "SV_VOICE_ID", "Hej {{name}}!", "Välkommen! Ditt nummer är nu verifierat. Hur kan jag hjälpa dig?"),
+    ("fr", "FR_VOICE_ID", "Bonjour {{name}} !", "Bienvenue ! Votre numéro est maintenant vérifié. Comment puis-je vous aider ?"),
+    ("es", "ES_VOICE_ID", "¡Hola {{name}}!", "¡Bienvenido! Tu número ya está verificado. ¿En qué puedo ayudarte?"),
+];
+
+fn load_languages() -> HashMap<String, VoiceLanguage> {
+    load_languages_from(|key| std::env::var(key).ok())
+}
+
+/// The language table from the given environment lookup
+fn load_languages_from(env: impl Fn(&str) -> Option<String>) -> HashMap<String, VoiceLanguage> {
+    let mut languages = HashMap::new();
+    for (code, voice_env, greeting, verified_message) in BUILTIN_LANGUAGES {
+        let voice_id = if code == DEFAULT_LANGUAGE {
+            env(voice_env).expect("US_VOICE_ID not set")
+        } else {
+            match env(voice_env) {
+                Some(voice_id) => voice_id,
+                None => continue,
+            }
+        };
+        languages.insert(code.to_string(), VoiceLanguage {
+            code: code.to_string(),
+            name: crate::utils::language_detection::language_name(code).to_string(),
+            voice_id,
+            greeting: greeting.to_string(),
+            verified_message: verified_message.to_string(),
+        });
+    }
+    // VOICE_LANGUAGES='[{"code":"it","voice_id":"...","greeting":"Ciao {{name}}!","verified_message":"..."}]'
+    // adds languages or overrides the built-in ones without a code change
+    if let Some(raw) = env("VOICE_LANGUAGES") {
+        match serde_json::from_str::<Vec<VoiceLanguage>>(&raw) {
+            Ok(extra) => {
+                for mut language in extra {
+                    language.code = language.code.to_lowercase();
+                    if language.name.trim().is_empty() {
+                        language.name = crate::utils::language_detection::language_name(&language.code).to_string();
+                    }
+                    languages.insert(language.code.clone(), language);
+                }
+            }
+            Err(e) => tracing::error!("Ignoring invalid VOICE_LANGUAGES: {}", e),
+        }
+    }
+    // VERIFIED_MESSAGES='{"en":"Welcome {{name}}, you're all set!"}' replaces just the first message
+    // of a newly verified caller, keeping the language's voice
+    if let Some(raw) = env("VERIFIED_MESSAGES") {
+        match serde_json::from_str::<HashMap<String, String>>(&raw) {
+            Ok(messages) => apply_verified_messages(&mut languages, messages),
+            Err(e) => tracing::error!("Ignoring invalid VERIFIED_MESSAGES: {}", e),
+        }
+    }
+    languages
+}
+
+/// Overrides verification messages by language code, codes without a configured voice are skipped
+fn apply_verified_messages(languages: &mut HashMap<String, VoiceLanguage>, messages: HashMap<String, String>) {
+    for (code, message) in messages {
+        match languages.get_mut(&code.to_lowercase()) {
+            Some(language) if !message.trim().is_empty() => language.verified_message = message,
+            Some(_) => tracing::warn!("Ignoring empty verified message for '{}'", code),
+            None => tracing::warn!("Ignoring verified message for '{}', no voice is configured for it", code),
+        }
+    }
+}
+
+fn languages() -> &'static HashMap<String, VoiceLanguage> {
+    static LANGUAGES: OnceLock<HashMap<String, VoiceLanguage>> = OnceLock::new();
+    LANGUAGES.get_or_init(load_languages)
+}
+
+/// Voice settings for the language code, English when the language isn't configured
+pub fn voice_language(code: &str) -> &'static VoiceLanguage {
+    language_or_default(languages(), code)
+}
+
+fn language_or_default<'a>(languages: &'a HashMap<String, VoiceLanguage>, code: &str) -> &'a VoiceLanguage {
+    languages
+        .get(&code.to_lowercase())
+        .unwrap_or_else(|| {
+            tracing::debug!("No voice configured for language '{}', using English", code);
+            &languages[DEFAULT_LANGUAGE]
+        })
+}
+
+/// Language codes the agent can be set to, sorted
+pub fn supported_languages() -> Vec<&'static str> {
+    let mut codes: Vec<&str> = languages().keys().map(|code| code.as_str()).collect();
+    codes.sort();
+    codes
+}
+
+pub fn is_supported_language(code: &str) -> bool {
+    languages().contains_key(&code.to_lowercase())
+}
+
+/// Configured languages sorted by code, for the language picker
+pub fn language_options() -> Vec<&'static VoiceLanguage> {
+    let mut options: Vec<&VoiceLanguage> = languages().values().collect();
+    options.sort_by(|a, b| a.code.cmp(&b.code));
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(vars: &[(&str, &str)]) -> HashMap<String, VoiceLanguage> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        load_languages_from(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn unmapped_language_falls_back_to_english() {
+        let languages = table(&[("US_VOICE_ID", "voice-en")]);
+        // Finnish is built in but has no voice configured here
+        for code in ["fi", "pt"] {
+            let language = language_or_default(&languages, code);
+            assert_eq!((language.code.as_str(), language.voice_id.as_str()), ("en", "voice-en"));
+            assert!(language.verified_message.starts_with("Welcome!"));
+        }
+    }
+
+    #[test]
+    fn configured_languages_are_honored() {
+        let languages = table(&[
+            ("US_VOICE_ID", "voice-en"),
+            ("SV_VOICE_ID", "voice-sv"),
+            ("VOICE_LANGUAGES", r#"[{"code":"IT","voice_id":"voice-it","greeting":"Ciao {{name}}!","verified_message":"Benvenuto!"}]"#),
+            ("VERIFIED_MESSAGES", r#"{"sv":"Välkommen {{name}}!"}"#),
+        ]);
+
+        let swedish = language_or_default(&languages, "SV");
+        assert_eq!((swedish.voice_id.as_str(), swedish.greeting.as_str()), ("voice-sv", "Hej {{name}}!"));
+        assert_eq!(swedish.verified_message, "Välkommen {{name}}!");
+        let italian = language_or_default(&languages, "it");
+        assert_eq!((italian.voice_id.as_str(), italian.verified_message.as_str()), ("voice-it", "Benvenuto!"));
+        assert!(!italian.name.is_empty());
+    }
+
+    #[test]
+    fn verified_message_override_keeps_its_languages_voice() {
+        let languages = table(&[
+            ("US_VOICE_ID", "voice-en"),
+            ("FI_VOICE_ID", "voice-fi"),
+            ("VERIFIED_MESSAGES", r#"{"FI":"Tervetuloa {{name}}, kaikki valmista!","de":"Willkommen!","en":"  "}"#),
+        ]);
+
+        let finnish = language_or_default(&languages, "fi");
+        assert_eq!(
+            (finnish.verified_message.as_str(), finnish.voice_id.as_str()),
+            ("Tervetuloa {{name}}, kaikki valmista!", "voice-fi")
+        );
+        // No German voice, so German callers get the English voice with the English message,
+        // never a German message read by the English voice
+        let german = language_or_default(&languages, "de");
+        assert_eq!(
+            (german.verified_message.as_str(), german.voice_id.as_str()),
+            ("Welcome! Your number is now verified. Anyways, how can I help?", "voice-en")
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// A validation older than this no longer counts as proof the webhook works
+const RECENT_SECS: i64 = 7 * 24 * 3600;
+
+/// (webhook, env vars its validator needs, where it's called)
+pub const WEBHOOKS: [(&str, &[&str], &str); 4] = [
+    ("twilio_sms", &["TWILIO_AUTH_TOKEN", "SERVER_URL"], "/api/sms/server"),
+    ("elevenlabs_tools", &["ELEVENLABS_SERVER_URL_SECRET"], "/api/call/*"),
+    ("elevenlabs_webhook", &["ELEVENLABS_WEBHOOK_SECRET"], "/api/webhook/elevenlabs"),
+    ("stripe", &["STRIPE_WEBHOOK_SECRET", "STRIPE_SECRET_KEY"], "/api/stripe/webhook"),
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Seen {
+    validated_at: Option<i64>,
+    rejected_at: Option<i64>,
+    validated_count: u64,
+    rejected_count: u64,
+}
+
+/// Accepted and rejected signatures per webhook since startup, with when each was last seen
+fn seen() -> &'static DashMap<&'static str, Seen> {
+    static SEEN: OnceLock<DashMap<&'static str, Seen>> = OnceLock::new();
+    SEEN.get_or_init(DashMap::new)
+}
+
+/// Called by the validators once a request's signature or secret checked out
+pub fn record_validated(webhook: &'static str) {
+    let mut seen = seen().entry(webhook).or_default();
+    seen.validated_at = Some(chrono::Utc::now().timestamp());
+    seen.validated_count += 1;
+}
+
+/// Called by the validators when a request carried a wrong or missing signature
+pub fn record_rejected(webhook: &'static str) {
+    let mut seen = seen().entry(webhook).or_default();
+    seen.rejected_at = Some(chrono::Utc::now().timestamp());
+    seen.rejected_count += 1;
+}
+
+fn seen_for(webhook: &str) -> Seen {
+    seen().get(webhook).map(|s| *s).unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookStatus {
+    pub webhook: &'static str,
+    pub path: &'static str,
+    /// "misconfigured", "failing", "ok" or "unverified"
+    pub status: &'static str,
+    pub missing_env: Vec<&'static str>,
+    pub last_validated_at: Option<i64>,
+    pub last_rejected_at: Option<i64>,
+    pub validated_count: u64,
+    pub rejected_count: u64,
+}
+
+/// Missing env vars win, then a rejection newer than the last success, which usually means the
+/// secret on the provider's side doesn't match ours. Without a recent success it's unverified.
+pub fn classify(missing_env: &[&str], validated_at: Option<i64>, rejected_at: Option<i64>, now: i64) -> &'static str {
+    if !missing_env.is_empty() {
+        return "misconfigured";
+    }
+    match (validated_at, rejected_at) {
+        (validated, Some(rejected)) if validated.map_or(true, |at| rejected > at) => "failing",
+        (Some(validated), _) if now - validated <= RECENT_SECS => "ok",
+        _ => "unverified",
+    }
+}
+
+/// Status of every webhook, `is_set` checks an env var and is `env_is_set` for the live report
+pub fn statuses(is_set: impl Fn(&str) -> bool, now: i64) -> Vec<WebhookStatus> {
+    WEBHOOKS
+        .iter()
+        .map(|&(webhook, env_vars, path)| {
+            let missing_env: Vec<&'static str> = env_vars.iter().copied().filter(|var| !is_set(var)).collect();
+            let seen = seen_for(webhook);
+            WebhookStatus {
+                webhook,
+                path,
+                status: classify(&missing_env, seen.validated_at, seen.rejected_at, now),
+                missing_env,
+                last_validated_at: seen.validated_at,
+                last_rejected_at: seen.rejected_at,
+                validated_count: seen.validated_count,
+                rejected_count: seen.rejected_count,
+            }
+        })
+        .collect()
+}
+
+pub fn env_is_set(var: &str) -> bool {
+    std::env::var(var).map_or(false, |value| !value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validations_and_rejections_are_counted_with_their_time() {
+        // A name of its own, the real webhooks are recorded by other tests running alongside
+        let webhook = "counter_test_webhook";
+        assert_eq!(seen_for(webhook), Seen::default());
+        let before = chrono::Utc::now().timestamp();
+
+        record_validated(webhook);
+        record_validated(webhook);
+        record_rejected(webhook);
+
+        let seen = seen_for(webhook);
+        assert_eq!((seen.validated_count, seen.rejected_count), (2, 1));
+        assert!(seen.validated_at.unwrap() >= before);
+        assert!(seen.rejected_at.unwrap() >= seen.validated_at.unwrap());
+    }
+
+    #[test]
+    fn rejection_after_success_is_failing() {
+        let now = 1_800_000_000;
+        assert_eq!(classify(&["STRIPE_WEBHOOK_SECRET"], Some(now), None, now), "misconfigured");
+        assert_eq!(classify(&[], Some(now - 60), Some(now), now), "failing");
+        assert_eq!(classify(&[], Some(now), Some(now - 60), now), "ok");
+        assert_eq!(classify(&[], Some(now - RECENT_SECS - 1), None, now), "unverified");
+        assert_eq!(classify(&[], None, None, now), "unverified");
+    }
+}